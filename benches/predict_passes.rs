@@ -1,7 +1,6 @@
 use std::path::PathBuf;
 
 use chrono::{TimeZone, Utc};
-use std::time::Duration;
 
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
 use lox_space::{
@@ -2,6 +2,9 @@ import { useState } from 'react';
 import { Sidebar, type Screen } from './components/Sidebar/Sidebar';
 import { Header } from './components/Header/Header';
 import { Dashboard } from './screens/Dashboard/Dashboard';
+import { TimelineScreen } from './screens/Timeline/Timeline';
+import { EditorScreen } from './screens/Editor/Editor';
+import { WaterfallScreen } from './screens/Waterfall/Waterfall';
 import styles from './App.module.css';
 
 function App() {
@@ -19,6 +22,9 @@ function App() {
         <Header />
         <main className={styles.content}>
           {screen === 'dashboard' && <Dashboard key={apiKeyVersion} />}
+          {screen === 'timeline' && <TimelineScreen key={apiKeyVersion} />}
+          {screen === 'editor' && <EditorScreen key={apiKeyVersion} />}
+          {screen === 'waterfall' && <WaterfallScreen key={apiKeyVersion} />}
           {screen === 'settings' && (
             <div className={styles.placeholder}>Settings</div>
           )}
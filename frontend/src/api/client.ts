@@ -1,10 +1,11 @@
 export async function apiFetch(path: string, opts?: RequestInit): Promise<Response> {
-  const apiKey = localStorage.getItem('api_key') ?? '';
+  const apiKey = localStorage.getItem('api_key');
   return fetch(path, {
     ...opts,
     headers: {
       ...opts?.headers,
-      'api_key': apiKey,
+      // Without a key, the server may still allow read-only access
+      ...(apiKey ? { 'api_key': apiKey } : {}),
     },
   });
 }
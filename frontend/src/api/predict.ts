@@ -1,16 +1,67 @@
 import { apiFetch } from './client';
-import type { GroundTrackPredictions, PassPredictions } from './types';
+import type { ApiObservables, GroundTrackPredictions, PassPredictions, SatelliteList, TransitPredictions, VisibilityStats } from './types';
 
-export async function fetchPasses(start: string, end: string): Promise<PassPredictions> {
+export async function fetchSatellites(): Promise<SatelliteList> {
+  const res = await apiFetch('/api/v1/predict/satellites');
+  if (!res.ok) throw new Error(`Failed to fetch satellites: ${res.status}`);
+  return res.json();
+}
+
+export async function fetchPasses(start: string, end: string, group?: string): Promise<PassPredictions> {
   const params = new URLSearchParams({ start, end });
-  const res = await apiFetch(`/api/predict/passes?${params}`);
+  if (group) params.set('group', group);
+  const res = await apiFetch(`/api/v1/predict/passes?${params}`);
   if (!res.ok) throw new Error(`Failed to fetch passes: ${res.status}`);
   return res.json();
 }
 
-export async function fetchGroundTracks(start: string, end: string): Promise<GroundTrackPredictions> {
+export async function fetchGroundTracks(start: string, end: string, group?: string): Promise<GroundTrackPredictions> {
   const params = new URLSearchParams({ start, end });
-  const res = await apiFetch(`/api/predict/ground_track?${params}`);
+  if (group) params.set('group', group);
+  const res = await apiFetch(`/api/v1/predict/ground_track?${params}`);
   if (!res.ok) throw new Error(`Failed to fetch ground tracks: ${res.status}`);
   return res.json();
 }
+
+export async function fetchStats(noradId: number, days?: number): Promise<VisibilityStats> {
+  const params = new URLSearchParams({ norad_id: String(noradId) });
+  if (days !== undefined) params.set('days', String(days));
+  const res = await apiFetch(`/api/v1/predict/stats?${params}`);
+  if (!res.ok) throw new Error(`Failed to fetch stats: ${res.status}`);
+  return res.json();
+}
+
+export async function fetchTransits(start: string, end: string, body?: 'sun' | 'moon'): Promise<TransitPredictions> {
+  const params = new URLSearchParams({ start, end });
+  if (body) params.set('body', body);
+  const res = await apiFetch(`/api/v1/predict/transits?${params}`);
+  if (!res.ok) throw new Error(`Failed to fetch transits: ${res.status}`);
+  return res.json();
+}
+
+export async function fetchObservables(satellite: string, frequency?: number): Promise<ApiObservables> {
+  const params = new URLSearchParams({ satellite });
+  if (frequency !== undefined) params.set('frequency', String(frequency));
+  const res = await apiFetch(`/api/v1/predict/observables?${params}`);
+  if (!res.ok) throw new Error(`Failed to fetch observables: ${res.status}`);
+  return res.json();
+}
+
+/**
+ * Follows a satellite live, calling `onSample` with its observables every second. Returns a
+ * function closing the stream.
+ */
+export function streamObservables(
+  satellite: string,
+  frequency: number | undefined,
+  onSample: (observables: ApiObservables) => void,
+  onError: (message: string) => void,
+): () => void {
+  const params = new URLSearchParams({ satellite });
+  if (frequency !== undefined) params.set('frequency', String(frequency));
+  const source = new EventSource(`/api/v1/predict/observables/stream?${params}`);
+  source.addEventListener('sample', (e) => onSample(JSON.parse((e as MessageEvent).data)));
+  // The browser reconnects by itself, the error is only shown until then
+  source.onerror = () => onError('Connection to the station lost, reconnecting…');
+  return () => source.close();
+}
@@ -0,0 +1,62 @@
+export interface Spectrum {
+  /** Milliseconds since the Unix epoch */
+  time: number;
+  /** Center frequency (Hz) */
+  frequency: number;
+  /** Sample rate (Hz), the bandwidth of the spectrum */
+  sampleRate: number;
+  /** Power (dBFS) of each bin, from the lowest frequency to the highest */
+  power: Float32Array;
+}
+
+/** The run whose spectrum is streamed, from the variables of its task */
+export interface SpectrumRun {
+  task: string;
+  satellite: string | null;
+  /** Downlink frequency (Hz) */
+  rx_frequency: number | null;
+}
+
+const HEADER_SIZE = 24;
+
+function decodeSpectrum(data: ArrayBuffer): Spectrum {
+  const view = new DataView(data);
+  const power = new Float32Array((data.byteLength - HEADER_SIZE) / 4);
+  for (let i = 0; i < power.length; i++) {
+    power[i] = view.getFloat32(HEADER_SIZE + 4 * i, true);
+  }
+  return {
+    time: Number(view.getBigInt64(0, true)),
+    frequency: Number(view.getBigUint64(8, true)),
+    sampleRate: Number(view.getBigUint64(16, true)),
+    power,
+  };
+}
+
+/**
+ * Follows the spectrum of the SDR of the run in progress, calling `onRun` once with the run
+ * followed. Returns a function to stop.
+ */
+export function streamSpectrum(
+  onRun: (run: SpectrumRun) => void,
+  onSpectrum: (spectrum: Spectrum) => void,
+  onClose: () => void,
+): () => void {
+  const params = new URLSearchParams();
+  // Browsers cannot set headers on WebSockets
+  const apiKey = localStorage.getItem('api_key');
+  if (apiKey) params.set('token', apiKey);
+  const scheme = location.protocol === 'https:' ? 'wss' : 'ws';
+  const socket = new WebSocket(`${scheme}://${location.host}/api/v1/radio/fft?${params}`);
+  socket.binaryType = 'arraybuffer';
+  socket.onmessage = (e) => {
+    if (typeof e.data === 'string') onRun(JSON.parse(e.data));
+    else onSpectrum(decodeSpectrum(e.data as ArrayBuffer));
+  };
+  // Also when no run keeps a spectrum or when the run is over
+  socket.onclose = onClose;
+  return () => {
+    socket.onclose = null;
+    socket.close();
+  };
+}
@@ -0,0 +1,20 @@
+import { apiFetch } from './client';
+import type { ArtifactEntry } from './types';
+
+export async function listArtifacts(id: string): Promise<ArtifactEntry[]> {
+  const res = await apiFetch(`/api/v1/runs/${encodeURIComponent(id)}/artifacts`);
+  if (!res.ok) throw new Error(`Failed to list artifacts: ${res.status}`);
+  return res.json();
+}
+
+export async function getArtifact(id: string, path: string): Promise<Blob> {
+  const res = await apiFetch(`/api/v1/runs/${encodeURIComponent(id)}/artifacts/${path}`);
+  if (!res.ok) throw new Error(`Failed to get artifact: ${res.status}`);
+  return res.blob();
+}
+
+export async function getArchive(id: string): Promise<Blob> {
+  const res = await apiFetch(`/api/v1/runs/${encodeURIComponent(id)}/download`);
+  if (!res.ok) throw new Error(`Failed to get archive: ${res.status}`);
+  return res.blob();
+}
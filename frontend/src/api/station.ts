@@ -2,10 +2,11 @@ import { apiFetch } from './client';
 
 export interface StationInfo {
   name: string;
+  hosted_stations: string[];
 }
 
 export async function getStation(): Promise<StationInfo> {
-  const res = await apiFetch('/api/station');
+  const res = await apiFetch('/api/v1/station');
   if (!res.ok) throw new Error(`Failed to get station info: ${res.status}`);
   return res.json();
 }
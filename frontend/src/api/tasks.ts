@@ -1,20 +1,33 @@
 import { apiFetch } from './client';
-import type { TaskListEntry } from './types';
+import type { BatchResult, RunStatus, TaskListEntry, ValidationReport } from './types';
 
-export async function listTasks(): Promise<TaskListEntry[]> {
-  const res = await apiFetch('/api/tasks');
+export interface TaskListParams {
+  owner?: string;
+  sort?: 'start' | 'submitted';
+  order?: 'asc' | 'desc';
+  limit?: number;
+  offset?: number;
+}
+
+export async function listTasks(params: TaskListParams = {}): Promise<TaskListEntry[]> {
+  const query = new URLSearchParams();
+  for (const [key, value] of Object.entries(params)) {
+    if (value !== undefined) query.set(key, String(value));
+  }
+  const qs = query.toString();
+  const res = await apiFetch(`/api/v1/tasks${qs ? `?${qs}` : ''}`);
   if (!res.ok) throw new Error(`Failed to list tasks: ${res.status}`);
   return res.json();
 }
 
 export async function getTask(id: string): Promise<string> {
-  const res = await apiFetch(`/api/tasks/${encodeURIComponent(id)}`);
+  const res = await apiFetch(`/api/v1/tasks/${encodeURIComponent(id)}`);
   if (!res.ok) throw new Error(`Failed to get task: ${res.status}`);
   return res.text();
 }
 
 export async function putTask(id: string, yaml: string): Promise<number> {
-  const res = await apiFetch(`/api/tasks/${encodeURIComponent(id)}`, {
+  const res = await apiFetch(`/api/v1/tasks/${encodeURIComponent(id)}`, {
     method: 'PUT',
     headers: { 'Content-Type': 'text/plain' },
     body: yaml,
@@ -26,8 +39,19 @@ export async function putTask(id: string, yaml: string): Promise<number> {
   return res.status;
 }
 
+export async function validateTask(yaml: string, id?: string): Promise<ValidationReport> {
+  const qs = id ? `?id=${encodeURIComponent(id)}` : '';
+  const res = await apiFetch(`/api/v1/tasks/validate${qs}`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'text/plain' },
+    body: yaml,
+  });
+  if (!res.ok) throw new Error(`Failed to validate task: ${res.status}`);
+  return res.json();
+}
+
 export async function deleteTask(id: string): Promise<void> {
-  const res = await apiFetch(`/api/tasks/${encodeURIComponent(id)}`, {
+  const res = await apiFetch(`/api/v1/tasks/${encodeURIComponent(id)}`, {
     method: 'DELETE',
   });
   if (!res.ok) {
@@ -35,3 +59,30 @@ export async function deleteTask(id: string): Promise<void> {
     throw new Error(text || `Failed to delete task: ${res.status}`);
   }
 }
+
+export async function approveBatch(
+  action: 'approve' | 'reject',
+  ids: string[],
+): Promise<BatchResult[]> {
+  const res = await apiFetch('/api/v1/tasks/approve-batch', {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ action, ids }),
+  });
+  if (!res.ok) throw new Error(`Failed to ${action} tasks: ${res.status}`);
+  return res.json();
+}
+
+export async function controlRun(
+  id: string,
+  action: 'pause' | 'resume' | 'abort',
+): Promise<RunStatus> {
+  const res = await apiFetch(`/api/v1/tasks/${encodeURIComponent(id)}/${action}`, {
+    method: 'POST',
+  });
+  if (!res.ok) {
+    const text = await res.text();
+    throw new Error(text || `Failed to ${action} task: ${res.status}`);
+  }
+  return res.json();
+}
@@ -1,21 +1,94 @@
-export type TaskState = 'Active' | 'PendingApproval' | 'Completed' | 'Failed';
+export type TaskState = 'Active' | 'PendingApproval' | 'Completed' | 'Failed' | 'Rejected';
 
 export interface TaskListEntry {
   id: string;
   state: TaskState;
   start: string | null;
   end: string | null;
+  owner: string | null;
+  submitted: string | null;
+  /** Progress of the run, while the task is running */
+  run?: RunStatus;
+  /** Why the task was rejected, if the approver gave a reason */
+  rejection?: string;
+  /** Outcome of the run, once the task is Completed or Failed */
+  execution?: ExecutionSummary;
+}
+
+export interface ExecutionSummary {
+  steps: number;
+  succeeded: number;
+  result: 'completed' | 'completed with errors' | 'aborted' | 'no steps';
+}
+
+export interface RunStatus {
+  state: 'running' | 'paused' | 'aborting';
+  step: number | null;
+}
+
+export interface BatchResult {
+  id: string;
+  ok: boolean;
+  error?: string;
+}
+
+export interface ApiSatellite {
+  name: string;
+  norad_id: number;
+  epoch: string;
+  regime: 'leo' | 'meo' | 'geo' | 'heo';
+  groups: string[];
+  source: string | null;
+}
+
+export interface SatelliteList {
+  satellites: ApiSatellite[];
+}
+
+export interface ApiGeoVisibility {
+  azimuth: number;
+  elevation: number;
+  range_km: number;
+  min_elevation: number;
+  max_elevation: number;
+  visible: boolean;
+  warnings: string[];
 }
 
 export interface ApiPass {
   start: string;
   end: string;
+  max_elevation: number;
+  tca: string;
+  geometric_start: string;
+  geometric_end: string;
+  geometric_max_elevation: number;
+  range_at_tca_km: number;
+  max_doppler_hz: number | null;
+  max_doppler_rate_hz_s: number | null;
   azimuth: number[];
   elevation: number[];
+  warnings: string[];
+}
+
+export interface ApiObservables {
+  satellite: string;
+  norad_id: number;
+  time: string;
+  azimuth: number;
+  elevation: number;
+  range_km: number;
+  range_rate_km_s: number;
+  doppler_hz: number | null;
 }
 
 export interface PassPredictions {
   predictions: Record<string, ApiPass[]>;
+  total_passes: number;
+  total_satellites: number;
+  geostationary: Record<string, ApiGeoVisibility>;
+  element_age_days: Record<string, number>;
+  warnings: string[];
 }
 
 export interface ApiGroundTrack {
@@ -26,4 +99,72 @@ export interface ApiGroundTrack {
 
 export interface GroundTrackPredictions {
   predictions: Record<string, ApiGroundTrack>;
+  element_age_days: Record<string, number>;
+  warnings: string[];
+}
+
+export interface ApiElevationBin {
+  min_elevation: number;
+  max_elevation: number;
+  passes: number;
+}
+
+export interface VisibilityStats {
+  satellite: string;
+  norad_id: number;
+  start: string;
+  end: string;
+  passes: number;
+  contact_minutes: number;
+  mean_pass_minutes: number;
+  max_elevation_histogram: ApiElevationBin[];
+}
+
+export interface ApiTransit {
+  body: 'sun' | 'moon';
+  time: string;
+  duration_s: number;
+  centerline_distance: number;
+  body_radius: number;
+  azimuth: number;
+  elevation: number;
+  range_km: number;
+}
+
+export interface TransitPredictions {
+  predictions: Record<string, ApiTransit[]>;
+}
+
+export interface ArtifactEntry {
+  path: string;
+  size: number;
+  sha256: string;
+}
+
+export interface ValidationProblem {
+  message: string;
+  line: number | null;
+  column: number | null;
+}
+
+export interface ReviewStep {
+  command: string;
+  time: string | null;
+  wait: boolean;
+  on_fail: string;
+  resources: string[];
+  transmit: boolean;
+}
+
+export interface ValidationReport {
+  file: string;
+  valid: boolean;
+  errors: ValidationProblem[];
+  warnings: ValidationProblem[];
+  start: string | null;
+  end: string | null;
+  steps: ReviewStep[];
+  cleanup: ReviewStep[];
+  resources: string[];
+  transmits: boolean;
 }
@@ -0,0 +1,157 @@
+import { useRef, useEffect } from 'react';
+import {
+  Chart,
+  LineController,
+  LineElement,
+  PointElement,
+  LinearScale,
+  TimeScale,
+  Filler,
+  Tooltip,
+} from 'chart.js';
+import 'chartjs-adapter-moment';
+import type { ApiPass } from '../../api/types';
+import { colorForName } from '../../theme/colors';
+import styles from './PassView.module.css';
+
+Chart.register(LineController, LineElement, PointElement, LinearScale, TimeScale, Filler, Tooltip);
+
+interface PassElevationChartProps {
+  satellite: string;
+  pass: ApiPass;
+  /** Current time (ms), drawn as a vertical line while within the pass. */
+  now: number;
+}
+
+/** Elevation of a single pass over time, from AOS to LOS. */
+export function PassElevationChart({ satellite, pass, now }: PassElevationChartProps) {
+  const canvasRef = useRef<HTMLCanvasElement>(null);
+  const chartRef = useRef<Chart | null>(null);
+  const nowRef = useRef(now);
+  nowRef.current = now;
+
+  const color = colorForName(satellite);
+
+  useEffect(() => {
+    const canvas = canvasRef.current;
+    if (!canvas) return;
+
+    chartRef.current?.destroy();
+
+    const startMs = new Date(pass.start).getTime();
+    const endMs = new Date(pass.end).getTime();
+    const step = pass.elevation.length > 1
+      ? (endMs - startMs) / (pass.elevation.length - 1)
+      : 0;
+    const data = pass.elevation.map((el, i) => ({ x: startMs + i * step, y: el }));
+
+    chartRef.current = new Chart(canvas, {
+      type: 'line',
+      data: {
+        datasets: [
+          {
+            label: 'Elevation',
+            data,
+            borderColor: color,
+            backgroundColor: color + '18',
+            borderWidth: 2,
+            pointRadius: 0,
+            pointHitRadius: 6,
+            fill: true,
+            tension: 0.3,
+          },
+        ],
+      },
+      options: {
+        responsive: true,
+        maintainAspectRatio: false,
+        animation: false,
+        interaction: {
+          mode: 'nearest',
+          axis: 'x',
+          intersect: false,
+        },
+        scales: {
+          x: {
+            type: 'time',
+            min: startMs,
+            max: endMs,
+            time: {
+              displayFormats: { second: 'HH:mm:ss', minute: 'HH:mm' },
+              tooltipFormat: 'HH:mm:ss',
+            },
+            ticks: {
+              color: '#8b949e',
+              font: { size: 11, family: 'sans-serif' },
+              maxRotation: 0,
+            },
+            grid: { color: '#21262d', lineWidth: 1 },
+            border: { color: '#30363d' },
+          },
+          y: {
+            min: 0,
+            max: 90,
+            ticks: {
+              stepSize: 30,
+              color: '#8b949e',
+              font: { size: 11, family: 'sans-serif' },
+              callback: (v) => `${v}°`,
+            },
+            grid: { color: '#21262d', lineWidth: 1 },
+            border: { color: '#30363d' },
+          },
+        },
+        plugins: {
+          legend: { display: false },
+          tooltip: {
+            backgroundColor: '#21262d',
+            titleColor: '#c9d1d9',
+            bodyColor: '#8b949e',
+            borderColor: '#30363d',
+            borderWidth: 1,
+            cornerRadius: 4,
+            padding: 8,
+            callbacks: {
+              label: (ctx) => ` El: ${(ctx.parsed.y ?? 0).toFixed(1)}°`,
+            },
+          },
+        },
+      },
+      plugins: [
+        {
+          id: 'nowLine',
+          afterDatasetsDraw(chart) {
+            const { ctx, chartArea, scales } = chart;
+            const t = nowRef.current;
+            if (!chartArea || t < scales.x.min || t > scales.x.max) return;
+            const x = scales.x.getPixelForValue(t);
+            ctx.save();
+            ctx.strokeStyle = '#c9d1d9';
+            ctx.setLineDash([4, 4]);
+            ctx.beginPath();
+            ctx.moveTo(x, chartArea.top);
+            ctx.lineTo(x, chartArea.bottom);
+            ctx.stroke();
+            ctx.restore();
+          },
+        },
+      ],
+    });
+
+    return () => {
+      chartRef.current?.destroy();
+      chartRef.current = null;
+    };
+  }, [pass, color]);
+
+  // Redraw the line at the current time
+  useEffect(() => {
+    chartRef.current?.update('none');
+  }, [now]);
+
+  return (
+    <div className={styles.elevationChartContainer}>
+      <canvas ref={canvasRef} />
+    </div>
+  );
+}
@@ -0,0 +1,141 @@
+import { useEffect, useState } from 'react';
+import { streamObservables } from '../../api/predict';
+import type { ApiObservables, ApiPass } from '../../api/types';
+import { PolarPlot } from './PolarPlot';
+import { PassElevationChart } from './PassElevationChart';
+import styles from './PassView.module.css';
+
+interface PassViewProps {
+  satellite: string;
+  pass: ApiPass;
+  onClose: () => void;
+}
+
+/** How often the pass status is refreshed (ms). */
+const CLOCK_INTERVAL = 1000;
+
+function formatTime(iso: string): string {
+  return new Date(iso).toLocaleString(undefined, {
+    month: 'short',
+    day: 'numeric',
+    hour: '2-digit',
+    minute: '2-digit',
+    second: '2-digit',
+    hour12: false,
+  });
+}
+
+function formatDuration(ms: number): string {
+  const total = Math.max(0, Math.round(ms / 1000));
+  const h = Math.floor(total / 3600);
+  const m = Math.floor((total % 3600) / 60);
+  const s = total % 60;
+  const mmss = `${String(m).padStart(2, '0')}:${String(s).padStart(2, '0')}`;
+  return h > 0 ? `${h}:${mmss}` : mmss;
+}
+
+function formatHz(hz: number): string {
+  const sign = hz >= 0 ? '+' : '-';
+  const abs = Math.abs(hz);
+  return abs >= 1000 ? `${sign}${(abs / 1000).toFixed(3)} kHz` : `${sign}${abs.toFixed(0)} Hz`;
+}
+
+/** Where we are relative to the pass at `now`. */
+function passStatus(pass: ApiPass, now: number): string {
+  const start = new Date(pass.start).getTime();
+  const end = new Date(pass.end).getTime();
+  if (now < start) return `AOS in ${formatDuration(start - now)}`;
+  if (now <= end) return `In progress, LOS in ${formatDuration(end - now)}`;
+  return `Ended ${formatDuration(now - end)} ago`;
+}
+
+/**
+ * A single pass: its track on a polar plot, its elevation over time and, streamed by the station
+ * every second, the current position, range and Doppler shift of the satellite.
+ */
+export function PassView({ satellite, pass, onClose }: PassViewProps) {
+  const [now, setNow] = useState(() => Date.now());
+  const [observables, setObservables] = useState<ApiObservables | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [frequencyMhz, setFrequencyMhz] = useState('');
+
+  const frequencyHz = parseFloat(frequencyMhz) * 1e6;
+  const frequency = Number.isFinite(frequencyHz) && frequencyHz > 0 ? frequencyHz : undefined;
+
+  useEffect(() => {
+    const timer = setInterval(() => setNow(Date.now()), CLOCK_INTERVAL);
+    return () => clearInterval(timer);
+  }, []);
+
+  useEffect(
+    () =>
+      streamObservables(
+        satellite,
+        frequency,
+        (obs) => {
+          setObservables(obs);
+          setError(null);
+        },
+        setError,
+      ),
+    [satellite, frequency],
+  );
+
+  const readings: [string, string][] = observables
+    ? [
+        ['Azimuth', `${observables.azimuth.toFixed(1)}°`],
+        ['Elevation', `${observables.elevation.toFixed(1)}°`],
+        ['Range', `${observables.range_km.toFixed(0)} km`],
+        ['Range rate', `${observables.range_rate_km_s.toFixed(3)} km/s`],
+        ['Doppler', observables.doppler_hz !== null ? formatHz(observables.doppler_hz) : '—'],
+        [
+          'Frequency',
+          frequency !== undefined && observables.doppler_hz !== null
+            ? `${((frequency + observables.doppler_hz) / 1e6).toFixed(6)} MHz`
+            : '—',
+        ],
+      ]
+    : [];
+
+  return (
+    <div className={styles.overlay} onClick={onClose}>
+      <div className={styles.dialog} onClick={(e) => e.stopPropagation()}>
+        <div className={styles.dialogHeader}>
+          <span>{satellite} &mdash; {formatTime(pass.start)}</span>
+          <button className={styles.closeButton} onClick={onClose}>&times;</button>
+        </div>
+        <div className={styles.dialogBody}>
+          <div className={styles.column}>
+            <PolarPlot satellite={satellite} pass={pass} live={observables} />
+          </div>
+          <div className={styles.column}>
+            <div className={styles.status}>
+              {passStatus(pass, now)} &middot; max elevation {pass.max_elevation.toFixed(1)}°
+            </div>
+            <PassElevationChart satellite={satellite} pass={pass} now={now} />
+            <label className={styles.frequency}>
+              Downlink
+              <input
+                type="number"
+                inputMode="decimal"
+                placeholder="437.000"
+                value={frequencyMhz}
+                onChange={(e) => setFrequencyMhz(e.target.value)}
+              />
+              MHz
+            </label>
+            {error && <div className={styles.status}>{error}</div>}
+            <div className={styles.readout}>
+              {readings.map(([label, value]) => (
+                <div key={label} className={styles.reading}>
+                  <div className={styles.readingLabel}>{label}</div>
+                  <div className={styles.readingValue}>{value}</div>
+                </div>
+              ))}
+            </div>
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -9,25 +9,15 @@ import {
 } from 'chart.js';
 import type { ApiPass } from '../../api/types';
 import { colorForName } from '../../theme/colors';
-import styles from './SatellitePasses.module.css';
+import styles from './PassView.module.css';
 
 Chart.register(ScatterController, LineElement, PointElement, RadialLinearScale, Tooltip);
 
-interface PolarPlotDialogProps {
+interface PolarPlotProps {
   satellite: string;
   pass: ApiPass;
-  onClose: () => void;
-}
-
-function formatTime(iso: string): string {
-  return new Date(iso).toLocaleString(undefined, {
-    month: 'short',
-    day: 'numeric',
-    hour: '2-digit',
-    minute: '2-digit',
-    second: '2-digit',
-    hour12: false,
-  });
+  /** Current position of the satellite, marked while above the horizon. */
+  live?: { azimuth: number; elevation: number } | null;
 }
 
 /** Convert az/el to cartesian x/y for a polar projection (0°el = edge, 90°el = center). */
@@ -45,7 +35,7 @@ function downsample<T>(arr: T[], max: number): T[] {
   return out;
 }
 
-export function PolarPlotDialog({ satellite, pass, onClose }: PolarPlotDialogProps) {
+export function PolarPlot({ satellite, pass, live }: PolarPlotProps) {
   const canvasRef = useRef<HTMLCanvasElement>(null);
   const chartRef = useRef<Chart | null>(null);
 
@@ -114,6 +104,15 @@ export function PolarPlotDialog({ satellite, pass, onClose }: PolarPlotDialogPro
             pointBorderWidth: 0,
             showLine: false,
           },
+          {
+            label: 'Now',
+            data: [],
+            pointRadius: 6,
+            pointBackgroundColor: '#ffffff',
+            pointBorderColor: trackColor,
+            pointBorderWidth: 3,
+            showLine: false,
+          },
         ],
       },
       options: {
@@ -234,19 +233,18 @@ export function PolarPlotDialog({ satellite, pass, onClose }: PolarPlotDialogPro
     };
   }, [pass, maxElIdx, maxEl, trackColor]);
 
+  // Move the live marker in place, without rebuilding the chart
+  useEffect(() => {
+    const chart = chartRef.current;
+    if (!chart) return;
+    const now = chart.data.datasets[4];
+    now.data = live && live.elevation >= 0 ? [toXY(live.azimuth, live.elevation)] : [];
+    chart.update('none');
+  }, [live]);
+
   return (
-    <div className={styles.overlay} onClick={onClose}>
-      <div className={styles.dialog} onClick={(e) => e.stopPropagation()}>
-        <div className={styles.dialogHeader}>
-          <span>{satellite} &mdash; {formatTime(pass.start)}</span>
-          <button className={styles.closeButton} onClick={onClose}>&times;</button>
-        </div>
-        <div className={styles.dialogBody}>
-          <div className={styles.polarChartContainer}>
-            <canvas ref={canvasRef} />
-          </div>
-        </div>
-      </div>
+    <div className={styles.polarChartContainer}>
+      <canvas ref={canvasRef} />
     </div>
   );
 }
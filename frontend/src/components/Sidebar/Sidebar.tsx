@@ -1,8 +1,8 @@
 import { useState } from 'react';
-import { LayoutDashboard, Settings, KeyRound, Check } from 'lucide-react';
+import { LayoutDashboard, CalendarRange, FilePen, AudioWaveform, Settings, KeyRound, Check } from 'lucide-react';
 import styles from './Sidebar.module.css';
 
-export type Screen = 'dashboard' | 'settings';
+export type Screen = 'dashboard' | 'timeline' | 'editor' | 'waterfall' | 'settings';
 
 interface SidebarProps {
   active: Screen;
@@ -12,6 +12,9 @@ interface SidebarProps {
 
 const navItems: { screen: Screen; icon: typeof LayoutDashboard; label: string }[] = [
   { screen: 'dashboard', icon: LayoutDashboard, label: 'Dashboard' },
+  { screen: 'timeline', icon: CalendarRange, label: 'Timeline' },
+  { screen: 'editor', icon: FilePen, label: 'Task editor' },
+  { screen: 'waterfall', icon: AudioWaveform, label: 'Waterfall' },
   { screen: 'settings', icon: Settings, label: 'Settings' },
 ];
 
@@ -1,9 +1,10 @@
 import { useState, useEffect, useCallback, useRef } from 'react';
 import { X } from 'lucide-react';
-import { getTask, putTask, deleteTask } from '../../api/tasks';
+import { getTask, putTask, deleteTask, controlRun } from '../../api/tasks';
+import type { RunStatus } from '../../api/types';
 import styles from './TaskModal.module.css';
 
-type Mode = { kind: 'edit'; taskId: string } | { kind: 'create' };
+type Mode = { kind: 'edit'; taskId: string; run?: RunStatus } | { kind: 'create' };
 
 interface TaskModalProps {
   mode: Mode;
@@ -104,7 +105,22 @@ export function TaskModal({ mode, onClose, onSaved }: TaskModalProps) {
     }
   }, [mode, onSaved]);
 
+  const handleControl = useCallback(async (action: 'pause' | 'resume' | 'abort') => {
+    if (mode.kind !== 'edit') return;
+    if (action === 'abort' && !confirm(`Abort task "${mode.taskId}"?`)) return;
+    setSaving(true);
+    setError(null);
+    try {
+      await controlRun(mode.taskId, action);
+      onSaved();
+    } catch (err) {
+      setError(String(err));
+      setSaving(false);
+    }
+  }, [mode, onSaved]);
+
   const isCreate = mode.kind === 'create';
+  const run = mode.kind === 'edit' ? mode.run : undefined;
   const loading = !isCreate && yaml === null && !error;
 
   return (
@@ -154,6 +170,24 @@ export function TaskModal({ mode, onClose, onSaved }: TaskModalProps) {
               Delete
             </button>
           )}
+          {run && run.state !== 'aborting' && (
+            <>
+              <button
+                className={styles.button}
+                onClick={() => handleControl(run.state === 'paused' ? 'resume' : 'pause')}
+                disabled={saving}
+              >
+                {run.state === 'paused' ? 'Resume' : 'Pause'}
+              </button>
+              <button
+                className={`${styles.button} ${styles.buttonDanger}`}
+                onClick={() => handleControl('abort')}
+                disabled={saving}
+              >
+                Abort
+              </button>
+            </>
+          )}
           <div className={styles.footerSpacer} />
           <button
             className={`${styles.button} ${styles.buttonPrimary}`}
@@ -1,6 +1,6 @@
 import { useState } from 'react';
 import { ChevronLeft, ChevronRight } from 'lucide-react';
-import type { TaskListEntry, TaskState } from '../../api/types';
+import type { RunStatus, TaskListEntry, TaskState } from '../../api/types';
 import styles from './TaskTable.module.css';
 
 const PAGE_SIZE = 15;
@@ -10,6 +10,7 @@ const stateStyleMap: Record<TaskState, string> = {
   PendingApproval: styles.statePendingApproval,
   Completed: styles.stateCompleted,
   Failed: styles.stateFailed,
+  Rejected: styles.stateRejected,
 };
 
 const stateLabel: Record<TaskState, string> = {
@@ -17,8 +18,21 @@ const stateLabel: Record<TaskState, string> = {
   PendingApproval: 'Pending',
   Completed: 'Completed',
   Failed: 'Failed',
+  Rejected: 'Rejected',
 };
 
+function runLabel(run: RunStatus): string {
+  const step = run.step === null ? '' : ` \u00b7 step ${run.step + 1}`;
+  switch (run.state) {
+    case 'paused':
+      return `Paused${step}`;
+    case 'aborting':
+      return 'Aborting';
+    default:
+      return `Running${step}`;
+  }
+}
+
 function formatTime(iso: string | null): string {
   if (!iso) return '\u2014';
   const d = new Date(iso);
@@ -88,8 +102,11 @@ export function TaskTable({ tasks, hoveredTaskId, onTaskClick, onTaskHover }: Ta
                 >
                   <td className={styles.mono}>{t.id}</td>
                   <td>
-                    <span className={`${styles.state} ${stateStyleMap[t.state]}`}>
-                      {stateLabel[t.state]}
+                    <span
+                      className={`${styles.state} ${stateStyleMap[t.state]}`}
+                      title={t.rejection}
+                    >
+                      {t.run ? runLabel(t.run) : stateLabel[t.state]}
                     </span>
                   </td>
                   <td className={styles.time}>{formatTime(t.start)}</td>
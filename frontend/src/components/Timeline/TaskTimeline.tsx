@@ -16,6 +16,7 @@ const groups: TimelineGroupBase[] = [
   { id: 'PendingApproval', title: 'Pending' },
   { id: 'Completed', title: 'Completed' },
   { id: 'Failed', title: 'Failed' },
+  { id: 'Rejected', title: 'Rejected' },
 ];
 
 const stateStyleMap: Record<TaskState, string> = {
@@ -23,6 +24,7 @@ const stateStyleMap: Record<TaskState, string> = {
   PendingApproval: styles.itemPendingApproval,
   Completed: styles.itemCompleted,
   Failed: styles.itemFailed,
+  Rejected: styles.itemRejected,
 };
 
 interface TaskTimelineProps {
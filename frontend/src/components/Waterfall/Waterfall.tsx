@@ -0,0 +1,140 @@
+import { useEffect, useRef, useState } from 'react';
+import { streamSpectrum, type Spectrum, type SpectrumRun } from '../../api/radio';
+import styles from './Waterfall.module.css';
+
+/** A frequency annotated on the waterfall */
+export interface WaterfallMarker {
+  /** Frequency (Hz) */
+  frequency: number;
+  label: string;
+  /** Drawn dashed, e.g. for a nominal frequency next to the expected one */
+  dashed?: boolean;
+}
+
+interface WaterfallProps {
+  /** Power (dBFS) shown at the bottom of the color scale. Default -100. */
+  minDb?: number;
+  /** Power (dBFS) shown at the top of the color scale. Default -20. */
+  maxDb?: number;
+  /** Spectra kept on screen. Default 256. */
+  rows?: number;
+  /** How long to wait before looking for a stream again (seconds). Default 5. */
+  retrySeconds?: number;
+  /** Frequencies marked over the waterfall, when within its bandwidth */
+  markers?: WaterfallMarker[];
+  /** Called with the run followed when a stream starts, and with null when it ends */
+  onRun?: (run: SpectrumRun | null) => void;
+}
+
+/** Black through blue, red and yellow to white, for `level` between 0 and 1. */
+function color(level: number): [number, number, number] {
+  const stops: [number, number, number][] = [
+    [0, 0, 0],
+    [0, 0, 160],
+    [200, 0, 40],
+    [255, 220, 0],
+    [255, 255, 255],
+  ];
+  const x = Math.min(Math.max(level, 0), 1) * (stops.length - 1);
+  const i = Math.min(Math.floor(x), stops.length - 2);
+  const t = x - i;
+  const [a, b] = [stops[i], stops[i + 1]];
+  return [0, 1, 2].map((c) => Math.round(a[c] + (b[c] - a[c]) * t)) as [number, number, number];
+}
+
+function formatMHz(hz: number): string {
+  return `${(hz / 1e6).toFixed(3)} MHz`;
+}
+
+export function Waterfall({
+  minDb = -100,
+  maxDb = -20,
+  rows = 256,
+  retrySeconds = 5,
+  markers = [],
+  onRun,
+}: WaterfallProps) {
+  const canvasRef = useRef<HTMLCanvasElement>(null);
+  const [latest, setLatest] = useState<Spectrum | null>(null);
+  // Kept in a ref so that a new callback does not restart the stream
+  const onRunRef = useRef(onRun);
+  useEffect(() => {
+    onRunRef.current = onRun;
+  }, [onRun]);
+
+  useEffect(() => {
+    let stop: (() => void) | null = null;
+    let retry: ReturnType<typeof setTimeout> | undefined;
+
+    const draw = (spectrum: Spectrum) => {
+      const canvas = canvasRef.current;
+      const ctx = canvas?.getContext('2d');
+      if (!canvas || !ctx) return;
+      const bins = spectrum.power.length;
+      if (canvas.width !== bins || canvas.height !== rows) {
+        canvas.width = bins;
+        canvas.height = rows;
+      }
+      // Older spectra move down a row
+      ctx.drawImage(canvas, 0, 1);
+      const row = ctx.createImageData(bins, 1);
+      spectrum.power.forEach((power, i) => {
+        const [r, g, b] = color((power - minDb) / (maxDb - minDb));
+        row.data.set([r, g, b, 255], 4 * i);
+      });
+      ctx.putImageData(row, 0, 0);
+      setLatest(spectrum);
+    };
+
+    const connect = () => {
+      stop = streamSpectrum(
+        (run) => onRunRef.current?.(run),
+        draw,
+        () => {
+          setLatest(null);
+          onRunRef.current?.(null);
+          retry = setTimeout(connect, retrySeconds * 1000);
+        },
+      );
+    };
+    connect();
+
+    return () => {
+      clearTimeout(retry);
+      stop?.();
+    };
+  }, [minDb, maxDb, rows, retrySeconds]);
+
+  return (
+    <div className={styles.wrapper}>
+      <div className={styles.info}>
+        {latest && (
+          <>
+            <span>{formatMHz(latest.frequency - latest.sampleRate / 2)}</span>
+            <span>{formatMHz(latest.frequency)}</span>
+            <span>{formatMHz(latest.frequency + latest.sampleRate / 2)}</span>
+          </>
+        )}
+      </div>
+      <div className={styles.canvasSection}>
+        <canvas ref={canvasRef} className={styles.canvas} />
+        {latest &&
+          markers.map(({ frequency, label, dashed }) => {
+            const low = latest.frequency - latest.sampleRate / 2;
+            const position = (frequency - low) / latest.sampleRate;
+            if (position < 0 || position > 1) return null;
+            return (
+              <div
+                key={label}
+                className={`${styles.marker} ${dashed ? styles.dashed : ''}`}
+                style={{ left: `${position * 100}%` }}
+              >
+                <span className={styles.markerLabel}>{label}</span>
+              </div>
+            );
+          })}
+        {!latest && <div className={styles.statusOverlay}>No SDR stream in progress</div>}
+      </div>
+    </div>
+  );
+}
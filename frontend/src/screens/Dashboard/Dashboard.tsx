@@ -6,8 +6,9 @@ import type { ApiPass, TaskListEntry } from '../../api/types';
 import { TaskTimeline } from '../../components/Timeline/TaskTimeline';
 import { TaskModal, type TaskModalMode } from '../../components/TaskModal/TaskModal';
 import { SatellitePasses } from '../../components/SatellitePasses/SatellitePasses';
-import { PolarPlotDialog } from '../../components/SatellitePasses/PolarPlotDialog';
+import { PassView } from '../../components/PassView/PassView';
 import { GroundTrack } from '../../components/GroundTrack/GroundTrack';
+import { Waterfall } from '../../components/Waterfall/Waterfall';
 import { WidgetGrid, type Widget } from '../../components/WidgetGrid/WidgetGrid';
 import styles from './Dashboard.module.css';
 
@@ -31,8 +32,9 @@ export function Dashboard() {
   useEffect(refreshTasks, [refreshTasks]);
 
   const handleTaskSelect = useCallback((id: string) => {
-    setModalMode({ kind: 'edit', taskId: id });
-  }, []);
+    const run = tasks.find((t) => t.id === id)?.run;
+    setModalMode({ kind: 'edit', taskId: id, run });
+  }, [tasks]);
 
   const handleSaved = useCallback(() => {
     setModalMode(null);
@@ -79,6 +81,12 @@ export function Dashboard() {
       ),
       layout: { i: 'passes', x: 6, y: 8, w: 6, h: 8, minH: 4 },
     },
+    {
+      key: 'waterfall',
+      title: 'Waterfall',
+      content: <Waterfall />,
+      layout: { i: 'waterfall', x: 0, y: 16, w: 12, h: 6, minH: 4 },
+    },
   ];
 
   return (
@@ -92,7 +100,7 @@ export function Dashboard() {
         />
       )}
       {selectedPass && (
-        <PolarPlotDialog
+        <PassView
           satellite={selectedPass.satellite}
           pass={selectedPass.pass}
           onClose={() => setSelectedPass(null)}
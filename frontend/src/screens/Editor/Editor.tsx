@@ -0,0 +1,267 @@
+import { useEffect, useRef, useState } from 'react';
+import { CircleAlert, TriangleAlert, Radio } from 'lucide-react';
+import { putTask, validateTask } from '../../api/tasks';
+import type { ReviewStep, ValidationProblem, ValidationReport } from '../../api/types';
+import styles from './Editor.module.css';
+
+/** Delay after the last keystroke before the task is validated (ms). */
+const VALIDATE_DELAY = 400;
+
+const STARTER_YAML = `variables:
+  start: "${new Date(Date.now() + 3600_000).toISOString().slice(0, 19)}Z"
+  end: "${new Date(Date.now() + 4500_000).toISOString().slice(0, 19)}Z"
+steps:
+  - cmd: "echo hello"
+    time: "T+0s"
+`;
+
+function formatTime(iso: string | null): string {
+  if (!iso) return '—';
+  return new Date(iso).toLocaleString(undefined, {
+    month: 'short',
+    day: 'numeric',
+    hour: '2-digit',
+    minute: '2-digit',
+    second: '2-digit',
+    hour12: false,
+  });
+}
+
+function location(problem: ValidationProblem): string {
+  if (problem.line === null) return '';
+  return problem.column === null ? `${problem.line}` : `${problem.line}:${problem.column}`;
+}
+
+function StepRows({ label, steps }: { label: string; steps: ReviewStep[] }) {
+  return (
+    <>
+      {steps.map((step, i) => (
+        <tr key={`${label}-${i}`} className={step.transmit ? styles.transmit : ''}>
+          <td>{label}[{i}]</td>
+          <td>{step.time ? formatTime(step.time) : 'after previous'}</td>
+          <td className={styles.command}>{step.command}</td>
+          <td>{step.wait ? 'yes' : ''}</td>
+          <td>{step.resources.join(', ')}</td>
+        </tr>
+      ))}
+    </>
+  );
+}
+
+/**
+ * Editor for a task definition, validated by the server as it is typed. Problems are shown next
+ * to the lines they were found at, together with the steps as they would run. The task is only
+ * submitted after confirmation.
+ */
+export function EditorScreen() {
+  const [taskId, setTaskId] = useState('');
+  const [yaml, setYaml] = useState(STARTER_YAML);
+  const [report, setReport] = useState<ValidationReport | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [confirming, setConfirming] = useState(false);
+  const [saving, setSaving] = useState(false);
+  const [submitted, setSubmitted] = useState<string | null>(null);
+  const editorRef = useRef<HTMLTextAreaElement>(null);
+  const gutterRef = useRef<HTMLDivElement>(null);
+
+  useEffect(() => {
+    let cancelled = false;
+    const timer = setTimeout(() => {
+      validateTask(yaml, taskId.trim() || undefined)
+        .then((r) => {
+          if (cancelled) return;
+          setReport(r);
+          setError(null);
+        })
+        .catch((err) => {
+          if (!cancelled) setError(String(err.message ?? err));
+        });
+    }, VALIDATE_DELAY);
+    return () => {
+      cancelled = true;
+      clearTimeout(timer);
+    };
+  }, [yaml, taskId]);
+
+  const lines = yaml.split('\n');
+  const problemLines = new Map<number, 'error' | 'warning'>();
+  for (const p of report?.warnings ?? []) {
+    if (p.line !== null) problemLines.set(p.line, 'warning');
+  }
+  for (const p of report?.errors ?? []) {
+    if (p.line !== null) problemLines.set(p.line, 'error');
+  }
+
+  const edit = (value: string) => {
+    setYaml(value);
+    setConfirming(false);
+    setSubmitted(null);
+  };
+
+  const handleTab = (e: React.KeyboardEvent<HTMLTextAreaElement>) => {
+    if (e.key !== 'Tab' || e.shiftKey) return;
+    e.preventDefault();
+    const ta = e.currentTarget;
+    const start = ta.selectionStart;
+    edit(ta.value.slice(0, start) + '  ' + ta.value.slice(ta.selectionEnd));
+    requestAnimationFrame(() => {
+      ta.selectionStart = ta.selectionEnd = start + 2;
+    });
+  };
+
+  const goToLine = (line: number) => {
+    const ta = editorRef.current;
+    if (!ta) return;
+    const offset = lines.slice(0, line - 1).reduce((sum, l) => sum + l.length + 1, 0);
+    ta.focus();
+    ta.setSelectionRange(offset, offset + (lines[line - 1]?.length ?? 0));
+  };
+
+  const submit = async () => {
+    const id = taskId.trim();
+    setSaving(true);
+    setError(null);
+    try {
+      const status = await putTask(id, yaml);
+      setSubmitted(status === 201 ? `Submitted ${id}` : `Updated ${id}`);
+      setConfirming(false);
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  const problems: [('error' | 'warning'), ValidationProblem][] = [
+    ...(report?.errors ?? []).map((p) => ['error', p] as ['error', ValidationProblem]),
+    ...(report?.warnings ?? []).map((p) => ['warning', p] as ['warning', ValidationProblem]),
+  ];
+  const canSubmit = !!taskId.trim() && !!report?.valid && !saving;
+
+  return (
+    <div className={styles.root}>
+      <div className={styles.editorPane}>
+        <div className={styles.toolbar}>
+          <input
+            className={styles.idInput}
+            value={taskId}
+            onChange={(e) => {
+              setTaskId(e.target.value);
+              setConfirming(false);
+              setSubmitted(null);
+            }}
+            placeholder="Task ID"
+            spellCheck={false}
+          />
+          <button
+            className={`${styles.button} ${styles.buttonPrimary}`}
+            onClick={() => setConfirming(true)}
+            disabled={!canSubmit || confirming}
+          >
+            Submit
+          </button>
+        </div>
+        {confirming && report && (
+          <div className={styles.confirm}>
+            <span>
+              Submit <strong>{taskId.trim()}</strong> with {report.steps.length} steps
+              {report.transmits && ', transmitting'}?
+            </span>
+            <button className={styles.button} onClick={() => setConfirming(false)} disabled={saving}>
+              Cancel
+            </button>
+            <button
+              className={`${styles.button} ${styles.buttonPrimary}`}
+              onClick={submit}
+              disabled={saving}
+            >
+              {saving ? 'Submitting…' : 'Confirm'}
+            </button>
+          </div>
+        )}
+        {submitted && <div className={styles.submitted}>{submitted}</div>}
+        {error && <div className={styles.error}>{error}</div>}
+        <div className={styles.editorBody}>
+          <div className={styles.gutter} ref={gutterRef} aria-hidden>
+            {lines.map((_, i) => (
+              <div
+                key={i}
+                className={
+                  problemLines.get(i + 1) === 'error'
+                    ? styles.lineError
+                    : problemLines.get(i + 1) === 'warning'
+                      ? styles.lineWarning
+                      : ''
+                }
+              >
+                {i + 1}
+              </div>
+            ))}
+          </div>
+          <textarea
+            ref={editorRef}
+            className={styles.editor}
+            value={yaml}
+            onChange={(e) => edit(e.target.value)}
+            onKeyDown={handleTab}
+            onScroll={(e) => {
+              if (gutterRef.current) gutterRef.current.scrollTop = e.currentTarget.scrollTop;
+            }}
+            spellCheck={false}
+            wrap="off"
+          />
+        </div>
+      </div>
+
+      <div className={styles.previewPane}>
+        <div className={styles.sectionTitle}>
+          {report === null ? 'Validating…' : report.valid ? 'Valid' : 'Invalid'}
+        </div>
+        {problems.length > 0 && (
+          <ul className={styles.problems}>
+            {problems.map(([severity, p], i) => (
+              <li key={i} className={severity === 'error' ? styles.problemError : styles.problemWarning}>
+                {severity === 'error' ? <CircleAlert size={14} /> : <TriangleAlert size={14} />}
+                {p.line !== null && (
+                  <button className={styles.lineLink} onClick={() => goToLine(p.line!)}>
+                    {location(p)}
+                  </button>
+                )}
+                <span>{p.message}</span>
+              </li>
+            ))}
+          </ul>
+        )}
+        {report && (
+          <>
+            <div className={styles.summary}>
+              <span>Start: {formatTime(report.start)}</span>
+              <span>End: {formatTime(report.end)}</span>
+              <span>Resources: {report.resources.length ? report.resources.join(', ') : 'none'}</span>
+              {report.transmits && (
+                <span className={styles.transmitBadge}>
+                  <Radio size={12} /> Transmits
+                </span>
+              )}
+            </div>
+            <table className={styles.steps}>
+              <thead>
+                <tr>
+                  <th>Step</th>
+                  <th>Time</th>
+                  <th>Command</th>
+                  <th>Wait</th>
+                  <th>Resources</th>
+                </tr>
+              </thead>
+              <tbody>
+                <StepRows label="steps" steps={report.steps} />
+                <StepRows label="cleanup" steps={report.cleanup} />
+              </tbody>
+            </table>
+          </>
+        )}
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,247 @@
+import { useCallback, useEffect, useRef, useState } from 'react';
+import Timeline, {
+  TimelineMarkers,
+  TodayMarker,
+  type TimelineGroupBase,
+  type TimelineItemBase,
+} from 'react-calendar-timeline';
+import 'react-calendar-timeline/style.css';
+import { listTasks } from '../../api/tasks';
+import { fetchPasses } from '../../api/predict';
+import type { ApiPass, TaskListEntry, TaskState } from '../../api/types';
+import { PassView } from '../../components/PassView/PassView';
+import { colorForName } from '../../theme/colors';
+import timelineStyles from '../../components/Timeline/TaskTimeline.module.css';
+import styles from './Timeline.module.css';
+
+type Resolution = 'hourly' | 'daily';
+
+/** Visible window for each resolution (ms). */
+const windowFor: Record<Resolution, number> = {
+  hourly: 12 * 3600_000,
+  daily: 7 * 86400_000,
+};
+
+/** Passes are not predicted over more than this (ms), to keep the request cheap. */
+const MAX_PASS_WINDOW = 14 * 86400_000;
+
+const stateGroups: TimelineGroupBase[] = [
+  { id: 'Active', title: 'Active' },
+  { id: 'PendingApproval', title: 'Pending' },
+  { id: 'Completed', title: 'Completed' },
+  { id: 'Failed', title: 'Failed' },
+  { id: 'Rejected', title: 'Rejected' },
+];
+
+const stateStyleMap: Record<TaskState, string> = {
+  Active: timelineStyles.itemActive,
+  PendingApproval: timelineStyles.itemPendingApproval,
+  Completed: timelineStyles.itemCompleted,
+  Failed: timelineStyles.itemFailed,
+  Rejected: timelineStyles.itemRejected,
+};
+
+interface Span {
+  start: number;
+  end: number;
+}
+
+function taskSpan(task: TaskListEntry): Span | null {
+  if (!task.start || !task.end) return null;
+  return { start: new Date(task.start).getTime(), end: new Date(task.end).getTime() };
+}
+
+function overlaps(a: Span, b: Span): boolean {
+  return a.start < b.end && b.start < a.end;
+}
+
+/** IDs of the upcoming tasks (Active or PendingApproval) overlapping another upcoming task. */
+function conflictingTasks(tasks: TaskListEntry[]): Set<string> {
+  const upcoming = tasks
+    .filter((t) => t.state === 'Active' || t.state === 'PendingApproval')
+    .map((t) => ({ id: t.id, span: taskSpan(t) }))
+    .filter((t): t is { id: string; span: Span } => t.span !== null);
+  const conflicts = new Set<string>();
+  for (let i = 0; i < upcoming.length; i++) {
+    for (let j = i + 1; j < upcoming.length; j++) {
+      if (overlaps(upcoming[i].span, upcoming[j].span)) {
+        conflicts.add(upcoming[i].id);
+        conflicts.add(upcoming[j].id);
+      }
+    }
+  }
+  return conflicts;
+}
+
+/**
+ * The schedule of the station and the predicted passes on one zoomable timeline. Upcoming tasks
+ * overlapping each other are highlighted, and passes covered by an upcoming task are marked.
+ */
+export function TimelineScreen() {
+  const [resolution, setResolution] = useState<Resolution>('hourly');
+  const [range, setRange] = useState<[number, number]>(() => {
+    const now = Date.now();
+    return [now - windowFor.hourly / 4, now + (windowFor.hourly * 3) / 4];
+  });
+  const [tasks, setTasks] = useState<TaskListEntry[]>([]);
+  const [passes, setPasses] = useState<Record<string, ApiPass[]>>({});
+  const [error, setError] = useState<string | null>(null);
+  const [selectedPass, setSelectedPass] = useState<{ satellite: string; pass: ApiPass } | null>(null);
+  const debounceRef = useRef<ReturnType<typeof setTimeout>>(undefined);
+  const fetchRef = useRef(0);
+
+  useEffect(() => {
+    listTasks()
+      .then(setTasks)
+      .catch((err) => setError(err.message));
+  }, []);
+
+  // Predict the passes of the visible window, debounced while scrolling
+  useEffect(() => {
+    clearTimeout(debounceRef.current);
+    debounceRef.current = setTimeout(() => {
+      const id = ++fetchRef.current;
+      const [start, end] = range;
+      const clampedEnd = Math.min(end, start + MAX_PASS_WINDOW);
+      fetchPasses(new Date(start).toISOString(), new Date(clampedEnd).toISOString())
+        .then((data) => {
+          if (id === fetchRef.current) setPasses(data.predictions);
+        })
+        .catch((err) => {
+          if (id === fetchRef.current) setError(err.message);
+        });
+    }, 300);
+    return () => clearTimeout(debounceRef.current);
+  }, [range]);
+
+  const handleTimeChange = useCallback(
+    (
+      start: number,
+      end: number,
+      updateScrollCanvas: (start: number, end: number) => void,
+    ) => {
+      updateScrollCanvas(start, end);
+      setRange([start, end]);
+    },
+    [],
+  );
+
+  const changeResolution = (next: Resolution) => {
+    const center = (range[0] + range[1]) / 2;
+    const half = windowFor[next] / 2;
+    setResolution(next);
+    setRange([center - half, center + half]);
+  };
+
+  const conflicts = conflictingTasks(tasks);
+  const upcomingSpans = tasks
+    .filter((t) => t.state === 'Active' || t.state === 'PendingApproval')
+    .map(taskSpan)
+    .filter((s): s is Span => s !== null);
+
+  const satellites = Object.keys(passes)
+    .filter((name) => passes[name].length > 0)
+    .sort();
+  const groups: TimelineGroupBase[] = [
+    ...stateGroups,
+    ...satellites.map((name) => ({ id: `pass:${name}`, title: name })),
+  ];
+
+  const taskItems: TimelineItemBase<number>[] = tasks
+    .filter((t) => t.start && t.end)
+    .map((t) => ({
+      id: `task:${t.id}`,
+      group: t.state,
+      title: conflicts.has(t.id) ? `${t.id} (conflict)` : t.id,
+      start_time: new Date(t.start!).getTime(),
+      end_time: new Date(t.end!).getTime(),
+      className: [
+        timelineStyles.item,
+        stateStyleMap[t.state],
+        conflicts.has(t.id) ? styles.conflict : '',
+      ].join(' '),
+    }));
+
+  const flatPasses = satellites.flatMap((satellite) =>
+    passes[satellite].map((pass) => ({ satellite, pass })),
+  );
+  const passItems: TimelineItemBase<number>[] = flatPasses.map(({ satellite, pass }, i) => {
+    const span = { start: new Date(pass.start).getTime(), end: new Date(pass.end).getTime() };
+    const scheduled = upcomingSpans.some((s) => overlaps(s, span));
+    return {
+      id: `pass:${i}`,
+      group: `pass:${satellite}`,
+      title: `${pass.max_elevation.toFixed(0)}°`,
+      start_time: span.start,
+      end_time: span.end,
+      className: [timelineStyles.item, scheduled ? styles.passScheduled : ''].join(' '),
+      itemProps: { style: { background: colorForName(satellite) } },
+    };
+  });
+
+  const handleItemSelect = (itemId: string | number) => {
+    const id = String(itemId);
+    if (!id.startsWith('pass:')) return;
+    const selected = flatPasses[Number(id.slice('pass:'.length))];
+    if (selected) setSelectedPass(selected);
+  };
+
+  return (
+    <div className={styles.root}>
+      <div className={styles.toolbar}>
+        <div className={styles.resolution}>
+          {(['hourly', 'daily'] as const).map((r) => (
+            <button
+              key={r}
+              className={resolution === r ? styles.selected : ''}
+              onClick={() => changeResolution(r)}
+            >
+              {r === 'hourly' ? 'Hourly' : 'Daily'}
+            </button>
+          ))}
+        </div>
+        <span className={styles.legend}>
+          <span className={`${styles.swatch} ${styles.conflict}`} /> Conflicting tasks
+          <span className={`${styles.swatch} ${styles.passScheduled}`} /> Scheduled pass
+        </span>
+        {error && <span className={styles.error}>{error}</span>}
+      </div>
+      <div className={`${timelineStyles.timelineWrapper} ${styles.timeline}`}>
+        <Timeline
+          groups={groups}
+          items={[...taskItems, ...passItems]}
+          defaultTimeStart={range[0]}
+          defaultTimeEnd={range[1]}
+          visibleTimeStart={range[0]}
+          visibleTimeEnd={range[1]}
+          onTimeChange={handleTimeChange}
+          onItemSelect={handleItemSelect}
+          onItemClick={handleItemSelect}
+          sidebarWidth={160}
+          lineHeight={32}
+          itemHeightRatio={0.75}
+          minZoom={3600_000}
+          maxZoom={30 * 86400_000}
+          canMove={false}
+          canResize={false}
+          canChangeGroup={false}
+        >
+          <TimelineMarkers>
+            <TodayMarker interval={10000}>
+              {({ styles: markerStyles }) => (
+                <div className={timelineStyles.nowMarker} style={markerStyles} />
+              )}
+            </TodayMarker>
+          </TimelineMarkers>
+        </Timeline>
+      </div>
+      {selectedPass && (
+        <PassView
+          satellite={selectedPass.satellite}
+          pass={selectedPass.pass}
+          onClose={() => setSelectedPass(null)}
+        />
+      )}
+    </div>
+  );
+}
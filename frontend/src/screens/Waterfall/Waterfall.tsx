@@ -0,0 +1,89 @@
+import { useEffect, useState } from 'react';
+import { streamObservables } from '../../api/predict';
+import type { SpectrumRun } from '../../api/radio';
+import type { ApiObservables } from '../../api/types';
+import { Waterfall, type WaterfallMarker } from '../../components/Waterfall/Waterfall';
+import styles from './Waterfall.module.css';
+
+/** The latest sample, or error, of the observables of the satellite of a task */
+interface Observed {
+  task: string;
+  observables: ApiObservables | null;
+  error: string | null;
+}
+
+function formatMHz(hz: number): string {
+  return `${(hz / 1e6).toFixed(4)} MHz`;
+}
+
+/**
+ * The live waterfall of the run in progress, with the downlink of the satellite of its task
+ * marked where it is expected: shifted by the Doppler effect, which follows the satellite along
+ * the pass.
+ */
+export function WaterfallScreen() {
+  const [run, setRun] = useState<SpectrumRun | null>(null);
+  const [observed, setObserved] = useState<Observed | null>(null);
+
+  const task = run?.task ?? null;
+  const satellite = run?.satellite ?? null;
+  const downlink = run?.rx_frequency ?? null;
+
+  useEffect(() => {
+    if (!task || !satellite || downlink === null) return;
+    return streamObservables(
+      satellite,
+      downlink,
+      (observables) => setObserved({ task, observables, error: null }),
+      (error) =>
+        setObserved((o) => ({ task, observables: o?.task === task ? o.observables : null, error })),
+    );
+  }, [task, satellite, downlink]);
+
+  // Those of a previous task are not shown
+  const current = observed?.task === task ? observed : null;
+  const observables = current?.observables ?? null;
+  const error = current?.error ?? null;
+  const doppler = observables?.doppler_hz ?? null;
+  const markers: WaterfallMarker[] = [];
+  if (downlink !== null) {
+    if (doppler !== null) {
+      const expected = downlink + doppler;
+      markers.push({ frequency: expected, label: `Expected ${formatMHz(expected)}` });
+    }
+    markers.push({ frequency: downlink, label: 'Nominal', dashed: true });
+  }
+
+  return (
+    <div className={styles.root}>
+      <div className={styles.toolbar}>
+        {run ? (
+          <>
+            <span>
+              Task <strong>{run.task}</strong>
+            </span>
+            {satellite && <span>{satellite}</span>}
+            {downlink !== null ? (
+              <span>Downlink {formatMHz(downlink)}</span>
+            ) : (
+              <span className={styles.muted}>No rx_frequency in the task</span>
+            )}
+            {doppler !== null && (
+              <span>
+                Doppler {doppler >= 0 ? '+' : ''}
+                {(doppler / 1e3).toFixed(2)} kHz
+              </span>
+            )}
+            {observables && <span>Elevation {observables.elevation.toFixed(1)}°</span>}
+            {error && <span className={styles.error}>{error}</span>}
+          </>
+        ) : (
+          <span className={styles.muted}>Waiting for a run streaming its spectrum</span>
+        )}
+      </div>
+      <div className={styles.waterfall}>
+        <Waterfall rows={512} markers={markers} onRun={setRun} />
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,262 @@
+//! Append-only log of the API calls that change the station state.
+//!
+//! Every POST, PUT and DELETE request is recorded, whatever its outcome, as a line of JSON in the
+//! audit file.
+
+use std::path::PathBuf;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::config::{Config, Permission};
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+
+const DEFAULT_AUDIT_FILE: &str = "audit.jsonl";
+
+const DEFAULT_LIMIT: usize = 100;
+
+pub struct AuditLog {
+    path: PathBuf,
+    /// Serializes the writes, so that lines are not interleaved.
+    lock: Mutex<()>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct AuditEntry {
+    /// Time of the request formatted as RFC3339
+    pub time: String,
+    pub request_id: String,
+    /// Identity of the caller, if authenticated
+    pub user: Option<String>,
+    pub method: String,
+    pub path: String,
+    /// Status code of the response
+    pub status: u16,
+}
+
+impl AuditLog {
+    /// Opens the audit log at the configured path.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            path: config
+                .api
+                .audit_path
+                .clone()
+                .unwrap_or_else(|| config.tasks_path.join(DEFAULT_AUDIT_FILE)),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Whether requests with `method` are recorded.
+    pub fn records(method: &Method) -> bool {
+        matches!(*method, Method::POST | Method::PUT | Method::DELETE)
+    }
+
+    /// Appends `entry` to the log.
+    pub async fn record(&self, entry: &AuditEntry) {
+        let Ok(mut line) = serde_json::to_string(entry) else {
+            return;
+        };
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        let result = async {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+            file.write_all(line.as_bytes()).await
+        }
+        .await;
+        if let Err(e) = result {
+            warn!(path = ?self.path, ?e, ?entry, "failed to write audit log");
+        }
+    }
+
+    async fn entries(&self) -> std::io::Result<Vec<AuditEntry>> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AuditQuery {
+    /// Only return the calls made by this user.
+    pub user: Option<String>,
+    /// Maximum number of entries to return, most recent first. Defaults to 100.
+    pub limit: Option<usize>,
+    /// Number of entries to skip, most recent first.
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Get the audit log.
+///
+/// Returns the recorded POST, PUT and DELETE calls, most recent first.
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = super::AUDIT_TAG,
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Audit log entries", body = Vec<AuditEntry>),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_audit(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, ApiError> {
+    auth.require(Permission::ViewAuditLog)?;
+
+    let entries = state.audit.entries().await.map_err(|e| {
+        warn!(?e, "failed to read audit log");
+        ApiError::Internal
+    })?;
+    let entries = entries
+        .into_iter()
+        .rev()
+        .filter(|e| query.user.is_none() || e.user == query.user)
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(DEFAULT_LIMIT))
+        .collect();
+
+    Ok(Json(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    #[tokio::test]
+    async fn mutating_calls_are_recorded() {
+        let tmp = tempfile::tempdir().unwrap();
+        for dir in ["Active", "PendingApproval"] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        let config = Config::for_test(
+            tmp.path(),
+            vec![
+                ApiKey {
+                    name: Some("operator".into()),
+                    id: None,
+                    key: "operator-key".into(),
+                    permissions: vec![Permission::ViewTasks, Permission::DeleteTask],
+                },
+                ApiKey {
+                    name: Some("admin".into()),
+                    id: None,
+                    key: "admin-key".into(),
+                    permissions: vec![Permission::ViewAuditLog],
+                },
+            ],
+        );
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+        let request = |method: &str, uri: &str, key: &str| {
+            router.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("api_key", key)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        // Reads are not recorded
+        request("GET", "/api/v1/tasks", "operator-key")
+            .await
+            .unwrap();
+        let resp = request("DELETE", "/api/v1/tasks/nope", "operator-key")
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        request("PUT", "/api/v1/tasks/new", "wrong-key")
+            .await
+            .unwrap();
+
+        let resp = request("GET", "/api/v1/audit", "operator-key")
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = request("GET", "/api/v1/audit", "admin-key").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let entries: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let entries = entries.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["method"], "PUT");
+        assert_eq!(entries[0]["user"], serde_json::Value::Null);
+        assert_eq!(entries[0]["status"], 401);
+        assert_eq!(entries[1]["method"], "DELETE");
+        assert_eq!(entries[1]["path"], "/api/v1/tasks/nope");
+        assert_eq!(entries[1]["user"], "key:operator");
+        assert_eq!(entries[1]["status"], 404);
+
+        let resp = request("GET", "/api/v1/audit?user=key:operator", "admin-key")
+            .await
+            .unwrap();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let entries: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(entries.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn calls_refused_after_failed_logins_are_recorded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            tasks_path: tmp.path().to_path_buf(),
+            ..Default::default()
+        };
+        config.api.failed_logins = 1;
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+        for status in [StatusCode::UNAUTHORIZED, StatusCode::TOO_MANY_REQUESTS] {
+            let resp = router
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("DELETE")
+                        .uri("/api/v1/tasks/nope")
+                        .header("api_key", "wrong-key")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), status);
+        }
+
+        let entries = AuditLog::new(&config).entries().await.unwrap();
+        let statuses: Vec<_> = entries.iter().map(|e| e.status).collect();
+        assert_eq!(statuses, [401, 429]);
+        assert!(entries.iter().all(|e| e.user.is_none()));
+    }
+}
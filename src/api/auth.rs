@@ -1,16 +1,56 @@
-use axum::extract::FromRequestParts;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
+
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use axum::extract::{ConnectInfo, FromRequestParts, Query, Request, State};
+use axum::http::header::AUTHORIZATION;
 use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
 
 use crate::config::Permission;
 
 use super::{AppState, error::ApiError};
 
-/// Extracts and validates the API key from the `api_key` header.
+/// Routes also taking the credentials from the `token` query parameter, for the clients that
+/// cannot set headers: calendar apps and browser WebSockets.
+const TOKEN_ROUTES: [&str; 3] = ["/tasks/calendar.ics", "/radio/fft", "/tracker/ws"];
+
+/// The caller of a request, as authenticated by [`authenticate`].
+///
+/// Requests without credentials are rejected, unless `public_read` is configured: they are then
+/// given ViewTasks permission only.
+#[derive(Debug, Clone)]
 pub struct AuthenticatedKey {
+    /// Identity of the caller, recorded as the owner of the tasks it submits: `key:<name>` for
+    /// config keys, `stored:<id>` for keys created through the API, or `jwt:<issuer>:<subject>`
+    /// for bearer tokens. The prefixes keep a token subject from passing for a key.
+    pub owner: String,
     pub permissions: Vec<Permission>,
 }
 
+/// Owner of the requests without credentials, allowed with `public_read`.
+const ANONYMOUS: &str = "";
+
 impl AuthenticatedKey {
+    fn anonymous() -> Self {
+        Self {
+            owner: ANONYMOUS.to_string(),
+            permissions: vec![Permission::ViewTasks],
+        }
+    }
+
+    /// Whether the request had no credentials.
+    pub fn is_anonymous(&self) -> bool {
+        self.owner == ANONYMOUS
+    }
+
     pub fn has(&self, permission: Permission) -> bool {
         self.permissions.contains(&permission)
     }
@@ -19,9 +59,64 @@ impl AuthenticatedKey {
         if self.has(permission) {
             Ok(())
         } else {
-            Err(ApiError::Forbidden)
+            Err(self.denied())
+        }
+    }
+
+    /// Error for a missing permission: anonymous callers are asked to authenticate.
+    fn denied(&self) -> ApiError {
+        if self.is_anonymous() {
+            ApiError::Unauthorized
+        } else {
+            ApiError::Forbidden
         }
     }
+
+    /// Requires `permission` on a task owned by `owner`, or its owner-scoped variant if the task
+    /// was submitted by the caller.
+    pub fn require_on(&self, permission: Permission, owner: Option<&str>) -> Result<(), ApiError> {
+        let own = owner_scoped(&permission);
+        if self.has(permission) || own.is_some_and(|own| self.has(own) && self.owns(owner)) {
+            Ok(())
+        } else {
+            Err(self.denied())
+        }
+    }
+
+    /// Returns whether the caller can see all tasks, or only its own.
+    pub fn can_view_all(&self) -> Result<bool, ApiError> {
+        if self.has(Permission::ViewTasks) {
+            Ok(true)
+        } else if self.has(Permission::ViewOwnTasks) {
+            Ok(false)
+        } else {
+            Err(self.denied())
+        }
+    }
+
+    pub fn owns(&self, owner: Option<&str>) -> bool {
+        !self.is_anonymous() && owner == Some(self.owner.as_str())
+    }
+}
+
+/// Returns the variant of `permission` restricted to the caller's own tasks.
+fn owner_scoped(permission: &Permission) -> Option<Permission> {
+    match permission {
+        Permission::ViewTasks => Some(Permission::ViewOwnTasks),
+        Permission::EditTask => Some(Permission::EditOwnTasks),
+        Permission::DeleteTask => Some(Permission::DeleteOwnTasks),
+        _ => None,
+    }
+}
+
+/// The outcome of authenticating a request, added to its extensions by [`authenticate`] for the
+/// rate limits and the handlers, and to those of the response for the request log.
+#[derive(Debug, Clone)]
+pub struct Authentication {
+    /// The caller, None if the credentials are missing or invalid
+    pub key: Option<AuthenticatedKey>,
+    /// Address the request came from, if known
+    pub address: Option<IpAddr>,
 }
 
 impl FromRequestParts<AppState> for AuthenticatedKey {
@@ -29,24 +124,330 @@ impl FromRequestParts<AppState> for AuthenticatedKey {
 
     async fn from_request_parts(
         parts: &mut Parts,
-        state: &AppState,
+        _state: &AppState,
     ) -> Result<Self, Self::Rejection> {
-        let key_value = parts
-            .headers
-            .get("api_key")
+        parts
+            .extensions
+            .get::<Authentication>()
+            .and_then(|auth| auth.key.clone())
+            .ok_or(ApiError::Unauthorized)
+    }
+}
+
+/// Credentials of a request.
+enum Credentials {
+    None,
+    /// Given, but not in a form that could be valid
+    Invalid,
+    ApiKey(String),
+    Jwt(String),
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// The API key from the `api_key` header or, if JWT authentication is configured, the bearer
+/// token from the `Authorization` header, or either from the `token` parameter of the
+/// [`TOKEN_ROUTES`].
+fn credentials(request: &Request, state: &AppState) -> Credentials {
+    let headers = request.headers();
+    if state.jwt.is_some()
+        && let Some(token) = headers
+            .get(AUTHORIZATION)
             .and_then(|v| v.to_str().ok())
-            .ok_or(ApiError::Unauthorized)?;
+            .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Credentials::Jwt(token.to_string());
+    }
+    if let Some(key) = headers.get("api_key") {
+        return key.to_str().map_or(Credentials::Invalid, |key| {
+            Credentials::ApiKey(key.to_string())
+        });
+    }
+    if headers.contains_key(AUTHORIZATION) {
+        return Credentials::Invalid;
+    }
+    let path = request.uri().path();
+    if TOKEN_ROUTES.iter().any(|route| path.ends_with(route))
+        && let Ok(Query(TokenQuery { token: Some(token) })) = Query::try_from_uri(request.uri())
+    {
+        return if state.jwt.is_some() && token.matches('.').count() == 2 {
+            Credentials::Jwt(token)
+        } else {
+            Credentials::ApiKey(token)
+        };
+    }
+    Credentials::None
+}
 
-        let api_key = state
+/// Middleware authenticating each request once, before the rate limits and the handlers, which
+/// find the outcome in its [`Authentication`].
+///
+/// Requests with credentials from an address that failed to authenticate too often are refused
+/// without checking them.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let address = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    let key = match credentials(&request, &state) {
+        Credentials::None => state
             .config
             .api
+            .public_read
+            .then(AuthenticatedKey::anonymous),
+        Credentials::Invalid => None,
+        credentials => {
+            if !state.failed_logins.allows(address, Instant::now()) {
+                warn!(
+                    ?address,
+                    "too many failed authentications, refusing request"
+                );
+                return ApiError::TooManyRequests.into_response();
+            }
+            let key = match credentials {
+                Credentials::Jwt(token) => validate_jwt(&state, &token),
+                Credentials::ApiKey(key) => find_api_key(&state, &key).await,
+                Credentials::None | Credentials::Invalid => unreachable!("handled above"),
+            };
+            if key.is_none() {
+                state.failed_logins.record(address, Instant::now());
+            }
+            key
+        }
+    };
+    let authentication = Authentication { key, address };
+    request.extensions_mut().insert(authentication.clone());
+    let mut response = next.run(request).await;
+    // For the request log, which wraps this middleware to also see the requests refused here
+    response.extensions_mut().insert(authentication);
+    response
+}
+
+fn validate_jwt(state: &AppState, token: &str) -> Option<AuthenticatedKey> {
+    let validator = state.jwt.as_ref()?;
+    validator
+        .validate(token, Utc::now().timestamp())
+        .inspect_err(|e| debug!(%e, "rejected bearer token"))
+        .ok()
+}
+
+/// Where a key to check comes from.
+enum Candidate {
+    /// The key of the config at this index
+    Config(usize),
+    /// The key of the store with this ID
+    Stored(String),
+}
+
+/// Looks up `key` in the config, then in the key store.
+///
+/// Keys starting with an ID (`sk_<id>_...`) are only checked against the entries of that ID, so
+/// that at most one slow hash is checked for them. Keys without one are checked against the
+/// entries without an ID. Hashes are checked on the blocking threads, without holding the store.
+async fn find_api_key(state: &AppState, key: &str) -> Option<AuthenticatedKey> {
+    let id = key_id(key);
+    let keys = &state.config.api.keys;
+    let mut candidates: Vec<(String, Candidate)> = keys
+        .iter()
+        .enumerate()
+        .filter(|(_, k)| is_candidate(id, k.id.as_deref(), &k.key))
+        .map(|(index, k)| (k.key.clone(), Candidate::Config(index)))
+        .collect();
+    candidates.extend(
+        state
             .keys
-            .iter()
-            .find(|k| k.key == key_value)
-            .ok_or(ApiError::Unauthorized)?;
+            .lock()
+            .await
+            .candidates(id)
+            .into_iter()
+            .map(|(hash, id)| (hash, Candidate::Stored(id))),
+    );
+
+    for (hash, candidate) in candidates {
+        if !verify_cached(state, key, hash).await {
+            continue;
+        }
+        return match candidate {
+            Candidate::Config(index) => Some(AuthenticatedKey {
+                owner: match &keys[index].name {
+                    Some(name) => format!("key:{name}"),
+                    None => format!("key:config-{index}"),
+                },
+                permissions: keys[index].permissions.clone(),
+            }),
+            Candidate::Stored(id) => state
+                .keys
+                .lock()
+                .await
+                .record_use(&id, Utc::now())
+                .await
+                .map(|stored| AuthenticatedKey {
+                    owner: format!("stored:{}", stored.id),
+                    permissions: stored.permissions,
+                }),
+        };
+    }
+    None
+}
+
+/// Whether `key_id`, the ID of a key if it has one, is to be checked against the config entry
+/// `id` with hash `stored`: the entry of its ID, or any entry without an ID if the hash is fast to
+/// check.
+fn is_candidate(key_id: Option<&str>, id: Option<&str>, stored: &str) -> bool {
+    match id {
+        Some(id) => key_id == Some(id),
+        None => key_id.is_none() || !is_argon2(stored),
+    }
+}
+
+/// Checks `key` against `stored` like [`verify_key`] on a blocking thread, unless it matched
+/// before.
+async fn verify_cached(state: &AppState, key: &str, stored: String) -> bool {
+    if state.keys.lock().await.verified(key, &stored) {
+        return true;
+    }
+    let checked = key.to_string();
+    let hash = stored.clone();
+    let matches = tokio::task::spawn_blocking(move || verify_key(&checked, &hash))
+        .await
+        .unwrap_or(false);
+    if matches {
+        state.keys.lock().await.remember(key, &stored);
+    }
+    matches
+}
+
+/// Prefix of the hashes returned by [`hash_key`].
+const ARGON2_PREFIX: &str = "$argon2";
+/// Prefix of the salted SHA-256 hashes of earlier versions, still accepted.
+const SHA256_PREFIX: &str = "sha256$";
+
+/// Returns the hash of `key`, as stored in the config and key store: an Argon2id hash with a
+/// random salt, in the PHC string format (`$argon2id$v=19$...`).
+pub fn hash_key(key: &str) -> std::io::Result<String> {
+    let mut salt = [0; 16];
+    random_bytes(&mut salt)?;
+    let hashed = SaltString::encode_b64(&salt).and_then(|salt| {
+        Argon2::default()
+            .hash_password(key.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    });
+    hashed.map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+/// Generates a new random API key, starting with a random ID (`sk_<id>_...`) to look it up by.
+pub fn generate_key() -> std::io::Result<String> {
+    Ok(format!("sk_{}_{}", random_hex(4)?, random_hex(32)?))
+}
+
+/// ID of the key `key`, which starts with it, e.g. `sk_0badf00d_...`. Keys generated before
+/// do not.
+pub fn key_id(key: &str) -> Option<&str> {
+    key.strip_prefix("sk_")?.split_once('_').map(|(id, _)| id)
+}
+
+/// Checks `key` against `stored`, which is either a hash as returned by [`hash_key`] or, for
+/// backwards compatibility, a salted SHA-256 hash (`sha256$<salt>$<hash>`, hex encoded) or the
+/// plaintext key.
+pub fn verify_key(key: &str, stored: &str) -> bool {
+    if is_argon2(stored) {
+        return PasswordHash::new(stored).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(key.as_bytes(), &hash)
+                .is_ok()
+        });
+    }
+    match stored
+        .strip_prefix(SHA256_PREFIX)
+        .and_then(|s| s.split_once('$'))
+    {
+        Some((salt, hash)) => constant_time_eq(&salted_hash(salt, key), hash),
+        None => constant_time_eq(key, stored),
+    }
+}
+
+/// Returns whether `stored` is a hashed key.
+pub fn is_hashed(stored: &str) -> bool {
+    is_argon2(stored) || stored.starts_with(SHA256_PREFIX)
+}
+
+/// Returns whether `stored` is a hash as returned by [`hash_key`], slow to verify on purpose.
+pub fn is_argon2(stored: &str) -> bool {
+    stored.starts_with(ARGON2_PREFIX)
+}
+
+fn salted_hash(salt: &str, key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(key.as_bytes());
+    to_hex(&hasher.finalize())
+}
+
+/// Compares two strings in time independent of where they differ.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Returns `bytes` random bytes, hex encoded.
+pub(super) fn random_hex(bytes: usize) -> std::io::Result<String> {
+    let mut buf = vec![0; bytes];
+    random_bytes(&mut buf)?;
+    Ok(to_hex(&buf))
+}
+
+fn random_bytes(buf: &mut [u8]) -> std::io::Result<()> {
+    std::fs::File::open("/dev/urandom")?.read_exact(buf)
+}
+
+pub(super) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashed_key_verifies() {
+        let hash = hash_key("sk_secret").unwrap();
+        assert!(is_hashed(&hash));
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(!hash.contains("sk_secret"));
+        assert!(verify_key("sk_secret", &hash));
+        assert!(!verify_key("sk_other", &hash));
+    }
+
+    #[test]
+    fn hashes_are_salted() {
+        assert_ne!(
+            hash_key("sk_secret").unwrap(),
+            hash_key("sk_secret").unwrap()
+        );
+    }
+
+    #[test]
+    fn sha256_hashes_still_verify() {
+        let hash = format!("sha256$0123${}", salted_hash("0123", "sk_secret"));
+        assert!(is_hashed(&hash));
+        assert!(!is_argon2(&hash));
+        assert!(verify_key("sk_secret", &hash));
+        assert!(!verify_key("sk_other", &hash));
+    }
 
-        Ok(AuthenticatedKey {
-            permissions: api_key.permissions.clone(),
-        })
+    #[test]
+    fn plaintext_key_still_verifies() {
+        assert!(verify_key("sk_secret", "sk_secret"));
+        assert!(!verify_key("sk_secre", "sk_secret"));
     }
 }
@@ -0,0 +1,562 @@
+//! Schedules passes automatically according to [`ScheduleRule`]s: those in the configuration, and
+//! those created through the API, stored in `Rules` in the tasks path.
+//!
+//! The rules are periodically evaluated against the pass predictions, and a task is rendered for
+//! every matching pass in the same way as `POST /api/predict/schedule`. Tasks are submitted
+//! through the same pipeline as API submissions: they are placed in PendingApproval unless the
+//! rule is auto-approved, and rejected if they overlap an Active task.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+use crate::config::{Permission, ScheduleRule};
+use crate::task::format::Task;
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use super::predict::{pass_variables, to_datetime};
+use super::templates::{read_template, submit_task};
+
+const RULES_DIR: &str = "Rules";
+
+/// A pass matched by a rule.
+struct Candidate<'a> {
+    rule: &'a ScheduleRule,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    variables: HashMap<String, String>,
+}
+
+/// Evaluates the rules every `interval_minutes` until the server exits.
+pub async fn run(state: AppState) {
+    let config = &state.config.auto_schedule;
+    info!(rules = config.rules.len(), "auto-scheduler running");
+    let period = Duration::from_secs(config.interval_minutes.max(1) * 60);
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let scheduled = evaluate(&state, Utc::now()).await;
+        if scheduled.is_empty() {
+            continue;
+        }
+        info!(count = scheduled.len(), "auto-scheduler evaluated rules");
+    }
+}
+
+/// Schedules the passes starting between `now` and the configured lookahead that match a rule.
+/// Returns the IDs of the submitted tasks.
+///
+/// Passes are considered in order of rule priority (highest first), then start time, and a pass
+/// overlapping one already scheduled is skipped.
+pub(super) async fn evaluate(state: &AppState, now: DateTime<Utc>) -> Vec<String> {
+    let config = &state.config.auto_schedule;
+    let rules = rules(state).await;
+    if rules.is_empty() {
+        return Vec::new();
+    }
+    let Some(gs) = state.config.ground_station.as_ref() else {
+        warn!("no ground station configured, not scheduling passes");
+        return Vec::new();
+    };
+    let end = now + chrono::Duration::hours(config.lookahead_hours);
+
+    let mut candidates = Vec::new();
+    {
+        let predict_db = state.predict_db.lock().await;
+        for rule in &rules {
+            let end = rule.until.map_or(end, |until| end.min(until));
+            if end <= now {
+                continue;
+            }
+            let passes = predict_db.predict_passes_filtered(now, end, gs, None, |name, sat| {
+                rule.group.as_ref().is_none_or(|group| sat.in_group(group))
+                    && rule.satellite.as_ref().is_none_or(|satellite| {
+                        satellite == name || *satellite == sat.elements.norad_id.to_string()
+                    })
+            });
+            for (id, passes) in passes {
+                let Some((name, sat)) = predict_db.find(id.as_str()) else {
+                    continue;
+                };
+                for predicted in passes {
+                    let interval = predicted.pass.interval();
+                    let start = to_datetime(interval.start());
+                    let end = to_datetime(interval.end());
+                    if start <= now || predicted.max_elevation.to_degrees() < rule.min_elevation {
+                        continue;
+                    }
+                    candidates.push(Candidate {
+                        rule,
+                        start,
+                        end,
+                        variables: pass_variables(name, sat, start, end, &state.config.predict),
+                    });
+                }
+            }
+        }
+    }
+    candidates.sort_by_key(|c| (std::cmp::Reverse(c.rule.priority), c.start));
+
+    let mut scheduled: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    let mut task_ids = Vec::new();
+    for candidate in candidates {
+        let Candidate {
+            rule,
+            start,
+            end,
+            variables: pass_variables,
+        } = candidate;
+        if scheduled.iter().any(|(s, e)| start < *e && *s < end) {
+            continue;
+        }
+
+        let (template, _) = match read_template(state, &rule.template).await {
+            Ok(template) => template,
+            Err(e) => {
+                warn!(rule = %rule.name, template = %rule.template, ?e, "failed to read template");
+                continue;
+            }
+        };
+        let mut variables = pass_variables;
+        variables.extend(rule.variables.clone());
+        let mut task = match template.render(variables, &*state.predict_db.lock().await) {
+            Ok(task) => task,
+            Err(e) => {
+                warn!(rule = %rule.name, template = %rule.template, ?e, "failed to render template");
+                continue;
+            }
+        };
+        task.template = Some(rule.template.clone());
+        task.owner = rule.owner.clone();
+
+        let task_id = format!(
+            "{}.{}",
+            rule.template,
+            start.to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        match submit_task(state, rule.auto_approve, &task_id, &task).await {
+            Ok(target_dir) => {
+                info!(rule = %rule.name, %task_id, %target_dir, "task created by rule");
+                scheduled.push((start, end));
+                task_ids.push(task_id);
+            }
+            Err(ApiError::Conflict(reason)) => {
+                debug!(rule = %rule.name, %task_id, %reason, "pass not scheduled");
+                scheduled.push((start, end));
+            }
+            Err(e) => warn!(rule = %rule.name, %task_id, ?e, "failed to submit task"),
+        }
+    }
+
+    task_ids
+}
+
+/// The rules in the configuration, then those created through the API.
+async fn rules(state: &AppState) -> Vec<ScheduleRule> {
+    let mut rules = state.config.auto_schedule.rules.clone();
+    let Ok(mut entries) = tokio::fs::read_dir(state.tasks_path.join(RULES_DIR)).await else {
+        return rules;
+    };
+    let mut stored = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let rule = match tokio::fs::read_to_string(&path).await {
+            Ok(yaml) => serde_yaml::from_str::<ScheduleRule>(&yaml).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match rule {
+            Ok(rule) => stored.push(rule),
+            Err(e) => warn!(?path, %e, "failed to read rule"),
+        }
+    }
+    stored.sort_by(|a, b| a.name.cmp(&b.name));
+    rules.extend(stored);
+    rules
+}
+
+fn rule_path(state: &AppState, name: &str) -> Result<std::path::PathBuf, ApiError> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == ".." || name == "." {
+        return Err(ApiError::BadRequest("invalid rule name".to_string()));
+    }
+    Ok(state.tasks_path.join(RULES_DIR).join(Task::filename(name)))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RuleEntry {
+    #[serde(flatten)]
+    pub rule: ScheduleRule,
+    /// Whether the rule is set in the configuration, so it cannot be deleted through the API
+    pub configured: bool,
+}
+
+/// List the rules scheduling passes automatically.
+#[utoipa::path(
+    get,
+    path = "/auto_schedule/rules",
+    tag = super::AUTO_SCHEDULE_TAG,
+    responses(
+        (status = 200, description = "List of rules", body = Vec<RuleEntry>),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_rules(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+) -> Result<Json<Vec<RuleEntry>>, ApiError> {
+    auth.require(Permission::SubmitFromTemplate)?;
+
+    let configured = state.config.auto_schedule.rules.len();
+    let entries = rules(&state)
+        .await
+        .into_iter()
+        .enumerate()
+        .map(|(i, rule)| RuleEntry {
+            rule,
+            configured: i < configured,
+        })
+        .collect();
+    Ok(Json(entries))
+}
+
+/// Create a rule scheduling passes automatically.
+///
+/// Every pass matching the rule within the lookahead of the auto-scheduler is scheduled with the
+/// template, e.g. every pass of a satellite above 30° until a given time. The tasks are owned by
+/// the caller, and placed in PendingApproval unless `auto_approve` is set, which requires the
+/// AutoApproveTask permission.
+#[utoipa::path(
+    post,
+    path = "/auto_schedule/rules",
+    tag = super::AUTO_SCHEDULE_TAG,
+    request_body = ScheduleRule,
+    responses(
+        (status = 201, description = "Rule created", body = ScheduleRule),
+        (status = 400, description = "Invalid rule"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 409, description = "A rule with the same name already exists"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_rule(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Json(mut rule): Json<ScheduleRule>,
+) -> Result<(StatusCode, Json<ScheduleRule>), ApiError> {
+    auth.require(Permission::SubmitFromTemplate)?;
+    if rule.auto_approve {
+        auth.require(Permission::AutoApproveTask)?;
+    }
+
+    let path = rule_path(&state, &rule.name)?;
+    if rules(&state).await.iter().any(|r| r.name == rule.name) {
+        return Err(ApiError::Conflict(format!(
+            "rule '{}' already exists",
+            rule.name
+        )));
+    }
+    if let Err(ApiError::NotFound) = read_template(&state, &rule.template).await {
+        return Err(ApiError::BadRequest(format!(
+            "template '{}' not found",
+            rule.template
+        )));
+    }
+    rule.owner = Some(auth.owner.clone());
+
+    let yaml = serde_yaml::to_string(&rule).map_err(|_| ApiError::Internal)?;
+    let write = async {
+        tokio::fs::create_dir_all(state.tasks_path.join(RULES_DIR)).await?;
+        tokio::fs::write(&path, yaml).await
+    };
+    write.await.map_err(|e| {
+        warn!(rule = %rule.name, ?e, "failed to write rule");
+        ApiError::Internal
+    })?;
+
+    info!(rule = %rule.name, template = %rule.template, "rule created");
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+/// Delete a rule created through the API. The tasks it already scheduled are kept.
+#[utoipa::path(
+    delete,
+    path = "/auto_schedule/rules/{name}",
+    tag = super::AUTO_SCHEDULE_TAG,
+    params(
+        ("name" = String, Path, description = "Name of the rule")
+    ),
+    responses(
+        (status = 204, description = "Rule deleted"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Rule not found"),
+        (status = 409, description = "The rule is set in the configuration"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_rule(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let path = rule_path(&state, &name)?;
+    if state
+        .config
+        .auto_schedule
+        .rules
+        .iter()
+        .any(|r| r.name == name)
+    {
+        auth.require(Permission::DeleteTask)?;
+        return Err(ApiError::Conflict(format!(
+            "rule '{name}' is set in the configuration"
+        )));
+    }
+    let yaml = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|_| ApiError::NotFound)?;
+    let rule: ScheduleRule = serde_yaml::from_str(&yaml).map_err(|_| ApiError::Internal)?;
+    auth.require_on(Permission::DeleteTask, rule.owner.as_deref())?;
+
+    tokio::fs::remove_file(&path).await.map_err(|e| {
+        warn!(rule = %name, ?e, "failed to delete rule");
+        ApiError::Internal
+    })?;
+    info!(rule = %name, "rule deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use chrono::TimeZone;
+    use http_body_util::BodyExt;
+    use lox_space::{
+        analysis::visibility::ElevationMask,
+        bodies::DynOrigin,
+        core::coords::LonLatAlt,
+        prelude::{GroundLocation, GroundStation},
+    };
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, AutoScheduleConfig, Config};
+
+    const TEMPLATE_YAML: &str = "\
+steps:
+  - cmd: \"echo $satellite\"
+    wait: true
+";
+
+    fn rule(template: &str, priority: i32, auto_approve: bool) -> ScheduleRule {
+        ScheduleRule {
+            name: template.to_string(),
+            group: Some("nanoff_a".to_string()),
+            satellite: None,
+            min_elevation: 10.0,
+            template: template.to_string(),
+            priority,
+            variables: HashMap::from([("downlink".to_string(), "437.5 MHz".to_string())]),
+            auto_approve,
+            until: None,
+            owner: None,
+        }
+    }
+
+    fn setup(rules: Vec<ScheduleRule>) -> (TempDir, AppState) {
+        let tmp = tempfile::tempdir().unwrap();
+        for dir in ["Active", "PendingApproval", "Templates", "tle"] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        let tle_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle");
+        std::fs::copy(
+            tle_dir.join("nanoff_a.txt"),
+            tmp.path().join("tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        for template in ["low", "high"] {
+            std::fs::write(
+                tmp.path().join(format!("Templates/{template}.yaml")),
+                TEMPLATE_YAML,
+            )
+            .unwrap();
+        }
+
+        let coords = LonLatAlt::from_degrees(13.4, 52.52, 100.0).unwrap();
+        let location = GroundLocation::try_new(coords, DynOrigin::Earth).unwrap();
+        let config = Config {
+            ground_station: Some(GroundStation::new(
+                "GS",
+                location,
+                ElevationMask::with_fixed_elevation(0.0),
+            )),
+            auto_schedule: AutoScheduleConfig {
+                lookahead_hours: 12,
+                rules,
+                ..Default::default()
+            },
+            ..Config::for_test(
+                tmp.path(),
+                vec![ApiKey {
+                    name: None,
+                    id: None,
+                    key: "test-key".into(),
+                    permissions: vec![Permission::SubmitFromTemplate, Permission::DeleteOwnTasks],
+                }],
+            )
+        };
+        (tmp, api::state(&config))
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap()
+    }
+
+    fn task_count(tmp: &TempDir, dir: &str) -> usize {
+        std::fs::read_dir(tmp.path().join(dir)).unwrap().count()
+    }
+
+    #[tokio::test]
+    async fn schedules_matching_passes_for_approval() {
+        let (tmp, state) = setup(vec![rule("low", 0, false)]);
+
+        let task_ids = evaluate(&state, now()).await;
+        assert!(!task_ids.is_empty());
+        assert_eq!(task_count(&tmp, "PendingApproval"), task_ids.len());
+        assert_eq!(task_count(&tmp, "Active"), 0);
+
+        let (_, yaml) = Task::find(&state.tasks_path, &task_ids[0]).await.unwrap();
+        let task = Task::from_yaml_str(&yaml).unwrap();
+        assert!(task_ids[0].starts_with("low.2026-01-15T"));
+        assert_eq!(task.variables["satellite"], "NanoFF A");
+        assert_eq!(task.variables["downlink"], "437.5 MHz");
+
+        // Evaluating again schedules nothing new
+        assert!(evaluate(&state, now()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn min_elevation_filters_passes() {
+        let (_tmp, low) = setup(vec![rule("low", 0, false)]);
+        let mut high_rule = rule("low", 0, false);
+        high_rule.min_elevation = 60.0;
+        let (_tmp, high) = setup(vec![high_rule]);
+
+        assert!(evaluate(&high, now()).await.len() < evaluate(&low, now()).await.len());
+    }
+
+    #[tokio::test]
+    async fn higher_priority_rule_wins_overlapping_passes() {
+        let (tmp, state) = setup(vec![rule("low", 0, true), rule("high", 10, true)]);
+
+        let task_ids = evaluate(&state, now()).await;
+        assert!(!task_ids.is_empty());
+        assert!(task_ids.iter().all(|id| id.starts_with("high.")));
+        assert_eq!(task_count(&tmp, "Active"), task_ids.len());
+    }
+
+    #[tokio::test]
+    async fn rules_are_limited_to_a_satellite_until_a_time() {
+        let (_tmp, all) = setup(vec![rule("low", 0, false)]);
+        let mut iss = rule("low", 0, false);
+        iss.satellite = Some("25544".to_string());
+        let (_tmp, none) = setup(vec![iss]);
+        let mut until = rule("low", 0, false);
+        until.satellite = Some("58810".to_string());
+        until.until = Some(now() + chrono::Duration::hours(10));
+        let (_tmp, first) = setup(vec![until]);
+
+        let all = evaluate(&all, now()).await;
+        let first = evaluate(&first, now()).await;
+        assert!(evaluate(&none, now()).await.is_empty());
+        assert!(!first.is_empty() && first.len() < all.len());
+        assert!(first.iter().all(|id| id.as_str() < "low.2026-01-15T10"));
+    }
+
+    #[tokio::test]
+    async fn rules_are_created_listed_and_deleted() {
+        let mut configured = rule("high", 10, false);
+        configured.group = Some("weather".to_string());
+        let (tmp, state) = setup(vec![configured]);
+        let (router, _) = api::routes(state.clone()).split_for_parts();
+        let send = |request: Request<Body>| async {
+            let resp = router.clone().oneshot(request).await.unwrap();
+            let status = resp.status();
+            let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+            (status, String::from_utf8(bytes.to_vec()).unwrap())
+        };
+        let create = |body: serde_json::Value| {
+            Request::post("/api/auto_schedule/rules")
+                .header("api_key", "test-key")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let delete = |name: &str| {
+            Request::delete(format!("/api/auto_schedule/rules/{name}"))
+                .header("api_key", "test-key")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let rule = serde_json::json!({
+            "name": "nanoff",
+            "satellite": "NanoFF A",
+            "min_elevation": 10.0,
+            "template": "low",
+        });
+        let (status, body) = send(create(rule.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(body.contains(r#""owner":"key:config-0""#));
+        assert!(tmp.path().join("Rules/nanoff.yaml").exists());
+        assert_eq!(send(create(rule.clone())).await.0, StatusCode::CONFLICT);
+        let mut approved = rule.clone();
+        approved["name"] = "approved".into();
+        approved["auto_approve"] = true.into();
+        assert_eq!(send(create(approved)).await.0, StatusCode::FORBIDDEN);
+        let mut missing = rule;
+        missing["name"] = "missing".into();
+        missing["template"] = "missing".into();
+        assert_eq!(send(create(missing)).await.0, StatusCode::BAD_REQUEST);
+
+        let list = Request::get("/api/auto_schedule/rules")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(list).await;
+        assert_eq!(status, StatusCode::OK);
+        let rules: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(rules[0]["name"], "high");
+        assert_eq!(rules[0]["configured"], true);
+        assert_eq!(rules[1]["name"], "nanoff");
+        assert_eq!(rules[1]["configured"], false);
+
+        // The stored rule schedules tasks owned by its creator
+        let task_ids = evaluate(&state, now()).await;
+        let owned = task_ids.iter().find(|id| id.starts_with("low.")).unwrap();
+        let (_, yaml) = Task::find(&state.tasks_path, owned).await.unwrap();
+        assert_eq!(
+            Task::from_yaml_str(&yaml).unwrap().owner.as_deref(),
+            Some("key:config-0")
+        );
+
+        assert_eq!(send(delete("high")).await.0, StatusCode::FORBIDDEN);
+        assert_eq!(send(delete("nanoff")).await.0, StatusCode::NO_CONTENT);
+        assert_eq!(send(delete("nanoff")).await.0, StatusCode::NOT_FOUND);
+        assert!(!tmp.path().join("Rules/nanoff.yaml").exists());
+    }
+}
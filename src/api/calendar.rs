@@ -0,0 +1,361 @@
+//! Export of the station calendar in iCalendar format (RFC 5545).
+
+use axum::extract::{Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::task::index::IndexedTask;
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use super::predict::{ground_station, to_datetime};
+
+/// States of the tasks that were approved.
+const APPROVED_STATES: &[&str] = &["Active", "Completed", "Failed"];
+
+const MAX_PASS_DAYS: i64 = 14;
+
+/// Maximum length of a content line, in octets, before it is folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CalendarQuery {
+    /// API key or token, for calendar clients that cannot set headers.
+    #[allow(dead_code)] // Read when authenticating the request
+    pub token: Option<String>,
+    /// Also include the tasks pending approval, as tentative events.
+    #[serde(default)]
+    pub pending: bool,
+    /// Also include the predicted passes reaching this elevation (degrees).
+    pub pass_min_elevation: Option<f64>,
+    /// Days of predicted passes to include, at most 14. Defaults to 7.
+    pub days: Option<i64>,
+}
+
+/// Export the station calendar.
+///
+/// Returns the approved tasks (Active, Completed and Failed) as an iCalendar feed, with the tasks
+/// pending approval if `pending` is set and, if `pass_min_elevation` is given, the upcoming passes
+/// reaching that elevation. Pending tasks and passes are tentative events.
+/// Calendar clients that cannot set headers can authenticate with the `token` parameter.
+#[utoipa::path(
+    get,
+    path = "/tasks/calendar.ics",
+    tag = super::TASKS_TAG,
+    params(CalendarQuery),
+    responses(
+        (status = 200, description = "iCalendar feed", body = String, content_type = "text/calendar"),
+        (status = 400, description = "Invalid parameters"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_calendar(
+    State(state): State<AppState>,
+    Query(query): Query<CalendarQuery>,
+    auth: AuthenticatedKey,
+) -> Result<impl IntoResponse, ApiError> {
+    let view_all = auth.can_view_all()?;
+
+    let now = Utc::now();
+    let mut calendar = Calendar::new(&state.config.station_name, now);
+
+    let pending = query.pending.then_some("PendingApproval");
+    for dir in APPROVED_STATES.iter().copied().chain(pending) {
+        for IndexedTask { id, task, .. } in state.tasks.tasks(dir).await {
+            if !view_all && !auth.owns(task.owner.as_deref()) {
+                continue;
+            }
+            let Ok((start, end)) = task.time_range() else {
+                continue;
+            };
+
+            let mut description = format!("State: {dir}");
+            if let Some(owner) = &task.owner {
+                description.push_str(&format!("\nOwner: {owner}"));
+            }
+            calendar.event(Event {
+                uid: format!("task-{id}"),
+                start,
+                end,
+                summary: id,
+                description,
+                tentative: Some(dir) == pending,
+            });
+        }
+    }
+
+    if let Some(min_elevation) = query.pass_min_elevation {
+        let days = query.days.unwrap_or(7);
+        if !(1..=MAX_PASS_DAYS).contains(&days) {
+            return Err(ApiError::BadRequest(format!(
+                "days must be between 1 and {MAX_PASS_DAYS}"
+            )));
+        }
+        let gs = ground_station(&state, None)?;
+
+        let predict_db = state.predict_db.lock().await;
+        let passes = predict_db.predict_passes_filtered(
+            now,
+            now + Duration::days(days),
+            gs,
+            None,
+            |_, _| true,
+        );
+        for (id, passes) in passes {
+            let Some((name, sat)) = predict_db.find(id.as_str()) else {
+                continue;
+            };
+            for predicted in passes {
+                let max_elevation = predicted.max_elevation.to_degrees();
+                if max_elevation < min_elevation {
+                    continue;
+                }
+                let interval = predicted.pass.interval();
+                let start = to_datetime(interval.start());
+                calendar.event(Event {
+                    uid: format!(
+                        "pass-{}-{}",
+                        sat.elements.norad_id,
+                        start.format(DATE_TIME_FORMAT)
+                    ),
+                    start,
+                    end: to_datetime(interval.end()),
+                    summary: format!("{name} pass ({max_elevation:.0}°)"),
+                    description: format!(
+                        "Predicted pass of {name} (NORAD {}), maximum elevation {max_elevation:.1}°",
+                        sat.elements.norad_id
+                    ),
+                    tentative: true,
+                });
+            }
+        }
+    }
+
+    Ok((
+        [(CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        calendar.finish(),
+    ))
+}
+
+struct Event {
+    /// Unique identifier, made globally unique by [`Calendar::event`].
+    uid: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    summary: String,
+    description: String,
+    /// Whether the event is only predicted, and does not block the station.
+    tentative: bool,
+}
+
+struct Calendar {
+    content: String,
+    timestamp: DateTime<Utc>,
+}
+
+impl Calendar {
+    fn new(name: &str, timestamp: DateTime<Utc>) -> Self {
+        let mut calendar = Self {
+            content: String::new(),
+            timestamp,
+        };
+        calendar.line("BEGIN:VCALENDAR");
+        calendar.line("VERSION:2.0");
+        calendar.line("PRODID:-//sat-o-mat//Station calendar//EN");
+        calendar.line("CALSCALE:GREGORIAN");
+        calendar.line(&format!("X-WR-CALNAME:{}", escape(name)));
+        calendar
+    }
+
+    fn event(&mut self, event: Event) {
+        let (status, transparency) = if event.tentative {
+            ("TENTATIVE", "TRANSPARENT")
+        } else {
+            ("CONFIRMED", "OPAQUE")
+        };
+
+        self.line("BEGIN:VEVENT");
+        self.line(&format!("UID:{}@sat-o-mat", escape(&event.uid)));
+        self.line(&format!(
+            "DTSTAMP:{}",
+            self.timestamp.format(DATE_TIME_FORMAT)
+        ));
+        self.line(&format!("DTSTART:{}", event.start.format(DATE_TIME_FORMAT)));
+        self.line(&format!("DTEND:{}", event.end.format(DATE_TIME_FORMAT)));
+        self.line(&format!("SUMMARY:{}", escape(&event.summary)));
+        self.line(&format!("DESCRIPTION:{}", escape(&event.description)));
+        self.line(&format!("STATUS:{status}"));
+        self.line(&format!("TRANSP:{transparency}"));
+        self.line("END:VEVENT");
+    }
+
+    fn finish(mut self) -> String {
+        self.line("END:VCALENDAR");
+        self.content
+    }
+
+    /// Appends a content line, folded into lines of at most 75 octets.
+    fn line(&mut self, line: &str) {
+        let mut length = 0;
+        for c in line.chars() {
+            if length + c.len_utf8() > MAX_LINE_LENGTH {
+                self.content.push_str("\r\n ");
+                length = 1;
+            }
+            self.content.push(c);
+            length += c.len_utf8();
+        }
+        self.content.push_str("\r\n");
+    }
+}
+
+/// Escapes a text value.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    fn task_yaml(start: &str, end: &str) -> String {
+        format!(
+            "variables:\n  start: \"{start}\"\n  end: \"{end}\"\nsteps:\n  - cmd: \"true\"\n    wait: true\n"
+        )
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let mut calendar = Calendar::new("test", Utc::now());
+        let summary = "a".repeat(200);
+        calendar.line(&format!("SUMMARY:{summary}"));
+        let content = calendar.finish();
+
+        assert!(
+            content
+                .split("\r\n")
+                .all(|line| line.len() <= MAX_LINE_LENGTH)
+        );
+        let unfolded = content.replace("\r\n ", "");
+        assert!(unfolded.contains(&format!("SUMMARY:{summary}\r\n")));
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape("a, b; c\\d\ne"), "a\\, b\\; c\\\\d\\ne");
+    }
+
+    #[tokio::test]
+    async fn exports_approved_tasks() {
+        let tmp = tempfile::tempdir().unwrap();
+        for dir in ["Active", "PendingApproval", "Completed"] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        std::fs::write(
+            tmp.path().join("Active/upcoming.yaml"),
+            task_yaml("2026-06-01T10:00:00Z", "2026-06-01T10:30:00Z"),
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.path().join("Completed/done.yaml"),
+            task_yaml("2026-05-01T10:00:00Z", "2026-05-01T10:30:00Z"),
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.path().join("PendingApproval/pending.yaml"),
+            task_yaml("2026-06-02T10:00:00Z", "2026-06-02T10:30:00Z"),
+        )
+        .unwrap();
+
+        let config = Config::for_test(
+            tmp.path(),
+            vec![ApiKey {
+                name: None,
+                id: None,
+                key: "test-key".into(),
+                permissions: vec![Permission::ViewTasks],
+            }],
+        );
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+
+        let resp = router
+            .clone()
+            .oneshot(
+                Request::get("/api/tasks/calendar.ics?token=test-key")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(
+            resp.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/calendar")
+        );
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+
+        assert!(body.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(body.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(body.matches("BEGIN:VEVENT").count(), 2);
+        assert!(body.contains("UID:task-upcoming@sat-o-mat\r\n"));
+        assert!(body.contains("DTSTART:20260601T100000Z\r\n"));
+        assert!(body.contains("UID:task-done@sat-o-mat\r\n"));
+        assert!(!body.contains("pending"));
+
+        let resp = router
+            .clone()
+            .oneshot(
+                Request::get("/api/tasks/calendar.ics?token=test-key&pending=true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        assert_eq!(body.matches("BEGIN:VEVENT").count(), 3);
+        let pending = &body[body.find("UID:task-pending@sat-o-mat").unwrap()..];
+        let pending = &pending[..pending.find("END:VEVENT").unwrap()];
+        assert!(pending.contains("STATUS:TENTATIVE\r\n"));
+
+        let resp = router
+            .oneshot(
+                Request::get("/api/tasks/calendar.ics?token=wrong-key")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
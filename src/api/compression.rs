@@ -0,0 +1,84 @@
+//! Gzip and Brotli compression of large API responses, for clients sending `Accept-Encoding`.
+
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{Extensions, HeaderMap, StatusCode, Version};
+use tower_http::compression::predicate::{Predicate, SizeAbove};
+use tower_http::compression::{CompressionLayer, CompressionLevel};
+
+/// Smaller responses are not worth compressing.
+const MIN_SIZE: u64 = 1024;
+
+/// Layer compressing JSON, YAML and text responses, as they are streamed.
+pub fn layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new()
+        .quality(CompressionLevel::Fastest)
+        .compress_when(SizeAbove::new(MIN_SIZE).and(is_compressible))
+}
+
+fn is_compressible(_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            // Events are sent as they happen, not held back by the compressor
+            (v.starts_with("text/") && !v.starts_with("text/event-stream"))
+                || v.starts_with("application/json")
+                || v.starts_with("application/yaml")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn encoding(path: &str, accept_encoding: &str) -> Option<String> {
+        let json = || async { ([(CONTENT_TYPE, "application/json")], "[0]".repeat(1000)) };
+        let router = Router::new()
+            .route("/large", get(json))
+            .route("/small", get(|| async { axum::Json([0]) }))
+            .route(
+                "/events",
+                get(|| async {
+                    (
+                        [(CONTENT_TYPE, "text/event-stream")],
+                        "data: 0\n\n".repeat(200),
+                    )
+                        .into_response()
+                }),
+            )
+            .layer(layer());
+        let resp = router
+            .oneshot(
+                Request::get(path)
+                    .header(ACCEPT_ENCODING, accept_encoding)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        resp.headers()
+            .get(CONTENT_ENCODING)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn large_responses_are_compressed() {
+        assert_eq!(encoding("/large", "gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(encoding("/large", "gzip, br").await.as_deref(), Some("br"));
+        assert_eq!(
+            encoding("/large", "br;q=0, gzip").await.as_deref(),
+            Some("gzip")
+        );
+        assert_eq!(encoding("/large", "deflate").await, None);
+        assert_eq!(encoding("/small", "gzip, br").await, None);
+        assert_eq!(encoding("/events", "gzip, br").await, None);
+    }
+}
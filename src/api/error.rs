@@ -1,12 +1,16 @@
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 
+#[derive(Debug)]
 pub enum ApiError {
     Unauthorized,
     Forbidden,
     NotFound,
     BadRequest(String),
     Conflict(String),
+    TooManyRequests,
+    /// Hardware or a server the station relies on failed.
+    BadGateway(String),
     Internal,
 }
 
@@ -22,6 +26,10 @@ impl IntoResponse for ApiError {
             ApiError::NotFound => (StatusCode::NOT_FOUND, "not found").into_response(),
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
             ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg).into_response(),
+            ApiError::TooManyRequests => {
+                (StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded").into_response()
+            }
+            ApiError::BadGateway(msg) => (StatusCode::BAD_GATEWAY, msg).into_response(),
             ApiError::Internal => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "internal error").into_response()
             }
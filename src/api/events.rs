@@ -0,0 +1,141 @@
+use std::convert::Infallible;
+
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::Stream;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+use crate::notify::StationEvent;
+use crate::task::format::Task;
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+
+/// Stream the events of the station.
+///
+/// Sends an event as tasks are submitted, approved or rejected, their runs start and finish, and
+/// the steps of the runs start and finish, named as the `event` in its data. Callers with only the
+/// ViewOwnTasks permission receive the events of their own tasks.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = super::TASKS_TAG,
+    responses(
+        (status = 200, description = "Stream of events", body = StationEvent, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn stream_events(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    // Anonymous callers get ViewTasks, so the others are filtered by owner
+    let view_all = auth.can_view_all()?;
+    let caller = auth.owner;
+    let events = state.notifier.subscribe();
+
+    let stream = futures_util::stream::unfold(events, move |mut events| {
+        let (state, caller) = (state.clone(), caller.clone());
+        async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(skipped, "event stream lagging behind, events skipped");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                };
+                if !view_all && owner(&state, &event.task_id).await.as_deref() != Some(&caller) {
+                    continue;
+                }
+                let Ok(sse) = Event::default().event(&event.event).json_data(&event) else {
+                    continue;
+                };
+                return Some((Ok(sse), events));
+            }
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Who submitted task `id`, if it still exists.
+async fn owner(state: &AppState, id: &str) -> Option<String> {
+    Task::find(&state.tasks_path, id)
+        .await
+        .and_then(|(_, content)| super::tasks::task_owner(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::api;
+    use crate::config::{ApiKey, Config, NotificationEvent, Permission};
+
+    fn config(tmp: &tempfile::TempDir, permissions: Vec<Permission>) -> Config {
+        let mut config = Config {
+            tasks_path: tmp.path().to_path_buf(),
+            tle_path: tmp.path().join("tle"),
+            ..Default::default()
+        };
+        config.api.keys = vec![ApiKey {
+            name: None,
+            id: None,
+            key: "test-key".into(),
+            permissions,
+        }];
+        config
+    }
+
+    #[tokio::test]
+    async fn events_are_streamed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = api::state(&config(&tmp, vec![Permission::ViewTasks]));
+        let notifier = state.notifier.clone();
+        let (router, _) = api::routes(state).split_for_parts();
+
+        let req = Request::get("/api/v1/events")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["content-type"], "text/event-stream");
+
+        notifier.notify(NotificationEvent::TaskSubmitted, "pass.1");
+        let mut body = resp.into_body();
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+        assert!(text.starts_with("event: task_submitted\ndata: {"));
+        let data = text.lines().nth(1).unwrap().strip_prefix("data: ").unwrap();
+        let event: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(event["task_id"], "pass.1");
+        assert!(event.get("step").is_none());
+    }
+
+    #[tokio::test]
+    async fn events_need_a_view_permission() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = api::state(&config(&tmp, vec![Permission::SubmitTask]));
+        let (router, _) = api::routes(state).split_for_parts();
+
+        let req = Request::get("/api/v1/events")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
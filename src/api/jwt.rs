@@ -0,0 +1,479 @@
+//! Validation of RS256 JSON Web Tokens issued by an OpenID Connect provider.
+
+use std::path::Path;
+
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::signature::Verifier;
+use rsa::{BigUint, RsaPublicKey};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::config::{JwtConfig, Permission};
+
+use super::auth::AuthenticatedKey;
+
+/// Tolerated clock difference with the issuer, in seconds.
+const LEEWAY_SECONDS: i64 = 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    #[error("failed to read JWKS: {0}")]
+    Jwks(String),
+    #[error("malformed token")]
+    Malformed,
+    #[error("unsupported algorithm {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("no matching signing key")]
+    UnknownKey,
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("token expired")]
+    Expired,
+    #[error("token not yet valid")]
+    NotYetValid,
+    #[error("invalid issuer")]
+    InvalidIssuer,
+    #[error("invalid audience")]
+    InvalidAudience,
+}
+
+/// An RSA public key from the provider's JWKS, verifying RSASSA-PKCS1-v1_5 signatures with
+/// SHA-256.
+struct RsaKey {
+    kid: Option<String>,
+    key: VerifyingKey<Sha256>,
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    #[serde(rename = "use")]
+    usage: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+pub struct JwtValidator {
+    config: JwtConfig,
+    keys: Vec<RsaKey>,
+}
+
+impl JwtValidator {
+    /// Creates a validator with the signing keys in the configured JWKS file.
+    pub fn load(config: &JwtConfig) -> Result<Self, JwtError> {
+        let keys = load_jwks(&config.jwks_path)?;
+        if keys.is_empty() {
+            return Err(JwtError::Jwks("no RSA signing keys".to_string()));
+        }
+        Ok(Self {
+            config: config.clone(),
+            keys,
+        })
+    }
+
+    /// Validates `token` at `now` (seconds since the Unix epoch) and returns its issuer and
+    /// subject with the permissions granted by its claims.
+    pub fn validate(&self, token: &str, now: i64) -> Result<AuthenticatedKey, JwtError> {
+        let (signed, signature) = token.rsplit_once('.').ok_or(JwtError::Malformed)?;
+        let (header, payload) = signed.split_once('.').ok_or(JwtError::Malformed)?;
+
+        let header: Header = decode_json(header)?;
+        if header.alg != "RS256" {
+            return Err(JwtError::UnsupportedAlgorithm(header.alg));
+        }
+        let key = match &header.kid {
+            Some(kid) => self.keys.iter().find(|k| k.kid.as_ref() == Some(kid)),
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        }
+        .ok_or(JwtError::UnknownKey)?;
+
+        let signature = base64url_decode(signature).ok_or(JwtError::Malformed)?;
+        let signature =
+            Signature::try_from(signature.as_slice()).map_err(|_| JwtError::InvalidSignature)?;
+        key.key
+            .verify(signed.as_bytes(), &signature)
+            .map_err(|_| JwtError::InvalidSignature)?;
+
+        let claims: Value = decode_json(payload)?;
+        self.check_claims(&claims, now)?;
+        let subject = claims["sub"]
+            .as_str()
+            .filter(|subject| !subject.is_empty())
+            .ok_or(JwtError::Malformed)?;
+        Ok(AuthenticatedKey {
+            owner: format!("jwt:{}:{subject}", self.config.issuer),
+            permissions: self.permissions(&claims),
+        })
+    }
+
+    fn check_claims(&self, claims: &Value, now: i64) -> Result<(), JwtError> {
+        match claims["exp"].as_i64() {
+            Some(exp) if now <= exp + LEEWAY_SECONDS => {}
+            _ => return Err(JwtError::Expired),
+        }
+        if claims["nbf"]
+            .as_i64()
+            .is_some_and(|nbf| now + LEEWAY_SECONDS < nbf)
+        {
+            return Err(JwtError::NotYetValid);
+        }
+        if claims["iss"].as_str() != Some(self.config.issuer.as_str()) {
+            return Err(JwtError::InvalidIssuer);
+        }
+        let audience = &self.config.audience;
+        let valid_audience = match &claims["aud"] {
+            Value::String(aud) => aud == audience,
+            Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+            _ => false,
+        };
+        if !valid_audience {
+            return Err(JwtError::InvalidAudience);
+        }
+        Ok(())
+    }
+
+    /// Returns the union of the permissions mapped to the values of the configured claim.
+    fn permissions(&self, claims: &Value) -> Vec<Permission> {
+        let values: Vec<&str> = match &claims[self.config.claim.as_str()] {
+            Value::String(value) => vec![value.as_str()],
+            Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut permissions = Vec::new();
+        for value in values {
+            for permission in self.config.permissions.get(value).into_iter().flatten() {
+                if !permissions.contains(permission) {
+                    permissions.push(permission.clone());
+                }
+            }
+        }
+        permissions
+    }
+}
+
+fn load_jwks(path: &Path) -> Result<Vec<RsaKey>, JwtError> {
+    let content = std::fs::read_to_string(path).map_err(|e| JwtError::Jwks(e.to_string()))?;
+    let jwks: Jwks = serde_json::from_str(&content).map_err(|e| JwtError::Jwks(e.to_string()))?;
+
+    Ok(jwks
+        .keys
+        .into_iter()
+        .filter(|k| k.kty == "RSA" && k.usage.as_deref().is_none_or(|u| u == "sig"))
+        .filter_map(|k| {
+            let n = base64url_decode(k.n.as_deref()?)?;
+            let e = base64url_decode(k.e.as_deref()?)?;
+            let key =
+                RsaPublicKey::new(BigUint::from_bytes_be(&n), BigUint::from_bytes_be(&e)).ok()?;
+            Some(RsaKey {
+                kid: k.kid,
+                key: VerifyingKey::new(key),
+            })
+        })
+        .collect())
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(part: &str) -> Result<T, JwtError> {
+    let bytes = base64url_decode(part).ok_or(JwtError::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| JwtError::Malformed)
+}
+
+/// Decodes unpadded base64url (RFC 4648, 5).
+fn base64url_decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in input.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rsa::RsaPrivateKey;
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::signature::{SignatureEncoding, Signer};
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::error::ApiError;
+
+    const N: &str = "eb4102c68e88922adfe14956d17e97ae6f87dd3b5cb27ad2380eea607481b9bf6498265ab3d6a5cccde1f75ba081fa11bf2cbd44e0f6b2e2cd58016d003828e3fcb5498595124d43e4b229ef0a90a09cdb41ca5059f13602db6e68b6d4b784611b7bafdb42be9b2d1c758ef7c0d8129d940cf52f9b66e41721539ec87e2b4ccb";
+    const D: &str = "5bbd856c92e6d5415cff08cf8f70b38bf5b860e0a65c7fa2fcf36c18e336378e589730d5cc2dd7884f7d602dde20f1b8fbf96171bbdcd893b38a2f3c278abefeb1f2f17f7d2a514063f4c9fb7319bb4a09effb601c078614e58f46c443f13c4f30995bf6d89fc8fcb8d6fd7b57566e5fbddb9b33b7010e4dc606abda8cc57231";
+    /// Another key, not in the JWKS.
+    const OTHER_N: &str = "a36e2fbbf698668decd2fe40bc01a2b5001b2f97a4ab5e438e8afa8c15618dac418cd8f23a966863ae510c8930a2226289317e785bd4e855e085a2a010cf5ad9e82043b3a272bb9cf37c164b2373c5d828885bf7c981b78f8e1d1a12e37f58d8c536fb8f67944ff5c3d29c5fd0267d381bfe6608964070e96c35d98831592f97";
+    const OTHER_D: &str = "9083fef1d73d2c7293d87fbbfe83f3bcda3b2ffa884281387bf3a959f60e2d9904dde7794b157579afcb3baa94b6ca7117a00c95a19ec3542d15fe984c450faff7330e0b3f7cc309289a49b2d25d08058c20b6f3b445d8ed5d4bf00d7506004a10ad578c45c0ca048ca5566379828b8dba08afb862644ccdb456ebe1f525b71";
+
+    const NOW: i64 = 1_800_000_000;
+
+    fn base64url_encode(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let b = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+            for i in 0..=chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        out
+    }
+
+    fn big(hex: &str) -> BigUint {
+        BigUint::parse_bytes(hex.as_bytes(), 16).unwrap()
+    }
+
+    /// Signs `claims` with the test key.
+    fn sign(claims: &Value) -> String {
+        sign_with(claims, N, D)
+    }
+
+    /// Signs `claims` with the key of modulus `n` and private exponent `d`.
+    fn sign_with(claims: &Value, n: &str, d: &str) -> String {
+        let header = json!({"alg": "RS256", "typ": "JWT", "kid": "test"});
+        let signed = format!(
+            "{}.{}",
+            base64url_encode(header.to_string().as_bytes()),
+            base64url_encode(claims.to_string().as_bytes())
+        );
+
+        let key = RsaPrivateKey::from_components(big(n), big("10001"), big(d), vec![]).unwrap();
+        let signature = SigningKey::<Sha256>::new(key).sign(signed.as_bytes());
+        format!("{signed}.{}", base64url_encode(&signature.to_bytes()))
+    }
+
+    /// Writes the JWKS of the test key and returns a config using it.
+    fn config(dir: &Path) -> JwtConfig {
+        let jwks = json!({"keys": [{
+            "kty": "RSA",
+            "kid": "test",
+            "use": "sig",
+            "alg": "RS256",
+            "n": base64url_encode(&big(N).to_bytes_be()),
+            "e": "AQAB",
+        }]});
+        let jwks_path = dir.join("jwks.json");
+        std::fs::write(&jwks_path, jwks.to_string()).unwrap();
+
+        JwtConfig {
+            issuer: "https://sso.example.edu".to_string(),
+            audience: "sat-o-mat".to_string(),
+            jwks_path,
+            claim: "groups".to_string(),
+            permissions: HashMap::from([
+                ("operators".to_string(), vec![Permission::ViewTasks]),
+                (
+                    "admins".to_string(),
+                    vec![Permission::ViewTasks, Permission::EditTask],
+                ),
+            ]),
+        }
+    }
+
+    fn claims() -> Value {
+        json!({
+            "iss": "https://sso.example.edu",
+            "aud": ["sat-o-mat", "other"],
+            "sub": "alice",
+            "exp": NOW + 3600,
+            "groups": ["operators", "admins", "students"],
+        })
+    }
+
+    fn validator() -> (tempfile::TempDir, JwtValidator) {
+        let tmp = tempfile::tempdir().unwrap();
+        let validator = JwtValidator::load(&config(tmp.path())).unwrap();
+        (tmp, validator)
+    }
+
+    #[test]
+    fn base64url_roundtrip() {
+        for input in [&b""[..], b"f", b"fo", b"foo", b"foob", b"\xff\xfe\x00"] {
+            assert_eq!(base64url_decode(&base64url_encode(input)).unwrap(), input);
+        }
+        assert_eq!(base64url_decode("AQAB").unwrap(), [1, 0, 1]);
+    }
+
+    #[test]
+    fn valid_token_maps_claims_to_permissions() {
+        let (_tmp, validator) = validator();
+        let user = validator.validate(&sign(&claims()), NOW).unwrap();
+        assert_eq!(user.owner, "jwt:https://sso.example.edu:alice");
+        assert_eq!(
+            user.permissions,
+            vec![Permission::ViewTasks, Permission::EditTask]
+        );
+    }
+
+    #[test]
+    fn subjects_do_not_pass_for_keys() {
+        let (_tmp, validator) = validator();
+        // A config key named `alice` owns the task
+        let mut user = validator.validate(&sign(&claims()), NOW).unwrap();
+        user.permissions = vec![Permission::EditOwnTasks];
+        assert!(!user.owns(Some("key:alice")));
+        assert!(matches!(
+            user.require_on(Permission::EditTask, Some("key:alice")),
+            Err(ApiError::Forbidden)
+        ));
+        assert!(user.owns(Some("jwt:https://sso.example.edu:alice")));
+
+        let mut anonymous = claims();
+        anonymous["sub"] = json!("");
+        assert!(matches!(
+            validator.validate(&sign(&anonymous), NOW),
+            Err(JwtError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_claims() {
+        let (_tmp, validator) = validator();
+
+        let mut wrong_audience = claims();
+        wrong_audience["aud"] = json!("other");
+        assert!(matches!(
+            validator.validate(&sign(&wrong_audience), NOW),
+            Err(JwtError::InvalidAudience)
+        ));
+
+        let mut wrong_issuer = claims();
+        wrong_issuer["iss"] = json!("https://evil.example.com");
+        assert!(matches!(
+            validator.validate(&sign(&wrong_issuer), NOW),
+            Err(JwtError::InvalidIssuer)
+        ));
+
+        assert!(matches!(
+            validator.validate(&sign(&claims()), NOW + 3600 + 2 * LEEWAY_SECONDS),
+            Err(JwtError::Expired)
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_token() {
+        let (_tmp, validator) = validator();
+        let token = sign(&claims());
+
+        let mut elevated = claims();
+        elevated["groups"] = json!(["admins"]);
+        let forged_payload = sign(&elevated).split('.').nth(1).unwrap().to_string();
+        let mut parts: Vec<&str> = token.split('.').collect();
+        parts[1] = &forged_payload;
+        assert!(matches!(
+            validator.validate(&parts.join("."), NOW),
+            Err(JwtError::InvalidSignature)
+        ));
+
+        let unsigned = format!("{}.{}.", base64url_encode(br#"{"alg":"none"}"#), parts[1]);
+        assert!(matches!(
+            validator.validate(&unsigned, NOW),
+            Err(JwtError::UnsupportedAlgorithm(_))
+        ));
+        // Nor with the signature of the original token
+        let unsigned = format!(
+            "{}.{}.{}",
+            base64url_encode(br#"{"alg":"none","kid":"test"}"#),
+            parts[1],
+            parts[2]
+        );
+        assert!(matches!(
+            validator.validate(&unsigned, NOW),
+            Err(JwtError::UnsupportedAlgorithm(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_tokens_signed_with_another_key() {
+        let (_tmp, validator) = validator();
+        assert!(matches!(
+            validator.validate(&sign_with(&claims(), OTHER_N, OTHER_D), NOW),
+            Err(JwtError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_signatures() {
+        let (_tmp, validator) = validator();
+        let token = sign(&claims());
+        let (signed, signature) = token.rsplit_once('.').unwrap();
+        let signature = base64url_decode(signature).unwrap();
+        for truncated in [&signature[..signature.len() - 1], &signature[1..], &[]] {
+            let token = format!("{signed}.{}", base64url_encode(truncated));
+            assert!(matches!(
+                validator.validate(&token, NOW),
+                Err(JwtError::InvalidSignature)
+            ));
+        }
+    }
+
+    #[test]
+    fn checks_the_validity_period() {
+        let (_tmp, validator) = validator();
+        let exp = NOW + 3600;
+        // The clocks may differ by the leeway
+        assert!(
+            validator
+                .validate(&sign(&claims()), exp + LEEWAY_SECONDS)
+                .is_ok()
+        );
+        assert!(matches!(
+            validator.validate(&sign(&claims()), exp + LEEWAY_SECONDS + 1),
+            Err(JwtError::Expired)
+        ));
+
+        let mut no_expiry = claims();
+        no_expiry.as_object_mut().unwrap().remove("exp");
+        assert!(matches!(
+            validator.validate(&sign(&no_expiry), NOW),
+            Err(JwtError::Expired)
+        ));
+
+        let mut not_yet_valid = claims();
+        not_yet_valid["nbf"] = json!(NOW + 600);
+        let token = sign(&not_yet_valid);
+        assert!(matches!(
+            validator.validate(&token, NOW),
+            Err(JwtError::NotYetValid)
+        ));
+        assert!(
+            validator
+                .validate(&token, NOW + 600 - LEEWAY_SECONDS)
+                .is_ok()
+        );
+    }
+}
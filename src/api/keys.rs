@@ -0,0 +1,533 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use axum::Json;
+use axum::extract::{Path as AxumPath, State};
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::config::{Config, Permission};
+
+use super::AppState;
+use super::auth::{AuthenticatedKey, generate_key, hash_key, is_argon2, key_id, to_hex};
+use super::error::ApiError;
+
+const DEFAULT_KEYS_FILE: &str = "api_keys.yaml";
+
+/// The last use of the keys is recorded to the minute, so that the store is not written on
+/// every request.
+const LAST_USED_RESOLUTION: Duration = Duration::minutes(1);
+
+/// API keys created at runtime through the API, persisted to a file separate from the config.
+///
+/// Only salted hashes of the keys are stored.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct KeyStore {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    keys: Vec<StoredKey>,
+    /// Digests of the keys that matched a hash and of the hash, as verifying Argon2 hashes is
+    /// slow on purpose.
+    #[serde(skip)]
+    verified: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StoredKey {
+    pub id: String,
+    pub name: String,
+    /// Salted hash of the key, see [`hash_key`].
+    pub key_hash: String,
+    pub permissions: Vec<Permission>,
+    pub created: DateTime<Utc>,
+    /// The key is rejected from then on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl StoredKey {
+    fn expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+impl KeyStore {
+    /// Loads the key store from the configured path. A missing file is an empty store.
+    pub fn load(config: &Config) -> Self {
+        let path = config
+            .api
+            .keys_path
+            .clone()
+            .unwrap_or_else(|| config.tasks_path.join(DEFAULT_KEYS_FILE));
+
+        let keys = match std::fs::read_to_string(&path) {
+            Ok(content) => match serde_yaml::from_str::<KeyStore>(&content) {
+                Ok(store) => store.keys,
+                Err(e) => {
+                    warn!(?path, ?e, "invalid API key store, ignoring it");
+                    Vec::new()
+                }
+            },
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            path,
+            keys,
+            verified: HashSet::new(),
+        }
+    }
+
+    /// Whether `key` matched the hash `stored` before.
+    pub fn verified(&self, key: &str, stored: &str) -> bool {
+        self.verified.contains(&verified_digest(key, stored))
+    }
+
+    /// Remembers that `key` matched the hash `stored`.
+    pub fn remember(&mut self, key: &str, stored: &str) {
+        self.verified.insert(verified_digest(key, stored));
+    }
+
+    /// The hashes and IDs of the entries to check a key with the ID `key_id` against. Keys with an
+    /// ID are only checked against the entry of their ID, and only keys with one have slow hashes.
+    pub fn candidates(&self, key_id: Option<&str>) -> Vec<(String, String)> {
+        self.keys
+            .iter()
+            .filter(|stored| match key_id {
+                Some(id) => stored.id == id,
+                None => !is_argon2(&stored.key_hash),
+            })
+            .map(|stored| (stored.key_hash.clone(), stored.id.clone()))
+            .collect()
+    }
+
+    /// Returns the entry `id` if it is still in the store and has not expired, recording that it
+    /// was used at `now`.
+    pub async fn record_use(&mut self, id: &str, now: DateTime<Utc>) -> Option<StoredKey> {
+        let index = self.keys.iter().position(|stored| stored.id == id)?;
+        if self.keys[index].expired(now) {
+            return None;
+        }
+
+        let used = now.duration_trunc(LAST_USED_RESOLUTION).unwrap_or(now);
+        if self.keys[index].last_used.is_none_or(|last| last < used) {
+            self.keys[index].last_used = Some(used);
+            // The key is still valid if this fails, and saving is tried again at the next use
+            let _ = self.save().await;
+        }
+        Some(self.keys[index].clone())
+    }
+
+    async fn save(&self) -> Result<(), ApiError> {
+        let yaml = serde_yaml::to_string(self).map_err(|_| ApiError::Internal)?;
+        write_private(&self.path, &yaml).await.map_err(|e| {
+            warn!(path = ?self.path, ?e, "failed to write API key store");
+            ApiError::Internal
+        })
+    }
+}
+
+/// Digest of `key` and the hash `stored` it matched, as verifying Argon2 hashes is slow on
+/// purpose.
+fn verified_digest(key: &str, stored: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(stored.as_bytes());
+    hasher.update([0]);
+    hasher.update(key.as_bytes());
+    to_hex(&hasher.finalize())
+}
+
+/// Writes `content` to `path`, readable only by the owner.
+async fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    tokio::fs::write(path, content).await?;
+    tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyEntry {
+    /// Identifier used to revoke the key
+    pub id: String,
+    pub name: String,
+    pub permissions: Vec<Permission>,
+    /// Creation time formatted as RFC3339
+    pub created: String,
+    /// Expiry time formatted as RFC3339, if the key expires
+    pub expires: Option<String>,
+    /// Time the key was last used, to the minute, formatted as RFC3339
+    pub last_used: Option<String>,
+}
+
+impl From<&StoredKey> for ApiKeyEntry {
+    fn from(key: &StoredKey) -> Self {
+        Self {
+            id: key.id.clone(),
+            name: key.name.clone(),
+            permissions: key.permissions.clone(),
+            created: key.created.to_rfc3339(),
+            expires: key.expires.map(|t| t.to_rfc3339()),
+            last_used: key.last_used.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({
+    "name": "Ground segment dashboard",
+    "permissions": ["view_tasks"],
+    "expires": "2027-01-01T00:00:00Z"
+}))]
+pub struct CreateKeyRequest {
+    /// Description of the key, e.g. who it was given to
+    pub name: String,
+    pub permissions: Vec<Permission>,
+    /// Time the key is rejected from, as RFC3339. It never expires if unset.
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub expires: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedKey {
+    #[serde(flatten)]
+    pub entry: ApiKeyEntry,
+    /// The key itself. It is only returned once, when the key is created.
+    pub key: String,
+}
+
+/// List the API keys created through the API.
+///
+/// Keys defined in the config file are not listed. The keys themselves are never returned.
+#[utoipa::path(
+    get,
+    path = "/keys",
+    tag = super::KEYS_TAG,
+    responses(
+        (status = 200, description = "List of API keys", body = Vec<ApiKeyEntry>),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_keys(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+) -> Result<Json<Vec<ApiKeyEntry>>, ApiError> {
+    auth.require(Permission::ManageKeys)?;
+
+    let store = state.keys.lock().await;
+    Ok(Json(store.keys.iter().map(ApiKeyEntry::from).collect()))
+}
+
+/// Create an API key.
+///
+/// Returns the new key. It cannot be retrieved again later. Keys with an `expires` time are
+/// rejected from then on.
+#[utoipa::path(
+    post,
+    path = "/keys",
+    tag = super::KEYS_TAG,
+    request_body = CreateKeyRequest,
+    responses(
+        (status = 201, description = "API key created", body = CreatedKey),
+        (status = 400, description = "Expiry time in the past"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_key(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Json(req): Json<CreateKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedKey>), ApiError> {
+    auth.require(Permission::ManageKeys)?;
+    let now = Utc::now();
+    if req.expires.is_some_and(|expires| expires <= now) {
+        return Err(ApiError::BadRequest("expires is in the past".into()));
+    }
+
+    let generated = generate_key().and_then(|key| {
+        // Starting with its ID, so that it is looked up without checking every hash
+        let id = key_id(&key).expect("generated keys have an ID").to_string();
+        let key_hash = hash_key(&key)?;
+        Ok((id, key, key_hash))
+    });
+    let (id, key, key_hash) = generated.map_err(|e| {
+        warn!(?e, "failed to generate API key");
+        ApiError::Internal
+    })?;
+    let stored = StoredKey {
+        id,
+        name: req.name,
+        key_hash,
+        permissions: req.permissions,
+        created: now,
+        expires: req.expires,
+        last_used: None,
+    };
+
+    let mut store = state.keys.lock().await;
+    store.keys.push(stored.clone());
+    if let Err(e) = store.save().await {
+        store.keys.pop();
+        return Err(e);
+    }
+
+    info!(id = %stored.id, name = %stored.name, "API key created");
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedKey {
+            entry: ApiKeyEntry::from(&stored),
+            key,
+        }),
+    ))
+}
+
+/// Revoke an API key created through the API.
+#[utoipa::path(
+    delete,
+    path = "/keys/{id}",
+    tag = super::KEYS_TAG,
+    params(
+        ("id" = String, Path, description = "Key identifier")
+    ),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "API key not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn revoke_key(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, ApiError> {
+    auth.require(Permission::ManageKeys)?;
+
+    let mut store = state.keys.lock().await;
+    let index = store
+        .keys
+        .iter()
+        .position(|k| k.id == id)
+        .ok_or(ApiError::NotFound)?;
+    let revoked = store.keys.remove(index);
+    if let Err(e) = store.save().await {
+        store.keys.insert(index, revoked);
+        return Err(e);
+    }
+
+    info!(%id, name = %revoked.name, "API key revoked");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    fn test_config(tmp: &TempDir, permissions: Vec<Permission>) -> Config {
+        Config::for_test(
+            tmp.path(),
+            vec![ApiKey {
+                name: None,
+                id: None,
+                key: "test-key".into(),
+                permissions,
+            }],
+        )
+    }
+
+    fn router(config: &Config) -> axum::Router {
+        api::routes(api::state(config)).split_for_parts().0
+    }
+
+    async fn request(
+        router: axum::Router,
+        method: &str,
+        uri: &str,
+        key: &str,
+        body: &str,
+    ) -> (StatusCode, String) {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("api_key", key)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        let status = resp.status();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    const CREATE_BODY: &str = r#"{"name": "alice", "permissions": ["ViewTasks"]}"#;
+
+    #[tokio::test]
+    async fn created_key_authenticates_and_persists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = test_config(&tmp, vec![Permission::ManageKeys]);
+
+        let (status, body) = request(
+            router(&config),
+            "POST",
+            "/api/keys",
+            "test-key",
+            CREATE_BODY,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let created: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let key = created["key"].as_str().unwrap();
+        assert!(key.starts_with("sk_"));
+        assert_eq!(created["name"], "alice");
+
+        let stored = std::fs::read_to_string(tmp.path().join("api_keys.yaml")).unwrap();
+        assert!(!stored.contains(key));
+
+        // The new key works, also after a restart, with its own permissions
+        let (status, _) = request(router(&config), "GET", "/api/tasks", key, "").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = request(router(&config), "GET", "/api/keys", key, "").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = request(router(&config), "GET", "/api/keys", "test-key", "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.contains(key));
+        let keys: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(keys.as_array().unwrap().len(), 1);
+        assert_eq!(keys[0]["id"], created["id"]);
+    }
+
+    #[tokio::test]
+    async fn revoked_key_no_longer_authenticates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = test_config(&tmp, vec![Permission::ManageKeys]);
+        let router = router(&config);
+
+        let (_, body) = request(router.clone(), "POST", "/api/keys", "test-key", CREATE_BODY).await;
+        let created: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let key = created["key"].as_str().unwrap();
+        let uri = format!("/api/keys/{}", created["id"].as_str().unwrap());
+
+        let (status, _) = request(router.clone(), "DELETE", &uri, "test-key", "").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = request(router.clone(), "GET", "/api/tasks", key, "").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = request(router, "DELETE", &uri, "test-key", "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn keys_expire_and_record_their_last_use() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = test_config(&tmp, vec![Permission::ManageKeys]);
+        let router = router(&config);
+
+        let body =
+            r#"{"name": "alice", "permissions": ["ViewTasks"], "expires": "2000-01-01T00:00:00Z"}"#;
+        let (status, _) = request(router.clone(), "POST", "/api/keys", "test-key", body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let body =
+            r#"{"name": "alice", "permissions": ["ViewTasks"], "expires": "2100-01-01T00:00:00Z"}"#;
+        let (status, body) = request(router.clone(), "POST", "/api/keys", "test-key", body).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let created: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let key = created["key"].as_str().unwrap();
+        let id = created["id"].as_str().unwrap();
+        assert!(key.starts_with(&format!("sk_{id}_")));
+        assert_eq!(created["expires"], "2100-01-01T00:00:00+00:00");
+        assert_eq!(created["last_used"], serde_json::Value::Null);
+
+        let (status, _) = request(router.clone(), "GET", "/api/tasks", key, "").await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = request(router.clone(), "GET", "/api/keys", "test-key", "").await;
+        let keys: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(keys[0]["last_used"].is_string());
+        let stored = std::fs::read_to_string(tmp.path().join("api_keys.yaml")).unwrap();
+        assert!(stored.contains("last_used"));
+
+        // Once expired, the key is rejected
+        let stored = stored.replace("2100-01-01", "2001-01-01");
+        std::fs::write(tmp.path().join("api_keys.yaml"), stored).unwrap();
+        let (status, _) = request(
+            api::routes(api::state(&config)).split_for_parts().0,
+            "GET",
+            "/api/tasks",
+            key,
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn hashed_config_key_authenticates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = test_config(&tmp, vec![Permission::ManageKeys]);
+        config.api.keys[0].key = api::auth::hash_key("test-key").unwrap();
+
+        let (status, _) = request(router(&config), "GET", "/api/keys", "test-key", "").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = request(router(&config), "GET", "/api/keys", "wrong-key", "").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // A key with an ID is only checked against the entry with that ID
+        let key = api::auth::generate_key().unwrap();
+        config.api.keys[0].key = api::auth::hash_key(&key).unwrap();
+        config.api.keys[0].id = Some("0badf00d".into());
+        let (status, _) = request(router(&config), "GET", "/api/keys", &key, "").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        config.api.keys[0].id = api::auth::key_id(&key).map(str::to_string);
+        let (status, _) = request(router(&config), "GET", "/api/keys", &key, "").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn failed_logins_are_limited_before_checking_keys() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = test_config(&tmp, vec![Permission::ManageKeys]);
+        config.api.failed_logins = 2;
+        let router = router(&config);
+
+        for _ in 0..2 {
+            let (status, _) = request(router.clone(), "GET", "/api/keys", "wrong-key", "").await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        let (status, _) = request(router, "GET", "/api/keys", "test-key", "").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn managing_keys_requires_permission() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = test_config(&tmp, vec![Permission::ViewTasks]);
+        let (status, _) = request(
+            router(&config),
+            "POST",
+            "/api/keys",
+            "test-key",
+            CREATE_BODY,
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,368 @@
+//! Metrics of the stations in the Prometheus text format, served at `/metrics` for dashboards and
+//! alerts.
+//!
+//! The trackers run as steps of the runs, in their own process. Their latest position is read from
+//! the [`STATUS_FILE`] they keep in the artifacts of the run.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Router, body::Body};
+use sat_o_mat::task::runner::RunStatus;
+
+use super::AppState;
+use crate::config::NotificationEvent;
+use crate::notify::event_name;
+use crate::track::{STATUS_FILE, TrackerStatus};
+
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Upper bounds (seconds) of the buckets of the request latency histogram.
+const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0];
+
+/// Events counted, exported from the start even if they have not happened yet.
+const EVENTS: [NotificationEvent; 8] = [
+    NotificationEvent::TaskSubmitted,
+    NotificationEvent::ApprovalNeeded,
+    NotificationEvent::TaskApproved,
+    NotificationEvent::TaskRejected,
+    NotificationEvent::RunStarted,
+    NotificationEvent::RunCompleted,
+    NotificationEvent::RunFailed,
+    NotificationEvent::RunAborted,
+];
+
+/// Name, help and value of the gauges of each tracker.
+type TrackerGauge = (&'static str, &'static str, fn(&TrackerStatus) -> f64);
+
+const TRACKER_GAUGES: [TrackerGauge; 4] = [
+    (
+        "satomat_tracker_azimuth_degrees",
+        "Azimuth of the tracked object.",
+        |t| t.azimuth,
+    ),
+    (
+        "satomat_tracker_elevation_degrees",
+        "Elevation of the tracked object.",
+        |t| t.elevation,
+    ),
+    (
+        "satomat_tracker_range_rate_meters_per_second",
+        "Range rate of the tracked object.",
+        |t| t.range_rate,
+    ),
+    (
+        "satomat_tracker_update_timestamp_seconds",
+        "Time of the last tracker update.",
+        |t| t.time.timestamp_millis() as f64 / 1000.0,
+    ),
+];
+
+/// Latency of the API requests.
+#[derive(Debug, Default)]
+pub struct RequestLatency {
+    /// Requests in each of the [`LATENCY_BUCKETS`] and not in the previous one
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl RequestLatency {
+    pub fn observe(&self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&le| seconds <= le) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// What is exported of a station.
+struct StationMetrics {
+    name: String,
+    events: HashMap<NotificationEvent, u64>,
+    /// Status of the runs in progress, by task ID
+    runs: Vec<(String, RunStatus)>,
+    /// Status of the trackers of the runs in progress, by task ID
+    trackers: Vec<(String, TrackerStatus)>,
+}
+
+/// Serves the metrics of `stations` at `/metrics`. Like the API documentation, it needs no key.
+pub fn router(stations: Vec<AppState>) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(Arc::new(stations))
+}
+
+/// Status of the trackers of the `runs` in progress, by task ID.
+pub(super) async fn tracker_statuses(
+    state: &AppState,
+    runs: &[(String, RunStatus)],
+) -> Vec<(String, TrackerStatus)> {
+    let mut trackers = Vec::new();
+    for (id, _) in runs {
+        let path = state
+            .tasks_path
+            .join("Artifacts")
+            .join(id)
+            .join(STATUS_FILE);
+        // Only there while a tracker runs as a step
+        if let Ok(status) = tokio::fs::read(&path).await
+            && let Ok(status) = serde_json::from_slice(&status)
+        {
+            trackers.push((id.clone(), status));
+        }
+    }
+    trackers
+}
+
+async fn metrics(State(stations): State<Arc<Vec<AppState>>>) -> impl IntoResponse {
+    let mut metrics = Vec::new();
+    for state in stations.iter() {
+        let runs = state.runs.statuses();
+        let trackers = tracker_statuses(state, &runs).await;
+        metrics.push(StationMetrics {
+            name: state.config.station_name.clone(),
+            events: state.notifier.counts(),
+            runs,
+            trackers,
+        });
+    }
+    let latency = stations
+        .first()
+        .map(|s| s.latency.clone())
+        .unwrap_or_default();
+    (
+        [(CONTENT_TYPE, CONTENT_TYPE_TEXT)],
+        Body::from(render(&metrics, &latency)),
+    )
+}
+
+/// Writes metric families in the text exposition format.
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {name} {help}");
+        let _ = writeln!(self.0, "# TYPE {name} {kind}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.0.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+                .collect();
+            let _ = write!(self.0, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.0, " {value}");
+    }
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+fn render(stations: &[StationMetrics], latency: &RequestLatency) -> String {
+    let mut out = Exposition::default();
+
+    out.family(
+        "satomat_events_total",
+        "counter",
+        "Task and run events, as notified to the webhooks.",
+    );
+    for station in stations {
+        for event in EVENTS {
+            let count = station.events.get(&event).copied().unwrap_or(0);
+            let event = event_name(event);
+            out.sample(
+                "satomat_events_total",
+                &[("station", &station.name), ("event", &event)],
+                count,
+            );
+        }
+    }
+
+    out.family(
+        "satomat_runs_in_progress",
+        "gauge",
+        "Number of runs in progress.",
+    );
+    for station in stations {
+        out.sample(
+            "satomat_runs_in_progress",
+            &[("station", &station.name)],
+            station.runs.len(),
+        );
+    }
+
+    out.family(
+        "satomat_run_step",
+        "gauge",
+        "Index of the last step started by a run in progress.",
+    );
+    for station in stations {
+        for (task, status) in &station.runs {
+            if let Some(step) = status.step {
+                out.sample(
+                    "satomat_run_step",
+                    &[("station", &station.name), ("task", task)],
+                    step,
+                );
+            }
+        }
+    }
+
+    let trackers = stations
+        .iter()
+        .flat_map(|s| s.trackers.iter().map(move |(task, t)| (&s.name, task, t)));
+    for (name, help, value) in TRACKER_GAUGES {
+        out.family(name, "gauge", help);
+        for (station, task, tracker) in trackers.clone() {
+            let labels = [
+                ("station", station.as_str()),
+                ("task", task),
+                ("object", &tracker.object),
+            ];
+            out.sample(name, &labels, value(tracker));
+        }
+    }
+
+    out.family(
+        "satomat_tracker_doppler_hertz",
+        "gauge",
+        "Doppler shift of the uplink (tx) and downlink (rx) frequencies.",
+    );
+    for (station, task, tracker) in trackers.clone() {
+        for (direction, shift) in [("tx", tracker.tx_doppler), ("rx", tracker.rx_doppler)] {
+            if let Some(shift) = shift {
+                let labels = [
+                    ("station", station.as_str()),
+                    ("task", task),
+                    ("object", &tracker.object),
+                    ("direction", direction),
+                ];
+                out.sample("satomat_tracker_doppler_hertz", &labels, shift);
+            }
+        }
+    }
+
+    out.family(
+        "satomat_rotator_error_degrees",
+        "gauge",
+        "Angle between the position a rotator was sent to and where it reported to be.",
+    );
+    for (station, task, tracker) in trackers {
+        for (rotator, error) in &tracker.rotator_errors {
+            let labels = [
+                ("station", station.as_str()),
+                ("task", task),
+                ("rotator", rotator),
+            ];
+            out.sample("satomat_rotator_error_degrees", &labels, error);
+        }
+    }
+
+    let name = "satomat_api_request_duration_seconds";
+    out.family(name, "histogram", "Latency of the API requests.");
+    let mut cumulative = 0;
+    for (le, count) in LATENCY_BUCKETS.iter().zip(&latency.buckets) {
+        cumulative += count.load(Ordering::Relaxed);
+        out.sample(
+            &format!("{name}_bucket"),
+            &[("le", &le.to_string())],
+            cumulative,
+        );
+    }
+    let count = latency.count.load(Ordering::Relaxed);
+    out.sample(&format!("{name}_bucket"), &[("le", "+Inf")], count);
+    let sum = latency.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+    out.sample(&format!("{name}_sum"), &[], sum);
+    out.sample(&format!("{name}_count"), &[], count);
+
+    out.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    #[test]
+    fn metrics_are_exported_in_the_text_format() {
+        let latency = RequestLatency::default();
+        latency.observe(Duration::from_millis(3));
+        latency.observe(Duration::from_millis(200));
+        latency.observe(Duration::from_secs(30));
+
+        let station = StationMetrics {
+            name: "main".into(),
+            events: HashMap::from([(NotificationEvent::TaskSubmitted, 3)]),
+            runs: vec![(
+                "pass.1".into(),
+                RunStatus {
+                    step: Some(2),
+                    ..Default::default()
+                },
+            )],
+            trackers: vec![(
+                "pass.1".into(),
+                TrackerStatus {
+                    object: "ISS (ZARYA)".into(),
+                    time: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+                    azimuth: 123.5,
+                    elevation: 45.25,
+                    range_rate: -2345.5,
+                    tx_doppler: None,
+                    rx_doppler: Some(-7123),
+                    rotator_errors: BTreeMap::from([("uhf1".into(), 1.5)]),
+                },
+            )],
+        };
+        let text = render(&[station], &latency);
+        let lines: Vec<&str> = text.lines().collect();
+
+        for line in [
+            r#"satomat_events_total{station="main",event="task_submitted"} 3"#,
+            r#"satomat_events_total{station="main",event="run_failed"} 0"#,
+            r#"satomat_runs_in_progress{station="main"} 1"#,
+            r#"satomat_run_step{station="main",task="pass.1"} 2"#,
+            r#"satomat_tracker_elevation_degrees{station="main",task="pass.1",object="ISS (ZARYA)"} 45.25"#,
+            r#"satomat_tracker_update_timestamp_seconds{station="main",task="pass.1",object="ISS (ZARYA)"} 1767323045"#,
+            r#"satomat_tracker_doppler_hertz{station="main",task="pass.1",object="ISS (ZARYA)",direction="rx"} -7123"#,
+            r#"satomat_rotator_error_degrees{station="main",task="pass.1",rotator="uhf1"} 1.5"#,
+            "# TYPE satomat_api_request_duration_seconds histogram",
+            r#"satomat_api_request_duration_seconds_bucket{le="0.005"} 1"#,
+            r#"satomat_api_request_duration_seconds_bucket{le="0.25"} 2"#,
+            r#"satomat_api_request_duration_seconds_bucket{le="10"} 2"#,
+            r#"satomat_api_request_duration_seconds_bucket{le="+Inf"} 3"#,
+            "satomat_api_request_duration_seconds_count 3",
+        ] {
+            assert!(lines.contains(&line), "{line} not in:\n{text}");
+        }
+        assert!(!text.contains(r#"direction="tx""#));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape("a \"b\"\\c\nd"), r#"a \"b\"\\c\nd"#);
+    }
+}
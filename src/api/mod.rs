@@ -1,34 +1,79 @@
+mod audit;
 pub mod auth;
+pub mod auto_schedule;
+mod calendar;
+mod compression;
 pub mod error;
-mod predict;
+mod events;
+mod jwt;
+mod keys;
+pub mod metrics;
+pub mod predict;
+mod radio;
+mod rate_limit;
+mod request_log;
+pub mod review;
+pub mod runs;
 mod station;
+mod status;
 mod tasks;
 mod templates;
+mod tracker;
+mod versioning;
 
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use axum::{Router, middleware};
 use sat_o_mat::predict::PredictDb;
+use sat_o_mat::scheduler::Runs;
+use sat_o_mat::task::index::TaskIndex;
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
 use utoipa::{
     Modify, OpenApi,
-    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
 };
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 use crate::config::Config;
+use crate::notify::Notifier;
+use audit::AuditLog;
+use jwt::JwtValidator;
+use keys::KeyStore;
+use metrics::RequestLatency;
+use rate_limit::{FailedLogins, RateLimiter};
 
 const TASKS_TAG: &str = "tasks";
 const TEMPLATES_TAG: &str = "templates";
 const STATION_TAG: &str = "station";
 const PREDICT_TAG: &str = "predict";
+const RUNS_TAG: &str = "runs";
+const KEYS_TAG: &str = "keys";
+const AUDIT_TAG: &str = "audit";
+const AUTO_SCHEDULE_TAG: &str = "auto_schedule";
 
 #[derive(Clone)]
 pub struct AppState {
     pub tasks_path: PathBuf,
+    /// The tasks parsed from `tasks_path`.
+    pub tasks: Arc<TaskIndex>,
     pub config: Arc<Config>,
     pub predict_db: Arc<Mutex<PredictDb>>,
+    pub keys: Arc<Mutex<KeyStore>>,
+    /// Validator of bearer tokens, if JWT authentication is configured.
+    pub jwt: Option<Arc<JwtValidator>>,
+    /// Rate limits, if configured.
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Failed authentications of each address.
+    pub failed_logins: Arc<FailedLogins>,
+    pub notifier: Notifier,
+    /// Log of the calls changing the state of any station.
+    pub audit: Arc<AuditLog>,
+    /// Controls of the runs in progress, added by the scheduler.
+    pub runs: Runs,
+    /// Latency of the API requests of any station.
+    pub latency: Arc<RequestLatency>,
 }
 
 // --- OpenAPI ---
@@ -39,7 +84,11 @@ pub struct AppState {
         (name = TASKS_TAG, description = "Tasks API"),
         (name = TEMPLATES_TAG, description = "Templates API"),
         (name = STATION_TAG, description = "Station API"),
-        (name = PREDICT_TAG, description = "Predictions API")
+        (name = PREDICT_TAG, description = "Predictions API"),
+        (name = RUNS_TAG, description = "Run artifacts API"),
+        (name = KEYS_TAG, description = "API keys API"),
+        (name = AUDIT_TAG, description = "Audit log API"),
+        (name = AUTO_SCHEDULE_TAG, description = "Auto-scheduling rules API")
     ),
     modifiers(&SecurityAddon)
 )]
@@ -53,40 +102,390 @@ impl Modify for SecurityAddon {
             components.add_security_scheme(
                 "api_key",
                 SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("api_key"))),
-            )
+            );
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
         }
     }
 }
 
 // --- Router ---
 
-pub fn router(config: &Config) -> OpenApiRouter {
-    let mut predict = PredictDb::new();
-    let count = predict.add_tles(&config.tle_path).unwrap();
-    info!(?count, "satellites loaded");
+/// Creates the state shared by the API handlers, loading the configured TLEs.
+pub fn state(config: &Config) -> AppState {
+    if config.api.keys.iter().any(|k| !auth::is_hashed(&k.key)) {
+        warn!("plaintext API keys in config, replace them with hashes from `sat-o-mat hash-key`");
+    }
+
+    let jwt = config
+        .api
+        .jwt
+        .as_ref()
+        .and_then(|jwt| match JwtValidator::load(jwt) {
+            Ok(validator) => Some(Arc::new(validator)),
+            Err(e) => {
+                warn!(%e, path = ?jwt.jwks_path, "JWT authentication disabled");
+                None
+            }
+        });
+
+    AppState {
+        tasks_path: config.tasks_path.clone(),
+        tasks: Arc::new(TaskIndex::new(&config.tasks_path)),
+        config: Arc::new(config.clone()),
+        predict_db: Arc::new(Mutex::new(predict_db(config))),
+        keys: Arc::new(Mutex::new(KeyStore::load(config))),
+        jwt,
+        rate_limiter: config
+            .api
+            .rate_limit
+            .clone()
+            .map(|limits| Arc::new(RateLimiter::new(limits))),
+        failed_logins: Arc::new(FailedLogins::new(config.api.failed_logins)),
+        notifier: Notifier::new(config),
+        audit: Arc::new(AuditLog::new(config)),
+        runs: Runs::default(),
+        latency: Default::default(),
+    }
+}
 
-    let state = AppState {
+/// Creates the state of a hosted station from its `config`, sharing the API keys, token
+/// validation, rate limits, failed authentications, audit log and request latency of the `main`
+/// station.
+pub fn hosted_state(main: &AppState, config: &Config) -> AppState {
+    AppState {
         tasks_path: config.tasks_path.clone(),
+        tasks: Arc::new(TaskIndex::new(&config.tasks_path)),
         config: Arc::new(config.clone()),
-        predict_db: Arc::new(Mutex::new(predict)),
-    };
+        predict_db: Arc::new(Mutex::new(predict_db(config))),
+        keys: main.keys.clone(),
+        jwt: main.jwt.clone(),
+        rate_limiter: main.rate_limiter.clone(),
+        failed_logins: main.failed_logins.clone(),
+        notifier: Notifier::new(config),
+        audit: main.audit.clone(),
+        runs: Runs::default(),
+        latency: main.latency.clone(),
+    }
+}
+
+/// Creates the predictions database of a station, loading its TLEs.
+pub fn predict_db(config: &Config) -> PredictDb {
+    let mut predict = empty_predict_db(config);
+    match predict.add_tles(&config.tle_path) {
+        Ok(count) => info!(?count, "satellites loaded"),
+        Err(e) => warn!(?e, path = ?config.tle_path, "failed to load TLEs"),
+    }
+    predict
+}
+
+/// A satellite database with the prediction settings of `config`, without any satellites.
+pub fn empty_predict_db(config: &Config) -> PredictDb {
+    let mut predict = PredictDb::new();
+    predict.set_elevation_thresholds(config.predict.min_elevation.clone());
+    predict.set_search_steps(config.predict.steps.clone());
+    predict.set_groups(config.predict.groups.clone());
+    predict.set_solar_outage_angle(config.predict.solar_outage_angle);
+    predict
+}
+
+/// Loads the satellites of `state` again from its `tle_path`. The passes computed ahead of time
+/// are kept for the satellites whose elements did not change. Returns the number of satellites.
+pub async fn reload_satellites(state: &AppState) -> usize {
+    let reloaded = predict_db(&state.config);
+    let mut predict_db = state.predict_db.lock().await;
+    let previous = std::mem::replace(&mut *predict_db, reloaded);
+    predict_db.keep_passes(previous);
+    predict_db.len()
+}
+
+/// The OpenAPI document of the API, as served at `/api-docs/openapi.json`. It does not depend on
+/// the configuration.
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    let mut config = Config::default();
+    config.api.keys.clear();
+    routes(state(&config)).split_for_parts().1
+}
 
+/// Routes of the main station, under `/api/v1`.
+///
+/// The routes are also served under `/api`, as they were before versioning, with deprecation
+/// headers. Only the versioned routes are documented.
+pub fn routes(state: AppState) -> OpenApiRouter {
     OpenApiRouter::with_openapi(ApiDoc::openapi())
-        .nest(
-            "/api",
-            OpenApiRouter::new()
-                .routes(routes!(station::get_station))
-                .routes(routes!(tasks::list_tasks))
-                .routes(routes!(
-                    tasks::get_task,
-                    tasks::put_task,
-                    tasks::delete_task
-                ))
-                .routes(routes!(predict::get_passes))
-                .routes(routes!(predict::get_ground_track))
-                .routes(routes!(templates::list_templates))
-                .routes(routes!(templates::get_template))
-                .routes(routes!(templates::submit_from_template)),
+        .nest("/api/v1", v1_routes(&state))
+        .merge(
+            Router::new()
+                .nest("/api", deprecated(v1_routes(&state)))
+                .into(),
         )
         .with_state(state)
 }
+
+/// Routes of the hosted station `name`, the same as the main station's under
+/// `/api/v1/stations/{name}` (and, deprecated, `/api/stations/{name}`).
+pub fn hosted_routes(name: &str, state: AppState) -> Router {
+    let (router, _) = v1_routes(&state)
+        .with_state(state.clone())
+        .split_for_parts();
+    Router::new()
+        .nest(&format!("/api/v1/stations/{name}"), router)
+        .nest(
+            &format!("/api/stations/{name}"),
+            deprecated(v1_routes(&state)).with_state(state),
+        )
+}
+
+/// Marks the unversioned alias of `routes` as deprecated.
+fn deprecated(routes: OpenApiRouter<AppState>) -> Router<AppState> {
+    let (router, _) = routes.split_for_parts();
+    router.layer(middleware::from_fn(versioning::deprecated))
+}
+
+/// Routes of version 1 of the API, for one station.
+///
+/// Incompatible changes go into a new version, with its own routes nested next to these under
+/// its own prefix, so that clients of older versions keep working.
+fn v1_routes(state: &AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(station::get_station))
+        .routes(routes!(station::park_rotator))
+        .routes(routes!(status::get_status))
+        .routes(routes!(tasks::list_tasks))
+        .routes(routes!(calendar::get_calendar))
+        .routes(routes!(tasks::approve_batch))
+        .routes(routes!(tasks::validate_task))
+        .routes(routes!(review::get_review))
+        .routes(routes!(tasks::dry_run_task))
+        .routes(routes!(
+            tasks::get_task,
+            tasks::put_task,
+            tasks::delete_task
+        ))
+        .routes(routes!(tasks::pause_task))
+        .routes(routes!(tasks::resume_task))
+        .routes(routes!(tasks::abort_task))
+        .routes(routes!(predict::list_satellites))
+        .routes(routes!(predict::reload_satellites))
+        .routes(routes!(predict::get_passes))
+        .routes(routes!(predict::get_ground_track))
+        .routes(routes!(predict::get_stats))
+        .routes(routes!(predict::get_observables))
+        .routes(routes!(predict::stream_observables))
+        .routes(routes!(predict::get_transits))
+        .routes(routes!(predict::schedule_pass))
+        .routes(routes!(predict::schedule_satellite_pass))
+        .routes(routes!(radio::stream_fft))
+        .routes(routes!(tracker::stream_tracker))
+        .routes(routes!(runs::get_execution))
+        .routes(routes!(runs::list_artifacts))
+        .routes(routes!(runs::get_artifact))
+        .routes(routes!(runs::download))
+        .routes(routes!(runs::stream_log))
+        .routes(routes!(events::stream_events))
+        .routes(routes!(keys::list_keys, keys::create_key))
+        .routes(routes!(keys::revoke_key))
+        .routes(routes!(audit::get_audit))
+        .routes(routes!(templates::list_templates))
+        .routes(routes!(templates::get_template))
+        .routes(routes!(templates::submit_from_template))
+        .routes(routes!(
+            auto_schedule::list_rules,
+            auto_schedule::create_rule
+        ))
+        .routes(routes!(auto_schedule::delete_rule))
+        .layer(compression::layer())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authenticate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            request_log::log,
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::config::{ApiKey, HostedStationConfig, Permission};
+
+    #[tokio::test]
+    async fn unversioned_routes_are_deprecated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            tasks_path: tmp.path().to_path_buf(),
+            tle_path: tmp.path().join("tle"),
+            ..Default::default()
+        };
+        let (router, openapi) = routes(state(&config)).split_for_parts();
+        assert!(openapi.paths.paths.contains_key("/api/v1/station"));
+        assert!(!openapi.paths.paths.contains_key("/api/station"));
+
+        let get = |uri: &str| {
+            router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        };
+        let resp = get("/api/v1/station").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key("deprecation"));
+
+        let resp = get("/api/station").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["deprecation"], "true");
+        assert_eq!(
+            resp.headers()["link"],
+            r#"</api/v1/station>; rel="successor-version""#
+        );
+    }
+
+    #[tokio::test]
+    async fn openapi_document_matches_the_served_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            tasks_path: tmp.path().to_path_buf(),
+            tle_path: tmp.path().join("tle"),
+            ..Default::default()
+        };
+        let (_, served) = routes(state(&config)).split_for_parts();
+        assert_eq!(
+            serde_json::to_value(openapi()).unwrap(),
+            serde_json::to_value(served).unwrap()
+        );
+    }
+
+    #[test]
+    fn openapi_document_is_complete() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            tasks_path: tmp.path().to_path_buf(),
+            tle_path: tmp.path().join("tle"),
+            ..Default::default()
+        };
+        let (_, openapi) = routes(state(&config)).split_for_parts();
+        let doc = serde_json::to_value(&openapi).unwrap();
+
+        // Every referenced schema is defined
+        let schemas = &doc["components"]["schemas"];
+        let mut pending = vec![&doc];
+        while let Some(value) = pending.pop() {
+            match value {
+                serde_json::Value::Object(map) => {
+                    if let Some(serde_json::Value::String(reference)) = map.get("$ref") {
+                        let name = reference.trim_start_matches("#/components/schemas/");
+                        assert!(schemas.get(name).is_some(), "undefined schema {reference}");
+                    }
+                    pending.extend(map.values());
+                }
+                serde_json::Value::Array(values) => pending.extend(values),
+                _ => {}
+            }
+        }
+
+        // Every response with content has a schema
+        for (path, item) in doc["paths"].as_object().unwrap() {
+            for (method, operation) in item.as_object().unwrap() {
+                let responses = operation["responses"].as_object().unwrap();
+                let ok = responses.get("200").or_else(|| responses.get("201"));
+                if let Some(content) = ok.and_then(|r| r.get("content")) {
+                    for (content_type, media) in content.as_object().unwrap() {
+                        assert!(
+                            media.get("schema").is_some(),
+                            "{method} {path} {content_type} has no schema"
+                        );
+                    }
+                }
+            }
+        }
+        for name in [
+            "TemplateListEntry",
+            "ArtifactEntry",
+            "ApiLogEntry",
+            "BinaryContent",
+        ] {
+            assert!(schemas.get(name).is_some(), "{name} is not documented");
+        }
+    }
+
+    #[tokio::test]
+    async fn hosted_stations_have_their_own_tasks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let south = tmp.path().join("south");
+        for dir in [tmp.path().join("Active"), south.join("Active")] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(south.join("Active/pass.yaml"), "steps: []\n").unwrap();
+
+        let config = Config {
+            station_name: "north".into(),
+            hosted_stations: [(
+                "south".to_string(),
+                HostedStationConfig {
+                    tasks_path: south,
+                    tle_path: None,
+                    ground_station: None,
+                    auto_schedule: Default::default(),
+                    resources: Vec::new(),
+                },
+            )]
+            .into(),
+            ..Config::for_test(
+                tmp.path(),
+                vec![ApiKey {
+                    name: None,
+                    id: None,
+                    key: "test-key".into(),
+                    permissions: vec![Permission::ViewTasks],
+                }],
+            )
+        };
+        let main = state(&config);
+        let hosted = hosted_state(&main, &config.hosted_station("south").unwrap());
+        let (router, _) = routes(main).split_for_parts();
+        let router = router.merge(hosted_routes("south", hosted));
+
+        let get = |uri: &str| {
+            router.clone().oneshot(
+                Request::get(uri)
+                    .header("api_key", "test-key")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let body = |resp: axum::response::Response| async {
+            let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let resp = get("/api/station").await.unwrap();
+        assert_eq!(
+            body(resp).await,
+            r#"{"name":"north","hosted_stations":["south"]}"#
+        );
+        let resp = get("/api/tasks").await.unwrap();
+        assert_eq!(body(resp).await, "[]");
+
+        let resp = get("/api/v1/stations/south/station").await.unwrap();
+        assert!(body(resp).await.contains(r#""name":"south""#));
+        let resp = get("/api/v1/stations/south/tasks").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(body(resp).await.contains(r#""id":"pass""#));
+    }
+}
@@ -1,16 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 
 use axum::Json;
-use axum::extract::{Query, State};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
 use chrono::{DateTime, Duration, Utc};
-use lox_space::time::utc::transformations::ToUtc;
+use futures_util::Stream;
+use lox_space::prelude::GroundStation;
+use lox_space::time::{
+    Time, intervals::TimeInterval, time_scales::DynTimeScale, utc::transformations::ToUtc,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+use sat_o_mat::predict::{
+    OrbitRegime, PredictDb, PredictedPass, Satellite, TransitBody, doppler_shift,
+};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
 use utoipa::{IntoParams, ToSchema};
 
-use crate::{api::error::ApiError, predict::PredictDb};
+use crate::api::error::ApiError;
+use crate::config::{Permission, PredictConfig};
 
 use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::templates::{read_template, submit_task};
 
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct PredictQuery {
@@ -20,28 +35,243 @@ pub struct PredictQuery {
     /// End time as RFC3339. Defaults to start + 24h.
     #[param(value_type = Option<String>)]
     pub end: Option<DateTime<Utc>>,
+    /// Transmitter frequency in Hz. If given, passes include the maximum Doppler shift and rate.
+    pub frequency: Option<f64>,
+    /// Only include satellites in this group.
+    pub group: Option<String>,
+    /// Only include the satellite with this NORAD ID.
+    pub norad_id: Option<u64>,
+    /// Only include satellites whose name contains this, ignoring case.
+    pub name: Option<String>,
+    /// Predict passes over this station (one of the configured `stations`) instead of ours.
+    pub station: Option<String>,
 }
 
+impl PredictQuery {
+    fn includes(&self, name: &str, sat: &Satellite) -> bool {
+        self.group.as_ref().is_none_or(|group| sat.in_group(group))
+            && self.norad_id.is_none_or(|id| sat.elements.norad_id == id)
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|part| name.to_lowercase().contains(&part.to_lowercase()))
+    }
+
+    /// Returns the ground station to predict passes over.
+    fn ground_station<'a>(&self, state: &'a AppState) -> Result<&'a GroundStation, ApiError> {
+        ground_station(state, self.station.as_deref())
+    }
+}
+
+/// Returns our ground station, or the configured remote `station` if given.
+pub(super) fn ground_station<'a>(
+    state: &'a AppState,
+    station: Option<&str>,
+) -> Result<&'a GroundStation, ApiError> {
+    match station {
+        None => state
+            .config
+            .ground_station
+            .as_ref()
+            .ok_or(ApiError::Internal),
+        Some(name) => state
+            .config
+            .stations
+            .get(name)
+            .ok_or_else(|| ApiError::BadRequest(format!("unknown station {name}"))),
+    }
+}
+
+/// How long to wait after a change to the TLE files before reloading them.
+const TLE_SETTLE_TIME: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How far ahead the passes over the stations are computed in the background.
+const PRECOMPUTE_HOURS: i64 = 48;
+
+/// The passes of a satellite are computed again once less than this is left of them.
+const PRECOMPUTE_MIN_HOURS: i64 = 36;
+
+/// How often the passes computed in the background are checked.
+const PRECOMPUTE_PERIOD: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Keeps the passes of the satellites over our station and the configured `stations` computed
+/// for the next [`PRECOMPUTE_HOURS`], for `GET /predict/passes`, until the server exits.
+///
+/// Only the passes of satellites with new elements or running out are computed, one satellite at
+/// a time so that requests are not held up.
+pub async fn precompute_passes(state: AppState) {
+    let mut interval = tokio::time::interval(PRECOMPUTE_PERIOD);
+    loop {
+        interval.tick().await;
+        let now = Utc::now();
+        // Start a bit earlier, so that the passes in progress are not cut short
+        let start = now - Duration::hours(1);
+        let end = now + Duration::hours(PRECOMPUTE_HOURS);
+        let stations = state.config.ground_station.iter();
+        for gs in stations.chain(state.config.stations.values()) {
+            let stale = state.predict_db.lock().await.stale_passes(
+                gs,
+                now,
+                now + Duration::hours(PRECOMPUTE_MIN_HOURS),
+            );
+            for name in &stale {
+                state
+                    .predict_db
+                    .lock()
+                    .await
+                    .precompute_passes(name, gs, start, end);
+                tokio::task::yield_now().await;
+            }
+            if !stale.is_empty() {
+                info!(station = ?gs.id(), satellites = stale.len(), "precomputed passes");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PassPageQuery {
+    /// Maximum number of passes to return, in order of start time.
+    pub limit: Option<usize>,
+    /// Number of passes to skip, in order of start time.
+    #[serde(default)]
+    pub offset: usize,
+    /// Maximum number of passes per satellite, applied before `limit` and `offset`.
+    pub max_passes_per_satellite: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct FieldsQuery {
+    /// Only include these fields of each pass or ground track, separated by commas, e.g.
+    /// `start,end,max_elevation`. All fields if unset.
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    /// Returns the selected fields, which must be among `available`.
+    fn selected(&self, available: &[&str]) -> Result<Option<Vec<String>>, ApiError> {
+        let Some(fields) = &self.fields else {
+            return Ok(None);
+        };
+        let fields: Vec<String> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .collect();
+        if let Some(unknown) = fields.iter().find(|f| !available.contains(&f.as_str())) {
+            return Err(ApiError::BadRequest(format!(
+                "unknown field {unknown}, expected one of {}",
+                available.join(", ")
+            )));
+        }
+        Ok(Some(fields))
+    }
+}
+
+/// Removes the fields of each object in `items` not in `fields`.
+fn select_fields<'a>(
+    items: impl IntoIterator<Item = &'a mut serde_json::Value>,
+    fields: &[String],
+) {
+    for item in items {
+        if let Some(object) = item.as_object_mut() {
+            object.retain(|key, _| fields.contains(key));
+        }
+    }
+}
+
+/// Fields of [`ApiPass`], selectable with `fields`.
+const PASS_FIELDS: &[&str] = &[
+    "start",
+    "end",
+    "max_elevation",
+    "tca",
+    "geometric_start",
+    "geometric_end",
+    "geometric_max_elevation",
+    "range_at_tca_km",
+    "max_doppler_hz",
+    "max_doppler_rate_hz_s",
+    "azimuth",
+    "elevation",
+    "warnings",
+];
+
+/// Fields of [`ApiGroundTrack`], selectable with `fields`.
+const GROUND_TRACK_FIELDS: &[&str] = &["start", "latitude", "longitude"];
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct PassPredictions {
+    /// Passes per satellite. Satellites without passes in the requested page are omitted.
     predictions: HashMap<String, Vec<ApiPass>>,
+    /// Total number of passes, before `limit` and `offset`
+    total_passes: usize,
+    /// Total number of satellites with passes, before `limit` and `offset`
+    total_satellites: usize,
+    /// Geostationary satellites, which have constant look angles instead of passes
+    geostationary: HashMap<String, ApiGeoVisibility>,
+    /// Age of each satellite's element set in days, at the start of the prediction
+    element_age_days: HashMap<String, f64>,
+    /// Warnings about the predictions, e.g. stale element sets
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiGeoVisibility {
+    /// Azimuth angle in degrees at the start of the prediction
+    azimuth: f64,
+    /// Elevation angle in degrees at the start of the prediction
+    elevation: f64,
+    /// Slant range in km at the start of the prediction
+    range_km: f64,
+    /// Lowest elevation in degrees during the prediction
+    min_elevation: f64,
+    /// Highest elevation in degrees during the prediction
+    max_elevation: f64,
+    /// Whether the satellite is visible during the whole prediction
+    visible: bool,
+    /// Warnings about the prediction, e.g. solar outages
+    warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ApiPass {
-    /// Start time formatted as RFC3339
+    /// Start time (AOS through the station's elevation mask) formatted as RFC3339
     start: String,
-    /// End time formatted as RFC3339
+    /// End time (LOS through the station's elevation mask) formatted as RFC3339
     end: String,
+    /// Maximum visible elevation in degrees
+    max_elevation: f64,
+    /// Time of maximum elevation formatted as RFC3339
+    tca: String,
+    /// Start time above the ideal 0° horizon formatted as RFC3339
+    geometric_start: String,
+    /// End time above the ideal 0° horizon formatted as RFC3339
+    geometric_end: String,
+    /// Maximum elevation above the ideal horizon in degrees
+    geometric_max_elevation: f64,
+    /// Slant range at the time of maximum elevation in km
+    range_at_tca_km: f64,
+    /// Maximum Doppler shift in Hz, if a frequency was given
+    max_doppler_hz: Option<f64>,
+    /// Maximum Doppler rate in Hz/s, if a frequency was given
+    max_doppler_rate_hz_s: Option<f64>,
     /// Azimuth angle in degrees
     azimuth: Vec<f64>,
     /// Elevation angle in degrees
     elevation: Vec<f64>,
+    /// Warnings about the pass, e.g. solar outages
+    warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct GroundTrackPredictions {
     predictions: HashMap<String, ApiGroundTrack>,
+    /// Age of each satellite's element set in days, at the start of the prediction
+    element_age_days: HashMap<String, f64>,
+    /// Warnings about the predictions, e.g. stale element sets
+    warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -54,12 +284,130 @@ pub struct ApiGroundTrack {
     longitude: Vec<f64>,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SatelliteList {
+    satellites: Vec<ApiSatellite>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiSatellite {
+    name: String,
+    norad_id: u64,
+    /// Element set epoch formatted as RFC3339
+    epoch: String,
+    /// Orbit regime: leo, meo, geo or heo
+    #[schema(value_type = String)]
+    regime: OrbitRegime,
+    /// Groups the satellite belongs to
+    groups: Vec<String>,
+    /// File the element set was loaded from
+    source: Option<String>,
+}
+
+/// List the satellites available for prediction.
+#[utoipa::path(
+    get,
+    path = "/predict/satellites",
+    tag = super::PREDICT_TAG,
+    responses(
+        (status = 200, description = "Loaded satellites", body = SatelliteList),
+    ),
+)]
+pub async fn list_satellites(State(state): State<AppState>) -> Json<SatelliteList> {
+    let predict_db = state.predict_db.lock().await;
+
+    let mut satellites: Vec<ApiSatellite> = predict_db
+        .iter()
+        .map(|(name, sat)| ApiSatellite {
+            name: name.clone(),
+            norad_id: sat.elements.norad_id,
+            epoch: sat.elements.datetime.and_utc().to_rfc3339(),
+            regime: sat.regime(),
+            groups: sat.groups.clone(),
+            source: sat.source.as_ref().map(|p| p.display().to_string()),
+        })
+        .collect();
+    satellites.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Json(SatelliteList { satellites })
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReloadedSatellites {
+    /// Number of satellites loaded
+    satellites: usize,
+}
+
+/// Load the satellites again from the TLE files.
+///
+/// The server reloads them by itself when the files in `tle_path` change; this is for where
+/// changes are not noticed, e.g. on network filesystems.
+#[utoipa::path(
+    post,
+    path = "/predict/satellites/reload",
+    tag = super::PREDICT_TAG,
+    responses(
+        (status = 200, description = "Satellites reloaded", body = ReloadedSatellites),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn reload_satellites(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+) -> Result<Json<ReloadedSatellites>, ApiError> {
+    auth.require(Permission::ReloadSatellites)?;
+    let satellites = super::reload_satellites(&state).await;
+    info!(satellites, "satellites reloaded");
+    Ok(Json(ReloadedSatellites { satellites }))
+}
+
+/// Loads the satellites again whenever the files in `tle_path` change, until the server exits.
+pub async fn watch_satellites(state: AppState) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let handler = move |res: notify::Result<notify::Event>| match res {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+            let _ = tx.send(());
+        }
+        Ok(_) => {}
+        Err(e) => warn!(?e, "TLE watcher error"),
+    };
+    let path = &state.config.tle_path;
+    let mut watcher = match notify::recommended_watcher(handler) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!(?e, "failed to start TLE watcher");
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(path, RecursiveMode::NonRecursive) {
+        warn!(
+            ?e,
+            ?path,
+            "failed to watch TLEs, reload them through the API"
+        );
+        return;
+    }
+
+    while rx.recv().await.is_some() {
+        // Files are often written in several steps, or several at once
+        tokio::time::sleep(TLE_SETTLE_TIME).await;
+        while rx.try_recv().is_ok() {}
+        let satellites = super::reload_satellites(&state).await;
+        info!(satellites, ?path, "TLEs changed, satellites reloaded");
+    }
+}
+
 /// Get pass predictions.
+///
+/// The azimuth and elevation of each pass make large responses; use `fields` to leave them out
+/// if not needed.
 #[utoipa::path(
     get,
     path = "/predict/passes",
     tag = super::PREDICT_TAG,
-    params(PredictQuery),
+    params(PredictQuery, PassPageQuery, FieldsQuery),
     responses(
         (status = 200, description = "Pass predictions", body = PassPredictions),
         (status = 400, description = "Invalid parameters"),
@@ -68,7 +416,10 @@ pub struct ApiGroundTrack {
 pub async fn get_passes(
     State(state): State<AppState>,
     Query(query): Query<PredictQuery>,
-) -> Result<Json<PassPredictions>, ApiError> {
+    Query(page): Query<PassPageQuery>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let fields = fields.selected(PASS_FIELDS)?;
     let start = query.start.unwrap_or_else(Utc::now);
     let end = query.end.unwrap_or_else(|| start + Duration::hours(24));
 
@@ -76,47 +427,123 @@ pub async fn get_passes(
         return Err(ApiError::BadRequest("end must be after start".to_string()));
     }
 
-    let gs = state
-        .config
-        .ground_station
-        .as_ref()
-        .ok_or(ApiError::Internal)?;
+    let gs = query.ground_station(&state)?;
 
     let predict_db = state.predict_db.lock().await;
 
-    let predictions = predict_db
-        .predict_passes(start, end, gs, None)
+    let mut passes: Vec<(String, PredictedPass)> = predict_db
+        .cached_passes_filtered(start, end, gs, |name, sat| query.includes(name, sat))
         .into_iter()
-        .map(|(id, passes)| {
-            let passes = passes
+        .flat_map(|(id, passes)| {
+            let max = page.max_passes_per_satellite.unwrap_or(usize::MAX);
+            passes
                 .into_iter()
-                .map(|pass| {
-                    let interval = pass.interval();
-
-                    let (azimuth, elevation) = pass
-                        .observables()
-                        .iter()
-                        .map(|obs| (obs.azimuth().to_degrees(), obs.elevation().to_degrees()))
-                        .collect();
-
-                    ApiPass {
-                        start: DateTime::<Utc>::try_from(interval.start().to_utc())
-                            .unwrap()
-                            .to_rfc3339(),
-                        end: DateTime::<Utc>::try_from(interval.end().to_utc())
-                            .unwrap()
-                            .to_rfc3339(),
-                        azimuth,
-                        elevation,
-                    }
-                })
-                .collect();
+                .take(max)
+                .map(move |pass| (id.to_string(), pass))
+        })
+        .collect();
+    passes.sort_by_key(|(_, predicted)| to_datetime(predicted.pass.interval().start()));
+
+    let total_passes = passes.len();
+    let total_satellites = passes
+        .iter()
+        .map(|(name, _)| name)
+        .collect::<HashSet<_>>()
+        .len();
+
+    let mut predictions: HashMap<String, Vec<ApiPass>> = HashMap::new();
+    for (name, predicted) in passes
+        .into_iter()
+        .skip(page.offset)
+        .take(page.limit.unwrap_or(usize::MAX))
+    {
+        predictions
+            .entry(name)
+            .or_default()
+            .push(to_api_pass(&predicted, query.frequency));
+    }
 
-            (id.to_string(), passes)
+    let geostationary = predict_db
+        .predict_geostationary(start, end, gs, None, |name, sat| query.includes(name, sat))
+        .into_iter()
+        .map(|(id, geo)| {
+            let visibility = ApiGeoVisibility {
+                azimuth: geo.observables.azimuth().to_degrees(),
+                elevation: geo.observables.elevation().to_degrees(),
+                range_km: geo.observables.range() / 1000.0,
+                min_elevation: geo.min_elevation.to_degrees(),
+                max_elevation: geo.max_elevation.to_degrees(),
+                visible: geo.visible,
+                warnings: solar_outage_warnings(&geo.solar_outages),
+            };
+            (id.to_string(), visibility)
         })
         .collect();
 
-    Ok(Json(PassPredictions { predictions }))
+    let (element_age_days, warnings) = element_ages(&state, &predict_db, start);
+
+    let mut response = serde_json::to_value(PassPredictions {
+        predictions,
+        total_passes,
+        total_satellites,
+        geostationary,
+        element_age_days,
+        warnings,
+    })
+    .map_err(|_| ApiError::Internal)?;
+    if let Some(fields) = fields
+        && let Some(predictions) = response["predictions"].as_object_mut()
+    {
+        select_fields(
+            predictions
+                .values_mut()
+                .filter_map(|passes| passes.as_array_mut())
+                .flatten(),
+            &fields,
+        );
+    }
+
+    Ok(Json(response))
+}
+
+pub fn to_api_pass(predicted: &PredictedPass, frequency: Option<f64>) -> ApiPass {
+    let interval = predicted.pass.interval();
+
+    let (azimuth, elevation) = predicted
+        .pass
+        .observables()
+        .iter()
+        .map(|obs| (obs.azimuth().to_degrees(), obs.elevation().to_degrees()))
+        .collect();
+
+    ApiPass {
+        start: to_datetime(interval.start()).to_rfc3339(),
+        end: to_datetime(interval.end()).to_rfc3339(),
+        max_elevation: predicted.max_elevation.to_degrees(),
+        tca: to_datetime(predicted.tca).to_rfc3339(),
+        geometric_start: to_datetime(predicted.geometric.start()).to_rfc3339(),
+        geometric_end: to_datetime(predicted.geometric.end()).to_rfc3339(),
+        geometric_max_elevation: predicted.geometric_max_elevation.to_degrees(),
+        range_at_tca_km: predicted.range_at_tca / 1000.0,
+        max_doppler_hz: frequency.map(|f| predicted.max_doppler(f)),
+        max_doppler_rate_hz_s: frequency.map(|f| predicted.max_doppler_rate(f)),
+        azimuth,
+        elevation,
+        warnings: solar_outage_warnings(&predicted.solar_outages),
+    }
+}
+
+fn solar_outage_warnings(outages: &[TimeInterval<DynTimeScale>]) -> Vec<String> {
+    outages
+        .iter()
+        .map(|outage| {
+            format!(
+                "Solar outage from {} to {}: the satellite is close to the Sun",
+                to_datetime(outage.start()).to_rfc3339(),
+                to_datetime(outage.end()).to_rfc3339(),
+            )
+        })
+        .collect()
 }
 
 /// Get ground track predictions.
@@ -124,7 +551,7 @@ pub async fn get_passes(
     get,
     path = "/predict/ground_track",
     tag = super::PREDICT_TAG,
-    params(PredictQuery),
+    params(PredictQuery, FieldsQuery),
     responses(
         (status = 200, description = "Ground track predictions", body = GroundTrackPredictions),
         (status = 400, description = "Invalid parameters"),
@@ -133,7 +560,9 @@ pub async fn get_passes(
 pub async fn get_ground_track(
     State(state): State<AppState>,
     Query(query): Query<PredictQuery>,
-) -> Result<Json<GroundTrackPredictions>, ApiError> {
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let fields = fields.selected(GROUND_TRACK_FIELDS)?;
     let start = query.start.unwrap_or_else(Utc::now);
     let end = query.end.unwrap_or_else(|| start + Duration::hours(24));
 
@@ -144,7 +573,7 @@ pub async fn get_ground_track(
     let predict_db = state.predict_db.lock().await;
 
     let predictions = predict_db
-        .predict_ground_track(start, end, None)
+        .predict_ground_track_filtered(start, end, None, |name, sat| query.includes(name, sat))
         .into_iter()
         .map(|(id, track)| {
             let (lats, lons) = track
@@ -163,5 +592,1385 @@ pub async fn get_ground_track(
         })
         .collect();
 
-    Ok(Json(GroundTrackPredictions { predictions }))
+    let (element_age_days, warnings) = element_ages(&state, &predict_db, start);
+
+    let mut response = serde_json::to_value(GroundTrackPredictions {
+        predictions,
+        element_age_days,
+        warnings,
+    })
+    .map_err(|_| ApiError::Internal)?;
+    if let Some(fields) = fields
+        && let Some(predictions) = response["predictions"].as_object_mut()
+    {
+        select_fields(predictions.values_mut(), &fields);
+    }
+
+    Ok(Json(response))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ObservablesQuery {
+    /// Name or NORAD ID of the satellite.
+    pub satellite: String,
+    /// Time as RFC3339. Defaults to now.
+    #[param(value_type = Option<String>)]
+    pub time: Option<DateTime<Utc>>,
+    /// Transmitter frequency in Hz. If given, the Doppler shift is included.
+    pub frequency: Option<f64>,
+    /// Observe from this station (one of the configured `stations`) instead of ours.
+    pub station: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiObservables {
+    satellite: String,
+    norad_id: u64,
+    /// Time of the observation formatted as RFC3339
+    time: String,
+    /// Azimuth angle in degrees
+    azimuth: f64,
+    /// Elevation angle in degrees
+    elevation: f64,
+    /// Slant range in km
+    range_km: f64,
+    /// Range rate in km/s, positive when the satellite moves away
+    range_rate_km_s: f64,
+    /// Doppler shift in Hz of the signal received at `frequency`, if given
+    doppler_hz: Option<f64>,
+}
+
+/// Get the position of a satellite as seen from the station.
+///
+/// Returns the azimuth, elevation, range, range rate and Doppler shift at a single time, e.g. to
+/// follow a pass live.
+#[utoipa::path(
+    get,
+    path = "/predict/observables",
+    tag = super::PREDICT_TAG,
+    params(ObservablesQuery),
+    responses(
+        (status = 200, description = "Observables of the satellite", body = ApiObservables),
+        (status = 400, description = "Invalid parameters"),
+        (status = 404, description = "Satellite not found"),
+    ),
+)]
+pub async fn get_observables(
+    State(state): State<AppState>,
+    Query(query): Query<ObservablesQuery>,
+) -> Result<Json<ApiObservables>, ApiError> {
+    let time = query.time.unwrap_or_else(Utc::now);
+    let gs = ground_station(&state, query.station.as_deref())?;
+
+    let predict_db = state.predict_db.lock().await;
+    let (observables, _) = observe(&predict_db, &query.satellite, time, gs, query.frequency)?;
+    Ok(Json(observables))
+}
+
+/// The observables of `satellite` from `gs` at `time`, and the epoch of the elements they were
+/// computed from.
+fn observe(
+    predict_db: &PredictDb,
+    satellite: &str,
+    time: DateTime<Utc>,
+    gs: &GroundStation,
+    frequency: Option<f64>,
+) -> Result<(ApiObservables, DateTime<Utc>), ApiError> {
+    let (name, sat) = predict_db.find(satellite).ok_or(ApiError::NotFound)?;
+    let observables = predict_db
+        .observables_at(time, &sat.spacecraft, gs)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let range_rate = observables.range_rate();
+
+    let observables = ApiObservables {
+        satellite: name.clone(),
+        norad_id: sat.elements.norad_id,
+        time: time.to_rfc3339(),
+        azimuth: observables.azimuth().to_degrees(),
+        elevation: observables.elevation().to_degrees(),
+        range_km: observables.range() / 1000.0,
+        range_rate_km_s: range_rate / 1000.0,
+        doppler_hz: frequency.map(|f| doppler_shift(f, range_rate)),
+    };
+    Ok((observables, sat.elements.datetime.and_utc()))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ObservablesStreamQuery {
+    /// Name or NORAD ID of the satellite.
+    pub satellite: String,
+    /// Transmitter frequency in Hz. If given, the Doppler shift is included.
+    pub frequency: Option<f64>,
+    /// Observe from this station (one of the configured `stations`) instead of ours.
+    pub station: Option<String>,
+    /// Seconds between samples, at least 0.1. Defaults to 1.
+    pub interval: Option<f64>,
+}
+
+/// Orbit elements the samples of a stream are computed from.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiElements {
+    satellite: String,
+    norad_id: u64,
+    /// Epoch of the elements formatted as RFC3339
+    epoch: String,
+}
+
+/// Follow a satellite live.
+///
+/// Sends the observables of the satellite every `interval` as `sample` events, like
+/// `/predict/observables` at the current time. An `elements` event is sent before the first sample
+/// and whenever the orbit elements are refreshed, e.g. after a TLE download, as the trajectory
+/// changes with them.
+#[utoipa::path(
+    get,
+    path = "/predict/observables/stream",
+    tag = super::PREDICT_TAG,
+    params(ObservablesStreamQuery),
+    responses(
+        (status = 200, description = "Stream of events, the data of `sample` events being the observables of the satellite", body = ApiObservables, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid parameters"),
+        (status = 404, description = "Satellite not found"),
+    ),
+)]
+pub async fn stream_observables(
+    State(state): State<AppState>,
+    Query(query): Query<ObservablesStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let gs = ground_station(&state, query.station.as_deref())?.clone();
+    let interval = query.interval.unwrap_or(1.0);
+    if !interval.is_finite() || interval < 0.1 {
+        return Err(ApiError::BadRequest(
+            "interval must be at least 0.1 seconds".into(),
+        ));
+    }
+    // Fail early for unknown satellites, rather than with an empty stream
+    observe(
+        &*state.predict_db.lock().await,
+        &query.satellite,
+        Utc::now(),
+        &gs,
+        None,
+    )?;
+
+    let (tx, rx) = mpsc::channel(16);
+    let interval = std::time::Duration::from_secs_f64(interval);
+    tokio::spawn(follow_satellite(state, query, gs, interval, tx));
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Sends the observables of the satellite of `query` to `tx` every `interval`, until the client
+/// goes away or the satellite is no longer known.
+async fn follow_satellite(
+    state: AppState,
+    query: ObservablesStreamQuery,
+    gs: GroundStation,
+    interval: std::time::Duration,
+    tx: mpsc::Sender<Event>,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut epoch = None;
+    loop {
+        ticks.tick().await;
+        let observed = observe(
+            &*state.predict_db.lock().await,
+            &query.satellite,
+            Utc::now(),
+            &gs,
+            query.frequency,
+        );
+        let Ok((observables, elements_epoch)) = observed else {
+            let _ = tx.send(Event::default().event("end")).await;
+            return;
+        };
+        let mut events = Vec::new();
+        if epoch != Some(elements_epoch) {
+            epoch = Some(elements_epoch);
+            let elements = ApiElements {
+                satellite: observables.satellite.clone(),
+                norad_id: observables.norad_id,
+                epoch: elements_epoch.to_rfc3339(),
+            };
+            events.extend(Event::default().event("elements").json_data(elements));
+        }
+        events.extend(Event::default().event("sample").json_data(observables));
+        for event in events {
+            if tx.send(event).await.is_err() {
+                // The client went away
+                return;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TransitQuery {
+    /// Only predict transits across this body: sun or moon. Both if unset.
+    #[param(value_type = Option<String>)]
+    pub body: Option<TransitBody>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransitPredictions {
+    /// Transits per satellite, in order of time. Satellites without transits are omitted.
+    predictions: HashMap<String, Vec<ApiTransit>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiTransit {
+    /// Body transited: sun or moon
+    #[schema(value_type = String)]
+    body: TransitBody,
+    /// Time of closest approach to the center of the disk formatted as RFC3339
+    time: String,
+    /// Time in front of the disk in seconds
+    duration_s: f64,
+    /// Smallest angular distance from the center of the disk in degrees
+    centerline_distance: f64,
+    /// Angular radius of the disk in degrees
+    body_radius: f64,
+    /// Azimuth angle in degrees at `time`
+    azimuth: f64,
+    /// Elevation angle in degrees at `time`
+    elevation: f64,
+    /// Slant range in km at `time`
+    range_km: f64,
+}
+
+/// Get solar and lunar transit predictions.
+///
+/// Predicts the times at which satellites cross the disk of the Sun or the Moon as seen from the
+/// station, e.g. for transit photography.
+#[utoipa::path(
+    get,
+    path = "/predict/transits",
+    tag = super::PREDICT_TAG,
+    params(PredictQuery, TransitQuery),
+    responses(
+        (status = 200, description = "Transit predictions", body = TransitPredictions),
+        (status = 400, description = "Invalid parameters"),
+    ),
+)]
+pub async fn get_transits(
+    State(state): State<AppState>,
+    Query(query): Query<PredictQuery>,
+    Query(transit): Query<TransitQuery>,
+) -> Result<Json<TransitPredictions>, ApiError> {
+    let start = query.start.unwrap_or_else(Utc::now);
+    let end = query.end.unwrap_or_else(|| start + Duration::hours(24));
+
+    if end <= start {
+        return Err(ApiError::BadRequest("end must be after start".to_string()));
+    }
+
+    let gs = query.ground_station(&state)?;
+    let bodies = match transit.body {
+        Some(body) => vec![body],
+        None => vec![TransitBody::Sun, TransitBody::Moon],
+    };
+
+    let predict_db = state.predict_db.lock().await;
+    let predictions = predict_db
+        .predict_transits_filtered(start, end, gs, &bodies, |name, sat| {
+            query.includes(name, sat)
+        })
+        .into_iter()
+        .filter(|(_, transits)| !transits.is_empty())
+        .map(|(id, transits)| {
+            let transits = transits
+                .into_iter()
+                .map(|transit| ApiTransit {
+                    body: transit.body,
+                    time: to_datetime(transit.time).to_rfc3339(),
+                    duration_s: transit.duration,
+                    centerline_distance: transit.centerline_distance.to_degrees(),
+                    body_radius: transit.body_radius.to_degrees(),
+                    azimuth: transit.observables.azimuth().to_degrees(),
+                    elevation: transit.observables.elevation().to_degrees(),
+                    range_km: transit.observables.range() / 1000.0,
+                })
+                .collect();
+            (id.to_string(), transits)
+        })
+        .collect();
+
+    Ok(Json(TransitPredictions { predictions }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct StatsQuery {
+    /// NORAD ID of the satellite.
+    pub norad_id: u64,
+    /// Length of the window in days. Defaults to 7, at most 31.
+    pub days: Option<f64>,
+    /// Start time as RFC3339. Defaults to now.
+    #[param(value_type = Option<String>)]
+    pub start: Option<DateTime<Utc>>,
+    /// Predict passes over this station (one of the configured `stations`) instead of ours.
+    pub station: Option<String>,
+}
+
+const MAX_STATS_DAYS: f64 = 31.0;
+
+/// Width (degrees) of the bins of the maximum elevation histogram.
+const ELEVATION_BIN_WIDTH: f64 = 10.0;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VisibilityStats {
+    satellite: String,
+    norad_id: u64,
+    /// Start of the window formatted as RFC3339
+    start: String,
+    /// End of the window formatted as RFC3339
+    end: String,
+    /// Number of passes in the window
+    passes: usize,
+    /// Total time visible in minutes
+    contact_minutes: f64,
+    /// Mean pass duration in minutes
+    mean_pass_minutes: f64,
+    /// Number of passes by maximum elevation, in 10° bins from 0° to 90°
+    max_elevation_histogram: Vec<ApiElevationBin>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiElevationBin {
+    /// Lower bound of the bin in degrees
+    min_elevation: f64,
+    /// Upper bound of the bin in degrees
+    max_elevation: f64,
+    passes: usize,
+}
+
+/// Get visibility statistics of a satellite.
+///
+/// Summarizes the passes of the satellite over a window: number of passes, total contact time
+/// and a histogram of their maximum elevations.
+#[utoipa::path(
+    get,
+    path = "/predict/stats",
+    tag = super::PREDICT_TAG,
+    params(StatsQuery),
+    responses(
+        (status = 200, description = "Visibility statistics", body = VisibilityStats),
+        (status = 400, description = "Invalid parameters"),
+        (status = 404, description = "Satellite not found"),
+    ),
+)]
+pub async fn get_stats(
+    State(state): State<AppState>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<VisibilityStats>, ApiError> {
+    let days = query.days.unwrap_or(7.0);
+    if !(days > 0.0 && days <= MAX_STATS_DAYS) {
+        return Err(ApiError::BadRequest(format!(
+            "days must be between 0 and {MAX_STATS_DAYS}"
+        )));
+    }
+    let start = query.start.unwrap_or_else(Utc::now);
+    let end = start + Duration::seconds((days * 86400.0) as i64);
+    let gs = ground_station(&state, query.station.as_deref())?;
+
+    let predict_db = state.predict_db.lock().await;
+    let (name, _) = predict_db
+        .find(&query.norad_id.to_string())
+        .ok_or(ApiError::NotFound)?;
+
+    let passes: Vec<PredictedPass> = predict_db
+        .predict_passes_filtered(start, end, gs, None, |n, _| n == name)
+        .into_values()
+        .flatten()
+        .collect();
+
+    let bins = (90.0 / ELEVATION_BIN_WIDTH) as usize;
+    let mut histogram: Vec<ApiElevationBin> = (0..bins)
+        .map(|i| ApiElevationBin {
+            min_elevation: i as f64 * ELEVATION_BIN_WIDTH,
+            max_elevation: (i + 1) as f64 * ELEVATION_BIN_WIDTH,
+            passes: 0,
+        })
+        .collect();
+    let mut contact_seconds = 0.0;
+    for predicted in &passes {
+        let interval = predicted.pass.interval();
+        contact_seconds += (to_datetime(interval.end()) - to_datetime(interval.start()))
+            .num_milliseconds() as f64
+            / 1000.0;
+        let bin = (predicted.max_elevation.to_degrees() / ELEVATION_BIN_WIDTH).floor();
+        histogram[(bin.max(0.0) as usize).min(bins - 1)].passes += 1;
+    }
+    let contact_minutes = contact_seconds / 60.0;
+
+    Ok(Json(VisibilityStats {
+        satellite: name.clone(),
+        norad_id: query.norad_id,
+        start: start.to_rfc3339(),
+        end: end.to_rfc3339(),
+        passes: passes.len(),
+        contact_minutes,
+        mean_pass_minutes: if passes.is_empty() {
+            0.0
+        } else {
+            contact_minutes / passes.len() as f64
+        },
+        max_elevation_histogram: histogram,
+    }))
+}
+
+/// Returns the element set age (days) of every satellite at `start`, and a warning for each one
+/// older than the configured maximum.
+fn element_ages(
+    state: &AppState,
+    predict_db: &PredictDb,
+    start: DateTime<Utc>,
+) -> (HashMap<String, f64>, Vec<String>) {
+    let max_age = state.config.predict.max_element_age_days;
+    let mut warnings = Vec::new();
+    let ages = predict_db
+        .iter()
+        .map(|(name, sat)| {
+            let age = sat.element_age(start).num_seconds() as f64 / 86400.0;
+            if age.abs() > max_age {
+                warn!(?name, age_days = age, "stale element set");
+                warnings.push(format!(
+                    "elements for {name} are {age:.1} days from the prediction start; \
+                     times may be inaccurate"
+                ));
+            }
+            (name.clone(), age)
+        })
+        .collect();
+    warnings.sort();
+
+    (ages, warnings)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "template_id": "noaa_apt",
+    "satellite": "NOAA 19",
+    "aos": "2026-06-01T10:02:00Z",
+    "variables": {"frequency": "137.1e6"},
+    "task_id": "noaa19-2026-06-01"
+}))]
+pub struct ScheduleFromPassRequest {
+    /// Satellite name or NORAD ID
+    pub satellite: String,
+    #[serde(flatten)]
+    pub pass: SchedulePassRequest,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "template_id": "noaa_apt",
+    "aos": "2026-06-01T10:02:00Z",
+    "variables": {"frequency": "137.1e6"},
+    "task_id": "noaa19-2026-06-01"
+}))]
+pub struct SchedulePassRequest {
+    /// Template used to render the task
+    pub template_id: String,
+    /// Start of the pass (AOS) formatted as RFC3339. The predicted pass starting closest to this
+    /// time is used.
+    #[schema(value_type = String)]
+    pub aos: DateTime<Utc>,
+    /// Additional variables (e.g. frequencies), overriding the ones derived from the pass
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// If given, the rendered task is also submitted with this ID
+    pub task_id: Option<String>,
+}
+
+/// Render a task for a predicted pass of a satellite given by name or NORAD ID.
+///
+/// Same as `POST /predict/{norad_id}/schedule`, with the satellite in the body.
+#[utoipa::path(
+    post,
+    path = "/predict/schedule",
+    tag = super::PREDICT_TAG,
+    request_body = ScheduleFromPassRequest,
+    responses(
+        (status = 200, description = "Rendered task YAML", body = String),
+        (status = 201, description = "Task created from pass", body = String),
+        (status = 400, description = "Invalid request"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Template, satellite or pass not found"),
+        (status = 409, description = "Task already exists or has a time conflict"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn schedule_pass(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Json(req): Json<ScheduleFromPassRequest>,
+) -> Result<(StatusCode, String), ApiError> {
+    schedule(&state, &auth, &req.satellite, req.pass).await
+}
+
+/// Render a task for a predicted pass of a satellite.
+///
+/// Finds the pass of the given satellite starting closest to `aos` and renders the template with
+/// the `start`, `end`, `tle`, `satellite` and `norad_id` variables filled in from the pass, and
+/// `rx_frequency` and `tx_frequency` from the frequencies configured for the satellite.
+/// Variables declared in the template act as defaults, and variables given in the request
+/// override all others. The parameters of the template are checked as when submitting from it.
+///
+/// Returns the rendered task YAML. If `task_id` is given, the task is also submitted: it is
+/// placed in PendingApproval unless the API key has AutoApproveTask permission.
+#[utoipa::path(
+    post,
+    path = "/predict/{norad_id}/schedule",
+    tag = super::PREDICT_TAG,
+    params(("norad_id" = u32, Path, description = "NORAD ID of the satellite")),
+    request_body = SchedulePassRequest,
+    responses(
+        (status = 200, description = "Rendered task YAML", body = String),
+        (status = 201, description = "Task created from pass", body = String),
+        (status = 400, description = "Invalid request"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Template, satellite or pass not found"),
+        (status = 409, description = "Task already exists or has a time conflict"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn schedule_satellite_pass(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Path(norad_id): Path<u32>,
+    Json(req): Json<SchedulePassRequest>,
+) -> Result<(StatusCode, String), ApiError> {
+    schedule(&state, &auth, &norad_id.to_string(), req).await
+}
+
+async fn schedule(
+    state: &AppState,
+    auth: &AuthenticatedKey,
+    satellite: &str,
+    req: SchedulePassRequest,
+) -> Result<(StatusCode, String), ApiError> {
+    auth.require(Permission::SubmitFromTemplate)?;
+
+    let (template, _) = read_template(state, &req.template_id).await?;
+
+    let gs = state
+        .config
+        .ground_station
+        .as_ref()
+        .ok_or(ApiError::Internal)?;
+
+    let mut task = {
+        let predict_db = state.predict_db.lock().await;
+        let (name, sat) = predict_db.find(satellite).ok_or(ApiError::NotFound)?;
+
+        let window = Duration::hours(1);
+        let (start, end) = predict_db
+            .predict_passes_filtered(req.aos - window, req.aos + window, gs, None, |n, _| {
+                n == name
+            })
+            .into_values()
+            .flatten()
+            .map(|predicted| {
+                let interval = predicted.pass.interval();
+                (to_datetime(interval.start()), to_datetime(interval.end()))
+            })
+            .min_by_key(|(start, _)| (*start - req.aos).abs())
+            .ok_or(ApiError::NotFound)?;
+
+        let mut variables = pass_variables(name, sat, start, end, &state.config.predict);
+        variables.extend(req.variables);
+        template.render(variables, &predict_db)?
+    };
+    task.owner = Some(auth.owner.clone());
+    task.template = Some(req.template_id.clone());
+    let yaml = serde_yaml::to_string(&task).map_err(|_| ApiError::Internal)?;
+
+    let Some(task_id) = &req.task_id else {
+        return Ok((StatusCode::OK, yaml));
+    };
+
+    let auto_approve = auth.has(Permission::AutoApproveTask);
+    let target_dir = submit_task(state, auto_approve, task_id, &task).await?;
+    info!(%task_id, template_id = %req.template_id, %target_dir, "task created from pass");
+    Ok((StatusCode::CREATED, yaml))
+}
+
+/// Returns the task variables describing a pass of `sat` from `start` to `end`, with the frequencies
+/// of `sat` configured in `predict`.
+pub fn pass_variables(
+    name: &str,
+    sat: &Satellite,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    predict: &PredictConfig,
+) -> HashMap<String, String> {
+    let mut variables = HashMap::from([
+        ("start".into(), start.to_rfc3339()),
+        ("end".into(), end.to_rfc3339()),
+        ("tle".into(), sat.orbit_text()),
+        ("satellite".into(), name.to_string()),
+        ("norad_id".into(), sat.elements.norad_id.to_string()),
+    ]);
+    if let Some(frequencies) = predict.frequencies_of(name, sat.elements.norad_id) {
+        for (variable, frequency) in [
+            ("rx_frequency", frequencies.rx),
+            ("tx_frequency", frequencies.tx),
+        ] {
+            if let Some(frequency) = frequency {
+                variables.insert(variable.into(), frequency.to_string());
+            }
+        }
+    }
+    variables
+}
+
+pub fn to_datetime(time: Time<DynTimeScale>) -> DateTime<Utc> {
+    DateTime::<Utc>::try_from(time.to_utc()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use chrono::{DateTime, Duration};
+    use http_body_util::BodyExt;
+    use lox_space::{
+        analysis::visibility::ElevationMask,
+        bodies::DynOrigin,
+        core::coords::LonLatAlt,
+        prelude::{GroundLocation, GroundStation},
+    };
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use super::{ApiPass, PASS_FIELDS};
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission, PredictConfig, SatelliteFrequencies};
+
+    const TEMPLATE_YAML: &str = "\
+variables:
+  downlink: \"437.5 MHz\"
+steps:
+  - cmd: \"echo $satellite\"
+    wait: true
+";
+
+    fn test_config(tmp: &TempDir, permissions: Vec<Permission>) -> Config {
+        let coords = LonLatAlt::from_degrees(13.4, 52.52, 100.0).unwrap();
+        let location = GroundLocation::try_new(coords, DynOrigin::Earth).unwrap();
+        Config {
+            ground_station: Some(GroundStation::new(
+                "GS",
+                location,
+                ElevationMask::with_fixed_elevation(0.0),
+            )),
+            stations: HashMap::from([(
+                "santiago".to_string(),
+                GroundStation::new(
+                    "santiago",
+                    GroundLocation::try_new(
+                        LonLatAlt::from_degrees(-70.6, -33.4, 500.0).unwrap(),
+                        DynOrigin::Earth,
+                    )
+                    .unwrap(),
+                    ElevationMask::with_fixed_elevation(0.0),
+                ),
+            )]),
+            predict: PredictConfig {
+                frequencies: [(
+                    "58810".to_string(),
+                    SatelliteFrequencies {
+                        rx: Some(437_500_000),
+                        tx: None,
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            },
+            ..Config::for_test(
+                tmp.path(),
+                vec![ApiKey {
+                    name: None,
+                    id: None,
+                    key: "test-key".into(),
+                    permissions,
+                }],
+            )
+        }
+    }
+
+    fn setup(permissions: Vec<Permission>) -> (TempDir, axum::Router) {
+        let tmp = tempfile::tempdir().unwrap();
+        for dir in [
+            "Active",
+            "PendingApproval",
+            "Completed",
+            "Failed",
+            "Templates",
+            "tle",
+        ] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        let tle_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle");
+        std::fs::copy(
+            tle_dir.join("nanoff_a.txt"),
+            tmp.path().join("tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        std::fs::write(tmp.path().join("Templates/uhf.yaml"), TEMPLATE_YAML).unwrap();
+
+        let config = test_config(&tmp, permissions);
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+        (tmp, router)
+    }
+
+    async fn response_body(router: axum::Router, req: Request<Body>) -> (StatusCode, String) {
+        let resp = router.oneshot(req).await.unwrap();
+        let status = resp.status();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    /// Returns the start time of the first predicted pass of NanoFF A.
+    async fn first_pass_start(router: axum::Router) -> String {
+        let (status, body) = response_body(
+            router,
+            Request::get("/api/predict/passes?start=2026-01-15T00:00:00Z&end=2026-01-15T12:00:00Z")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        json["predictions"]["NanoFF A"][0]["start"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn schedule_request(satellite: &str, aos: &str, task_id: Option<&str>) -> Request<Body> {
+        let mut body = schedule_body(aos, task_id);
+        body["satellite"] = satellite.into();
+        Request::post("/api/predict/schedule")
+            .header("api_key", "test-key")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn schedule_body(aos: &str, task_id: Option<&str>) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "template_id": "uhf",
+            "aos": aos,
+            "variables": { "uplink": "145.9 MHz" },
+        });
+        if let Some(task_id) = task_id {
+            body["task_id"] = task_id.into();
+        }
+        body
+    }
+
+    #[tokio::test]
+    async fn list_satellites_returns_epoch_and_source() {
+        let (_tmp, router) = setup(vec![]);
+        let req = Request::get("/api/predict/satellites")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = response_body(router, req).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let sat = &json["satellites"][0];
+        assert_eq!(sat["name"], "NanoFF A");
+        assert_eq!(sat["norad_id"], 58810);
+        assert!(sat["epoch"].as_str().unwrap().starts_with("2026-01-14T"));
+        assert_eq!(sat["regime"], "leo");
+        assert_eq!(sat["groups"], serde_json::json!(["nanoff_a"]));
+        assert!(sat["source"].as_str().unwrap().ends_with("nanoff_a.txt"));
+    }
+
+    #[tokio::test]
+    async fn passes_include_element_age_and_stale_warning() {
+        let (_tmp, router) = setup(vec![]);
+
+        let req = |start: &str| {
+            Request::get(format!("/api/predict/passes?start={start}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // Elements epoch is 2026-01-14
+        let (status, body) = response_body(router.clone(), req("2026-01-15T00:00:00Z")).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let age = json["element_age_days"]["NanoFF A"].as_f64().unwrap();
+        assert!((0.0..1.0).contains(&age), "age = {age}");
+        assert_eq!(json["warnings"].as_array().unwrap().len(), 0);
+
+        let (_, body) = response_body(router, req("2026-02-15T00:00:00Z")).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let warnings = json["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].as_str().unwrap().contains("NanoFF A"));
+    }
+
+    #[tokio::test]
+    async fn passes_include_doppler_for_frequency() {
+        let (_tmp, router) = setup(vec![]);
+        let uri = "/api/predict/passes?start=2026-01-15T00:00:00Z&end=2026-01-15T12:00:00Z";
+
+        let (_, body) = response_body(
+            router.clone(),
+            Request::get(uri).body(Body::empty()).unwrap(),
+        )
+        .await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let pass = &json["predictions"]["NanoFF A"][0];
+        let range = pass["range_at_tca_km"].as_f64().unwrap();
+        // LEO at ~500 km: the range at TCA is between the altitude and the horizon distance
+        assert!((400.0..2600.0).contains(&range), "range = {range}");
+        assert!(pass["max_doppler_hz"].is_null());
+
+        let (_, body) = response_body(
+            router,
+            Request::get(format!("{uri}&frequency=437500000"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let pass = &json["predictions"]["NanoFF A"][0];
+        // At most v/c * f, with v < 8 km/s
+        let doppler = pass["max_doppler_hz"].as_f64().unwrap();
+        assert!((1000.0..12000.0).contains(&doppler), "doppler = {doppler}");
+        let rate = pass["max_doppler_rate_hz_s"].as_f64().unwrap();
+        assert!((0.0..200.0).contains(&rate), "rate = {rate}");
+    }
+
+    #[tokio::test]
+    async fn passes_with_selected_fields() {
+        let (_tmp, router) = setup(vec![]);
+        let uri = "/api/predict/passes?start=2026-01-15T00:00:00Z&end=2026-01-15T12:00:00Z";
+
+        let (status, body) = response_body(
+            router.clone(),
+            Request::get(format!("{uri}&fields=start,max_elevation"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let pass = json["predictions"]["NanoFF A"][0].as_object().unwrap();
+        assert_eq!(
+            pass.keys().collect::<Vec<_>>(),
+            vec!["max_elevation", "start"]
+        );
+        assert!(json["total_passes"].as_u64().unwrap() > 0);
+
+        let (status, body) = response_body(
+            router,
+            Request::get(format!("{uri}&fields=start,azimuth_deg"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("unknown field azimuth_deg"));
+    }
+
+    #[tokio::test]
+    async fn large_responses_are_compressed() {
+        use std::io::Read;
+
+        let (_tmp, router) = setup(vec![]);
+        let uri = "/api/predict/ground_track?start=2026-01-15T00:00:00Z&end=2026-01-15T06:00:00Z";
+
+        let resp = router
+            .oneshot(
+                Request::get(uri)
+                    .header("accept-encoding", "gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["content-encoding"], "gzip");
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+
+        let mut body = String::new();
+        flate2::read::GzDecoder::new(&bytes[..])
+            .read_to_string(&mut body)
+            .unwrap();
+        assert!(body.len() > bytes.len());
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(json["predictions"]["NanoFF A"]["latitude"].is_array());
+    }
+
+    #[test]
+    fn pass_fields_are_selectable() {
+        let pass = ApiPass {
+            start: String::new(),
+            end: String::new(),
+            max_elevation: 0.0,
+            tca: String::new(),
+            geometric_start: String::new(),
+            geometric_end: String::new(),
+            geometric_max_elevation: 0.0,
+            range_at_tca_km: 0.0,
+            max_doppler_hz: None,
+            max_doppler_rate_hz_s: None,
+            azimuth: Vec::new(),
+            elevation: Vec::new(),
+            warnings: Vec::new(),
+        };
+        let value = serde_json::to_value(pass).unwrap();
+        let mut fields: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        let mut expected = PASS_FIELDS.to_vec();
+        fields.sort();
+        expected.sort();
+        assert_eq!(fields, expected);
+    }
+
+    #[tokio::test]
+    async fn satellites_reloaded_through_the_api() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("tle")).unwrap();
+        let state = api::state(&test_config(&tmp, vec![Permission::ReloadSatellites]));
+        let (router, _) = api::routes(state.clone()).split_for_parts();
+        assert!(state.predict_db.lock().await.is_empty());
+
+        let tle_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle");
+        std::fs::copy(
+            tle_dir.join("nanoff_a.txt"),
+            tmp.path().join("tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        let reload = |key: &'static str| {
+            Request::post("/api/v1/predict/satellites/reload")
+                .header("api_key", key)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (status, body) = response_body(router.clone(), reload("test-key")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"satellites":1}"#);
+        assert!(state.predict_db.lock().await.contains("NanoFF A"));
+
+        let (status, _) = response_body(router, reload("other-key")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn satellites_reloaded_when_tles_change() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("tle")).unwrap();
+        let state = api::state(&test_config(&tmp, vec![]));
+        let watching = tokio::spawn(super::watch_satellites(state.clone()));
+        // Let the watcher start
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let tle_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle");
+        std::fs::copy(
+            tle_dir.join("nanoff_a.txt"),
+            tmp.path().join("tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !state.predict_db.lock().await.contains("NanoFF A") {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+        watching.abort();
+    }
+
+    #[tokio::test]
+    async fn passes_filtered_by_group() {
+        let (_tmp, router) = setup(vec![]);
+        let passes = |group: &'static str| {
+            let router = router.clone();
+            async move {
+                let uri = format!(
+                    "/api/predict/passes?start=2026-01-15T00:00:00Z&end=2026-01-15T12:00:00Z&group={group}"
+                );
+                let (status, body) =
+                    response_body(router, Request::get(uri).body(Body::empty()).unwrap()).await;
+                assert_eq!(status, StatusCode::OK);
+                let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+                json["predictions"].as_object().unwrap().len()
+            }
+        };
+
+        assert_eq!(passes("nanoff_a").await, 1);
+        assert_eq!(passes("noaa").await, 0);
+    }
+
+    #[tokio::test]
+    async fn passes_filtered_by_satellite() {
+        let (_tmp, router) = setup(vec![]);
+        let passes = |filter: &'static str| {
+            let router = router.clone();
+            async move {
+                let uri = format!(
+                    "/api/predict/passes?start=2026-01-15T00:00:00Z&end=2026-01-15T12:00:00Z&{filter}"
+                );
+                let (status, body) =
+                    response_body(router, Request::get(uri).body(Body::empty()).unwrap()).await;
+                assert_eq!(status, StatusCode::OK);
+                let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+                json["predictions"].as_object().unwrap().len()
+            }
+        };
+
+        assert_eq!(passes("norad_id=58810").await, 1);
+        assert_eq!(passes("norad_id=25544").await, 0);
+        assert_eq!(passes("name=nanoff%20a").await, 1);
+        assert_eq!(passes("name=NanoFF%20B").await, 0);
+        assert_eq!(passes("name=nanoff&norad_id=25544").await, 0);
+    }
+
+    #[tokio::test]
+    async fn passes_paginated() {
+        let (tmp, _) = setup(vec![]);
+        std::fs::copy(
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle/nanoff_b.txt"),
+            tmp.path().join("tle/nanoff_b.txt"),
+        )
+        .unwrap();
+        let (router, _) = api::routes(api::state(&test_config(&tmp, vec![]))).split_for_parts();
+
+        let passes = |params: &'static str| {
+            let router = router.clone();
+            async move {
+                let uri = format!(
+                    "/api/predict/passes?start=2026-01-15T00:00:00Z&end=2026-01-16T00:00:00Z&{params}"
+                );
+                let (status, body) =
+                    response_body(router, Request::get(uri).body(Body::empty()).unwrap()).await;
+                assert_eq!(status, StatusCode::OK);
+                serde_json::from_str::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        let count = |json: &serde_json::Value| {
+            json["predictions"]
+                .as_object()
+                .unwrap()
+                .values()
+                .map(|p| p.as_array().unwrap().len())
+                .sum::<usize>()
+        };
+
+        let all = passes("").await;
+        let total = all["total_passes"].as_u64().unwrap() as usize;
+        assert_eq!(count(&all), total);
+        assert_eq!(all["total_satellites"], 2);
+        assert!(total > 4);
+
+        let page = passes("offset=1&limit=3").await;
+        assert_eq!(page["total_passes"], total);
+        assert_eq!(count(&page), 3);
+
+        let capped = passes("max_passes_per_satellite=1").await;
+        assert_eq!(capped["total_passes"], 2);
+        for sat_passes in capped["predictions"].as_object().unwrap().values() {
+            assert_eq!(sat_passes.as_array().unwrap().len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_for_remote_station() {
+        let (_tmp, router) = setup(vec![]);
+        let passes = |station: &'static str| {
+            let router = router.clone();
+            async move {
+                let uri = format!(
+                    "/api/predict/passes?start=2026-01-15T00:00:00Z&end=2026-01-15T12:00:00Z{station}"
+                );
+                response_body(router, Request::get(uri).body(Body::empty()).unwrap()).await
+            }
+        };
+        let first_start = |body: &str| {
+            let json: serde_json::Value = serde_json::from_str(body).unwrap();
+            json["predictions"]["NanoFF A"][0]["start"].clone()
+        };
+
+        let (_, local) = passes("").await;
+        let (status, remote) = passes("&station=santiago").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(first_start(&remote).is_string());
+        assert_ne!(first_start(&local), first_start(&remote));
+
+        let (status, _) = passes("&station=nowhere").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn transits_over_remote_station() {
+        let (tmp, _) = setup(vec![]);
+        let mut config = test_config(&tmp, vec![]);
+        config.stations.insert(
+            "trento".to_string(),
+            GroundStation::new(
+                "trento",
+                GroundLocation::try_new(
+                    LonLatAlt::from_degrees(12.0, 46.0, 0.0).unwrap(),
+                    DynOrigin::Earth,
+                )
+                .unwrap(),
+                ElevationMask::with_fixed_elevation(0.0),
+            ),
+        );
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+
+        let (status, body) = response_body(
+            router,
+            Request::get(
+                "/api/predict/transits?station=trento&body=sun\
+                 &start=2026-01-15T08:50:00Z&end=2026-01-15T09:20:00Z",
+            )
+            .body(Body::empty())
+            .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let transits = json["predictions"]["NanoFF A"].as_array().unwrap();
+        assert_eq!(transits.len(), 1);
+        assert_eq!(transits[0]["body"], "sun");
+        let distance = transits[0]["centerline_distance"].as_f64().unwrap();
+        assert!(distance < transits[0]["body_radius"].as_f64().unwrap());
+    }
+
+    #[tokio::test]
+    async fn stats_summarize_passes() {
+        let (_tmp, router) = setup(vec![]);
+        let (status, body) = response_body(
+            router,
+            Request::get("/api/predict/stats?norad_id=58810&days=2&start=2026-01-15T00:00:00Z")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["satellite"], "NanoFF A");
+        let passes = json["passes"].as_u64().unwrap();
+        assert!(passes > 0);
+        let histogram = json["max_elevation_histogram"].as_array().unwrap();
+        assert_eq!(histogram.len(), 9);
+        let binned: u64 = histogram
+            .iter()
+            .map(|b| b["passes"].as_u64().unwrap())
+            .sum();
+        assert_eq!(binned, passes);
+        let contact = json["contact_minutes"].as_f64().unwrap();
+        let mean = json["mean_pass_minutes"].as_f64().unwrap();
+        assert!((contact - mean * passes as f64).abs() < 1e-6);
+        assert!(mean > 1.0 && mean < 20.0, "mean = {mean}");
+    }
+
+    #[tokio::test]
+    async fn observables_follow_a_pass() {
+        let (_tmp, router) = setup(vec![]);
+        let aos = first_pass_start(router.clone()).await;
+        let (status, body) = response_body(
+            router.clone(),
+            Request::get(format!(
+                "/api/predict/observables?satellite=58810&frequency=437000000&time={}",
+                aos.replace('+', "%2B")
+            ))
+            .body(Body::empty())
+            .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["satellite"], "NanoFF A");
+        assert_eq!(json["norad_id"], 58810);
+        assert!(json["elevation"].as_f64().unwrap().abs() < 1.0);
+        // Approaching at AOS: closing range, positive Doppler shift
+        let range_rate = json["range_rate_km_s"].as_f64().unwrap();
+        assert!(range_rate < -1.0, "range rate = {range_rate}");
+        let doppler = json["doppler_hz"].as_f64().unwrap();
+        assert!(
+            (doppler + 437e6 * range_rate * 1000.0 / 299_792_458.0).abs() < 1e-3,
+            "doppler = {doppler}"
+        );
+
+        let (status, _) = response_body(
+            router,
+            Request::get("/api/predict/observables?satellite=unknown")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn observables_are_streamed() {
+        let (_tmp, router) = setup(vec![]);
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/predict/observables/stream?satellite=58810&interval=0.1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The stream does not end, read until two samples arrived
+        let mut body = response.into_body();
+        let mut received = String::new();
+        while received.matches("event: sample").count() < 2 {
+            let frame = body.frame().await.unwrap().unwrap();
+            if let Ok(data) = frame.into_data() {
+                received.push_str(std::str::from_utf8(&data).unwrap());
+            }
+        }
+        let events: Vec<&str> = received.split("\n\n").collect();
+        assert!(events[0].starts_with("event: elements\n"), "{received}");
+        assert!(events[0].contains("\"norad_id\":58810"));
+        assert!(events[1].starts_with("event: sample\n"));
+        assert!(events[1].contains("\"satellite\":\"NanoFF A\""));
+        assert!(events[2].starts_with("event: sample\n"));
+
+        for query in ["satellite=unknown", "satellite=58810&interval=0"] {
+            let (status, _) = response_body(
+                router.clone(),
+                Request::get(format!("/api/predict/observables/stream?{query}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+            assert_ne!(status, StatusCode::OK, "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn stats_unknown_satellite_returns_404() {
+        let (_tmp, router) = setup(vec![]);
+        let (status, _) = response_body(
+            router,
+            Request::get("/api/predict/stats?norad_id=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn stats_reject_too_many_days() {
+        let (_tmp, router) = setup(vec![]);
+        let (status, _) = response_body(
+            router,
+            Request::get("/api/predict/stats?norad_id=58810&days=365")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn schedule_pass_renders_task() {
+        let (_tmp, router) = setup(vec![Permission::SubmitFromTemplate]);
+        let aos = first_pass_start(router.clone()).await;
+
+        let (status, body) = response_body(router, schedule_request("58810", &aos, None)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let task = crate::task::format::Task::from_yaml_str(&body).unwrap();
+        // The pass is predicted again over a different window, so AOS may differ slightly
+        let start = DateTime::parse_from_rfc3339(&task.variables["start"]).unwrap();
+        let aos = DateTime::parse_from_rfc3339(&aos).unwrap();
+        assert!((start - aos).abs() < Duration::seconds(1));
+        assert!(task.variables.contains_key("end"));
+        assert!(task.variables["tle"].starts_with("NanoFF A\n1 58810U"));
+        assert_eq!(task.variables["satellite"], "NanoFF A");
+        assert_eq!(task.variables["downlink"], "437.5 MHz");
+        assert_eq!(task.variables["uplink"], "145.9 MHz");
+        // Configured by NORAD ID
+        assert_eq!(task.variables["rx_frequency"], "437500000");
+        assert!(!task.variables.contains_key("tx_frequency"));
+    }
+
+    #[tokio::test]
+    async fn schedule_pass_submits_task() {
+        let (tmp, router) = setup(vec![Permission::SubmitFromTemplate]);
+        let aos = first_pass_start(router.clone()).await;
+
+        let (status, _) =
+            response_body(router, schedule_request("NanoFF A", &aos, Some("pass1"))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(tmp.path().join("PendingApproval/pass1.yaml").exists());
+    }
+
+    #[tokio::test]
+    async fn schedule_pass_unknown_satellite_returns_404() {
+        let (_tmp, router) = setup(vec![Permission::SubmitFromTemplate]);
+        let (status, _) = response_body(
+            router,
+            schedule_request("nope", "2026-01-15T00:00:00Z", None),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn schedule_pass_of_norad_id_in_path() {
+        let (tmp, router) = setup(vec![Permission::SubmitFromTemplate]);
+        let aos = first_pass_start(router.clone()).await;
+        let request = |norad_id: &str| {
+            Request::post(format!("/api/predict/{norad_id}/schedule"))
+                .header("api_key", "test-key")
+                .header("content-type", "application/json")
+                .body(Body::from(schedule_body(&aos, Some("pass1")).to_string()))
+                .unwrap()
+        };
+
+        let (status, body) = response_body(router.clone(), request("58810")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let task = crate::task::format::Task::from_yaml_str(&body).unwrap();
+        assert_eq!(task.variables["satellite"], "NanoFF A");
+        assert!(tmp.path().join("PendingApproval/pass1.yaml").exists());
+
+        let (status, _) = response_body(router.clone(), request("99999")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = response_body(router, request("NanoFF")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn schedule_pass_without_permission_returns_403() {
+        let (_tmp, router) = setup(vec![Permission::ViewTasks]);
+        let (status, _) = response_body(
+            router,
+            schedule_request("NanoFF A", "2026-01-15T00:00:00Z", None),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
 }
@@ -0,0 +1,271 @@
+//! Live power spectrum of the SDR streams of the runs in progress, for the waterfall of the web UI.
+//!
+//! The streams run as steps of the runs, in their own process. Their latest spectrum is read from
+//! the [`SPECTRUM_FILE`] they keep in the artifacts of the run.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use axum::extract::ws::{
+    Message, WebSocket, WebSocketUpgrade, rejection::WebSocketUpgradeRejection,
+};
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use sat_o_mat::radio::spectrum::SPECTRUM_FILE;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use super::runs::{ARTIFACTS_DIR, require_view};
+use crate::task::format::Task;
+
+/// How often the spectrum file is checked for a new spectrum.
+const SPECTRUM_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct FftQuery {
+    /// Task ID of the run to follow. The first run in progress keeping a spectrum if unset.
+    pub task: Option<String>,
+    /// API key or token, for browsers, which cannot set headers on WebSockets.
+    #[allow(dead_code)] // Read when authenticating the request
+    pub token: Option<String>,
+}
+
+/// The run whose spectrum a stream follows, from the variables of its task.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiSpectrumRun {
+    /// Task ID of the run
+    task: String,
+    /// The `satellite` variable of the task, if set
+    satellite: Option<String>,
+    /// The `rx_frequency` variable of the task, the downlink frequency in Hz, if set
+    rx_frequency: Option<u64>,
+}
+
+/// Stream the power spectrum of an SDR.
+///
+/// Switches to a WebSocket sending the spectrum kept by a `sat-o-mat radio run --web-fft` step of
+/// a run in progress as a binary message each time it is computed, until the run is over. Each
+/// message holds, all little endian: the time (milliseconds since the Unix epoch, i64), the center
+/// frequency and the sample rate (Hz, u64), then the power (dBFS, f32) of each bin from the lowest
+/// frequency to the highest.
+///
+/// The first message is text instead: the run followed, as an `ApiSpectrumRun` in JSON, telling
+/// which satellite the spectrum should show and at which frequency.
+#[utoipa::path(
+    get,
+    path = "/radio/fft",
+    tag = super::STATION_TAG,
+    params(FftQuery),
+    responses(
+        (status = 101, description = "Switching to the WebSocket"),
+        (status = 400, description = "Not a WebSocket request"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "No run in progress keeps a spectrum"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn stream_fft(
+    State(state): State<AppState>,
+    Query(query): Query<FftQuery>,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+    auth: AuthenticatedKey,
+) -> Result<Response, ApiError> {
+    let running = state.runs.statuses();
+    let mut candidates = running
+        .iter()
+        .map(|(id, _)| id)
+        .filter(|id| query.task.as_ref().is_none_or(|task| task == *id));
+    let (id, path) = loop {
+        let Some(id) = candidates.next() else {
+            return Err(ApiError::NotFound);
+        };
+        let path = state
+            .tasks_path
+            .join(ARTIFACTS_DIR)
+            .join(id)
+            .join(SPECTRUM_FILE);
+        if tokio::fs::metadata(&path).await.is_ok() {
+            break (id.clone(), path);
+        }
+    };
+    require_view(&state, &auth, &id).await?;
+
+    let ws = match ws {
+        Ok(ws) => ws,
+        Err(rejection) => return Ok(rejection.into_response()),
+    };
+    Ok(ws.on_upgrade(move |socket| send_spectra(socket, state, id, path)))
+}
+
+/// The run of task `id`, with the satellite and downlink frequency of its task.
+async fn spectrum_run(state: &AppState, id: &str) -> ApiSpectrumRun {
+    let task = Task::find(&state.tasks_path, id)
+        .await
+        .and_then(|(_, yaml)| Task::from_yaml_str(&yaml).ok());
+    let variable = |name| task.as_ref()?.variables.get(name).cloned();
+    ApiSpectrumRun {
+        task: id.to_string(),
+        satellite: variable("satellite"),
+        rx_frequency: variable("rx_frequency").and_then(|f| f.parse().ok()),
+    }
+}
+
+/// Sends the run of task `id`, then each new spectrum in `path` to `socket` until the run is over
+/// or the client goes away.
+async fn send_spectra(mut socket: WebSocket, state: AppState, id: String, path: PathBuf) {
+    let run = serde_json::to_string(&spectrum_run(&state, &id).await).unwrap_or_default();
+    if socket.send(Message::Text(run.into())).await.is_err() {
+        return;
+    }
+    let mut last = None;
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                // Nothing is expected from the client
+                Some(Ok(_)) => continue,
+            },
+            _ = tokio::time::sleep(SPECTRUM_POLL_INTERVAL) => {}
+        }
+
+        if state.runs.get(&id).is_none() {
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+        let Ok(spectrum) = tokio::fs::read(&path).await else {
+            continue;
+        };
+        if last.as_ref() == Some(&spectrum) {
+            continue;
+        }
+        if socket
+            .send(Message::Binary(spectrum.clone().into()))
+            .await
+            .is_err()
+        {
+            return;
+        }
+        last = Some(spectrum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use futures_util::StreamExt;
+    use sat_o_mat::radio::spectrum::Spectrum;
+    use sat_o_mat::scheduler;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    fn config(tmp: &tempfile::TempDir) -> Config {
+        let mut config = Config {
+            tasks_path: tmp.path().to_path_buf(),
+            tle_path: tmp.path().join("tle"),
+            ..Default::default()
+        };
+        config.api.keys = vec![ApiKey {
+            name: None,
+            id: None,
+            key: "test-key".into(),
+            permissions: vec![Permission::ViewTasks],
+        }];
+        config
+    }
+
+    #[tokio::test]
+    async fn spectra_need_a_run_keeping_them() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (router, _) = api::routes(api::state(&config(&tmp))).split_for_parts();
+
+        let req = Request::get("/api/v1/radio/fft")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = Request::get("/api/v1/radio/fft")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn spectra_are_streamed_while_the_run_is_in_progress() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = api::state(&config(&tmp));
+        let runs = state.runs.clone();
+        let (router, _) = api::routes(state).split_for_parts();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router).into_future());
+
+        std::fs::create_dir_all(tmp.path().join("Active")).unwrap();
+        std::fs::write(
+            tmp.path().join("Active/pass.yaml"),
+            "variables:\n  end: \"2099-01-01T00:00:00Z\"\n  satellite: NOAA 19\n  rx_frequency: \"137100000\"\nsteps:\n  - cmd: \"sleep 60\"\n    wait: true\n",
+        )
+        .unwrap();
+        let tasks_path = tmp.path().to_path_buf();
+        let scheduler_runs = runs.clone();
+        let scheduler = tokio::spawn(async move {
+            scheduler::run_until(
+                &tasks_path,
+                None,
+                scheduler_runs,
+                Default::default(),
+                std::future::pending(),
+            )
+            .await
+        });
+        while runs.get("pass").is_none() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let spectrum = Spectrum {
+            time: chrono::Utc::now(),
+            frequency: 137_100_000,
+            sample_rate: 1_024_000,
+            power: vec![-90.0, -40.5, -91.0],
+        };
+        let path = tmp.path().join("Artifacts/pass").join(SPECTRUM_FILE);
+        std::fs::write(&path, spectrum.to_bytes()).unwrap();
+
+        let url = format!("ws://{address}/api/v1/radio/fft?token=test-key");
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let mut next = async || {
+            tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap()
+        };
+        let message = next().await;
+        let tungstenite::Message::Text(run) = message else {
+            panic!("expected a text message, got {message:?}");
+        };
+        assert_eq!(
+            run.as_str(),
+            r#"{"task":"pass","satellite":"NOAA 19","rx_frequency":137100000}"#
+        );
+        let message = next().await;
+        let tungstenite::Message::Binary(bytes) = message else {
+            panic!("expected a binary message, got {message:?}");
+        };
+        assert_eq!(bytes.as_ref(), spectrum.to_bytes());
+
+        scheduler.abort();
+    }
+}
@@ -0,0 +1,489 @@
+//! Per-client request rate limiting.
+//!
+//! Requests are counted in fixed one-minute windows, per API key (or token subject) and per
+//! address for the unauthenticated requests. Task submissions and predictions are also counted
+//! against the quotas configured for them, in windows of their own period. Responses carry
+//! `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers for the limit closest to
+//! being reached, and requests over a limit are rejected with 429 and `Retry-After`.
+//!
+//! Failed authentications are counted per address too, see [`FailedLogins`].
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::config::{QuotaRequests, RateLimitConfig};
+
+use super::AppState;
+use super::auth::Authentication;
+use super::error::ApiError;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    /// Current window per limit and client.
+    windows: Mutex<HashMap<(Limit, Client), Window>>,
+}
+
+/// Who requests are counted for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    /// The owner of an API key or token
+    Key(String),
+    /// The address of unauthenticated requests, None if unknown
+    Address(Option<IpAddr>),
+}
+
+impl Client {
+    fn key(&self) -> Option<&str> {
+        match self {
+            Client::Key(key) => Some(key),
+            Client::Address(_) => None,
+        }
+    }
+}
+
+/// What requests are counted against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Limit {
+    /// The limit on all requests, per minute
+    Overall,
+    /// The quota at this index in the configuration
+    Quota(usize),
+}
+
+struct Window {
+    start: Instant,
+    length: Duration,
+    count: u32,
+}
+
+/// Outcome of counting a request.
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The limits on `requests` from `client`: the overall limit, and the quotas naming the client
+    /// or, if none does, those applying to every client.
+    fn limits(
+        &self,
+        client: &Client,
+        requests: Option<QuotaRequests>,
+    ) -> Vec<(Limit, u32, Duration)> {
+        let client = client.key();
+        let overall = match client {
+            Some(_) => self.config.per_key,
+            None => self.config.unauthenticated,
+        };
+        let mut limits = vec![(Limit::Overall, overall, WINDOW)];
+        let Some(requests) = requests else {
+            return limits;
+        };
+        let quotas = || {
+            self.config
+                .quotas
+                .iter()
+                .enumerate()
+                .filter(move |(_, q)| q.requests == requests)
+        };
+        let named = |keys: &[String]| client.is_some_and(|c| keys.iter().any(|k| k == c));
+        let quotas: Vec<_> = if quotas().any(|(_, q)| named(&q.keys)) {
+            quotas().filter(|(_, q)| named(&q.keys)).collect()
+        } else {
+            quotas().filter(|(_, q)| q.keys.is_empty()).collect()
+        };
+        limits.extend(
+            quotas
+                .into_iter()
+                .map(|(i, q)| (Limit::Quota(i), q.limit, q.per.duration())),
+        );
+        limits
+    }
+
+    /// Counts a request from `client` at `now`, against the quotas of `requests` if any. The
+    /// request is only counted if it is within all of its limits.
+    fn check(&self, client: &Client, requests: Option<QuotaRequests>, now: Instant) -> Decision {
+        let limits = self.limits(client, requests);
+
+        let mut windows = self.windows.lock().unwrap();
+        windows.retain(|_, w| now.duration_since(w.start) < w.length);
+        let mut decisions: Vec<_> = limits
+            .iter()
+            .map(|&(limit, max, length)| {
+                let window = windows.entry((limit, client.clone())).or_insert(Window {
+                    start: now,
+                    length,
+                    count: 0,
+                });
+                Decision {
+                    allowed: window.count < max,
+                    limit: max,
+                    remaining: max.saturating_sub(window.count),
+                    reset: length - now.duration_since(window.start),
+                }
+            })
+            .collect();
+
+        let allowed = decisions.iter().all(|d| d.allowed);
+        if allowed {
+            for &(limit, _, _) in &limits {
+                let key = (limit, client.clone());
+                windows.get_mut(&key).expect("window just opened").count += 1;
+            }
+            for decision in &mut decisions {
+                decision.remaining -= 1;
+            }
+            decisions
+                .into_iter()
+                .min_by_key(|d| d.remaining)
+                .expect("overall limit")
+        } else {
+            // Retrying is only worth it once all the limits reached are reset
+            decisions
+                .into_iter()
+                .filter(|d| !d.allowed)
+                .max_by_key(|d| d.reset)
+                .expect("a limit reached")
+        }
+    }
+}
+
+/// The kind of quota the request for `path`, relative to the routes of a station, counts
+/// against.
+fn quota_requests(method: &Method, path: &str) -> Option<QuotaRequests> {
+    let submission = (*method == Method::PUT && path.starts_with("/tasks/"))
+        || (*method == Method::POST
+            && (matches!(path, "/tasks/submit_from_template" | "/predict/schedule")
+                || path.starts_with("/predict/") && path.ends_with("/schedule")));
+    if submission {
+        Some(QuotaRequests::Submissions)
+    } else if *method == Method::GET && path.starts_with("/predict/") {
+        Some(QuotaRequests::Predictions)
+    } else {
+        None
+    }
+}
+
+/// Middleware rejecting requests over the configured rate limits.
+pub async fn limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(limiter) = state.rate_limiter.clone() else {
+        return next.run(request).await;
+    };
+
+    let auth = request.extensions().get::<Authentication>();
+    let client = match auth.and_then(|auth| auth.key.as_ref()) {
+        Some(key) if !key.is_anonymous() => Client::Key(key.owner.clone()),
+        _ => Client::Address(auth.and_then(|auth| auth.address)),
+    };
+    // The routes of the station are nested, and see their path without the prefix
+    let requests = quota_requests(request.method(), request.uri().path());
+    let decision = limiter.check(&client, requests, Instant::now());
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        ApiError::TooManyRequests.into_response()
+    };
+    add_headers(response.headers_mut(), &decision);
+    response
+}
+
+/// Failed authentications of each address in the current minute, so that the requests of an
+/// address guessing keys are refused before their hashes are checked.
+pub struct FailedLogins {
+    limit: u32,
+    windows: Mutex<HashMap<Option<IpAddr>, Window>>,
+}
+
+impl FailedLogins {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the credentials of requests from `address` are still checked at `now`.
+    pub fn allows(&self, address: Option<IpAddr>, now: Instant) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        windows.retain(|_, w| now.duration_since(w.start) < w.length);
+        windows
+            .get(&address)
+            .is_none_or(|window| window.count < self.limit)
+    }
+
+    /// Counts a failed authentication from `address` at `now`.
+    pub fn record(&self, address: Option<IpAddr>, now: Instant) {
+        let mut windows = self.windows.lock().unwrap();
+        windows
+            .entry(address)
+            .or_insert(Window {
+                start: now,
+                length: WINDOW,
+                count: 0,
+            })
+            .count += 1;
+    }
+}
+
+fn add_headers(headers: &mut HeaderMap, decision: &Decision) {
+    // Round up, so that clients waiting for the reset find a new window
+    let reset = decision.reset.as_secs() + u64::from(decision.reset.subsec_nanos() > 0);
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(reset));
+    if !decision.allowed {
+        headers.insert("retry-after", HeaderValue::from(reset));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiConfig, ApiKey, Config, Permission, QuotaConfig, QuotaPeriod};
+
+    fn key(owner: &str) -> Client {
+        Client::Key(owner.to_string())
+    }
+
+    fn address(address: Option<[u8; 4]>) -> Client {
+        Client::Address(address.map(IpAddr::from))
+    }
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            per_key: 2,
+            unauthenticated: 1,
+            quotas: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn limits_each_client_separately() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        assert!(limiter.check(&key("alice"), None, now).allowed);
+        let second = limiter.check(&key("alice"), None, now);
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+        assert!(!limiter.check(&key("alice"), None, now).allowed);
+
+        assert!(limiter.check(&key("bob"), None, now).allowed);
+        assert!(limiter.check(&address(None), None, now).allowed);
+        assert!(!limiter.check(&address(None), None, now).allowed);
+        // Unauthenticated requests are counted per address
+        assert!(
+            limiter
+                .check(&address(Some([10, 0, 0, 1])), None, now)
+                .allowed
+        );
+        assert!(
+            !limiter
+                .check(&address(Some([10, 0, 0, 1])), None, now)
+                .allowed
+        );
+        assert!(
+            limiter
+                .check(&address(Some([10, 0, 0, 2])), None, now)
+                .allowed
+        );
+    }
+
+    #[test]
+    fn failed_logins_are_counted_per_address() {
+        let failed = FailedLogins::new(2);
+        let now = Instant::now();
+        let attacker = Some(IpAddr::from([10, 0, 0, 1]));
+
+        failed.record(attacker, now);
+        assert!(failed.allows(attacker, now));
+        failed.record(attacker, now);
+        assert!(!failed.allows(attacker, now));
+        assert!(failed.allows(Some(IpAddr::from([10, 0, 0, 2])), now));
+        assert!(failed.allows(attacker, now + WINDOW));
+    }
+
+    #[test]
+    fn window_resets_after_a_minute() {
+        let limiter = limiter();
+        let now = Instant::now();
+        limiter.check(&address(None), None, now);
+
+        let denied = limiter.check(&address(None), None, now + Duration::from_secs(45));
+        assert!(!denied.allowed);
+        assert_eq!(denied.reset, Duration::from_secs(15));
+        assert!(limiter.check(&address(None), None, now + WINDOW).allowed);
+    }
+
+    #[test]
+    fn quotas_limit_their_requests() {
+        let quota = |requests, limit, per, keys: &[&str]| QuotaConfig {
+            requests,
+            limit,
+            per,
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+        };
+        let limiter = RateLimiter::new(RateLimitConfig {
+            per_key: 100,
+            unauthenticated: 100,
+            quotas: vec![
+                quota(QuotaRequests::Predictions, 2, QuotaPeriod::Minute, &[]),
+                quota(QuotaRequests::Submissions, 1, QuotaPeriod::Hour, &[]),
+                quota(QuotaRequests::Submissions, 3, QuotaPeriod::Hour, &["alice"]),
+            ],
+        });
+        let now = Instant::now();
+        let predictions = Some(QuotaRequests::Predictions);
+        let submissions = Some(QuotaRequests::Submissions);
+
+        assert!(limiter.check(&key("bob"), predictions, now).allowed);
+        let second = limiter.check(&key("bob"), predictions, now);
+        assert_eq!((second.limit, second.remaining), (2, 0));
+        assert!(!limiter.check(&key("bob"), predictions, now).allowed);
+        // Other requests are only counted against the overall limit
+        let other = limiter.check(&key("bob"), None, now);
+        assert!(other.allowed);
+        assert_eq!(other.remaining, 97);
+
+        assert!(limiter.check(&key("bob"), submissions, now).allowed);
+        let denied = limiter.check(&key("bob"), submissions, now + WINDOW);
+        assert!(!denied.allowed);
+        assert_eq!(denied.reset, Duration::from_secs(59 * 60));
+        assert!(
+            limiter
+                .check(&key("bob"), predictions, now + WINDOW)
+                .allowed
+        );
+        assert!(
+            limiter
+                .check(&key("bob"), submissions, now + QuotaPeriod::Hour.duration())
+                .allowed
+        );
+
+        for _ in 0..3 {
+            assert!(limiter.check(&key("alice"), submissions, now).allowed);
+        }
+        assert!(!limiter.check(&key("alice"), submissions, now).allowed);
+    }
+
+    #[test]
+    fn requests_are_told_apart_by_route() {
+        assert_eq!(
+            quota_requests(&Method::PUT, "/tasks/pass.1"),
+            Some(QuotaRequests::Submissions)
+        );
+        assert_eq!(
+            quota_requests(&Method::POST, "/predict/schedule"),
+            Some(QuotaRequests::Submissions)
+        );
+        assert_eq!(
+            quota_requests(&Method::POST, "/predict/58810/schedule"),
+            Some(QuotaRequests::Submissions)
+        );
+        assert_eq!(
+            quota_requests(&Method::GET, "/predict/passes"),
+            Some(QuotaRequests::Predictions)
+        );
+        assert_eq!(quota_requests(&Method::GET, "/tasks/pass.1"), None);
+        assert_eq!(quota_requests(&Method::POST, "/tasks/pass.1/abort"), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_over_the_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            api: ApiConfig {
+                keys: vec![
+                    ApiKey {
+                        name: None,
+                        id: None,
+                        key: "test-key".into(),
+                        permissions: vec![Permission::ViewTasks],
+                    },
+                    ApiKey {
+                        name: Some("observer".into()),
+                        id: None,
+                        key: "observer-key".into(),
+                        permissions: vec![Permission::ViewTasks],
+                    },
+                ],
+                rate_limit: Some(RateLimitConfig {
+                    per_key: 2,
+                    unauthenticated: 1,
+                    quotas: vec![QuotaConfig {
+                        requests: QuotaRequests::Predictions,
+                        limit: 1,
+                        per: QuotaPeriod::Hour,
+                        keys: vec!["key:observer".into()],
+                    }],
+                }),
+                ..Default::default()
+            },
+            ..Config::for_test(tmp.path(), Vec::new())
+        };
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+
+        let request = |path: &str, key: Option<&str>| {
+            let mut builder = axum::http::Request::get(path);
+            if let Some(key) = key {
+                builder = builder.header("api_key", key);
+            }
+            router.clone().oneshot(builder.body(Body::empty()).unwrap())
+        };
+
+        let response = request("/api/tasks", Some("test-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["ratelimit-limit"], "2");
+        assert_eq!(response.headers()["ratelimit-remaining"], "1");
+        request("/api/tasks", Some("test-key")).await.unwrap();
+        let response = request("/api/tasks", Some("test-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+        // Keys given as a token are counted for the key too
+        let response = request("/api/tasks/calendar.ics?token=test-key", None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Unauthenticated requests have their own, stricter, limit
+        let response = request("/api/tasks", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = request("/api/tasks", Some("wrong-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let response = request("/api/v1/predict/satellites", Some("observer-key"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["ratelimit-limit"], "1");
+        let response = request("/api/v1/predict/satellites", Some("observer-key"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "3600");
+    }
+}
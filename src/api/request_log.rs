@@ -0,0 +1,198 @@
+//! Logging of each API request, with a request ID to correlate it with what the client saw.
+//!
+//! The ID is taken from the `X-Request-Id` header of the request if given, or generated. It is
+//! returned in the `X-Request-Id` header of every response and appended to the text of error
+//! responses, so that users can report it.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use axum::body::Body;
+use axum::body::HttpBody as _;
+use axum::extract::{OriginalUri, Request, State};
+use axum::http::HeaderValue;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::Utc;
+use tracing::{info, warn};
+
+use super::AppState;
+use super::audit::{AuditEntry, AuditLog};
+use super::auth::Authentication;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request ID accepted from clients.
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+/// Error responses larger than this (bytes) are returned unchanged.
+const MAX_ERROR_BODY: u64 = 64 * 1024;
+
+/// Middleware logging the method, path, status, latency and caller of each request, and recording
+/// the calls changing the station state in the audit log.
+pub async fn log(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map_or_else(generate_request_id, str::to_string);
+
+    let method = request.method().clone();
+    let path = request.extensions().get::<OriginalUri>().map_or_else(
+        || request.uri().path().to_string(),
+        |uri| uri.path().to_string(),
+    );
+
+    let mut response = next.run(request).await;
+    // Unknown for the requests refused before authenticating them
+    let user = response
+        .extensions()
+        .get::<Authentication>()
+        .and_then(|auth| auth.key.as_ref())
+        .filter(|key| !key.is_anonymous())
+        .map(|key| key.owner.clone());
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        response = with_request_id(response, &request_id).await;
+    }
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    if AuditLog::records(&method) {
+        state
+            .audit
+            .record(&AuditEntry {
+                time: Utc::now().to_rfc3339(),
+                request_id: request_id.clone(),
+                user: user.clone(),
+                method: method.to_string(),
+                path: path.clone(),
+                status: status.as_u16(),
+            })
+            .await;
+    }
+
+    let latency = start.elapsed();
+    state.latency.observe(latency);
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+    if status.is_server_error() {
+        warn!(%request_id, %method, %path, status = status.as_u16(), latency_ms, ?user, "request");
+    } else {
+        info!(%request_id, %method, %path, status = status.as_u16(), latency_ms, ?user, "request");
+    }
+    response
+}
+
+/// Appends the request ID to the text of an error response.
+async fn with_request_id(response: Response, request_id: &str) -> Response {
+    let is_text = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_none_or(|v| v.starts_with("text/plain"));
+    let is_small = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|size| size <= MAX_ERROR_BODY);
+    if !is_text || !is_small {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_ERROR_BODY as usize).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let text = format!(
+        "{} (request ID {request_id})",
+        String::from_utf8_lossy(&bytes).trim_end()
+    );
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(text))
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Generates an ID unique within this process, and unlikely to repeat across restarts.
+fn generate_request_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    static STARTED: std::sync::OnceLock<i64> = std::sync::OnceLock::new();
+
+    let started = STARTED.get_or_init(|| Utc::now().timestamp_millis());
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{started:x}-{count:06x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    fn router(tmp: &tempfile::TempDir) -> axum::Router {
+        let config = Config::for_test(
+            tmp.path(),
+            vec![ApiKey {
+                name: None,
+                id: None,
+                key: "test-key".into(),
+                permissions: vec![Permission::ViewTasks],
+            }],
+        );
+        api::routes(api::state(&config)).split_for_parts().0
+    }
+
+    #[test]
+    fn generated_ids_are_unique() {
+        let id = generate_request_id();
+        assert!(is_valid_request_id(&id));
+        assert_ne!(id, generate_request_id());
+        assert!(!is_valid_request_id("a b"));
+    }
+
+    #[tokio::test]
+    async fn request_id_is_returned_in_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let resp = router(&tmp)
+            .oneshot(
+                Request::get("/api/tasks/nope")
+                    .header("api_key", "test-key")
+                    .header(REQUEST_ID_HEADER, "client-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()[REQUEST_ID_HEADER], "client-123");
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "not found (request ID client-123)");
+    }
+
+    #[tokio::test]
+    async fn successful_responses_are_unchanged() {
+        let tmp = tempfile::tempdir().unwrap();
+        let resp = router(&tmp)
+            .oneshot(Request::get("/api/station").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().contains_key(REQUEST_ID_HEADER));
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        assert!(bytes.starts_with(b"{\"name\":\"test\""));
+    }
+}
@@ -0,0 +1,491 @@
+//! Summary of a task for its approvers.
+
+use std::collections::{BTreeSet, HashMap};
+
+use axum::Json;
+use axum::extract::{Path as AxumPath, State};
+use sat_o_mat::predict::PredictDb;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::{Permission, ResourceConfig};
+use crate::task::format::{Step, Task};
+use crate::task::utils::{check_time_conflict, resolve_time, substitute_variables};
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use super::templates::read_template;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskReview {
+    pub id: String,
+    pub state: String,
+    pub owner: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub steps: Vec<ReviewStep>,
+    pub cleanup: Vec<ReviewStep>,
+    /// Names of the station resources used by any step
+    pub resources: Vec<String>,
+    /// Whether any step uses a resource that can transmit
+    pub transmits: bool,
+    /// Age of the orbital elements in the `tle` variable at the start of the task, in days
+    pub element_age_days: Option<f64>,
+    /// Active task whose time range overlaps this task's, if any
+    pub conflict: Option<String>,
+    /// Differences from the template the task was generated from, if any
+    pub template: Option<TemplateDiff>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReviewStep {
+    /// Command with the task variables substituted
+    pub command: String,
+    /// Time the step starts, if set. Otherwise it starts right after the previous step.
+    pub time: Option<String>,
+    pub wait: bool,
+    pub on_fail: String,
+    pub resources: Vec<String>,
+    pub transmit: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TemplateDiff {
+    pub id: String,
+    /// Whether the template still exists. If not, no differences are listed.
+    pub found: bool,
+    pub variables: Vec<Difference>,
+    pub steps: Vec<Difference>,
+    pub cleanup: Vec<Difference>,
+}
+
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+pub struct Difference {
+    /// Variable name, or index of the step
+    pub name: String,
+    /// Value in the template, None if only in the task
+    pub template: Option<String>,
+    /// Value in the task, None if only in the template
+    pub task: Option<String>,
+}
+
+/// Review a task before approving it.
+///
+/// Returns everything needed to approve the task: the timeline of its steps with variables and
+/// times resolved, the station resources they use and whether any of them transmits, the age of
+/// the orbital elements it tracks, any conflicting Active task, and its differences from the
+/// template it was generated from.
+///
+/// Variables evaluated by shell commands (`${...}`) are only resolved when the task runs, so they
+/// are shown unresolved.
+#[utoipa::path(
+    get,
+    path = "/tasks/{id}/review",
+    tag = super::TASKS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Task review", body = TaskReview),
+        (status = 400, description = "Invalid task definition"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Task not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_review(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<TaskReview>, ApiError> {
+    let (task_state, content) = Task::find(&state.tasks_path, &id)
+        .await
+        .ok_or(ApiError::NotFound)?;
+    let task = Task::from_yaml_str(&content)
+        .map_err(|e| ApiError::BadRequest(format!("invalid task: {e}")))?;
+    auth.require_on(Permission::ViewTasks, task.owner.as_deref())?;
+
+    let resources = &state.config.resources;
+    let steps = review_steps(&task.steps, &task.variables, resources);
+    let cleanup = review_steps(&task.cleanup, &task.variables, resources);
+    let used: BTreeSet<&String> = steps
+        .iter()
+        .chain(&cleanup)
+        .flat_map(|s| &s.resources)
+        .collect();
+
+    let start = task.get_time_variable("start").ok();
+    let element_age_days = start
+        .zip(task.variables.get("tle"))
+        .and_then(|(start, tle)| {
+            let mut db = PredictDb::new();
+            db.add(tle);
+            let (_, sat) = db.iter().next()?;
+            Some(sat.element_age(start).num_seconds() as f64 / 86400.0)
+        });
+
+    let conflict = match task.time_range() {
+        Ok(_) if task_state != "Active" => check_time_conflict(&state.tasks, &id, &task).await,
+        _ => None,
+    };
+
+    let template = match &task.template {
+        Some(template_id) => Some(template_diff(&state, template_id, &task).await),
+        None => None,
+    };
+
+    Ok(Json(TaskReview {
+        id,
+        state: task_state,
+        owner: task.owner.clone(),
+        start: start.map(|t| t.to_rfc3339()),
+        end: task.get_time_variable("end").ok().map(|t| t.to_rfc3339()),
+        transmits: steps.iter().chain(&cleanup).any(|s| s.transmit),
+        resources: used.into_iter().cloned().collect(),
+        steps,
+        cleanup,
+        element_age_days,
+        conflict,
+        template,
+    }))
+}
+
+pub fn review_steps(
+    steps: &[Step],
+    variables: &HashMap<String, String>,
+    resources: &[ResourceConfig],
+) -> Vec<ReviewStep> {
+    steps
+        .iter()
+        .map(|step| {
+            let command = substitute_variables(&step.cmd, variables);
+            let used = used_resources(&command, resources);
+            ReviewStep {
+                time: step
+                    .time
+                    .as_ref()
+                    .and_then(|t| resolve_time(t, variables))
+                    .map(|t| t.to_rfc3339()),
+                wait: step.wait,
+                on_fail: step_text(&step.on_fail),
+                resources: used.iter().map(|r| r.name.clone()).collect(),
+                transmit: used.iter().any(|r| r.transmit),
+                command,
+            }
+        })
+        .collect()
+}
+
+/// The resources `command` runs one of the programs of, or refers to by name.
+pub fn used_resources<'a>(
+    command: &str,
+    resources: &'a [ResourceConfig],
+) -> Vec<&'a ResourceConfig> {
+    let references = hardware_references(command);
+    resources
+        .iter()
+        .filter(|r| uses(command, r) || references.iter().any(|(_, name)| *name == r.name))
+        .collect()
+}
+
+/// Whether `command` runs one of the programs controlling `resource`.
+fn uses(command: &str, resource: &ResourceConfig) -> bool {
+    command.split_whitespace().any(|word| {
+        let program = word.rsplit('/').next().unwrap_or(word);
+        resource.commands.iter().any(|c| c == program)
+    })
+}
+
+/// Checks that the hardware the steps and cleanup of `task` refer to by name is configured in
+/// `resources`. Returns a message for each reference that does not resolve, e.g.
+/// `steps[1]: unknown rotator uhf2`.
+pub fn check_hardware(task: &Task, resources: &[ResourceConfig]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (list, steps) in [("steps", &task.steps), ("cleanup", &task.cleanup)] {
+        for (i, step) in steps.iter().enumerate() {
+            let command = substitute_variables(&step.cmd, &task.variables);
+            for (kind, name) in hardware_references(&command) {
+                let resource = resources.iter().find(|r| r.name == name);
+                let error = match (kind, resource) {
+                    (_, None) => format!("unknown {} {name}", kind.name()),
+                    (Hardware::Sdr, Some(r)) if r.sdr.is_none() => {
+                        format!("resource {name} has no sdr settings")
+                    }
+                    (Hardware::Rotator | Hardware::Radio, Some(r)) if r.address.is_none() => {
+                        format!("resource {name} has no address")
+                    }
+                    _ => continue,
+                };
+                errors.push(format!("{list}[{i}]: {error}"));
+            }
+        }
+    }
+    errors
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Hardware {
+    Rotator,
+    Radio,
+    Sdr,
+}
+
+impl Hardware {
+    fn name(self) -> &'static str {
+        match self {
+            Hardware::Rotator => "rotator",
+            Hardware::Radio => "radio",
+            Hardware::Sdr => "SDR",
+        }
+    }
+}
+
+/// The hardware `command` refers to by name if it runs `sat-o-mat`: `--rotator NAME`,
+/// `--radio NAME`, `--sdr NAME`, `--out rotctl=NAME`, `--out rigctl=NAME` and
+/// `rotator park NAME`. Addresses (`host:port`) and variables not substituted are skipped.
+fn hardware_references(command: &str) -> Vec<(Hardware, String)> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    if !words
+        .iter()
+        .any(|word| word.rsplit('/').next() == Some("sat-o-mat"))
+    {
+        return Vec::new();
+    }
+
+    let mut references = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        let next = words.get(i + 1).copied();
+        let (flag, value) = match word.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => (flag, Some(value)),
+            _ => (*word, next),
+        };
+        let reference = match (flag, value) {
+            ("--rotator", Some(name)) => Some((Hardware::Rotator, name)),
+            ("--radio", Some(name)) => Some((Hardware::Radio, name)),
+            ("--sdr", Some(name)) => Some((Hardware::Sdr, name)),
+            ("--out" | "-o", Some(output)) => match output.split_once('=') {
+                Some(("rotctl", name)) => Some((Hardware::Rotator, name)),
+                Some(("rigctl", name)) => Some((Hardware::Radio, name)),
+                _ => None,
+            },
+            ("rotator", Some("park")) => {
+                first_argument(&words[i + 2..]).map(|name| (Hardware::Rotator, name))
+            }
+            _ => None,
+        };
+        if let Some((kind, name)) = reference
+            && !name.contains(':')
+            && !name.contains('$')
+            && !name.starts_with('-')
+        {
+            references.push((kind, name.to_string()));
+        }
+    }
+    references
+}
+
+/// The first argument in `words` that is not an option, skipping the value of `--station`.
+fn first_argument<'a>(words: &[&'a str]) -> Option<&'a str> {
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        if *word == "--station" {
+            words.next();
+        } else if !word.starts_with('-') {
+            return Some(word);
+        }
+    }
+    None
+}
+
+async fn template_diff(state: &AppState, template_id: &str, task: &Task) -> TemplateDiff {
+    let Ok((template, _)) = read_template(state, template_id).await else {
+        return TemplateDiff {
+            id: template_id.to_string(),
+            found: false,
+            variables: Vec::new(),
+            steps: Vec::new(),
+            cleanup: Vec::new(),
+        };
+    };
+
+    let mut names: Vec<&String> = template
+        .variables
+        .keys()
+        .chain(task.variables.keys())
+        .collect();
+    names.sort();
+    names.dedup();
+    let variables = names
+        .into_iter()
+        .map(|name| Difference {
+            name: name.clone(),
+            template: template.variables.get(name).cloned(),
+            task: task.variables.get(name).cloned(),
+        })
+        .filter(|d| d.template != d.task)
+        .collect();
+
+    TemplateDiff {
+        id: template_id.to_string(),
+        found: true,
+        variables,
+        steps: step_diff(&template.steps, &task.steps),
+        cleanup: step_diff(&template.cleanup, &task.cleanup),
+    }
+}
+
+/// Compares the steps at each index.
+fn step_diff(template: &[Step], task: &[Step]) -> Vec<Difference> {
+    (0..template.len().max(task.len()))
+        .map(|i| Difference {
+            name: i.to_string(),
+            template: template.get(i).map(step_text),
+            task: task.get(i).map(step_text),
+        })
+        .filter(|d| d.template != d.task)
+        .collect()
+}
+
+/// Returns the YAML text of `value`, e.g. a step or its `on_fail`.
+fn step_text(value: &impl Serialize) -> String {
+    serde_yaml::to_string(value)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, Config};
+
+    const TEMPLATE_YAML: &str = "\
+variables:
+  downlink: \"437.5 MHz\"
+steps:
+  - cmd: \"rotctl -m 2 track $satellite\"
+  - cmd: \"/usr/bin/rigctl F $downlink\"
+    time: \"T+10s\"
+    wait: true
+";
+
+    #[test]
+    fn steps_are_compared_by_index() {
+        let template = Task::from_yaml_str(TEMPLATE_YAML).unwrap();
+        let mut task = template.clone();
+        task.steps[1].cmd = "rigctl T 1".into();
+        task.steps.push(task.steps[0].clone());
+
+        let diff = step_diff(&template.steps, &task.steps);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].name, "1");
+        assert!(diff[0].task.as_ref().unwrap().contains("rigctl T 1"));
+        assert_eq!(diff[1].template, None);
+    }
+
+    #[tokio::test]
+    async fn reviews_pending_task() {
+        let tmp = tempfile::tempdir().unwrap();
+        for dir in ["Active", "PendingApproval", "Templates"] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        std::fs::write(tmp.path().join("Templates/uhf.yaml"), TEMPLATE_YAML).unwrap();
+
+        let tle = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        let mut task = Task::from_yaml_str(TEMPLATE_YAML).unwrap();
+        task.template = Some("uhf".into());
+        task.variables.extend([
+            ("start".to_string(), "2026-01-16T15:00:00Z".to_string()),
+            ("end".to_string(), "2026-01-16T15:10:00Z".to_string()),
+            ("satellite".to_string(), "NanoFF A".to_string()),
+            ("downlink".to_string(), "437.6 MHz".to_string()),
+            ("tle".to_string(), tle),
+        ]);
+        std::fs::write(
+            tmp.path().join("PendingApproval/pass.yaml"),
+            serde_yaml::to_string(&task).unwrap(),
+        )
+        .unwrap();
+
+        let config = Config {
+            resources: vec![
+                ResourceConfig {
+                    name: "rotator".into(),
+                    commands: vec!["rotctl".into()],
+                    transmit: false,
+                    address: None,
+                    limits: None,
+                    park: None,
+                    sdr: None,
+                },
+                ResourceConfig {
+                    name: "radio".into(),
+                    commands: vec!["rigctl".into()],
+                    transmit: true,
+                    address: None,
+                    limits: None,
+                    park: None,
+                    sdr: None,
+                },
+            ],
+            ..Config::for_test(
+                tmp.path(),
+                vec![ApiKey {
+                    name: None,
+                    id: None,
+                    key: "test-key".into(),
+                    permissions: vec![Permission::ViewTasks],
+                }],
+            )
+        };
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+
+        let resp = router
+            .oneshot(
+                Request::get("/api/tasks/pass/review")
+                    .header("api_key", "test-key")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let review: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(review["state"], "PendingApproval");
+        assert_eq!(review["steps"][0]["command"], "rotctl -m 2 track NanoFF A");
+        assert_eq!(
+            review["steps"][0]["resources"],
+            serde_json::json!(["rotator"])
+        );
+        assert_eq!(review["steps"][1]["time"], "2026-01-16T15:00:10+00:00");
+        assert_eq!(review["steps"][1]["transmit"], true);
+        assert_eq!(review["resources"], serde_json::json!(["radio", "rotator"]));
+        assert_eq!(review["transmits"], true);
+        let age = review["element_age_days"].as_f64().unwrap();
+        assert!((age - 2.0).abs() < 0.1, "{age}");
+
+        let template = &review["template"];
+        assert_eq!(template["found"], true);
+        assert!(template["steps"].as_array().unwrap().is_empty());
+        let downlink = template["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["name"] == "downlink")
+            .unwrap();
+        assert_eq!(downlink["template"], "437.5 MHz");
+        assert_eq!(downlink["task"], "437.6 MHz");
+    }
+}
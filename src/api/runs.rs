@@ -0,0 +1,913 @@
+use std::convert::Infallible;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{Path as AxumPath, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use futures_util::Stream;
+use sat_o_mat::task::runner::{LogEntry, StepResult, read_execution_log};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::mpsc;
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::config::Permission;
+use crate::task::format::Task;
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+
+pub(super) const ARTIFACTS_DIR: &str = "Artifacts";
+
+/// How often the execution log of a running task is checked for new entries.
+const LOG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Contents of a file, in the OpenAPI document.
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+#[allow(dead_code)]
+pub struct BinaryContent(Vec<u8>);
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArtifactEntry {
+    /// Path relative to the run's artifact directory, separated by `/`
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the contents, hex encoded
+    pub sha256: String,
+}
+
+/// List the artifacts of a run.
+///
+/// Returns every file (execution log, step logs, recordings...) in the artifact directory of the
+/// task with the given ID, sorted by path. The output of each step is in `steps/<index>.log`, and
+/// of each cleanup step in `cleanup/<index>.log`.
+///
+/// Requires ViewTasks permission, or ViewOwnTasks if the task was submitted by the caller, as do
+/// the other endpoints of a run.
+#[utoipa::path(
+    get,
+    path = "/runs/{id}/artifacts",
+    tag = super::RUNS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "List of artifacts", body = Vec<ArtifactEntry>),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Run not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_artifacts(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<Vec<ArtifactEntry>>, ApiError> {
+    require_view(&state, &auth, &id).await?;
+
+    let dir = run_dir(&state, &id).await?;
+    let entries = tokio::task::spawn_blocking(move || {
+        artifact_files(&dir)?
+            .into_iter()
+            .map(|(path, full_path)| {
+                let mut hasher = Sha256::new();
+                let size = io::copy(&mut std::fs::File::open(full_path)?, &mut hasher)?;
+                let sha256 = hasher
+                    .finalize()
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect();
+                Ok(ArtifactEntry { path, size, sha256 })
+            })
+            .collect::<io::Result<Vec<_>>>()
+    })
+    .await
+    .map_err(|_| ApiError::Internal)?
+    .map_err(|e| {
+        warn!(%id, ?e, "failed to list artifacts");
+        ApiError::Internal
+    })?;
+
+    Ok(Json(entries))
+}
+
+/// Download an artifact of a run.
+///
+/// The artifact is streamed from disk. A single `Range` of bytes can be requested, e.g. to resume
+/// an interrupted download.
+#[utoipa::path(
+    get,
+    path = "/runs/{id}/artifacts/{*path}",
+    tag = super::RUNS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)"),
+        ("path" = String, Path, description = "Artifact path, as returned when listing artifacts")
+    ),
+    responses(
+        (status = 200, description = "Artifact contents", body = BinaryContent, content_type = "application/octet-stream"),
+        (status = 206, description = "The range of the artifact requested", body = BinaryContent, content_type = "application/octet-stream"),
+        (status = 400, description = "Invalid path"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Run or artifact not found"),
+        (status = 416, description = "The range is outside the artifact"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_artifact(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath((id, path)): AxumPath<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_view(&state, &auth, &id).await?;
+
+    // Reject path traversal
+    if !Path::new(&path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(ApiError::BadRequest("invalid artifact path".to_string()));
+    }
+
+    let dir = run_dir(&state, &id).await?;
+    let mut file = tokio::fs::File::open(dir.join(&path))
+        .await
+        .map_err(|_| ApiError::NotFound)?;
+    let metadata = file.metadata().await.map_err(|_| ApiError::NotFound)?;
+    if !metadata.is_file() {
+        return Err(ApiError::NotFound);
+    }
+    let size = metadata.len();
+
+    let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| parse_range(range, size));
+    let (status, start, length) = match range {
+        None => (StatusCode::OK, 0, size),
+        Some(Some((start, end))) => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        Some(None) => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{size}"))],
+            )
+                .into_response());
+        }
+    };
+    if start > 0 {
+        file.seek(io::SeekFrom::Start(start)).await.map_err(|e| {
+            warn!(%id, %path, ?e, "failed to read artifact");
+            ApiError::Internal
+        })?;
+    }
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        .header(header::CONTENT_LENGTH, length)
+        .header(header::ACCEPT_RANGES, "bytes");
+    if status == StatusCode::PARTIAL_CONTENT {
+        let range = format!("bytes {start}-{}/{size}", start + length - 1);
+        response = response.header(header::CONTENT_RANGE, range);
+    }
+    let body = Body::from_stream(ReaderStream::new(file.take(length)));
+    response.body(body).map_err(|_| ApiError::Internal)
+}
+
+/// The first and last byte of the single range `range` (a `Range` header) of a file of `size`
+/// bytes. None if the header is not a single range of bytes, in which case the whole file is sent,
+/// and Some(None) if the range is outside the file.
+fn parse_range(range: &str, size: u64) -> Option<Option<(u64, u64)>> {
+    let (start, end) = range.strip_prefix("bytes=")?.trim().split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    if end.contains(',') {
+        return None;
+    }
+    let (start, end) = if start.is_empty() {
+        // The last `end` bytes
+        let suffix: u64 = end.parse().ok()?;
+        (size.saturating_sub(suffix), size.checked_sub(1))
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = match end {
+            "" => size.checked_sub(1),
+            end => Some(end.parse::<u64>().ok()?.min(size.saturating_sub(1))),
+        };
+        (start, end)
+    };
+    Some(
+        end.filter(|&end| start <= end && start < size)
+            .map(|end| (start, end)),
+    )
+}
+
+/// Download all the artifacts of a run as a .tar.gz archive.
+///
+/// The archive contains the execution log and the artifacts of every step under a directory named
+/// after the run. It is generated while it is sent, so large runs start downloading immediately.
+#[utoipa::path(
+    get,
+    path = "/runs/{id}/download",
+    tag = super::RUNS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Archive of the run's artifacts", body = BinaryContent, content_type = "application/gzip"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Run not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn download(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Response, ApiError> {
+    require_view(&state, &auth, &id).await?;
+
+    let dir = run_dir(&state, &id).await?;
+    let (tx, rx) = mpsc::channel(4);
+    let prefix = id.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut gz = GzEncoder::new(ChannelWriter::new(tx.clone()), Compression::default());
+        let result = write_archive(&mut gz, &dir, &prefix)
+            .and_then(|_| gz.finish())
+            .and_then(|mut out| out.flush());
+        if let Err(e) = result {
+            warn!(id = %prefix, ?e, "failed to archive artifacts");
+            // Aborts the response, so that the client does not get a truncated archive
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let disposition = format!("attachment; filename=\"{id}.tar.gz\"");
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/gzip"),
+            (header::CONTENT_DISPOSITION, disposition.as_str()),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// Size of the chunks the archive is sent in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Writer sending what is written to a channel, in chunks of [`CHUNK_SIZE`] bytes.
+struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Vec<u8>>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn new(tx: mpsc::Sender<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            tx,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client went away"))
+    }
+}
+
+/// Writes the files under `dir` to a tar archive, under the directory `prefix`.
+fn write_archive(out: &mut impl Write, dir: &Path, prefix: &str) -> io::Result<()> {
+    for (path, full_path) in artifact_files(dir)? {
+        let file = std::fs::File::open(full_path)?;
+        let size = file.metadata()?.len();
+        write_tar_entry(out, &format!("{prefix}/{path}"), file, size)?;
+    }
+    // End of archive: two zero blocks
+    out.write_all(&[0; 1024])
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiExecution {
+    /// State of the task: `Active` while running, then `Completed` or `Failed`. Missing if the
+    /// task was deleted.
+    pub state: Option<String>,
+    pub entries: Vec<ApiLogEntry>,
+}
+
+/// Get the execution log of a run.
+///
+/// Returns the outcome of each step finished so far and of the uploads of the artifacts, in the
+/// order they were recorded.
+#[utoipa::path(
+    get,
+    path = "/runs/{id}/execution",
+    tag = super::RUNS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Execution log", body = ApiExecution),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Run not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_execution(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<ApiExecution>, ApiError> {
+    require_view(&state, &auth, &id).await?;
+
+    let dir = run_dir(&state, &id).await?;
+    let task_state = task_state(&state.tasks_path, &id).await;
+    let entries = tokio::task::spawn_blocking(move || read_execution_log(&dir))
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .map_err(|e| {
+            warn!(%id, ?e, "failed to read execution log");
+            ApiError::Internal
+        })?;
+
+    Ok(Json(ApiExecution {
+        state: task_state.map(str::to_string),
+        entries: entries.iter().map(ApiLogEntry::from).collect(),
+    }))
+}
+
+/// Execution log entry, recorded when a step of a run finishes.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiLogEntry {
+    /// Time the step finished, formatted as RFC3339
+    pub time: String,
+    pub cmd: String,
+    /// One of `completed`, `aborted`, `spawn_error` or `timed_out` for steps, `uploaded` or
+    /// `upload_failed` for uploads of the artifacts
+    pub result: String,
+    /// Exit code of the command, if it exited
+    pub exit_code: Option<i32>,
+    /// Why the command could not be run, or was aborted
+    pub error: Option<String>,
+    /// How many times the step was run, if retried
+    pub attempts: Option<u32>,
+}
+
+/// Outcome of a finished run, summarized from its execution log.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiExecutionSummary {
+    /// Steps run, not counting the uploads of the artifacts
+    pub steps: usize,
+    /// Steps that exited successfully
+    pub succeeded: usize,
+    /// `completed`, `completed with errors`, `aborted` or `no steps`
+    pub result: String,
+}
+
+impl From<&[LogEntry]> for ApiExecutionSummary {
+    fn from(log: &[LogEntry]) -> Self {
+        Self {
+            steps: log.iter().filter(|e| !e.result.is_upload()).count(),
+            succeeded: log
+                .iter()
+                .filter(|e| e.result == StepResult::Completed && e.exit_code == Some(0))
+                .count(),
+            result: run_result(log).to_string(),
+        }
+    }
+}
+
+/// Overall result of the run with execution `log`.
+pub fn run_result(log: &[LogEntry]) -> &'static str {
+    let log: Vec<&LogEntry> = log.iter().filter(|e| !e.result.is_upload()).collect();
+    if log.is_empty() {
+        "no steps"
+    } else if log
+        .iter()
+        .any(|e| !matches!(e.result, StepResult::Completed | StepResult::TimedOut))
+    {
+        "aborted"
+    } else if log.iter().any(|e| e.exit_code != Some(0)) {
+        "completed with errors"
+    } else {
+        "completed"
+    }
+}
+
+/// Name of `result` in the API.
+pub fn result_name(result: StepResult) -> &'static str {
+    match result {
+        StepResult::Completed => "completed",
+        StepResult::Aborted => "aborted",
+        StepResult::SpawnError => "spawn_error",
+        StepResult::TimedOut => "timed_out",
+        StepResult::Uploaded => "uploaded",
+        StepResult::UploadFailed => "upload_failed",
+    }
+}
+
+impl From<&LogEntry> for ApiLogEntry {
+    fn from(entry: &LogEntry) -> Self {
+        ApiLogEntry {
+            time: entry.time.to_rfc3339(),
+            cmd: entry.cmd.clone(),
+            result: result_name(entry.result).to_string(),
+            exit_code: entry.exit_code,
+            error: entry.error.clone(),
+            attempts: entry.attempts,
+        }
+    }
+}
+
+/// Stream the execution log of a run.
+///
+/// Sends each entry of the execution log so far as a `step` event, then each new entry as the
+/// steps of the run finish. A final `end` event, with the state the task ended in, is sent once
+/// the run is over.
+#[utoipa::path(
+    get,
+    path = "/runs/{id}/log/stream",
+    tag = super::RUNS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Stream of events, the data of `step` events being an execution log entry", body = ApiLogEntry, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Run not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn stream_log(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    require_view(&state, &auth, &id).await?;
+
+    let dir = run_dir(&state, &id).await?;
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(follow_log(state.tasks_path.clone(), id, dir, tx));
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Sends the entries of the execution log in `dir` to `tx` until the run is over or the client
+/// goes away.
+async fn follow_log(tasks_path: PathBuf, id: String, dir: PathBuf, tx: mpsc::Sender<Event>) {
+    let mut sent = 0;
+    loop {
+        // Checked before reading the log, so that the last entries are not missed
+        let task_state = task_state(&tasks_path, &id).await;
+
+        let log_dir = dir.clone();
+        let entries = tokio::task::spawn_blocking(move || read_execution_log(&log_dir))
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        for entry in entries.iter().skip(sent) {
+            let Ok(event) = Event::default()
+                .event("step")
+                .id(sent.to_string())
+                .json_data(ApiLogEntry::from(entry))
+            else {
+                continue;
+            };
+            if tx.send(event).await.is_err() {
+                return;
+            }
+            sent += 1;
+        }
+
+        if task_state != Some("Active") {
+            let end = Event::default()
+                .event("end")
+                .data(task_state.unwrap_or_default());
+            let _ = tx.send(end).await;
+            return;
+        }
+        tokio::time::sleep(LOG_POLL_INTERVAL).await;
+    }
+}
+
+/// Returns the state of the run task `id`, or `None` if it no longer exists.
+async fn task_state(tasks_path: &Path, id: &str) -> Option<&'static str> {
+    for dir in ["Active", "Completed", "Failed"] {
+        let Ok(mut entries) = tokio::fs::read_dir(tasks_path.join(dir)).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.path().file_stem().is_some_and(|stem| stem == id) {
+                return Some(dir);
+            }
+        }
+    }
+    None
+}
+
+/// Requires ViewTasks permission on the task of run `id`, or ViewOwnTasks if it was submitted by
+/// the caller.
+pub(super) async fn require_view(
+    state: &AppState,
+    auth: &AuthenticatedKey,
+    id: &str,
+) -> Result<(), ApiError> {
+    let owner = Task::find(&state.tasks_path, id)
+        .await
+        .and_then(|(_, content)| super::tasks::task_owner(&content));
+    auth.require_on(Permission::ViewTasks, owner.as_deref())
+}
+
+/// Returns the artifact directory of the run with the given ID.
+async fn run_dir(state: &AppState, id: &str) -> Result<PathBuf, ApiError> {
+    // Reject path traversal
+    if id.contains('/') || id.contains('\\') || id == ".." || id == "." {
+        return Err(ApiError::BadRequest("invalid run ID".to_string()));
+    }
+
+    let dir = state.tasks_path.join(ARTIFACTS_DIR).join(id);
+    match tokio::fs::metadata(&dir).await {
+        Ok(metadata) if metadata.is_dir() => Ok(dir),
+        _ => Err(ApiError::NotFound),
+    }
+}
+
+/// Returns the files under `dir` as (path relative to `dir`, full path), sorted by path.
+pub fn artifact_files(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative) = path.strip_prefix(dir) {
+                let relative = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((relative, path));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Writes a regular file entry of `size` bytes read from `data` to a ustar archive.
+///
+/// Files changing while they are archived are truncated or padded with zeros to `size`.
+fn write_tar_entry(out: &mut impl Write, path: &str, data: impl Read, size: u64) -> io::Result<()> {
+    let mut header = [0u8; 512];
+
+    // Names longer than 100 bytes are split into a prefix (up to 155 bytes) and a name
+    let (prefix, name) = if path.len() <= 100 {
+        ("", path)
+    } else {
+        path.char_indices()
+            .filter(|&(i, c)| c == '/' && i <= 155 && path.len() - i - 1 <= 100)
+            .map(|(i, _)| (&path[..i], &path[i + 1..]))
+            .next()
+            .ok_or_else(|| io::Error::other(format!("path too long for archive: {path}")))?
+    };
+
+    let mut field = |offset: usize, value: &[u8]| {
+        header[offset..offset + value.len()].copy_from_slice(value);
+    };
+    field(0, name.as_bytes());
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    field(124, &size_field(size));
+    field(136, b"00000000000\0");
+    field(148, b"        ");
+    field(156, b"0");
+    field(257, b"ustar\0");
+    field(263, b"00");
+    field(345, prefix.as_bytes());
+
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    out.write_all(&header)?;
+    let copied = io::copy(&mut data.take(size), out)?;
+    io::copy(&mut io::repeat(0).take(size - copied), out)?;
+    let padding = (512 - size % 512) % 512;
+    io::copy(&mut io::repeat(0).take(padding), out)?;
+    Ok(())
+}
+
+/// The size field of a tar header: octal, or for 8 GiB and more, which do not fit in its 11
+/// digits, big-endian binary marked by the high bit of the first byte (a GNU extension).
+fn size_field(size: u64) -> [u8; 12] {
+    let mut field = [0; 12];
+    if size < 1 << 33 {
+        field.copy_from_slice(format!("{size:011o}\0").as_bytes());
+    } else {
+        field[0] = 0x80;
+        field[4..].copy_from_slice(&size.to_be_bytes());
+    }
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use flate2::read::GzDecoder;
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use super::{parse_range, size_field};
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    fn test_config(tmp: &TempDir, permissions: Vec<Permission>) -> Config {
+        Config::for_test(
+            tmp.path(),
+            vec![ApiKey {
+                name: None,
+                id: None,
+                key: "test-key".into(),
+                permissions,
+            }],
+        )
+    }
+
+    fn setup(permissions: Vec<Permission>) -> (TempDir, axum::Router) {
+        let tmp = tempfile::tempdir().unwrap();
+        let run = tmp.path().join("Artifacts/run1");
+        std::fs::create_dir_all(run.join("steps")).unwrap();
+        std::fs::write(run.join("execution_log.yaml"), "hello").unwrap();
+        std::fs::write(run.join("steps/0.log"), "step output\n").unwrap();
+
+        let config = test_config(&tmp, permissions);
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+        (tmp, router)
+    }
+
+    async fn get(router: axum::Router, uri: &str) -> (StatusCode, Vec<u8>) {
+        let req = Request::get(uri)
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        let status = resp.status();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, bytes.to_vec())
+    }
+
+    #[tokio::test]
+    async fn list_artifacts_returns_sizes_and_checksums() {
+        let (_tmp, router) = setup(vec![Permission::ViewTasks]);
+        let (status, body) = get(router, "/api/runs/run1/artifacts").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let entries = json.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["path"], "execution_log.yaml");
+        assert_eq!(entries[0]["size"], 5);
+        assert_eq!(
+            entries[0]["sha256"],
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(entries[1]["path"], "steps/0.log");
+    }
+
+    #[tokio::test]
+    async fn list_artifacts_of_unknown_run_returns_404() {
+        let (_tmp, router) = setup(vec![Permission::ViewTasks]);
+        let (status, _) = get(router, "/api/runs/nope/artifacts").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn list_artifacts_without_permission_returns_403() {
+        let (_tmp, router) = setup(vec![Permission::SubmitTask]);
+        let (status, _) = get(router, "/api/runs/run1/artifacts").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn get_artifact_returns_contents() {
+        let (_tmp, router) = setup(vec![Permission::ViewTasks]);
+        let (status, body) = get(router, "/api/runs/run1/artifacts/steps/0.log").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"step output\n");
+    }
+
+    #[tokio::test]
+    async fn get_artifact_serves_ranges() {
+        let (_tmp, router) = setup(vec![Permission::ViewTasks]);
+        let request = |range: Option<&str>| {
+            let mut req =
+                Request::get("/api/runs/run1/artifacts/steps/0.log").header("api_key", "test-key");
+            if let Some(range) = range {
+                req = req.header("range", range);
+            }
+            router.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let resp = request(None).await.unwrap();
+        assert_eq!(resp.headers()["content-length"], "12");
+        assert_eq!(resp.headers()["accept-ranges"], "bytes");
+
+        let resp = request(Some("bytes=5-")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()["content-range"], "bytes 5-11/12");
+        assert_eq!(resp.headers()["content-length"], "7");
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"output\n");
+
+        let resp = request(Some("bytes=-3")).await.unwrap();
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"ut\n");
+
+        let resp = request(Some("bytes=12-")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(resp.headers()["content-range"], "bytes */12");
+    }
+
+    #[test]
+    fn large_sizes_are_in_base_256() {
+        assert_eq!(&size_field(0o777), b"00000000777\0");
+        assert_eq!(&size_field((1 << 33) - 1), b"77777777777\0");
+        let field = size_field(10 << 30);
+        assert_eq!(field[..4], [0x80, 0, 0, 0]);
+        assert_eq!(u64::from_be_bytes(field[4..].try_into().unwrap()), 10 << 30);
+    }
+
+    #[test]
+    fn ranges_are_parsed() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Some((0, 99))));
+        assert_eq!(parse_range("bytes=900-2000", 1000), Some(Some((900, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Some((900, 999))));
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(None));
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+    }
+
+    #[tokio::test]
+    async fn get_artifact_rejects_path_traversal() {
+        let (tmp, router) = setup(vec![Permission::ViewTasks]);
+        std::fs::write(tmp.path().join("secret"), "secret").unwrap();
+        let (status, _) = get(router, "/api/runs/run1/artifacts/..%2F..%2Fsecret").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn log_stream_follows_the_run() {
+        let (tmp, router) = setup(vec![Permission::ViewTasks]);
+        let run = tmp.path().join("Artifacts/run1");
+        let entry =
+            "- time: 2026-01-12T10:00:00Z\n  cmd: echo one\n  result: completed\n  exit_code: 0\n";
+        std::fs::write(run.join("execution_log.yaml"), entry).unwrap();
+        for dir in ["Active", "Completed"] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        std::fs::write(tmp.path().join("Active/run1.yaml"), "").unwrap();
+
+        let finish = {
+            let tasks_path = tmp.path().to_path_buf();
+            let entry = entry.replace("one", "two");
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                let mut log = std::fs::read_to_string(run.join("execution_log.yaml")).unwrap();
+                log.push_str(&entry);
+                std::fs::write(run.join("execution_log.yaml"), log).unwrap();
+                std::fs::rename(
+                    tasks_path.join("Active/run1.yaml"),
+                    tasks_path.join("Completed/run1.yaml"),
+                )
+                .unwrap();
+            })
+        };
+
+        let (status, body) = get(router, "/api/runs/run1/log/stream").await;
+        finish.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        let body = String::from_utf8(body).unwrap();
+        let events: Vec<_> = body
+            .split("\n\n")
+            .filter(|e| !e.trim().is_empty())
+            .collect();
+        assert_eq!(events.len(), 3, "{body}");
+        assert!(events[0].contains("event: step\n"));
+        assert!(events[0].contains("\"cmd\":\"echo one\""));
+        assert!(events[1].contains("\"cmd\":\"echo two\""));
+        assert!(events[1].contains("id: 1\n"));
+        assert!(events[2].contains("event: end\n"));
+        assert!(events[2].contains("data: Completed"));
+    }
+
+    #[tokio::test]
+    async fn execution_log_is_returned_to_the_task_owner() {
+        let (tmp, router) = setup(vec![Permission::ViewOwnTasks]);
+        let entry =
+            "- time: 2026-01-12T10:00:00Z\n  cmd: echo one\n  result: aborted\n  exit_code: 1\n";
+        std::fs::write(tmp.path().join("Artifacts/run1/execution_log.yaml"), entry).unwrap();
+        std::fs::create_dir_all(tmp.path().join("Failed")).unwrap();
+        std::fs::write(
+            tmp.path().join("Failed/run1.yaml"),
+            "owner: key:config-0\nsteps: []\n",
+        )
+        .unwrap();
+
+        let (status, body) = get(router.clone(), "/api/runs/run1/execution").await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["state"], "Failed");
+        assert_eq!(json["entries"][0]["cmd"], "echo one");
+        assert_eq!(json["entries"][0]["result"], "aborted");
+
+        std::fs::write(
+            tmp.path().join("Failed/run1.yaml"),
+            "owner: someone\nsteps: []\n",
+        )
+        .unwrap();
+        let (status, _) = get(router, "/api/runs/run1/artifacts/steps/0.log").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn download_contains_all_artifacts() {
+        let (_tmp, router) = setup(vec![Permission::ViewTasks]);
+        let (status, body) = get(router, "/api/runs/run1/download").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let mut tar = Vec::new();
+        GzDecoder::new(body.as_slice())
+            .read_to_end(&mut tar)
+            .unwrap();
+        // Header, contents padded to a block, for each file, then two zero blocks
+        assert_eq!(tar.len(), 2 * 1024 + 1024);
+        assert!(tar.starts_with(b"run1/execution_log.yaml\0"));
+        assert_eq!(&tar[512..517], b"hello");
+        assert!(tar[1024..].starts_with(b"run1/steps/0.log\0"));
+        assert_eq!(&tar[1024 + 512..1024 + 524], b"step output\n");
+    }
+
+    #[tokio::test]
+    async fn download_streams_large_artifacts() {
+        let (tmp, router) = setup(vec![Permission::ViewTasks]);
+        let recording: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(tmp.path().join("Artifacts/run1/steps/1.raw"), &recording).unwrap();
+
+        let (status, body) = get(router, "/api/runs/run1/download").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let mut tar = Vec::new();
+        GzDecoder::new(body.as_slice())
+            .read_to_end(&mut tar)
+            .unwrap();
+        let offset = 2 * 1024 + 512;
+        assert!(tar[2 * 1024..].starts_with(b"run1/steps/1.raw\0"));
+        assert_eq!(&tar[offset..offset + recording.len()], recording.as_slice());
+    }
+}
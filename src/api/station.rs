@@ -1,13 +1,22 @@
 use axum::Json;
-use axum::extract::State;
+use axum::extract::{Path as AxumPath, State};
+use axum::http::StatusCode;
 use serde::Serialize;
+use tracing::{info, warn};
 use utoipa::ToSchema;
 
 use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use crate::config::Permission;
+use crate::track::resolve_rotator;
+use crate::tracker::rotator;
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct StationInfo {
     pub name: String,
+    /// Other stations served by this instance, under `/api/v1/stations/{name}`
+    pub hosted_stations: Vec<String>,
 }
 
 /// Get station information.
@@ -24,5 +33,159 @@ pub async fn get_station(
 ) -> Json<StationInfo> {
     Json(StationInfo {
         name: state.config.station_name.clone(),
+        hosted_stations: state.config.hosted_stations.keys().cloned().collect(),
     })
 }
+
+/// Park a rotator at its configured park position.
+///
+/// The rotator is the resource `name`, driven through the `rotctld` server at its `address`.
+/// Without a `park` position, the park command of `rotctld` is used.
+#[utoipa::path(
+    post,
+    path = "/rotator/{name}/park",
+    tag = super::STATION_TAG,
+    params(
+        ("name" = String, Path, description = "Resource name of the rotator")
+    ),
+    responses(
+        (status = 204, description = "Rotator parked"),
+        (status = 400, description = "The resource has no address"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Resource not found"),
+        (status = 502, description = "The rotctld server failed"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn park_rotator(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(name): AxumPath<String>,
+) -> Result<StatusCode, ApiError> {
+    auth.require(Permission::ControlHardware)?;
+
+    // Only configured resources, not arbitrary addresses
+    if !state.config.resources.iter().any(|r| r.name == name) {
+        return Err(ApiError::NotFound);
+    }
+    let rotator =
+        resolve_rotator(&name, &state.config).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    if let Err(e) = rotator::park(&rotator).await {
+        warn!(%name, ?e, "failed to park rotator");
+        return Err(ApiError::BadGateway(format!(
+            "failed to park rotator {name}: {e:#}"
+        )));
+    }
+
+    info!(%name, address = %rotator.address, "rotator parked");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use sat_o_mat::tracker::rotator::ParkPosition;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission, ResourceConfig};
+
+    fn test_config(tmp: &TempDir, resources: Vec<ResourceConfig>) -> Config {
+        Config {
+            resources,
+            ..Config::for_test(
+                tmp.path(),
+                vec![
+                    ApiKey {
+                        name: None,
+                        id: None,
+                        key: "test-key".into(),
+                        permissions: vec![Permission::ControlHardware],
+                    },
+                    ApiKey {
+                        name: None,
+                        id: None,
+                        key: "viewer-key".into(),
+                        permissions: vec![Permission::ViewTasks],
+                    },
+                ],
+            )
+        }
+    }
+
+    async fn park(config: &Config, name: &str, key: &str) -> StatusCode {
+        let router = api::routes(api::state(config)).split_for_parts().0;
+        let req = Request::builder()
+            .method("POST")
+            .uri(format!("/api/v1/rotator/{name}/park"))
+            .header("api_key", key)
+            .body(Body::empty())
+            .unwrap();
+        router.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn rotators_are_parked_at_their_park_position() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let rotctld = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read, mut write) = socket.into_split();
+            let mut line = String::new();
+            BufReader::new(read).read_line(&mut line).await.unwrap();
+            write.write_all(b"RPRT 0\n").await.unwrap();
+            line
+        });
+
+        let tmp = TempDir::new().unwrap();
+        let rotator = |name: &str, address: Option<String>| ResourceConfig {
+            name: name.into(),
+            commands: vec!["rotctl".into()],
+            transmit: false,
+            address,
+            limits: None,
+            park: Some(ParkPosition {
+                azimuth: 180.0,
+                elevation: 90.0,
+            }),
+            sdr: None,
+        };
+        let config = test_config(
+            &tmp,
+            vec![
+                rotator("uhf1", Some(address)),
+                rotator("vhf", None),
+                // Nothing listens on port 1
+                rotator("broken", Some("127.0.0.1:1".into())),
+            ],
+        );
+
+        assert_eq!(
+            park(&config, "uhf1", "test-key").await,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(rotctld.await.unwrap(), "P 180.00 90.00\n");
+
+        assert_eq!(
+            park(&config, "uhf1", "viewer-key").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            park(&config, "sband", "test-key").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            park(&config, "vhf", "test-key").await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            park(&config, "broken", "test-key").await,
+            StatusCode::BAD_GATEWAY
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! The status of the whole station in a single document, for monitoring systems: the runs in
+//! progress and their trackers, the rotators and radios, the TLE catalog and the disk space.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use axum::Json;
+use axum::extract::State;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::time::timeout;
+use utoipa::ToSchema;
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use super::metrics::tracker_statuses;
+use crate::config::{Permission, ResourceConfig};
+use crate::doctor::free_space;
+use crate::tracker::rigctl::RigctlClient;
+use crate::tracker::rotctl::RotctlClient;
+
+/// How long rotators and radios have to answer, so that one unreachable does not hold the status.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StationStatus {
+    pub station: String,
+    #[schema(value_type = String)]
+    pub time: DateTime<Utc>,
+    pub runs: Vec<RunInProgress>,
+    /// Latest update of the trackers running as steps of the runs in progress
+    pub trackers: Vec<TrackerSample>,
+    /// Rotator resources with an address, as reported by their `rotctld` server
+    pub rotators: Vec<RotatorState>,
+    /// Radio resources with an address, as reported by their `rigctld` server
+    pub radios: Vec<RadioState>,
+    pub tles: TleCatalog,
+    pub disk: DiskSpace,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunInProgress {
+    pub task: String,
+    /// Index of the last step started
+    pub step: Option<usize>,
+    pub paused: bool,
+    pub aborted: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrackerSample {
+    /// Task of the run the tracker is a step of
+    pub task: String,
+    pub object: String,
+    #[schema(value_type = String)]
+    pub time: DateTime<Utc>,
+    pub azimuth: f64,
+    pub elevation: f64,
+    /// Range rate (m/s)
+    pub range_rate: f64,
+    /// Doppler shift (Hz) of the uplink frequency
+    pub tx_doppler: Option<i64>,
+    /// Doppler shift (Hz) of the downlink frequency
+    pub rx_doppler: Option<i64>,
+    /// Pointing error (degrees) of each rotator
+    pub rotator_errors: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RotatorState {
+    pub name: String,
+    pub address: String,
+    pub azimuth: Option<f64>,
+    pub elevation: Option<f64>,
+    /// Why the position is unknown
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RadioState {
+    pub name: String,
+    pub address: String,
+    /// Frequency (Hz) of the current VFO
+    pub frequency: Option<u64>,
+    /// Why the frequency is unknown
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TleCatalog {
+    pub satellites: usize,
+    /// Epoch of the oldest elements loaded
+    #[schema(value_type = Option<String>)]
+    pub oldest_epoch: Option<DateTime<Utc>>,
+    /// Epoch of the newest elements loaded
+    #[schema(value_type = Option<String>)]
+    pub newest_epoch: Option<DateTime<Utc>>,
+    /// Satellites with elements older than `predict.max_element_age_days`
+    pub stale: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DiskSpace {
+    /// Directory the tasks and their artifacts are kept in
+    pub path: String,
+    /// Free space (bytes), unknown if it could not be determined
+    pub free_bytes: Option<u64>,
+}
+
+/// Get the status of the station.
+///
+/// Gathers what is otherwise spread over several endpoints, and the state of the hardware:
+/// rotators and radios are asked for their position and frequency on each request.
+#[utoipa::path(
+    get,
+    path = "/status",
+    tag = super::STATION_TAG,
+    responses(
+        (status = 200, description = "Station status", body = StationStatus),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_status(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+) -> Result<Json<StationStatus>, ApiError> {
+    auth.require(Permission::ViewTasks)?;
+
+    let probes: Vec<_> = state
+        .config
+        .resources
+        .iter()
+        .filter_map(|resource| {
+            let address = resource.address.clone()?;
+            let resource = resource.clone();
+            Some(tokio::spawn(probe(resource, address)))
+        })
+        .collect();
+
+    let runs = state.runs.statuses();
+    let trackers = tracker_statuses(&state, &runs)
+        .await
+        .into_iter()
+        .map(|(task, t)| TrackerSample {
+            task,
+            object: t.object,
+            time: t.time,
+            azimuth: t.azimuth,
+            elevation: t.elevation,
+            range_rate: t.range_rate,
+            tx_doppler: t.tx_doppler,
+            rx_doppler: t.rx_doppler,
+            rotator_errors: t.rotator_errors,
+        })
+        .collect();
+    let runs = runs
+        .into_iter()
+        .map(|(task, status)| RunInProgress {
+            task,
+            step: status.step,
+            paused: status.paused,
+            aborted: status.aborted,
+        })
+        .collect();
+
+    let now = Utc::now();
+    let tles = {
+        let predict_db = state.predict_db.lock().await;
+        let epochs: Vec<DateTime<Utc>> = predict_db
+            .iter()
+            .map(|(_, sat)| now - sat.element_age(now))
+            .collect();
+        let max_age = state.config.predict.max_element_age_days;
+        TleCatalog {
+            satellites: epochs.len(),
+            oldest_epoch: epochs.iter().min().copied(),
+            newest_epoch: epochs.iter().max().copied(),
+            stale: epochs
+                .iter()
+                .filter(|epoch| (now - **epoch).num_seconds() as f64 / 86400.0 > max_age)
+                .count(),
+        }
+    };
+
+    let tasks_path = state.tasks_path.clone();
+    let free_bytes = tokio::task::spawn_blocking(move || free_space(&tasks_path))
+        .await
+        .ok()
+        .flatten();
+
+    let (mut rotators, mut radios) = (Vec::new(), Vec::new());
+    for probe in probes {
+        match probe.await {
+            Ok(Hardware::Rotator(rotator)) => rotators.push(rotator),
+            Ok(Hardware::Radio(radio)) => radios.push(radio),
+            Ok(Hardware::Other) | Err(_) => {}
+        }
+    }
+
+    Ok(Json(StationStatus {
+        station: state.config.station_name.clone(),
+        time: now,
+        runs,
+        trackers,
+        rotators,
+        radios,
+        tles,
+        disk: DiskSpace {
+            path: state.tasks_path.display().to_string(),
+            free_bytes,
+        },
+    }))
+}
+
+enum Hardware {
+    Rotator(RotatorState),
+    Radio(RadioState),
+    Other,
+}
+
+/// Asks the `rotctld` or `rigctld` server at `address` where the rotator or radio `resource` is.
+async fn probe(resource: ResourceConfig, address: String) -> Hardware {
+    let controlled_by = |prefix: &str| resource.commands.iter().any(|c| c.starts_with(prefix));
+    if controlled_by("rotctl") {
+        let position = timeout(PROBE_TIMEOUT, async {
+            RotctlClient::connect(&address).await?.get_position().await
+        })
+        .await;
+        let (position, error) = match position {
+            Ok(Ok(position)) => (Some(position), None),
+            Ok(Err(e)) => (None, Some(format!("{e:#}"))),
+            Err(_) => (None, Some("did not answer".to_string())),
+        };
+        return Hardware::Rotator(RotatorState {
+            name: resource.name,
+            address,
+            azimuth: position.map(|(az, _)| az),
+            elevation: position.map(|(_, el)| el),
+            error,
+        });
+    }
+    if controlled_by("rigctl") {
+        let frequency = timeout(PROBE_TIMEOUT, async {
+            RigctlClient::connect(&address).await?.get_frequency().await
+        })
+        .await;
+        let (frequency, error) = match frequency {
+            Ok(Ok(frequency)) => (Some(frequency), None),
+            Ok(Err(e)) => (None, Some(format!("{e:#}"))),
+            Err(_) => (None, Some("did not answer".to_string())),
+        };
+        return Hardware::Radio(RadioState {
+            name: resource.name,
+            address,
+            frequency,
+            error,
+        });
+    }
+    Hardware::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission, ResourceConfig};
+
+    /// A `rotctld` or `rigctld` server answering each command with `reply`.
+    async fn server(reply: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read, mut write) = socket.into_split();
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(_)) = lines.next_line().await {
+                write.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        address
+    }
+
+    fn resource(name: &str, command: &str, address: String) -> ResourceConfig {
+        ResourceConfig {
+            name: name.into(),
+            commands: vec![command.into()],
+            transmit: false,
+            address: Some(address),
+            limits: None,
+            park: None,
+            sdr: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn status_gathers_the_station() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join("tle")).unwrap();
+        std::fs::copy(
+            "examples/tle/nanoff_a.txt",
+            tmp.path().join("tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        let key = |key: &str, permission| ApiKey {
+            name: None,
+            id: None,
+            key: key.into(),
+            permissions: vec![permission],
+        };
+        let mut config = Config::for_test(
+            tmp.path(),
+            vec![
+                key("test-key", Permission::ViewTasks),
+                key("submit-key", Permission::SubmitTask),
+            ],
+        );
+        config.resources = vec![
+            resource("uhf1", "rotctl", server("180.5\n45.25\n").await),
+            resource("radio", "rigctl", server("437500000\n").await),
+            // Nothing listens on port 1
+            resource("broken", "rigctl", "127.0.0.1:1".into()),
+        ];
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+
+        let req = Request::get("/api/status")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status["station"], "test");
+        assert_eq!(status["runs"], serde_json::json!([]));
+        assert_eq!(status["rotators"][0]["name"], "uhf1");
+        assert_eq!(status["rotators"][0]["azimuth"], 180.5);
+        assert_eq!(status["rotators"][0]["elevation"], 45.25);
+        assert_eq!(status["radios"][0]["frequency"], 437_500_000);
+        assert_eq!(status["radios"][1]["name"], "broken");
+        assert!(status["radios"][1]["error"].is_string());
+        assert_eq!(status["tles"]["satellites"], 1);
+        assert!(status["tles"]["oldest_epoch"].is_string());
+        assert_eq!(
+            status["disk"]["path"],
+            tmp.path().display().to_string().as_str()
+        );
+
+        let req = Request::get("/api/status")
+            .header("api_key", "submit-key")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -1,21 +1,38 @@
 use axum::Json;
-use axum::extract::{Path as AxumPath, State};
-use axum::http::StatusCode;
-use serde::Serialize;
-use tracing::{info, warn};
-use utoipa::ToSchema;
-
-use crate::config::Permission;
+use axum::extract::{Path as AxumPath, Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::task::format::{TASK_STATES, Task};
-use crate::task::utils::check_time_conflict;
+use crate::config::{NotificationEvent, Permission};
+use crate::dry_run::{self, DryRun};
+use crate::scheduler;
+use crate::task::format::{Rejected, TASK_STATES, Task};
+use crate::task::index::IndexedTask;
+use crate::task::runner::{RunControl, RunStatus, read_execution_log};
+use crate::task::utils::{check_time_conflict, find_overlapping};
+use crate::validate::{self, Problem, Report};
 
 use super::AppState;
 use super::auth::AuthenticatedKey;
 use super::error::ApiError;
+use super::review::check_hardware;
+use super::runs::{ARTIFACTS_DIR, ApiExecutionSummary};
 
 const EDITABLE_STATES: &[&str] = &["Active", "PendingApproval"];
+/// States of the tasks that were run.
+const FINISHED_STATES: &[&str] = &["Completed", "Failed"];
+
+/// Task shown in the OpenAPI document.
+const TASK_EXAMPLE: &str = "\
+variables:
+  start: \"2026-06-01T10:00:00Z\"
+  end: \"2026-06-01T10:15:00Z\"
+steps:
+  - cmd: \"rtl_fm -f 137.1M -s 48k recording.raw\"
+";
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct TaskListEntry {
@@ -23,17 +40,104 @@ pub struct TaskListEntry {
     pub state: String,
     pub start: Option<String>,
     pub end: Option<String>,
+    /// Identity of whoever submitted the task, if known
+    pub owner: Option<String>,
+    /// Time the task was submitted or last edited, as RFC3339
+    pub submitted: Option<String>,
+    /// Progress of the run, while the task is running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run: Option<ApiRunStatus>,
+    /// Why the task was rejected, if the approver gave a reason
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<String>,
+    /// Outcome of the run, once the task is Completed or Failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution: Option<ApiExecutionSummary>,
 }
 
+/// Progress of a task being run.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiRunStatus {
+    /// `running`, `paused`, or `aborting` while the steps are stopped and the cleanup steps run
+    pub state: String,
+    /// Index of the last step started, if any
+    pub step: Option<usize>,
+}
+
+impl From<RunStatus> for ApiRunStatus {
+    fn from(status: RunStatus) -> Self {
+        let state = if status.aborted {
+            "aborting"
+        } else if status.paused {
+            "paused"
+        } else {
+            "running"
+        };
+        Self {
+            state: state.to_string(),
+            step: status.step,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TaskListQuery {
+    /// Only list the tasks in this state, e.g. `rejected`. Case insensitive.
+    pub state: Option<String>,
+    /// Only list the tasks submitted by this owner, or by the caller if `me`.
+    pub owner: Option<String>,
+    /// Sort by task start time (`start`, the default) or submission time (`submitted`).
+    #[serde(default)]
+    #[param(value_type = Option<String>)]
+    pub sort: TaskSort,
+    /// Sort order: `desc` (the default) or `asc`.
+    #[serde(default)]
+    #[param(value_type = Option<String>)]
+    pub order: SortOrder,
+    /// Maximum number of tasks to return.
+    pub limit: Option<usize>,
+    /// Number of tasks to skip.
+    #[serde(default)]
+    pub offset: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSort {
+    #[default]
+    Start,
+    Submitted,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
 /// List all tasks.
 ///
-/// Returns tasks in all states (Active, PendingApproval, Completed and Failed).
+/// Returns tasks in all states (Active, PendingApproval, Completed, Failed and Rejected), or in
+/// `state`. With ViewOwnTasks instead of ViewTasks permission, only the caller's own tasks are
+/// listed, so that submitters can see why theirs were rejected.
+///
+/// Completed and Failed tasks are kept, with the outcome of their run, until deleted by the
+/// retention policy of the daemon (`daemon.retention.max_history_days`).
+///
+/// The total number of matching tasks, before `limit` and `offset`, is returned in the
+/// `X-Total-Count` header.
 #[utoipa::path(
     get,
     path = "/tasks",
     tag = super::TASKS_TAG,
+    params(TaskListQuery),
     responses(
-        (status = 200, description = "List of tasks", body = Vec<TaskListEntry>),
+        (status = 200, description = "List of tasks", body = Vec<TaskListEntry>,
+            headers(("X-Total-Count" = usize, description = "Total number of matching tasks"))),
         (status = 401, description = "Missing or invalid API key"),
         (status = 403, description = "Insufficient permissions"),
     ),
@@ -42,48 +146,87 @@ pub struct TaskListEntry {
 pub async fn list_tasks(
     State(state): State<AppState>,
     auth: AuthenticatedKey,
-) -> Result<Json<Vec<TaskListEntry>>, ApiError> {
-    auth.require(Permission::ViewTasks)?;
+    Query(query): Query<TaskListQuery>,
+) -> Result<(HeaderMap, Json<Vec<TaskListEntry>>), ApiError> {
+    let view_all = auth.can_view_all()?;
+    let owner = match query.owner.as_deref() {
+        Some("me") => Some(auth.owner.as_str()),
+        owner => owner,
+    };
 
     let mut entries = Vec::new();
-    for &dir in TASK_STATES {
-        let dir_path = state.tasks_path.join(dir);
-        let Ok(mut read_dir) = tokio::fs::read_dir(&dir_path).await else {
-            continue;
-        };
-        while let Ok(Some(entry)) = read_dir.next_entry().await {
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            let id = Task::id_from_filename(&file_name).to_string();
-
-            let Some(task) = tokio::fs::read_to_string(entry.path())
-                .await
-                .ok()
-                .and_then(|c| Task::from_yaml_str(&c).ok())
-            else {
+    let states = TASK_STATES
+        .iter()
+        .filter(|dir| (query.state.as_ref()).is_none_or(|state| dir.eq_ignore_ascii_case(state)));
+    for &dir in states {
+        for IndexedTask { id, task, modified } in state.tasks.tasks(dir).await {
+            if (!view_all && !auth.owns(task.owner.as_deref()))
+                || owner.is_some_and(|owner| task.owner.as_deref() != Some(owner))
+            {
                 continue;
-            };
+            }
 
             let start = task.get_time_variable("start").ok();
             let end = task.get_time_variable("end").ok();
+            let submitted = Some(DateTime::<Utc>::from(modified));
+            let sort_key = match query.sort {
+                TaskSort::Start => start,
+                TaskSort::Submitted => submitted,
+            };
 
             entries.push((
-                start,
+                sort_key,
                 TaskListEntry {
+                    run: state.runs.get(&id).map(|control| control.status().into()),
                     id,
                     state: dir.to_string(),
                     start: start.map(|t| t.to_string()),
                     end: end.map(|t| t.to_string()),
+                    owner: task.owner.clone(),
+                    submitted: submitted.map(|t| t.to_rfc3339()),
+                    rejection: (task.rejected.as_ref())
+                        .and_then(|rejected| rejected.reason.clone()),
+                    execution: None,
                 },
             ));
         }
     }
 
-    entries.sort_by(|a, b| b.0.cmp(&a.0));
+    entries.sort_by_key(|(key, _)| *key);
+    if let SortOrder::Desc = query.order {
+        entries.reverse();
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(entries.len()));
+    let mut page: Vec<TaskListEntry> = entries
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .map(|(_, e)| e)
+        .collect();
+
+    // Only the execution logs of the page are read
+    let artifacts = state.tasks_path.join(ARTIFACTS_DIR);
+    page = tokio::task::spawn_blocking(move || {
+        for entry in page.iter_mut() {
+            if FINISHED_STATES.contains(&entry.state.as_str())
+                && let Ok(log) = read_execution_log(&artifacts.join(&entry.id))
+            {
+                entry.execution = Some(ApiExecutionSummary::from(log.as_slice()));
+            }
+        }
+        page
+    })
+    .await
+    .map_err(|_| ApiError::Internal)?;
 
-    Ok(Json(entries.into_iter().map(|(_, e)| e).collect()))
+    Ok((headers, Json(page)))
 }
 
 /// Get the full YAML text of a specific task.
+///
+/// Requires ViewTasks permission, or ViewOwnTasks if the task was submitted by the caller.
 #[utoipa::path(
     get,
     path = "/tasks/{id}",
@@ -92,7 +235,7 @@ pub async fn list_tasks(
         ("id" = String, Path, description = "Task unique identifier (filename)")
     ),
     responses(
-        (status = 200, description = "Task YAML content", body = String),
+        (status = 200, description = "Task YAML content", body = String, example = json!(TASK_EXAMPLE)),
         (status = 401, description = "Missing or invalid API key"),
         (status = 403, description = "Insufficient permissions"),
         (status = 404, description = "Task not found"),
@@ -104,11 +247,10 @@ pub async fn get_task(
     auth: AuthenticatedKey,
     AxumPath(id): AxumPath<String>,
 ) -> Result<String, ApiError> {
-    auth.require(Permission::ViewTasks)?;
-
     let (_task_state, content) = Task::find(&state.tasks_path, &id)
         .await
         .ok_or(ApiError::NotFound)?;
+    auth.require_on(Permission::ViewTasks, task_owner(&content).as_deref())?;
 
     Ok(content)
 }
@@ -119,8 +261,12 @@ pub async fn get_task(
 /// PendingApproval unless the API key has AutoApproveTask permission, in which case
 /// they go directly to Active.
 ///
-/// If the task already exists, requires EditTask permission. Only tasks in Active or
-/// PendingApproval state can be edited.
+/// If the task already exists, requires EditTask permission, or EditOwnTasks if the task was
+/// submitted by the caller. Only tasks in Active or PendingApproval state can be edited. An Active
+/// task whose definition changes goes back to PendingApproval, unless the API key has
+/// AutoApproveTask permission. Running tasks cannot be edited.
+///
+/// New tasks are owned by the caller. The owner of an existing task is kept.
 ///
 /// Returns 409 if the task's time range conflicts with another active task.
 #[utoipa::path(
@@ -130,14 +276,14 @@ pub async fn get_task(
     params(
         ("id" = String, Path, description = "Task unique identifier (filename)")
     ),
-    request_body = String,
+    request_body(content = String, description = "Task YAML", example = json!(TASK_EXAMPLE)),
     responses(
         (status = 200, description = "Task updated"),
         (status = 201, description = "Task created"),
         (status = 400, description = "Invalid task definition"),
         (status = 401, description = "Missing or invalid API key"),
         (status = 403, description = "Insufficient permissions"),
-        (status = 409, description = "Task is not editable or has a time conflict"),
+        (status = 409, description = "Task is not editable, is running or has a time conflict"),
     ),
     security(("api_key" = []))
 )]
@@ -147,30 +293,24 @@ pub async fn put_task(
     AxumPath(id): AxumPath<String>,
     body: String,
 ) -> Result<StatusCode, ApiError> {
-    // Reject path traversal
-    if id.contains('/') || id.contains('\\') || id == ".." || id == "." {
+    if !Task::is_valid_id(&id) {
         return Err(ApiError::BadRequest("invalid task ID".to_string()));
     }
 
-    // Validate the task definition
-    let task = Task::from_yaml_str(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
-
-    // Check for time conflicts with other active tasks
-    if let Some(conflict) = check_time_conflict(&state.tasks_path, &id, &task).await {
-        return Err(ApiError::Conflict(format!(
-            "time conflict with task '{conflict}'"
-        )));
-    }
-
-    let (target_dir, status) = match Task::find(&state.tasks_path, &id).await {
-        Some((task_state, _)) => {
-            auth.require(Permission::EditTask)?;
+    // Check the permissions before revealing anything about the other tasks
+    let (task_state, status, owner, previous) = match Task::find(&state.tasks_path, &id).await {
+        Some((task_state, content)) => {
+            let owner = task_owner(&content);
+            auth.require_on(Permission::EditTask, owner.as_deref())?;
             if !EDITABLE_STATES.contains(&task_state.as_str()) {
                 return Err(ApiError::Conflict(format!(
                     "task in state '{task_state}' cannot be edited"
                 )));
             }
-            (task_state, StatusCode::OK)
+            if state.runs.get(&id).is_some() {
+                return Err(ApiError::Conflict(format!("task '{id}' is running")));
+            }
+            (task_state, StatusCode::OK, owner, Some(content))
         }
         None => {
             auth.require(Permission::SubmitTask)?;
@@ -179,22 +319,75 @@ pub async fn put_task(
             } else {
                 "PendingApproval"
             };
-            (dir.to_string(), StatusCode::CREATED)
+            (
+                dir.to_string(),
+                StatusCode::CREATED,
+                Some(auth.owner.clone()),
+                None,
+            )
         }
     };
 
+    // Validate the task definition
+    let task = Task::from_yaml_str(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let unresolved = check_hardware(&task, &state.config.resources);
+    if !unresolved.is_empty() {
+        return Err(ApiError::BadRequest(unresolved.join("; ")));
+    }
+
+    // Check for time conflicts with other active tasks
+    if let Some(conflict) = check_time_conflict(&state.tasks, &id, &task).await {
+        return Err(ApiError::Conflict(format!(
+            "time conflict with task '{conflict}'"
+        )));
+    }
+
+    let body = if task.owner == owner {
+        body
+    } else {
+        with_owner(&body, owner)?
+    };
+    let reapproval = task_state == "Active"
+        && previous.is_some_and(|previous| previous != body)
+        && !auth.has(Permission::AutoApproveTask);
+    let target_dir = if reapproval {
+        "PendingApproval".to_string()
+    } else {
+        task_state.clone()
+    };
+    if target_dir == "Active" {
+        preempt(&state, &id, &task).await?;
+    }
+
     let file_path = state.tasks_path.join(&target_dir).join(Task::filename(&id));
     tokio::fs::write(&file_path, &body).await.map_err(|e| {
         warn!(%id, ?e, "failed to write task file");
         ApiError::Internal
     })?;
+    if reapproval {
+        let old_path = state.tasks_path.join(&task_state).join(Task::filename(&id));
+        tokio::fs::remove_file(&old_path).await.map_err(|e| {
+            warn!(%id, ?e, "failed to remove approved task file");
+            ApiError::Internal
+        })?;
+        state.notifier.pending_approval(&id);
+    }
 
-    info!(%id, %target_dir, created = status == StatusCode::CREATED);
+    info!(%id, %target_dir, created = status == StatusCode::CREATED, reapproval);
+    if status == StatusCode::CREATED {
+        state.notifier.notify(NotificationEvent::TaskSubmitted, &id);
+        if target_dir == "Active" {
+            state.notifier.notify(NotificationEvent::TaskApproved, &id);
+        } else {
+            state.notifier.pending_approval(&id);
+        }
+    }
     Ok(status)
 }
 
 /// Delete a task
 ///
+/// Requires DeleteTask permission, or DeleteOwnTasks if the task was submitted by the caller.
 /// Only tasks in Active or PendingApproval state can be deleted.
 #[utoipa::path(
     delete,
@@ -217,11 +410,10 @@ pub async fn delete_task(
     auth: AuthenticatedKey,
     AxumPath(id): AxumPath<String>,
 ) -> Result<StatusCode, ApiError> {
-    auth.require(Permission::DeleteTask)?;
-
-    let (task_state, _) = Task::find(&state.tasks_path, &id)
+    let (task_state, content) = Task::find(&state.tasks_path, &id)
         .await
         .ok_or(ApiError::NotFound)?;
+    auth.require_on(Permission::DeleteTask, task_owner(&content).as_deref())?;
 
     if !EDITABLE_STATES.contains(&task_state.as_str()) {
         return Err(ApiError::Conflict(format!(
@@ -236,9 +428,402 @@ pub async fn delete_task(
     })?;
 
     info!(%id, %task_state, "task deleted");
+    if task_state == "PendingApproval" {
+        state.notifier.notify(NotificationEvent::TaskRejected, &id);
+    }
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Pause a running task.
+///
+/// The steps already running go on, but no further step is started until the task is resumed,
+/// even once its time has come. The end of the task still stops it.
+///
+/// Requires EditTask permission, or EditOwnTasks if the task was submitted by the caller.
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/pause",
+    tag = super::TASKS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Task paused", body = ApiRunStatus),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Task not found"),
+        (status = 409, description = "Task is not running"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn pause_task(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<ApiRunStatus>, ApiError> {
+    let control = running_task(&state, &auth, &id).await?;
+    control.pause();
+    info!(%id, "run paused");
+    Ok(Json(control.status().into()))
+}
+
+/// Resume a paused task.
+///
+/// Steps whose time has passed while the task was paused are started right away.
+///
+/// Requires EditTask permission, or EditOwnTasks if the task was submitted by the caller.
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/resume",
+    tag = super::TASKS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Task resumed", body = ApiRunStatus),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Task not found"),
+        (status = 409, description = "Task is not running"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn resume_task(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<ApiRunStatus>, ApiError> {
+    let control = running_task(&state, &auth, &id).await?;
+    control.resume();
+    info!(%id, "run resumed");
+    Ok(Json(control.status().into()))
+}
+
+/// Abort a running task.
+///
+/// The running steps are stopped like at the end of the task, then the cleanup steps are run and
+/// the task is moved to Failed.
+///
+/// Requires EditTask permission, or EditOwnTasks if the task was submitted by the caller.
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/abort",
+    tag = super::TASKS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Task aborted", body = ApiRunStatus),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Task not found"),
+        (status = 409, description = "Task is not running"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn abort_task(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<ApiRunStatus>, ApiError> {
+    let control = running_task(&state, &auth, &id).await?;
+    control.abort();
+    info!(%id, "run aborted");
+    Ok(Json(control.status().into()))
+}
+
+/// Returns the control of the run of task `id`, if the caller may edit it and it is running.
+async fn running_task(
+    state: &AppState,
+    auth: &AuthenticatedKey,
+    id: &str,
+) -> Result<RunControl, ApiError> {
+    let (_task_state, content) = Task::find(&state.tasks_path, id)
+        .await
+        .ok_or(ApiError::NotFound)?;
+    auth.require_on(Permission::EditTask, task_owner(&content).as_deref())?;
+    state
+        .runs
+        .get(id)
+        .ok_or_else(|| ApiError::Conflict("task is not running".to_string()))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ValidateQuery {
+    /// ID the task would be submitted as, so that an existing task is not reported as
+    /// conflicting with itself.
+    pub id: Option<String>,
+}
+
+/// Validate a task without submitting it.
+///
+/// Checks the task like `sat-o-mat validate` does, and for time conflicts with Active tasks like
+/// submitting it would. Errors in the YAML are reported with their line and column. The report
+/// also contains the steps with their variables substituted and times resolved.
+///
+/// Requires SubmitTask permission.
+#[utoipa::path(
+    post,
+    path = "/tasks/validate",
+    tag = super::TASKS_TAG,
+    params(ValidateQuery),
+    request_body(content = String, description = "Task YAML", example = json!(TASK_EXAMPLE)),
+    responses(
+        (status = 200, description = "Validation report", body = Report),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn validate_task(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Query(query): Query<ValidateQuery>,
+    body: String,
+) -> Result<Json<Report>, ApiError> {
+    auth.require(Permission::SubmitTask)?;
+
+    let id = query.id.unwrap_or_default();
+    let mut report = validate::validate(Task::filename(&id).into(), &body, &state.config);
+    if let Ok(task) = Task::from_yaml_str(&body)
+        && task.time_range().is_ok()
+        && let Some(conflict) = check_time_conflict(&state.tasks, &id, &task).await
+    {
+        report.errors.push(Problem::new(format!(
+            "time conflict with task '{conflict}'"
+        )));
+        report.valid = false;
+    }
+    Ok(Json(report))
+}
+
+/// Dry run a task.
+///
+/// Goes through the task as the runner would, without touching the hardware or spawning any
+/// process: the time each step would start at with the variables substituted, and the station
+/// resources it would use. Steps waited for are assumed to finish at once, and the others to run
+/// until the end of the task.
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/dry-run",
+    tag = super::TASKS_TAG,
+    params(
+        ("id" = String, Path, description = "Task unique identifier (filename)")
+    ),
+    responses(
+        (status = 200, description = "Dry run of the task", body = DryRun),
+        (status = 400, description = "Invalid task definition"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Task not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn dry_run_task(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<DryRun>, ApiError> {
+    let (_, content) = Task::find(&state.tasks_path, &id)
+        .await
+        .ok_or(ApiError::NotFound)?;
+    let task = Task::from_yaml_str(&content)
+        .map_err(|e| ApiError::BadRequest(format!("invalid task: {e}")))?;
+    auth.require_on(Permission::ViewTasks, task.owner.as_deref())?;
+
+    let dry_run =
+        dry_run::dry_run(&task, &state.config).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    Ok(Json(dry_run))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchAction {
+    /// Move the tasks from PendingApproval to Active
+    Approve,
+    /// Move the tasks from PendingApproval to Rejected, recording who rejected them and why
+    Reject,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({"action": "approve", "ids": ["noaa19-2026-06-01", "meteor-2026-06-01"]}))]
+pub struct BatchRequest {
+    pub action: BatchAction,
+    pub ids: Vec<String>,
+    /// Why the tasks are rejected, shown to their submitters
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResult {
+    pub id: String,
+    pub ok: bool,
+    /// Why the task could not be approved or rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Approve or reject several tasks pending approval.
+///
+/// The tasks are processed in order, so a task conflicting with one approved earlier in the same
+/// batch is not approved. Returns the result for each task.
+#[utoipa::path(
+    post,
+    path = "/tasks/approve-batch",
+    tag = super::TASKS_TAG,
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Result for each task", body = Vec<BatchResult>),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn approve_batch(
+    State(state): State<AppState>,
+    auth: AuthenticatedKey,
+    Json(req): Json<BatchRequest>,
+) -> Result<Json<Vec<BatchResult>>, ApiError> {
+    auth.require(Permission::ApproveTask)?;
+
+    let mut results = Vec::with_capacity(req.ids.len());
+    for id in req.ids {
+        let outcome = match req.action {
+            BatchAction::Approve => approve(&state, &id).await,
+            BatchAction::Reject => reject(&state, &id, &auth.owner, req.reason.clone()).await,
+        };
+        results.push(BatchResult {
+            ok: outcome.is_ok(),
+            error: outcome.err(),
+            id,
+        });
+    }
+
+    Ok(Json(results))
+}
+
+/// Moves task `id` from PendingApproval to Active, unless it conflicts with an Active task.
+async fn approve(state: &AppState, id: &str) -> Result<(), String> {
+    let task = pending_task(state, id).await?;
+    if task.time_range().is_ok()
+        && let Some(conflict) = check_time_conflict(&state.tasks, id, &task).await
+    {
+        return Err(format!("time conflict with task '{conflict}'"));
+    }
+    preempt(state, id, &task)
+        .await
+        .map_err(|_| "internal error".to_string())?;
+
+    let filename = Task::filename(id);
+    tokio::fs::rename(
+        state.tasks_path.join("PendingApproval").join(&filename),
+        state.tasks_path.join("Active").join(&filename),
+    )
+    .await
+    .map_err(|e| {
+        warn!(%id, ?e, "failed to move task file");
+        "internal error".to_string()
+    })?;
+
+    info!(%id, "task approved");
+    state.notifier.notify(NotificationEvent::TaskApproved, id);
+    Ok(())
+}
+
+/// Takes the Active tasks overlapping `task`, about to be placed in Active as `id`, off the
+/// schedule with [`scheduler::preempt`], as the scheduler does with the tasks outranked when they
+/// are due. They are of lower priority, as [`check_time_conflict`] found no conflict. Those
+/// running are left to the scheduler, which aborts them when `task` starts.
+pub(super) async fn preempt(state: &AppState, id: &str, task: &Task) -> Result<(), ApiError> {
+    let preemption = state.config.scheduling.preemption;
+    for overlapping in find_overlapping(&state.tasks, id, task).await {
+        let other = overlapping.id;
+        if overlapping.task.priority() >= task.priority() || state.runs.get(&other).is_some() {
+            continue;
+        }
+
+        let (base, other_id, by) = (state.tasks_path.clone(), other.clone(), id.to_string());
+        let moved = tokio::task::spawn_blocking(move || {
+            scheduler::preempt(&base, &other_id, &by, preemption)
+        })
+        .await;
+        if !matches!(moved, Ok(Ok(()))) {
+            warn!(id = %other, ?moved, "failed to move preempted task");
+            return Err(ApiError::Internal);
+        }
+        state.notifier.preempted(&other, preemption);
+    }
+    Ok(())
+}
+
+/// Moves task `id` from PendingApproval to Rejected, recording that `by` rejected it.
+async fn reject(
+    state: &AppState,
+    id: &str,
+    by: &str,
+    reason: Option<String>,
+) -> Result<(), String> {
+    pending_task(state, id).await?;
+
+    let rejected = Rejected {
+        by: by.to_string(),
+        reason,
+        time: Utc::now(),
+    };
+    let filename = Task::filename(id);
+    let path = state.tasks_path.join("PendingApproval").join(&filename);
+    let moved = async {
+        let content = tokio::fs::read_to_string(&path).await?;
+        let content = rejected.record(&content).map_err(std::io::Error::other)?;
+        let dir = state.tasks_path.join("Rejected");
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(dir.join(&filename), content).await?;
+        tokio::fs::remove_file(&path).await
+    }
+    .await;
+    moved.map_err(|e| {
+        warn!(%id, ?e, "failed to move rejected task");
+        "internal error".to_string()
+    })?;
+
+    info!(%id, %by, "task rejected");
+    state.notifier.notify(NotificationEvent::TaskRejected, id);
+    Ok(())
+}
+
+/// Returns task `id` if it is pending approval.
+async fn pending_task(state: &AppState, id: &str) -> Result<Task, String> {
+    match Task::find(&state.tasks_path, id).await {
+        Some((task_state, content)) if task_state == "PendingApproval" => {
+            Task::from_yaml_str(&content).map_err(|e| e.to_string())
+        }
+        Some((task_state, _)) => Err(format!("task is in state '{task_state}'")),
+        None => Err("task not found".to_string()),
+    }
+}
+
+/// Returns the owner of the task defined by `content`.
+pub(super) fn task_owner(content: &str) -> Option<String> {
+    Task::from_yaml_str(content)
+        .ok()
+        .and_then(|task| task.owner)
+}
+
+/// Sets the owner of the task defined by `body`, removing it if `owner` is None.
+fn with_owner(body: &str, owner: Option<String>) -> Result<String, ApiError> {
+    let mut value: serde_yaml::Value =
+        serde_yaml::from_str(body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let mapping = value
+        .as_mapping_mut()
+        .ok_or_else(|| ApiError::BadRequest("task must be a mapping".to_string()))?;
+    match owner {
+        Some(owner) => mapping.insert("owner".into(), owner.into()),
+        None => mapping.remove("owner"),
+    };
+    serde_yaml::to_string(&value).map_err(|_| ApiError::Internal)
+}
+
 #[cfg(test)]
 mod tests {
     use axum::body::Body;
@@ -248,7 +833,9 @@ mod tests {
     use tower::ServiceExt;
 
     use crate::api;
-    use crate::config::{ApiConfig, ApiKey, Config, Permission};
+    use crate::config::{ApiKey, Config, Permission};
+    use crate::scheduler::{self, Preemption};
+    use crate::task::format::Task;
 
     const TASK_YAML: &str = "\
 variables:
@@ -266,18 +853,15 @@ steps:
     }
 
     fn test_config(tmp: &TempDir, permissions: Vec<Permission>) -> Config {
-        Config {
-            station_name: "test".into(),
-            api: ApiConfig {
-                keys: vec![ApiKey {
-                    key: "test-key".into(),
-                    permissions,
-                }],
-            },
-            tasks_path: tmp.path().to_path_buf(),
-            tle_path: tmp.path().join("tle"),
-            ground_station: None,
-        }
+        Config::for_test(
+            tmp.path(),
+            vec![ApiKey {
+                name: None,
+                id: None,
+                key: "test-key".into(),
+                permissions,
+            }],
+        )
     }
 
     fn all_permissions() -> Vec<Permission> {
@@ -296,7 +880,7 @@ steps:
             std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
         }
         let config = test_config(&tmp, permissions);
-        let (router, _) = api::router(&config).split_for_parts();
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
         (tmp, router)
     }
 
@@ -320,6 +904,44 @@ steps:
         assert_eq!(response_status(router, req).await, StatusCode::UNAUTHORIZED);
     }
 
+    #[tokio::test]
+    async fn public_read_allows_viewing_without_key() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("Active")).unwrap();
+        std::fs::write(tmp.path().join("Active/pass.yaml"), TASK_YAML).unwrap();
+        let mut config = test_config(&tmp, all_permissions());
+        config.api.public_read = true;
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+
+        let req = Request::get("/api/tasks/pass").body(Body::empty()).unwrap();
+        assert_eq!(response_status(router.clone(), req).await, StatusCode::OK);
+
+        let req = Request::put("/api/tasks/other")
+            .body(Body::from(task_yaml_at(
+                "2026-07-01T10:00:00Z",
+                "2026-07-01T10:30:00Z",
+            )))
+            .unwrap();
+        assert_eq!(
+            response_status(router.clone(), req).await,
+            StatusCode::UNAUTHORIZED
+        );
+        let req = Request::delete("/api/tasks/pass")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            response_status(router.clone(), req).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // Wrong credentials are still rejected
+        let req = Request::get("/api/tasks")
+            .header("api_key", "wrong-key")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(response_status(router, req).await, StatusCode::UNAUTHORIZED);
+    }
+
     #[tokio::test]
     async fn invalid_api_key_returns_401() {
         let (_, router) = setup(all_permissions());
@@ -376,6 +998,96 @@ steps:
         assert_eq!(body.matches("\"id\"").count(), 2);
     }
 
+    #[tokio::test]
+    async fn list_shows_the_outcome_of_finished_runs() {
+        let (tmp, router) = setup(all_permissions());
+        std::fs::write(tmp.path().join("Active/a.yaml"), TASK_YAML).unwrap();
+        std::fs::write(tmp.path().join("Completed/b.yaml"), TASK_YAML).unwrap();
+        let run = tmp.path().join("Artifacts/b");
+        std::fs::create_dir_all(&run).unwrap();
+        std::fs::write(
+            run.join("execution_log.yaml"),
+            "- time: 2026-06-01T10:00:00Z\n  cmd: echo one\n  result: completed\n  exit_code: 0\n\
+             - time: 2026-06-01T10:01:00Z\n  cmd: echo two\n  result: completed\n  exit_code: 1\n",
+        )
+        .unwrap();
+
+        let (_, body) = response_body(
+            router,
+            Request::get("/api/tasks?sort=start&order=asc")
+                .header("api_key", "test-key")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let tasks: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let execution = |id| {
+            let task = tasks.as_array().unwrap().iter().find(|t| t["id"] == id);
+            task.unwrap().get("execution").cloned()
+        };
+        assert_eq!(execution("a"), None);
+        assert_eq!(
+            execution("b").unwrap(),
+            serde_json::json!({"steps": 2, "succeeded": 1, "result": "completed with errors"})
+        );
+    }
+
+    #[tokio::test]
+    async fn list_pages_and_sorts_tasks() {
+        let (tmp, router) = setup(all_permissions());
+        for (id, hour) in [("a", 10), ("b", 12), ("c", 11)] {
+            std::fs::write(
+                tmp.path().join(format!("Completed/{id}.yaml")),
+                task_yaml_at(
+                    &format!("2026-06-01T{hour}:00:00Z"),
+                    &format!("2026-06-01T{hour}:30:00Z"),
+                ),
+            )
+            .unwrap();
+        }
+
+        let list = |query: &'static str| {
+            let router = router.clone();
+            async move {
+                let resp = router
+                    .oneshot(
+                        Request::get(format!("/api/tasks?{query}"))
+                            .header("api_key", "test-key")
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(resp.status(), StatusCode::OK);
+                let total = resp.headers()["x-total-count"]
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+                let entries: Vec<serde_json::Value> = serde_json::from_slice(&bytes).unwrap();
+                let ids: Vec<String> = entries
+                    .iter()
+                    .map(|e| e["id"].as_str().unwrap().to_string())
+                    .collect();
+                (total, ids)
+            }
+        };
+
+        assert_eq!(
+            list("").await,
+            ("3".to_string(), vec!["b".into(), "c".into(), "a".into()])
+        );
+        assert_eq!(
+            list("order=asc&limit=2").await,
+            ("3".to_string(), vec!["a".into(), "c".into()])
+        );
+        assert_eq!(
+            list("offset=1&limit=1").await,
+            ("3".to_string(), vec!["c".into()])
+        );
+        assert_eq!(list("sort=submitted").await.1.len(), 3);
+    }
+
     // --- Get tests ---
 
     #[tokio::test]
@@ -449,6 +1161,71 @@ steps:
         assert_eq!(response_status(router, req).await, StatusCode::BAD_REQUEST);
     }
 
+    #[tokio::test]
+    async fn validate_reports_problems_and_conflicts() {
+        let (tmp, router) = setup(all_permissions());
+        std::fs::write(tmp.path().join("Active/existing.yaml"), TASK_YAML).unwrap();
+
+        let req = Request::post("/api/tasks/validate")
+            .header("api_key", "test-key")
+            .body(Body::from(
+                "variables: {}\nsteps:\n  - cmd: true\n    wait: maybe\n",
+            ))
+            .unwrap();
+        let (status, body) = response_body(router.clone(), req).await;
+        assert_eq!(status, StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["valid"], false);
+        assert_eq!(report["errors"][0]["line"], 3);
+
+        let req = Request::post("/api/tasks/validate")
+            .header("api_key", "test-key")
+            .body(Body::from(TASK_YAML))
+            .unwrap();
+        let (_, body) = response_body(router.clone(), req).await;
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            report["errors"][0]["message"],
+            "time conflict with task 'existing'"
+        );
+
+        let req = Request::post("/api/tasks/validate?id=existing")
+            .header("api_key", "test-key")
+            .body(Body::from(TASK_YAML))
+            .unwrap();
+        let (_, body) = response_body(router, req).await;
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["steps"][0]["command"], "echo hello");
+    }
+
+    #[tokio::test]
+    async fn dry_run_resolves_steps_without_running_them() {
+        let (tmp, router) = setup(all_permissions());
+        std::fs::write(
+            tmp.path().join("Active/pass.yaml"),
+            "variables:\n  start: \"2026-06-01T10:00:00Z\"\n  end: \"2026-06-01T10:30:00Z\"\n  out: /tmp/pass\nsteps:\n  - cmd: \"touch $out\"\n    time: \"T+5m\"\n",
+        )
+        .unwrap();
+
+        let req = Request::post("/api/tasks/pass/dry-run")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = response_body(router.clone(), req).await;
+        assert_eq!(status, StatusCode::OK);
+        let dry_run: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(dry_run["variables"]["out"], "/tmp/pass");
+        assert_eq!(dry_run["steps"][0]["command"], "touch /tmp/pass");
+        assert_eq!(dry_run["steps"][0]["time"], "2026-06-01T10:05:00+00:00");
+
+        let req = Request::post("/api/tasks/missing/dry-run")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(response_status(router, req).await, StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn put_invalid_task_definition_returns_400() {
         let (_, router) = setup(all_permissions());
@@ -460,6 +1237,19 @@ steps:
         assert_eq!(response_status(router, req).await, StatusCode::BAD_REQUEST);
     }
 
+    #[tokio::test]
+    async fn put_unknown_hardware_returns_400() {
+        let (_, router) = setup(all_permissions());
+        let yaml = "variables:\n  start: \"2026-01-01T00:00:00Z\"\nsteps:\n  - cmd: \"sat-o-mat rotator park uhf1\"\n";
+        let req = Request::put("/api/tasks/park")
+            .header("api_key", "test-key")
+            .body(Body::from(yaml))
+            .unwrap();
+        let (status, body) = response_body(router, req).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.starts_with("steps[0]: unknown rotator uhf1"));
+    }
+
     // --- Put (update) tests ---
 
     #[tokio::test]
@@ -479,6 +1269,36 @@ steps:
         );
     }
 
+    #[tokio::test]
+    async fn put_changed_active_task_needs_approval_again() {
+        let (tmp, router) = setup(vec![
+            Permission::ViewTasks,
+            Permission::SubmitTask,
+            Permission::EditTask,
+        ]);
+        std::fs::write(tmp.path().join("Active/t.yaml"), TASK_YAML).unwrap();
+
+        // The same definition keeps its approval
+        let put = |body: String| {
+            Request::put("/api/tasks/t")
+                .header("api_key", "test-key")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let status = response_status(router.clone(), put(TASK_YAML.to_string())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(tmp.path().join("Active/t.yaml").exists());
+
+        let updated = task_yaml_at("2026-06-01T11:00:00Z", "2026-06-01T11:30:00Z");
+        let status = response_status(router, put(updated.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!tmp.path().join("Active/t.yaml").exists());
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join("PendingApproval/t.yaml")).unwrap(),
+            updated
+        );
+    }
+
     #[tokio::test]
     async fn put_update_without_edit_permission_returns_403() {
         let (tmp, router) = setup(vec![Permission::ViewTasks, Permission::SubmitTask]);
@@ -526,6 +1346,25 @@ steps:
         assert_eq!(response_status(router, req).await, StatusCode::CONFLICT);
     }
 
+    #[tokio::test]
+    async fn put_without_permission_does_not_reveal_conflicts() {
+        let (tmp, router) = setup(vec![Permission::ViewTasks]);
+        std::fs::write(
+            tmp.path().join("Active/existing.yaml"),
+            task_yaml_at("2026-06-01T10:00:00Z", "2026-06-01T10:30:00Z"),
+        )
+        .unwrap();
+
+        let req = Request::put("/api/tasks/new")
+            .header("api_key", "test-key")
+            .body(Body::from(task_yaml_at(
+                "2026-06-01T10:15:00Z",
+                "2026-06-01T10:45:00Z",
+            )))
+            .unwrap();
+        assert_eq!(response_status(router, req).await, StatusCode::FORBIDDEN);
+    }
+
     #[tokio::test]
     async fn put_non_overlapping_task_succeeds() {
         let (tmp, router) = setup(all_permissions());
@@ -546,6 +1385,55 @@ steps:
         assert_eq!(response_status(router, req).await, StatusCode::CREATED);
     }
 
+    #[tokio::test]
+    async fn put_higher_priority_task_preempts_overlapping_ones() {
+        let with_priority = |start, end, priority| {
+            task_yaml_at(start, end).replace(
+                "variables:",
+                &format!("variables:\n  priority: \"{priority}\""),
+            )
+        };
+        for preemption in [Preemption::Pending, Preemption::Reject] {
+            let tmp = tempfile::tempdir().unwrap();
+            for dir in ["Active", "PendingApproval"] {
+                std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+            }
+            let mut config = test_config(&tmp, all_permissions());
+            config.scheduling.preemption = preemption;
+            let (router, _) = api::routes(api::state(&config)).split_for_parts();
+            std::fs::write(
+                tmp.path().join("Active/routine.yaml"),
+                task_yaml_at("2026-06-01T10:00:00Z", "2026-06-01T10:30:00Z"),
+            )
+            .unwrap();
+            std::fs::write(
+                tmp.path().join("Active/important.yaml"),
+                with_priority("2026-06-01T11:00:00Z", "2026-06-01T11:30:00Z", 20),
+            )
+            .unwrap();
+
+            // Tasks of the same or higher priority still conflict
+            let put = |end| {
+                Request::put("/api/tasks/emergency")
+                    .header("api_key", "test-key")
+                    .body(Body::from(with_priority("2026-06-01T10:15:00Z", end, 10)))
+                    .unwrap()
+            };
+            let status = response_status(router.clone(), put("2026-06-01T11:15:00Z")).await;
+            assert_eq!(status, StatusCode::CONFLICT);
+            assert!(tmp.path().join("Active/routine.yaml").exists());
+
+            let status = response_status(router, put("2026-06-01T10:45:00Z")).await;
+            assert_eq!(status, StatusCode::CREATED);
+            assert!(tmp.path().join("Active/emergency.yaml").exists());
+            assert!(!tmp.path().join("Active/routine.yaml").exists());
+            let dir = tmp.path().join(preemption.state());
+            let content = std::fs::read_to_string(dir.join("routine.yaml")).unwrap();
+            let routine = Task::from_yaml_str(&content).unwrap();
+            assert_eq!(routine.preempted.unwrap().by, "emergency");
+        }
+    }
+
     #[tokio::test]
     async fn put_update_own_time_range_no_self_conflict() {
         let (tmp, router) = setup(all_permissions());
@@ -615,6 +1503,270 @@ steps:
         assert_eq!(response_status(router, req).await, StatusCode::FORBIDDEN);
     }
 
+    // --- Batch approval tests ---
+
+    #[tokio::test]
+    async fn running_tasks_are_paused_and_aborted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = test_config(&tmp, all_permissions());
+        let state = api::state(&config);
+        let runs = state.runs.clone();
+        let (router, _) = api::routes(state).split_for_parts();
+        let post = |uri: &str| {
+            Request::post(uri)
+                .header("api_key", "test-key")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        std::fs::create_dir_all(tmp.path().join("Active")).unwrap();
+        std::fs::write(
+            tmp.path().join("Active/pass.yaml"),
+            "variables:\n  end: \"2099-01-01T00:00:00Z\"\nsteps:\n  - cmd: \"sleep 60\"\n    wait: true\n",
+        )
+        .unwrap();
+        let (status, _) = response_body(router.clone(), post("/api/tasks/pass/pause")).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let tasks_path = tmp.path().to_path_buf();
+        let scheduler = tokio::spawn(async move {
+            let preemption = Preemption::default();
+            scheduler::run_until(&tasks_path, None, runs, preemption, std::future::pending()).await
+        });
+        let (status, body) = loop {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            let (status, body) = response_body(router.clone(), post("/api/tasks/pass/pause")).await;
+            if status != StatusCode::CONFLICT {
+                break (status, body);
+            }
+        };
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#""state":"paused""#));
+
+        let req = Request::get("/api/tasks")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let (_, body) = response_body(router.clone(), req).await;
+        assert!(body.contains(r#""run":{"state":"paused","step":0}"#));
+
+        // Not even an unchanged definition can be written while it runs
+        let content = std::fs::read_to_string(tmp.path().join("Active/pass.yaml")).unwrap();
+        let req = Request::put("/api/tasks/pass")
+            .header("api_key", "test-key")
+            .body(Body::from(content))
+            .unwrap();
+        let (status, body) = response_body(router.clone(), req).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(body.starts_with("task 'pass' is running"), "{body}");
+
+        let (status, body) = response_body(router.clone(), post("/api/tasks/pass/abort")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#""state":"aborting""#));
+        let failed = tmp.path().join("Failed/pass.yaml");
+        for _ in 0..100 {
+            if failed.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert!(failed.exists());
+        scheduler.abort();
+    }
+
+    #[tokio::test]
+    async fn approve_batch_reports_each_task() {
+        let (tmp, router) = setup(vec![Permission::ApproveTask]);
+        std::fs::write(
+            tmp.path().join("PendingApproval/a.yaml"),
+            task_yaml_at("2026-06-01T10:00:00Z", "2026-06-01T10:30:00Z"),
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.path().join("PendingApproval/b.yaml"),
+            task_yaml_at("2026-06-01T10:15:00Z", "2026-06-01T10:45:00Z"),
+        )
+        .unwrap();
+
+        let (status, body) = response_body(
+            router.clone(),
+            Request::post("/api/tasks/approve-batch")
+                .header("api_key", "test-key")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"action": "approve", "ids": ["a", "b", "missing"]}"#,
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let results: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(results[0]["ok"], true);
+        assert_eq!(results[1]["ok"], false);
+        assert!(results[1]["error"].as_str().unwrap().contains("conflict"));
+        assert_eq!(results[2]["error"], "task not found");
+        assert!(tmp.path().join("Active/a.yaml").exists());
+
+        let (_, body) = response_body(
+            router,
+            Request::post("/api/tasks/approve-batch")
+                .header("api_key", "test-key")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"action": "reject", "ids": ["b"]}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(body, r#"[{"id":"b","ok":true}]"#);
+        assert!(!tmp.path().join("PendingApproval/b.yaml").exists());
+        assert!(tmp.path().join("Rejected/b.yaml").exists());
+    }
+
+    #[tokio::test]
+    async fn rejected_tasks_are_kept_with_the_reason() {
+        let (tmp, router) = setup(vec![Permission::ApproveTask, Permission::ViewTasks]);
+        std::fs::write(tmp.path().join("PendingApproval/t.yaml"), TASK_YAML).unwrap();
+
+        let (status, _) = response_body(
+            router.clone(),
+            Request::post("/api/tasks/approve-batch")
+                .header("api_key", "test-key")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"action": "reject", "ids": ["t"], "reason": "antenna maintenance"}"#,
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let content = std::fs::read_to_string(tmp.path().join("Rejected/t.yaml")).unwrap();
+        let rejected = Task::from_yaml_str(&content).unwrap().rejected.unwrap();
+        assert_eq!(rejected.by, "key:config-0");
+        assert_eq!(rejected.reason.as_deref(), Some("antenna maintenance"));
+
+        let list = |state| {
+            Request::get(format!("/api/tasks?state={state}"))
+                .header("api_key", "test-key")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (_, body) = response_body(router.clone(), list("rejected")).await;
+        let tasks: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(tasks[0]["id"], "t");
+        assert_eq!(tasks[0]["state"], "Rejected");
+        assert_eq!(tasks[0]["rejection"], "antenna maintenance");
+        let (_, body) = response_body(router, list("pendingapproval")).await;
+        assert_eq!(body, "[]");
+    }
+
+    #[tokio::test]
+    async fn approve_batch_requires_permission() {
+        let (_, router) = setup(all_permissions());
+        let req = Request::post("/api/tasks/approve-batch")
+            .header("api_key", "test-key")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"action": "approve", "ids": []}"#))
+            .unwrap();
+        assert_eq!(response_status(router, req).await, StatusCode::FORBIDDEN);
+    }
+
+    // --- Ownership tests ---
+
+    fn owned_task_yaml(owner: &str, start: &str, end: &str) -> String {
+        format!("owner: {owner}\n{}", task_yaml_at(start, end))
+    }
+
+    fn write_owned_tasks(tmp: &TempDir) {
+        std::fs::write(
+            tmp.path().join("Active/mine.yaml"),
+            owned_task_yaml("key:config-0", "2026-06-01T10:00:00Z", "2026-06-01T10:30:00Z"),
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.path().join("Active/theirs.yaml"),
+            owned_task_yaml("alice", "2026-06-01T11:00:00Z", "2026-06-01T11:30:00Z"),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn put_records_caller_as_owner() {
+        let (tmp, router) = setup(all_permissions());
+        let yaml = owned_task_yaml("alice", "2026-06-01T10:00:00Z", "2026-06-01T10:30:00Z");
+        let req = Request::put("/api/tasks/new")
+            .header("api_key", "test-key")
+            .body(Body::from(yaml))
+            .unwrap();
+        assert_eq!(response_status(router, req).await, StatusCode::CREATED);
+
+        let stored = std::fs::read_to_string(tmp.path().join("Active/new.yaml")).unwrap();
+        let task = Task::from_yaml_str(&stored).unwrap();
+        assert_eq!(task.owner.as_deref(), Some("key:config-0"));
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_owner() {
+        let (tmp, router) = setup(all_permissions());
+        write_owned_tasks(&tmp);
+
+        for (uri, expected) in [
+            ("/api/tasks", 2),
+            ("/api/tasks?owner=me", 1),
+            ("/api/tasks?owner=alice", 1),
+            ("/api/tasks?owner=bob", 0),
+        ] {
+            let (status, body) = response_body(
+                router.clone(),
+                Request::get(uri)
+                    .header("api_key", "test-key")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body.matches("\"id\"").count(), expected, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn own_task_permissions_are_scoped_to_owner() {
+        let (tmp, router) = setup(vec![
+            Permission::ViewOwnTasks,
+            Permission::DeleteOwnTasks,
+            Permission::SubmitTask,
+        ]);
+        write_owned_tasks(&tmp);
+
+        let (status, body) = response_body(
+            router.clone(),
+            Request::get("/api/tasks")
+                .header("api_key", "test-key")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\"mine\"") && !body.contains("\"theirs\""));
+
+        for (method, id, expected) in [
+            ("GET", "theirs", StatusCode::FORBIDDEN),
+            ("DELETE", "theirs", StatusCode::FORBIDDEN),
+            ("GET", "mine", StatusCode::OK),
+            ("DELETE", "mine", StatusCode::NO_CONTENT),
+        ] {
+            let req = Request::builder()
+                .method(method)
+                .uri(format!("/api/tasks/{id}"))
+                .header("api_key", "test-key")
+                .body(Body::empty())
+                .unwrap();
+            assert_eq!(
+                response_status(router.clone(), req).await,
+                expected,
+                "{method} {id}"
+            );
+        }
+    }
+
     // --- Path traversal ---
 
     #[tokio::test]
@@ -626,4 +1778,14 @@ steps:
             .unwrap();
         assert_eq!(response_status(router, req).await, StatusCode::NOT_FOUND);
     }
+
+    #[tokio::test]
+    async fn control_characters_rejected_in_ids() {
+        let (_, router) = setup(all_permissions());
+        let req = Request::put("/api/tasks/pass%0D%0ABcc:%20x@example.org")
+            .header("api_key", "test-key")
+            .body(Body::from(TASK_YAML))
+            .unwrap();
+        assert_eq!(response_status(router, req).await, StatusCode::BAD_REQUEST);
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use axum::Json;
 use axum::extract::State;
@@ -7,19 +7,123 @@ use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 use utoipa::ToSchema;
 
-use crate::config::Permission;
-use crate::task::format::Task;
+use crate::config::{NotificationEvent, Permission};
+use crate::predict::PredictDb;
+use crate::task::format::{Step, Task};
 use crate::task::utils::check_time_conflict;
+use crate::tracker::Frequency;
 
 use super::AppState;
 use super::auth::AuthenticatedKey;
 use super::error::ApiError;
-
-const TEMPLATES_DIR: &str = "Templates";
+use super::review::check_hardware;
+use super::tasks::preempt;
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct TemplateListEntry {
     pub id: String,
+    /// Parameters to fill in when submitting a task from the template
+    pub parameters: BTreeMap<String, Parameter>,
+}
+
+/// A task template: the task, and the parameters whoever submits it fills in. The parameters are
+/// declared under `parameters`, next to the variables and steps of the task:
+///
+/// ```yaml
+/// parameters:
+///   satellite: {kind: satellite}
+///   frequency: {kind: frequency, default: 437.8 MHz}
+/// variables:
+///   start: "2026-06-01T10:00:00Z"
+/// steps:
+///   - cmd: record ${satellite} ${frequency}
+/// ```
+#[derive(Deserialize)]
+pub(super) struct Template {
+    #[serde(default)]
+    pub parameters: BTreeMap<String, Parameter>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+    #[serde(default)]
+    pub cleanup: Vec<Step>,
+}
+
+/// A variable of a template filled in on submission.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Parameter {
+    #[serde(default)]
+    pub kind: ParameterKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Value used when none is given. Parameters without a default, nor a variable of the same
+    /// name in the template, are required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterKind {
+    #[default]
+    Text,
+    /// Name or NORAD ID of a satellite with known elements
+    Satellite,
+    /// Time as in the task variables, e.g. `2026-06-01T10:00:00Z` or `$start + 60 seconds`
+    Time,
+    /// Frequency in Hz, or with a unit, e.g. `437.8 MHz`. Passed to the steps in Hz.
+    Frequency,
+}
+
+impl Template {
+    /// Renders the task with the `values` of the parameters. The variables of the template act as
+    /// defaults, then the defaults of the parameters, and `values` override them all, including
+    /// variables that are not parameters.
+    pub(super) fn render(
+        self,
+        mut values: HashMap<String, String>,
+        predict_db: &PredictDb,
+    ) -> Result<Task, ApiError> {
+        let invalid = |name: &str, e: &dyn std::fmt::Display| {
+            ApiError::BadRequest(format!("invalid value for parameter '{name}': {e}"))
+        };
+        let mut variables = self.variables;
+        for (name, parameter) in &self.parameters {
+            let value = values
+                .remove(name)
+                .or_else(|| parameter.default.clone())
+                .or_else(|| variables.get(name).cloned())
+                .ok_or_else(|| ApiError::BadRequest(format!("missing parameter '{name}'")))?;
+            let value = match parameter.kind {
+                ParameterKind::Text | ParameterKind::Time => value,
+                ParameterKind::Satellite => {
+                    if predict_db.find(&value).is_none() {
+                        return Err(invalid(name, &format!("unknown satellite {value}")));
+                    }
+                    value
+                }
+                ParameterKind::Frequency => match value.trim().parse::<f64>() {
+                    Ok(hz) if hz > 0.0 => format!("{}", hz.round() as u64),
+                    _ => {
+                        let Frequency(hz) = value.parse().map_err(|e| invalid(name, &e))?;
+                        hz.to_string()
+                    }
+                },
+            };
+            variables.insert(name.clone(), value);
+        }
+        variables.extend(values);
+
+        let task = Task::new(variables, self.steps, self.cleanup);
+        for (name, parameter) in &self.parameters {
+            if parameter.kind == ParameterKind::Time {
+                task.get_time_variable(name)
+                    .map_err(|e| invalid(name, &e))?;
+            }
+        }
+        Ok(task)
+    }
 }
 
 /// List available task templates.
@@ -40,7 +144,7 @@ pub async fn list_templates(
 ) -> Result<Json<Vec<TemplateListEntry>>, ApiError> {
     auth.require(Permission::SubmitFromTemplate)?;
 
-    let dir_path = state.tasks_path.join(TEMPLATES_DIR);
+    let dir_path = state.config.templates_path();
     let Ok(mut read_dir) = tokio::fs::read_dir(&dir_path).await else {
         return Ok(Json(Vec::new()));
     };
@@ -49,7 +153,14 @@ pub async fn list_templates(
     while let Ok(Some(entry)) = read_dir.next_entry().await {
         let file_name = entry.file_name().to_string_lossy().to_string();
         let id = Task::id_from_filename(&file_name).to_string();
-        entries.push(TemplateListEntry { id });
+        let parameters = match read_template(&state, &id).await {
+            Ok((template, _)) => template.parameters,
+            Err(e) => {
+                warn!(%id, ?e, "failed to read template");
+                BTreeMap::new()
+            }
+        };
+        entries.push(TemplateListEntry { id, parameters });
     }
     entries.sort_by(|a, b| a.id.cmp(&b.id));
 
@@ -83,9 +194,20 @@ pub async fn get_template(
 }
 
 #[derive(Deserialize, ToSchema)]
+#[schema(example = json!({
+    "template_id": "noaa_apt",
+    "task_id": "noaa19-2026-06-01",
+    "variables": {
+        "satellite": "NOAA 19",
+        "frequency": "137.1 MHz",
+        "start": "2026-06-01T10:00:00Z",
+        "end": "2026-06-01T10:15:00Z"
+    }
+}))]
 pub struct SubmitFromTemplateRequest {
     pub template_id: String,
     pub task_id: String,
+    /// Values of the parameters of the template, and of any other variables
     pub variables: HashMap<String, String>,
 }
 
@@ -95,6 +217,10 @@ pub struct SubmitFromTemplateRequest {
 /// variables, and creates a new task. Users can only change variables — the
 /// commands are fixed by the template.
 ///
+/// The variables of the template act as defaults. Parameters declared by the
+/// template must be given, unless they have a default, and are checked
+/// according to their kind: satellites must be known and times valid.
+///
 /// New tasks are placed in PendingApproval unless the API key also has
 /// AutoApproveTask permission.
 #[utoipa::path(
@@ -122,16 +248,32 @@ pub async fn submit_from_template(
     let task_id = &req.task_id;
     let template_id = &req.template_id;
 
-    // Reject path traversal
-    if task_id.contains('/') || task_id.contains('\\') || task_id == ".." || task_id == "." {
-        return Err(ApiError::BadRequest("invalid task ID".to_string()));
-    }
-
     // Load the template
     let (template, _) = read_template(&state, template_id).await?;
 
     // Build the task: template steps + user-provided variables
-    let task = Task::new(req.variables, template.steps, template.cleanup);
+    let mut task = template.render(req.variables, &*state.predict_db.lock().await)?;
+    task.owner = Some(auth.owner.clone());
+    task.template = Some(template_id.clone());
+
+    let auto_approve = auth.has(Permission::AutoApproveTask);
+    let target_dir = submit_task(&state, auto_approve, task_id, &task).await?;
+
+    info!(%task_id, %template_id, %target_dir, "task created from template");
+    Ok(StatusCode::CREATED)
+}
+
+/// Write a new task, placing it in PendingApproval unless `auto_approve` is set (e.g. the API key
+/// also has AutoApproveTask permission). Returns the state the task was placed in.
+pub(super) async fn submit_task(
+    state: &AppState,
+    auto_approve: bool,
+    task_id: &str,
+    task: &Task,
+) -> Result<&'static str, ApiError> {
+    if !Task::is_valid_id(task_id) {
+        return Err(ApiError::BadRequest("invalid task ID".to_string()));
+    }
 
     // Reject if a task with this ID already exists
     if Task::find(&state.tasks_path, task_id).await.is_some() {
@@ -140,20 +282,27 @@ pub async fn submit_from_template(
         )));
     }
 
+    // Reject steps referring to hardware that is not configured
+    let unresolved = check_hardware(task, &state.config.resources);
+    if !unresolved.is_empty() {
+        return Err(ApiError::BadRequest(unresolved.join("; ")));
+    }
+
     // Check for time conflicts
-    if let Some(conflict) = check_time_conflict(&state.tasks_path, task_id, &task).await {
+    if let Some(conflict) = check_time_conflict(&state.tasks, task_id, task).await {
         return Err(ApiError::Conflict(format!(
             "time conflict with task '{conflict}'"
         )));
     }
 
-    let target_dir = if auth.has(Permission::AutoApproveTask) {
+    let target_dir = if auto_approve {
+        preempt(state, task_id, task).await?;
         "Active"
     } else {
         "PendingApproval"
     };
 
-    let yaml = serde_yaml::to_string(&task)
+    let yaml = serde_yaml::to_string(task)
         .map_err(|e| ApiError::BadRequest(format!("failed to serialize task: {e}")))?;
 
     let file_path = state
@@ -165,29 +314,34 @@ pub async fn submit_from_template(
         ApiError::Internal
     })?;
 
-    info!(%task_id, %template_id, %target_dir, "task created from template");
-    Ok(StatusCode::CREATED)
+    state.notifier.notify(NotificationEvent::TaskSubmitted, task_id);
+    if auto_approve {
+        state.notifier.notify(NotificationEvent::TaskApproved, task_id);
+    } else {
+        state.notifier.pending_approval(task_id);
+    }
+    Ok(target_dir)
 }
 
-/// Read and parse a template file. Returns (parsed Task, raw YAML content).
-async fn read_template(state: &AppState, id: &str) -> Result<(Task, String), ApiError> {
+/// Read and parse a template file. Returns (parsed template, raw YAML content).
+pub(super) async fn read_template(
+    state: &AppState,
+    id: &str,
+) -> Result<(Template, String), ApiError> {
     if id.contains('/') || id.contains('\\') || id == ".." || id == "." {
         return Err(ApiError::BadRequest("invalid template ID".to_string()));
     }
 
-    let path = state
-        .tasks_path
-        .join(TEMPLATES_DIR)
-        .join(Task::filename(id));
+    let path = state.config.templates_path().join(Task::filename(id));
 
     let content = tokio::fs::read_to_string(&path)
         .await
         .map_err(|_| ApiError::NotFound)?;
 
-    let task = Task::from_yaml_str(&content)
+    let template = serde_yaml::from_str(&content)
         .map_err(|e| ApiError::BadRequest(format!("invalid template: {e}")))?;
 
-    Ok((task, content))
+    Ok((template, content))
 }
 
 #[cfg(test)]
@@ -199,7 +353,7 @@ mod tests {
     use tower::ServiceExt;
 
     use crate::api;
-    use crate::config::{ApiConfig, ApiKey, Config, Permission};
+    use crate::config::{ApiKey, Config, Permission};
 
     const TEMPLATE_YAML: &str = "\
 variables:
@@ -211,18 +365,15 @@ steps:
 ";
 
     fn test_config(tmp: &TempDir, permissions: Vec<Permission>) -> Config {
-        Config {
-            station_name: "test".into(),
-            api: ApiConfig {
-                keys: vec![ApiKey {
-                    key: "test-key".into(),
-                    permissions,
-                }],
-            },
-            tasks_path: tmp.path().to_path_buf(),
-            tle_path: tmp.path().join("tle"),
-            ground_station: None,
-        }
+        Config::for_test(
+            tmp.path(),
+            vec![ApiKey {
+                name: None,
+                id: None,
+                key: "test-key".into(),
+                permissions,
+            }],
+        )
     }
 
     fn setup(permissions: Vec<Permission>) -> (TempDir, axum::Router) {
@@ -237,7 +388,7 @@ steps:
             std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
         }
         let config = test_config(&tmp, permissions);
-        let (router, _) = api::router(&config).split_for_parts();
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
         (tmp, router)
     }
 
@@ -442,4 +593,85 @@ steps:
             .unwrap();
         assert_eq!(response_status(router, req).await, StatusCode::FORBIDDEN);
     }
+
+    // --- Parameters ---
+
+    const PARAMETERS_YAML: &str = "\
+parameters:
+  satellite:
+    kind: satellite
+  frequency:
+    kind: frequency
+    default: 437.8 MHz
+  start:
+    kind: time
+variables:
+  end: \"2026-07-01T10:30:00Z\"
+steps:
+  - \"record ${satellite} ${frequency}\"
+";
+
+    #[tokio::test]
+    async fn parameters_are_checked_and_filled_in() {
+        let tmp = tempfile::tempdir().unwrap();
+        for dir in ["PendingApproval", "tle", "templates"] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        let tle = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        std::fs::write(tmp.path().join("tle/nanoff_a.txt"), tle).unwrap();
+        std::fs::write(tmp.path().join("templates/uhf.yaml"), PARAMETERS_YAML).unwrap();
+        let mut config = test_config(&tmp, vec![Permission::SubmitFromTemplate]);
+        config.templates_path = Some(tmp.path().join("templates"));
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+
+        let (status, body) = response_body(
+            router.clone(),
+            Request::get("/api/templates")
+                .header("api_key", "test-key")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#""satellite":{"kind":"satellite"}"#));
+
+        let submit = |vars: &[(&str, &str)]| {
+            Request::post("/api/tasks/submit_from_template")
+                .header("api_key", "test-key")
+                .header("content-type", "application/json")
+                .body(Body::from(submit_json("uhf", "pass", vars)))
+                .unwrap()
+        };
+        let start = ("start", "2026-07-01T10:00:00Z");
+        for (vars, error) in [
+            (vec![start], "missing parameter 'satellite'"),
+            (vec![("satellite", "99999"), start], "unknown satellite"),
+            (
+                vec![("satellite", "58810"), ("start", "soon")],
+                "parameter 'start'",
+            ),
+            (
+                vec![("satellite", "58810"), start, ("frequency", "UHF")],
+                "parameter 'frequency'",
+            ),
+        ] {
+            let (status, body) = response_body(router.clone(), submit(&vars)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body.contains(error), "{body}");
+        }
+
+        let vars = [("satellite", "58810"), start];
+        assert_eq!(
+            response_status(router, submit(&vars)).await,
+            StatusCode::CREATED
+        );
+        let yaml = std::fs::read_to_string(tmp.path().join("PendingApproval/pass.yaml")).unwrap();
+        let task = crate::task::format::Task::from_yaml_str(&yaml).unwrap();
+        assert_eq!(task.variables["frequency"], "437800000");
+        assert_eq!(task.variables["satellite"], "58810");
+        assert_eq!(task.variables["end"], "2026-07-01T10:30:00Z");
+    }
 }
@@ -0,0 +1,424 @@
+//! Live position of the trackers of the runs in progress, for the dashboard of the web UI and
+//! other clients following a pass.
+//!
+//! The trackers run as steps of the runs, in their own process. Their latest update is read from
+//! the [`STATUS_FILE`] they keep in the artifacts of the run.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::extract::ws::{
+    Message, WebSocket, WebSocketUpgrade, rejection::WebSocketUpgradeRejection,
+};
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::AppState;
+use super::auth::AuthenticatedKey;
+use super::error::ApiError;
+use super::runs::{ARTIFACTS_DIR, require_view};
+use crate::track::{STATUS_FILE, TrackerStatus};
+
+/// How often the status files are checked for a new update.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Time between the points of a trajectory.
+const TRAJECTORY_STEP: chrono::Duration = chrono::Duration::seconds(10);
+
+/// How far ahead trajectories go.
+const TRAJECTORY_SPAN: chrono::Duration = chrono::Duration::minutes(15);
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TrackerQuery {
+    /// Task ID of the run to follow. The runs in progress with a tracker, in turn, if unset.
+    pub task: Option<String>,
+    /// API key or token, for browsers, which cannot set headers on WebSockets.
+    #[allow(dead_code)] // Read when authenticating the request
+    pub token: Option<String>,
+}
+
+/// What a tracker does.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackerMode {
+    /// No run in progress has a tracker
+    Idle,
+    /// The tracker of a run follows an object
+    Tracking,
+}
+
+/// A point of the path of an object across the sky.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiTrajectoryPoint {
+    /// Time formatted as RFC3339
+    time: String,
+    /// Azimuth angle in degrees
+    azimuth: f64,
+    /// Elevation angle in degrees
+    elevation: f64,
+}
+
+/// Message of the tracker WebSocket, told apart by its `type`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TrackerMessage {
+    /// The tracker followed changed: sent first, then whenever a tracker starts or stops
+    Mode {
+        mode: TrackerMode,
+        /// Task ID of the run of the tracker, when tracking
+        task: Option<String>,
+        /// Object tracked, when tracking
+        object: Option<String>,
+    },
+    /// Latest update of the tracker: position, Doppler shifts and pointing errors
+    Sample {
+        task: String,
+        #[schema(value_type = Object)]
+        status: TrackerStatus,
+    },
+    /// Predicted path of the object tracked over the next minutes, sent when tracking starts and
+    /// whenever the orbit elements are refreshed. Only for objects known to the predictions.
+    Trajectory {
+        task: String,
+        object: String,
+        /// Epoch of the elements the path is computed from, formatted as RFC3339
+        epoch: String,
+        points: Vec<ApiTrajectoryPoint>,
+    },
+}
+
+/// Follow the trackers live.
+///
+/// Switches to a WebSocket sending, as `TrackerMessage`s in JSON text messages, the tracker
+/// followed (`mode`), each update it makes (`sample`) and the path the object will follow
+/// (`trajectory`). The trackers are the `sat-o-mat tracker` steps of the runs in progress, or of
+/// the run of `task` only, in which case the socket closes when the run is over.
+#[utoipa::path(
+    get,
+    path = "/tracker/ws",
+    tag = super::STATION_TAG,
+    params(TrackerQuery),
+    responses(
+        (status = 101, description = "Switching to the WebSocket", body = TrackerMessage),
+        (status = 400, description = "Not a WebSocket request"),
+        (status = 401, description = "Missing or invalid API key"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Run not in progress"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn stream_tracker(
+    State(state): State<AppState>,
+    Query(query): Query<TrackerQuery>,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+    auth: AuthenticatedKey,
+) -> Result<Response, ApiError> {
+    auth.can_view_all()?;
+    if let Some(task) = &query.task {
+        if state.runs.get(task).is_none() {
+            return Err(ApiError::NotFound);
+        }
+        require_view(&state, &auth, task).await?;
+    }
+
+    let ws = match ws {
+        Ok(ws) => ws,
+        Err(rejection) => return Ok(rejection.into_response()),
+    };
+    Ok(ws.on_upgrade(move |socket| send_updates(socket, state, auth, query.task)))
+}
+
+/// The first of the runs in progress, or the run of `task`, whose tracker keeps a status that
+/// `auth` can view. Whether each run can be viewed is kept in `viewable`.
+async fn current_tracker(
+    state: &AppState,
+    auth: &AuthenticatedKey,
+    task: Option<&str>,
+    viewable: &mut HashMap<String, bool>,
+) -> Option<(String, TrackerStatus)> {
+    for (id, _) in state.runs.statuses() {
+        if task.is_some_and(|task| task != id) {
+            continue;
+        }
+        if !viewable.contains_key(&id) {
+            let view = require_view(state, auth, &id).await.is_ok();
+            viewable.insert(id.clone(), view);
+        }
+        if !viewable[&id] {
+            continue;
+        }
+        let path = state
+            .tasks_path
+            .join(ARTIFACTS_DIR)
+            .join(&id)
+            .join(STATUS_FILE);
+        // Only there while a tracker runs as a step
+        if let Ok(status) = tokio::fs::read(&path).await
+            && let Ok(status) = serde_json::from_slice(&status)
+        {
+            return Some((id, status));
+        }
+    }
+    None
+}
+
+/// The path of `object` from our station from `start` on, and the epoch of the elements it was
+/// computed from. None if `object` is unknown to the predictions.
+async fn trajectory(
+    state: &AppState,
+    object: &str,
+    start: DateTime<Utc>,
+) -> Option<(DateTime<Utc>, Vec<ApiTrajectoryPoint>)> {
+    let gs = state.config.ground_station.as_ref()?;
+    let predict_db = state.predict_db.lock().await;
+    let (_, sat) = predict_db.find(object)?;
+    let mut points = Vec::new();
+    let mut time = start;
+    while time <= start + TRAJECTORY_SPAN {
+        let Ok(observables) = predict_db.observables_at(time, &sat.spacecraft, gs) else {
+            break;
+        };
+        points.push(ApiTrajectoryPoint {
+            time: time.to_rfc3339(),
+            azimuth: observables.azimuth().to_degrees(),
+            elevation: observables.elevation().to_degrees(),
+        });
+        time += TRAJECTORY_STEP;
+    }
+    Some((sat.elements.datetime.and_utc(), points))
+}
+
+/// Epoch of the elements of `object` known to the predictions.
+async fn elements_epoch(state: &AppState, object: &str) -> Option<DateTime<Utc>> {
+    let predict_db = state.predict_db.lock().await;
+    let (_, sat) = predict_db.find(object)?;
+    Some(sat.elements.datetime.and_utc())
+}
+
+async fn send(socket: &mut WebSocket, message: &TrackerMessage) -> bool {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(text.into())).await.is_ok()
+}
+
+/// Sends the tracker followed and its updates to `socket`, until the run of `task`, if given, is
+/// over or the client goes away.
+async fn send_updates(
+    mut socket: WebSocket,
+    state: AppState,
+    auth: AuthenticatedKey,
+    task: Option<String>,
+) {
+    let mut viewable = HashMap::new();
+    // Run and object tracked, and time of the last sample sent
+    let mut followed: Option<(String, String)> = None;
+    let mut last_sample = None;
+    let mut epoch = None;
+    let mut first = true;
+    loop {
+        if !first {
+            tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                    // Nothing is expected from the client
+                    Some(Ok(_)) => continue,
+                },
+                _ = tokio::time::sleep(STATUS_POLL_INTERVAL) => {}
+            }
+        }
+
+        if let Some(task) = &task
+            && state.runs.get(task).is_none()
+        {
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+        let current = current_tracker(&state, &auth, task.as_deref(), &mut viewable).await;
+
+        let tracked = current
+            .as_ref()
+            .map(|(id, status)| (id.clone(), status.object.clone()));
+        if first || tracked != followed {
+            first = false;
+            followed = tracked;
+            last_sample = None;
+            epoch = None;
+            let mode = TrackerMessage::Mode {
+                mode: match followed {
+                    Some(_) => TrackerMode::Tracking,
+                    None => TrackerMode::Idle,
+                },
+                task: followed.as_ref().map(|(id, _)| id.clone()),
+                object: followed.as_ref().map(|(_, object)| object.clone()),
+            };
+            if !send(&mut socket, &mode).await {
+                return;
+            }
+        }
+        let Some((id, status)) = current else {
+            continue;
+        };
+
+        if epoch.is_none() || elements_epoch(&state, &status.object).await != epoch {
+            epoch = None;
+            if let Some((elements_epoch, points)) =
+                trajectory(&state, &status.object, Utc::now()).await
+            {
+                epoch = Some(elements_epoch);
+                let trajectory = TrackerMessage::Trajectory {
+                    task: id.clone(),
+                    object: status.object.clone(),
+                    epoch: elements_epoch.to_rfc3339(),
+                    points,
+                };
+                if !send(&mut socket, &trajectory).await {
+                    return;
+                }
+            }
+        }
+        if last_sample != Some(status.time) {
+            last_sample = Some(status.time);
+            if !send(&mut socket, &TrackerMessage::Sample { task: id, status }).await {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use futures_util::StreamExt;
+    use sat_o_mat::scheduler;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    fn config(tmp: &tempfile::TempDir) -> Config {
+        let tle_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle");
+        std::fs::create_dir_all(tmp.path().join("tle")).unwrap();
+        std::fs::copy(
+            tle_dir.join("nanoff_a.txt"),
+            tmp.path().join("tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        let keys = vec![ApiKey {
+            name: None,
+            id: None,
+            key: "test-key".into(),
+            permissions: vec![Permission::ViewTasks],
+        }];
+        Config {
+            ground_station: Config::default().ground_station,
+            ..Config::for_test(tmp.path(), keys)
+        }
+    }
+
+    #[tokio::test]
+    async fn trackers_need_a_key_and_a_run_in_progress() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (router, _) = api::routes(api::state(&config(&tmp))).split_for_parts();
+
+        let req = Request::get("/api/v1/tracker/ws?task=pass")
+            .header("api_key", "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = Request::get("/api/v1/tracker/ws")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn tracker_updates_are_streamed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = api::state(&config(&tmp));
+        let runs = state.runs.clone();
+        let (router, _) = api::routes(state).split_for_parts();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router).into_future());
+
+        let url = format!("ws://{address}/api/v1/tracker/ws?token=test-key");
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let mut next = async || {
+            let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            let tungstenite::Message::Text(text) = message else {
+                panic!("expected a text message, got {message:?}");
+            };
+            serde_json::from_str::<serde_json::Value>(text.as_str()).unwrap()
+        };
+        assert_eq!(
+            next().await,
+            serde_json::json!({"type": "mode", "mode": "idle", "task": null, "object": null})
+        );
+
+        std::fs::create_dir_all(tmp.path().join("Active")).unwrap();
+        std::fs::write(
+            tmp.path().join("Active/pass.yaml"),
+            "variables:\n  end: \"2099-01-01T00:00:00Z\"\nsteps:\n  - cmd: \"sleep 60\"\n    wait: true\n",
+        )
+        .unwrap();
+        let tasks_path = tmp.path().to_path_buf();
+        let scheduler_runs = runs.clone();
+        let scheduler = tokio::spawn(async move {
+            scheduler::run_until(
+                &tasks_path,
+                None,
+                scheduler_runs,
+                Default::default(),
+                std::future::pending(),
+            )
+            .await
+        });
+        while runs.get("pass").is_none() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let status = TrackerStatus {
+            object: "NanoFF A".into(),
+            time: Utc::now(),
+            azimuth: 120.5,
+            elevation: 30.25,
+            range_rate: -1500.0,
+            tx_doppler: None,
+            rx_doppler: Some(4200),
+            rotator_errors: Default::default(),
+        };
+        let path = tmp.path().join("Artifacts/pass").join(STATUS_FILE);
+        std::fs::write(&path, serde_json::to_vec(&status).unwrap()).unwrap();
+
+        assert_eq!(
+            next().await,
+            serde_json::json!({"type": "mode", "mode": "tracking", "task": "pass", "object": "NanoFF A"})
+        );
+        let trajectory = next().await;
+        assert_eq!(trajectory["type"], "trajectory");
+        assert_eq!(trajectory["object"], "NanoFF A");
+        assert_eq!(trajectory["epoch"], "2026-01-14T14:57:16.387776+00:00");
+        assert_eq!(trajectory["points"].as_array().unwrap().len(), 91);
+        let sample = next().await;
+        assert_eq!(sample["type"], "sample");
+        assert_eq!(sample["task"], "pass");
+        assert_eq!(sample["status"], serde_json::to_value(&status).unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(next().await["mode"], "idle");
+
+        scheduler.abort();
+    }
+}
@@ -0,0 +1,29 @@
+//! Deprecation of the unversioned API routes.
+
+use axum::extract::{OriginalUri, Request};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Prefix of the unversioned routes.
+const UNVERSIONED_PREFIX: &str = "/api/";
+
+/// Prefix of the routes replacing them.
+const CURRENT_PREFIX: &str = "/api/v1/";
+
+/// Middleware adding the `Deprecation` header, and a `Link` to the versioned route.
+pub async fn deprecated(request: Request, next: Next) -> Response {
+    let successor = request
+        .extensions()
+        .get::<OriginalUri>()
+        .and_then(|uri| uri.path().strip_prefix(UNVERSIONED_PREFIX))
+        .map(|path| format!("<{CURRENT_PREFIX}{path}>; rel=\"successor-version\""));
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Some(value) = successor.and_then(|s| HeaderValue::from_str(&s).ok()) {
+        headers.insert("link", value);
+    }
+    response
+}
@@ -0,0 +1,365 @@
+//! Command line client for the API of a remote station.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, anyhow, bail};
+use clap::{Args, Subcommand};
+use serde_json::{Value, json};
+
+use crate::http;
+
+#[derive(Args)]
+pub struct ClientArgs {
+    /// URL of the station, e.g. http://station.example.org:8080. HTTPS URLs are requested with
+    /// `curl`.
+    #[arg(long, env = "SAT_O_MAT_SERVER")]
+    pub server: String,
+    /// API key. Prefer the environment variable, which keeps the key out of the process list.
+    #[arg(long, env = "SAT_O_MAT_API_KEY", hide_env_values = true)]
+    pub key: Option<String>,
+    /// Talk to this hosted station instead of the main one
+    #[arg(long)]
+    pub station: Option<String>,
+
+    #[command(subcommand)]
+    pub command: ClientCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ClientCommand {
+    /// Submits a task, or replaces it if it exists and can still be edited
+    Submit {
+        /// The task definition file
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// ID of the task. Defaults to the file name without extension
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Lists the tasks
+    List {
+        /// Only list the tasks in this state (Active, PendingApproval, Completed, Failed or
+        /// Rejected)
+        #[arg(long)]
+        state: Option<String>,
+    },
+    /// Prints the definition of a task
+    Show { id: String },
+    /// Approves tasks pending approval
+    Approve {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Rejects tasks pending approval
+    Reject {
+        #[arg(required = true)]
+        ids: Vec<String>,
+        /// Why the tasks are rejected, shown to their submitters
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Deletes a task
+    Delete { id: String },
+}
+
+/// Runs the client command, returning what to print.
+pub async fn run(args: ClientArgs) -> anyhow::Result<String> {
+    let client = Client::new(&args)?;
+
+    match args.command {
+        ClientCommand::Submit { file, id } => {
+            let yaml = fs::read_to_string(&file)
+                .with_context(|| format!("failed to read {}", file.display()))?;
+            let id = match id {
+                Some(id) => id,
+                None => file
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .ok_or_else(|| anyhow!("cannot derive a task ID from {}", file.display()))?,
+            };
+            let response = client
+                .send("PUT", &task_path(&id), Some(("text/plain", &yaml)))
+                .await?;
+            let action = if response.status == 201 {
+                "submitted"
+            } else {
+                "updated"
+            };
+            Ok(format!("{id} {action}\n"))
+        }
+        ClientCommand::List { state } => {
+            let response = client.send("GET", "/tasks", None).await?;
+            let tasks: Vec<Value> = serde_json::from_slice(&response.body)?;
+            let rows = tasks
+                .iter()
+                .filter(|task| state.as_ref().is_none_or(|state| task["state"] == **state))
+                .map(|task| {
+                    ["id", "state", "start", "end", "owner"]
+                        .map(|field| task[field].as_str().unwrap_or("-").to_string())
+                });
+            Ok(table(["ID", "STATE", "START", "END", "OWNER"], rows))
+        }
+        ClientCommand::Show { id } => {
+            let response = client.send("GET", &task_path(&id), None).await?;
+            Ok(response.text())
+        }
+        ClientCommand::Approve { ids } => {
+            batch(
+                &client,
+                json!({ "action": "approve", "ids": ids }),
+                "approved",
+            )
+            .await
+        }
+        ClientCommand::Reject { ids, reason } => {
+            let body = json!({ "action": "reject", "ids": ids, "reason": reason });
+            batch(&client, body, "rejected").await
+        }
+        ClientCommand::Delete { id } => {
+            client.send("DELETE", &task_path(&id), None).await?;
+            Ok(format!("{id} deleted\n"))
+        }
+    }
+}
+
+/// Sends the approve-batch request `body`, reporting the tasks `done` and those that failed.
+async fn batch(client: &Client, body: Value, done: &str) -> anyhow::Result<String> {
+    let body = body.to_string();
+    let response = client
+        .send(
+            "POST",
+            "/tasks/approve-batch",
+            Some(("application/json", &body)),
+        )
+        .await?;
+    let results: Vec<Value> = serde_json::from_slice(&response.body)?;
+    let mut output = String::new();
+    let mut failed = false;
+    for result in results {
+        let id = result["id"].as_str().unwrap_or_default();
+        match result["error"].as_str() {
+            Some(error) => {
+                failed = true;
+                output.push_str(&format!("{id} not {done}: {error}\n"));
+            }
+            None => output.push_str(&format!("{id} {done}\n")),
+        }
+    }
+    if failed {
+        bail!("{}", output.trim_end());
+    }
+    Ok(output)
+}
+
+struct Client {
+    base: String,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Client {
+    fn new(args: &ClientArgs) -> anyhow::Result<Self> {
+        let server = args.server.trim_end_matches('/');
+        if !http::is_supported(server) {
+            bail!("unsupported server URL {server}, expected http(s)://host[:port]");
+        }
+        let base = match &args.station {
+            Some(station) => format!(
+                "{server}/api/v1/stations/{}",
+                http::encode_path_segment(station)
+            ),
+            None => format!("{server}/api/v1"),
+        };
+        let headers = args
+            .key
+            .iter()
+            .map(|key| ("api_key", key.clone()))
+            .collect();
+        Ok(Self { base, headers })
+    }
+
+    /// Sends a request to `path` under the API, failing with the error returned by the station
+    /// unless the response status is 2xx.
+    async fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<(&str, &str)>,
+    ) -> anyhow::Result<http::Response> {
+        let url = format!("{}{path}", self.base);
+        let response = http::request(method, &url, &self.headers, body)
+            .await
+            .with_context(|| format!("request to {url} failed"))?;
+        if !response.is_success() {
+            bail!("{} ({})", response.text().trim(), response.status);
+        }
+        Ok(response)
+    }
+}
+
+fn task_path(id: &str) -> String {
+    format!("/tasks/{}", http::encode_path_segment(id))
+}
+
+/// Formats `rows` as a table with aligned columns.
+pub fn table<const N: usize>(header: [&str; N], rows: impl Iterator<Item = [String; N]>) -> String {
+    let rows: Vec<[String; N]> = std::iter::once(header.map(str::to_string))
+        .chain(rows)
+        .collect();
+    let widths: [usize; N] =
+        std::array::from_fn(|i| rows.iter().map(|row| row[i].len()).max().unwrap_or(0));
+
+    let mut output = String::new();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::api;
+    use crate::config::{ApiKey, Config, Permission};
+
+    const TASK_YAML: &str = "\
+variables:
+  start: \"2099-06-01T10:00:00Z\"
+  end: \"2099-06-01T10:30:00Z\"
+steps:
+  - cmd: \"echo hello\"
+";
+
+    async fn serve(tmp: &tempfile::TempDir) -> String {
+        let config = Config::for_test(
+            tmp.path(),
+            vec![ApiKey {
+                name: Some("operator".into()),
+                id: None,
+                key: "test-key".into(),
+                permissions: vec![
+                    Permission::ViewTasks,
+                    Permission::SubmitTask,
+                    Permission::ApproveTask,
+                    Permission::DeleteTask,
+                ],
+            }],
+        );
+        for dir in ["Active", "PendingApproval", "Completed", "Failed"] {
+            fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        }
+        let (router, _) = api::routes(api::state(&config)).split_for_parts();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        url
+    }
+
+    async fn client(server: &str, key: &str, command: ClientCommand) -> anyhow::Result<String> {
+        run(ClientArgs {
+            server: server.to_string(),
+            key: Some(key.to_string()),
+            station: None,
+            command,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn manages_tasks_of_a_remote_station() {
+        let tmp = tempfile::tempdir().unwrap();
+        let server = serve(&tmp).await;
+        let file = tmp.path().join("pass.yml");
+        fs::write(&file, TASK_YAML).unwrap();
+
+        let submit = ClientCommand::Submit { file, id: None };
+        assert_eq!(
+            client(&server, "test-key", submit).await.unwrap(),
+            "pass submitted\n"
+        );
+
+        let list = ClientCommand::List { state: None };
+        let output = client(&server, "test-key", list).await.unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("ID    STATE"));
+        assert!(lines[1].starts_with("pass  PendingApproval  2099-06-01 10:00:00 UTC"));
+        assert!(lines[1].ends_with("operator"));
+
+        let show = ClientCommand::Show { id: "pass".into() };
+        let output = client(&server, "test-key", show).await.unwrap();
+        assert!(output.contains("echo hello"));
+
+        let approve = ClientCommand::Approve {
+            ids: vec!["pass".into(), "nope".into()],
+        };
+        let error = client(&server, "test-key", approve).await.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("pass approved\nnope not approved")
+        );
+        assert!(tmp.path().join("Active/pass.yaml").exists());
+
+        let file = tmp.path().join("other.yaml");
+        fs::write(&file, TASK_YAML.replace("2099-06-01", "2099-06-02")).unwrap();
+        let submit = ClientCommand::Submit { file, id: None };
+        client(&server, "test-key", submit).await.unwrap();
+        let reject = ClientCommand::Reject {
+            ids: vec!["other".into()],
+            reason: Some("clashes with maintenance".into()),
+        };
+        assert_eq!(
+            client(&server, "test-key", reject).await.unwrap(),
+            "other rejected\n"
+        );
+        let list = ClientCommand::List {
+            state: Some("Rejected".into()),
+        };
+        let output = client(&server, "test-key", list).await.unwrap();
+        assert!(
+            output
+                .lines()
+                .nth(1)
+                .unwrap()
+                .starts_with("other  Rejected")
+        );
+
+        let delete = ClientCommand::Delete { id: "pass".into() };
+        assert_eq!(
+            client(&server, "test-key", delete).await.unwrap(),
+            "pass deleted\n"
+        );
+
+        let show = ClientCommand::Show { id: "pass".into() };
+        let error = client(&server, "test-key", show).await.unwrap_err();
+        assert!(error.to_string().starts_with("not found"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn reports_authentication_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let server = serve(&tmp).await;
+        let list = ClientCommand::List { state: None };
+        let error = client(&server, "wrong-key", list).await.unwrap_err();
+        assert!(error.to_string().ends_with("(401)"), "{error}");
+    }
+
+    #[test]
+    fn tables_are_aligned() {
+        let rows = [["a".to_string(), "long value".to_string()]].into_iter();
+        assert_eq!(
+            table(["NAME", "VALUE"], rows),
+            "NAME  VALUE\na     long value\n"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Shell completions and manual pages generated from the command line definitions, for packaging.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Command;
+use clap_complete::Shell;
+use clap_mangen::Man;
+
+/// The completion script of `command` for `shell`.
+pub fn completions(mut command: Command, shell: Shell) -> String {
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    String::from_utf8(script).expect("completion scripts are UTF-8")
+}
+
+/// Writes a manual page for `command` and each of its subcommands to `dir`, named after the
+/// command, e.g. `sat-o-mat-tle-fetch.1`. Returns the paths written.
+pub fn manpages(mut command: Command, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    // Names the subcommands after the commands leading to them
+    command.build();
+    let mut written = Vec::new();
+    write_manpages(command, dir, &mut written)?;
+    Ok(written)
+}
+
+fn write_manpages(command: Command, dir: &Path, written: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for sub in command
+        .get_subcommands()
+        .filter(|sub| sub.get_name() != "help" && !sub.is_hide_set())
+    {
+        write_manpages(sub.clone(), dir, written)?;
+    }
+    written.push(Man::new(command).generate_to(dir)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn completions_cover_every_subcommand() {
+        let command = crate::Args::command();
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = completions(command.clone(), shell);
+            for sub in command.get_subcommands() {
+                assert!(
+                    script.contains(sub.get_name()),
+                    "{shell}: {}",
+                    sub.get_name()
+                );
+            }
+        }
+        let bash = completions(command.clone(), Shell::Bash);
+        assert!(bash.contains("cmd=\"sat__o__mat__subcmd__tle__subcmd__fetch\""));
+        assert!(
+            bash.contains("--format)\n                    COMPREPLY=($(compgen -W \"text json\"")
+        );
+        assert!(completions(command, Shell::Zsh).starts_with("#compdef sat-o-mat\n"));
+    }
+
+    #[test]
+    fn manpages_are_written_for_every_command() {
+        let tmp = tempfile::tempdir().unwrap();
+        let written = manpages(crate::Args::command(), tmp.path()).unwrap();
+        let names: Vec<_> = written
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert!(names.contains(&"sat-o-mat.1"));
+        assert!(names.contains(&"sat-o-mat-tracker.1"));
+        assert!(!names.iter().any(|name| name.contains("help")), "{names:?}");
+
+        let page = fs::read_to_string(tmp.path().join("sat-o-mat-tle-fetch.1")).unwrap();
+        assert!(page.starts_with(".ie \\n(.g .ds Aq \\(aq"), "{page}");
+        assert!(page.contains(".TH sat-o-mat-tle-fetch 1"), "{page}");
+        assert!(page.contains("\\fB\\-\\-group\\fR"), "{page}");
+    }
+}
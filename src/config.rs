@@ -1,15 +1,30 @@
-use std::{fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    f64::consts::PI,
+    fs,
+    path::PathBuf,
+    time::Duration,
+};
 
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use cross_xdg::BaseDirs;
 use lox_space::{
-    analysis::visibility::ElevationMask,
+    analysis::visibility::{ElevationMask, ElevationMaskError},
     bodies::DynOrigin,
     core::coords::LonLatAlt,
     prelude::{GroundLocation, GroundStation},
 };
-use serde::{Deserialize, Serialize, Serializer, de, ser::SerializeStruct};
+use sat_o_mat::predict::{ElevationThresholds, PassSearchSteps, SatelliteGroups};
+use sat_o_mat::radio::Backend;
+use sat_o_mat::scheduler::Preemption;
+use sat_o_mat::tracker::rotator::{ParkPosition, RotatorLimits};
+use serde::{Deserialize, Serialize, Serializer, de};
+use serde_yaml::Value;
+use thiserror::Error;
 use tracing::info;
+use utoipa::ToSchema;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Config {
@@ -17,20 +32,544 @@ pub struct Config {
     pub api: ApiConfig,
     pub tasks_path: PathBuf,
     pub tle_path: PathBuf,
+    /// Directory the task templates are read from. Defaults to `Templates` in `tasks_path`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates_path: Option<PathBuf>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_ground_station",
+        serialize_with = "serialize_ground_station"
+    )]
+    pub ground_station: Option<GroundStation>,
+    /// Other (remote) stations, by name, for which passes can be predicted.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        deserialize_with = "deserialize_stations",
+        serialize_with = "serialize_stations"
+    )]
+    pub stations: HashMap<String, GroundStation>,
+    #[serde(default)]
+    pub predict: PredictConfig,
+    #[serde(default)]
+    pub auto_schedule: AutoScheduleConfig,
+    #[serde(default)]
+    pub scheduling: SchedulingConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    /// Station hardware, used to describe the tasks to their approvers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<ResourceConfig>,
+    /// Other stations controlled by this instance, by name, served under `/api/v1/stations/{name}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hosted_stations: BTreeMap<String, HostedStationConfig>,
+    /// Background work done by `sat-o-mat daemon` besides serving the stations.
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    /// Remote storage the artifacts of each run are uploaded to once it finishes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uploads: Vec<UploadConfig>,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub web: WebConfig,
+}
+
+/// A station controlled by the same instance as the main one, with its own location, tasks and
+/// hardware. It shares the API keys, TLEs (unless `tle_path` is set), templates (if
+/// `templates_path` is set), prediction settings, notifications and uploads of the main station.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct HostedStationConfig {
+    pub tasks_path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tle_path: Option<PathBuf>,
     #[serde(
         default,
         deserialize_with = "deserialize_ground_station",
         serialize_with = "serialize_ground_station"
     )]
     pub ground_station: Option<GroundStation>,
+    #[serde(default)]
+    pub auto_schedule: AutoScheduleConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<ResourceConfig>,
+}
+
+impl Config {
+    /// Directory the task templates are read from.
+    pub fn templates_path(&self) -> PathBuf {
+        self.templates_path
+            .clone()
+            .unwrap_or_else(|| self.tasks_path.join("Templates"))
+    }
+
+    /// Returns the configuration of the hosted station `name`, as if it was the main station.
+    pub fn hosted_station(&self, name: &str) -> Option<Config> {
+        let station = self.hosted_stations.get(name)?.clone();
+        Some(Config {
+            station_name: name.to_string(),
+            api: self.api.clone(),
+            tasks_path: station.tasks_path,
+            tle_path: station.tle_path.unwrap_or_else(|| self.tle_path.clone()),
+            templates_path: self.templates_path.clone(),
+            ground_station: station.ground_station,
+            stations: self.stations.clone(),
+            predict: self.predict.clone(),
+            auto_schedule: station.auto_schedule,
+            scheduling: self.scheduling.clone(),
+            notifications: self.notifications.clone(),
+            resources: station.resources,
+            hosted_stations: BTreeMap::new(),
+            daemon: DaemonConfig::default(),
+            uploads: self.uploads.clone(),
+            logging: self.logging.clone(),
+            web: WebConfig::default(),
+        })
+    }
+
+    /// The `sdr` settings of the resource `name`.
+    pub fn sdr(&self, name: &str) -> anyhow::Result<&SdrConfig> {
+        let resource = self
+            .resources
+            .iter()
+            .find(|r| r.name == name)
+            .with_context(|| format!("unknown resource {name}"))?;
+        resource
+            .sdr
+            .as_ref()
+            .with_context(|| format!("resource {name} has no sdr settings"))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PredictConfig {
+    /// Per-group and per-satellite minimum elevation (degrees), overriding the ground station's
+    /// `min_elevation` when finding passes.
+    #[serde(default)]
+    pub min_elevation: ElevationThresholds,
+    /// Step sizes (seconds) used when searching for passes.
+    #[serde(default)]
+    pub steps: PassSearchSteps,
+    /// Named groups of satellites, each listing satellite names or NORAD IDs. Satellites are also
+    /// grouped by the name (without extension) of the file they were loaded from.
+    #[serde(default)]
+    pub groups: SatelliteGroups,
+    /// Element sets older than this (days) at the start of a prediction produce a warning.
+    #[serde(default = "default_max_element_age_days")]
+    pub max_element_age_days: f64,
+    /// Predict solar outages: times when a satellite is within this many degrees of the Sun as
+    /// seen from the station. Disabled if unset.
+    #[serde(default)]
+    pub solar_outage_angle: Option<f64>,
+    /// URL the TLEs of a group are downloaded from by `sat-o-mat tle fetch`, with `{group}`
+    /// replaced by the name of the group. Downloaded with curl, over HTTP or HTTPS.
+    #[serde(default = "default_tle_source")]
+    pub tle_source: String,
+    /// Frequencies of the satellites, by name or NORAD ID, filled in as variables of the tasks
+    /// scheduled for their passes.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub frequencies: BTreeMap<String, SatelliteFrequencies>,
+    /// Groups downloaded from Space-Track instead of `tle_source`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub space_track: Option<SpaceTrackConfig>,
+}
+
+fn default_max_element_age_days() -> f64 {
+    3.0
+}
+
+fn default_tle_source() -> String {
+    "https://celestrak.org/NORAD/elements/gp.php?GROUP={group}&FORMAT=tle".to_string()
+}
+
+impl Default for PredictConfig {
+    fn default() -> Self {
+        Self {
+            min_elevation: Default::default(),
+            steps: Default::default(),
+            groups: Default::default(),
+            max_element_age_days: default_max_element_age_days(),
+            solar_outage_angle: None,
+            tle_source: default_tle_source(),
+            frequencies: BTreeMap::new(),
+            space_track: None,
+        }
+    }
+}
+
+/// Frequencies (Hz) of a satellite, filled in as the `rx_frequency` and `tx_frequency` variables of
+/// the tasks scheduled for its passes.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct SatelliteFrequencies {
+    /// Downlink
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rx: Option<u64>,
+    /// Uplink
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx: Option<u64>,
+}
+
+impl PredictConfig {
+    /// The frequencies configured for satellite `name`, by name or else by NORAD ID.
+    pub fn frequencies_of(&self, name: &str, norad_id: u64) -> Option<&SatelliteFrequencies> {
+        self.frequencies
+            .get(name)
+            .or_else(|| self.frequencies.get(&norad_id.to_string()))
+    }
+}
+
+/// An account on Space-Track and the satellites downloaded with it. The downloads go through
+/// `curl`, as Space-Track is only served over HTTPS.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SpaceTrackConfig {
+    pub username: String,
+    /// Best set with `password_file`.
+    pub password: String,
+    /// NORAD IDs of the satellites of each group, downloaded to `<group>.txt` in `tle_path`.
+    pub groups: BTreeMap<String, Vec<u64>>,
+    #[serde(default = "default_space_track_url")]
+    pub url: String,
+}
+
+fn default_space_track_url() -> String {
+    "https://www.space-track.org".to_string()
+}
+
+/// Rules for scheduling passes automatically.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AutoScheduleConfig {
+    /// How often (minutes) the rules are evaluated against new predictions.
+    #[serde(default = "default_auto_schedule_interval_minutes")]
+    pub interval_minutes: u64,
+    /// How far ahead (hours) passes are scheduled.
+    #[serde(default = "default_auto_schedule_lookahead_hours")]
+    pub lookahead_hours: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<ScheduleRule>,
+}
+
+fn default_auto_schedule_interval_minutes() -> u64 {
+    60
+}
+
+fn default_auto_schedule_lookahead_hours() -> i64 {
+    24
+}
+
+impl Default for AutoScheduleConfig {
+    fn default() -> Self {
+        Self {
+            interval_minutes: default_auto_schedule_interval_minutes(),
+            lookahead_hours: default_auto_schedule_lookahead_hours(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// How the tasks of overlapping time ranges are scheduled.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SchedulingConfig {
+    /// What happens to the Active tasks overlapping a task of higher `priority` placed in Active.
+    #[serde(default)]
+    pub preemption: Preemption,
+}
+
+/// Notifications of task and run events sent to external services.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct NotificationConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Notify the approvers when a task is placed in PendingApproval, and optionally when a run
+    /// fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approvals: Option<ApprovalNotificationConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ApprovalNotificationConfig {
+    /// URL of the dashboard where the tasks are approved, included in the notifications.
+    pub dashboard_url: String,
+    /// Also notify the approvers of the runs that failed.
+    #[serde(default)]
+    pub failed_runs: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<EmailConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<MatrixConfig>,
+}
+
+/// Email sent through an SMTP relay, e.g. a local MTA or, over TLS, a mail provider.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    /// Encryption of the connection to the relay. Mail is sent with `curl` over TLS.
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// Log in to the relay, only over TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    25
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Plaintext, e.g. to a local MTA
+    #[default]
+    None,
+    /// Upgraded with STARTTLS, usually on port 587
+    Starttls,
+    /// TLS from the start, usually on port 465
+    Implicit,
+}
+
+/// Message posted to a Matrix room.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MatrixConfig {
+    /// `http://` or `https://` URL of the homeserver's client API.
+    pub homeserver: String,
+    pub access_token: String,
+    pub room_id: String,
+}
+
+/// A piece of station hardware, used by the task steps running one of its `commands`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ResourceConfig {
+    pub name: String,
+    /// Programs controlling the resource, e.g. `rotctl` or `rigctl`.
+    pub commands: Vec<String>,
+    /// Whether the resource can transmit.
+    #[serde(default)]
+    pub transmit: bool,
+    /// Address of the `rotctld` or `rigctld` server controlling the resource, used by
+    /// `sat-o-mat tracker`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Travel of a rotator, the positions it is steered to are kept within.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<RotatorLimits>,
+    /// Position a rotator is parked at after tracking and by `sat-o-mat rotator park`. The park
+    /// position of its `rotctld` server if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub park: Option<ParkPosition>,
+    /// Settings of an SDR, used by `sat-o-mat radio run|demodulate --sdr NAME`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdr: Option<SdrConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SdrConfig {
+    /// Tools driving the SDR: `soapy` (rx_sdr) or `rtlsdr` (rtl_sdr).
+    #[serde(default = "default_sdr_backend")]
+    pub backend: Backend,
+    /// SoapySDR device arguments, e.g. `driver=rtlsdr,serial=01`, or the index of an RTL-SDR.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Gain (dB). Automatic if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f64>,
+    /// Sample rates (Hz) the device supports. Any if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample_rates: Vec<u64>,
 }
 
-#[derive(Deserialize)]
+fn default_sdr_backend() -> Backend {
+    Backend::Soapy
+}
+
+/// Posts a JSON description of each event to `url`, or the payload of `template`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct WebhookConfig {
+    /// `http://` or `https://` URL receiving the events, e.g. a Slack incoming webhook.
+    pub url: String,
+    /// Only send these events. All events if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<NotificationEvent>,
+    /// Sign the payloads with HMAC-SHA256 using this secret, sent in the `X-Sat-O-Mat-Signature`
+    /// header as `sha256=<hex>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Payload sent instead of the JSON description, with `{event}`, `{task_id}`, `{station}`
+    /// and `{time}` replaced, e.g. `{"text": "{task_id} on {station}: {event}"}` for a chat
+    /// service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Content type of the payloads of `template`.
+    #[serde(default = "default_webhook_content_type")]
+    pub content_type: String,
+}
+
+fn default_webhook_content_type() -> String {
+    "application/json".to_string()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// A task was submitted through the API or by an auto-scheduling rule.
+    TaskSubmitted,
+    /// A task was placed in Active, either on submission or from PendingApproval.
+    TaskApproved,
+    /// A task was placed in PendingApproval, to be approved or rejected.
+    ApprovalNeeded,
+    /// A task pending approval was rejected or deleted.
+    TaskRejected,
+    RunStarted,
+    RunCompleted,
+    /// The task could not be run.
+    RunFailed,
+    /// A step failed and the run was aborted.
+    RunAborted,
+}
+
+/// Schedules every pass of the satellites in `group` reaching `min_elevation` using `template`.
+///
+/// When passes overlap, the one matched by the rule with the highest `priority` is scheduled.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(example = json!({
+    "name": "iss-voice",
+    "satellite": "25544",
+    "min_elevation": 30.0,
+    "template": "fm_voice",
+    "until": "2026-06-08T00:00:00Z"
+}))]
+pub struct ScheduleRule {
+    pub name: String,
+    /// Only schedule satellites in this group. All satellites if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Only schedule this satellite, by name or NORAD ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub satellite: Option<String>,
+    /// Minimum maximum elevation (degrees) of the passes to schedule.
+    #[serde(default)]
+    pub min_elevation: f64,
+    /// Template used to render the tasks, as in `POST /api/predict/schedule`.
+    pub template: String,
+    #[serde(default)]
+    pub priority: i32,
+    /// Variables overriding the template's and the pass' ones.
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// Place the tasks in Active instead of PendingApproval.
+    #[serde(default)]
+    pub auto_approve: bool,
+    /// Only schedule passes starting before this time. Unlimited if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub until: Option<DateTime<Utc>>,
+    /// Identity of whoever created the rule through the API, who owns the tasks it schedules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(read_only)]
+    pub owner: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
 struct GroundStationDef {
     longitude: f64,
     latitude: f64,
     altitude: f64,
     min_elevation: f64,
+    /// Horizon profile, linearly interpolated in azimuth. Elevations below `min_elevation` are
+    /// raised to it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    horizon: Vec<HorizonPoint>,
+}
+
+impl GroundStationDef {
+    fn into_ground_station(self, name: &str) -> Result<GroundStation, String> {
+        let coords = LonLatAlt::from_degrees(self.longitude, self.latitude, self.altitude)
+            .map_err(|e| e.to_string())?;
+        let location =
+            GroundLocation::try_new(coords, DynOrigin::Earth).map_err(|e| e.to_string())?;
+        let mask = horizon_mask(self.min_elevation, &self.horizon).map_err(|e| e.to_string())?;
+        Ok(GroundStation::new(name, location, mask))
+    }
+
+    fn from_ground_station(gs: &GroundStation) -> Self {
+        let coords = gs.location().coordinates();
+        let (min_elevation, horizon) = match gs.mask() {
+            ElevationMask::Fixed(min_elevation) => (*min_elevation, vec![]),
+            ElevationMask::Variable(series) => {
+                let min_elevation = series.y().iter().copied().fold(f64::INFINITY, f64::min);
+                // The last point (at 180°) duplicates the first one (at -180°)
+                let horizon = series
+                    .x()
+                    .iter()
+                    .zip(series.y())
+                    .take(series.x().len() - 1)
+                    .map(|(az, el)| HorizonPoint {
+                        azimuth: az.to_degrees(),
+                        elevation: el.to_degrees(),
+                    })
+                    .collect();
+                (min_elevation, horizon)
+            }
+        };
+
+        Self {
+            longitude: coords.lon().to_degrees(),
+            latitude: coords.lat().to_degrees(),
+            altitude: coords.alt().to_meters(),
+            min_elevation: min_elevation.to_degrees(),
+            horizon,
+        }
+    }
+}
+
+/// A point of the station's horizon profile, in degrees. Azimuth is measured clockwise from north.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+struct HorizonPoint {
+    azimuth: f64,
+    elevation: f64,
+}
+
+/// Builds the elevation mask for a station with the given minimum elevation and horizon profile.
+fn horizon_mask(
+    min_elevation: f64,
+    horizon: &[HorizonPoint],
+) -> Result<ElevationMask, ElevationMaskError> {
+    if horizon.is_empty() {
+        return Ok(ElevationMask::with_fixed_elevation(
+            min_elevation.to_radians(),
+        ));
+    }
+
+    // Azimuths in [-180, 180), as used by lox
+    let mut points: Vec<(f64, f64)> = horizon
+        .iter()
+        .map(|p| {
+            let azimuth = (p.azimuth + 180.0).rem_euclid(360.0) - 180.0;
+            (azimuth, p.elevation.max(min_elevation))
+        })
+        .collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // The mask must span [-180, 180]: interpolate across the wrap-around at 180
+    let (first_az, first_el) = points[0];
+    let (last_az, last_el) = points[points.len() - 1];
+    let span = first_az + 360.0 - last_az;
+    let wrap_el = last_el + (first_el - last_el) * (180.0 - last_az) / span;
+
+    let mut azimuth = vec![-PI];
+    let mut elevation = vec![wrap_el.to_radians()];
+    for (az, el) in points.into_iter().skip_while(|(az, _)| *az == -180.0) {
+        azimuth.push(az.to_radians());
+        elevation.push(el.to_radians());
+    }
+    azimuth.push(PI);
+    elevation.push(wrap_el.to_radians());
+
+    ElevationMask::new(azimuth, elevation)
 }
 
 fn deserialize_ground_station<'de, D>(deserializer: D) -> Result<Option<GroundStation>, D::Error>
@@ -39,12 +578,8 @@ where
 {
     Option::<GroundStationDef>::deserialize(deserializer)?
         .map(|def| {
-            let coords = LonLatAlt::from_degrees(def.longitude, def.latitude, def.altitude)
-                .map_err(de::Error::custom)?;
-            let location =
-                GroundLocation::try_new(coords, DynOrigin::Earth).map_err(de::Error::custom)?;
-            let mask = ElevationMask::with_fixed_elevation(def.min_elevation.to_radians());
-            Ok(GroundStation::new("GS", location, mask))
+            def.into_ground_station("GS")
+                .map_err(|e| de::Error::custom(format!("ground_station: {e}")))
         })
         .transpose()
 }
@@ -53,45 +588,223 @@ fn serialize_ground_station<S>(gs: &Option<GroundStation>, serializer: S) -> Res
 where
     S: Serializer,
 {
-    match gs {
-        None => serializer.serialize_none(),
-        Some(gs) => {
-            let coords = gs.location().coordinates();
-            let mut state = serializer.serialize_struct("GroundStation", 4)?;
-            state.serialize_field("longitude", &coords.lon().to_degrees())?;
-            state.serialize_field("latitude", &coords.lat().to_degrees())?;
-            state.serialize_field("altitude", &coords.alt().to_meters())?;
-            state.serialize_field("min_elevation", &gs.mask().min_elevation(0.0).to_degrees())?;
-            state.end()
-        }
-    }
+    gs.as_ref()
+        .map(GroundStationDef::from_ground_station)
+        .serialize(serializer)
+}
+
+fn deserialize_stations<'de, D>(deserializer: D) -> Result<HashMap<String, GroundStation>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    HashMap::<String, GroundStationDef>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, def)| {
+            let gs = def
+                .into_ground_station(&name)
+                .map_err(|e| de::Error::custom(format!("stations.{name}: {e}")))?;
+            Ok((name, gs))
+        })
+        .collect()
+}
+
+fn serialize_stations<S>(
+    stations: &HashMap<String, GroundStation>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    stations
+        .iter()
+        .map(|(name, gs)| (name, GroundStationDef::from_ground_station(gs)))
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ApiConfig {
     pub keys: Vec<ApiKey>,
+    /// File storing the keys managed through the API. Defaults to `api_keys.yaml` in
+    /// `tasks_path`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keys_path: Option<PathBuf>,
+    /// Also accept JSON Web Tokens issued by an OpenID Connect provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwt: Option<JwtConfig>,
+    /// Limit the request rate of each client. Unlimited if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Give requests without credentials ViewTasks permission, e.g. for a public status page.
+    /// Predictions and station information are always public.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub public_read: bool,
+    /// File the POST, PUT and DELETE calls are recorded in. Defaults to `audit.jsonl` in
+    /// `tasks_path`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_path: Option<PathBuf>,
+    /// Failed authentications allowed per minute from each address. Further requests with
+    /// credentials from the address are refused with 429 without checking them, as checking the
+    /// hashes of the keys is slow on purpose.
+    #[serde(default = "default_failed_logins")]
+    pub failed_logins: u32,
+}
+
+fn default_failed_logins() -> u32 {
+    10
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            keys_path: None,
+            jwt: None,
+            rate_limit: None,
+            public_read: false,
+            audit_path: None,
+            failed_logins: default_failed_logins(),
+        }
+    }
+}
+
+/// Requests allowed per minute, and quotas of particular requests.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Limit for each API key or token subject.
+    #[serde(default = "default_rate_limit_per_key")]
+    pub per_key: u32,
+    /// Limit for each address of the requests without valid credentials.
+    #[serde(default = "default_rate_limit_unauthenticated")]
+    pub unauthenticated: u32,
+    /// Limits on task submissions and predictions, on top of the overall limits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quotas: Vec<QuotaConfig>,
+}
+
+/// Requests of a kind allowed per minute, hour or day to each client.
+///
+/// Quotas naming a client replace, for that client, the quotas of the same requests applying to
+/// every client, so that some keys can be given more (or less) than the others.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct QuotaConfig {
+    pub requests: QuotaRequests,
+    pub limit: u32,
+    #[serde(default)]
+    pub per: QuotaPeriod,
+    /// Clients the quota applies to, as they own tasks: `key:<name>` for the keys of the config,
+    /// `stored:<id>` for the keys created through the API or `jwt:<issuer>:<subject>` for bearer
+    /// tokens. Every client, unauthenticated ones by address, if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaRequests {
+    /// Tasks submitted or edited, from a template or for a predicted pass
+    Submissions,
+    /// Requests for predictions under `/predict`
+    Predictions,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPeriod {
+    #[default]
+    Minute,
+    Hour,
+    Day,
+}
+
+impl QuotaPeriod {
+    pub fn duration(self) -> Duration {
+        Duration::from_secs(match self {
+            Self::Minute => 60,
+            Self::Hour => 60 * 60,
+            Self::Day => 24 * 60 * 60,
+        })
+    }
+}
+
+fn default_rate_limit_per_key() -> u32 {
+    600
+}
+
+fn default_rate_limit_unauthenticated() -> u32 {
+    60
+}
+
+/// Validation of bearer tokens issued by an OpenID Connect provider, e.g. a university SSO.
+///
+/// Tokens must be signed with RS256 by a key in `jwks_path` and carry the configured issuer and
+/// audience. The values of `claim` are mapped to permissions through `permissions`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct JwtConfig {
+    pub issuer: String,
+    pub audience: String,
+    /// JSON Web Key Set of the provider, as served at its `jwks_uri`.
+    pub jwks_path: PathBuf,
+    /// Claim holding the user's groups or roles, either a string or a list of strings.
+    #[serde(default = "default_jwt_claim")]
+    pub claim: String,
+    /// Permissions granted for each value of `claim`.
+    #[serde(default)]
+    pub permissions: HashMap<String, Vec<Permission>>,
+}
+
+fn default_jwt_claim() -> String {
+    "groups".to_string()
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ApiKey {
+    /// Owner of the tasks submitted with this key. Defaults to `config-<index>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// ID the key starts with (`sk_<id>_...`), as printed by `sat-o-mat hash-key`, so that only
+    /// the hash of this entry is checked for the key. Keys without an ID are checked against
+    /// every entry without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The salted hash of the key, as generated by `sat-o-mat hash-key`. Plaintext keys are still
+    /// accepted, but discouraged.
     pub key: String,
     pub permissions: Vec<Permission>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub enum Permission {
     ViewTasks,
     SubmitTask,
     EditTask,
     DeleteTask,
+    /// Like ViewTasks, EditTask and DeleteTask, restricted to the tasks submitted by the caller.
+    ViewOwnTasks,
+    EditOwnTasks,
+    DeleteOwnTasks,
     AutoApproveTask,
+    /// Approve or reject tasks pending approval.
+    ApproveTask,
     SubmitFromTemplate,
+    ManageKeys,
+    /// Read the audit log of the calls changing the station state.
+    ViewAuditLog,
+    /// Drive the station hardware directly, e.g. park a rotator.
+    ControlHardware,
+    /// Load the satellites again from the TLE files.
+    ReloadSatellites,
 }
 
-pub fn load(path: Option<&PathBuf>) -> anyhow::Result<Config> {
-    let default_config_path = PathBuf::from(BaseDirs::new()?.config_home())
+/// Path of the configuration file used when none is given.
+pub fn default_path() -> anyhow::Result<PathBuf> {
+    Ok(PathBuf::from(BaseDirs::new()?.config_home())
         .join("sat-o-mat")
-        .join("config.yml");
+        .join("config.yml"))
+}
+
+pub fn load(path: Option<&PathBuf>) -> anyhow::Result<Config> {
+    let default_config_path = default_path()?;
     let config_path = path.unwrap_or(&default_config_path);
 
     if !fs::exists(config_path)? {
@@ -101,20 +814,327 @@ pub fn load(path: Option<&PathBuf>) -> anyhow::Result<Config> {
         fs::write(config_path, serde_yaml::to_string(&config)?)?;
     }
 
-    let config: Config = serde_yaml::from_str(
-        fs::read_to_string(config_path)
-            .context(format!("Error reading config file {:?}", config_path))?
-            .as_ref(),
+    let config = parse(
+        &fs::read_to_string(config_path)
+            .context(format!("Error reading config file {:?}", config_path))?,
     )
     .context("Error parsing Config file")?;
 
     // Create folders referenced in the config
     fs::create_dir_all(&config.tle_path)?;
     fs::create_dir_all(&config.tasks_path)?;
+    if let Some(templates_path) = &config.templates_path {
+        fs::create_dir_all(templates_path)?;
+    }
+    for station in config.hosted_stations.values() {
+        fs::create_dir_all(&station.tasks_path)?;
+        if let Some(tle_path) = &station.tle_path {
+            fs::create_dir_all(tle_path)?;
+        }
+    }
 
     Ok(config)
 }
 
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("{0}")]
+    Secret(String),
+}
+
+/// Parses the configuration `yaml`, resolving the secrets kept out of it:
+///
+/// - `${NAME}` in any value is replaced by the environment variable `NAME`, and `$${` by `${`.
+/// - A `<field>_file` key sets `<field>` to the contents of the file it names, without the final
+///   newline, e.g. `secret_file: ${CREDENTIALS_DIRECTORY}/webhook` for systemd credentials.
+///
+/// The `variables` of tasks are left as they are, to be evaluated when the tasks run.
+pub fn parse(yaml: &str) -> Result<Config, ParseError> {
+    let mut value: Value = serde_yaml::from_str(yaml)?;
+    resolve_secrets(&mut value, "")?;
+    serde_yaml::from_value(value).map_err(|e| {
+        // The same error from the text has its location
+        serde_yaml::from_str::<Config>(yaml)
+            .err()
+            .unwrap_or(e)
+            .into()
+    })
+}
+
+fn resolve_secrets(value: &mut Value, path: &str) -> Result<(), ParseError> {
+    match value {
+        Value::String(s) => {
+            *s = interpolate(s).map_err(|name| {
+                ParseError::Secret(format!("{path}: environment variable {name} is not set"))
+            })?;
+        }
+        Value::Sequence(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                resolve_secrets(item, &format!("{path}[{i}]"))?;
+            }
+        }
+        Value::Mapping(map) => {
+            let field_path = |key: &str| match path {
+                "" => key.to_string(),
+                _ => format!("{path}.{key}"),
+            };
+            let mut files = Vec::new();
+            for (key, item) in map.iter_mut() {
+                let Some(key) = key.as_str() else { continue };
+                if key == "variables" {
+                    continue;
+                }
+                resolve_secrets(item, &field_path(key))?;
+                if let Some(field) = key.strip_suffix("_file")
+                    && let Value::String(file) = item
+                {
+                    files.push((key.to_string(), field.to_string(), file.clone()));
+                }
+            }
+            for (key, field, file) in files {
+                if map.contains_key(&field) {
+                    return Err(ParseError::Secret(format!(
+                        "{} and {} are both set",
+                        field_path(&field),
+                        field_path(&key)
+                    )));
+                }
+                let contents = fs::read_to_string(&file).map_err(|e| {
+                    ParseError::Secret(format!("{}: failed to read {file}: {e}", field_path(&key)))
+                })?;
+                map.remove(&key);
+                map.insert(
+                    Value::String(field),
+                    Value::String(contents.trim_end_matches(['\n', '\r']).to_string()),
+                );
+            }
+        }
+        Value::Tagged(tagged) => resolve_secrets(&mut tagged.value, path)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replaces each `${NAME}` in `s` by the environment variable `NAME`, returning the name of the
+/// first one not set. `${...}` not naming a variable, e.g. a shell command, is kept.
+fn interpolate(s: &str) -> Result<String, String> {
+    let mut output = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find("${") {
+        if rest[..i].ends_with('$') {
+            output.push_str(&rest[..i - 1]);
+            output.push_str("${");
+            rest = &rest[i + 2..];
+            continue;
+        }
+        output.push_str(&rest[..i]);
+        let after = &rest[i + 2..];
+        let name = after.find('}').map(|end| &after[..end]);
+        match name {
+            Some(name) if is_variable_name(name) => {
+                output.push_str(&env::var(name).map_err(|_| name.to_string())?);
+                rest = &after[name.len() + 1..];
+            }
+            _ => {
+                output.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn is_variable_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Log output of the server and daemon. `RUST_LOG`, when set, overrides the levels.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Level of the modules not listed in `modules`.
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Level by module, e.g. `sat_o_mat::task::runner: debug`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub modules: BTreeMap<String, String>,
+    /// Whether the log lines of each run are also written to `run.log` in its artifacts.
+    #[serde(default = "default_run_logs")]
+    pub run_logs: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_run_logs() -> bool {
+    true
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: default_log_level(),
+            modules: BTreeMap::new(),
+            run_logs: default_run_logs(),
+        }
+    }
+}
+
+/// The web server of `sat-o-mat server` and `sat-o-mat daemon`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct WebConfig {
+    /// Serve HTTPS with this certificate and key, instead of plain HTTP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM file of the certificate chain, the server's certificate first.
+    pub cert_path: PathBuf,
+    /// PEM file of the private key (PKCS#8, PKCS#1 or SEC1).
+    pub key_path: PathBuf,
+}
+
+/// Background work done by `sat-o-mat daemon` besides serving the stations. `sat-o-mat server`
+/// serves the stations alone, without any of it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DaemonConfig {
+    /// Groups of TLEs downloaded from `predict.tle_source` to `tle_path`, and to the `tle_path`
+    /// of each hosted station that has its own, e.g. `amateur`. The stations reload their TLEs
+    /// after each refresh.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tle_groups: Vec<String>,
+    /// How often (hours) the TLE groups, and those of `predict.space_track`, are downloaded.
+    #[serde(default = "default_tle_refresh_hours")]
+    pub tle_refresh_hours: u64,
+    /// Limits on the artifacts of the runs, enforced every few minutes.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Programs kept running alongside the stations, e.g. `rotctld` and `rigctld`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<ServiceConfig>,
+}
+
+fn default_tle_refresh_hours() -> u64 {
+    12
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            tle_groups: Vec::new(),
+            tle_refresh_hours: default_tle_refresh_hours(),
+            retention: Default::default(),
+            services: Vec::new(),
+        }
+    }
+}
+
+/// Limits on the disk space used by run artifacts. The artifacts of Active tasks are never
+/// deleted. Unset limits are not enforced.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RetentionConfig {
+    /// Runs older than this (days) are deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u64>,
+    /// While the artifacts of all runs take more than this (MiB), the oldest runs are deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_mb: Option<u64>,
+    /// The largest artifacts of a run taking more than this (MiB) are deleted, keeping its
+    /// execution log. Also used as the expected size of upcoming runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_run_mb: Option<u64>,
+    /// While the disk has less free space than this (MiB), the oldest runs are deleted. A warning
+    /// is given when the upcoming runs may leave less than this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_free_mb: Option<u64>,
+    /// Completed, Failed and Rejected tasks last changed longer ago than this (days) are deleted,
+    /// with the artifacts of their runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_history_days: Option<u64>,
+}
+
+/// Remote storage the artifacts of the runs are uploaded to, under `<station name>/<run ID>/`,
+/// once they complete, are aborted or fail.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UploadConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub target: UploadTarget,
+    /// How many times the upload is attempted before giving up.
+    #[serde(default = "default_upload_attempts")]
+    pub attempts: u32,
+    /// How long (seconds) to wait before attempting a failed upload again.
+    #[serde(default = "default_upload_retry_delay_secs")]
+    pub retry_delay_secs: u64,
+}
+
+fn default_upload_attempts() -> u32 {
+    3
+}
+
+fn default_upload_retry_delay_secs() -> u64 {
+    60
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum UploadTarget {
+    /// Copied with `rsync` to `destination`, e.g. `user@host:/srv/products`.
+    Rsync { destination: String },
+    /// Copied with `aws s3 cp` to `url`, e.g. `s3://products/stations`. The credentials are those
+    /// of the AWS CLI, e.g. from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+    S3 {
+        url: String,
+        /// Endpoint of S3-compatible storage other than AWS, e.g. MinIO.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        endpoint_url: Option<String>,
+    },
+    /// Each artifact sent with an HTTP PUT by `curl` below `url`. The credentials are passed to
+    /// `curl` on stdin.
+    Webdav {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        username: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+    },
+}
+
+/// A program supervised by the daemon: restarted when it exits, and stopped on shutdown once the
+/// runs in progress have finished.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ServiceConfig {
+    pub name: String,
+    /// Command line, run with `sh -c`.
+    pub command: String,
+    /// How long (seconds) to wait before restarting the program when it exits, at least one.
+    /// Doubled, up to five minutes, while the program keeps exiting within a minute.
+    #[serde(default = "default_restart_delay_secs")]
+    pub restart_delay_secs: u64,
+}
+
+fn default_restart_delay_secs() -> u64 {
+    5
+}
+
 impl Default for Config {
     fn default() -> Self {
         let dirs = BaseDirs::new().unwrap();
@@ -124,6 +1144,8 @@ impl Default for Config {
             station_name: "Sat-o-Mat Test Station".to_string(),
             api: ApiConfig {
                 keys: vec![ApiKey {
+                    name: Some("admin".to_string()),
+                    id: None,
                     key: "sk_test_admin".to_string(),
                     permissions: vec![
                         Permission::ViewTasks,
@@ -131,12 +1153,19 @@ impl Default for Config {
                         Permission::EditTask,
                         Permission::DeleteTask,
                         Permission::AutoApproveTask,
+                        Permission::ApproveTask,
                         Permission::SubmitFromTemplate,
+                        Permission::ManageKeys,
+                        Permission::ViewAuditLog,
+                        Permission::ControlHardware,
+                        Permission::ReloadSatellites,
                     ],
                 }],
+                ..Default::default()
             },
             tasks_path: base.join("tasks"),
             tle_path: base.join("tle"),
+            templates_path: None,
             ground_station: Some(GroundStation::new(
                 "GS",
                 GroundLocation::try_new(
@@ -146,6 +1175,201 @@ impl Default for Config {
                 .unwrap(),
                 ElevationMask::with_fixed_elevation(0.0),
             )),
+            stations: HashMap::new(),
+            predict: PredictConfig::default(),
+            auto_schedule: AutoScheduleConfig::default(),
+            scheduling: Default::default(),
+            notifications: NotificationConfig::default(),
+            resources: Vec::new(),
+            hosted_stations: BTreeMap::new(),
+            daemon: DaemonConfig::default(),
+            uploads: Default::default(),
+            logging: LoggingConfig::default(),
+            web: WebConfig::default(),
+        }
+    }
+}
+
+/// A station named `test` keeping its tasks in `dir` and its TLEs in `dir/tle`, without a ground
+/// station, accepting only `keys`.
+#[cfg(test)]
+impl Config {
+    pub fn for_test(dir: &std::path::Path, keys: Vec<ApiKey>) -> Self {
+        Self {
+            station_name: "test".into(),
+            api: ApiConfig {
+                keys,
+                ..Default::default()
+            },
+            tasks_path: dir.to_path_buf(),
+            tle_path: dir.join("tle"),
+            ground_station: None,
+            ..Default::default()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(azimuth: f64, elevation: f64) -> HorizonPoint {
+        HorizonPoint { azimuth, elevation }
+    }
+
+    #[test]
+    fn horizon_mask_without_profile_is_fixed() {
+        let mask = horizon_mask(5.0, &[]).unwrap();
+        assert_eq!(
+            mask,
+            ElevationMask::with_fixed_elevation(5.0_f64.to_radians())
+        );
+    }
+
+    #[test]
+    fn horizon_mask_interpolates_and_wraps() {
+        // Building to the west, clear elsewhere
+        let horizon = [point(0.0, 0.0), point(90.0, 0.0), point(270.0, 20.0)];
+        let mask = horizon_mask(5.0, &horizon).unwrap();
+
+        let at = |az: f64| mask.min_elevation(az.to_radians()).to_degrees();
+        assert!((at(0.0) - 5.0).abs() < 1e-9);
+        assert!((at(-90.0) - 20.0).abs() < 1e-9);
+        // Halfway between 90° and 270° through 180°
+        assert!((at(180.0) - 12.5).abs() < 1e-9);
+        assert!((at(-180.0) - 12.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stations_are_named() {
+        let yaml = "
+remote:
+  longitude: -3.7
+  latitude: 40.4
+  altitude: 650.0
+  min_elevation: 10.0
+";
+        let stations = deserialize_stations(serde_yaml::Deserializer::from_str(yaml)).unwrap();
+        let remote = &stations["remote"];
+        assert_eq!(remote.id().as_str(), "remote");
+        assert!((remote.location().coordinates().lat().to_degrees() - 40.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hosted_station_overrides_main_station() {
+        let yaml = "
+south:
+  tasks_path: /srv/south/tasks
+  ground_station:
+    longitude: 13.4
+    latitude: 52.50
+    altitude: 90.0
+    min_elevation: 5.0
+";
+        let config = Config {
+            hosted_stations: serde_yaml::from_str(yaml).unwrap(),
+            ..Default::default()
+        };
+
+        let south = config.hosted_station("south").unwrap();
+        assert_eq!(south.station_name, "south");
+        assert_eq!(south.tasks_path, PathBuf::from("/srv/south/tasks"));
+        assert_eq!(south.tle_path, config.tle_path);
+        assert!(south.hosted_stations.is_empty());
+        assert!(config.hosted_station("north").is_none());
+    }
+
+    #[test]
+    fn secrets_are_read_from_the_environment_and_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let secret = tmp.path().join("webhook");
+        fs::write(&secret, "hunter2\n").unwrap();
+        let yaml = format!(
+            "
+station_name: test
+api:
+  keys:
+    - key_file: {}
+      permissions: []
+tasks_path: ${{CARGO_MANIFEST_DIR}}/tasks
+tle_path: /tmp/tle
+auto_schedule:
+  rules:
+    - name: all
+      template: pass
+      variables:
+        start_unix: \"${{date +%s}}\"
+        home: \"${{HOME}}\"
+notifications:
+  webhooks:
+    - url: http://localhost/$${{not_a_variable}}
+",
+            secret.display()
+        );
+        let config = parse(&yaml).unwrap();
+        assert_eq!(config.api.keys[0].key, "hunter2");
+        assert_eq!(
+            config.tasks_path,
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tasks")
+        );
+        let variables = &config.auto_schedule.rules[0].variables;
+        assert_eq!(variables["start_unix"], "${date +%s}");
+        assert_eq!(variables["home"], "${HOME}");
+        assert_eq!(
+            config.notifications.webhooks[0].url,
+            "http://localhost/${not_a_variable}"
+        );
+
+        let error = parse("station_name: ${SAT_O_MAT_UNSET_VARIABLE}\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "station_name: environment variable SAT_O_MAT_UNSET_VARIABLE is not set"
+        );
+    }
+
+    #[test]
+    fn uploads_are_parsed_by_kind() {
+        let yaml = "
+- name: central
+  kind: s3
+  url: s3://products/stations
+  attempts: 5
+- name: backup
+  kind: rsync
+  destination: backup@central:/srv/products
+";
+        let uploads: Vec<UploadConfig> = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            uploads[0].target,
+            UploadTarget::S3 {
+                url: "s3://products/stations".into(),
+                endpoint_url: None,
+            }
+        );
+        assert_eq!(uploads[0].attempts, 5);
+        assert_eq!(uploads[1].retry_delay_secs, 60);
+    }
+
+    #[test]
+    fn ground_station_with_horizon_round_trips() {
+        let yaml = "
+longitude: 13.4
+latitude: 52.52
+altitude: 100.0
+min_elevation: 5.0
+horizon:
+  - { azimuth: 0.0, elevation: 0.0 }
+  - { azimuth: 270.0, elevation: 20.0 }
+";
+        let de = |yaml: &str| {
+            deserialize_ground_station(serde_yaml::Deserializer::from_str(yaml))
+                .unwrap()
+                .unwrap()
+        };
+        let gs = de(yaml);
+        let serialized =
+            serialize_ground_station(&Some(gs.clone()), serde_yaml::value::Serializer).unwrap();
+        let gs2 = de(&serde_yaml::to_string(&serialized).unwrap());
+        assert_eq!(gs.mask(), gs2.mask());
+    }
+}
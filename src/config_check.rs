@@ -0,0 +1,431 @@
+//! Checks a configuration file without starting the station, so that mistakes do not only surface
+//! when the server starts or in the middle of a pass.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::{env, fs};
+
+use anyhow::bail;
+use sat_o_mat::predict::PredictDb;
+
+use crate::api::auth;
+use crate::config::{self, Config, ParseError, ResourceConfig, SmtpTls};
+use crate::http;
+use crate::tls;
+use crate::validate::Problem;
+
+/// Key of the configuration created by default, which must not be used on a reachable station.
+const DEFAULT_KEY: &str = "sk_test_admin";
+
+#[derive(Debug, Default)]
+pub struct Findings {
+    pub errors: Vec<Problem>,
+    pub warnings: Vec<Problem>,
+}
+
+impl Findings {
+    fn error(&mut self, message: impl ToString) {
+        self.errors.push(Problem::new(message));
+    }
+
+    fn warning(&mut self, message: impl ToString) {
+        self.warnings.push(Problem::new(message));
+    }
+}
+
+/// Checks the configuration file at `path`, printing the problems found. Fails if there are
+/// errors.
+pub fn run(path: &Path) -> anyhow::Result<()> {
+    let findings = check(path);
+    let file = path.display();
+    for (severity, problems) in [("error", &findings.errors), ("warning", &findings.warnings)] {
+        for problem in problems {
+            let location = match (problem.line, problem.column) {
+                (Some(line), Some(column)) => format!(":{line}:{column}"),
+                _ => String::new(),
+            };
+            println!("{file}{location}: {severity}: {}", problem.message);
+        }
+    }
+
+    if !findings.errors.is_empty() {
+        bail!(
+            "{} errors, {} warnings",
+            findings.errors.len(),
+            findings.warnings.len()
+        );
+    }
+    println!("{file}: ok, {} warnings", findings.warnings.len());
+    Ok(())
+}
+
+/// Parses the configuration file at `path` and checks the stations, API keys, paths and
+/// notification targets it defines. Nothing is created or modified.
+pub fn check(path: &Path) -> Findings {
+    let mut findings = Findings::default();
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            findings.error(format!("failed to read the configuration: {e}"));
+            return findings;
+        }
+    };
+    let config = match config::parse(&content) {
+        Ok(config) => config,
+        Err(ParseError::Secret(e)) => {
+            findings.error(e);
+            return findings;
+        }
+        Err(ParseError::Yaml(e)) => {
+            let location = e.location();
+            findings.errors.push(Problem {
+                message: e.to_string(),
+                line: location.as_ref().map(|l| l.line()),
+                column: location.as_ref().map(|l| l.column()),
+            });
+            return findings;
+        }
+    };
+
+    check_station(&mut findings, "", &config);
+    for name in config.hosted_stations.keys() {
+        if let Some(station) = config.hosted_station(name) {
+            check_station(&mut findings, &format!("hosted_stations.{name}."), &station);
+        }
+    }
+    check_api(&mut findings, &config);
+    check_notifications(&mut findings, &config);
+    if let Some(tls) = &config.web.tls
+        && let Err(e) = tls::acceptor(tls)
+    {
+        findings.error(format!("web.tls: {e:#}"));
+    }
+    findings
+}
+
+/// Checks the settings of a station, prefixing the names of its fields with `prefix`.
+fn check_station(findings: &mut Findings, prefix: &str, config: &Config) {
+    if config.ground_station.is_none() {
+        findings.warning(format!(
+            "{prefix}ground_station is not set, passes cannot be predicted"
+        ));
+    }
+
+    let tle_path = &config.tle_path;
+    if !tle_path.exists() {
+        findings.error(format!(
+            "{prefix}tle_path {} does not exist",
+            tle_path.display()
+        ));
+    } else if !tle_path.is_dir() {
+        findings.error(format!(
+            "{prefix}tle_path {} is not a directory",
+            tle_path.display()
+        ));
+    } else if PredictDb::new().add_tles(tle_path).unwrap_or(0) == 0 {
+        findings.warning(format!(
+            "{prefix}tle_path {} contains no orbital elements",
+            tle_path.display()
+        ));
+    }
+
+    let tasks_path = &config.tasks_path;
+    match fs::metadata(tasks_path) {
+        Err(_) => findings.warning(format!(
+            "{prefix}tasks_path {} does not exist, it is created on startup",
+            tasks_path.display()
+        )),
+        Ok(metadata) if !metadata.is_dir() => findings.error(format!(
+            "{prefix}tasks_path {} is not a directory",
+            tasks_path.display()
+        )),
+        Ok(metadata) if metadata.permissions().readonly() => findings.error(format!(
+            "{prefix}tasks_path {} is not writable",
+            tasks_path.display()
+        )),
+        Ok(_) => {}
+    }
+
+    check_resources(findings, prefix, &config.resources);
+
+    for rule in &config.auto_schedule.rules {
+        let template = config
+            .templates_path()
+            .join(crate::task::format::Task::filename(&rule.template));
+        if !template.exists() {
+            findings.warning(format!(
+                "{prefix}auto_schedule rule {}: template {} not found at {}",
+                rule.name,
+                rule.template,
+                template.display()
+            ));
+        }
+    }
+}
+
+fn check_resources(findings: &mut Findings, prefix: &str, resources: &[ResourceConfig]) {
+    let mut names = HashSet::new();
+    for resource in resources {
+        let name = &resource.name;
+        if !names.insert(name) {
+            findings.error(format!(
+                "{prefix}resources: {name} is defined more than once"
+            ));
+        }
+        if resource.commands.is_empty() {
+            findings.error(format!(
+                "{prefix}resources: {name} lists no commands, no step can use it"
+            ));
+        }
+        for command in &resource.commands {
+            if !is_installed(command) {
+                findings.warning(format!(
+                    "{prefix}resources: {name} uses {command}, which is not found in PATH"
+                ));
+            }
+        }
+    }
+}
+
+fn check_api(findings: &mut Findings, config: &Config) {
+    let api = &config.api;
+    if api.keys.is_empty() && api.jwt.is_none() {
+        findings.warning("api.keys is empty, no one can submit tasks");
+    }
+    for (i, key) in api.keys.iter().enumerate() {
+        let name = key.name.clone().unwrap_or_else(|| format!("config-{i}"));
+        if key.key.is_empty() {
+            findings.error(format!("api.keys: key {name} is empty"));
+        } else if key.key == DEFAULT_KEY {
+            findings.error(format!(
+                "api.keys: key {name} is the default test key, generate one with `sat-o-mat hash-key`"
+            ));
+        } else if !auth::is_hashed(&key.key) {
+            findings.warning(format!(
+                "api.keys: key {name} is stored in plaintext, store its hash from `sat-o-mat hash-key KEY` instead"
+            ));
+        }
+        if key.permissions.is_empty() {
+            findings.warning(format!("api.keys: key {name} has no permissions"));
+        }
+    }
+
+    if let Some(jwt) = &api.jwt
+        && !jwt.jwks_path.is_file()
+    {
+        findings.error(format!(
+            "api.jwt.jwks_path {} does not exist",
+            jwt.jwks_path.display()
+        ));
+    }
+    for (field, path) in [
+        ("keys_path", &api.keys_path),
+        ("audit_path", &api.audit_path),
+    ] {
+        if let Some(parent) = path.as_ref().and_then(|p| p.parent())
+            && !parent.as_os_str().is_empty()
+            && !parent.is_dir()
+        {
+            findings.error(format!(
+                "api.{field}: directory {} does not exist",
+                parent.display()
+            ));
+        }
+    }
+}
+
+fn check_notifications(findings: &mut Findings, config: &Config) {
+    let notifications = &config.notifications;
+    for webhook in &notifications.webhooks {
+        if !http::is_supported(&webhook.url) {
+            findings.error(format!(
+                "notifications.webhooks: unsupported URL {}, expected http(s)://host[:port]/path",
+                webhook.url
+            ));
+        }
+    }
+    if let Some(matrix) = notifications
+        .approvals
+        .as_ref()
+        .and_then(|a| a.matrix.as_ref())
+        && !http::is_supported(&matrix.homeserver)
+    {
+        findings.error(format!(
+            "notifications.approvals.matrix: unsupported homeserver URL {}, expected http(s)://host[:port]",
+            matrix.homeserver
+        ));
+    }
+    if let Some(email) = notifications
+        .approvals
+        .as_ref()
+        .and_then(|a| a.email.as_ref())
+        && email.username.is_some()
+        && email.tls == SmtpTls::None
+    {
+        findings
+            .error("notifications.approvals.email: credentials are only sent over TLS, set `tls`");
+    }
+}
+
+/// Whether `program` is a path to a file, or found in one of the directories in `PATH`.
+fn is_installed(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(problems: &[Problem]) -> Vec<&str> {
+        problems.iter().map(|p| p.message.as_str()).collect()
+    }
+
+    /// A configuration accepting `keys`, with the given paths, followed by `rest`.
+    fn config_yaml(keys: &str, tasks_path: &str, tle_path: &str, rest: &str) -> String {
+        format!(
+            "station_name: test\napi:\n  keys:{keys}\ntasks_path: {tasks_path}\ntle_path: {tle_path}\n{rest}"
+        )
+    }
+
+    #[test]
+    fn valid_configuration_has_no_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join("tle")).unwrap();
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/tle/nanoff_a.txt"),
+            tmp.path().join("tle/nanoff_a.txt"),
+        )
+        .unwrap();
+        let path = tmp.path().join("config.yml");
+        let dir = tmp.path().display();
+        fs::write(
+            &path,
+            config_yaml(
+                "\n    - key: plaintext\n      permissions: [ViewTasks]",
+                &format!("{dir}/tasks"),
+                &format!("{dir}/tle"),
+                "\
+ground_station:
+  longitude: 13.4
+  latitude: 52.5
+  altitude: 100
+  min_elevation: 0
+resources:
+  - name: shell
+    commands: [sh]
+",
+            ),
+        )
+        .unwrap();
+
+        let findings = check(&path);
+        assert!(findings.errors.is_empty(), "{:?}", findings.errors);
+        assert_eq!(
+            messages(&findings.warnings),
+            [
+                format!(
+                    "tasks_path {}/tasks does not exist, it is created on startup",
+                    tmp.path().display()
+                ),
+                "api.keys: key config-0 is stored in plaintext, store its hash from `sat-o-mat hash-key KEY` instead".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.yml");
+        fs::write(
+            &path,
+            config_yaml(
+                "\n    - key: sk_test_admin\n      permissions: []",
+                "/nonexistent/tasks",
+                "/nonexistent/tle",
+                "\
+resources:
+  - name: rotator
+    commands: [no-such-rotctl]
+  - name: rotator
+    commands: []
+notifications:
+  webhooks:
+    - url: ftp://example.org/hook
+  approvals:
+    dashboard_url: http://station.local/
+    email:
+      smtp_host: smtp.example.org
+      username: station
+      password: hunter2
+      from: station@example.org
+      to: [ops@example.org]
+",
+            ),
+        )
+        .unwrap();
+
+        let findings = check(&path);
+        assert_eq!(
+            messages(&findings.errors),
+            [
+                "tle_path /nonexistent/tle does not exist",
+                "resources: rotator is defined more than once",
+                "resources: rotator lists no commands, no step can use it",
+                "api.keys: key config-0 is the default test key, generate one with `sat-o-mat hash-key`",
+                "notifications.webhooks: unsupported URL ftp://example.org/hook, expected http(s)://host[:port]/path",
+                "notifications.approvals.email: credentials are only sent over TLS, set `tls`",
+            ]
+        );
+        assert!(
+            messages(&findings.warnings)
+                .contains(&"resources: rotator uses no-such-rotctl, which is not found in PATH")
+        );
+
+        fs::write(
+            &path,
+            config_yaml(
+                " []",
+                "/tmp",
+                "/tmp",
+                "\
+ground_station:
+  longitude: 13.4
+  latitude: 95
+  altitude: 100
+  min_elevation: 0
+",
+            ),
+        )
+        .unwrap();
+        let findings = check(&path);
+        assert_eq!(
+            messages(&findings.errors),
+            ["ground_station: latitude must between -90 deg and 90 deg but was 95 deg"]
+        );
+
+        fs::write(
+            &path,
+            config_yaml(
+                " []",
+                "/tmp",
+                "/tmp",
+                "predict:\n  steps:\n    fine_step: 0\n",
+            ),
+        )
+        .unwrap();
+        let findings = check(&path);
+        assert_eq!(findings.errors.len(), 1);
+        assert!(
+            findings.errors[0]
+                .message
+                .starts_with("predict.steps: steps must be positive seconds, got 0"),
+            "{}",
+            findings.errors[0].message
+        );
+        assert_eq!(findings.errors[0].line, Some(8));
+    }
+}
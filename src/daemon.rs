@@ -0,0 +1,310 @@
+//! Runs everything a station needs as one unit: the server and schedulers of the stations, the TLE
+//! refresher, the artifact retention and the supervised services, all stopped together on SIGINT
+//! or SIGTERM.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::Utc;
+use tokio::process::Command;
+use tokio::spawn;
+use tokio::sync::watch;
+use tokio::time::{sleep, timeout};
+use tracing::{info, warn};
+
+use crate::api::{self, AppState};
+use crate::config::{Config, RetentionConfig, ServiceConfig};
+use crate::doctor::free_space;
+use crate::retention;
+use crate::server;
+use crate::tle;
+
+/// How often the artifact retention is enforced.
+const RETENTION_PERIOD: Duration = Duration::from_secs(600);
+
+/// How far ahead the runs are checked to fit on the disk.
+const UPCOMING_WINDOW: Duration = Duration::from_secs(3600);
+
+/// How long a service has to exit after SIGTERM before it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Least and most time waited before restarting a service.
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(300);
+
+/// How long a service has to run for its restart delay to go back to the configured one.
+const HEALTHY_RUN: Duration = Duration::from_secs(60);
+
+pub async fn run(config: Config, host: String, port: u32) -> anyhow::Result<()> {
+    let daemon = config.daemon.clone();
+    let mut tle_groups = daemon.tle_groups.clone();
+    if let Some(space_track) = &config.predict.space_track {
+        let groups = space_track.groups.keys();
+        tle_groups.extend(groups.filter(|g| !daemon.tle_groups.contains(g)).cloned());
+    }
+    server::serve(config, host, port, move |states, shutdown| {
+        let mut tasks = Vec::new();
+        for service in daemon.services {
+            tasks.push(spawn(supervise(service, shutdown.clone())));
+        }
+        if !tle_groups.is_empty() {
+            let period = Duration::from_secs(daemon.tle_refresh_hours.max(1) * 3600);
+            tasks.push(spawn(refresh_tles(
+                tle_groups,
+                period,
+                states.clone(),
+                shutdown.clone(),
+            )));
+        }
+        let tasks_paths = states.iter().map(|s| s.tasks_path.clone()).collect();
+        tasks.push(spawn(retain_artifacts(
+            tasks_paths,
+            daemon.retention,
+            shutdown,
+        )));
+        tasks
+    })
+    .await
+}
+
+/// Runs `service` until `shutdown` becomes true, restarting it whenever it exits. Services
+/// exiting soon after starting are restarted less and less often, see [`restart_delay`].
+async fn supervise(service: ServiceConfig, mut shutdown: watch::Receiver<bool>) {
+    let name = &service.name;
+    let configured = Duration::from_secs(service.restart_delay_secs);
+    let mut delay = None;
+    loop {
+        info!(%name, command = %service.command, "starting service");
+        let started = Instant::now();
+        // exec, so that the service itself receives SIGTERM rather than the shell. In a process
+        // group of its own, so that its children are stopped with it
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(format!("exec {}", service.command))
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                let stopping = tokio::select! {
+                    status = child.wait() => {
+                        warn!(%name, ?status, "service exited");
+                        false
+                    }
+                    _ = shutdown.wait_for(|&shutdown| shutdown) => true,
+                };
+                if stopping {
+                    info!(%name, "stopping service");
+                    stop(&mut child).await;
+                    return;
+                }
+            }
+            Err(e) => warn!(%name, ?e, "failed to start service"),
+        }
+        let wait = restart_delay(delay, configured, started.elapsed());
+        delay = Some(wait);
+        info!(%name, delay = ?wait, "restarting service");
+        tokio::select! {
+            _ = sleep(wait) => {}
+            _ = shutdown.wait_for(|&shutdown| shutdown) => return,
+        }
+    }
+}
+
+/// Time to wait before restarting a service that ran for `ran`: the `configured` delay, at least
+/// [`MIN_RESTART_DELAY`], doubling the `previous` one while the service keeps exiting within
+/// [`HEALTHY_RUN`], up to [`MAX_RESTART_DELAY`].
+fn restart_delay(previous: Option<Duration>, configured: Duration, ran: Duration) -> Duration {
+    let base = configured.max(MIN_RESTART_DELAY);
+    match previous {
+        Some(previous) if ran < HEALTHY_RUN => {
+            (previous * 2).clamp(base, MAX_RESTART_DELAY.max(base))
+        }
+        _ => base,
+    }
+}
+
+/// Stops the process group of `child`: SIGTERM first, and SIGKILL if it has not exited within
+/// [`STOP_TIMEOUT`].
+async fn stop(child: &mut tokio::process::Child) {
+    let Some(pid) = child.id() else {
+        // Already exited
+        return;
+    };
+    let group = -(pid as libc::pid_t);
+    // SAFETY: kill has no memory safety requirements. The child has not been reaped, so its
+    // process group still exists.
+    unsafe { libc::kill(group, libc::SIGTERM) };
+    if timeout(STOP_TIMEOUT, child.wait()).await.is_err() {
+        warn!(pid, timeout = ?STOP_TIMEOUT, "service did not stop, killing it");
+        // SAFETY: as above
+        unsafe { libc::kill(group, libc::SIGKILL) };
+        let _ = child.kill().await;
+    }
+}
+
+/// Downloads `groups` from their sources to the `tle_path` of every station every `period`,
+/// reloading the TLEs of the stations whose groups were updated.
+async fn refresh_tles(
+    groups: Vec<String>,
+    period: Duration,
+    states: Vec<AppState>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let config = states[0].config.clone();
+    let mut tle_paths: Vec<PathBuf> = Vec::new();
+    for state in &states {
+        if !tle_paths.contains(&state.config.tle_path) {
+            tle_paths.push(state.config.tle_path.clone());
+        }
+    }
+    let mut interval = tokio::time::interval(period);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait_for(|&shutdown| shutdown) => return,
+        }
+        let mut updated = Vec::new();
+        for group in &groups {
+            // Do not download again on every restart, sources like CelesTrak block that
+            let file = format!("{group}.txt");
+            let stale: Vec<&PathBuf> = tle_paths
+                .iter()
+                .filter(|path| !modified_within(&path.join(&file), period))
+                .collect();
+            let Some((first, others)) = stale.split_first() else {
+                continue;
+            };
+            let source = tle::Source::of(group, &config.predict);
+            if let Err(e) = tle::fetch(group, &source, first).await {
+                warn!(%group, "failed to refresh TLEs: {e:#}");
+                continue;
+            }
+            updated.push(*first);
+            // Downloaded once for all the stations
+            for path in others {
+                match copy_tles(&first.join(&file), path, &file) {
+                    Ok(()) => updated.push(*path),
+                    Err(e) => warn!(%group, ?path, ?e, "failed to copy TLEs"),
+                }
+            }
+        }
+        for state in &states {
+            if updated.contains(&&state.config.tle_path) {
+                api::reload_satellites(state).await;
+            }
+        }
+    }
+}
+
+/// Copies the TLEs at `from` to `<dir>/<file>` at once, like [`tle::fetch`].
+fn copy_tles(from: &Path, dir: &Path, file: &str) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let partial = dir.join(format!(".{file}.partial"));
+    fs::copy(from, &partial)?;
+    fs::rename(&partial, dir.join(file))
+}
+
+fn modified_within(path: &Path, period: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < period)
+}
+
+/// Enforces `retention` on the artifacts of each of `tasks_paths`, and warns when the runs
+/// starting soon may not fit on the disk.
+async fn retain_artifacts(
+    tasks_paths: Vec<PathBuf>,
+    retention: RetentionConfig,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(RETENTION_PERIOD);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait_for(|&shutdown| shutdown) => return,
+        }
+        for tasks_path in &tasks_paths {
+            let free = free_space(tasks_path);
+            match retention::prune(tasks_path, &retention, free, SystemTime::now()) {
+                Ok(pruned) if pruned.freed > 0 || !pruned.tasks.is_empty() => info!(
+                    ?tasks_path,
+                    tasks = ?pruned.tasks,
+                    runs = ?pruned.runs,
+                    files = ?pruned.files,
+                    freed_mb = pruned.freed >> 20,
+                    "deleted artifacts"
+                ),
+                Ok(_) => {}
+                Err(e) => warn!(?e, ?tasks_path, "failed to delete artifacts"),
+            }
+
+            let Some(free) = free_space(tasks_path) else {
+                continue;
+            };
+            let window = chrono::Duration::from_std(UPCOMING_WINDOW).unwrap();
+            match retention::check_upcoming(tasks_path, &retention, free, Utc::now(), window) {
+                Ok(tasks) if !tasks.is_empty() => warn!(
+                    ?tasks_path,
+                    ?tasks,
+                    free_mb = free >> 20,
+                    "the recordings of upcoming runs may fill the disk"
+                ),
+                Ok(_) => {}
+                Err(e) => warn!(?e, ?tasks_path, "failed to check upcoming runs"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn services_are_restarted_until_shutdown() {
+        let tmp = tempfile::tempdir().unwrap();
+        let starts = tmp.path().join("starts");
+        let service = ServiceConfig {
+            name: "test".into(),
+            command: format!("echo started >> {}", starts.display()),
+            restart_delay_secs: 0,
+        };
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let supervisor = spawn(supervise(service, shutdown_rx));
+
+        let restarted = async {
+            while fs::read_to_string(&starts).map_or(0, |s| s.lines().count()) < 2 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        };
+        timeout(Duration::from_secs(10), restarted).await.unwrap();
+        shutdown_tx.send(true).unwrap();
+        timeout(Duration::from_secs(10), supervisor)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test]
+    fn restarts_back_off_while_services_keep_exiting() {
+        let secs = Duration::from_secs;
+        let quick = secs(1);
+        assert_eq!(restart_delay(None, secs(0), quick), MIN_RESTART_DELAY);
+        assert_eq!(restart_delay(None, secs(5), quick), secs(5));
+        assert_eq!(restart_delay(Some(secs(5)), secs(5), quick), secs(10));
+        assert_eq!(
+            restart_delay(Some(secs(200)), secs(5), quick),
+            MAX_RESTART_DELAY
+        );
+        assert_eq!(
+            restart_delay(Some(secs(200)), secs(5), HEALTHY_RUN),
+            secs(5)
+        );
+        // Never shorter than configured
+        assert_eq!(restart_delay(Some(secs(600)), secs(900), quick), secs(900));
+    }
+}
@@ -0,0 +1,369 @@
+//! Checks of the station hardware and environment, the first thing to run when something does not
+//! work.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::bail;
+use chrono::Utc;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::config::{Config, ResourceConfig};
+use crate::tracker::rotctl::RotctlClient;
+
+/// How long to wait for a hardware server to answer.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Free space below which runs are likely to fail, in bytes.
+const MIN_FREE_SPACE: u64 = 1 << 30;
+
+/// Free space below which a warning is given, in bytes.
+const LOW_FREE_SPACE: u64 = 5 << 30;
+
+/// USB vendor and product IDs of common SDRs.
+const SDR_DEVICES: &[(&str, &str, &str)] = &[
+    ("0bda", "2832", "RTL-SDR"),
+    ("0bda", "2838", "RTL-SDR"),
+    ("1d50", "6089", "HackRF One"),
+    ("1d50", "60a1", "Airspy"),
+    ("03eb", "800c", "Airspy HF+"),
+    ("1d50", "6108", "LimeSDR"),
+    ("0403", "601f", "LimeSDR Mini"),
+    ("2500", "0020", "USRP B200/B210"),
+    ("2500", "0021", "USRP B200mini"),
+    ("0456", "b673", "ADALM-Pluto"),
+    ("1df7", "2500", "SDRplay RSP1"),
+    ("1df7", "3000", "SDRplay RSP1A"),
+    ("1df7", "3010", "SDRplay RSP2"),
+    ("1df7", "3020", "SDRplay RSPduo"),
+    ("1df7", "3050", "SDRplay RSPdx"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl ToString, status: Status, detail: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        };
+        write!(f, "[{status}] {}: {}", self.name, self.detail)
+    }
+}
+
+/// Runs every check, printing the report. Fails if any check failed.
+pub async fn run(config: &Config) -> anyhow::Result<()> {
+    let mut checks = Vec::new();
+    let mut stations = vec![(String::new(), config.clone())];
+    for name in config.hosted_stations.keys() {
+        if let Some(station) = config.hosted_station(name) {
+            stations.push((format!("{name}: "), station));
+        }
+    }
+    for (prefix, station) in &stations {
+        for resource in &station.resources {
+            if let Some(address) = &resource.address {
+                let mut check = probe_resource(resource, address).await;
+                check.name = format!("{prefix}{}", check.name);
+                checks.push(check);
+            }
+        }
+        let mut tle = check_tles(station);
+        tle.name = format!("{prefix}{}", tle.name);
+        checks.push(tle);
+        let mut disk = check_disk_space(&station.tasks_path);
+        disk.name = format!("{prefix}{}", disk.name);
+        checks.push(disk);
+    }
+    checks.push(check_sdrs(Path::new("/sys/bus/usb/devices")));
+    checks.push(check_clock());
+
+    for check in &checks {
+        println!("{check}");
+    }
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        bail!("{failed} of {} checks failed", checks.len());
+    }
+    Ok(())
+}
+
+/// Connects to the `rotctld` or `rigctld` server controlling `resource`, reading the position of
+/// rotators.
+async fn probe_resource(resource: &ResourceConfig, address: &str) -> Check {
+    let name = format!("resource {}", resource.name);
+    let is_rotator = resource.commands.iter().any(|c| c.starts_with("rotctl"));
+    if is_rotator {
+        let position = timeout(PROBE_TIMEOUT, async {
+            RotctlClient::connect(address).await?.get_position().await
+        })
+        .await;
+        return match position {
+            Ok(Ok((az, el))) => Check::new(
+                name,
+                Status::Pass,
+                format!("{address} reports az {az:.1} el {el:.1}"),
+            ),
+            Ok(Err(e)) => Check::new(name, Status::Fail, format!("{address}: {e:#}")),
+            Err(_) => Check::new(name, Status::Fail, format!("{address} did not answer")),
+        };
+    }
+    match timeout(PROBE_TIMEOUT, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => Check::new(name, Status::Pass, format!("{address} is reachable")),
+        Ok(Err(e)) => Check::new(name, Status::Fail, format!("{address}: {e}")),
+        Err(_) => Check::new(name, Status::Fail, format!("{address} did not answer")),
+    }
+}
+
+/// Checks that TLEs are loaded and younger than `predict.max_element_age_days`.
+fn check_tles(config: &Config) -> Check {
+    let name = "TLEs";
+    let predict_db = crate::api::predict_db(config);
+    let now = Utc::now();
+    let max_age = config.predict.max_element_age_days;
+    let mut stale: Vec<&String> = predict_db
+        .iter()
+        .filter(|(_, sat)| sat.element_age(now).num_seconds() as f64 / 86400.0 > max_age)
+        .map(|(name, _)| name)
+        .collect();
+    let count = predict_db.iter().count();
+    if count == 0 {
+        return Check::new(
+            name,
+            Status::Fail,
+            format!("no satellites loaded from {}", config.tle_path.display()),
+        );
+    }
+    if stale.is_empty() {
+        return Check::new(
+            name,
+            Status::Pass,
+            format!("{count} satellites, none older than {max_age} days"),
+        );
+    }
+    stale.sort();
+    let listed: Vec<&str> = stale.iter().take(5).map(|s| s.as_str()).collect();
+    let more = if stale.len() > listed.len() {
+        ", ..."
+    } else {
+        ""
+    };
+    Check::new(
+        name,
+        Status::Warn,
+        format!(
+            "{} of {count} satellites older than {max_age} days ({}{more}), run `sat-o-mat tle fetch`",
+            stale.len(),
+            listed.join(", ")
+        ),
+    )
+}
+
+/// Checks the free space on the file system of `tasks_path`, where the artifacts are written.
+fn check_disk_space(tasks_path: &Path) -> Check {
+    let name = "disk space";
+    let Some(free) = free_space(tasks_path) else {
+        return Check::new(
+            name,
+            Status::Warn,
+            format!(
+                "could not determine the free space of {}",
+                tasks_path.display()
+            ),
+        );
+    };
+    let detail = format!(
+        "{:.1} GiB free for {}",
+        free as f64 / (1u64 << 30) as f64,
+        tasks_path.display()
+    );
+    let status = if free < MIN_FREE_SPACE {
+        Status::Fail
+    } else if free < LOW_FREE_SPACE {
+        Status::Warn
+    } else {
+        Status::Pass
+    };
+    Check::new(name, status, detail)
+}
+
+/// Free space, in bytes, on the file system of `path`.
+pub fn free_space(path: &Path) -> Option<u64> {
+    // The directory may not be created yet
+    let path = path
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("/"));
+    let output = Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the available space, in bytes, from the output of `df -Pk`.
+fn parse_df(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let available: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available * 1024)
+}
+
+/// Lists the SDRs connected over USB, found in the sysfs directory `devices`.
+fn check_sdrs(devices: &Path) -> Check {
+    let name = "SDR devices";
+    let found = sdr_devices(devices);
+    if found.is_empty() {
+        Check::new(name, Status::Warn, "no known SDR connected over USB")
+    } else {
+        Check::new(name, Status::Pass, found.join(", "))
+    }
+}
+
+fn sdr_devices(devices: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(devices) else {
+        return Vec::new();
+    };
+    let read = |dir: &PathBuf, file| {
+        std::fs::read_to_string(dir.join(file)).map(|s| s.trim().to_lowercase())
+    };
+    let mut found: Vec<String> = entries
+        .filter_map(|entry| {
+            let dir = entry.ok()?.path();
+            let vendor = read(&dir, "idVendor").ok()?;
+            let product = read(&dir, "idProduct").ok()?;
+            let (_, _, model) = SDR_DEVICES
+                .iter()
+                .find(|(v, p, _)| *v == vendor && *p == product)?;
+            let serial = read(&dir, "serial").ok();
+            Some(match serial {
+                Some(serial) => format!("{model} (serial {serial})"),
+                None => model.to_string(),
+            })
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Checks that the system clock is synchronized, which pass predictions depend on.
+fn check_clock() -> Check {
+    let name = "clock";
+    let output = Command::new("timedatectl")
+        .args(["show", "--property=NTPSynchronized", "--value"])
+        .output();
+    match output {
+        Ok(o) if o.status.success() => match String::from_utf8_lossy(&o.stdout).trim() {
+            "yes" => Check::new(name, Status::Pass, "synchronized with NTP"),
+            _ => Check::new(
+                name,
+                Status::Fail,
+                "not synchronized, passes will be tracked at the wrong time",
+            ),
+        },
+        _ => Check::new(
+            name,
+            Status::Warn,
+            "could not determine if the clock is synchronized (timedatectl not available)",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn finds_sdrs_in_sysfs() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (dir, vendor, product, serial) in [
+            ("1-1", "0bda", "2838", Some("00000001")),
+            ("1-2", "1d6b", "0002", None),
+            ("2-1", "1d50", "6089", None),
+        ] {
+            let dir = tmp.path().join(dir);
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join("idVendor"), format!("{vendor}\n")).unwrap();
+            fs::write(dir.join("idProduct"), format!("{product}\n")).unwrap();
+            if let Some(serial) = serial {
+                fs::write(dir.join("serial"), serial).unwrap();
+            }
+        }
+        assert_eq!(
+            sdr_devices(tmp.path()),
+            ["HackRF One", "RTL-SDR (serial 00000001)"]
+        );
+        assert_eq!(check_sdrs(&tmp.path().join("missing")).status, Status::Warn);
+    }
+
+    #[test]
+    fn free_space_is_parsed() {
+        let output = "\
+Filesystem     1024-blocks      Used Available Capacity Mounted on
+/dev/sda1        102400000  51200000  40960000      56% /
+";
+        assert_eq!(parse_df(output), Some(40960000 * 1024));
+        assert_eq!(parse_df("garbage"), None);
+    }
+
+    #[tokio::test]
+    async fn rotators_are_probed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut command = [0; 2];
+            socket.read_exact(&mut command).await.unwrap();
+            socket.write_all(b"180.00\n45.50\n").await.unwrap();
+        });
+        let rotator = ResourceConfig {
+            name: "uhf1".into(),
+            commands: vec!["rotctl".into()],
+            transmit: false,
+            address: Some(address.clone()),
+            limits: None,
+            park: None,
+            sdr: None,
+        };
+
+        let check = probe_resource(&rotator, &address).await;
+        assert_eq!(check.status, Status::Pass);
+        assert_eq!(
+            check.to_string(),
+            format!("[PASS] resource uhf1: {address} reports az 180.0 el 45.5")
+        );
+
+        let check = probe_resource(&rotator, "127.0.0.1:1").await;
+        assert_eq!(check.status, Status::Fail);
+    }
+}
@@ -0,0 +1,118 @@
+//! Dry runs of tasks: the steps the runner would start, when, and the hardware they would use,
+//! without running anything.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::api::review::{check_hardware, used_resources};
+use crate::config::Config;
+use crate::task::format::{Step, Task};
+use crate::task::runner::{self, PlannedStep};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DryRun {
+    pub start: String,
+    pub end: Option<String>,
+    /// Variables of the task. Those evaluated by shell commands (`${...}`) are only resolved when
+    /// the task runs, so they are shown unresolved.
+    pub variables: BTreeMap<String, String>,
+    pub steps: Vec<DryRunStep>,
+    pub cleanup: Vec<DryRunStep>,
+    /// Names of the station resources used by any step
+    pub resources: Vec<String>,
+    /// Whether any step uses a resource that can transmit
+    pub transmits: bool,
+    /// Hardware referred to by name that is not configured
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DryRunStep {
+    /// Command with the task variables substituted
+    pub command: String,
+    /// Time the step would start at. None if the task ends before, so it would not be started.
+    pub time: Option<String>,
+    pub wait: bool,
+    pub resources: Vec<String>,
+    pub transmit: bool,
+}
+
+/// Goes through `task` as the runner would on the station of `config`, without touching the
+/// hardware or spawning any process.
+///
+/// Steps waited for are assumed to finish at once, and the others to run until the end of the
+/// task.
+pub fn dry_run(task: &Task, config: &Config) -> Result<DryRun, runner::Error> {
+    let plan = runner::plan(task)?;
+    let steps = dry_run_steps(plan.steps, &task.steps, config);
+    let cleanup = dry_run_steps(plan.cleanup, &task.cleanup, config);
+    let resources: BTreeSet<&String> = steps
+        .iter()
+        .chain(&cleanup)
+        .flat_map(|s| &s.resources)
+        .collect();
+    Ok(DryRun {
+        start: plan.start.to_rfc3339(),
+        end: plan.end.map(|t| t.to_rfc3339()),
+        variables: task.variables.clone().into_iter().collect(),
+        transmits: steps.iter().chain(&cleanup).any(|s| s.transmit),
+        resources: resources.into_iter().cloned().collect(),
+        errors: check_hardware(task, &config.resources),
+        steps,
+        cleanup,
+    })
+}
+
+fn dry_run_steps(planned: Vec<PlannedStep>, steps: &[Step], config: &Config) -> Vec<DryRunStep> {
+    planned
+        .into_iter()
+        .zip(steps)
+        .map(|(planned, step)| {
+            let used = used_resources(&planned.cmd, &config.resources);
+            DryRunStep {
+                time: planned.time.map(|t| t.to_rfc3339()),
+                wait: step.wait,
+                resources: used.iter().map(|r| r.name.clone()).collect(),
+                transmit: used.iter().any(|r| r.transmit),
+                command: planned.cmd,
+            }
+        })
+        .collect()
+}
+
+/// The dry run as printed by `sat-o-mat run --dry-run`.
+pub fn text(dry_run: &DryRun) -> String {
+    let mut output = String::new();
+    let _ = writeln!(output, "start: {}", dry_run.start);
+    if let Some(end) = &dry_run.end {
+        let _ = writeln!(output, "end: {end}");
+    }
+    let _ = writeln!(output, "variables:");
+    for (name, value) in &dry_run.variables {
+        let _ = writeln!(output, "  {name}: {value}");
+    }
+    for (list, steps) in [("steps", &dry_run.steps), ("cleanup", &dry_run.cleanup)] {
+        if steps.is_empty() {
+            continue;
+        }
+        let _ = writeln!(output, "{list}:");
+        for step in steps {
+            let time = step.time.as_deref().unwrap_or("not started");
+            let _ = write!(output, "  {time} {}", step.command);
+            if !step.resources.is_empty() {
+                let _ = write!(output, " [{}]", step.resources.join(", "));
+            }
+            output.push('\n');
+        }
+    }
+    if dry_run.transmits {
+        let _ = writeln!(output, "transmits");
+    }
+    for error in &dry_run.errors {
+        let _ = writeln!(output, "error: {error}");
+    }
+    output
+}
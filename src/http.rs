@@ -0,0 +1,436 @@
+//! Minimal HTTP/1.1 client, used for notifications, TLE downloads and by the API client.
+//!
+//! Plain `http://` URLs are requested directly. `https://` ones go through `curl`, as the uploads
+//! do, rather than linking a TLS implementation for a few requests.
+
+use std::io;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+/// Time allowed for a whole request, redirects included.
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Redirects followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// Response to a [`request`].
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Sends a request with a JSON `body` to `url`, failing unless the response status is 2xx.
+pub async fn send_json(
+    method: &str,
+    url: &str,
+    headers: &[(&str, String)],
+    body: &str,
+) -> io::Result<()> {
+    send(method, url, headers, ("application/json", body)).await
+}
+
+/// Sends a request with a `body` and its content type to `url`, failing unless the response
+/// status is 2xx.
+pub async fn send(
+    method: &str,
+    url: &str,
+    headers: &[(&str, String)],
+    body: (&str, &str),
+) -> io::Result<()> {
+    let response = request(method, url, headers, Some(body)).await?;
+    if response.is_success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "unexpected response status {}",
+            response.status
+        )))
+    }
+}
+
+/// Sends a request to `url` with an optional body and its content type, returning the response
+/// whatever its status. Redirects are followed, and the request fails if it takes longer than
+/// [`TIMEOUT`].
+pub async fn request(
+    method: &str,
+    url: &str,
+    headers: &[(&str, String)],
+    body: Option<(&str, &str)>,
+) -> io::Result<Response> {
+    if url.starts_with("https://") {
+        return curl(method, url, headers, body).await;
+    }
+    timeout(TIMEOUT, follow_redirects(method, url, headers, body))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "request timed out"))?
+}
+
+/// Sends the request to `url`, then to where the responses redirect to, if anywhere. Requests
+/// redirected to an `https://` URL are passed to `curl`.
+async fn follow_redirects(
+    method: &str,
+    url: &str,
+    headers: &[(&str, String)],
+    body: Option<(&str, &str)>,
+) -> io::Result<Response> {
+    let (mut method, mut url, mut body) = (method, url.to_string(), body);
+    for _ in 0..=MAX_REDIRECTS {
+        if url.starts_with("https://") {
+            return curl(method, &url, headers, body).await;
+        }
+        let (response, location) = send_once(method, &url, headers, body).await?;
+        let Some(location) = location.filter(|_| is_redirect(response.status)) else {
+            return Ok(response);
+        };
+        // As browsers and curl do, the redirects of POST requests but 307 and 308 are GETs
+        if response.status == 303 || (method == "POST" && matches!(response.status, 301 | 302)) {
+            (method, body) = ("GET", None);
+        }
+        url = resolve(&url, &location)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unsupported redirect"))?;
+    }
+    Err(io::Error::other("too many redirects"))
+}
+
+fn is_redirect(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// The URL `location` refers to, relative to `url`. Only absolute URLs and paths are supported.
+fn resolve(url: &str, location: &str) -> Option<String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return Some(location.to_string());
+    }
+    if !location.starts_with('/') {
+        return None;
+    }
+    let (host, port, _) = parse_url(url)?;
+    Some(format!("http://{host}:{port}{location}"))
+}
+
+/// Sends one request to the `http://` URL `url`, returning the response and the `Location` it
+/// redirects to, if any.
+async fn send_once(
+    method: &str,
+    url: &str,
+    headers: &[(&str, String)],
+    body: Option<(&str, &str)>,
+) -> io::Result<(Response, Option<String>)> {
+    let (host, port, path) = parse_url(url)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unsupported URL"))?;
+
+    let mut request = format!("{method} {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n");
+    if let Some((content_type, body)) = body {
+        request.push_str(&format!(
+            "Content-Type: {content_type}\r\nContent-Length: {}\r\n",
+            body.len()
+        ));
+    }
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+    if let Some((_, body)) = body {
+        request.push_str(body);
+    }
+
+    let mut stream = TcpStream::connect((host, port)).await?;
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    parse_response(&response)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))
+}
+
+/// Parses a complete HTTP/1.1 response, decoding chunked bodies, along with its `Location`
+/// header if any.
+fn parse_response(response: &[u8]) -> Option<(Response, Option<String>)> {
+    let head_end = response.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&response[..head_end]).ok()?;
+    let body = &response[head_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let (mut chunked, mut location) = (false, None);
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("location") {
+            location = Some(value.to_string());
+        }
+    }
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        body.to_vec()
+    };
+    Some((Response { status, body }, location))
+}
+
+/// Sends the request with `curl`, which follows the redirects itself. The URL, the headers and
+/// the body are passed as a config on stdin, to keep credentials out of the process list.
+async fn curl(
+    method: &str,
+    url: &str,
+    headers: &[(&str, String)],
+    body: Option<(&str, &str)>,
+) -> io::Result<Response> {
+    let mut config = curl_option("url", url);
+    for (name, value) in headers {
+        config.push_str(&curl_option("header", &format!("{name}: {value}")));
+    }
+    if let Some((content_type, body)) = body {
+        config.push_str(&curl_option(
+            "header",
+            &format!("Content-Type: {content_type}"),
+        ));
+        config.push_str(&curl_option("data-raw", body));
+    }
+    // GET, or POST with a body, is what curl does by default, and changes on redirects
+    if !matches!((method, body), ("GET", None) | ("POST", Some(_))) {
+        config.push_str(&curl_option("request", method));
+    }
+
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--max-redirs"])
+        .arg(MAX_REDIRECTS.to_string())
+        .arg("--max-time")
+        .arg(TIMEOUT.as_secs().to_string())
+        .args(["--write-out", "\n%{http_code}", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to run curl: {e}")))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(config.as_bytes()).await?;
+    drop(stdin);
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "curl exited with {}: {}",
+            output.status,
+            stderr.trim()
+        )));
+    }
+
+    // The status follows the body, on a line of its own
+    let mut body = output.stdout;
+    let newline = body.iter().rposition(|&b| b == b'\n');
+    let status = newline
+        .and_then(|i| std::str::from_utf8(&body[i + 1..]).ok()?.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed curl output"))?;
+    body.truncate(newline.unwrap_or_default());
+    Ok(Response { status, body })
+}
+
+/// A line of a curl config (`--config`) setting `option` to `value`, quoted and escaped.
+pub fn curl_option(option: &str, value: &str) -> String {
+    let mut line = format!("{option} = \"");
+    for c in value.chars() {
+        match c {
+            '\\' => line.push_str("\\\\"),
+            '"' => line.push_str("\\\""),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c => line.push(c),
+        }
+    }
+    line.push_str("\"\n");
+    line
+}
+
+fn decode_chunked(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&data[..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Some(body);
+        }
+        body.extend_from_slice(data.get(..size)?);
+        data = data.get(size + 2..)?;
+    }
+}
+
+/// Whether `url` can be requested: an `http://` or `https://` URL with a host.
+pub fn is_supported(url: &str) -> bool {
+    match url.strip_prefix("https://") {
+        Some(rest) => !rest.is_empty() && !rest.starts_with('/'),
+        None => parse_url(url).is_some(),
+    }
+}
+
+/// Splits an `http://` URL into host, port and path.
+pub fn parse_url(url: &str) -> Option<(&str, u16, &str)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    (!host.is_empty()).then_some((host, port, path))
+}
+
+/// Percent-encodes `segment` for use in a URL path.
+pub fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn urls_are_parsed() {
+        assert_eq!(
+            parse_url("http://localhost:8008/hooks/sat"),
+            Some(("localhost", 8008, "/hooks/sat"))
+        );
+        assert_eq!(parse_url("http://bridge"), Some(("bridge", 80, "/")));
+        assert_eq!(parse_url("https://hooks.example.com/x"), None);
+    }
+
+    #[test]
+    fn responses_are_parsed() {
+        let (response, location) =
+            parse_response(b"HTTP/1.1 404 Not Found\r\ncontent-length: 9\r\n\r\nnot found")
+                .unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.text(), "not found");
+        assert_eq!(location, None);
+
+        let (response, _) = parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.text(), "hello world");
+
+        let (_, location) =
+            parse_response(b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\n\r\n").unwrap();
+        assert_eq!(location.as_deref(), Some("/new"));
+    }
+
+    #[test]
+    fn https_urls_are_supported() {
+        assert!(is_supported("https://hooks.slack.com/services/T0/B0/x"));
+        assert!(is_supported("http://bridge:8080"));
+        assert!(!is_supported("https://"));
+        assert!(!is_supported("ftp://example.com"));
+    }
+
+    #[test]
+    fn curl_options_are_escaped() {
+        assert_eq!(
+            curl_option("data-raw", "{\"text\": \"a\\b\"}\n"),
+            "data-raw = \"{\\\"text\\\": \\\"a\\\\b\\\"}\\n\"\n"
+        );
+    }
+
+    /// Answers each connection to `listener` with the next of `responses`, returning the
+    /// requests received.
+    async fn serve(listener: TcpListener, responses: Vec<&'static str>) -> Vec<String> {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let mut request = String::new();
+            while !request.contains("\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.push_str(std::str::from_utf8(&buf[..n]).unwrap());
+            }
+            stream.write_all(response.as_bytes()).await.unwrap();
+            requests.push(request);
+        }
+        requests
+    }
+
+    #[tokio::test]
+    async fn redirects_are_followed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec![
+                "HTTP/1.1 308 Permanent Redirect\r\nLocation: /moved\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+            ],
+        ));
+
+        let response = request("POST", &url, &[], Some(("text/plain", "hello")))
+            .await
+            .unwrap();
+        assert_eq!(response.text(), "ok");
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /hook "));
+        assert!(requests[1].starts_with("POST /moved "));
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_curl() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec![
+                "HTTP/1.1 302 Found\r\nLocation: /moved\r\nContent-Length: 0\r\n\r\n",
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 10\r\n\r\nnot\nfound\n",
+            ],
+        ));
+
+        let headers = [("Authorization", "Bearer \"secret\"".to_string())];
+        let response = curl("PUT", &url, &headers, Some(("text/plain", "hi")))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.text(), "not\nfound\n");
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("PUT /hook "));
+        assert!(requests[0].contains("Authorization: Bearer \"secret\"\r\n"));
+        assert!(requests[1].starts_with("PUT /moved "));
+    }
+
+    #[test]
+    fn path_segments_are_encoded() {
+        assert_eq!(
+            encode_path_segment("!room:example.org"),
+            "%21room%3Aexample.org"
+        );
+    }
+}
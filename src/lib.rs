@@ -1,5 +1,35 @@
+//! The core of sat-o-mat, for tools that work with its schedules and predictions without going
+//! through the `sat-o-mat` command line or its API.
+//!
+//! - [`task`]: parsing task definitions and running their steps.
+//! - [`scheduler`]: the schedule of a station, as Task files in one directory per state.
+//! - [`predict`]: orbit propagation and pass prediction from TLEs or OMMs.
+//! - [`radio`]: streaming the IQ samples received by an SDR.
+//! - [`tracker`]: observables and Doppler corrected frequencies of an object, and the outputs
+//!   they are sent to, such as `rotctld` and `rigctld`.
+//!
+//! ```
+//! use sat_o_mat::Task;
+//!
+//! let task = Task::from_yaml_str(
+//!     r#"
+//! variables:
+//!   start: "2026-01-01T10:00:00Z"
+//!   end: "2026-01-01T10:10:00Z"
+//! steps:
+//!   - cmd: "echo hello"
+//!     time: "T+0s"
+//! "#,
+//! )
+//! .unwrap();
+//! assert_eq!(task.steps.len(), 1);
+//! assert!(task.get_time_variable("start").is_ok());
+//! ```
+
 pub mod predict;
+pub mod radio;
 pub mod scheduler;
 pub mod task;
+pub mod tracker;
 
 pub use task::Task;
@@ -0,0 +1,223 @@
+//! Log output as configured in `logging`: text or JSON lines, levels by module, and the lines
+//! logged during each run written to `run.log` in its artifacts.
+
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use chrono::{SecondsFormat, Utc};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+/// Name of the span a run is executed in, whose `artifacts` field is its artifact directory.
+const RUN_SPAN: &str = "run";
+
+/// File the lines logged during a run are written to, in its artifact directory.
+pub const RUN_LOG: &str = "run.log";
+
+/// Installs the global subscriber logging as configured in `logging`.
+///
+/// `RUST_LOG`, when set, overrides the configured levels, and `SAT_O_MAT_LOGGING_FMT=json` the
+/// format.
+pub fn init(logging: &LoggingConfig) -> anyhow::Result<()> {
+    let json = match std::env::var("SAT_O_MAT_LOGGING_FMT") {
+        Ok(format) => format.eq_ignore_ascii_case("json"),
+        Err(_) => logging.format == LogFormat::Json,
+    };
+    let output = if json {
+        tracing_subscriber::fmt::layer().json().boxed()
+    } else {
+        tracing_subscriber::fmt::layer().boxed()
+    };
+    let run_logs = logging
+        .run_logs
+        .then(|| RunLogLayer.with_filter(LevelFilter::INFO));
+
+    tracing_subscriber::registry()
+        .with(output.with_filter(filter(logging)?))
+        .with(run_logs)
+        .try_init()
+        .context("failed to set up logging")
+}
+
+/// The filter for the levels of `logging`, unless overridden by `RUST_LOG`.
+pub fn filter(logging: &LoggingConfig) -> anyhow::Result<EnvFilter> {
+    if std::env::var_os(EnvFilter::DEFAULT_ENV).is_some() {
+        return Ok(EnvFilter::builder()
+            .with_default_directive(LevelFilter::INFO.into())
+            .from_env_lossy());
+    }
+    let level = |level: &str| {
+        level
+            .parse::<LevelFilter>()
+            .with_context(|| format!("invalid log level '{level}'"))
+    };
+    let mut directives = vec![level(&logging.level)?.to_string()];
+    for (module, module_level) in &logging.modules {
+        directives.push(format!("{module}={}", level(module_level)?));
+    }
+    Ok(EnvFilter::builder().parse(directives.join(","))?)
+}
+
+/// Writes the events within a run span to `run.log` in the artifact directory of the run.
+pub struct RunLogLayer;
+
+/// The log file of a run, stored in the extensions of its span. Only opened once something is
+/// logged.
+struct RunLog {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl RunLog {
+    fn write(&self, line: &str) -> std::io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            *file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?,
+            );
+        }
+        file.as_mut().unwrap().write_all(line.as_bytes())
+    }
+}
+
+impl<S> Layer<S> for RunLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        if attrs.metadata().name() != RUN_SPAN {
+            return;
+        }
+        let mut artifacts = ArtifactsVisitor(None);
+        attrs.record(&mut artifacts);
+        if let (Some(dir), Some(span)) = (artifacts.0, ctx.span(id)) {
+            span.extensions_mut().insert(RunLog {
+                path: PathBuf::from(dir).join(RUN_LOG),
+                file: Mutex::new(None),
+            });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: LayerContext<'_, S>) {
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+        for span in scope {
+            let extensions = span.extensions();
+            let Some(log) = extensions.get::<RunLog>() else {
+                continue;
+            };
+            let metadata = event.metadata();
+            let mut line = format!(
+                "{} {} {}:",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                metadata.level(),
+                metadata.target()
+            );
+            event.record(&mut LineVisitor(&mut line));
+            line.push('\n');
+            // Logging the failure would come back here
+            if let Err(e) = log.write(&line) {
+                eprintln!("failed to write {}: {e}", log.path.display());
+            }
+            return;
+        }
+    }
+}
+
+/// Finds the `artifacts` field of a run span.
+struct ArtifactsVisitor(Option<String>);
+
+impl Visit for ArtifactsVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "artifacts" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "artifacts" {
+            self.0 = Some(value.to_string());
+        }
+    }
+}
+
+/// Appends the message and then the other fields of an event to a line.
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let _ = if field.name() == "message" {
+            write!(self.0, " {value:?}")
+        } else {
+            write!(self.0, " {}={value:?}", field.name())
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tracing::{info, info_span, warn};
+
+    use super::*;
+
+    #[test]
+    fn events_within_a_run_are_written_to_its_log() {
+        let tmp = tempfile::tempdir().unwrap();
+        let artifacts = tmp.path().join("Artifacts/pass");
+        let subscriber = tracing_subscriber::registry().with(RunLogLayer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            info!("before the run");
+            info_span!("run", id = "pass", artifacts = %artifacts.display()).in_scope(|| {
+                info_span!("step").in_scope(|| info!(step = 0, "starting step"));
+                warn!("step failed");
+            });
+            info!("after the run");
+        });
+
+        let log = fs::read_to_string(artifacts.join(RUN_LOG)).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" INFO sat_o_mat::logging::tests: starting step step=0"));
+        assert!(lines[1].ends_with(" WARN sat_o_mat::logging::tests: step failed"));
+    }
+
+    #[test]
+    fn levels_are_set_by_module() {
+        let logging = LoggingConfig {
+            level: "warn".into(),
+            modules: BTreeMap::from([("sat_o_mat::task::runner".into(), "debug".into())]),
+            ..Default::default()
+        };
+        let directives = filter(&logging).unwrap().to_string();
+        assert!(directives.contains("sat_o_mat::task::runner=debug"));
+        assert!(directives.contains("warn"));
+
+        let logging = LoggingConfig {
+            modules: BTreeMap::from([("sat_o_mat".into(), "loud".into())]),
+            ..Default::default()
+        };
+        assert!(filter(&logging).is_err());
+    }
+}
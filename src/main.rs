@@ -1,30 +1,44 @@
 use std::fs;
-use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 mod api;
+mod client;
+mod completions;
 mod config;
+mod config_check;
+mod daemon;
+mod doctor;
+mod dry_run;
 mod frontend;
-mod predict;
-mod scheduler;
+mod http;
+mod logging;
+mod notify;
+mod passes;
+mod plan;
+mod retention;
+mod runs;
 mod server;
-mod task;
-mod tracker;
+mod tls;
+mod tle;
+mod track;
+mod upload;
+mod validate;
 
-use clap::{Parser, Subcommand};
-use tracing::level_filters::LevelFilter;
-use tracing::{error, info};
-use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{EnvFilter, fmt};
+use sat_o_mat::{predict, radio, scheduler, task, tracker};
 
-use crate::predict::PredictDb;
+use chrono::{DateTime, Utc};
+use clap::{CommandFactory, Parser, Subcommand};
+use tracing::info;
+
+use crate::config::LoggingConfig;
 use crate::task::format::Task;
-use crate::task::runner::{RunConfig, run};
+use crate::task::runner::{Simulation, read_execution_log};
+use crate::tracker::Frequency;
 
 #[derive(Parser)]
 #[command(name = "sat-o-mat")]
 #[command(about = "An application to control satellite ground station hardware")]
+#[command(version)]
 struct Args {
     /// The config file. Defaults to $XDG_CONFIG_HOME/sat-o-mat/config.yaml
     #[arg(short, long, value_name = "FILE")]
@@ -36,11 +50,34 @@ struct Args {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Run a Task
+    /// Run a Task now, as the scheduler of the station would, writing its artifacts to the
+    /// station's Artifacts directory. The task file is not moved.
     Run {
         /// The task definition file
         #[arg(value_name = "FILE")]
         file: PathBuf,
+        /// ID of the run, naming its artifact directory. Defaults to the file name without
+        /// extension
+        #[arg(long)]
+        id: Option<String>,
+        /// Run on this hosted station instead of the main one
+        #[arg(long)]
+        station: Option<String>,
+        /// Start the task at this time (`now` or RFC3339) instead of its `start` variable, moving
+        /// `end`, the other timestamps and the step times with it
+        #[arg(long, value_parser = parse_start)]
+        start: Option<DateTime<Utc>>,
+        /// Play the task through on a simulated clock, printing the step commands instead of
+        /// executing them
+        #[arg(long)]
+        simulate: bool,
+        /// How many times faster than real time the simulated clock runs
+        #[arg(long, default_value_t = 60.0, requires = "simulate")]
+        speed: f64,
+        /// Print the steps that would run, when, and the hardware they would use, without running
+        /// anything
+        #[arg(long, conflicts_with = "simulate")]
+        dry_run: bool,
     },
 
     Server {
@@ -48,91 +85,546 @@ enum Commands {
         port: u32,
     },
 
+    /// Runs the station as one supervised unit: the web server and schedulers, plus the TLE
+    /// refresher, artifact retention and background services configured under `daemon`. Everything
+    /// is stopped together on SIGINT or SIGTERM, once the runs in progress have finished.
+    Daemon {
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        #[arg(long, default_value_t = 8080)]
+        port: u32,
+    },
+
     /// Tracks an object in space and publishes information about the observables
     /// (azimuth, elevation, range and range rate) relative to the ground location
     /// specified in the configuration.
     ///
     /// If TX/RX frequencies are given, Doppler corrected frequencies are also calculated
-    /// and published.
+    /// and published. Run from a terminal, it prints them as well, which is handy for field
+    /// testing antennas without the server.
+    ///
+    /// Reads orbit information from `--tle`, or from STDIN, in any of the supported formats
+    /// ({3,T}LE, CCSDS OMM).
+    #[command(visible_alias = "track")]
+    Tracker(track::TrackerArgs),
+
+    /// Receives with an SDR.
+    Radio {
+        #[command(subcommand)]
+        command: RadioCommand,
+    },
+
+    /// Controls a rotator.
+    Rotator {
+        #[command(subcommand)]
+        command: RotatorCommand,
+    },
+
+    /// Renders a task for the next pass of a satellite from a template, with the start and end
+    /// times, TLE and satellite filled in.
+    ///
+    /// The TLEs and ground station are taken from the configuration.
+    Plan(plan::PlanArgs),
+
+    /// Prints the passes of satellites over the station between two times, e.g. to plan
+    /// observations without the server.
     ///
-    /// Reads orbit information from STDIN in any of the supported formats ({3,T}LE, CCSDS OMM).
-    Tracker(tracker::TrackerArgs),
+    /// The ground station is taken from the configuration.
+    Predict(passes::PredictArgs),
+
+    /// Checks task definitions, listing their errors and the resolved times and resources used by
+    /// their steps.
+    Validate(validate::ValidateArgs),
+
+    /// Manages the tasks of a remote station through its API.
+    Client(client::ClientArgs),
+
+    /// Checks the station hardware and environment: the rotators and radios with an address, the
+    /// connected SDRs, the age of the TLEs, the free disk space and the clock synchronization.
+    Doctor,
+
+    /// Reviews past runs from their artifacts and execution logs.
+    Runs(runs::RunsArgs),
+
+    /// Downloads and inspects the TLEs of the station.
+    Tle(tle::TleArgs),
+
+    /// Prints the OpenAPI document of the API, e.g. to generate clients without a running station.
+    Openapi,
+
+    /// Prints the completion script for a shell, e.g. to ship in a package.
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Writes a manual page for each command to a directory, e.g. to ship in a package.
+    Manpages {
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+
+    /// Manages the configuration file.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Generates an API key and the Argon2 hash to put in the configuration instead of the key
+    /// itself.
+    HashKey {
+        /// Hash this key instead of generating a new one
+        #[arg(value_name = "KEY")]
+        key: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RadioCommand {
+    /// Tunes an SDR and streams its IQ samples to a UDP destination, a file or a SigMF recording
+    /// until SIGINT or SIGTERM, e.g. from a task step, which the runner stops at the end of the
+    /// task.
+    Run {
+        #[command(flatten)]
+        tune: TuneArgs,
+        /// Format of the samples: cu8, cs16 or cf32
+        #[arg(long, default_value = "cs16")]
+        format: radio::SampleFormat,
+        /// Where to stream the samples: udp=HOST:PORT, file=PATH, or record=NAME to record them
+        /// in SigMF format to NAME.sigmf-data and NAME.sigmf-meta in the artifacts of the run
+        #[arg(long)]
+        out: radio::Destination,
+        /// Satellite received, described as such in recordings. Defaults to the `satellite`
+        /// variable of the task
+        #[arg(long, env = "SATOMAT_VAR_SATELLITE")]
+        satellite: Option<String>,
+        /// Keep the power spectrum of the samples in the artifacts of the run, for the live
+        /// waterfall of the web UI. Only when running as a step
+        #[arg(long)]
+        web_fft: bool,
+        /// Number of bins of the spectrum
+        #[arg(long, default_value = "1024")]
+        fft_size: usize,
+    },
+
+    /// Tunes an SDR and demodulates the AX.25 packets sent in AFSK at 1200 baud over FM until
+    /// SIGINT or SIGTERM, serving them to KISS clients over TCP and logging them to packets.log
+    /// in the artifacts of the run when running as a step.
+    Demodulate {
+        #[command(flatten)]
+        tune: TuneArgs,
+        /// Address to serve the packets on to KISS clients, e.g. "0.0.0.0:8001"
+        #[arg(long, default_value = "127.0.0.1:8001")]
+        kiss: String,
+        /// Log the packets to this file instead
+        #[arg(long)]
+        log: Option<PathBuf>,
+        /// Also stream the samples to udp=HOST:PORT, file=PATH or record=NAME, e.g. to keep a
+        /// recording of the pass
+        #[arg(long)]
+        out: Option<radio::Destination>,
+        /// Satellite received, described as such in recordings. Defaults to the `satellite`
+        /// variable of the task
+        #[arg(long, env = "SATOMAT_VAR_SATELLITE")]
+        satellite: Option<String>,
+    },
+}
+
+/// The SDR to tune, and how.
+#[derive(clap::Args)]
+struct TuneArgs {
+    /// Center frequency, e.g. "137.1 MHz"
+    #[arg(long)]
+    frequency: Frequency,
+    /// Bandwidth received, the sample rate of the stream, e.g. "1.024 MHz"
+    #[arg(long)]
+    bandwidth: Frequency,
+    /// SDR resource to use, e.g. "sdr1", taking its backend, device and gain from its `sdr`
+    /// settings. The options given override them
+    #[arg(long)]
+    sdr: Option<String>,
+    /// Use the resources of this hosted station instead of the main one
+    #[arg(long)]
+    station: Option<String>,
+    /// Tools driving the SDR: soapy (rx_sdr) or rtlsdr (rtl_sdr). Defaults to soapy
+    #[arg(long)]
+    backend: Option<radio::Backend>,
+    /// SoapySDR device arguments, e.g. "driver=rtlsdr,serial=01", or the index of an RTL-SDR
+    #[arg(long)]
+    device: Option<String>,
+    /// Gain (dB). Automatic if not given
+    #[arg(long)]
+    gain: Option<f64>,
+}
+
+#[derive(Subcommand)]
+enum RotatorCommand {
+    /// Drives a rotator to its `park` position, or the park position of its `rotctld` server if
+    /// unset, e.g. from a task step after a pass.
+    Park {
+        /// Name of a resource with an `address`, or the address of a `rotctld`-compatible server
+        #[arg(value_name = "ROTATOR")]
+        rotator: String,
+        /// Use the resources of this hosted station instead of the main one
+        #[arg(long)]
+        station: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Checks the configuration without starting the station: the ground stations, resources,
+    /// API keys, paths and notification targets. Nothing is created or modified.
+    Check {
+        /// The config file. Defaults to the one given with --config, or the default one
+        #[arg(value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let use_json_logging = std::env::var("SAT_O_MAT_LOGGING_FMT")
-        .map(|v| v.eq_ignore_ascii_case("json"))
-        .unwrap_or(false);
-
-    let registry = tracing_subscriber::registry()
-        .with(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::INFO.into())
-                .from_env_lossy(),
-        );
+    // Logs go to stdout, keep them out of the documents
+    match &args.command {
+        Commands::Openapi => {
+            println!("{}", api::openapi().to_pretty_json()?);
+            return Ok(());
+        }
+        Commands::Completions { shell } => {
+            print!("{}", completions::completions(Args::command(), *shell));
+            return Ok(());
+        }
+        Commands::Manpages { dir } => {
+            for path in completions::manpages(Args::command(), dir)? {
+                println!("{}", path.display());
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
 
-    if use_json_logging {
-        registry.with(fmt::layer().json()).init();
-    } else {
-        registry.with(fmt::layer()).init();
+    // The client talks to a remote station, it needs no local configuration. The configuration
+    // check must not create the default one.
+    let command = match args.command {
+        Commands::Client(args) => {
+            logging::init(&LoggingConfig::default())?;
+            print!("{}", client::run(args).await?);
+            return Ok(());
+        }
+        Commands::Config {
+            command: ConfigCommand::Check { file },
+        } => {
+            let path = match file.or(args.config) {
+                Some(path) => path,
+                None => config::default_path()?,
+            };
+            logging::init(&LoggingConfig::default())?;
+            return config_check::run(&path);
+        }
+        command => command,
     };
 
-    let config = config::load(args.config.as_ref())?;
+    // Logging is set up from the configuration, so what is logged while loading it goes to the
+    // default output
+    let config = tracing::subscriber::with_default(tracing_subscriber::fmt().finish(), || {
+        config::load(args.config.as_ref())
+    })?;
+    logging::init(&config.logging)?;
     info!(?config);
 
-    match args.command {
-        Commands::Run { file } => {
-            println!("running {:?}", file);
+    match command {
+        Commands::Run {
+            file,
+            id,
+            station,
+            start,
+            simulate,
+            speed,
+            dry_run,
+        } => {
+            let config = station_config(config, station.as_deref())?;
+            if dry_run {
+                let mut task = Task::from_yaml_str(&fs::read_to_string(&file)?)?;
+                if let Some(start) = start {
+                    task.rebase(start)?;
+                }
+                let dry_run = dry_run::dry_run(&task, &config)?;
+                print!("{}", dry_run::text(&dry_run));
+                if !dry_run.errors.is_empty() {
+                    anyhow::bail!("{} refers to hardware that is not configured", file.display());
+                }
+                return Ok(());
+            }
+            let simulation = simulate.then_some(Simulation { speed });
+            run_runner(&file, id, start, &config, simulation).await?;
         }
         Commands::Server { host, port } => {
             server::run(config, host, port).await?;
         }
+        Commands::Daemon { host, port } => {
+            daemon::run(config, host, port).await?;
+        }
         Commands::Tracker(args) => {
-            // Read orbit info (TLE, OMM, ...) from stdin
-            let mut orbit_info = String::new();
-            let mut stdin = std::io::stdin();
-            info!("waiting for orbit info from stdin");
-            stdin.read_to_string(&mut orbit_info).unwrap();
-
-            // Create PredictDb
-            let mut pdb = PredictDb::new();
-            match pdb.add(&orbit_info) {
-                0 => {
-                    todo!();
-                }
-                1 => {
-                    let (object, _) = pdb.first().unwrap();
-                    info!(?object, "loaded one orbit");
-                }
-                2.. => {
-                    error!("more than one orbit loaded, please specify which object to track");
-                    return Ok(());
+            let config = station_config(config, args.station.as_deref())?;
+            track::run(args, &config).await?;
+        }
+        Commands::Runs(args) => {
+            let config = station_config(config, args.station.as_deref())?;
+            print!("{}", runs::run(args, &config)?);
+        }
+        Commands::Doctor => {
+            doctor::run(&config).await?;
+        }
+        Commands::Tle(args) => {
+            print!("{}", tle::run(args, &config).await?);
+        }
+        Commands::Plan(args) => {
+            let config = station_config(config, args.station.as_deref())?;
+            plan::run(args, &config)?;
+        }
+        Commands::Predict(args) => {
+            let config = station_config(config, args.station.as_deref())?;
+            print!("{}", passes::run(args, &config)?);
+        }
+        Commands::Radio {
+            command:
+                RadioCommand::Run {
+                    tune,
+                    format,
+                    out,
+                    satellite,
+                    web_fft,
+                    fft_size,
+                },
+        } => {
+            let artifacts_dir = std::env::var_os("SATOMAT_ARTIFACTS_DIR").map(PathBuf::from);
+            let spectrum = if web_fft {
+                let dir = artifacts_dir
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("--web-fft is only supported in task steps"))?;
+                if fft_size < 2 {
+                    anyhow::bail!("the FFT needs at least 2 bins");
                 }
+                Some(radio::spectrum::SpectrumOutput {
+                    path: dir.join(radio::spectrum::SPECTRUM_FILE),
+                    bins: fft_size,
+                })
+            } else {
+                None
+            };
+            let stream = radio::Stream {
+                format,
+                out: Some(recording_in(out, artifacts_dir.as_deref())?),
+                spectrum,
+                satellite,
+                ..tuned_stream(tune, config)?
+            };
+            let bytes = radio::run(&stream, server::shutdown_signal()).await?;
+            info!(bytes, "SDR stream stopped");
+        }
+        Commands::Radio {
+            command:
+                RadioCommand::Demodulate {
+                    tune,
+                    kiss,
+                    log,
+                    out,
+                    satellite,
+                },
+        } => {
+            let artifacts_dir = std::env::var_os("SATOMAT_ARTIFACTS_DIR").map(PathBuf::from);
+            let log = log.or_else(|| {
+                artifacts_dir
+                    .as_ref()
+                    .map(|dir| dir.join(radio::ax25::PACKETS_FILE))
+            });
+            let out = match out {
+                Some(out) => Some(recording_in(out, artifacts_dir.as_deref())?),
+                None => None,
+            };
+            let stream = tuned_stream(tune, config)?;
+            let stream = radio::Stream {
+                // The only format of rtl_sdr
+                format: match stream.backend {
+                    radio::Backend::RtlSdr => radio::SampleFormat::Cu8,
+                    radio::Backend::Soapy => radio::SampleFormat::Cs16,
+                },
+                out,
+                packets: Some(radio::ax25::PacketOutput {
+                    kiss: Some(kiss),
+                    log,
+                }),
+                satellite,
+                ..stream
+            };
+            radio::run(&stream, server::shutdown_signal()).await?;
+            info!("demodulation stopped");
+        }
+        Commands::Rotator {
+            command: RotatorCommand::Park { rotator, station },
+        } => {
+            let config = station_config(config, station.as_deref())?;
+            let rotator = track::resolve_rotator(&rotator, &config)?;
+            tracker::rotator::park(&rotator).await?;
+            info!(address = %rotator.address, "rotator parked");
+        }
+        Commands::Validate(args) => {
+            let config = station_config(config, args.station.as_deref())?;
+            validate::run(args, &config)?;
+        }
+        Commands::Client(_)
+        | Commands::Config { .. }
+        | Commands::Openapi
+        | Commands::Completions { .. }
+        | Commands::Manpages { .. } => {
+            unreachable!("handled before loading the configuration")
+        }
+        Commands::HashKey { key } => {
+            let key = match key {
+                Some(key) => key,
+                None => api::auth::generate_key()?,
+            };
+            println!("key: {key}");
+            if let Some(id) = api::auth::key_id(&key) {
+                println!("id: {id}");
             }
-
-            // Run tracker
-            tracker::run(args, &pdb, &config).await;
+            println!("hash: {}", api::auth::hash_key(&key)?);
         }
     }
 
     Ok(())
 }
 
-async fn run_runner(task_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// The configuration of the hosted `station`, or `config` itself for the main one.
+fn station_config(config: config::Config, station: Option<&str>) -> anyhow::Result<config::Config> {
+    match station {
+        Some(name) => config
+            .hosted_station(name)
+            .ok_or_else(|| anyhow::anyhow!("unknown station {name}")),
+        None => Ok(config),
+    }
+}
+
+/// A stream from the SDR given by `tune`, in cs16 and going nowhere until set.
+fn tuned_stream(tune: TuneArgs, config: config::Config) -> anyhow::Result<radio::Stream> {
+    let config = station_config(config, tune.station.as_deref())?;
+    let sdr = match &tune.sdr {
+        Some(name) => Some(config.sdr(name)?),
+        None => None,
+    };
+    if let Some(sdr) = sdr
+        && !sdr.sample_rates.is_empty()
+        && !sdr.sample_rates.contains(&tune.bandwidth.0)
+    {
+        anyhow::bail!(
+            "the SDR does not support a sample rate of {} Hz, expected one of {:?}",
+            tune.bandwidth.0,
+            sdr.sample_rates
+        );
+    }
+    Ok(radio::Stream {
+        backend: tune
+            .backend
+            .or(sdr.map(|s| s.backend))
+            .unwrap_or(radio::Backend::Soapy),
+        device: tune.device.or_else(|| sdr.and_then(|s| s.device.clone())),
+        frequency: tune.frequency.0,
+        sample_rate: tune.bandwidth.0,
+        gain: tune.gain.or(sdr.and_then(|s| s.gain)),
+        format: radio::SampleFormat::Cs16,
+        out: None,
+        spectrum: None,
+        packets: None,
+        satellite: None,
+    })
+}
+
+/// `out`, with recordings in `artifacts_dir`, only set in task steps.
+fn recording_in(
+    out: radio::Destination,
+    artifacts_dir: Option<&Path>,
+) -> anyhow::Result<radio::Destination> {
+    match out {
+        radio::Destination::Record(name) => {
+            let dir = artifacts_dir
+                .ok_or_else(|| anyhow::anyhow!("--out record= is only supported in task steps"))?;
+            Ok(radio::Destination::Record(dir.join(name)))
+        }
+        out => Ok(out),
+    }
+}
+
+async fn run_runner(
+    task_path: &Path,
+    id: Option<String>,
+    start: Option<DateTime<Utc>>,
+    config: &config::Config,
+    simulation: Option<Simulation>,
+) -> anyhow::Result<()> {
+    if let Some(Simulation { speed }) = simulation
+        && !(speed.is_finite() && speed > 0.0)
+    {
+        anyhow::bail!("speed must be a positive number");
+    }
     let yaml = fs::read_to_string(task_path)?;
-    let task = Task::from_yaml_str(&yaml)?;
-    let config = RunConfig {
-        artifact_base: PathBuf::from("artifacts"),
+    let mut task = Task::from_yaml_str(&yaml)?;
+    if let Some(start) = start {
+        task.rebase(start)?;
+        info!(%start, "moved the task start");
+    }
+    let id = match id {
+        Some(id) => id,
+        None => task_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow::anyhow!("cannot derive an ID from {}", task_path.display()))?,
     };
-    let outcome = run(task, config).await?;
+    if id.is_empty() || id.contains(['/', '\\']) || id == "." || id == ".." {
+        anyhow::bail!("invalid ID {id}");
+    }
+
+    let artifact_dir = config.tasks_path.join("Artifacts").join(&id);
+    if artifact_dir.exists() {
+        anyhow::bail!(
+            "{} already exists, choose another ID with --id",
+            artifact_dir.display()
+        );
+    }
 
-    println!("aborted: {}", outcome.aborted());
-    println!("artifact_dir: {}", outcome.artifact_dir.display());
-    println!("steps: {}", outcome.step_outcomes.len());
-    println!("outcomes: {:?}", outcome.step_outcomes);
+    let event = scheduler::execute(
+        &config.tasks_path,
+        &id,
+        task,
+        simulation,
+        Default::default(),
+    )
+    .await;
 
-    Ok(())
+    println!("artifacts: {}", artifact_dir.display());
+    for entry in read_execution_log(&artifact_dir)? {
+        let exit_code = entry.exit_code.map(|c| c.to_string()).unwrap_or_default();
+        println!(
+            "{} {:?} {exit_code} {}",
+            entry.time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            entry.result,
+            entry.cmd
+        );
+    }
+    match event {
+        scheduler::RunEvent::Completed(_) => Ok(()),
+        scheduler::RunEvent::Aborted(_) => anyhow::bail!("run of {id} aborted"),
+        _ => anyhow::bail!("run of {id} failed"),
+    }
+}
+
+/// Parses `now` or an RFC3339 timestamp.
+fn parse_start(s: &str) -> Result<DateTime<Utc>, String> {
+    if s.eq_ignore_ascii_case("now") {
+        return Ok(Utc::now());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("expected `now` or an RFC3339 time: {e}"))
 }
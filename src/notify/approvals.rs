@@ -0,0 +1,393 @@
+//! Notifications to the approvers of tasks pending approval and of failed runs, by email and
+//! Matrix.
+
+use std::io;
+use std::process::Stdio;
+
+use chrono::Utc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+
+use crate::config::{ApprovalNotificationConfig, EmailConfig, MatrixConfig, SmtpTls};
+use crate::task::Task;
+
+use crate::http;
+
+/// What the approvers are told about a task.
+#[derive(Debug, Clone, Copy)]
+pub enum Reason {
+    PendingApproval,
+    RunFailed,
+}
+
+/// Subject and text of the notification about `task_id`, with a summary of the `task` if its
+/// file was found.
+pub fn message(
+    config: &ApprovalNotificationConfig,
+    station: &str,
+    task_id: &str,
+    reason: Reason,
+    task: Option<&Task>,
+) -> (String, String) {
+    let (subject, intro, link) = match reason {
+        Reason::PendingApproval => (
+            format!("[{station}] Task {task_id} is pending approval"),
+            format!("Task {task_id} was submitted to {station} and is waiting for approval."),
+            "Review it at",
+        ),
+        Reason::RunFailed => (
+            format!("[{station}] Run of task {task_id} failed"),
+            format!("The run of task {task_id} on {station} failed."),
+            "See its logs at",
+        ),
+    };
+    let summary = task.map(summary).unwrap_or_default();
+    // A line break in the subject would start a new header of the email
+    let subject = subject.chars().filter(|c| !c.is_control()).collect();
+    (
+        subject,
+        format!("{intro}\n\n{summary}{link} {}\n", config.dashboard_url),
+    )
+}
+
+/// The time, owner, template and steps of `task`, one per line, ending with an empty line.
+fn summary(task: &Task) -> String {
+    let mut summary = String::new();
+    if let Ok((start, end)) = task.time_range() {
+        let format = "%Y-%m-%d %H:%M:%S UTC";
+        summary += &format!("Time: {} to {}\n", start.format(format), end.format(format));
+    }
+    if let Some(owner) = &task.owner {
+        summary += &format!("Submitted by: {owner}\n");
+    }
+    if let Some(template) = &task.template {
+        summary += &format!("Template: {template}\n");
+    }
+    summary += &format!("Steps: {}\n\n", task.steps.len());
+    summary
+}
+
+/// Sends an email through the configured SMTP relay.
+pub async fn send_email(config: &EmailConfig, subject: &str, text: &str) -> io::Result<()> {
+    let message = email(config, subject, text);
+    match config.tls {
+        SmtpTls::None => send_plaintext(config, &message).await,
+        SmtpTls::Starttls | SmtpTls::Implicit => send_with_curl(config, &message).await,
+    }
+}
+
+/// The headers and the body of the email, with CRLF line endings.
+fn email(config: &EmailConfig, subject: &str, text: &str) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {subject}\r\nDate: {}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\r\n",
+        config.from,
+        config.to.join(", "),
+        Utc::now().to_rfc2822()
+    );
+    for line in text.lines() {
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+/// Sends `message` over a plaintext SMTP connection, without credentials to be read on the way.
+async fn send_plaintext(config: &EmailConfig, message: &str) -> io::Result<()> {
+    if config.username.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "SMTP credentials are only sent over TLS, set `tls`",
+        ));
+    }
+    let stream = TcpStream::connect((config.smtp_host.as_str(), config.smtp_port)).await?;
+    let mut smtp = Smtp {
+        stream: BufReader::new(stream),
+    };
+
+    smtp.expect(220).await?;
+    smtp.command("EHLO sat-o-mat", 250).await?;
+    smtp.command(&format!("MAIL FROM:<{}>", config.from), 250)
+        .await?;
+    for to in &config.to {
+        smtp.command(&format!("RCPT TO:<{to}>"), 250).await?;
+    }
+    smtp.command("DATA", 354).await?;
+
+    let mut data = String::with_capacity(message.len() + 3);
+    for line in message.split_inclusive("\r\n") {
+        // Dot-stuffing, so that a line with a single dot does not end the message
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+    }
+    data.push('.');
+    smtp.command(&data, 250).await?;
+    smtp.command("QUIT", 221).await
+}
+
+/// Sends `message` with `curl`, which encrypts the connection. The message goes through a
+/// temporary file, as the credentials are passed on stdin to keep them out of the process list.
+async fn send_with_curl(config: &EmailConfig, message: &str) -> io::Result<()> {
+    let path = std::env::temp_dir().join(format!(
+        "sat-o-mat-{}-{}.eml",
+        std::process::id(),
+        Utc::now().timestamp_nanos_opt().unwrap_or(0)
+    ));
+    tokio::fs::write(&path, message).await?;
+    let result = async {
+        let mut curl = Command::new("curl")
+            .args(["--silent", "--show-error", "--upload-file"])
+            .arg(&path)
+            .args(["--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to run curl: {e}")))?;
+        let mut stdin = curl.stdin.take().expect("stdin is piped");
+        stdin.write_all(curl_config(config).as_bytes()).await?;
+        drop(stdin);
+        let output = curl.wait_with_output().await?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(io::Error::other(format!(
+                "curl exited with {}: {}",
+                output.status,
+                stderr.trim()
+            )))
+        }
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&path).await;
+    result
+}
+
+/// The curl config sending an email as configured, over TLS.
+fn curl_config(config: &EmailConfig) -> String {
+    let scheme = match config.tls {
+        SmtpTls::Implicit => "smtps",
+        SmtpTls::None | SmtpTls::Starttls => "smtp",
+    };
+    let url = format!("{scheme}://{}:{}", config.smtp_host, config.smtp_port);
+    let mut curl = http::curl_option("url", &url);
+    // Fail rather than fall back to plaintext if the relay does not offer STARTTLS
+    curl.push_str("ssl-reqd\n");
+    curl.push_str(&http::curl_option("mail-from", &config.from));
+    for to in &config.to {
+        curl.push_str(&http::curl_option("mail-rcpt", to));
+    }
+    if let Some(username) = &config.username {
+        let password = config.password.as_deref().unwrap_or_default();
+        curl.push_str(&http::curl_option(
+            "user",
+            &format!("{username}:{password}"),
+        ));
+    }
+    curl
+}
+
+struct Smtp {
+    stream: BufReader<TcpStream>,
+}
+
+impl Smtp {
+    /// Sends `line` and expects a reply with `code`.
+    async fn command(&mut self, line: &str, code: u16) -> io::Result<()> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{line}\r\n").as_bytes())
+            .await?;
+        self.expect(code).await
+    }
+
+    /// Reads a (possibly multiline) reply and checks its code.
+    async fn expect(&mut self, code: u16) -> io::Result<()> {
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "SMTP connection closed",
+                ));
+            }
+            if line.get(..3).and_then(|c| c.parse::<u16>().ok()) != Some(code) {
+                return Err(io::Error::other(format!(
+                    "unexpected SMTP reply {}",
+                    line.trim_end()
+                )));
+            }
+            // "250-" continues a multiline reply, "250 " ends it
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Posts a text message to the configured Matrix room.
+pub async fn send_matrix(config: &MatrixConfig, text: &str) -> io::Result<()> {
+    let transaction_id = format!(
+        "sat-o-mat.{}",
+        Utc::now().timestamp_nanos_opt().unwrap_or(0)
+    );
+    let url = format!(
+        "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{transaction_id}",
+        config.homeserver.trim_end_matches('/'),
+        http::encode_path_segment(&config.room_id)
+    );
+    let body = serde_json::json!({ "msgtype": "m.text", "body": text }).to_string();
+    let authorization = format!("Bearer {}", config.access_token);
+
+    http::send_json("PUT", &url, &[("Authorization", authorization)], &body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn messages_summarize_the_task() {
+        let config = ApprovalNotificationConfig {
+            dashboard_url: "http://station.local/".into(),
+            failed_runs: true,
+            email: None,
+            matrix: None,
+        };
+        let task = Task::from_yaml_str(
+            r#"
+variables:
+  start: "2026-01-01T10:00:00Z"
+  end: "2026-01-01T10:10:00Z"
+owner: alice
+steps:
+  - cmd: "echo hello"
+    time: "T+0s"
+"#,
+        )
+        .unwrap();
+
+        let (subject, text) = message(&config, "test", "pass.1", Reason::RunFailed, Some(&task));
+        assert_eq!(subject, "[test] Run of task pass.1 failed");
+        assert_eq!(
+            text,
+            "The run of task pass.1 on test failed.\n\n\
+             Time: 2026-01-01 10:00:00 UTC to 2026-01-01 10:10:00 UTC\n\
+             Submitted by: alice\n\
+             Steps: 1\n\n\
+             See its logs at http://station.local/\n"
+        );
+
+        let (subject, text) = message(&config, "test", "pass.1", Reason::PendingApproval, None);
+        assert_eq!(subject, "[test] Task pass.1 is pending approval");
+        assert_eq!(
+            text,
+            "Task pass.1 was submitted to test and is waiting for approval.\n\n\
+             Review it at http://station.local/\n"
+        );
+
+        let (subject, _) = message(&config, "test", "a\r\nBcc: x", Reason::RunFailed, None);
+        assert_eq!(subject, "[test] Run of task aBcc: x failed");
+    }
+
+    #[test]
+    fn curl_sends_the_credentials_over_tls() {
+        let config = EmailConfig {
+            smtp_host: "smtp.example.org".into(),
+            smtp_port: 465,
+            tls: SmtpTls::Implicit,
+            username: Some("station".into()),
+            password: Some("hunter\"2".into()),
+            from: "station@example.org".into(),
+            to: vec!["ops@example.org".into()],
+        };
+        assert_eq!(
+            curl_config(&config),
+            "url = \"smtps://smtp.example.org:465\"\n\
+             ssl-reqd\n\
+             mail-from = \"station@example.org\"\n\
+             mail-rcpt = \"ops@example.org\"\n\
+             user = \"station:hunter\\\"2\"\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn email_is_sent_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = EmailConfig {
+            smtp_host: "127.0.0.1".into(),
+            smtp_port: listener.local_addr().unwrap().port(),
+            tls: SmtpTls::None,
+            username: None,
+            password: None,
+            from: "station@example.org".into(),
+            to: vec!["ops@example.org".into(), "pi@example.org".into()],
+        };
+
+        // Scripted server accepting everything
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            stream
+                .get_mut()
+                .write_all(b"220 relay ready\r\n")
+                .await
+                .unwrap();
+            let mut transcript = String::new();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                transcript.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    stream.get_mut().write_all(b"221 bye\r\n").await.unwrap();
+                    let mut rest = Vec::new();
+                    stream.read_to_end(&mut rest).await.unwrap();
+                    break;
+                } else {
+                    b"250-ok\r\n250 ok\r\n"
+                };
+                stream.get_mut().write_all(reply).await.unwrap();
+            }
+            transcript
+        });
+
+        send_email(&config, "Pending", "Approve it\n.\nthanks")
+            .await
+            .unwrap();
+        let transcript = server.await.unwrap();
+
+        assert!(!transcript.contains("AUTH"));
+        assert!(transcript.contains("MAIL FROM:<station@example.org>\r\n"));
+        assert!(transcript.contains("RCPT TO:<ops@example.org>\r\nRCPT TO:<pi@example.org>\r\n"));
+        assert!(transcript.contains("Subject: Pending\r\n"));
+        assert!(transcript.contains("\r\nApprove it\r\n..\r\nthanks\r\n.\r\n"));
+
+        // Credentials are not sent in plaintext
+        config.username = Some("station".into());
+        config.password = Some("hunter2".into());
+        let error = send_email(&config, "Pending", "Approve it")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,483 @@
+//! Notifications of task and run events to webhooks, and of pending approvals and failed runs to
+//! the approvers.
+
+mod approvals;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use sat_o_mat::scheduler::{Preemption, RunEvent};
+use sat_o_mat::task::runner::StepEvent;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{Notify, broadcast};
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use crate::config::{ApprovalNotificationConfig, Config, NotificationEvent, WebhookConfig};
+use crate::http;
+use crate::task::Task;
+
+const SIGNATURE_HEADER: &str = "X-Sat-O-Mat-Signature";
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events kept for subscribers lagging behind.
+const EVENTS_CAPACITY: usize = 256;
+
+/// Sends events to the configured webhooks. Delivery happens in the background, failures are
+/// only logged.
+#[derive(Clone)]
+pub struct Notifier {
+    station: Arc<str>,
+    /// Where the tasks are read from to summarize them to the approvers
+    tasks_path: Arc<PathBuf>,
+    webhooks: Arc<[WebhookConfig]>,
+    approvals: Option<Arc<ApprovalNotificationConfig>>,
+    in_flight: Arc<InFlight>,
+    /// Number of each event notified, for the metrics
+    counts: Arc<Mutex<HashMap<NotificationEvent, u64>>>,
+    /// Every event, including the steps of the runs
+    events: broadcast::Sender<StationEvent>,
+}
+
+/// Event of a station, as streamed by the API.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StationEvent {
+    /// One of the notification events (`task_submitted`, `approval_needed`, `task_approved`,
+    /// `task_rejected`, `run_started`, `run_completed`, `run_failed`, `run_aborted`),
+    /// `step_started` or `step_completed`
+    pub event: String,
+    pub task_id: String,
+    pub station: String,
+    /// Time of the event as RFC3339
+    pub time: String,
+    /// Index of the step, for step events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<usize>,
+    /// Result of the step, for `step_completed`, as in the execution log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    /// Exit code of the step, for `step_completed` if it exited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+/// Deliveries in progress, so that they can be waited for on shutdown.
+#[derive(Default)]
+struct InFlight {
+    count: AtomicUsize,
+    done: Notify,
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    event: NotificationEvent,
+    task_id: &'a str,
+    station: &'a str,
+    /// Time of the event as RFC3339
+    time: String,
+}
+
+impl Payload<'_> {
+    /// `template` with the fields of the payload in place of their `{name}`, escaped as in a JSON
+    /// string if `json`. Placeholders are only looked for in the template, not in the values.
+    fn render(&self, template: &str, json: bool) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let name = rest[1..].split_once('}').map(|(name, _)| name);
+            let value = match name {
+                Some("event") => event_name(self.event),
+                Some("task_id") => self.task_id.to_string(),
+                Some("station") => self.station.to_string(),
+                Some("time") => self.time.clone(),
+                _ => {
+                    rendered.push('{');
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+            if json {
+                let quoted = serde_json::Value::from(value).to_string();
+                rendered.push_str(&quoted[1..quoted.len() - 1]);
+            } else {
+                rendered.push_str(&value);
+            }
+            rest = &rest[name.map_or(0, str::len) + 2..];
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+/// Whether `content_type` is JSON, e.g. `application/json` or `application/vnd.api+json`.
+fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence.eq_ignore_ascii_case("application/json") || essence.ends_with("+json")
+}
+
+impl Notifier {
+    pub fn new(config: &Config) -> Self {
+        let approvals = config.notifications.approvals.as_ref();
+        let urls = config.notifications.webhooks.iter().map(|w| &w.url).chain(
+            approvals
+                .and_then(|a| a.matrix.as_ref())
+                .map(|m| &m.homeserver),
+        );
+        for url in urls {
+            if !http::is_supported(url) {
+                warn!(%url, "unsupported notification URL, expected http:// or https://");
+            }
+        }
+
+        Self {
+            station: config.station_name.as_str().into(),
+            tasks_path: config.tasks_path.clone().into(),
+            webhooks: config.notifications.webhooks.clone().into(),
+            approvals: approvals.cloned().map(Arc::new),
+            in_flight: Default::default(),
+            counts: Default::default(),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+
+    /// Receives the events of the station from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<StationEvent> {
+        self.events.subscribe()
+    }
+
+    /// Sends `event` to the subscribers, if any.
+    fn publish(&self, event: String, task_id: &str, step: Option<&StepEvent>) {
+        let (step, result, exit_code) = match step {
+            Some(StepEvent::Started { step }) => (Some(*step), None, None),
+            Some(StepEvent::Completed {
+                step,
+                result,
+                exit_code,
+            }) => (
+                Some(*step),
+                Some(crate::api::runs::result_name(*result).to_string()),
+                *exit_code,
+            ),
+            None => (None, None, None),
+        };
+        let _ = self.events.send(StationEvent {
+            event,
+            task_id: task_id.to_string(),
+            station: self.station.to_string(),
+            time: Utc::now().to_rfc3339(),
+            step,
+            result,
+            exit_code,
+        });
+    }
+
+    /// How many times each event was notified since the start.
+    pub fn counts(&self) -> HashMap<NotificationEvent, u64> {
+        self.counts.lock().unwrap().clone()
+    }
+
+    /// Waits until the notifications sent so far have been delivered, or have failed.
+    pub async fn flush(&self) {
+        loop {
+            // Registered before checking, so that the last delivery finishing is not missed
+            let done = self.in_flight.done.notified();
+            if self.in_flight.count.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            done.await;
+        }
+    }
+
+    /// Runs `delivery` in the background, keeping track of it for [`Notifier::flush`].
+    fn spawn(&self, delivery: impl Future<Output = ()> + Send + 'static) {
+        let in_flight = self.in_flight.clone();
+        in_flight.count.fetch_add(1, Ordering::SeqCst);
+        tokio::spawn(async move {
+            delivery.await;
+            if in_flight.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+                in_flight.done.notify_waiters();
+            }
+        });
+    }
+
+    /// Notifies `event` about task `task_id` to the webhooks interested in it.
+    pub fn notify(&self, event: NotificationEvent, task_id: &str) {
+        *self.counts.lock().unwrap().entry(event).or_default() += 1;
+        self.publish(event_name(event), task_id, None);
+        let payload = Payload {
+            event,
+            task_id,
+            station: &self.station,
+            time: Utc::now().to_rfc3339(),
+        };
+        let Ok(json) = serde_json::to_string(&payload) else {
+            return;
+        };
+
+        for webhook in self.webhooks.iter() {
+            if !webhook.events.is_empty() && !webhook.events.contains(&event) {
+                continue;
+            }
+            let webhook = webhook.clone();
+            let body = match &webhook.template {
+                Some(template) => payload.render(template, is_json(&webhook.content_type)),
+                None => json.clone(),
+            };
+            self.spawn(async move {
+                match tokio::time::timeout(TIMEOUT, deliver(&webhook, &body)).await {
+                    Ok(Ok(())) => debug!(url = %webhook.url, ?event, "notification delivered"),
+                    Ok(Err(e)) => warn!(url = %webhook.url, ?event, %e, "notification failed"),
+                    Err(_) => warn!(url = %webhook.url, ?event, "notification timed out"),
+                }
+            });
+        }
+    }
+
+    /// Notifies the approvers that task `task_id` was placed in PendingApproval, by email and
+    /// Matrix as well as to the webhooks.
+    pub fn pending_approval(&self, task_id: &str) {
+        self.notify(NotificationEvent::ApprovalNeeded, task_id);
+        self.message_approvers(approvals::Reason::PendingApproval, task_id);
+    }
+
+    /// Notifies that task `task_id` was taken off the schedule by a task of higher priority, and
+    /// moved as `preemption` says.
+    pub fn preempted(&self, task_id: &str, preemption: Preemption) {
+        match preemption {
+            Preemption::Pending => self.pending_approval(task_id),
+            Preemption::Reject => self.notify(NotificationEvent::TaskRejected, task_id),
+        }
+    }
+
+    /// Messages the approvers about task `task_id`, with a summary of the task.
+    fn message_approvers(&self, reason: approvals::Reason, task_id: &str) {
+        let Some(config) = self.approvals.clone() else {
+            return;
+        };
+        let station = self.station.clone();
+        let tasks_path = self.tasks_path.clone();
+        let task_id = task_id.to_string();
+
+        self.spawn(async move {
+            let task = Task::find(&tasks_path, &task_id)
+                .await
+                .and_then(|(_, yaml)| Task::from_yaml_str(&yaml).ok());
+            let (subject, text) =
+                approvals::message(&config, &station, &task_id, reason, task.as_ref());
+            if let Some(email) = &config.email {
+                match tokio::time::timeout(TIMEOUT, approvals::send_email(email, &subject, &text))
+                    .await
+                {
+                    Ok(Ok(())) => debug!(%task_id, "approval email sent"),
+                    Ok(Err(e)) => warn!(%task_id, %e, "failed to send approval email"),
+                    Err(_) => warn!(%task_id, "approval email timed out"),
+                }
+            }
+            if let Some(matrix) = &config.matrix {
+                match tokio::time::timeout(TIMEOUT, approvals::send_matrix(matrix, &text)).await {
+                    Ok(Ok(())) => debug!(%task_id, "approval Matrix message sent"),
+                    Ok(Err(e)) => warn!(%task_id, %e, "failed to send approval Matrix message"),
+                    Err(_) => warn!(%task_id, "approval Matrix message timed out"),
+                }
+            }
+        });
+    }
+
+    /// Forwards the scheduler's run events until the scheduler exits. Step events are only
+    /// published to the subscribers.
+    pub async fn forward_run_events(
+        self,
+        mut events: tokio::sync::mpsc::UnboundedReceiver<RunEvent>,
+    ) {
+        while let Some(event) = events.recv().await {
+            let (event, task_id) = match event {
+                RunEvent::Started(id) => (NotificationEvent::RunStarted, id),
+                RunEvent::Completed(id) => (NotificationEvent::RunCompleted, id),
+                RunEvent::Failed(id) => (NotificationEvent::RunFailed, id),
+                RunEvent::Aborted(id) => (NotificationEvent::RunAborted, id),
+                RunEvent::Step(id, step) => {
+                    let name = match step {
+                        StepEvent::Started { .. } => "step_started",
+                        StepEvent::Completed { .. } => "step_completed",
+                    };
+                    self.publish(name.to_string(), &id, Some(&step));
+                    continue;
+                }
+                RunEvent::Preempted(id, preemption) => {
+                    self.preempted(&id, preemption);
+                    continue;
+                }
+            };
+            self.notify(event, &task_id);
+            if event == NotificationEvent::RunFailed
+                && self.approvals.as_ref().is_some_and(|a| a.failed_runs)
+            {
+                self.message_approvers(approvals::Reason::RunFailed, &task_id);
+            }
+        }
+    }
+}
+
+/// Name of `event` as in the configuration and the payloads, e.g. `task_submitted`.
+pub fn event_name(event: NotificationEvent) -> String {
+    serde_json::to_value(event)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Posts `body` to the webhook.
+async fn deliver(webhook: &WebhookConfig, body: &str) -> std::io::Result<()> {
+    let mut headers = Vec::new();
+    if let Some(secret) = &webhook.secret {
+        let signature = hmac_sha256(secret.as_bytes(), body.as_bytes());
+        headers.push((SIGNATURE_HEADER, format!("sha256={}", to_hex(&signature))));
+    }
+    // The content type only applies to templated payloads
+    let content_type = match webhook.template {
+        Some(_) => webhook.content_type.as_str(),
+        None => "application/json",
+    };
+    http::send("POST", &webhook.url, &headers, (content_type, body)).await
+}
+
+/// HMAC (RFC 2104) with SHA-256.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut block = [0; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::config::NotificationConfig;
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        // Test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            to_hex(&mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn templates_are_rendered_with_the_event() {
+        let payload = Payload {
+            event: NotificationEvent::ApprovalNeeded,
+            task_id: "pass.1",
+            station: "test",
+            time: "2026-01-01T10:00:00+00:00".into(),
+        };
+        assert_eq!(
+            payload.render(
+                r#"{"text": "{task_id} on {station}: {event} at {time}"}"#,
+                true
+            ),
+            r#"{"text": "pass.1 on test: approval_needed at 2026-01-01T10:00:00+00:00"}"#
+        );
+
+        // Values are escaped, and placeholders in them left alone
+        let payload = Payload {
+            task_id: "\"{station}\"\n",
+            ..payload
+        };
+        assert_eq!(
+            payload.render(r#"{"text": "{task_id} {unknown} {station"}"#, true),
+            r#"{"text": "\"{station}\"\n {unknown} {station"}"#
+        );
+        assert_eq!(payload.render("{task_id}", false), "\"{station}\"\n");
+        assert!(is_json("application/json; charset=utf-8"));
+        assert!(!is_json("text/plain"));
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_matching_events() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let config = Config {
+            station_name: "test".into(),
+            notifications: NotificationConfig {
+                webhooks: vec![WebhookConfig {
+                    url,
+                    events: vec![NotificationEvent::RunFailed],
+                    secret: Some("secret".into()),
+                    template: None,
+                    content_type: "application/json".into(),
+                }],
+                approvals: None,
+            },
+            ..Default::default()
+        };
+        let notifier = Notifier::new(&config);
+
+        // Not subscribed, so the first request received is the failure
+        notifier.notify(NotificationEvent::RunStarted, "ignored");
+        notifier.notify(NotificationEvent::RunFailed, "pass.1");
+
+        let (mut stream, _) = tokio::time::timeout(TIMEOUT, listener.accept())
+            .await
+            .unwrap()
+            .unwrap();
+        let mut buf = vec![0; 4096];
+        let mut request = String::new();
+        while !request.contains("\r\n\r\n") || !request.ends_with('}') {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.push_str(std::str::from_utf8(&buf[..n]).unwrap());
+        }
+        let flush = tokio::time::timeout(Duration::from_millis(100), notifier.flush());
+        assert!(flush.await.is_err(), "delivery should still be in progress");
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .await
+            .unwrap();
+        drop(stream);
+        tokio::time::timeout(TIMEOUT, notifier.flush())
+            .await
+            .unwrap();
+
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("POST /hook HTTP/1.1\r\n"));
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["event"], "run_failed");
+        assert_eq!(payload["task_id"], "pass.1");
+        assert_eq!(payload["station"], "test");
+
+        let signature = to_hex(&hmac_sha256(b"secret", body.as_bytes()));
+        assert!(head.contains(&format!("{SIGNATURE_HEADER}: sha256={signature}")));
+    }
+}
@@ -0,0 +1,184 @@
+//! Predicts the passes of satellites over the station, without the server.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, anyhow, bail};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use clap::Args;
+use serde::Serialize;
+
+use crate::api::predict::{ApiPass, to_api_pass, to_datetime};
+use crate::client::table;
+use crate::config::Config;
+
+#[derive(Args)]
+pub struct PredictArgs {
+    /// A file of TLEs or OMMs, or the NORAD ID or name of a satellite in `tle_path`. Defaults to
+    /// all the satellites in `tle_path`
+    #[arg(long, value_name = "FILE|NORAD")]
+    pub tle: Option<String>,
+    /// Start of the prediction (RFC3339). Defaults to now
+    #[arg(long)]
+    pub from: Option<DateTime<Utc>>,
+    /// End of the prediction (RFC3339). Defaults to 24 hours after the start
+    #[arg(long)]
+    pub to: Option<DateTime<Utc>>,
+    /// Minimum maximum elevation of the passes, in degrees
+    #[arg(long, default_value = "0")]
+    pub min_elevation: f64,
+    /// Print the passes as JSON, as returned by `GET /api/v1/predict/passes`
+    #[arg(long)]
+    pub json: bool,
+    /// Predict for this hosted station instead of the main one
+    #[arg(long)]
+    pub station: Option<String>,
+}
+
+/// A pass in the `--json` output.
+#[derive(Debug, Serialize)]
+struct Pass {
+    satellite: String,
+    norad_id: u64,
+    #[serde(flatten)]
+    pass: ApiPass,
+}
+
+/// Predicts the passes between `args.from` and `args.to`, returning what to print.
+pub fn run(args: PredictArgs, config: &Config) -> anyhow::Result<String> {
+    let gs = config
+        .ground_station
+        .as_ref()
+        .ok_or_else(|| anyhow!("ground station not configured"))?;
+    let from = args.from.unwrap_or_else(Utc::now);
+    let to = args.to.unwrap_or(from + Duration::hours(24));
+    if to <= from {
+        bail!("--to must be after --from");
+    }
+
+    let (predict_db, satellite) = match &args.tle {
+        Some(tle) if Path::new(tle).is_file() => {
+            let text = fs::read_to_string(tle).with_context(|| format!("failed to read {tle}"))?;
+            let mut predict_db = crate::api::empty_predict_db(config);
+            if predict_db.add(&text) == 0 {
+                bail!("no TLEs or OMMs found in {tle}");
+            }
+            (predict_db, None)
+        }
+        Some(norad) => {
+            let predict_db = crate::api::predict_db(config);
+            let (name, _) = predict_db.find(norad).ok_or_else(|| {
+                anyhow!(
+                    "satellite {norad} not found in {}",
+                    config.tle_path.display()
+                )
+            })?;
+            let name = name.clone();
+            (predict_db, Some(name))
+        }
+        None => (crate::api::predict_db(config), None),
+    };
+
+    let mut passes: Vec<_> = predict_db
+        .predict_passes_filtered(from, to, gs, None, |name, _| {
+            satellite.as_deref().is_none_or(|s| s == name)
+        })
+        .into_iter()
+        .flat_map(|(id, passes)| {
+            let name = id.as_str().to_string();
+            passes
+                .into_iter()
+                .map(move |predicted| (name.clone(), predicted))
+        })
+        .filter(|(_, predicted)| predicted.max_elevation.to_degrees() >= args.min_elevation)
+        .collect();
+    passes.sort_by_key(|(name, predicted)| {
+        (to_datetime(predicted.pass.interval().start()), name.clone())
+    });
+    let norad_id = |name: &str| predict_db.get(name).map_or(0, |sat| sat.elements.norad_id);
+
+    if args.json {
+        let passes: Vec<Pass> = passes
+            .iter()
+            .map(|(name, predicted)| Pass {
+                satellite: name.clone(),
+                norad_id: norad_id(name),
+                pass: to_api_pass(predicted, None),
+            })
+            .collect();
+        return Ok(format!("{}\n", serde_json::to_string_pretty(&passes)?));
+    }
+    let time = |t| to_datetime(t).to_rfc3339_opts(SecondsFormat::Secs, true);
+    Ok(table(
+        ["SATELLITE", "NORAD", "START", "TCA", "END", "MAX EL"],
+        passes.iter().map(|(name, predicted)| {
+            let interval = predicted.pass.interval();
+            [
+                name.clone(),
+                norad_id(name).to_string(),
+                time(interval.start()),
+                time(predicted.tca),
+                time(interval.end()),
+                format!("{:.1}", predicted.max_elevation.to_degrees()),
+            ]
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::TimeZone;
+    use lox_space::{
+        analysis::visibility::ElevationMask,
+        bodies::DynOrigin,
+        core::coords::LonLatAlt,
+        prelude::{GroundLocation, GroundStation},
+    };
+
+    use super::*;
+
+    #[test]
+    fn passes_are_listed_from_a_tle_file() {
+        let coords = LonLatAlt::from_degrees(13.4, 52.52, 100.0).unwrap();
+        let location = GroundLocation::try_new(coords, DynOrigin::Earth).unwrap();
+        let config = Config {
+            ground_station: Some(GroundStation::new(
+                "GS",
+                location,
+                ElevationMask::with_fixed_elevation(0.0),
+            )),
+            ..Default::default()
+        };
+        let tle = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle/nanoff_a.txt");
+        let from = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let args = |min_elevation, json| PredictArgs {
+            tle: Some(tle.to_string_lossy().into_owned()),
+            from: Some(from),
+            to: Some(from + Duration::hours(24)),
+            min_elevation,
+            json,
+            station: None,
+        };
+
+        let output = run(args(0.0, false), &config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("SATELLITE"));
+        assert!(lines.len() > 2, "{output}");
+        assert!(
+            lines[1..]
+                .iter()
+                .all(|l| l.starts_with("NanoFF A   58810  2026-01-1"))
+        );
+
+        let json = run(args(20.0, true), &config).unwrap();
+        let passes: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert!(!passes.is_empty() && passes.len() < lines.len() - 1);
+        for pass in &passes {
+            assert_eq!(pass["norad_id"], 58810);
+            assert!(pass["max_elevation"].as_f64().unwrap() >= 20.0);
+        }
+        assert!(passes.is_sorted_by_key(|p| p["start"].as_str().unwrap().to_string()));
+    }
+}
@@ -0,0 +1,198 @@
+//! Renders a task for the next pass of a satellite from a template, without going through the API.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use clap::Args;
+use lox_space::prelude::GroundStation;
+use tracing::info;
+
+use crate::api::predict::{pass_variables, to_datetime};
+use crate::config::Config;
+use crate::predict::PredictDb;
+use crate::task::format::Task;
+
+#[derive(Args)]
+pub struct PlanArgs {
+    /// NORAD ID or name of the satellite
+    #[arg(long)]
+    pub norad: String,
+    /// The template to render
+    #[arg(long, value_name = "FILE")]
+    pub template: PathBuf,
+    /// Where to write the task. Defaults to stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+    /// Minimum maximum elevation of the pass, in degrees
+    #[arg(long, default_value = "0")]
+    pub min_elevation: f64,
+    /// Find the first pass starting after this time (RFC3339) instead of now
+    #[arg(long)]
+    pub after: Option<DateTime<Utc>>,
+    /// How far ahead to look for a pass, in hours
+    #[arg(long, default_value = "48")]
+    pub within: i64,
+    /// Sets a variable (e.g. a frequency), overriding the template and the pass
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+    pub variables: Vec<(String, String)>,
+    /// Plan for this hosted station instead of the main one
+    #[arg(long)]
+    pub station: Option<String>,
+}
+
+/// Renders `args.template` for the next pass of the satellite, writing the task to `args.output`.
+pub fn run(args: PlanArgs, config: &Config) -> anyhow::Result<()> {
+    let content = fs::read_to_string(&args.template)
+        .with_context(|| format!("failed to read {}", args.template.display()))?;
+    let template = Task::from_yaml_str(&content)?;
+    let gs = config
+        .ground_station
+        .as_ref()
+        .ok_or_else(|| anyhow!("ground station not configured"))?;
+
+    let predict_db = crate::api::predict_db(config);
+    let (name, sat) = predict_db.find(&args.norad).ok_or_else(|| {
+        anyhow!(
+            "satellite {} not found in {:?}",
+            args.norad,
+            config.tle_path
+        )
+    })?;
+    let after = args.after.unwrap_or_else(Utc::now);
+    let (start, end) = next_pass(
+        &predict_db,
+        gs,
+        name,
+        after,
+        after + Duration::hours(args.within),
+        args.min_elevation,
+    )
+    .ok_or_else(|| anyhow!("no pass of {name} in the next {} hours", args.within))?;
+    info!(%name, %start, %end, "found pass");
+
+    let mut variables = template.variables;
+    variables.extend(pass_variables(name, sat, start, end, &config.predict));
+    variables.extend(args.variables);
+    let mut task = Task::new(variables, template.steps, template.cleanup);
+    task.template = args
+        .template
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned());
+    let yaml = serde_yaml::to_string(&task)?;
+
+    match &args.output {
+        Some(path) => {
+            fs::write(path, yaml).with_context(|| format!("failed to write {}", path.display()))?
+        }
+        None => print!("{yaml}"),
+    }
+    Ok(())
+}
+
+/// Returns the start and end of the first pass of satellite `name` starting between `after` and
+/// `before`, reaching at least `min_elevation` degrees.
+fn next_pass(
+    predict_db: &PredictDb,
+    gs: &GroundStation,
+    name: &str,
+    after: DateTime<Utc>,
+    before: DateTime<Utc>,
+    min_elevation: f64,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    predict_db
+        .predict_passes_filtered(after, before, gs, None, |n, _| n == name)
+        .into_values()
+        .flatten()
+        .filter(|predicted| predicted.max_elevation.to_degrees() >= min_elevation)
+        .map(|predicted| {
+            let interval = predicted.pass.interval();
+            (to_datetime(interval.start()), to_datetime(interval.end()))
+        })
+        .filter(|(start, _)| *start > after)
+        .min_by_key(|(start, _)| *start)
+}
+
+fn parse_variable(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {s}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use lox_space::{
+        analysis::visibility::ElevationMask, bodies::DynOrigin, core::coords::LonLatAlt,
+        prelude::GroundLocation,
+    };
+
+    use super::*;
+
+    #[test]
+    fn renders_the_next_pass() {
+        let tmp = tempfile::tempdir().unwrap();
+        let template = tmp.path().join("uhf.yml");
+        fs::write(
+            &template,
+            "variables:\n  downlink: \"437.5 MHz\"\nsteps:\n  - cmd: \"echo $satellite\"\n",
+        )
+        .unwrap();
+
+        let tle_path = tmp.path().join("tle");
+        fs::create_dir(&tle_path).unwrap();
+        fs::copy(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle/nanoff_a.txt"),
+            tle_path.join("nanoff_a.txt"),
+        )
+        .unwrap();
+
+        let coords = LonLatAlt::from_degrees(13.4, 52.52, 100.0).unwrap();
+        let location = GroundLocation::try_new(coords, DynOrigin::Earth).unwrap();
+        let config = Config {
+            tle_path,
+            ground_station: Some(GroundStation::new(
+                "GS",
+                location,
+                ElevationMask::with_fixed_elevation(0.0),
+            )),
+            ..Default::default()
+        };
+        let after = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let args = PlanArgs {
+            norad: "58810".into(),
+            template,
+            output: Some(tmp.path().join("sched.yml")),
+            min_elevation: 20.0,
+            after: Some(after),
+            within: 48,
+            variables: vec![parse_variable("uplink=145.9 MHz").unwrap()],
+            station: None,
+        };
+        run(args, &config).unwrap();
+
+        let task = Task::from_yaml_str(&fs::read_to_string(tmp.path().join("sched.yml")).unwrap())
+            .unwrap();
+        let start = task.get_time_variable("start").unwrap();
+        let end = task.get_time_variable("end").unwrap();
+        assert!(start > after && start < after + Duration::hours(48));
+        assert!(end > start);
+        assert_eq!(task.variables["norad_id"], "58810");
+        assert_eq!(task.variables["satellite"], "NanoFF A");
+        assert_eq!(task.variables["downlink"], "437.5 MHz");
+        assert_eq!(task.variables["uplink"], "145.9 MHz");
+        assert!(task.variables["tle"].contains("58810"));
+        assert_eq!(task.template.as_deref(), Some("uhf"));
+        assert_eq!(task.steps.len(), 1);
+    }
+
+    #[test]
+    fn variables_are_parsed() {
+        assert_eq!(
+            parse_variable("freq=437.5e6=x"),
+            Ok(("freq".into(), "437.5e6=x".into()))
+        );
+        assert!(parse_variable("freq").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Passes of each satellite over each ground station, computed ahead of time so that predictions
+//! for a full catalog do not have to be computed again on every request.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use lox_space::{
+    prelude::GroundStation,
+    time::{Time, time_scales::DynTimeScale, utc::transformations::ToUtc},
+};
+
+use super::PredictedPass;
+
+/// Passes of a satellite over a ground station between `start` and `end`.
+struct CachedPasses {
+    /// Epoch of the elements the passes were computed from.
+    epoch: NaiveDateTime,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    passes: Vec<PredictedPass>,
+}
+
+/// Cached passes, keyed by the ID of the ground station and the NORAD ID of the satellite.
+///
+/// Entries are only used while they were computed from the elements loaded for the satellite, so
+/// loading newer elements invalidates them.
+#[derive(Default)]
+pub(super) struct PassCache {
+    entries: HashMap<(String, u64), CachedPasses>,
+}
+
+impl PassCache {
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// The cached passes of `norad_id` over `gs` overlapping `start`..`end`, if the cached ones
+    /// were computed from elements of `epoch` and cover that interval.
+    pub(super) fn get(
+        &self,
+        gs: &GroundStation,
+        norad_id: u64,
+        epoch: NaiveDateTime,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Option<Vec<PredictedPass>> {
+        let cached = self.entries.get(&(gs.id().to_string(), norad_id))?;
+        if cached.epoch != epoch || start < cached.start || cached.end < end {
+            return None;
+        }
+        let passes = cached
+            .passes
+            .iter()
+            .filter(|p| {
+                to_utc(p.pass.interval().start()) < end && start < to_utc(p.pass.interval().end())
+            })
+            .cloned()
+            .collect();
+        Some(passes)
+    }
+
+    /// Whether the cached passes of `norad_id` over `gs` were computed from elements of `epoch`
+    /// and cover `start`..`end`.
+    pub(super) fn covers(
+        &self,
+        gs: &GroundStation,
+        norad_id: u64,
+        epoch: NaiveDateTime,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> bool {
+        self.entries
+            .get(&(gs.id().to_string(), norad_id))
+            .is_some_and(|c| c.epoch == epoch && c.start <= start && end <= c.end)
+    }
+
+    pub(super) fn insert(
+        &mut self,
+        gs: &GroundStation,
+        norad_id: u64,
+        epoch: NaiveDateTime,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        passes: Vec<PredictedPass>,
+    ) {
+        let cached = CachedPasses {
+            epoch,
+            start,
+            end,
+            passes,
+        };
+        self.entries.insert((gs.id().to_string(), norad_id), cached);
+    }
+
+    /// Keeps only the entries for which `keep` returns true, given the NORAD ID and epoch.
+    pub(super) fn retain(&mut self, keep: impl Fn(u64, NaiveDateTime) -> bool) {
+        self.entries
+            .retain(|(_, norad_id), cached| keep(*norad_id, cached.epoch));
+    }
+}
+
+fn to_utc(time: Time<DynTimeScale>) -> DateTime<Utc> {
+    DateTime::<Utc>::try_from(time.to_utc()).unwrap()
+}
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, fs, io, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 use chrono::{DateTime, Utc};
 use lox_space::{
@@ -8,29 +12,238 @@ use lox_space::{
     },
     bodies::DynOrigin,
     core::coords::LonLatAlt,
-    frames::DynFrame,
+    frames::{DynFrame, providers::DefaultRotationProvider},
     orbits::{
-        events::{EventsToIntervals, IntervalDetector, RootFindingDetector},
+        ground::Observables,
         orbits::DynTrajectory,
         propagators::{
             OrbitSource,
             sgp4::{Sgp4, Sgp4Error},
         },
     },
-    prelude::{
-        Cartesian, GroundStation, Interval, Orbit, Pass, Propagator, Spacecraft, Tai, TimeDelta,
-    },
-    time::{Time, intervals::TimeInterval, time_scales::DynTimeScale},
+    prelude::{Cartesian, GroundStation, Interval, Orbit, Propagator, Spacecraft, Tai, TimeDelta},
+    time::{Time, intervals::TimeInterval, time_scales::DynTimeScale, utc::transformations::ToUtc},
 };
+use serde::{Deserialize, Serialize};
 use sgp4::Elements;
 use tracing::{info, warn};
 
+use cache::PassCache;
 use utils::{CachedRotationProvider, SimpleElevationDetector};
 
+pub use search::PassSearchSteps;
+
+mod cache;
+pub mod moon;
+mod search;
+pub mod sun;
+pub mod tle;
 mod utils;
 
+#[derive(Default)]
 pub struct PredictDb {
-    spacecraft: HashMap<String, Spacecraft>,
+    spacecraft: HashMap<String, Satellite>,
+    thresholds: ElevationThresholds,
+    steps: PassSearchSteps,
+    groups: SatelliteGroups,
+    solar_outage_angle: Option<f64>,
+    /// Passes computed ahead of time, see [`PredictDb::precompute_passes`]
+    passes: PassCache,
+}
+
+/// Named groups of satellites (e.g. "noaa", "cubesats", "priority"), each listing satellite names
+/// or NORAD IDs.
+pub type SatelliteGroups = HashMap<String, Vec<String>>;
+
+/// A spacecraft loaded into the [`PredictDb`], along with the elements it was created from.
+pub struct Satellite {
+    pub spacecraft: Spacecraft,
+    pub elements: Elements,
+    /// The groups this satellite belongs to: the stem of the file it was loaded from, followed by
+    /// the configured groups listing it.
+    pub groups: Vec<String>,
+    /// The file this satellite was loaded from, if any.
+    pub source: Option<PathBuf>,
+    /// The TLE lines the satellite was loaded from, if it was loaded from a TLE.
+    pub tle: Option<String>,
+}
+
+impl Satellite {
+    pub fn in_group(&self, group: &str) -> bool {
+        self.groups.iter().any(|g| g == group)
+    }
+
+    /// Returns the orbit information for this satellite as text: the original TLE if available,
+    /// otherwise the elements as CCSDS OMM (JSON).
+    pub fn orbit_text(&self) -> String {
+        self.tle.clone().unwrap_or_else(|| {
+            serde_json::to_string(&self.elements).expect("elements should serialize")
+        })
+    }
+
+    pub fn regime(&self) -> OrbitRegime {
+        OrbitRegime::from_elements(&self.elements)
+    }
+
+    /// Returns how old the elements are at `time`.
+    pub fn element_age(&self, time: DateTime<Utc>) -> chrono::Duration {
+        time - self.elements.datetime.and_utc()
+    }
+}
+
+/// A predicted pass of a satellite over a ground station.
+#[derive(Clone)]
+pub struct PredictedPass {
+    /// The pass while visible through the station's elevation mask (horizon profile or minimum
+    /// elevation).
+    pub pass: DynPass,
+    /// Maximum elevation (radians) while visible through the mask.
+    pub max_elevation: f64,
+    /// Start and end of the pass above the ideal 0° horizon.
+    pub geometric: TimeInterval<DynTimeScale>,
+    /// Maximum elevation (radians) above the ideal horizon.
+    pub geometric_max_elevation: f64,
+    /// Time of closest approach, i.e. of maximum elevation.
+    pub tca: Time<DynTimeScale>,
+    /// Slant range (meters) at TCA.
+    pub range_at_tca: f64,
+    /// Largest magnitude of the range rate (m/s) while visible.
+    pub max_range_rate: f64,
+    /// Largest magnitude of the rate of change of the range rate (m/s²) while visible.
+    pub max_range_acceleration: f64,
+    /// Intervals during which the satellite is close to the Sun as seen from the station, see
+    /// [`PredictDb::set_solar_outage_angle`].
+    pub solar_outages: Vec<TimeInterval<DynTimeScale>>,
+}
+
+const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+
+/// Returns the Doppler shift (Hz) of a signal at `frequency` (Hz) received from an object moving
+/// away at `range_rate` (m/s).
+pub fn doppler_shift(frequency: f64, range_rate: f64) -> f64 {
+    -frequency * range_rate / SPEED_OF_LIGHT
+}
+
+impl PredictedPass {
+    /// Returns the largest Doppler shift (Hz) of a signal at `frequency` (Hz) during the pass.
+    pub fn max_doppler(&self, frequency: f64) -> f64 {
+        frequency * self.max_range_rate / SPEED_OF_LIGHT
+    }
+
+    /// Returns the largest Doppler rate (Hz/s) of a signal at `frequency` (Hz) during the pass.
+    pub fn max_doppler_rate(&self, frequency: f64) -> f64 {
+        frequency * self.max_range_acceleration / SPEED_OF_LIGHT
+    }
+}
+
+/// The orbit regime of a satellite, derived from its elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrbitRegime {
+    /// Low Earth orbit: more than 11.25 revolutions per day.
+    Leo,
+    /// Medium Earth orbit, between LEO and GEO.
+    Meo,
+    /// Geosynchronous, near-circular orbit.
+    Geo,
+    /// Highly elliptical orbit (e.g. Molniya).
+    Heo,
+}
+
+impl OrbitRegime {
+    pub fn from_elements(el: &Elements) -> Self {
+        if el.eccentricity > 0.25 {
+            OrbitRegime::Heo
+        } else if el.mean_motion >= 11.25 {
+            OrbitRegime::Leo
+        } else if (0.99..=1.01).contains(&el.mean_motion) && el.eccentricity < 0.01 {
+            OrbitRegime::Geo
+        } else {
+            OrbitRegime::Meo
+        }
+    }
+}
+
+/// Look angles of a geostationary satellite from a ground station over a prediction window.
+///
+/// Geostationary satellites don't have passes: they are either visible or not, at nearly constant
+/// look angles.
+pub struct GeoVisibility {
+    /// Observables at the start of the window.
+    pub observables: Observables,
+    /// Lowest elevation (radians) during the window.
+    pub min_elevation: f64,
+    /// Highest elevation (radians) during the window.
+    pub max_elevation: f64,
+    /// Whether the satellite is above the station's elevation mask during the whole window.
+    pub visible: bool,
+    /// Intervals during which the satellite is close to the Sun as seen from the station, see
+    /// [`PredictDb::set_solar_outage_angle`].
+    pub solar_outages: Vec<TimeInterval<DynTimeScale>>,
+}
+
+/// A body whose disk a satellite can be seen crossing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitBody {
+    Sun,
+    Moon,
+}
+
+impl TransitBody {
+    /// Returns the azimuth and elevation, and the angular radius, of the body as seen from
+    /// `location` at `time`, in radians.
+    fn look_angles(self, time: DateTime<Utc>, location: &LonLatAlt) -> ((f64, f64), f64) {
+        match self {
+            TransitBody::Sun => (sun::look_angles(time, location), sun::angular_radius(time)),
+            TransitBody::Moon => (
+                moon::look_angles(time, location),
+                moon::angular_radius(time, location),
+            ),
+        }
+    }
+}
+
+/// A transit of a satellite across the disk of the Sun or the Moon, as seen from a station.
+pub struct Transit {
+    pub body: TransitBody,
+    /// Time of closest approach to the center of the disk.
+    pub time: Time<DynTimeScale>,
+    /// Time (seconds) the satellite spends in front of the disk.
+    pub duration: f64,
+    /// Smallest angular distance (radians) between the satellite and the center of the disk.
+    pub centerline_distance: f64,
+    /// Angular radius (radians) of the disk.
+    pub body_radius: f64,
+    /// Observables of the satellite at `time`.
+    pub observables: Observables,
+}
+
+/// Transits are searched for while the satellite is within this angle (degrees) of the body.
+const TRANSIT_SEARCH_ANGLE: f64 = 10.0;
+
+/// Minimum elevation overrides (in degrees) applied when searching for passes.
+///
+/// A satellite override (keyed by name or NORAD ID) takes precedence over a group override, and
+/// the group of the file a satellite was loaded from takes precedence over configured groups.
+/// If neither matches, the ground station's elevation mask is used.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ElevationThresholds {
+    #[serde(default)]
+    pub groups: HashMap<String, f64>,
+    #[serde(default)]
+    pub satellites: HashMap<String, f64>,
+}
+
+impl ElevationThresholds {
+    /// Returns the minimum elevation (degrees) configured for `sat`, if any.
+    pub fn min_elevation(&self, name: &str, sat: &Satellite) -> Option<f64> {
+        self.satellites
+            .get(name)
+            .or_else(|| self.satellites.get(&sat.elements.norad_id.to_string()))
+            .or_else(|| sat.groups.iter().find_map(|g| self.groups.get(g)))
+            .copied()
+    }
 }
 
 #[derive(thiserror::Error, Clone, Debug)]
@@ -39,79 +252,201 @@ pub enum Error {
     UnsupportedOrbitSource(String),
     #[error("SGP4 error: {0}")]
     Sgp4(String),
+    #[error("frame transformation error: {0}")]
+    Frame(String),
 }
 
 impl PredictDb {
     pub fn new() -> Self {
-        Self {
-            spacecraft: HashMap::new(),
-        }
+        Self::default()
     }
 
     pub fn len(&self) -> usize {
         self.spacecraft.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.spacecraft.is_empty()
+    }
+
+    pub fn set_elevation_thresholds(&mut self, thresholds: ElevationThresholds) {
+        self.thresholds = thresholds;
+        self.passes.clear();
+    }
+
+    pub fn set_search_steps(&mut self, steps: PassSearchSteps) {
+        self.steps = steps;
+        self.passes.clear();
+    }
+
+    /// Enables prediction of solar outages: times when the satellite is within `angle` degrees of
+    /// the Sun as seen from the station, and a dish pointed at it loses lock.
+    pub fn set_solar_outage_angle(&mut self, angle: Option<f64>) {
+        self.solar_outage_angle = angle;
+        self.passes.clear();
+    }
+
+    pub fn set_groups(&mut self, groups: SatelliteGroups) {
+        self.groups = groups;
+        for (name, sat) in self.spacecraft.iter_mut() {
+            sat.groups = resolve_groups(&self.groups, name, &sat.elements, sat.source.as_deref());
+        }
+        self.passes.clear();
+    }
+
     pub fn contains(&self, name: &str) -> bool {
         self.spacecraft.contains_key(name)
     }
 
     pub fn first(&self) -> Option<(&String, &Spacecraft)> {
-        self.spacecraft.iter().next()
+        self.spacecraft
+            .iter()
+            .next()
+            .map(|(name, sat)| (name, &sat.spacecraft))
     }
 
-    fn add_from_elements(&mut self, el: &Elements) -> Result<(), Sgp4Error> {
-        let sgp4 = Sgp4::new(el.clone())?;
-        let source = OrbitSource::Sgp4(sgp4);
+    /// Iterates over all loaded satellites and their names.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Satellite)> {
+        self.spacecraft.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Satellite> {
+        self.spacecraft.get(name)
+    }
+
+    /// Finds a satellite by name or NORAD ID.
+    pub fn find(&self, name_or_id: &str) -> Option<(&String, &Satellite)> {
+        self.spacecraft.get_key_value(name_or_id).or_else(|| {
+            self.spacecraft
+                .iter()
+                .find(|(_, sat)| sat.elements.norad_id.to_string() == name_or_id)
+        })
+    }
+
+    /// Adds a satellite from `el`, returning whether it was added.
+    ///
+    /// If a satellite with the same NORAD ID is already loaded, only the one with the newest
+    /// epoch is kept.
+    fn add_from_elements(
+        &mut self,
+        el: &Elements,
+        source: Option<&Path>,
+        tle: Option<String>,
+    ) -> Result<bool, Sgp4Error> {
         let name = el
             .object_name
             .clone()
             .unwrap_or(format!("ID {}", el.norad_id));
 
-        info!(?name, "loaded spacecraft (SGP4)");
-        self.spacecraft
-            .insert(name.clone(), Spacecraft::new(name.clone(), source));
+        if let Some((existing, sat)) = self
+            .spacecraft
+            .iter()
+            .find(|(_, sat)| sat.elements.norad_id == el.norad_id)
+        {
+            if sat.elements.datetime >= el.datetime {
+                info!(
+                    ?name,
+                    ?existing,
+                    norad_id = el.norad_id,
+                    "ignoring older elements"
+                );
+                return Ok(false);
+            }
+            info!(
+                ?name,
+                ?existing,
+                norad_id = el.norad_id,
+                "replacing older elements"
+            );
+            let existing = existing.clone();
+            self.spacecraft.remove(&existing);
+        }
 
-        Ok(())
+        let sgp4 = Sgp4::new(el.clone())?;
+        let source_type = OrbitSource::Sgp4(sgp4);
+        let groups = resolve_groups(&self.groups, &name, el, source);
+
+        info!(?name, ?groups, "loaded spacecraft (SGP4)");
+        self.spacecraft.insert(
+            name.clone(),
+            Satellite {
+                spacecraft: Spacecraft::new(name.clone(), source_type),
+                elements: el.clone(),
+                groups,
+                source: source.map(Path::to_path_buf),
+                tle,
+            },
+        );
+
+        Ok(true)
     }
 
     pub fn add_tle(&mut self, text: &str) -> usize {
-        sgp4::parse_3les(text)
-            .inspect_err(|e| warn!(?e, "error parsing TLE file"))
-            .unwrap_or_default()
+        self.add_tle_from(text, None)
+    }
+
+    fn add_tle_from(&mut self, text: &str, source: Option<&Path>) -> usize {
+        tle::parse(text)
             .into_iter()
-            .filter_map(|el| match self.add_from_elements(&el) {
-                Ok(_) => Some(()),
-                Err(e) => {
-                    warn!(?e, "error in elements");
-                    None
+            .filter_map(|parsed| {
+                let parsed = parsed
+                    .inspect_err(|e| warn!(?e, "error parsing TLE"))
+                    .ok()?;
+                match self.add_from_elements(&parsed.elements, source, Some(parsed.text)) {
+                    Ok(added) => added.then_some(()),
+                    Err(e) => {
+                        warn!(?e, "error in elements");
+                        None
+                    }
                 }
             })
             .count()
     }
 
     pub fn add_omm(&mut self, omm: &str) -> usize {
-        match serde_json::from_str(omm) {
-            Ok(el) => {
-                if let Err(e) = self.add_from_elements(&el) {
-                    warn!(?e, "error in elements");
-                    0
-                } else {
-                    1
-                }
-            }
+        self.add_omm_from(omm, None)
+    }
+
+    /// Adds the element sets in `omm`: a single CCSDS OMM (JSON) object, or an array of them as
+    /// served by CelesTrak (`FORMAT=json`).
+    fn add_omm_from(&mut self, omm: &str, source: Option<&Path>) -> usize {
+        let parsed = if omm.trim_start().starts_with('[') {
+            serde_json::from_str::<Vec<Elements>>(omm)
+        } else {
+            serde_json::from_str::<Elements>(omm).map(|el| vec![el])
+        };
+        let elements = match parsed {
+            Ok(elements) => elements,
             Err(e) => {
                 warn!(?e, "error parsing CCSDS OMM");
-                0
+                return 0;
             }
-        }
+        };
+
+        elements
+            .iter()
+            .filter(|el| match self.add_from_elements(el, source, None) {
+                Ok(added) => added,
+                Err(e) => {
+                    warn!(?e, "error in elements");
+                    false
+                }
+            })
+            .count()
     }
 
+    /// Loads all element files in `dir`. Files with a `.json` extension are read as CCSDS OMM,
+    /// anything else as TLEs.
     pub fn add_tles(&mut self, dir: &PathBuf) -> Result<usize, io::Error> {
         let mut added = 0;
         for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
             let path = entry.path();
-            added += self.add_tle(&fs::read_to_string(path)?);
+            let text = fs::read_to_string(&path)?;
+            added += if path.extension().is_some_and(|ext| ext == "json") {
+                self.add_omm_from(&text, Some(&path))
+            } else {
+                self.add_tle_from(&text, Some(&path))
+            };
         }
 
         Ok(added)
@@ -143,6 +478,20 @@ impl PredictDb {
         }
     }
 
+    /// Returns the azimuth, elevation, range and range rate of `sc` as seen from `gs` at `time`.
+    pub fn observables_at(
+        &self,
+        time: DateTime<Utc>,
+        sc: &Spacecraft,
+        gs: &GroundStation,
+    ) -> Result<Observables, Error> {
+        let state = self
+            .state_at(time, sc)?
+            .try_to_frame(gs.body_fixed_frame(), &DefaultRotationProvider)
+            .map_err(|e| Error::Frame(e.to_string()))?;
+        Ok(gs.location().observables_dyn(state))
+    }
+
     pub fn predict(
         &self,
         interval: TimeInterval<Tai>,
@@ -167,6 +516,19 @@ impl PredictDb {
         end: DateTime<Utc>,
         target_frame: DynFrame,
         provider: Option<&mut CachedRotationProvider>,
+    ) -> HashMap<AssetId, DynTrajectory> {
+        self.predict_trajectories_filtered(start, end, target_frame, provider, |_, _| true)
+    }
+
+    /// Like [`PredictDb::predict_trajectories`], but only for satellites for which `filter`
+    /// returns true.
+    pub fn predict_trajectories_filtered(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        target_frame: DynFrame,
+        provider: Option<&mut CachedRotationProvider>,
+        filter: impl Fn(&str, &Satellite) -> bool,
     ) -> HashMap<AssetId, DynTrajectory> {
         let mut default_provider = CachedRotationProvider::new();
         let provider = provider.unwrap_or(&mut default_provider);
@@ -174,7 +536,9 @@ impl PredictDb {
         let interval = Interval::new(start.into(), end.into());
 
         self.spacecraft
-            .values()
+            .iter()
+            .filter(|(name, sat)| filter(name, sat))
+            .map(|(_, sat)| &sat.spacecraft)
             .filter_map(|sc| match self.predict(interval, sc) {
                 Ok(trajectory) => {
                     // Valid trajectory
@@ -202,37 +566,121 @@ impl PredictDb {
         end: DateTime<Utc>,
         gs: &GroundStation,
         provider: Option<&mut CachedRotationProvider>,
-    ) -> HashMap<AssetId, Vec<DynPass>> {
+    ) -> HashMap<AssetId, Vec<PredictedPass>> {
+        self.predict_passes_filtered(start, end, gs, provider, |_, _| true)
+    }
+
+    /// Like [`PredictDb::predict_passes`], but only for satellites for which `filter` returns
+    /// true.
+    pub fn predict_passes_filtered(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        gs: &GroundStation,
+        provider: Option<&mut CachedRotationProvider>,
+        filter: impl Fn(&str, &Satellite) -> bool,
+    ) -> HashMap<AssetId, Vec<PredictedPass>> {
         let tai_start: Time<Tai> = start.into();
         let tai_end: Time<Tai> = end.into();
         let interval = Interval::new(tai_start, tai_end);
         let frame = gs.body_fixed_frame();
+        let horizon = ElevationMask::with_fixed_elevation(0.0);
+
+        // Geostationary satellites don't have passes, see `predict_geostationary`
+        let filter =
+            |name: &str, sat: &Satellite| sat.regime() != OrbitRegime::Geo && filter(name, sat);
 
-        self.predict_trajectories(start, end, frame, provider)
+        self.predict_trajectories_filtered(start, end, frame, provider, filter)
             .iter()
             .map(|(sc, trajectory)| {
-                let detector = EventsToIntervals::new(RootFindingDetector::new(
-                    SimpleElevationDetector { gs, trajectory },
-                    TimeDelta::from_seconds(60),
-                ));
-
-                let passes: Vec<Pass<_>> = detector
-                    .detect(interval)
-                    .unwrap()
+                let mask = self.elevation_mask(sc.as_str(), gs);
+                let coarse = self.spacecraft.get(sc.as_str()).map_or(60.0, |sat| {
+                    self.steps.coarse_step_for(sat.elements.mean_motion)
+                });
+                let masked = SimpleElevationDetector {
+                    gs,
+                    trajectory,
+                    mask: &mask,
+                };
+                let geometric = SimpleElevationDetector {
+                    gs,
+                    trajectory,
+                    mask: &horizon,
+                };
+                let elevation = |t| geometric.margin(t);
+
+                let windows = search::find_windows(
+                    |t| masked.margin(t),
+                    interval,
+                    coarse,
+                    self.steps.fine_step,
+                );
+                let geometric_windows = if windows.is_empty() || mask == horizon {
+                    windows.clone()
+                } else {
+                    search::find_windows(elevation, interval, coarse, self.steps.fine_step)
+                };
+
+                let passes = windows
                     .into_iter()
-                    .filter_map(|pass_interval| {
-                        DynPass::from_interval(
-                            // todo: pass_interval.into_dyn()
-                            Interval::new(
-                                pass_interval.start().into_dyn(),
-                                pass_interval.end().into_dyn(),
-                            ),
-                            TimeDelta::from_seconds(20),
+                    .filter_map(|window| {
+                        // If the mask is below the horizon, the satellite can be visible before
+                        // it rises above it
+                        let geometric_window = geometric_windows
+                            .iter()
+                            .find(|g| g.start() <= window.end() && window.start() <= g.end())
+                            .copied()
+                            .unwrap_or(window);
+                        let (tca, geometric_max_elevation) =
+                            search::find_maximum(elevation, geometric_window);
+                        // Range rate is largest at the edges of the pass, and its rate of change
+                        // at TCA
+                        let tca_visible = window.start() <= tca && tca <= window.end();
+                        let max_elevation = if tca_visible {
+                            geometric_max_elevation
+                        } else {
+                            elevation(window.start()).max(elevation(window.end()))
+                        };
+                        let max_range_rate = [window.start(), window.end()]
+                            .map(|t| geometric.observables(t).range_rate().abs())
+                            .into_iter()
+                            .fold(0.0, f64::max);
+                        let max_range_acceleration = if tca_visible {
+                            vec![tca]
+                        } else {
+                            vec![window.start(), window.end()]
+                        }
+                        .into_iter()
+                        .map(|t| geometric.range_acceleration(t).abs())
+                        .fold(0.0, f64::max);
+
+                        let pass = DynPass::from_interval(
+                            Interval::new(window.start().into_dyn(), window.end().into_dyn()),
+                            TimeDelta::from_seconds_f64(self.steps.sample_step),
                             gs.location(),
-                            &ElevationMask::with_fixed_elevation(0.0),
+                            &mask,
                             trajectory,
                             gs.body_fixed_frame(),
-                        )
+                        )?;
+
+                        Some(PredictedPass {
+                            pass,
+                            max_elevation,
+                            geometric: Interval::new(
+                                geometric_window.start().into_dyn(),
+                                geometric_window.end().into_dyn(),
+                            ),
+                            geometric_max_elevation,
+                            tca: tca.into_dyn(),
+                            range_at_tca: geometric.observables(tca).range(),
+                            max_range_rate,
+                            max_range_acceleration,
+                            solar_outages: self.solar_outages(
+                                &masked,
+                                window,
+                                self.steps.fine_step,
+                            ),
+                        })
                     })
                     .collect();
 
@@ -241,15 +689,315 @@ impl PredictDb {
             .collect()
     }
 
+    /// Like [`PredictDb::predict_passes_filtered`], but using the passes computed ahead of time
+    /// by [`PredictDb::precompute_passes`] where they cover `start`..`end`. Only the passes of the
+    /// other satellites are computed.
+    ///
+    /// Cached passes in progress at `start` or `end` are returned whole.
+    pub fn cached_passes_filtered(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        gs: &GroundStation,
+        filter: impl Fn(&str, &Satellite) -> bool,
+    ) -> HashMap<AssetId, Vec<PredictedPass>> {
+        let mut passes = HashMap::new();
+        let mut missing = HashSet::new();
+        for (name, sat) in &self.spacecraft {
+            if sat.regime() == OrbitRegime::Geo || !filter(name, sat) {
+                continue;
+            }
+            let el = &sat.elements;
+            match self.passes.get(gs, el.norad_id, el.datetime, start, end) {
+                Some(cached) => {
+                    passes.insert(sat.spacecraft.id().clone(), cached);
+                }
+                None => {
+                    missing.insert(name.as_str());
+                }
+            }
+        }
+        if !missing.is_empty() {
+            passes.extend(
+                self.predict_passes_filtered(start, end, gs, None, |name, _| {
+                    missing.contains(name)
+                }),
+            );
+        }
+        passes
+    }
+
+    /// Returns the names of the satellites whose passes over `gs` between `start` and `end` have
+    /// not been computed ahead of time from their current elements.
+    pub fn stale_passes(
+        &self,
+        gs: &GroundStation,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<String> {
+        self.spacecraft
+            .iter()
+            .filter(|(_, sat)| {
+                let el = &sat.elements;
+                sat.regime() != OrbitRegime::Geo
+                    && !self.passes.covers(gs, el.norad_id, el.datetime, start, end)
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Computes the passes of satellite `name` over `gs` between `start` and `end` ahead of time,
+    /// for [`PredictDb::cached_passes_filtered`]. They are kept until newer elements of the
+    /// satellite are loaded or the prediction settings change.
+    pub fn precompute_passes(
+        &mut self,
+        name: &str,
+        gs: &GroundStation,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) {
+        let Some(sat) = self.spacecraft.get(name) else {
+            return;
+        };
+        let (norad_id, epoch) = (sat.elements.norad_id, sat.elements.datetime);
+        let passes = self
+            .predict_passes_filtered(start, end, gs, None, |n, _| n == name)
+            .into_values()
+            .next()
+            .unwrap_or_default();
+        self.passes.insert(gs, norad_id, epoch, start, end, passes);
+    }
+
+    /// Takes over the passes `previous` computed ahead of time for the satellites whose elements
+    /// did not change, e.g. after reloading the TLEs with the same settings.
+    pub fn keep_passes(&mut self, previous: PredictDb) {
+        let epochs: HashMap<u64, _> = self
+            .spacecraft
+            .values()
+            .map(|sat| (sat.elements.norad_id, sat.elements.datetime))
+            .collect();
+        self.passes = previous.passes;
+        self.passes
+            .retain(|norad_id, epoch| epochs.get(&norad_id) == Some(&epoch));
+    }
+
+    /// Computes the look angles and visibility of the geostationary satellites for which `filter`
+    /// returns true.
+    pub fn predict_geostationary(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        gs: &GroundStation,
+        provider: Option<&mut CachedRotationProvider>,
+        filter: impl Fn(&str, &Satellite) -> bool,
+    ) -> HashMap<AssetId, GeoVisibility> {
+        let tai_start: Time<Tai> = start.into();
+        let tai_end: Time<Tai> = end.into();
+        let filter =
+            |name: &str, sat: &Satellite| sat.regime() == OrbitRegime::Geo && filter(name, sat);
+
+        self.predict_trajectories_filtered(start, end, gs.body_fixed_frame(), provider, filter)
+            .iter()
+            .map(|(sc, trajectory)| {
+                let mask = self.elevation_mask(sc.as_str(), gs);
+                let detector = SimpleElevationDetector {
+                    gs,
+                    trajectory,
+                    mask: &mask,
+                };
+                let step = self.spacecraft.get(sc.as_str()).map_or(900.0, |sat| {
+                    self.steps.coarse_step_for(sat.elements.mean_motion)
+                });
+
+                let mut samples = vec![];
+                let mut t = tai_start + TimeDelta::from_seconds_f64(step);
+                while t < tai_end {
+                    samples.push(t);
+                    t = t + TimeDelta::from_seconds_f64(step);
+                }
+                samples.push(tai_end);
+
+                let observables = detector.observables(tai_start);
+                let mut visibility = GeoVisibility {
+                    min_elevation: observables.elevation(),
+                    max_elevation: observables.elevation(),
+                    observables,
+                    visible: true,
+                    solar_outages: vec![],
+                };
+                for t in samples {
+                    let obs = detector.observables(t);
+                    visibility.min_elevation = visibility.min_elevation.min(obs.elevation());
+                    visibility.max_elevation = visibility.max_elevation.max(obs.elevation());
+                    visibility.visible &= obs.elevation() >= mask.min_elevation(obs.azimuth());
+                }
+                visibility.solar_outages =
+                    self.solar_outages(&detector, Interval::new(tai_start, tai_end), 60.0);
+
+                (sc.clone(), visibility)
+            })
+            .collect()
+    }
+
+    /// Predicts the transits of the satellites for which `filter` returns true across the disks of
+    /// `bodies`, while the satellites are visible from `gs`.
+    pub fn predict_transits_filtered(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        gs: &GroundStation,
+        bodies: &[TransitBody],
+        filter: impl Fn(&str, &Satellite) -> bool,
+    ) -> HashMap<AssetId, Vec<Transit>> {
+        let interval = Interval::new(start.into(), end.into());
+        let location = gs.location().coordinates();
+        let filter =
+            |name: &str, sat: &Satellite| sat.regime() != OrbitRegime::Geo && filter(name, sat);
+
+        self.predict_trajectories_filtered(start, end, gs.body_fixed_frame(), None, filter)
+            .iter()
+            .map(|(sc, trajectory)| {
+                let mask = self.elevation_mask(sc.as_str(), gs);
+                let detector = SimpleElevationDetector {
+                    gs,
+                    trajectory,
+                    mask: &mask,
+                };
+                let coarse = self.spacecraft.get(sc.as_str()).map_or(60.0, |sat| {
+                    self.steps.coarse_step_for(sat.elements.mean_motion)
+                });
+                let windows = search::find_windows(
+                    |t| detector.margin(t),
+                    interval,
+                    coarse,
+                    self.steps.fine_step,
+                );
+
+                let mut transits = vec![];
+                for &body in bodies {
+                    // Angular distance from the center of the disk, and angular radius of the disk
+                    let distance = |t: Time<Tai>| {
+                        let obs = detector.observables(t);
+                        let (direction, radius) = body.look_angles(to_utc(t), &location);
+                        (
+                            sun::separation((obs.azimuth(), obs.elevation()), direction),
+                            radius,
+                        )
+                    };
+                    let near = |t| TRANSIT_SEARCH_ANGLE.to_radians() - distance(t).0;
+
+                    for window in &windows {
+                        for near_window in
+                            search::find_windows(near, *window, self.steps.fine_step, 1.0)
+                        {
+                            let (time, _) = search::find_maximum(|t| -distance(t).0, near_window);
+                            let (centerline_distance, body_radius) = distance(time);
+                            if centerline_distance > body_radius {
+                                continue;
+                            }
+
+                            let duration = search::find_windows(
+                                |t| {
+                                    let (distance, radius) = distance(t);
+                                    radius - distance
+                                },
+                                near_window,
+                                0.1,
+                                0.01,
+                            )
+                            .into_iter()
+                            .find(|w| w.start() <= time && time <= w.end())
+                            .map_or(0.0, |w| (w.end() - w.start()).to_seconds().to_f64());
+
+                            transits.push(Transit {
+                                body,
+                                time: time.into_dyn(),
+                                duration,
+                                centerline_distance,
+                                body_radius,
+                                observables: detector.observables(time),
+                            });
+                        }
+                    }
+                }
+                transits.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+
+                (sc.clone(), transits)
+            })
+            .collect()
+    }
+
+    /// Returns the intervals within `window` during which the line of sight of `detector` is within
+    /// the solar outage angle of the Sun, searching with steps of up to `step` seconds.
+    fn solar_outages(
+        &self,
+        detector: &SimpleElevationDetector,
+        window: TimeInterval<Tai>,
+        step: f64,
+    ) -> Vec<TimeInterval<DynTimeScale>> {
+        let Some(angle) = self.solar_outage_angle else {
+            return vec![];
+        };
+        let angle = angle.to_radians();
+        let location = detector.gs.location().coordinates();
+
+        let close_to_sun = |t: Time<Tai>| {
+            let obs = detector.observables(t);
+            let sun = sun::look_angles(to_utc(t), &location);
+            angle - sun::separation((obs.azimuth(), obs.elevation()), sun)
+        };
+
+        search::find_windows(close_to_sun, window, step, step.min(1.0))
+            .into_iter()
+            .map(|w| Interval::new(w.start().into_dyn(), w.end().into_dyn()))
+            .collect()
+    }
+
+    /// Returns the elevation mask to use when finding passes of `name` over `gs`.
+    ///
+    /// A minimum elevation override replaces a fixed station mask, and raises a horizon profile
+    /// to at least the override.
+    fn elevation_mask(&self, name: &str, gs: &GroundStation) -> ElevationMask {
+        let Some(min_elevation) = self
+            .spacecraft
+            .get(name)
+            .and_then(|sat| self.thresholds.min_elevation(name, sat))
+            .map(f64::to_radians)
+        else {
+            return gs.mask().clone();
+        };
+
+        match gs.mask() {
+            ElevationMask::Fixed(_) => ElevationMask::with_fixed_elevation(min_elevation),
+            ElevationMask::Variable(series) => ElevationMask::new(
+                series.x().to_vec(),
+                series.y().iter().map(|el| el.max(min_elevation)).collect(),
+            )
+            .expect("raising a valid mask keeps it valid"),
+        }
+    }
+
     pub fn predict_ground_track(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
         provider: Option<&mut CachedRotationProvider>,
+    ) -> HashMap<AssetId, Vec<(Time<Tai>, LonLatAlt)>> {
+        self.predict_ground_track_filtered(start, end, provider, |_, _| true)
+    }
+
+    /// Like [`PredictDb::predict_ground_track`], but only for satellites for which `filter`
+    /// returns true.
+    pub fn predict_ground_track_filtered(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        provider: Option<&mut CachedRotationProvider>,
+        filter: impl Fn(&str, &Satellite) -> bool,
     ) -> HashMap<AssetId, Vec<(Time<Tai>, LonLatAlt)>> {
         let frame = DynFrame::Iau(DynOrigin::Earth);
 
-        self.predict_trajectories(start, end, frame, provider)
+        self.predict_trajectories_filtered(start, end, frame, provider, filter)
             .iter()
             .map(|(sc, trajectory)| {
                 (
@@ -271,6 +1019,35 @@ impl PredictDb {
     }
 }
 
+/// Returns the groups of the satellite `name`: the stem of its `source` file, followed by the
+/// groups in `groups` listing it by name or NORAD ID.
+fn resolve_groups(
+    groups: &SatelliteGroups,
+    name: &str,
+    el: &Elements,
+    source: Option<&Path>,
+) -> Vec<String> {
+    let norad_id = el.norad_id.to_string();
+    let mut configured: Vec<String> = groups
+        .iter()
+        .filter(|(_, members)| members.iter().any(|m| *m == name || *m == norad_id))
+        .map(|(group, _)| group.clone())
+        .collect();
+    configured.sort();
+
+    source
+        .and_then(|path| path.file_stem())
+        .and_then(|s| s.to_str())
+        .map(str::to_string)
+        .into_iter()
+        .chain(configured)
+        .collect()
+}
+
+fn to_utc(time: Time<Tai>) -> DateTime<Utc> {
+    DateTime::<Utc>::try_from(time.to_utc()).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,16 +1076,51 @@ mod tests {
         let mut db = PredictDb::new();
         db.add_tles(&tle_dir()).unwrap();
 
-        assert_eq!(db.len(), 7);
+        assert_eq!(db.len(), 5);
         assert!(db.contains("NanoFF B LEOP D-Orbit"));
         assert!(db.contains("NanoFF B SatNOGS"));
-        assert!(db.contains("NanoFF B Space-Track"));
         assert!(db.contains("NanoFF A GNSS TLE SatNOGS"));
-        assert!(db.contains("NanoFF A Space-Track"));
         assert!(db.contains("NanoFF A"));
         assert!(db.contains("NanoFF B"));
     }
 
+    #[test]
+    fn add_tles_keeps_newest_elements_per_norad_id() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+
+        // nanoff.txt has older elements for both NORAD IDs, under different names
+        assert!(!db.contains("NanoFF A Space-Track"));
+        assert!(!db.contains("NanoFF B Space-Track"));
+        let (_, sat) = db.find("58810").unwrap();
+        assert_eq!(sat.elements.object_name.as_deref(), Some("NanoFF A"));
+        assert_eq!(sat.source, Some(tle_dir().join("nanoff_a.txt")));
+    }
+
+    #[test]
+    fn element_age_is_relative_to_epoch() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+        let sat = db.get("NanoFF A").unwrap();
+
+        let epoch = sat.elements.datetime.and_utc();
+        assert_eq!(
+            sat.element_age(epoch + chrono::Duration::days(2)),
+            chrono::Duration::days(2)
+        );
+    }
+
+    #[test]
+    fn add_tle_ignores_older_elements() {
+        let mut db = PredictDb::new();
+        let newer = fs::read_to_string(tle_dir().join("nanoff_a.txt")).unwrap();
+        let older = fs::read_to_string(tle_dir().join("nanoff.txt")).unwrap();
+        assert_eq!(db.add_tle(&newer), 1);
+        assert_eq!(db.add_tle(&older), 4);
+        assert!(db.contains("NanoFF A"));
+        assert!(!db.contains("NanoFF A Space-Track"));
+    }
+
     #[test]
     fn add_tles_returns_error_for_nonexistent_directory() {
         let mut db = PredictDb::new();
@@ -316,6 +1128,83 @@ mod tests {
         assert!(db.add_tles(&dir).is_err());
     }
 
+    const ISS_OMM: &str = r#"{"OBJECT_NAME":"ISS (ZARYA)","OBJECT_ID":"1998-067A","EPOCH":"2020-12-13T16:36:04.502592","MEAN_MOTION":15.49181153,"ECCENTRICITY":0.0001776,"INCLINATION":51.6441,"RA_OF_ASC_NODE":180.2076,"ARG_OF_PERICENTER":142.4313,"MEAN_ANOMALY":6.6417,"EPHEMERIS_TYPE":0,"CLASSIFICATION_TYPE":"U","NORAD_CAT_ID":25544,"ELEMENT_SET_NO":999,"REV_AT_EPOCH":25882,"BSTAR":2.7468e-5,"MEAN_MOTION_DOT":1.219e-5,"MEAN_MOTION_DDOT":0}"#;
+
+    #[test]
+    fn add_tles_loads_omm_json_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let css = ISS_OMM
+            .replace("ISS (ZARYA)", "CSS (TIANHE)")
+            .replace("25544", "48274");
+        fs::write(
+            tmp.path().join("stations.json"),
+            format!("[{ISS_OMM},{css}]"),
+        )
+        .unwrap();
+
+        let mut db = PredictDb::new();
+        assert_eq!(db.add_tles(&tmp.path().to_path_buf()).unwrap(), 2);
+        let iss = db.get("ISS (ZARYA)").unwrap();
+        assert_eq!(iss.elements.norad_id, 25544);
+        assert_eq!(iss.groups, vec!["stations"]);
+        assert!(iss.tle.is_none());
+        assert!(db.contains("CSS (TIANHE)"));
+    }
+
+    fn geo_omm() -> String {
+        ISS_OMM
+            .replace("ISS (ZARYA)", "GEO TEST")
+            .replace("25544", "99001")
+            .replace(r#""MEAN_MOTION":15.49181153"#, r#""MEAN_MOTION":1.00271"#)
+            .replace(r#""ECCENTRICITY":0.0001776"#, r#""ECCENTRICITY":0.0002"#)
+            .replace(r#""INCLINATION":51.6441"#, r#""INCLINATION":0.05"#)
+            .replace(r#""BSTAR":2.7468e-5"#, r#""BSTAR":0"#)
+            .replace(r#""MEAN_MOTION_DOT":1.219e-5"#, r#""MEAN_MOTION_DOT":0"#)
+    }
+
+    #[test]
+    fn orbit_regime_from_mean_motion() {
+        let mut db = PredictDb::new();
+        db.add_omm(ISS_OMM);
+        db.add_omm(&geo_omm());
+        db.add_omm(
+            &ISS_OMM
+                .replace("ISS (ZARYA)", "MEO TEST")
+                .replace("25544", "99002")
+                .replace(r#""MEAN_MOTION":15.49181153"#, r#""MEAN_MOTION":2.0056"#),
+        );
+
+        assert_eq!(db.get("ISS (ZARYA)").unwrap().regime(), OrbitRegime::Leo);
+        assert_eq!(db.get("GEO TEST").unwrap().regime(), OrbitRegime::Geo);
+        assert_eq!(db.get("MEO TEST").unwrap().regime(), OrbitRegime::Meo);
+    }
+
+    #[test]
+    fn geostationary_satellites_have_look_angles_instead_of_passes() {
+        let mut db = PredictDb::new();
+        db.add_omm(&geo_omm());
+        let gs = test_ground_station();
+        let start = Utc.with_ymd_and_hms(2020, 12, 14, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2020, 12, 15, 0, 0, 0).unwrap();
+
+        assert!(db.predict_passes(start, end, &gs, None).is_empty());
+
+        let geo = db.predict_geostationary(start, end, &gs, None, |_, _| true);
+        let visibility = &geo[&AssetId::new("GEO TEST")];
+        assert!(visibility.min_elevation <= visibility.observables.elevation());
+        assert!(visibility.observables.elevation() <= visibility.max_elevation);
+        // Nearly constant look angles
+        assert!(visibility.max_elevation - visibility.min_elevation < 1.0_f64.to_radians());
+        assert_eq!(visibility.visible, visibility.min_elevation >= 0.0);
+    }
+
+    #[test]
+    fn add_omm_accepts_single_object() {
+        let mut db = PredictDb::new();
+        assert_eq!(db.add_omm(ISS_OMM), 1);
+        assert_eq!(db.add_omm("not json"), 0);
+    }
+
     #[test]
     fn add_tles_on_empty_dir_loads_nothing() {
         let tmp = tempfile::tempdir().unwrap();
@@ -359,12 +1248,240 @@ mod tests {
         let end = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
 
         let passes = db.predict_passes(start, end, &gs, None);
-        for (_id, sat_passes) in &passes {
+        for sat_passes in passes.values() {
             for pass in sat_passes {
-                for obs in pass.observables() {
+                for obs in pass.pass.observables() {
                     assert!(obs.elevation() >= 0.0,);
                 }
             }
         }
     }
+
+    #[test]
+    fn predict_passes_without_mask_are_geometric() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+
+        let gs = test_ground_station();
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+
+        for pass in &db.predict_passes(start, end, &gs, None)[&AssetId::new("NanoFF A")] {
+            assert_eq!(pass.pass.interval(), &pass.geometric);
+            assert_eq!(pass.max_elevation, pass.geometric_max_elevation);
+            assert!(pass.geometric.start() < pass.tca && pass.tca < pass.geometric.end());
+            for obs in pass.pass.observables() {
+                assert!(obs.elevation() <= pass.max_elevation + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn predict_passes_apply_horizon_profile() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+
+        // 30° obstruction to the east, clear elsewhere
+        let deg = |d: f64| d.to_radians();
+        let mask = ElevationMask::new(
+            vec![deg(-180.0), deg(0.0), deg(45.0), deg(135.0), deg(180.0)],
+            vec![0.0, 0.0, deg(30.0), deg(30.0), 0.0],
+        )
+        .unwrap();
+        let gs = GroundStation::new("GS", test_ground_station().location().clone(), mask);
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 1, 16, 0, 0, 0).unwrap();
+
+        let passes = &db.predict_passes(start, end, &gs, None)[&AssetId::new("NanoFF A")];
+        assert!(!passes.is_empty());
+        assert!(passes.iter().any(|p| p.pass.interval() != &p.geometric));
+        for pass in passes {
+            // Both are root-found independently, so allow for a small difference
+            let masked = pass.pass.interval();
+            let seconds = |delta: TimeDelta| delta.to_seconds().to_f64();
+            assert!(seconds(masked.start() - pass.geometric.start()) > -1e-3);
+            assert!(seconds(pass.geometric.end() - masked.end()) > -1e-3);
+            assert!(pass.max_elevation <= pass.geometric_max_elevation + 1e-9);
+        }
+    }
+
+    #[test]
+    fn predict_passes_report_solar_outages() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+
+        let gs = test_ground_station();
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let id = AssetId::new("NanoFF A");
+
+        let passes = &db.predict_passes(start, end, &gs, None)[&id];
+        assert!(!passes.is_empty());
+        assert!(passes.iter().all(|p| p.solar_outages.is_empty()));
+
+        // Every direction is within 180° of the Sun
+        db.set_solar_outage_angle(Some(180.0));
+        for pass in &db.predict_passes(start, end, &gs, None)[&id] {
+            assert_eq!(pass.solar_outages, vec![*pass.pass.interval()]);
+        }
+    }
+
+    #[test]
+    fn predict_transits_across_the_sun() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+
+        let coords = LonLatAlt::from_degrees(12.0, 46.0, 0.0).unwrap();
+        let location = GroundLocation::try_new(coords, DynOrigin::Earth).unwrap();
+        let gs = GroundStation::new("GS", location, ElevationMask::with_fixed_elevation(0.0));
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 8, 50, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 1, 15, 9, 20, 0).unwrap();
+        let bodies = [TransitBody::Sun, TransitBody::Moon];
+
+        let transits =
+            db.predict_transits_filtered(start, end, &gs, &bodies, |n, _| n == "NanoFF A");
+        let transits = &transits[&AssetId::new("NanoFF A")];
+        assert_eq!(transits.len(), 1);
+
+        let transit = &transits[0];
+        assert_eq!(transit.body, TransitBody::Sun);
+        assert!(transit.centerline_distance < transit.body_radius);
+        assert!(transit.duration > 0.0 && transit.duration < 10.0);
+        assert!(transit.observables.elevation() > 0.0);
+    }
+
+    #[test]
+    fn add_tles_assigns_group_from_file_stem() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+
+        assert_eq!(db.get("NanoFF A").unwrap().groups, vec!["nanoff_a"]);
+        assert_eq!(
+            db.get("NanoFF A GNSS TLE SatNOGS").unwrap().groups,
+            vec!["nanoff"]
+        );
+    }
+
+    #[test]
+    fn set_groups_adds_configured_groups() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+        db.set_groups(SatelliteGroups::from([
+            ("priority".to_string(), vec!["58810".to_string()]),
+            ("stations".to_string(), vec!["25544".to_string()]),
+            (
+                "cubesats".to_string(),
+                vec!["NanoFF A".to_string(), "NanoFF B".to_string()],
+            ),
+        ]));
+
+        let sat = db.get("NanoFF A").unwrap();
+        assert_eq!(sat.groups, vec!["nanoff_a", "cubesats", "priority"]);
+        assert!(sat.in_group("priority"));
+        assert!(!db.get("NanoFF B").unwrap().in_group("priority"));
+
+        // Satellites loaded afterwards are grouped too
+        db.add_omm(ISS_OMM);
+        assert_eq!(db.get("ISS (ZARYA)").unwrap().groups, vec!["stations"]);
+    }
+
+    #[test]
+    fn elevation_thresholds_prefer_satellite_over_group() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+
+        let thresholds = ElevationThresholds {
+            groups: HashMap::from([("nanoff".to_string(), 5.0)]),
+            satellites: HashMap::from([("NanoFF A GNSS TLE SatNOGS".to_string(), 20.0)]),
+        };
+
+        let min_elevation = |name| thresholds.min_elevation(name, db.get(name).unwrap());
+        assert_eq!(min_elevation("NanoFF A GNSS TLE SatNOGS"), Some(20.0));
+        assert_eq!(min_elevation("NanoFF B SatNOGS"), Some(5.0));
+        assert_eq!(min_elevation("NanoFF A"), None);
+    }
+
+    #[test]
+    fn elevation_thresholds_match_norad_id() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+        let name = "NanoFF A GNSS TLE SatNOGS";
+        let sat = db.get(name).unwrap();
+
+        let thresholds = ElevationThresholds {
+            satellites: HashMap::from([(sat.elements.norad_id.to_string(), 15.0)]),
+            ..Default::default()
+        };
+        assert_eq!(thresholds.min_elevation(name, sat), Some(15.0));
+    }
+
+    #[test]
+    fn predict_passes_respects_min_elevation_override() {
+        let mut db = PredictDb::new();
+        db.add_tles(&tle_dir()).unwrap();
+        let gs = test_ground_station();
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+
+        let name = "NanoFF A";
+        db.set_elevation_thresholds(ElevationThresholds {
+            satellites: HashMap::from([(name.to_string(), 20.0)]),
+            ..Default::default()
+        });
+
+        let passes = db.predict_passes(start, end, &gs, None);
+        for pass in &passes[&AssetId::new(name)] {
+            for obs in pass.pass.observables() {
+                assert!(obs.elevation() >= 20.0_f64.to_radians());
+            }
+        }
+    }
+
+    #[test]
+    fn precomputed_passes_are_used_until_newer_elements_are_loaded() {
+        let mut db = PredictDb::new();
+        let older = fs::read_to_string(tle_dir().join("nanoff.txt")).unwrap();
+        db.add_tle(&older);
+
+        let gs = test_ground_station();
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 1, 16, 0, 0, 0).unwrap();
+        let name = "NanoFF A Space-Track";
+        let id = AssetId::new(name);
+        assert!(db.stale_passes(&gs, start, end).contains(&name.to_string()));
+        db.precompute_passes(name, &gs, start, end);
+        assert!(!db.stale_passes(&gs, start, end).contains(&name.to_string()));
+
+        let later = start + chrono::Duration::hours(6);
+        let computed = db.predict_passes_filtered(later, end, &gs, None, |n, _| n == name);
+        let cached = db.cached_passes_filtered(later, end, &gs, |n, _| n == name);
+        let ends = |passes: &[PredictedPass]| -> Vec<_> {
+            passes
+                .iter()
+                .map(|p| {
+                    // To the second, the search steps differ
+                    DateTime::<Utc>::try_from(p.pass.interval().end().to_utc())
+                        .unwrap()
+                        .timestamp()
+                })
+                .collect()
+        };
+        assert!(!cached[&id].is_empty());
+        assert_eq!(ends(&cached[&id]), ends(&computed[&id]));
+
+        // The cached ones do not cover this, so they are computed
+        let earlier = start - chrono::Duration::hours(1);
+        let passes = db.cached_passes_filtered(earlier, end, &gs, |n, _| n == name);
+        assert!(!passes[&id].is_empty());
+
+        let mut reloaded = PredictDb::new();
+        reloaded.add_tle(&older);
+        reloaded.keep_passes(db);
+        let stale = reloaded.stale_passes(&gs, start, end);
+        assert!(!stale.contains(&name.to_string()));
+        let newer = fs::read_to_string(tle_dir().join("nanoff_a.txt")).unwrap();
+        reloaded.add_tle(&newer);
+        let stale = reloaded.stale_passes(&gs, start, end);
+        assert!(stale.contains(&"NanoFF A".to_string()));
+    }
 }
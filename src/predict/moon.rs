@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use lox_space::core::coords::LonLatAlt;
+
+use super::sun::{days_since_j2000, sidereal_time};
+
+/// Difference between Terrestrial Time, in which the lunar theory is expressed, and UTC.
+const TT_MINUS_UTC_SECONDS: f64 = 69.184;
+
+const MOON_RADIUS_KM: f64 = 1737.4;
+
+const EARTH_EQUATORIAL_RADIUS_KM: f64 = 6378.137;
+const EARTH_FLATTENING: f64 = 1.0 / 298.257223563;
+
+/// Periodic terms of the Moon's longitude (1e-6 degrees) and distance (1e-3 km) as multiples of
+/// (D, M, M', F), after Meeus, Astronomical Algorithms, table 47.A, truncated.
+const LONGITUDE_DISTANCE_TERMS: [(i8, i8, i8, i8, f64, f64); 22] = [
+    (0, 0, 1, 0, 6288774.0, -20905355.0),
+    (2, 0, -1, 0, 1274027.0, -3699111.0),
+    (2, 0, 0, 0, 658314.0, -2955968.0),
+    (0, 0, 2, 0, 213618.0, -569925.0),
+    (0, 1, 0, 0, -185116.0, 48888.0),
+    (0, 0, 0, 2, -114332.0, -3149.0),
+    (2, 0, -2, 0, 58793.0, 246158.0),
+    (2, -1, -1, 0, 57066.0, -152138.0),
+    (2, 0, 1, 0, 53322.0, -170733.0),
+    (2, -1, 0, 0, 45758.0, -204586.0),
+    (0, 1, -1, 0, -40923.0, -129620.0),
+    (1, 0, 0, 0, -34720.0, 108743.0),
+    (0, 1, 1, 0, -30383.0, 104755.0),
+    (2, 0, 0, -2, 15327.0, 10321.0),
+    (0, 0, 1, 2, -12528.0, 0.0),
+    (0, 0, 1, -2, 10980.0, 79661.0),
+    (4, 0, -1, 0, 10675.0, -34782.0),
+    (0, 0, 3, 0, 10034.0, -23210.0),
+    (4, 0, -2, 0, 8548.0, -21636.0),
+    (2, 1, -1, 0, -7888.0, 24208.0),
+    (2, 1, 0, 0, -6766.0, 30824.0),
+    (1, 0, -1, 0, -5163.0, -8379.0),
+];
+
+/// Periodic terms of the Moon's latitude (1e-6 degrees), after Meeus, table 47.B, truncated.
+const LATITUDE_TERMS: [(i8, i8, i8, i8, f64); 13] = [
+    (0, 0, 0, 1, 5128122.0),
+    (0, 0, 1, 1, 280602.0),
+    (0, 0, 1, -1, 277693.0),
+    (2, 0, 0, -1, 173237.0),
+    (2, 0, -1, 1, 55413.0),
+    (2, 0, -1, -1, 46271.0),
+    (2, 0, 0, 1, 32573.0),
+    (0, 0, 2, 1, 17198.0),
+    (2, 0, 1, -1, 9266.0),
+    (0, 0, 2, -1, 8822.0),
+    (2, -1, 0, -1, 8216.0),
+    (2, 0, -2, -1, 4324.0),
+    (2, 0, 1, 1, 4200.0),
+];
+
+/// Returns the azimuth (from north, towards east) and elevation of the Moon as seen from
+/// `location` at `time`, in radians.
+///
+/// Uses the main terms of Meeus' lunar theory, accurate to about a hundredth of a degree, and
+/// accounts for the parallax of the observer.
+pub fn look_angles(time: DateTime<Utc>, location: &LonLatAlt) -> (f64, f64) {
+    let (azimuth, elevation, _) = topocentric(time, location);
+    (azimuth, elevation)
+}
+
+/// Returns the apparent angular radius (radians) of the Moon as seen from `location` at `time`.
+pub fn angular_radius(time: DateTime<Utc>, location: &LonLatAlt) -> f64 {
+    let (_, _, range) = topocentric(time, location);
+    (MOON_RADIUS_KM / range).asin()
+}
+
+/// Returns the geocentric ecliptic longitude and latitude (radians, mean equinox of date) and
+/// distance (km) of the Moon `n` days after J2000 (TT).
+fn ecliptic_position(n: f64) -> (f64, f64, f64) {
+    let t = n / 36525.0;
+    let mean_longitude = 218.3164477 + 481267.88123421 * t;
+    let elongation = 297.8501921 + 445267.1114034 * t;
+    let sun_anomaly = 357.5291092 + 35999.0502909 * t;
+    let moon_anomaly = 134.9633964 + 477198.8675055 * t;
+    let latitude_argument = 93.2720950 + 483202.0175233 * t;
+    let eccentricity = 1.0 - 0.002516 * t;
+
+    let argument = |d: i8, m: i8, mp: i8, f: i8| {
+        (d as f64 * elongation
+            + m as f64 * sun_anomaly
+            + mp as f64 * moon_anomaly
+            + f as f64 * latitude_argument)
+            .to_radians()
+    };
+    let eccentricity_factor = |m: i8| eccentricity.powi(m.abs() as i32);
+
+    let (mut sum_l, mut sum_r) = (0.0, 0.0);
+    for (d, m, mp, f, l, r) in LONGITUDE_DISTANCE_TERMS {
+        let arg = argument(d, m, mp, f);
+        sum_l += l * eccentricity_factor(m) * arg.sin();
+        sum_r += r * eccentricity_factor(m) * arg.cos();
+    }
+    let mut sum_b = 0.0;
+    for (d, m, mp, f, b) in LATITUDE_TERMS {
+        sum_b += b * eccentricity_factor(m) * argument(d, m, mp, f).sin();
+    }
+
+    // Action of Venus, Jupiter and the flattening of the Earth
+    let a1 = (119.75 + 131.849 * t).to_radians();
+    let a2 = (53.09 + 479264.290 * t).to_radians();
+    let a3 = (313.45 + 481266.484 * t).to_radians();
+    let l = mean_longitude.to_radians();
+    let mp = moon_anomaly.to_radians();
+    let f = latitude_argument.to_radians();
+    sum_l += 3958.0 * a1.sin() + 1962.0 * (l - f).sin() + 318.0 * a2.sin();
+    sum_b += -2235.0 * l.sin()
+        + 382.0 * a3.sin()
+        + 175.0 * (a1 - f).sin()
+        + 175.0 * (a1 + f).sin()
+        + 127.0 * (l - mp).sin()
+        - 115.0 * (l + mp).sin();
+
+    (
+        (mean_longitude + sum_l / 1e6).to_radians(),
+        (sum_b / 1e6).to_radians(),
+        385000.56 + sum_r / 1e3,
+    )
+}
+
+/// Returns the azimuth and elevation (radians) and range (km) of the Moon from `location`.
+fn topocentric(time: DateTime<Utc>, location: &LonLatAlt) -> (f64, f64, f64) {
+    let n = days_since_j2000(time);
+    let (longitude, latitude, distance) = ecliptic_position(n + TT_MINUS_UTC_SECONDS / 86400.0);
+    let obliquity = (23.439291 - 0.0130042 * n / 36525.0).to_radians();
+
+    // Geocentric equatorial, then Earth-fixed, coordinates
+    let x = distance * latitude.cos() * longitude.cos();
+    let y = distance
+        * (obliquity.cos() * latitude.cos() * longitude.sin() - obliquity.sin() * latitude.sin());
+    let z = distance
+        * (obliquity.sin() * latitude.cos() * longitude.sin() + obliquity.cos() * latitude.sin());
+    let theta = sidereal_time(n);
+    let moon = [
+        x * theta.cos() + y * theta.sin(),
+        -x * theta.sin() + y * theta.cos(),
+        z,
+    ];
+
+    // Station on the WGS84 ellipsoid
+    let lon = location.lon().to_radians();
+    let lat = location.lat().to_radians();
+    let alt = location.alt().to_meters() / 1000.0;
+    let e2 = EARTH_FLATTENING * (2.0 - EARTH_FLATTENING);
+    let radius = EARTH_EQUATORIAL_RADIUS_KM / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    let station = [
+        (radius + alt) * lat.cos() * lon.cos(),
+        (radius + alt) * lat.cos() * lon.sin(),
+        (radius * (1.0 - e2) + alt) * lat.sin(),
+    ];
+
+    let [dx, dy, dz] = [0, 1, 2].map(|i| moon[i] - station[i]);
+    let east = -lon.sin() * dx + lon.cos() * dy;
+    let north = -lat.sin() * lon.cos() * dx - lat.sin() * lon.sin() * dy + lat.cos() * dz;
+    let up = lat.cos() * lon.cos() * dx + lat.cos() * lon.sin() * dy + lat.sin() * dz;
+    let range = (dx * dx + dy * dy + dz * dz).sqrt();
+
+    (east.atan2(north), (up / range).asin(), range)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::predict::sun;
+
+    #[test]
+    fn ecliptic_position_matches_meeus_example() {
+        // Meeus, example 47.a: 1992 April 12, 0h TT
+        let j2000 = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+        let time = Utc.with_ymd_and_hms(1992, 4, 12, 0, 0, 0).unwrap();
+        let n = (time - j2000).num_seconds() as f64 / 86400.0;
+
+        let (longitude, latitude, distance) = ecliptic_position(n);
+        assert!((longitude.to_degrees().rem_euclid(360.0) - 133.162655).abs() < 0.02);
+        assert!((latitude.to_degrees() + 3.229126).abs() < 0.02);
+        assert!((distance - 368409.7).abs() < 50.0);
+    }
+
+    #[test]
+    fn moon_angular_radius_is_about_a_quarter_degree() {
+        let location = LonLatAlt::from_degrees(13.4, 52.52, 100.0).unwrap();
+        let time = Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).unwrap();
+        let radius = angular_radius(time, &location).to_degrees();
+        assert!((0.24..0.28).contains(&radius), "{radius}");
+    }
+
+    #[test]
+    fn moon_is_opposite_the_sun_when_full() {
+        // Full moon of 2026 January 3, 10:03 UTC
+        let location = LonLatAlt::from_degrees(0.0, 0.0, 0.0).unwrap();
+        let time = Utc.with_ymd_and_hms(2026, 1, 3, 10, 3, 0).unwrap();
+        let moon = look_angles(time, &location);
+        let sun = sun::look_angles(time, &location);
+        let separation = sun::separation(moon, sun).to_degrees();
+        assert!(separation > 170.0, "{separation}");
+    }
+}
@@ -0,0 +1,274 @@
+use lox_space::{
+    math::roots::{Brent, FindBracketedRoot},
+    prelude::{Interval, Tai, TimeDelta},
+    time::{Time, intervals::TimeInterval},
+};
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+/// The time of a maximum is refined to within this many seconds.
+const MAXIMUM_TOLERANCE_SECONDS: f64 = 0.1;
+
+/// The default coarse step is the orbital period divided by this.
+const STEPS_PER_ORBIT: f64 = 100.0;
+
+/// Step sizes (in seconds) used when searching for passes.
+///
+/// The search steps adaptively between `fine_step` and `coarse_step`: it takes large steps while
+/// the satellite is far from (or moving away from) the elevation threshold and shortens them as
+/// the current elevation slope brings it closer to a crossing.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct PassSearchSteps {
+    /// Largest step taken while searching. Defaults to 1/100th of the satellite's orbital period,
+    /// i.e. about a minute for LEO and about a quarter of an hour for GEO.
+    #[serde(deserialize_with = "positive_option")]
+    pub coarse_step: Option<f64>,
+    /// Smallest step taken while searching.
+    #[serde(deserialize_with = "positive")]
+    pub fine_step: f64,
+    /// Time between the samples of a pass' observables.
+    #[serde(deserialize_with = "positive")]
+    pub sample_step: f64,
+}
+
+/// A step, which has to be positive for the search to advance.
+fn positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let step = f64::deserialize(deserializer)?;
+    if step > 0.0 && step.is_finite() {
+        Ok(step)
+    } else {
+        Err(de::Error::custom(format!(
+            "steps must be positive seconds, got {step}"
+        )))
+    }
+}
+
+fn positive_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    Option::<f64>::deserialize(deserializer)?
+        .map(|step| positive(de::value::F64Deserializer::new(step)))
+        .transpose()
+}
+
+impl Default for PassSearchSteps {
+    fn default() -> Self {
+        Self {
+            coarse_step: None,
+            fine_step: 5.0,
+            sample_step: 20.0,
+        }
+    }
+}
+
+impl PassSearchSteps {
+    /// Returns the coarse step to use for an orbit with the given mean motion (revolutions/day).
+    pub fn coarse_step_for(&self, mean_motion: f64) -> f64 {
+        let coarse = self
+            .coarse_step
+            .unwrap_or_else(|| 86400.0 / mean_motion / STEPS_PER_ORBIT);
+        if coarse.is_finite() {
+            coarse.max(self.fine_step)
+        } else {
+            self.fine_step
+        }
+    }
+}
+
+/// Finds the sub-intervals of `interval` in which `f` is non-negative.
+///
+/// `f` is sampled with a step between `fine` and `coarse` seconds chosen from the estimated time
+/// until the next sign change, and each sign change is refined with Brent's method.
+pub(super) fn find_windows(
+    f: impl Fn(Time<Tai>) -> f64,
+    interval: TimeInterval<Tai>,
+    coarse: f64,
+    fine: f64,
+) -> Vec<TimeInterval<Tai>> {
+    let start = interval.start();
+    let at = |t: f64| start + TimeDelta::from_seconds_f64(t);
+    let total = (interval.end() - start).to_seconds().to_f64();
+
+    let mut windows = Vec::new();
+    let mut t = 0.0;
+    let mut value = f(start);
+    let mut slope: Option<f64> = None;
+    let mut open = (value >= 0.0).then_some(0.0);
+
+    while t < total {
+        let dt = next_step(value, slope, coarse, fine).min(total - t);
+        let next_t = t + dt;
+        let next_value = f(at(next_t));
+
+        if (value >= 0.0) != (next_value >= 0.0) {
+            let crossing = find_crossing(&f, &at, t, next_t);
+            match open.take() {
+                Some(aos) => windows.push(Interval::new(at(aos), at(crossing))),
+                None => open = Some(crossing),
+            }
+        }
+
+        slope = Some((next_value - value) / dt);
+        t = next_t;
+        value = next_value;
+    }
+
+    if let Some(aos) = open {
+        windows.push(Interval::new(at(aos), interval.end()));
+    }
+
+    windows
+}
+
+/// Chooses the next step: half the time until `value` would reach zero at the current `slope`,
+/// or `coarse` if it is moving away from zero.
+fn next_step(value: f64, slope: Option<f64>, coarse: f64, fine: f64) -> f64 {
+    let Some(slope) = slope else {
+        return fine;
+    };
+
+    let approaching = (value < 0.0 && slope > 0.0) || (value >= 0.0 && slope < 0.0);
+    if !approaching {
+        return coarse;
+    }
+
+    (0.5 * value.abs() / slope.abs()).clamp(fine, coarse)
+}
+
+/// Returns the time (seconds from the start) at which `f` changes sign between `lo` and `hi`.
+fn find_crossing(
+    f: &impl Fn(Time<Tai>) -> f64,
+    at: &impl Fn(f64) -> Time<Tai>,
+    lo: f64,
+    hi: f64,
+) -> f64 {
+    Brent::default()
+        .find_in_bracket(|t| Ok(f(at(t))), (lo, hi))
+        .unwrap_or(0.5 * (lo + hi))
+}
+
+/// Finds the time in `interval` at which `f` is largest, assuming it is unimodal there, returning
+/// the time and the value of `f` at it.
+pub(super) fn find_maximum(
+    f: impl Fn(Time<Tai>) -> f64,
+    interval: TimeInterval<Tai>,
+) -> (Time<Tai>, f64) {
+    let start = interval.start();
+    let at = |t: f64| start + TimeDelta::from_seconds_f64(t);
+    let inv_phi = (5f64.sqrt() - 1.0) / 2.0;
+
+    // Golden-section search
+    let (mut lo, mut hi) = (0.0, (interval.end() - start).to_seconds().to_f64());
+    let mut a = hi - inv_phi * (hi - lo);
+    let mut b = lo + inv_phi * (hi - lo);
+    let (mut fa, mut fb) = (f(at(a)), f(at(b)));
+    while hi - lo > MAXIMUM_TOLERANCE_SECONDS {
+        if fa > fb {
+            hi = b;
+            (b, fb) = (a, fa);
+            a = hi - inv_phi * (hi - lo);
+            fa = f(at(a));
+        } else {
+            lo = a;
+            (a, fa) = (b, fb);
+            b = lo + inv_phi * (hi - lo);
+            fb = f(at(b));
+        }
+    }
+
+    let t = at(0.5 * (lo + hi));
+    (t, f(t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn epoch() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    fn interval(seconds: i64) -> TimeInterval<Tai> {
+        let start: Time<Tai> = epoch().into();
+        Interval::new(start, start + TimeDelta::from_seconds(seconds))
+    }
+
+    fn seconds_since_epoch(t: Time<Tai>) -> f64 {
+        let start: Time<Tai> = epoch().into();
+        (t - start).to_seconds().to_f64()
+    }
+
+    #[test]
+    fn finds_window_shorter_than_coarse_step() {
+        // Non-negative for 200 s around t = 5000 s
+        let f = |t: Time<Tai>| 1.0 - ((seconds_since_epoch(t) - 5000.0) / 100.0).powi(2);
+        let windows = find_windows(f, interval(86400), 600.0, 5.0);
+
+        assert_eq!(windows.len(), 1);
+        let aos = seconds_since_epoch(windows[0].start());
+        let los = seconds_since_epoch(windows[0].end());
+        assert!((aos - 4900.0).abs() < 1e-3, "aos = {aos}");
+        assert!((los - 5100.0).abs() < 1e-3, "los = {los}");
+    }
+
+    #[test]
+    fn finds_maximum() {
+        let f = |t: Time<Tai>| 1.0 - ((seconds_since_epoch(t) - 420.0) / 100.0).powi(2);
+        let (t, value) = find_maximum(f, interval(600));
+        assert!((seconds_since_epoch(t) - 420.0).abs() < MAXIMUM_TOLERANCE_SECONDS);
+        assert!((value - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn window_open_at_both_ends_spans_interval() {
+        let search = interval(3600);
+        let windows = find_windows(|_| 1.0, search, 60.0, 5.0);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].start(), search.start());
+        assert_eq!(windows[0].end(), search.end());
+    }
+
+    #[test]
+    fn takes_coarse_steps_while_moving_away() {
+        let evaluations = Cell::new(0);
+        let f = |t: Time<Tai>| {
+            evaluations.set(evaluations.get() + 1);
+            -1.0 - seconds_since_epoch(t)
+        };
+        assert!(find_windows(f, interval(86400), 900.0, 5.0).is_empty());
+        assert!(evaluations.get() < 100, "{} evaluations", evaluations.get());
+    }
+
+    #[test]
+    fn default_coarse_step_scales_with_period() {
+        let steps = PassSearchSteps::default();
+        let leo = steps.coarse_step_for(15.5);
+        let geo = steps.coarse_step_for(1.0027);
+        assert!((50.0..60.0).contains(&leo), "leo = {leo}");
+        assert!((800.0..900.0).contains(&geo), "geo = {geo}");
+        assert_eq!(steps.coarse_step_for(0.0), steps.fine_step);
+    }
+
+    #[test]
+    fn non_positive_steps_are_rejected() {
+        let steps = |yaml| serde_yaml::from_str::<PassSearchSteps>(yaml);
+        let parsed = steps("fine_step: 2\ncoarse_step: 30").unwrap();
+        assert_eq!((parsed.fine_step, parsed.coarse_step), (2.0, Some(30.0)));
+        assert_eq!(parsed.sample_step, 20.0);
+
+        for yaml in [
+            "fine_step: 0",
+            "fine_step: -5",
+            "sample_step: 0",
+            "coarse_step: -1",
+        ] {
+            let error = steps(yaml).unwrap_err().to_string();
+            assert!(
+                error.contains("steps must be positive seconds"),
+                "{yaml}: {error}"
+            );
+        }
+    }
+}
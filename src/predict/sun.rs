@@ -0,0 +1,104 @@
+use chrono::{DateTime, TimeZone, Utc};
+use lox_space::core::coords::LonLatAlt;
+
+/// Returns the azimuth (from north, towards east) and elevation of the Sun as seen from
+/// `location` at `time`, in radians.
+///
+/// Uses the low-precision formulas from the Astronomical Almanac, accurate to about 0.01° between
+/// 1950 and 2050.
+pub fn look_angles(time: DateTime<Utc>, location: &LonLatAlt) -> (f64, f64) {
+    let n = days_since_j2000(time);
+
+    // Ecliptic longitude and obliquity
+    let mean_longitude = 280.460 + 0.9856474 * n;
+    let mean_anomaly = mean_anomaly(n);
+    let longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin())
+            .to_radians();
+    let obliquity = (23.439 - 0.0000004 * n).to_radians();
+
+    let right_ascension = (obliquity.cos() * longitude.sin()).atan2(longitude.cos());
+    let declination = (obliquity.sin() * longitude.sin()).asin();
+
+    let hour_angle = sidereal_time(n) + location.lon().to_radians() - right_ascension;
+    let lat = location.lat().to_radians();
+
+    let elevation =
+        (lat.sin() * declination.sin() + lat.cos() * declination.cos() * hour_angle.cos()).asin();
+    let azimuth = (-hour_angle.sin() * declination.cos())
+        .atan2(lat.cos() * declination.sin() - lat.sin() * declination.cos() * hour_angle.cos());
+
+    (azimuth, elevation)
+}
+
+/// Returns the apparent angular radius (radians) of the Sun at `time`.
+pub fn angular_radius(time: DateTime<Utc>) -> f64 {
+    let g = mean_anomaly(days_since_j2000(time));
+    let distance_au = 1.00014 - 0.01671 * g.cos() - 0.00014 * (2.0 * g).cos();
+    (0.2666 / distance_au).to_radians()
+}
+
+/// Returns the Sun's mean anomaly (radians) `n` days after J2000.
+fn mean_anomaly(n: f64) -> f64 {
+    (357.528 + 0.9856003 * n).to_radians()
+}
+
+/// Returns the (fractional) number of days between J2000 and `time`.
+pub(super) fn days_since_j2000(time: DateTime<Utc>) -> f64 {
+    let j2000 = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+    (time - j2000).num_milliseconds() as f64 / 86_400_000.0
+}
+
+/// Returns the Greenwich mean sidereal time (radians) `n` days after J2000.
+pub(super) fn sidereal_time(n: f64) -> f64 {
+    (280.46061837 + 360.98564736629 * n).to_radians()
+}
+
+/// Returns the angle (radians) between two directions given as azimuth and elevation.
+pub fn separation((az1, el1): (f64, f64), (az2, el2): (f64, f64)) -> f64 {
+    let cos = el1.sin() * el2.sin() + el1.cos() * el2.cos() * (az1 - az2).cos();
+    cos.clamp(-1.0, 1.0).acos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_at_noon_on_equinox() {
+        // Greenwich, March 2024 equinox: the Sun culminates due south at 90° - latitude
+        let location = LonLatAlt::from_degrees(0.0, 51.48, 0.0).unwrap();
+        let time = Utc.with_ymd_and_hms(2024, 3, 20, 12, 7, 0).unwrap();
+
+        let (azimuth, elevation) = look_angles(time, &location);
+        assert!(
+            (azimuth.to_degrees().abs() - 180.0).abs() < 1.0,
+            "{azimuth}"
+        );
+        assert!((elevation.to_degrees() - 38.52).abs() < 0.5, "{elevation}");
+    }
+
+    #[test]
+    fn sun_below_horizon_at_midnight() {
+        let location = LonLatAlt::from_degrees(13.4, 52.52, 100.0).unwrap();
+        let time = Utc.with_ymd_and_hms(2026, 1, 15, 23, 0, 0).unwrap();
+        assert!(look_angles(time, &location).1 < -0.5);
+    }
+
+    #[test]
+    fn sun_angular_radius() {
+        // Perihelion and aphelion
+        let january = Utc.with_ymd_and_hms(2026, 1, 3, 0, 0, 0).unwrap();
+        let july = Utc.with_ymd_and_hms(2026, 7, 6, 0, 0, 0).unwrap();
+        assert!((angular_radius(january).to_degrees() - 0.2711).abs() < 0.001);
+        assert!((angular_radius(july).to_degrees() - 0.2622).abs() < 0.001);
+    }
+
+    #[test]
+    fn separation_of_directions() {
+        let zenith = (0.0, 90_f64.to_radians());
+        let horizon = (1.0, 0.0);
+        assert!((separation(zenith, horizon) - 90_f64.to_radians()).abs() < 1e-9);
+        assert!(separation(horizon, horizon).abs() < 1e-6);
+    }
+}
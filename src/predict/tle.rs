@@ -0,0 +1,85 @@
+use sgp4::Elements;
+
+/// A parsed element set along with the TLE lines it was parsed from.
+pub struct ParsedTle {
+    pub elements: Elements,
+    /// The original lines (including the name line, if any) separated by newlines.
+    pub text: String,
+}
+
+/// Parses a text containing any number of 2LEs and/or 3LEs.
+///
+/// Unlike [`sgp4::parse_3les`], an invalid entry does not prevent the rest of the text from being
+/// parsed; each entry yields its own result.
+pub fn parse(text: &str) -> Vec<Result<ParsedTle, sgp4::TleError>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+
+    let mut results = Vec::new();
+    let mut name: Option<&str> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let next = lines.get(i + 1);
+        if line.starts_with("1 ") && next.is_some_and(|n| n.starts_with("2 ")) {
+            let line2 = next.unwrap();
+            let object_name = name
+                .take()
+                .map(|n| n.trim().trim_start_matches("0 ").to_string());
+            let text = match &object_name {
+                Some(n) => format!("{n}\n{line}\n{line2}"),
+                None => format!("{line}\n{line2}"),
+            };
+            results.push(
+                Elements::from_tle(object_name, line.as_bytes(), line2.as_bytes())
+                    .map(|elements| ParsedTle { elements, text }),
+            );
+            i += 2;
+        } else {
+            // Anything that is not part of a TLE is the name line for the next one
+            name = Some(line);
+            i += 1;
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISS_L1: &str = "1 25544U 98067A   08264.51782528 -.00002182  00000-0 -11606-4 0  2927";
+    const ISS_L2: &str = "2 25544  51.6416 247.4627 0006703 130.5360 325.0288 15.72125391563537";
+
+    #[test]
+    fn parse_3le() {
+        let text = format!("ISS (ZARYA)\n{ISS_L1}\n{ISS_L2}\n");
+        let parsed = parse(&text);
+        assert_eq!(parsed.len(), 1);
+        let tle = parsed[0].as_ref().unwrap();
+        assert_eq!(tle.elements.object_name.as_deref(), Some("ISS (ZARYA)"));
+        assert_eq!(tle.elements.norad_id, 25544);
+        assert_eq!(tle.text, text.trim_end());
+    }
+
+    #[test]
+    fn parse_2le_without_name() {
+        let parsed = parse(&format!("{ISS_L1}\n{ISS_L2}"));
+        assert_eq!(parsed.len(), 1);
+        assert!(parsed[0].as_ref().unwrap().elements.object_name.is_none());
+    }
+
+    #[test]
+    fn invalid_entry_does_not_stop_parsing() {
+        let bad_l2 = ISS_L2.replace("51.6416", "xx.xxxx");
+        let text = format!("BAD\n{ISS_L1}\n{bad_l2}\nISS\n{ISS_L1}\n{ISS_L2}\n");
+        let parsed = parse(&text);
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].is_err());
+        assert!(parsed[1].is_ok());
+    }
+}
@@ -1,10 +1,11 @@
 use lox_space::{
+    analysis::visibility::ElevationMask,
     frames::{
         DynFrame,
         providers::DefaultRotationProvider,
         rotations::{DynRotationError, Rotation, TryRotation},
     },
-    orbits::{events::DetectFn, orbits::DynTrajectory},
+    orbits::{ground::Observables, orbits::DynTrajectory},
     prelude::{GroundStation, Interval, Tai, TimeDelta},
     time::{
         Time,
@@ -152,27 +153,36 @@ fn lerp_rotation(a: &Rotation, b: &Rotation, t: f64) -> Rotation {
         dm: a.dm * s + b.dm * t,
     }
 }
+/// Detects when the elevation of `trajectory` as seen from `gs` is above `mask`.
 pub(super) struct SimpleElevationDetector<'a> {
     pub gs: &'a GroundStation,
     pub trajectory: &'a DynTrajectory,
+    pub mask: &'a ElevationMask,
 }
 
-#[derive(thiserror::Error, Debug)]
-pub(super) enum ElevationDetectError {}
-
-impl<'a, T: TimeScale + Into<DynTimeScale>> DetectFn<T> for SimpleElevationDetector<'a> {
-    type Error = ElevationDetectError;
-
-    fn eval(&self, time: Time<T>) -> Result<f64, Self::Error> {
+impl SimpleElevationDetector<'_> {
+    /// Returns the observables of the trajectory from the ground station at `time`.
+    pub fn observables<T: TimeScale + Into<DynTimeScale>>(&self, time: Time<T>) -> Observables {
         let state = self.trajectory.interpolate_at(time.into_dyn());
         let state_bf = state
             .try_to_frame(self.gs.body_fixed_frame(), &DefaultRotationProvider)
             .unwrap();
 
-        Ok(self
-            .gs
+        self.gs
             .location()
             .compute_observables(state_bf.position(), state_bf.velocity())
-            .elevation())
+    }
+
+    /// Returns the elevation above the mask (radians) at `time`.
+    pub fn margin<T: TimeScale + Into<DynTimeScale>>(&self, time: Time<T>) -> f64 {
+        let obs = self.observables(time);
+        obs.elevation() - self.mask.min_elevation(obs.azimuth())
+    }
+
+    /// Returns the rate of change of the range rate (m/s²) at `time`.
+    pub fn range_acceleration(&self, time: Time<Tai>) -> f64 {
+        let half_step = TimeDelta::from_seconds_f64(0.5);
+        self.observables(time + half_step).range_rate()
+            - self.observables(time - half_step).range_rate()
     }
 }
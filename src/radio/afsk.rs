@@ -0,0 +1,320 @@
+//! Demodulation of packets sent in AFSK at 1200 baud (Bell 202 tones) over FM, as by most amateur
+//! cubesats and the APRS digipeater of the ISS: the frames are recovered from the IQ samples and
+//! only kept if their checksum is valid.
+
+use rustfft::num_complex::{Complex32, Complex64};
+
+use super::{SampleDecoder, SampleFormat};
+
+const BAUD: f64 = 1200.0;
+/// Tone sent for ones, before NRZI coding
+const MARK: f64 = 1200.0;
+const SPACE: f64 = 2200.0;
+
+/// Highest rate the FM discriminator runs at, the samples are decimated to it.
+const AUDIO_RATE: u64 = 48_000;
+
+/// How much of the timing error is corrected at each transition of the tones.
+const CLOCK_GAIN: f64 = 0.4;
+
+/// Addresses of the destination and source, control field and checksum.
+const MIN_FRAME: usize = 17;
+/// Longest frame accepted, past which the bits received are dropped as noise.
+const MAX_FRAME: usize = 1024;
+
+/// The frame check sequence of AX.25 (CRC-16/X.25) of `bytes`, sent least significant byte first.
+pub fn fcs(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xffff;
+    for byte in bytes {
+        crc ^= *byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x8408
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Correlates the audio with a tone over the last bit.
+struct ToneDetector {
+    /// Phase advance of the tone per sample
+    step: f64,
+    phase: f64,
+    /// The audio mixed with the tone over the last bit, and its sum
+    mixed: Vec<Complex64>,
+    sum: Complex64,
+    next: usize,
+}
+
+impl ToneDetector {
+    fn new(frequency: f64, rate: f64) -> Self {
+        let window = (rate / BAUD).round().max(1.0) as usize;
+        Self {
+            step: std::f64::consts::TAU * frequency / rate,
+            phase: 0.0,
+            mixed: vec![Complex64::default(); window],
+            sum: Complex64::default(),
+            next: 0,
+        }
+    }
+
+    /// The energy of the tone over the last bit, including `audio`.
+    fn push(&mut self, audio: f64) -> f64 {
+        let mixed = Complex64::from_polar(audio, -self.phase);
+        self.phase = (self.phase + self.step) % std::f64::consts::TAU;
+        self.sum += mixed - self.mixed[self.next];
+        self.mixed[self.next] = mixed;
+        self.next = (self.next + 1) % self.mixed.len();
+        self.sum.norm_sqr()
+    }
+}
+
+/// Recovers the frames sent in the samples of a stream, in the order they are received.
+pub struct Demodulator {
+    samples: SampleDecoder,
+    receiver: Receiver,
+}
+
+impl Demodulator {
+    /// A demodulator of samples in `format` at `sample_rate` (Hz).
+    pub fn new(format: SampleFormat, sample_rate: u64) -> Self {
+        Self {
+            samples: SampleDecoder::new(format),
+            receiver: Receiver::new(sample_rate),
+        }
+    }
+
+    /// Adds the samples in `bytes`, which may start or end in the middle of a sample.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let Self { samples, receiver } = self;
+        samples.decode(bytes, |sample| receiver.add(sample));
+    }
+
+    /// The frames received since the last call, without their checksum.
+    pub fn take(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.receiver.deframer.frames)
+    }
+}
+
+/// Demodulates the bits of the frames from the samples.
+struct Receiver {
+    /// Samples averaged into each sample of the discriminator, and their sum so far
+    decimation: usize,
+    decimated: Complex32,
+    count: usize,
+    previous: Complex32,
+    mark: ToneDetector,
+    space: ToneDetector,
+    /// Position in the current bit, sampled when it wraps, in the middle of the bit
+    clock: f64,
+    clock_step: f64,
+    /// Tone heard last, true for the mark
+    tone: bool,
+    /// Tone of the last bit, to decode the NRZI
+    last_bit_tone: bool,
+    deframer: Deframer,
+}
+
+impl Receiver {
+    fn new(sample_rate: u64) -> Self {
+        let decimation = (sample_rate / AUDIO_RATE).max(1) as usize;
+        let rate = sample_rate as f64 / decimation as f64;
+        Self {
+            decimation,
+            decimated: Complex32::default(),
+            count: 0,
+            previous: Complex32::default(),
+            mark: ToneDetector::new(MARK, rate),
+            space: ToneDetector::new(SPACE, rate),
+            clock: 0.0,
+            clock_step: BAUD / rate,
+            tone: false,
+            last_bit_tone: false,
+            deframer: Deframer::default(),
+        }
+    }
+
+    fn add(&mut self, sample: Complex32) {
+        self.decimated += sample;
+        self.count += 1;
+        if self.count < self.decimation {
+            return;
+        }
+        let sample = std::mem::take(&mut self.decimated);
+        self.count = 0;
+
+        // FM discriminator: the frequency is the phase difference between samples
+        let audio = (sample * self.previous.conj()).arg() as f64;
+        self.previous = sample;
+
+        let tone = self.mark.push(audio) > self.space.push(audio);
+        if tone != self.tone {
+            // Tones change between bits, at the middle of the clock
+            self.clock += (0.5 - self.clock) * CLOCK_GAIN;
+            self.tone = tone;
+        }
+        self.clock += self.clock_step;
+        if self.clock >= 1.0 {
+            self.clock -= 1.0;
+            // NRZI: ones keep the tone, zeros change it
+            self.deframer.push(tone == self.last_bit_tone);
+            self.last_bit_tone = tone;
+        }
+    }
+}
+
+/// Splits HDLC frames at their flags, removing the bits stuffed in them.
+#[derive(Default)]
+struct Deframer {
+    /// Consecutive ones received
+    ones: u32,
+    in_frame: bool,
+    frame: Vec<u8>,
+    byte: u8,
+    bits: usize,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Deframer {
+    fn push(&mut self, bit: bool) {
+        if bit {
+            self.ones += 1;
+            if self.ones > 6 {
+                // Abort, or the line idling
+                self.in_frame = false;
+                return;
+            }
+        } else {
+            let ones = std::mem::take(&mut self.ones);
+            if ones == 6 {
+                self.flag();
+                return;
+            }
+            if ones == 5 {
+                // Stuffed after five ones, so that frames never contain a flag
+                return;
+            }
+        }
+        if !self.in_frame {
+            return;
+        }
+        self.byte = (self.byte >> 1) | ((bit as u8) << 7);
+        self.bits += 1;
+        if self.bits == 8 {
+            self.frame.push(self.byte);
+            self.bits = 0;
+            if self.frame.len() > MAX_FRAME {
+                self.in_frame = false;
+            }
+        }
+    }
+
+    fn flag(&mut self) {
+        // The first seven bits of the flag were taken as part of the frame
+        if self.in_frame && self.bits == 7 && self.frame.len() >= MIN_FRAME {
+            let (frame, check) = self.frame.split_at(self.frame.len() - 2);
+            if fcs(frame).to_le_bytes() == check {
+                self.frames.push(frame.to_vec());
+            }
+        }
+        self.frame.clear();
+        self.bits = 0;
+        self.in_frame = true;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Frame delimiter of HDLC.
+    const FLAG: u8 = 0x7e;
+
+    /// `frame` with its checksum, sent in AFSK over FM with a 3 kHz deviation, as cs16 samples at
+    /// `sample_rate`.
+    pub(crate) fn modulate(frame: &[u8], sample_rate: u64) -> Vec<u8> {
+        let mut bits = Vec::new();
+        let flags = |bits: &mut Vec<bool>, count| {
+            for _ in 0..count {
+                bits.extend((0..8).map(|i| FLAG >> i & 1 == 1));
+            }
+        };
+        flags(&mut bits, 30);
+        let mut ones = 0;
+        for byte in frame.iter().chain(&fcs(frame).to_le_bytes()) {
+            for i in 0..8 {
+                let bit = byte >> i & 1 == 1;
+                bits.push(bit);
+                ones = if bit { ones + 1 } else { 0 };
+                if ones == 5 {
+                    bits.push(false);
+                    ones = 0;
+                }
+            }
+        }
+        flags(&mut bits, 5);
+
+        let rate = sample_rate as f64;
+        let (mut tone, mut tone_phase, mut phase) = (true, 0.0f64, 0.0f64);
+        let mut samples = Vec::new();
+        for (i, bit) in bits.into_iter().enumerate() {
+            if !bit {
+                tone = !tone;
+            }
+            let frequency = if tone { MARK } else { SPACE };
+            let end = ((i + 1) as f64 * rate / BAUD) as usize;
+            while samples.len() / 4 < end {
+                tone_phase += std::f64::consts::TAU * frequency / rate;
+                phase += std::f64::consts::TAU * 3000.0 * tone_phase.sin() / rate;
+                let (i, q) = (
+                    (phase.cos() * 16000.0) as i16,
+                    (phase.sin() * 16000.0) as i16,
+                );
+                samples.extend(i.to_le_bytes());
+                samples.extend(q.to_le_bytes());
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn checksums_match_ax25() {
+        assert_eq!(fcs(b"123456789"), 0x906e);
+    }
+
+    #[test]
+    fn frames_are_demodulated() {
+        let frame: Vec<u8> = b"\x82\xa0\xa4\xa6\x40\x40\xe0\x9c\x6e\x98\x8a\x9a\x40\x61\x03\xf0"
+            .iter()
+            .copied()
+            .chain(b"Hello from orbit! \x7e\xff\x00".iter().copied())
+            .collect();
+        for sample_rate in [48_000, 250_000] {
+            let samples = modulate(&frame, sample_rate);
+            let mut demodulator = Demodulator::new(SampleFormat::Cs16, sample_rate);
+            // Split in the middle of samples
+            for chunk in samples.chunks(1001) {
+                demodulator.push(chunk);
+            }
+            assert_eq!(demodulator.take(), vec![frame.clone()], "at {sample_rate} Hz");
+            assert!(demodulator.take().is_empty());
+        }
+    }
+
+    #[test]
+    fn noise_is_not_taken_for_frames() {
+        let mut demodulator = Demodulator::new(SampleFormat::Cu8, 48_000);
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..480_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        demodulator.push(&noise);
+        assert!(demodulator.take().is_empty());
+    }
+}
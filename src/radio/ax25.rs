@@ -0,0 +1,249 @@
+//! AX.25 packets demodulated from a stream: logged as they are received, in the monitor format of
+//! TNCs, and served to KISS clients.
+
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{SecondsFormat, Utc};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::SampleFormat;
+use super::afsk::Demodulator;
+use super::kiss;
+
+/// File the packets received during a run are logged to, in its artifacts.
+pub const PACKETS_FILE: &str = "packets.log";
+
+/// Addresses of the destination and source, and of up to 8 digipeaters.
+const MAX_ADDRESSES: usize = 10;
+
+/// Where the packets demodulated from a stream go.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketOutput {
+    /// Address to serve the frames on to KISS clients over TCP, e.g. `127.0.0.1:8001`
+    pub kiss: Option<String>,
+    /// File each packet is appended to, a line each
+    pub log: Option<PathBuf>,
+}
+
+/// A station, and for digipeaters whether the frame was repeated by it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub callsign: String,
+    pub ssid: u8,
+    pub repeated: bool,
+}
+
+impl Address {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let callsign: String = bytes[..6].iter().map(|b| (b >> 1) as char).collect();
+        let callsign = callsign.trim_end().to_string();
+        if callsign.is_empty() || !callsign.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        Some(Self {
+            callsign,
+            ssid: (bytes[6] >> 1) & 0x0f,
+            repeated: false,
+        })
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.callsign)?;
+        if self.ssid != 0 {
+            write!(f, "-{}", self.ssid)?;
+        }
+        if self.repeated {
+            f.write_str("*")?;
+        }
+        Ok(())
+    }
+}
+
+/// An AX.25 frame, without its checksum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub destination: Address,
+    pub source: Address,
+    pub digipeaters: Vec<Address>,
+    pub control: u8,
+    /// Protocol of the information field, only in information and UI frames
+    pub pid: Option<u8>,
+    pub info: Vec<u8>,
+}
+
+impl Frame {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut addresses = Vec::new();
+        let mut rest = bytes;
+        loop {
+            let address = rest.get(..7)?;
+            rest = &rest[7..];
+            let mut parsed = Address::parse(address)?;
+            // Digipeaters set the high bit of their SSID once they repeated the frame
+            parsed.repeated = addresses.len() >= 2 && address[6] & 0x80 != 0;
+            addresses.push(parsed);
+            // The last address is marked by the low bit of its SSID
+            if address[6] & 1 == 1 {
+                break;
+            }
+            if addresses.len() == MAX_ADDRESSES {
+                return None;
+            }
+        }
+        if addresses.len() < 2 {
+            return None;
+        }
+        let digipeaters = addresses.split_off(2);
+        let source = addresses.pop().expect("two addresses");
+        let destination = addresses.pop().expect("two addresses");
+
+        let (&control, rest) = rest.split_first()?;
+        let (pid, info) = if control & 0x01 == 0 || control & 0xef == 0x03 {
+            let (&pid, info) = rest.split_first()?;
+            (Some(pid), info)
+        } else {
+            (None, rest)
+        };
+        Some(Self {
+            destination,
+            source,
+            digipeaters,
+            control,
+            pid,
+            info: info.to_vec(),
+        })
+    }
+}
+
+/// The monitor format of TNCs, e.g. `N7LEM>APRS,RS0ISS*:>Hello`. Unprintable bytes of the
+/// information field are written as `<0xNN>`.
+impl Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}>{}", self.source, self.destination)?;
+        for digipeater in &self.digipeaters {
+            write!(f, ",{digipeater}")?;
+        }
+        f.write_str(":")?;
+        for &byte in &self.info {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                write!(f, "{}", byte as char)?;
+            } else {
+                write!(f, "<0x{byte:02x}>")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The frame as logged: in the monitor format, or in hexadecimal if it is not AX.25.
+fn describe(frame: &[u8]) -> String {
+    match Frame::parse(frame) {
+        Some(frame) => frame.to_string(),
+        None => frame.iter().map(|b| format!("{b:02x}")).collect(),
+    }
+}
+
+/// Demodulates the packets of a stream, logging and serving them.
+pub(super) struct PacketWriter {
+    demodulator: Demodulator,
+    /// Disabled after failing to write
+    log: Option<(PathBuf, File)>,
+    frames: broadcast::Sender<Vec<u8>>,
+    server: Option<JoinHandle<()>>,
+}
+
+impl PacketWriter {
+    /// Opens the log and starts serving the frames to KISS clients.
+    pub(super) async fn start(
+        output: &PacketOutput,
+        format: SampleFormat,
+        sample_rate: u64,
+    ) -> std::io::Result<Self> {
+        let log = match &output.log {
+            Some(path) => {
+                let file = File::options().create(true).append(true).open(path)?;
+                Some((path.clone(), file))
+            }
+            None => None,
+        };
+        let (frames, _) = broadcast::channel(64);
+        let server = match &output.kiss {
+            Some(address) => {
+                let listener = TcpListener::bind(address).await?;
+                info!(%address, "serving packets to KISS clients");
+                Some(tokio::spawn(kiss::serve(listener, frames.clone())))
+            }
+            None => None,
+        };
+        Ok(Self {
+            demodulator: Demodulator::new(format, sample_rate),
+            log,
+            frames,
+            server,
+        })
+    }
+
+    pub(super) fn push(&mut self, samples: &[u8]) {
+        self.demodulator.push(samples);
+        for frame in self.demodulator.take() {
+            let packet = describe(&frame);
+            info!(%packet, "packet received");
+            if let Some((path, file)) = &mut self.log {
+                let time = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+                if let Err(e) = writeln!(file, "{time} {packet}") {
+                    warn!(path = %path.display(), ?e, "failed to log packet, not trying again");
+                    self.log = None;
+                }
+            }
+            // Without KISS clients, nobody is subscribed
+            let _ = self.frames.send(frame);
+        }
+    }
+}
+
+impl Drop for PacketWriter {
+    fn drop(&mut self) {
+        if let Some(server) = &self.server {
+            server.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(callsign: &str, ssid: u8, last: bool) -> Vec<u8> {
+        let mut bytes: Vec<u8> = format!("{callsign:6}").bytes().map(|b| b << 1).collect();
+        bytes.push(0x60 | (ssid << 1) | last as u8);
+        bytes
+    }
+
+    #[test]
+    fn frames_are_shown_in_the_monitor_format() {
+        let mut bytes = [
+            address("APRS", 0, false),
+            address("N7LEM", 7, false),
+            address("RS0ISS", 0, true),
+        ]
+        .concat();
+        bytes[20] |= 0x80;
+        bytes.extend(b"\x03\xf0>Hello\r");
+
+        let frame = Frame::parse(&bytes).unwrap();
+        assert_eq!(frame.source.callsign, "N7LEM");
+        assert_eq!(frame.pid, Some(0xf0));
+        assert_eq!(frame.to_string(), "N7LEM-7>APRS,RS0ISS*:>Hello<0x0d>");
+
+        assert_eq!(describe(&bytes), frame.to_string());
+        assert_eq!(describe(&bytes[..10]), "82a0a4a64040609c6e98");
+    }
+}
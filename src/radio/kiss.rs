@@ -0,0 +1,107 @@
+//! The [KISS](http://www.ax25.net/kiss.aspx) protocol TNCs hand the frames they receive to packet
+//! applications with, served over TCP like the KISS port of Dire Wolf, so that decoders and APRS
+//! clients can be connected to a stream.
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+const FEND: u8 = 0xc0;
+const FESC: u8 = 0xdb;
+const TFEND: u8 = 0xdc;
+const TFESC: u8 = 0xdd;
+
+/// Command of the frames received, on the first port of the TNC.
+const DATA_FRAME: u8 = 0x00;
+
+/// `frame` as a KISS data frame.
+pub fn encode(frame: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(frame.len() + 4);
+    encoded.extend([FEND, DATA_FRAME]);
+    for &byte in frame {
+        match byte {
+            FEND => encoded.extend([FESC, TFEND]),
+            FESC => encoded.extend([FESC, TFESC]),
+            byte => encoded.push(byte),
+        }
+    }
+    encoded.push(FEND);
+    encoded
+}
+
+/// Sends each of the `frames` to the clients connected to `listener`, until aborted. Frames sent
+/// by the clients are not transmitted.
+pub async fn serve(listener: TcpListener, frames: broadcast::Sender<Vec<u8>>) {
+    loop {
+        match listener.accept().await {
+            Ok((client, address)) => {
+                debug!(%address, "KISS client connected");
+                tokio::spawn(send_frames(client, frames.subscribe()));
+            }
+            Err(e) => warn!(?e, "failed to accept KISS client"),
+        }
+    }
+}
+
+async fn send_frames(mut client: TcpStream, mut frames: broadcast::Receiver<Vec<u8>>) {
+    let mut buf = [0; 512];
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    if client.write_all(&encode(&frame)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("KISS client lagging, skipped {n} frames");
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            read = client.read(&mut buf) => {
+                if matches!(read, Ok(0) | Err(_)) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn special_bytes_are_escaped() {
+        assert_eq!(
+            encode(&[0x01, FEND, 0x02, FESC]),
+            [FEND, 0x00, 0x01, FESC, TFEND, 0x02, FESC, TFESC, FEND]
+        );
+    }
+
+    #[tokio::test]
+    async fn frames_are_sent_to_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (frames, _) = broadcast::channel(4);
+        let server = tokio::spawn(serve(listener, frames.clone()));
+
+        let mut client = TcpStream::connect(address).await.unwrap();
+        // Connected once the server subscribed it
+        while frames.receiver_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        frames.send(b"packet".to_vec()).unwrap();
+
+        let mut received = [0; 9];
+        tokio::time::timeout(Duration::from_secs(5), client.read_exact(&mut received))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received, *b"\xc0\x00packet\xc0");
+        server.abort();
+    }
+}
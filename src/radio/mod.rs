@@ -0,0 +1,577 @@
+//! Receiving with an SDR: tunes a device and streams its IQ samples to a UDP destination, a file
+//! or a [SigMF](sigmf) recording until stopped.
+//!
+//! The device is driven by the command line tools of its driver, `rx_sdr` for SoapySDR devices
+//! and `rtl_sdr` for RTL-SDRs, reading the samples from their standard output. The power spectrum
+//! of the samples can be kept on the side, see [`spectrum`], and the AX.25 packets in them
+//! demodulated, see [`ax25`].
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::str::FromStr;
+
+use chrono::Utc;
+use rustfft::num_complex::Complex32;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UdpSocket;
+use tokio::process::Command;
+use tracing::info;
+
+use ax25::{PacketOutput, PacketWriter};
+use spectrum::{Analyzer, SpectrumOutput, SpectrumWriter};
+
+pub mod afsk;
+pub mod ax25;
+pub mod kiss;
+pub mod sigmf;
+pub mod spectrum;
+
+/// Largest UDP payload sent, fitting the MTU of an Ethernet link. A multiple of the size of a
+/// sample in every format, so that samples are not split across datagrams.
+const DATAGRAM_SIZE: usize = 1472;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0} does not support the {1} format")]
+    UnsupportedFormat(&'static str, SampleFormat),
+    #[error("failed to run {0}: {1}")]
+    Spawn(&'static str, std::io::Error),
+    #[error("{0} exited with {1}")]
+    Exited(&'static str, ExitStatus),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The tools driving the SDR.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// `rx_sdr`, for any device with a SoapySDR driver
+    Soapy,
+    /// `rtl_sdr`, only supporting the `cu8` format
+    RtlSdr,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "soapy" | "soapysdr" => Ok(Backend::Soapy),
+            "rtlsdr" | "rtl-sdr" => Ok(Backend::RtlSdr),
+            other => Err(format!(
+                "unknown SDR backend '{other}', expected soapy/rtlsdr"
+            )),
+        }
+    }
+}
+
+/// Format of the IQ samples streamed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleFormat {
+    /// Unsigned 8 bit integers
+    Cu8,
+    /// Signed 16 bit integers
+    Cs16,
+    /// 32 bit floats
+    Cf32,
+}
+
+impl SampleFormat {
+    /// Bytes of a sample, both the I and Q components.
+    pub fn sample_size(self) -> usize {
+        match self {
+            SampleFormat::Cu8 => 2,
+            SampleFormat::Cs16 => 4,
+            SampleFormat::Cf32 => 8,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SampleFormat::Cu8 => "cu8",
+            SampleFormat::Cs16 => "cs16",
+            SampleFormat::Cf32 => "cf32",
+        }
+    }
+}
+
+/// Decodes samples from bytes read in any size, which may start or end in the middle of a sample.
+pub(crate) struct SampleDecoder {
+    format: SampleFormat,
+    /// Bytes of a sample split across reads
+    partial: Vec<u8>,
+}
+
+impl SampleDecoder {
+    pub(crate) fn new(format: SampleFormat) -> Self {
+        Self {
+            format,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Calls `f` with each sample completed by `bytes`, scaled so that full scale is 1.
+    pub(crate) fn decode(&mut self, bytes: &[u8], mut f: impl FnMut(Complex32)) {
+        let size = self.format.sample_size();
+        let mut bytes = bytes;
+        if !self.partial.is_empty() {
+            let missing = (size - self.partial.len()).min(bytes.len());
+            self.partial.extend_from_slice(&bytes[..missing]);
+            bytes = &bytes[missing..];
+            if self.partial.len() < size {
+                return;
+            }
+            f(sample_at(self.format, &self.partial));
+            self.partial.clear();
+        }
+        let whole = bytes.len() - bytes.len() % size;
+        for sample in bytes[..whole].chunks_exact(size) {
+            f(sample_at(self.format, sample));
+        }
+        self.partial.extend_from_slice(&bytes[whole..]);
+    }
+}
+
+/// The sample in `bytes`, scaled so that full scale is 1.
+fn sample_at(format: SampleFormat, bytes: &[u8]) -> Complex32 {
+    match format {
+        SampleFormat::Cu8 => Complex32::new(
+            (bytes[0] as f32 - 127.5) / 127.5,
+            (bytes[1] as f32 - 127.5) / 127.5,
+        ),
+        SampleFormat::Cs16 => Complex32::new(
+            i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            i16::from_le_bytes([bytes[2], bytes[3]]) as f32 / 32768.0,
+        ),
+        SampleFormat::Cf32 => Complex32::new(
+            f32::from_le_bytes(bytes[0..4].try_into().expect("4 bytes")),
+            f32::from_le_bytes(bytes[4..8].try_into().expect("4 bytes")),
+        ),
+    }
+}
+
+impl std::fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SampleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "cu8" => Ok(SampleFormat::Cu8),
+            "cs16" => Ok(SampleFormat::Cs16),
+            "cf32" => Ok(SampleFormat::Cf32),
+            other => Err(format!(
+                "unknown sample format '{other}', expected cu8/cs16/cf32"
+            )),
+        }
+    }
+}
+
+/// Where the samples are streamed to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    /// Datagrams sent to the address
+    Udp(String),
+    /// Written to the file
+    File(PathBuf),
+    /// Recorded in SigMF format, to the data and metadata files with the path given and the
+    /// [`sigmf`] extensions
+    Record(PathBuf),
+}
+
+/// Parses strings like `udp=127.0.0.1:5000`, `file=recording.cs16` or `record=pass`.
+impl FromStr for Destination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected 'type=address', got '{s}'"))?;
+        match key.trim().to_lowercase().as_str() {
+            "udp" => Ok(Destination::Udp(value.to_string())),
+            "file" => Ok(Destination::File(value.into())),
+            "record" => Ok(Destination::Record(value.into())),
+            other => Err(format!(
+                "unknown destination type '{other}', expected udp/file/record"
+            )),
+        }
+    }
+}
+
+/// A stream of IQ samples received by an SDR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stream {
+    pub backend: Backend,
+    /// Device to open: SoapySDR device arguments, e.g. `driver=rtlsdr,serial=01`, or the index of
+    /// an RTL-SDR. The first device found if unset.
+    pub device: Option<String>,
+    /// Center frequency (Hz)
+    pub frequency: u64,
+    /// Sample rate (Hz), the bandwidth received
+    pub sample_rate: u64,
+    /// Gain (dB), automatic if unset
+    pub gain: Option<f64>,
+    pub format: SampleFormat,
+    /// Where the samples go, if anywhere
+    pub out: Option<Destination>,
+    /// Also keep the power spectrum of the samples here
+    pub spectrum: Option<SpectrumOutput>,
+    /// Also demodulate the AX.25 packets in the samples, sending them there
+    pub packets: Option<PacketOutput>,
+    /// Satellite received, described as such in recordings
+    pub satellite: Option<String>,
+}
+
+impl Stream {
+    /// The program and arguments writing the samples of the stream to their standard output.
+    pub fn command(&self) -> Result<(&'static str, Vec<String>), Error> {
+        let mut args = vec![
+            "-f".to_string(),
+            self.frequency.to_string(),
+            "-s".to_string(),
+            self.sample_rate.to_string(),
+        ];
+        if let Some(gain) = self.gain {
+            args.extend(["-g".to_string(), gain.to_string()]);
+        }
+        if let Some(device) = &self.device {
+            args.extend(["-d".to_string(), device.clone()]);
+        }
+        let program = match self.backend {
+            Backend::Soapy => {
+                args.extend(["-F".to_string(), self.format.name().to_uppercase()]);
+                "rx_sdr"
+            }
+            Backend::RtlSdr if self.format == SampleFormat::Cu8 => "rtl_sdr",
+            Backend::RtlSdr => return Err(Error::UnsupportedFormat("rtl_sdr", self.format)),
+        };
+        args.push("-".to_string());
+        Ok((program, args))
+    }
+}
+
+/// Streams the samples of `stream` until `shutdown` completes, returning the number of bytes
+/// streamed. Fails if the SDR tool cannot be started or stops by itself with an error.
+///
+/// The metadata of recordings is written when they start, so that they are described even if
+/// the stream fails, and again with the number of samples recorded when they end.
+pub async fn run(stream: &Stream, shutdown: impl Future<Output = ()>) -> Result<u64, Error> {
+    let (program, args) = stream.command()?;
+    info!(
+        program,
+        frequency = stream.frequency,
+        sample_rate = stream.sample_rate,
+        format = %stream.format,
+        out = ?stream.out,
+        "starting SDR stream"
+    );
+    let spectrum = stream.spectrum.as_ref().map(|output| {
+        let analyzer = Analyzer::new(stream.format, output.bins);
+        SpectrumWriter::new(
+            analyzer,
+            stream.frequency,
+            stream.sample_rate,
+            output.path.clone(),
+        )
+    });
+    let packets = match &stream.packets {
+        Some(output) => Some(PacketWriter::start(output, stream.format, stream.sample_rate).await?),
+        None => None,
+    };
+    let recording = match &stream.out {
+        Some(Destination::Record(base)) => {
+            let metadata = sigmf::Metadata::new(stream, Utc::now());
+            metadata.write(base)?;
+            Some((base, metadata))
+        }
+        _ => None,
+    };
+    let out = stream.out.as_ref();
+    let bytes = pipe(program, &args, out, spectrum, packets, shutdown).await?;
+    if let Some((base, mut metadata)) = recording {
+        metadata.finish(bytes / stream.format.sample_size() as u64);
+        metadata.write(base)?;
+    }
+    Ok(bytes)
+}
+
+/// Where the samples are written.
+enum Sink {
+    Udp(UdpSocket),
+    File(File),
+    Discard,
+}
+
+impl Sink {
+    async fn write(&mut self, samples: &[u8]) -> std::io::Result<()> {
+        match self {
+            _ if samples.is_empty() => Ok(()),
+            Sink::Udp(socket) => socket.send(samples).await.map(|_| ()),
+            Sink::File(file) => file.write_all(samples).await,
+            Sink::Discard => Ok(()),
+        }
+    }
+}
+
+/// Runs `program` with `args`, copying its standard output to `out`, `spectrum` and `packets` if
+/// given, until it exits or `shutdown` completes.
+async fn pipe(
+    program: &'static str,
+    args: &[String],
+    out: Option<&Destination>,
+    mut spectrum: Option<SpectrumWriter>,
+    mut packets: Option<PacketWriter>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<u64, Error> {
+    let mut sink = match out {
+        None => Sink::Discard,
+        Some(Destination::Udp(address)) => {
+            let socket = UdpSocket::bind("0.0.0.0:0").await?;
+            socket.connect(address).await?;
+            Sink::Udp(socket)
+        }
+        Some(Destination::File(path)) => Sink::File(File::create(path).await?),
+        Some(Destination::Record(base)) => {
+            Sink::File(File::create(sigmf::path(base, sigmf::DATA_EXTENSION)).await?)
+        }
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| Error::Spawn(program, e))?;
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+
+    tokio::pin!(shutdown);
+    let mut buf = vec![0; DATAGRAM_SIZE];
+    let (mut filled, mut total) = (0, 0u64);
+    let stopped = loop {
+        let n = tokio::select! {
+            _ = &mut shutdown => break true,
+            n = stdout.read(&mut buf[filled..]) => n?,
+        };
+        if n == 0 {
+            break false;
+        }
+        if let Some(spectrum) = &mut spectrum {
+            spectrum.push(&buf[filled..filled + n]);
+        }
+        if let Some(packets) = &mut packets {
+            packets.push(&buf[filled..filled + n]);
+        }
+        filled += n;
+        total += n as u64;
+        // Datagrams are only sent full, except the last
+        if filled == buf.len() {
+            sink.write(&buf).await?;
+            filled = 0;
+        }
+    };
+    sink.write(&buf[..filled]).await?;
+    if let Sink::File(file) = &mut sink {
+        file.flush().await?;
+    }
+
+    if stopped {
+        info!(program, bytes = total, "stopping SDR stream");
+        child.kill().await?;
+        return Ok(total);
+    }
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(Error::Exited(program, status));
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn stream(backend: Backend, format: SampleFormat) -> Stream {
+        Stream {
+            backend,
+            device: Some("driver=rtlsdr".into()),
+            frequency: 137_100_000,
+            sample_rate: 1_024_000,
+            gain: Some(40.0),
+            format,
+            out: Some("udp=127.0.0.1:5000".parse().unwrap()),
+            spectrum: None,
+            packets: None,
+            satellite: None,
+        }
+    }
+
+    #[test]
+    fn commands_tune_the_device() {
+        let (program, args) = stream(Backend::Soapy, SampleFormat::Cs16)
+            .command()
+            .unwrap();
+        assert_eq!(program, "rx_sdr");
+        assert_eq!(
+            args.join(" "),
+            "-f 137100000 -s 1024000 -g 40 -d driver=rtlsdr -F CS16 -"
+        );
+
+        let (program, _) = stream(Backend::RtlSdr, SampleFormat::Cu8)
+            .command()
+            .unwrap();
+        assert_eq!(program, "rtl_sdr");
+        assert!(matches!(
+            stream(Backend::RtlSdr, SampleFormat::Cs16).command(),
+            Err(Error::UnsupportedFormat("rtl_sdr", SampleFormat::Cs16))
+        ));
+    }
+
+    #[test]
+    fn destinations_are_parsed() {
+        assert_eq!(
+            "udp=127.0.0.1:5000".parse(),
+            Ok(Destination::Udp("127.0.0.1:5000".into()))
+        );
+        assert_eq!(
+            "file=pass.cf32".parse(),
+            Ok(Destination::File("pass.cf32".into()))
+        );
+        assert_eq!(
+            "record=NOAA 19".parse(),
+            Ok(Destination::Record("NOAA 19".into()))
+        );
+        assert!("tcp=localhost:5000".parse::<Destination>().is_err());
+    }
+
+    #[tokio::test]
+    async fn samples_are_sent_in_whole_datagrams() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let out = Destination::Udp(receiver.local_addr().unwrap().to_string());
+        let args = ["-c".to_string(), "head -c 3000 /dev/zero".to_string()];
+
+        let total = pipe("sh", &args, Some(&out), None, None, std::future::pending())
+            .await
+            .unwrap();
+        assert_eq!(total, 3000);
+        let mut sizes = Vec::new();
+        let mut buf = [0; 2048];
+        for _ in 0..3 {
+            sizes.push(receiver.recv(&mut buf).await.unwrap());
+        }
+        assert_eq!(
+            sizes,
+            [DATAGRAM_SIZE, DATAGRAM_SIZE, 3000 - 2 * DATAGRAM_SIZE]
+        );
+    }
+
+    #[tokio::test]
+    async fn spectrum_is_kept_beside_the_stream() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = Destination::File(tmp.path().join("samples.cu8"));
+        let path = tmp.path().join(spectrum::SPECTRUM_FILE);
+        let writer = SpectrumWriter::new(
+            Analyzer::new(SampleFormat::Cu8, 256),
+            137_100_000,
+            1_024_000,
+            path.clone(),
+        );
+        let args = [
+            "-c".to_string(),
+            "head -c 4096 /dev/zero; sleep 0.3; head -c 512 /dev/zero".to_string(),
+        ];
+
+        pipe(
+            "sh",
+            &args,
+            Some(&out),
+            Some(writer),
+            None,
+            std::future::pending(),
+        )
+        .await
+        .unwrap();
+        let spectrum = spectrum::Spectrum::from_bytes(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(spectrum.frequency, 137_100_000);
+        assert_eq!(spectrum.power.len(), 256);
+    }
+
+    #[tokio::test]
+    async fn packets_are_demodulated_and_logged() {
+        let tmp = tempfile::tempdir().unwrap();
+        let samples = tmp.path().join("pass.cs16");
+        let frame = b"\x82\xa0\xa4\xa6\x40\x40\xe0\x9c\x6e\x98\x8a\x9a\x40\x61\x03\xf0Hi";
+        std::fs::write(&samples, afsk::tests::modulate(frame, 240_000)).unwrap();
+        let log = tmp.path().join(ax25::PACKETS_FILE);
+        let output = PacketOutput {
+            kiss: None,
+            log: Some(log.clone()),
+        };
+        let packets = PacketWriter::start(&output, SampleFormat::Cs16, 240_000)
+            .await
+            .unwrap();
+        let args = [samples.display().to_string()];
+
+        pipe(
+            "cat",
+            &args,
+            None,
+            None,
+            Some(packets),
+            std::future::pending(),
+        )
+        .await
+        .unwrap();
+        let log = std::fs::read_to_string(log).unwrap();
+        assert_eq!(log.lines().count(), 1, "{log}");
+        assert!(log.trim_end().ends_with(" N7LEM>APRS:Hi"), "{log}");
+    }
+
+    #[tokio::test]
+    async fn recordings_are_written_to_their_data_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = Destination::Record(tmp.path().join("NOAA 19"));
+        let args = ["-c".to_string(), "printf iqiq".to_string()];
+
+        pipe("sh", &args, Some(&out), None, None, std::future::pending())
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(tmp.path().join("NOAA 19.sigmf-data")).unwrap(),
+            b"iqiq"
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_stop_on_shutdown_and_fail_when_the_tool_does() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = Destination::File(tmp.path().join("samples.cu8"));
+
+        let args = ["-c".to_string(), "printf iq; sleep 10".to_string()];
+        let shutdown = tokio::time::sleep(Duration::from_millis(200));
+        assert_eq!(
+            pipe("sh", &args, Some(&out), None, None, shutdown)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            std::fs::read(tmp.path().join("samples.cu8")).unwrap(),
+            b"iq"
+        );
+
+        let args = ["-c".to_string(), "exit 3".to_string()];
+        assert!(matches!(
+            pipe("sh", &args, Some(&out), None, None, std::future::pending()).await,
+            Err(Error::Exited("sh", _))
+        ));
+    }
+}
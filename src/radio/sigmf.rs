@@ -0,0 +1,168 @@
+//! Recordings in the [SigMF](https://sigmf.org) format: the samples as they are received in a
+//! `.sigmf-data` file, described by the JSON metadata in the `.sigmf-meta` file beside it.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{SampleFormat, Stream};
+
+pub const DATA_EXTENSION: &str = "sigmf-data";
+pub const META_EXTENSION: &str = "sigmf-meta";
+
+const VERSION: &str = "1.0.0";
+
+/// The file of the recording `base` with `extension`, added to the name rather than replacing
+/// what follows a dot in it.
+pub fn path(base: &Path, extension: &str) -> PathBuf {
+    let mut path = OsString::from(base.as_os_str());
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// The SigMF datatype of samples in `format`.
+fn datatype(format: SampleFormat) -> &'static str {
+    match format {
+        SampleFormat::Cu8 => "cu8",
+        SampleFormat::Cs16 => "ci16_le",
+        SampleFormat::Cf32 => "cf32_le",
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Global {
+    #[serde(rename = "core:datatype")]
+    pub datatype: String,
+    #[serde(rename = "core:sample_rate")]
+    pub sample_rate: f64,
+    #[serde(rename = "core:version")]
+    pub version: String,
+    #[serde(rename = "core:recorder")]
+    pub recorder: String,
+    #[serde(rename = "core:description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "core:hw", skip_serializing_if = "Option::is_none")]
+    pub hw: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Capture {
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    #[serde(rename = "core:frequency")]
+    pub frequency: f64,
+    #[serde(rename = "core:datetime")]
+    pub datetime: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Annotation {
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    #[serde(rename = "core:sample_count")]
+    pub sample_count: u64,
+    #[serde(rename = "core:freq_lower_edge")]
+    pub freq_lower_edge: f64,
+    #[serde(rename = "core:freq_upper_edge")]
+    pub freq_upper_edge: f64,
+    #[serde(rename = "core:label", skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// What is kept in the `.sigmf-meta` file of a recording.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Metadata {
+    pub global: Global,
+    pub captures: Vec<Capture>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Metadata {
+    /// The metadata of a recording of `stream` started at `start`.
+    pub fn new(stream: &Stream, start: DateTime<Utc>) -> Self {
+        Self {
+            global: Global {
+                datatype: datatype(stream.format).to_string(),
+                sample_rate: stream.sample_rate as f64,
+                version: VERSION.to_string(),
+                recorder: format!("sat-o-mat {}", env!("CARGO_PKG_VERSION")),
+                description: stream.satellite.clone(),
+                hw: stream.device.clone(),
+            },
+            captures: vec![Capture {
+                sample_start: 0,
+                frequency: stream.frequency as f64,
+                datetime: start,
+            }],
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Marks the `samples` recorded as receiving the satellite, once the recording is over.
+    pub fn finish(&mut self, samples: u64) {
+        let capture = &self.captures[0];
+        let half_bandwidth = self.global.sample_rate / 2.0;
+        self.annotations = vec![Annotation {
+            sample_start: 0,
+            sample_count: samples,
+            freq_lower_edge: capture.frequency - half_bandwidth,
+            freq_upper_edge: capture.frequency + half_bandwidth,
+            label: self.global.description.clone(),
+        }];
+    }
+
+    /// Writes the `.sigmf-meta` file of the recording `base`.
+    pub fn write(&self, base: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path(base, META_EXTENSION), json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::radio::{Backend, Destination};
+
+    #[test]
+    fn metadata_describes_the_recording() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("NOAA 19.pass");
+        let stream = Stream {
+            backend: Backend::Soapy,
+            device: Some("driver=rtlsdr".into()),
+            frequency: 137_100_000,
+            sample_rate: 1_024_000,
+            gain: None,
+            format: SampleFormat::Cs16,
+            out: Some(Destination::Record(base.clone())),
+            spectrum: None,
+            packets: None,
+            satellite: Some("NOAA 19".into()),
+        };
+        let start = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        let mut metadata = Metadata::new(&stream, start);
+        metadata.finish(2048);
+        metadata.write(&base).unwrap();
+
+        let path = tmp.path().join("NOAA 19.pass.sigmf-meta");
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(json["global"]["core:datatype"], "ci16_le");
+        assert_eq!(json["global"]["core:sample_rate"], 1_024_000.0);
+        assert_eq!(json["global"]["core:description"], "NOAA 19");
+        assert_eq!(json["captures"][0]["core:frequency"], 137_100_000.0);
+        assert_eq!(json["captures"][0]["core:datetime"], "2026-01-02T03:04:05Z");
+        assert_eq!(json["annotations"][0]["core:sample_count"], 2048);
+        assert_eq!(
+            json["annotations"][0]["core:freq_lower_edge"],
+            136_588_000.0
+        );
+        assert_eq!(json["annotations"][0]["core:label"], "NOAA 19");
+        assert_eq!(serde_json::from_value::<Metadata>(json).unwrap(), metadata);
+    }
+}
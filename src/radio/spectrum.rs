@@ -0,0 +1,257 @@
+//! Power spectra of the samples of a stream, kept in a file for the live waterfall of the web UI.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rustfft::num_complex::Complex32;
+use rustfft::{Fft, FftPlanner};
+use tracing::warn;
+
+use super::{SampleDecoder, SampleFormat};
+
+/// File the latest [`Spectrum`] of a stream is kept in, in the artifacts of the run it is a step
+/// of.
+pub const SPECTRUM_FILE: &str = "spectrum.bin";
+
+/// How often the spectrum is computed from the samples received since the last one.
+pub const SPECTRUM_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Bytes before the power of the bins in an encoded [`Spectrum`].
+const HEADER_SIZE: usize = 24;
+
+/// Where the spectrum of a stream is kept, and in how many bins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrumOutput {
+    pub path: PathBuf,
+    pub bins: usize,
+}
+
+/// The average power of the samples received over a short time, in bins of equal bandwidth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrum {
+    /// End of the time averaged
+    pub time: DateTime<Utc>,
+    /// Center frequency (Hz) of the stream
+    pub frequency: u64,
+    /// Sample rate (Hz), the bandwidth of the spectrum
+    pub sample_rate: u64,
+    /// Power (dB relative to full scale) of each bin, from the lowest frequency to the highest
+    pub power: Vec<f32>,
+}
+
+impl Spectrum {
+    /// Encodes the spectrum as sent to the web UI, all little endian: the time (milliseconds
+    /// since the Unix epoch, i64), the center frequency and the sample rate (Hz, u64), then the
+    /// power of each bin (f32).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 4 * self.power.len());
+        bytes.extend(self.time.timestamp_millis().to_le_bytes());
+        bytes.extend(self.frequency.to_le_bytes());
+        bytes.extend(self.sample_rate.to_le_bytes());
+        for power in &self.power {
+            bytes.extend(power.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a spectrum encoded by [`Spectrum::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE || !(bytes.len() - HEADER_SIZE).is_multiple_of(4) {
+            return None;
+        }
+        let word = |i: usize| bytes[i..i + 8].try_into().expect("8 bytes");
+        Some(Self {
+            time: DateTime::from_timestamp_millis(i64::from_le_bytes(word(0)))?,
+            frequency: u64::from_le_bytes(word(8)),
+            sample_rate: u64::from_le_bytes(word(16)),
+            power: bytes[HEADER_SIZE..]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().expect("4 bytes")))
+                .collect(),
+        })
+    }
+}
+
+/// Averages the power spectra of blocks of samples, in the order they are received.
+pub struct Analyzer {
+    samples: SampleDecoder,
+    fft: Arc<dyn Fft<f32>>,
+    /// Hann window, normalized so that a full scale tone is at 0 dB
+    window: Vec<f32>,
+    block: Vec<Complex32>,
+    /// Sum of the power of each bin over the `blocks` transformed since the last spectrum
+    sum: Vec<f32>,
+    blocks: usize,
+}
+
+impl Analyzer {
+    /// An analyzer of samples in `format`, with `size` bins.
+    pub fn new(format: SampleFormat, size: usize) -> Self {
+        let hann: Vec<f32> = (0..size)
+            .map(|i| {
+                (std::f32::consts::PI * i as f32 / size as f32)
+                    .sin()
+                    .powi(2)
+            })
+            .collect();
+        let gain: f32 = hann.iter().sum();
+        Self {
+            samples: SampleDecoder::new(format),
+            fft: FftPlanner::new().plan_fft_forward(size),
+            window: hann.into_iter().map(|w| w / gain).collect(),
+            block: Vec::with_capacity(size),
+            sum: vec![0.0; size],
+            blocks: 0,
+        }
+    }
+
+    /// Adds the samples in `bytes`, which may start or end in the middle of a sample.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let Self {
+            samples,
+            fft,
+            window,
+            block,
+            sum,
+            blocks,
+        } = self;
+        samples.decode(bytes, |sample| {
+            block.push(sample * window[block.len()]);
+            if block.len() < window.len() {
+                return;
+            }
+            fft.process(block);
+            for (sum, bin) in sum.iter_mut().zip(block.iter()) {
+                *sum += bin.norm_sqr();
+            }
+            *blocks += 1;
+            block.clear();
+        });
+    }
+
+    /// The average power (dBFS) of each bin of the blocks added since the last call, from the
+    /// lowest frequency to the highest. None if no block was completed.
+    pub fn take(&mut self) -> Option<Vec<f32>> {
+        if self.blocks == 0 {
+            return None;
+        }
+        let size = self.sum.len();
+        // The FFT puts the negative frequencies in the second half
+        let power = (0..size)
+            .map(|i| self.sum[(i + size.div_ceil(2)) % size] / self.blocks as f32)
+            .map(|power| 10.0 * power.max(1e-20).log10())
+            .collect();
+        self.sum.fill(0.0);
+        self.blocks = 0;
+        Some(power)
+    }
+}
+
+/// Writes the spectrum of a stream to a file every [`SPECTRUM_INTERVAL`].
+pub(super) struct SpectrumWriter {
+    analyzer: Analyzer,
+    frequency: u64,
+    sample_rate: u64,
+    /// Disabled after failing to write
+    path: Option<PathBuf>,
+    written: Instant,
+}
+
+impl SpectrumWriter {
+    pub(super) fn new(analyzer: Analyzer, frequency: u64, sample_rate: u64, path: PathBuf) -> Self {
+        Self {
+            analyzer,
+            frequency,
+            sample_rate,
+            path: Some(path),
+            written: Instant::now(),
+        }
+    }
+
+    pub(super) fn push(&mut self, samples: &[u8]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.analyzer.push(samples);
+        if self.written.elapsed() < SPECTRUM_INTERVAL {
+            return;
+        }
+        self.written = Instant::now();
+        let Some(power) = self.analyzer.take() else {
+            return;
+        };
+        let spectrum = Spectrum {
+            time: Utc::now(),
+            frequency: self.frequency,
+            sample_rate: self.sample_rate,
+            power,
+        };
+        if let Err(e) = write_spectrum(path, &spectrum) {
+            warn!(path = %path.display(), ?e, "failed to write spectrum, not trying again");
+            self.path = None;
+        }
+    }
+}
+
+/// Replaces the spectrum in `path`, so that readers never see part of it.
+fn write_spectrum(path: &Path, spectrum: &Spectrum) -> std::io::Result<()> {
+    let tmp = path.with_extension("bin.tmp");
+    std::fs::write(&tmp, spectrum.to_bytes())?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn spectra_are_encoded_and_decoded() {
+        let spectrum = Spectrum {
+            time: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            frequency: 137_100_000,
+            sample_rate: 1_024_000,
+            power: vec![-80.5, -3.25, 0.0],
+        };
+        let bytes = spectrum.to_bytes();
+        assert_eq!(bytes.len(), 24 + 3 * 4);
+        assert_eq!(&bytes[..8], &1_767_323_045_000i64.to_le_bytes());
+        assert_eq!(Spectrum::from_bytes(&bytes), Some(spectrum));
+        assert_eq!(Spectrum::from_bytes(&bytes[..25]), None);
+    }
+
+    #[test]
+    fn tones_are_found_in_their_bin() {
+        let size = 64;
+        let mut analyzer = Analyzer::new(SampleFormat::Cs16, size);
+        assert_eq!(analyzer.take(), None);
+
+        // A full scale tone at a quarter of the sample rate, in the bin 3/4 up the spectrum
+        let samples: Vec<u8> = (0..4 * size)
+            .flat_map(|n| {
+                let phase = std::f32::consts::FRAC_PI_2 * n as f32;
+                let (i, q) = (
+                    (phase.cos() * 32767.0) as i16,
+                    (phase.sin() * 32767.0) as i16,
+                );
+                [i.to_le_bytes(), q.to_le_bytes()].concat()
+            })
+            .collect();
+        // Split in the middle of samples
+        for chunk in samples.chunks(7) {
+            analyzer.push(chunk);
+        }
+        let power = analyzer.take().unwrap();
+        assert_eq!(power.len(), size);
+        let peak = (0..size)
+            .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+            .unwrap();
+        assert_eq!(peak, size / 2 + size / 4);
+        assert!(power[peak].abs() < 0.1, "{}", power[peak]);
+        assert!(power[size / 2] < -60.0);
+        assert_eq!(analyzer.take(), None);
+    }
+}
@@ -0,0 +1,411 @@
+//! Keeps the artifacts of the runs within the limits of the disk: deletes old runs, trims the runs
+//! over their size cap, and warns when the recordings of upcoming runs may fill the disk. Also
+//! deletes the tasks that were run or rejected long ago.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::api::runs::artifact_files;
+use crate::config::RetentionConfig;
+use crate::task::format::Task;
+
+const ARTIFACTS_DIR: &str = "Artifacts";
+
+/// States of the tasks no longer on the schedule, deleted after `max_history_days`.
+const HISTORY_STATES: &[&str] = &["Completed", "Failed", "Rejected"];
+
+/// Artifacts kept when trimming a run over its size cap.
+const KEPT_ARTIFACTS: &[&str] = &["execution_log.yaml", crate::logging::RUN_LOG];
+
+const MB: u64 = 1 << 20;
+
+/// The artifacts of a run.
+struct Run {
+    id: String,
+    dir: PathBuf,
+    /// Full path and size of each artifact
+    files: Vec<(PathBuf, u64)>,
+    modified: SystemTime,
+    /// Whether the task of the run is Active, so it may still be running
+    active: bool,
+}
+
+impl Run {
+    fn size(&self) -> u64 {
+        self.files.iter().map(|(_, size)| size).sum()
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Pruned {
+    /// IDs of the tasks deleted from the history, with their runs
+    pub tasks: Vec<String>,
+    /// IDs of the runs deleted, oldest first
+    pub runs: Vec<String>,
+    /// Artifacts deleted from runs over their size cap
+    pub files: Vec<PathBuf>,
+    /// Bytes freed
+    pub freed: u64,
+}
+
+/// Deletes artifacts from `tasks_path` until its runs are within the limits of `retention`, given
+/// the `free` bytes of its disk, if known.
+///
+/// The tasks older than `max_history_days` are deleted first, with their runs. Then runs over
+/// `max_run_mb` are trimmed, and the oldest runs are deleted while they are older than
+/// `max_age_days`, all runs take more than `max_total_mb` or the disk has less than `min_free_mb`
+/// free.
+pub fn prune(
+    tasks_path: &Path,
+    retention: &RetentionConfig,
+    free: Option<u64>,
+    now: SystemTime,
+) -> io::Result<Pruned> {
+    let mut pruned = Pruned::default();
+    if let Some(days) = retention.max_history_days {
+        prune_history(
+            tasks_path,
+            std::time::Duration::from_secs(days * 86400),
+            now,
+            &mut pruned,
+        )?;
+    }
+    let mut runs = runs(tasks_path)?;
+
+    if let Some(cap) = retention.max_run_mb.map(|mb| mb * MB) {
+        for run in runs.iter_mut().filter(|r| !r.active) {
+            let mut size = run.size();
+            // Largest first, which are the recordings
+            run.files.sort_by_key(|(_, size)| Reverse(*size));
+            for (path, len) in std::mem::take(&mut run.files) {
+                let kept = path
+                    .file_name()
+                    .is_some_and(|name| KEPT_ARTIFACTS.iter().any(|k| name == *k));
+                if size > cap && !kept {
+                    fs::remove_file(&path)?;
+                    size -= len;
+                    pruned.freed += len;
+                    pruned.files.push(path);
+                } else {
+                    run.files.push((path, len));
+                }
+            }
+        }
+    }
+
+    let max_age = retention
+        .max_age_days
+        .map(|days| std::time::Duration::from_secs(days * 86400));
+    let max_total = retention.max_total_mb.map(|mb| mb * MB);
+    let min_free = retention.min_free_mb.map(|mb| mb * MB);
+    let mut total: u64 = runs.iter().map(Run::size).sum();
+    let mut free = free.map(|free| free + pruned.freed);
+    for run in runs.iter().filter(|r| !r.active) {
+        let old =
+            max_age.is_some_and(|max| now.duration_since(run.modified).is_ok_and(|age| age > max));
+        let over_total = max_total.is_some_and(|max| total > max);
+        let low_space = min_free.zip(free).is_some_and(|(min, free)| free < min);
+        // The runs are sorted oldest first, so none of the remaining ones is over the limits
+        if !(old || over_total || low_space) {
+            break;
+        }
+        fs::remove_dir_all(&run.dir)?;
+        let size = run.size();
+        total -= size;
+        free = free.map(|free| free + size);
+        pruned.freed += size;
+        pruned.runs.push(run.id.clone());
+    }
+    Ok(pruned)
+}
+
+/// Deletes the tasks of the history last modified more than `max_age` before `now`, and their runs.
+fn prune_history(
+    tasks_path: &Path,
+    max_age: std::time::Duration,
+    now: SystemTime,
+    pruned: &mut Pruned,
+) -> io::Result<()> {
+    for state in HISTORY_STATES {
+        let entries = match fs::read_dir(tasks_path.join(state)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let modified = entry.metadata()?.modified()?;
+            if now.duration_since(modified).is_ok_and(|age| age > max_age) {
+                let id = Task::id_from_filename(&entry.file_name().to_string_lossy()).to_string();
+                fs::remove_file(entry.path())?;
+                let run = tasks_path.join(ARTIFACTS_DIR).join(&id);
+                if run.is_dir() {
+                    for (_, path) in artifact_files(&run)? {
+                        pruned.freed += fs::metadata(path)?.len();
+                    }
+                    fs::remove_dir_all(&run)?;
+                }
+                pruned.tasks.push(id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the runs of the Active tasks in `tasks_path` starting within `window` of `now` fit
+/// in the `free` bytes of its disk, leaving `min_free_mb`. Returns the IDs of the tasks whose runs
+/// may not fit, sorted by start time.
+///
+/// Each run is expected to take `max_run_mb`, or as much as the largest run kept if unset.
+pub fn check_upcoming(
+    tasks_path: &Path,
+    retention: &RetentionConfig,
+    free: u64,
+    now: DateTime<Utc>,
+    window: Duration,
+) -> io::Result<Vec<String>> {
+    let expected = match retention.max_run_mb {
+        Some(mb) => mb * MB,
+        None => runs(tasks_path)?
+            .iter()
+            .filter(|r| !r.active)
+            .map(Run::size)
+            .max()
+            .unwrap_or(0),
+    };
+    let min_free = retention.min_free_mb.unwrap_or(0) * MB;
+
+    let mut upcoming = Vec::new();
+    let entries = match fs::read_dir(tasks_path.join("Active")) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let Some(task) = fs::read_to_string(entry.path())
+            .ok()
+            .and_then(|yaml| Task::from_yaml_str(&yaml).ok())
+        else {
+            continue;
+        };
+        let Ok(start) = task.get_time_variable("start") else {
+            continue;
+        };
+        if start >= now && start - now <= window {
+            let id = Task::id_from_filename(&entry.file_name().to_string_lossy()).to_string();
+            upcoming.push((start, id));
+        }
+    }
+    upcoming.sort();
+
+    let mut needed = min_free;
+    let mut short = Vec::new();
+    for (_, id) in upcoming {
+        needed += expected;
+        if needed > free {
+            short.push(id);
+        }
+    }
+    Ok(short)
+}
+
+/// The runs with artifacts in `tasks_path`, oldest first.
+fn runs(tasks_path: &Path) -> io::Result<Vec<Run>> {
+    let entries = match fs::read_dir(tasks_path.join(ARTIFACTS_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut runs = Vec::new();
+    for entry in entries {
+        let dir = entry?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        let id = dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let mut modified = fs::metadata(&dir)?.modified()?;
+        let mut files = Vec::new();
+        for (_, path) in artifact_files(&dir)? {
+            let metadata = fs::metadata(&path)?;
+            modified = modified.max(metadata.modified()?);
+            files.push((path, metadata.len()));
+        }
+        runs.push(Run {
+            active: tasks_path.join("Active").join(Task::filename(&id)).exists(),
+            id,
+            dir,
+            files,
+            modified,
+        });
+    }
+    runs.sort_by_key(|r| r.modified);
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    /// Writes run `id` with a `size` byte recording, last modified `age_days` ago.
+    fn write_run(tasks_path: &Path, id: &str, size: usize, age_days: u64) {
+        let dir = tasks_path.join(ARTIFACTS_DIR).join(id);
+        fs::create_dir_all(dir.join("steps")).unwrap();
+        fs::write(dir.join("execution_log.yaml"), "[]").unwrap();
+        fs::write(dir.join("steps/0.raw"), vec![0u8; size]).unwrap();
+        let modified = SystemTime::now() - std::time::Duration::from_secs(age_days * 86400);
+        for path in [
+            dir.join("execution_log.yaml"),
+            dir.join("steps/0.raw"),
+            dir.join("steps"),
+            dir.clone(),
+        ] {
+            File::open(path).unwrap().set_modified(modified).unwrap();
+        }
+    }
+
+    fn write_active(tasks_path: &Path, id: &str, start: DateTime<Utc>) {
+        fs::create_dir_all(tasks_path.join("Active")).unwrap();
+        fs::write(
+            tasks_path.join("Active").join(Task::filename(id)),
+            format!(
+                "variables:\n  start: \"{}\"\n  end: \"{}\"\nsteps: []\n",
+                start.to_rfc3339(),
+                (start + Duration::minutes(10)).to_rfc3339()
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn old_artifacts_are_deleted() {
+        let tmp = tempfile::tempdir().unwrap();
+        for id in ["old", "running", "recent"] {
+            write_run(tmp.path(), id, 10, if id == "recent" { 0 } else { 10 });
+        }
+        write_active(tmp.path(), "running", Utc::now());
+
+        let retention = RetentionConfig {
+            max_age_days: Some(7),
+            ..Default::default()
+        };
+        let pruned = prune(tmp.path(), &retention, None, SystemTime::now()).unwrap();
+        assert_eq!(pruned.runs, ["old"]);
+        let artifacts = tmp.path().join(ARTIFACTS_DIR);
+        assert!(artifacts.join("running").exists());
+        assert!(artifacts.join("recent").exists());
+
+        let missing = tmp.path().join("missing");
+        assert_eq!(
+            prune(&missing, &retention, None, SystemTime::now()).unwrap(),
+            Pruned::default()
+        );
+    }
+
+    #[test]
+    fn old_tasks_are_deleted_with_their_runs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let old = SystemTime::now() - std::time::Duration::from_secs(40 * 86400);
+        for (state, id, modified) in [
+            ("Completed", "done", old),
+            ("Failed", "broken", old),
+            ("Rejected", "declined", old),
+            ("Completed", "recent", SystemTime::now()),
+        ] {
+            fs::create_dir_all(tmp.path().join(state)).unwrap();
+            let path = tmp.path().join(state).join(Task::filename(id));
+            fs::write(&path, "steps: []\n").unwrap();
+            File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        write_run(tmp.path(), "done", 10, 40);
+        write_run(tmp.path(), "recent", 10, 40);
+
+        let retention = RetentionConfig {
+            max_history_days: Some(30),
+            ..Default::default()
+        };
+        let mut pruned = prune(tmp.path(), &retention, None, SystemTime::now()).unwrap();
+        pruned.tasks.sort();
+        assert_eq!(pruned.tasks, ["broken", "declined", "done"]);
+        assert_eq!(pruned.freed, 12);
+        // The runs themselves are only deleted by their own limits
+        assert!(pruned.runs.is_empty());
+        assert!(!tmp.path().join(ARTIFACTS_DIR).join("done").exists());
+        assert!(tmp.path().join(ARTIFACTS_DIR).join("recent").exists());
+        assert!(tmp.path().join("Completed/recent.yaml").exists());
+    }
+
+    #[test]
+    fn runs_are_kept_within_the_size_limits() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_run(tmp.path(), "a", MB as usize, 3);
+        write_run(tmp.path(), "b", MB as usize, 2);
+        write_run(tmp.path(), "huge", 3 * MB as usize, 1);
+        write_run(tmp.path(), "c", MB as usize, 0);
+
+        let retention = RetentionConfig {
+            max_total_mb: Some(3),
+            max_run_mb: Some(2),
+            ..Default::default()
+        };
+        let pruned = prune(tmp.path(), &retention, None, SystemTime::now()).unwrap();
+        // The recording of the huge run goes first, then the oldest runs until within 3 MiB
+        let artifacts = tmp.path().join(ARTIFACTS_DIR);
+        assert_eq!(pruned.files, [artifacts.join("huge/steps/0.raw")]);
+        assert_eq!(pruned.runs, ["a"]);
+        assert!(artifacts.join("huge/execution_log.yaml").exists());
+        assert!(artifacts.join("b").exists());
+
+        // Low on free space, the oldest runs are deleted until enough is free
+        let retention = RetentionConfig {
+            min_free_mb: Some(2),
+            ..Default::default()
+        };
+        let pruned = prune(tmp.path(), &retention, Some(MB), SystemTime::now()).unwrap();
+        assert_eq!(pruned.runs, ["b"]);
+    }
+
+    #[test]
+    fn upcoming_runs_that_may_not_fit_are_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        write_run(tmp.path(), "past", 2 * MB as usize, 1);
+        write_active(tmp.path(), "first", now + Duration::minutes(10));
+        write_active(tmp.path(), "second", now + Duration::minutes(30));
+        write_active(tmp.path(), "tomorrow", now + Duration::days(1));
+
+        let retention = RetentionConfig {
+            min_free_mb: Some(1),
+            ..Default::default()
+        };
+        let window = Duration::hours(1);
+        // Runs are expected to be as large as the largest run kept
+        let short = check_upcoming(tmp.path(), &retention, 4 * MB, now, window).unwrap();
+        assert_eq!(short, ["second"]);
+        let short = check_upcoming(tmp.path(), &retention, 8 * MB, now, window).unwrap();
+        assert!(short.is_empty());
+
+        let retention = RetentionConfig {
+            max_run_mb: Some(4),
+            ..Default::default()
+        };
+        let short = check_upcoming(tmp.path(), &retention, 5 * MB, now, window).unwrap();
+        assert_eq!(short, ["second"]);
+    }
+}
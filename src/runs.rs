@@ -0,0 +1,304 @@
+//! Review of past runs from their artifacts, e.g. over SSH without the web UI.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, anyhow, bail};
+use clap::{Args, Subcommand};
+
+use crate::api::review::review_steps;
+use crate::api::runs::{ApiExecutionSummary, artifact_files, run_result};
+use crate::client::table;
+use crate::config::Config;
+use crate::task::format::Task;
+use crate::task::runner::{EXECUTION_LOG, LogEntry, StepResult, read_execution_log};
+
+/// Directory of the run artifacts in `tasks_path`, named after the task IDs.
+const ARTIFACTS_DIR: &str = "Artifacts";
+
+/// File in the artifact directory with the task as it was run, variables resolved.
+const RESOLVED_TASK: &str = "task.yml";
+
+#[derive(Args)]
+pub struct RunsArgs {
+    /// Review the runs of this hosted station instead of the main one
+    #[arg(long)]
+    pub station: Option<String>,
+
+    #[command(subcommand)]
+    pub command: RunsCommand,
+}
+
+#[derive(Subcommand)]
+pub enum RunsCommand {
+    /// Lists the runs, most recent first
+    List,
+    /// Prints the outcome of each step of a run, the hardware it used and its artifacts
+    Show { id: String },
+}
+
+/// Runs the command, returning what to print.
+pub fn run(args: RunsArgs, config: &Config) -> anyhow::Result<String> {
+    let artifacts = config.tasks_path.join(ARTIFACTS_DIR);
+    match args.command {
+        RunsCommand::List => {
+            let mut runs = Vec::new();
+            for entry in fs::read_dir(&artifacts)
+                .with_context(|| format!("failed to read {}", artifacts.display()))?
+            {
+                let dir = entry?.path();
+                if dir.is_dir() {
+                    let id = dir.file_name().unwrap_or_default().to_string_lossy();
+                    runs.push(summary(config, &id, &dir));
+                }
+            }
+            runs.sort_by(|a, b| b[1].cmp(&a[1]).then_with(|| a[0].cmp(&b[0])));
+            Ok(table(
+                ["ID", "START", "STATE", "STEPS", "RESULT"],
+                runs.into_iter(),
+            ))
+        }
+        RunsCommand::Show { id } => {
+            if id.contains(['/', '\\']) || id == "." || id == ".." {
+                bail!("invalid run ID {id}");
+            }
+            let dir = artifacts.join(&id);
+            if !dir.is_dir() {
+                bail!("run {id} not found in {}", artifacts.display());
+            }
+            show(config, &id, &dir)
+        }
+    }
+}
+
+/// Row of `runs list`: ID, start, state of the task, steps completed and result.
+fn summary(config: &Config, id: &str, dir: &Path) -> [String; 5] {
+    let start = resolved_task(dir)
+        .and_then(|task| task.get_time_variable("start").ok())
+        .map_or_else(|| "-".to_string(), |t| t.to_rfc3339());
+    let log = read_execution_log(dir).unwrap_or_default();
+    let summary = ApiExecutionSummary::from(log.as_slice());
+    [
+        id.to_string(),
+        start,
+        task_state(config, id),
+        format!("{}/{}", summary.succeeded, summary.steps),
+        summary.result,
+    ]
+}
+
+fn show(config: &Config, id: &str, dir: &Path) -> anyhow::Result<String> {
+    let task =
+        resolved_task(dir).ok_or_else(|| anyhow!("{RESOLVED_TASK} not found in run {id}"))?;
+    let log = read_execution_log(dir)
+        .with_context(|| format!("failed to read the execution log of {id}"))?;
+
+    let mut output = format!("{id}: {} ({})\n", run_result(&log), task_state(config, id));
+    for name in ["start", "end"] {
+        if let Ok(time) = task.get_time_variable(name) {
+            output.push_str(&format!("{name}: {}\n", time.to_rfc3339()));
+        }
+    }
+    for name in ["satellite", "norad_id"] {
+        if let Some(value) = task.variables.get(name) {
+            output.push_str(&format!("{name}: {value}\n"));
+        }
+    }
+
+    // Pointing and radio: the commands sent to each station resource
+    let mut used: BTreeMap<&str, (bool, Vec<String>)> = BTreeMap::new();
+    let steps = review_steps(&task.steps, &task.variables, &config.resources);
+    let cleanup = review_steps(&task.cleanup, &task.variables, &config.resources);
+    for step in steps.iter().chain(&cleanup) {
+        for resource in &step.resources {
+            let (transmit, commands) = used.entry(resource).or_default();
+            *transmit |= step.transmit;
+            commands.push(step.command.clone());
+        }
+    }
+    if !used.is_empty() {
+        output.push_str("\nresources:\n");
+        for (resource, (transmit, commands)) in used {
+            let transmit = if transmit { ", transmits" } else { "" };
+            output.push_str(&format!(
+                "  {resource} ({} commands{transmit})\n",
+                commands.len()
+            ));
+            for command in commands {
+                output.push_str(&format!("    {command}\n"));
+            }
+        }
+    }
+
+    output.push_str("\nsteps:\n");
+    if log.is_empty() {
+        output.push_str("  none ran\n");
+    }
+    let rows = log.iter().map(|entry| {
+        [
+            entry.time.format("%H:%M:%S").to_string(),
+            step_result(entry),
+            entry.cmd.clone(),
+        ]
+    });
+    for line in table(["TIME", "RESULT", "COMMAND"], rows).lines().skip(1) {
+        output.push_str(&format!("  {line}\n"));
+    }
+
+    output.push_str("\nartifacts:\n");
+    for (name, path) in artifact_files(dir)? {
+        if name == RESOLVED_TASK || name == EXECUTION_LOG {
+            continue;
+        }
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+        output.push_str(&format!("  {name} ({size} bytes)\n"));
+    }
+    Ok(output)
+}
+
+fn resolved_task(dir: &Path) -> Option<Task> {
+    let yaml = fs::read_to_string(dir.join(RESOLVED_TASK)).ok()?;
+    Task::from_yaml_str(&yaml).ok()
+}
+
+/// State of the task of the run, e.g. `Completed`, or `-` if it no longer exists.
+fn task_state(config: &Config, id: &str) -> String {
+    ["Active", "Completed", "Failed"]
+        .into_iter()
+        .find(|state| {
+            config
+                .tasks_path
+                .join(state)
+                .join(Task::filename(id))
+                .exists()
+        })
+        .unwrap_or("-")
+        .to_string()
+}
+
+/// Overall result of a run from its execution log.
+fn step_result(entry: &LogEntry) -> String {
+    let result = match entry.result {
+        StepResult::Completed => "completed",
+        StepResult::Aborted => "aborted",
+        StepResult::SpawnError => "spawn error",
+        StepResult::TimedOut => "timed out",
+        StepResult::Uploaded => "uploaded",
+        StepResult::UploadFailed => "upload failed",
+    };
+    let result = match (entry.exit_code, &entry.error) {
+        (_, Some(error)) => format!("{result}: {error}"),
+        (Some(code), None) if code != 0 => format!("{result} ({code})"),
+        _ => result.to_string(),
+    };
+    match entry.attempts {
+        Some(attempts) => format!("{result} after {attempts} attempts"),
+        None => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ResourceConfig;
+
+    const TASK: &str = "\
+variables:
+  start: \"2099-06-01T10:00:00Z\"
+  end: \"2099-06-01T10:10:00Z\"
+  satellite: NanoFF A
+steps:
+  - rotctl P 180 10
+  - rigctl F 437000000
+  - echo done
+";
+
+    const LOG: &str = "\
+- time: 2099-06-01T10:00:01Z
+  cmd: rotctl P 180 10
+  result: completed
+  exit_code: 0
+- time: 2099-06-01T10:00:02Z
+  cmd: rigctl F 437000000
+  result: completed
+  exit_code: 1
+- time: 2099-06-01T10:00:03Z
+  cmd: echo done
+  result: aborted
+  error: exit signal received
+";
+
+    fn setup() -> (tempfile::TempDir, Config) {
+        let tmp = tempfile::tempdir().unwrap();
+        let run = tmp.path().join("Artifacts/pass");
+        fs::create_dir_all(run.join("iq")).unwrap();
+        fs::write(run.join(RESOLVED_TASK), TASK).unwrap();
+        fs::write(run.join(EXECUTION_LOG), LOG).unwrap();
+        fs::write(run.join("iq/recording.cf32"), [0; 16]).unwrap();
+        fs::create_dir_all(tmp.path().join("Failed")).unwrap();
+        fs::write(tmp.path().join("Failed/pass.yaml"), TASK).unwrap();
+        fs::create_dir_all(tmp.path().join("Artifacts/empty")).unwrap();
+
+        let config = Config {
+            tasks_path: tmp.path().to_path_buf(),
+            resources: vec![
+                ResourceConfig {
+                    name: "rotator".into(),
+                    commands: vec!["rotctl".into()],
+                    transmit: false,
+                    address: None,
+                    limits: None,
+                    park: None,
+                    sdr: None,
+                },
+                ResourceConfig {
+                    name: "radio".into(),
+                    commands: vec!["rigctl".into()],
+                    transmit: true,
+                    address: None,
+                    limits: None,
+                    park: None,
+                    sdr: None,
+                },
+            ],
+            ..Default::default()
+        };
+        (tmp, config)
+    }
+
+    fn runs(config: &Config, command: RunsCommand) -> anyhow::Result<String> {
+        run(
+            RunsArgs {
+                station: None,
+                command,
+            },
+            config,
+        )
+    }
+
+    #[test]
+    fn runs_are_listed() {
+        let (_tmp, config) = setup();
+        let output = runs(&config, RunsCommand::List).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("pass   2099-06-01T10:00:00+00:00  Failed  1/3    aborted"));
+        assert!(lines[2].starts_with("empty  -"));
+    }
+
+    #[test]
+    fn run_is_shown() {
+        let (_tmp, config) = setup();
+        let output = runs(&config, RunsCommand::Show { id: "pass".into() }).unwrap();
+        assert!(output.starts_with("pass: aborted (Failed)\nstart: 2099-06-01T10:00:00+00:00\n"));
+        assert!(output.contains("satellite: NanoFF A\n"));
+        assert!(output.contains("  radio (1 commands, transmits)\n    rigctl F 437000000\n"));
+        assert!(output.contains("  10:00:02  completed (1)"));
+        assert!(output.contains("  10:00:03  aborted: exit signal received  echo done\n"));
+        assert!(output.ends_with("artifacts:\n  iq/recording.cf32 (16 bytes)\n"));
+
+        let error = runs(&config, RunsCommand::Show { id: "nope".into() }).unwrap_err();
+        assert!(error.to_string().starts_with("run nope not found"));
+    }
+}
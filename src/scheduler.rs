@@ -22,7 +22,7 @@
 //!
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     io,
     path::Path,
     string::FromUtf8Error,
@@ -34,13 +34,16 @@ use std::{
 use chrono::{DateTime, Utc};
 use notify::{
     EventKind, Watcher,
-    event::{CreateKind, RemoveKind},
+    event::{AccessKind, AccessMode, CreateKind, ModifyKind, RemoveKind, RenameMode},
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::sync::Notify;
-use tracing::{debug, error, info, warn};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{Instrument, debug, error, info, info_span, warn};
 
-use crate::task::runner::RunConfig;
+use crate::task::format::Preempted;
+use crate::task::runner::{RunConfig, RunControl, RunStatus, Simulation, StepEvent};
 use crate::{Task, task};
 
 #[derive(Debug, Error)]
@@ -55,8 +58,98 @@ pub enum Error {
     NotifyWatcher(#[from] notify::Error),
 }
 
+/// Change in the state of a Task run, identified by the Task's unique identifier (without extension).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunEvent {
+    Started(String),
+    Completed(String),
+    /// The Task could not be run, e.g. its artifact directory could not be created.
+    Failed(String),
+    /// A step failed and the Task was aborted.
+    Aborted(String),
+    /// A step of the Task started or finished.
+    Step(String, StepEvent),
+    /// The Task was taken off the schedule before running, as it overlaps a Task of higher
+    /// priority, and moved as the [`Preemption`] says.
+    Preempted(String, Preemption),
+}
+
+/// What happens to the Active Tasks overlapping a Task of higher `priority`, before they run.
+/// Those already running are aborted, and moved to *Failed*.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Preemption {
+    /// Moved back to PendingApproval, to be approved again, e.g. once moved to another time
+    #[default]
+    Pending,
+    /// Moved to Rejected without running
+    Reject,
+}
+
+impl Preemption {
+    /// The state the preempted Tasks are moved to.
+    pub fn state(self) -> &'static str {
+        match self {
+            Preemption::Pending => "PendingApproval",
+            Preemption::Reject => "Rejected",
+        }
+    }
+}
+
+/// Controls of the Task runs in progress, by the Task's unique identifier (without extension).
+#[derive(Debug, Clone, Default)]
+pub struct Runs(Arc<Mutex<HashMap<String, RunControl>>>);
+
+impl Runs {
+    /// The control of the run of Task `id`, if it is running.
+    pub fn get(&self, id: &str) -> Option<RunControl> {
+        self.0.lock().unwrap().get(id).cloned()
+    }
+
+    /// The status of each run in progress, by Task ID.
+    pub fn statuses(&self) -> Vec<(String, RunStatus)> {
+        let runs = self.0.lock().unwrap();
+        let mut statuses: Vec<_> = runs
+            .iter()
+            .map(|(id, control)| (id.clone(), control.status()))
+            .collect();
+        statuses.sort_by(|a, b| a.0.cmp(&b.0));
+        statuses
+    }
+}
+
 /// Monitors a directory structure containing Task descriptions and executes them at the corresponding time.
 pub async fn run(base: &Path) -> Result<(), Error> {
+    run_with_events(base, None).await
+}
+
+/// Like [`run`], also sending a [`RunEvent`] to `events` when a Task starts and finishes running.
+pub async fn run_with_events(
+    base: &Path,
+    events: Option<UnboundedSender<RunEvent>>,
+) -> Result<(), Error> {
+    run_until(
+        base,
+        events,
+        Runs::default(),
+        Preemption::default(),
+        std::future::pending(),
+    )
+    .await
+}
+
+/// Like [`run_with_events`], until `shutdown` completes, adding the control of each run to `runs`
+/// while it is in progress. Tasks outranked before they run are handled as `preemption` says.
+///
+/// No Task is started after that, and the function returns once the running Tasks have finished
+/// and been moved to the *Completed* or *Failed* state.
+pub async fn run_until(
+    base: &Path,
+    events: Option<UnboundedSender<RunEvent>>,
+    runs: Runs,
+    preemption: Preemption,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error> {
     let active_path = base.join("Active");
     let failed_path = base.join("Failed");
     let completed_path = base.join("Completed");
@@ -76,14 +169,17 @@ pub async fn run(base: &Path) -> Result<(), Error> {
 
     // Spawn Active directory watcher in a background OS thread (notify crate is sync)
     let tasks = Arc::new(Mutex::new(tasks));
+    // Unique IDs of the Tasks being run, whose files are left alone by the watcher
+    let started = Arc::new(Mutex::new(HashSet::new()));
     let notify = Arc::new(Notify::new());
     {
         let tasks = tasks.clone();
+        let started = started.clone();
         let active_path = active_path.clone();
         let failed_path = failed_path.clone();
         let notify = notify.clone();
         thread::spawn(move || {
-            if let Err(e) = directory_watcher(tasks, &active_path, &failed_path, notify) {
+            if let Err(e) = directory_watcher(tasks, started, &active_path, &failed_path, notify) {
                 error!(?e, "watcher exited with error");
             }
         });
@@ -92,7 +188,18 @@ pub async fn run(base: &Path) -> Result<(), Error> {
     info!("scheduler running");
 
     // Main loop
+    let mut running = tokio::task::JoinSet::new();
+    // Definitions of the Tasks being run, by unique ID
+    let mut running_tasks: HashMap<String, Task> = HashMap::new();
+    tokio::pin!(shutdown);
     loop {
+        // Forget the Tasks that have finished running
+        while let Some(finished) = running.try_join_next() {
+            if let Ok(unique_id) = finished {
+                running_tasks.remove(&unique_id);
+            }
+        }
+
         // Find the next task that should be run by start time
         let next = {
             let tasks = tasks.lock().unwrap();
@@ -106,6 +213,10 @@ pub async fn run(base: &Path) -> Result<(), Error> {
         };
 
         tokio::select! {
+            _ = &mut shutdown => {
+                info!("shutting down, no more tasks will be started");
+                break;
+            }
             _ = notify.notified() => {
                 // The set of Active Tasks has changed.
                 // Re-run the loop.
@@ -125,6 +236,45 @@ pub async fn run(base: &Path) -> Result<(), Error> {
             continue;
         };
 
+        // Overlapping Tasks of higher priority take precedence, e.g. ones placed in Active by hand
+        let outranked_by = {
+            let tasks = tasks.lock().unwrap();
+            tasks
+                .iter()
+                .chain(&running_tasks)
+                .find(|(_, other)| other.priority() > task.priority() && other.overlaps(&task))
+                .map(|(other, _)| Task::id_from_filename(other).to_string())
+        };
+        if let Some(by) = outranked_by {
+            let id = Task::id_from_filename(&unique_id);
+            if let Err(e) = preempt(base, id, &by, preemption) {
+                error!(?e, %unique_id, "failed to move preempted task");
+            } else if let Some(events) = &events {
+                let _ = events.send(RunEvent::Preempted(id.to_string(), preemption));
+            }
+            continue;
+        }
+        // Overlapping runs of lower priority are stopped to make room for it
+        for (other, other_task) in &running_tasks {
+            if other_task.priority() < task.priority() && other_task.overlaps(&task) {
+                let Some(control) = runs.get(Task::id_from_filename(other)) else {
+                    continue;
+                };
+                warn!(%other, by = %unique_id, "aborting run preempted by a task of higher priority");
+                // The watcher leaves the files of running Tasks alone
+                let path = active_path.join(other);
+                if let Err(e) = record_preemption(&path, &path, Task::id_from_filename(&unique_id))
+                {
+                    error!(?e, %other, "failed to record preemption");
+                }
+                control.abort();
+            }
+        }
+
+        running_tasks.insert(unique_id.clone(), task.clone());
+        started.lock().unwrap().insert(unique_id.clone());
+        let started = started.clone();
+
         let task_path = active_path.join(&unique_id);
         let failed_path = failed_path.clone();
         let completed_path = completed_path.clone();
@@ -134,28 +284,96 @@ pub async fn run(base: &Path) -> Result<(), Error> {
             .to_str()
             .unwrap()
             .to_string();
-        let config = RunConfig {
-            artifact_base: artifact_base.join(task_stem),
-        };
+        let base = base.to_path_buf();
+        let control = RunControl::default();
+        runs.0
+            .lock()
+            .unwrap()
+            .insert(task_stem.clone(), control.clone());
+        let runs = runs.clone();
 
         info!(%unique_id, "spawning runner for task");
-        tokio::spawn(async move {
-            let outcome = task::runner::run(task, config).await;
+        let events = events.clone();
+        let send = move |event| {
+            if let Some(events) = &events {
+                // The receiver going away only means nobody is interested anymore
+                let _ = events.send(event);
+            }
+        };
+        let mut steps = control.subscribe();
+        running.spawn(async move {
+            send(RunEvent::Started(task_stem.clone()));
+            let run = execute(&base, &task_stem, task, None, control);
+            tokio::pin!(run);
+            let event = loop {
+                tokio::select! {
+                    event = &mut run => break event,
+                    Ok(step) = steps.recv() => send(RunEvent::Step(task_stem.clone(), step)),
+                }
+            };
+            while let Ok(step) = steps.try_recv() {
+                send(RunEvent::Step(task_stem.clone(), step));
+            }
 
-            let dest = match &outcome {
-                Ok(o) if !o.aborted() => &completed_path,
+            let dest = match &event {
+                RunEvent::Completed(_) => &completed_path,
                 _ => &failed_path,
             };
 
             if let Err(e) = tokio::fs::rename(&task_path, dest.join(&unique_id)).await {
                 error!(?e, %unique_id, "failed to move task file after completion");
             }
+            started.lock().unwrap().remove(&unique_id);
+            runs.0.lock().unwrap().remove(&task_stem);
+            send(event);
+            unique_id
         });
     }
+
+    if !running.is_empty() {
+        info!(count = running.len(), "waiting for running tasks to finish");
+    }
+    while running.join_next().await.is_some() {}
+    info!("scheduler stopped");
+    Ok(())
+}
+
+/// Runs `task`, identified by `id` (its unique identifier without extension), writing its
+/// artifacts to `Artifacts/<id>` under `base`.
+///
+/// Returns the [`RunEvent`] telling how the run finished. The Task file is not moved.
+///
+/// The run is instrumented with a `run` span, whose `artifacts` field is the artifact directory.
+pub async fn execute(
+    base: &Path,
+    id: &str,
+    task: Task,
+    simulation: Option<Simulation>,
+    control: RunControl,
+) -> RunEvent {
+    let artifact_base = base.join("Artifacts").join(id);
+    let span = info_span!("run", %id, artifacts = %artifact_base.display());
+    let config = RunConfig {
+        artifact_base,
+        simulation,
+        control,
+    };
+    match task::runner::run(task, config).instrument(span).await {
+        Ok(outcome) if !outcome.aborted() => RunEvent::Completed(id.to_string()),
+        Ok(_) => RunEvent::Aborted(id.to_string()),
+        Err(e) => {
+            warn!(%e, %id, "task could not be run");
+            RunEvent::Failed(id.to_string())
+        }
+    }
 }
 
+/// Keeps `tasks` in sync with the Task files in `active_path`: files written or moved there, e.g. by
+/// an approval, are (re)loaded, and files deleted or moved away are forgotten. The files of the
+/// `started` Tasks are not reloaded.
 fn directory_watcher(
     tasks: Arc<Mutex<HashMap<String, Task>>>,
+    started: Arc<Mutex<HashSet<String>>>,
     active_path: &Path,
     failed_path: &Path,
     notify: Arc<Notify>,
@@ -169,19 +387,43 @@ fn directory_watcher(
         debug!(kind = ?event.kind, paths = ?event.paths, "got event");
 
         let mut tasks = tasks.lock().unwrap();
+        let started = started.lock().unwrap();
+        let load = |tasks: &mut HashMap<String, Task>, path: &Path| {
+            if started.contains(&path_to_unique_id(path)) {
+                debug!(?path, "task is running, ignoring change");
+                return Ok(false);
+            }
+            // Created files are loaded once written
+            if std::fs::metadata(path).is_ok_and(|m| m.len() == 0) {
+                return Ok(false);
+            }
+            parse_task_or_move_to_failed(tasks, path, failed_path).map(|()| true)
+        };
         let changed = match event.kind {
-            EventKind::Create(CreateKind::File) => {
+            EventKind::Create(CreateKind::File)
+            | EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
+                let path = &event.paths[0];
+                info!(?path, "file written");
+                load(&mut tasks, path)?
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
                 let path = &event.paths[0];
-                info!(?path, "file created");
-                parse_task_or_move_to_failed(&mut tasks, path, failed_path)?;
+                info!(?path, "file moved in");
+                load(&mut tasks, path)?
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                let (from, to) = (&event.paths[0], &event.paths[1]);
+                info!(?from, ?to, "file renamed");
+                tasks.remove(&path_to_unique_id(from));
+                load(&mut tasks, to)?;
                 true
             }
-            EventKind::Remove(RemoveKind::File) => {
+            EventKind::Remove(RemoveKind::File)
+            | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
                 let path = &event.paths[0];
                 info!(?path, "file removed");
                 let unique_id = path_to_unique_id(path);
-                tasks.remove(&unique_id);
-                true
+                tasks.remove(&unique_id).is_some()
             }
             // Irrelevant events
             EventKind::Access(_) => false,
@@ -242,6 +484,38 @@ fn next_to_run(tasks: &HashMap<String, Task>) -> Option<(String, DateTime<Utc>)>
         .min_by_key(|(_, start)| *start)
 }
 
+/// Takes Active Task `id`, which is not running, off the schedule under `base` as `preemption`
+/// says, recording that Task `by` preempted it.
+pub fn preempt(base: &Path, id: &str, by: &str, preemption: Preemption) -> io::Result<()> {
+    let filename = Task::filename(id);
+    let dir = base.join(preemption.state());
+    std::fs::create_dir_all(&dir)?;
+    record_preemption(
+        &base.join("Active").join(&filename),
+        &dir.join(&filename),
+        by,
+    )?;
+    info!(%id, %by, state = preemption.state(), "task preempted");
+    Ok(())
+}
+
+/// Records in the Task file at `path` that it was preempted by Task `by`, writing it to `dest`
+/// (`path` itself, or where it is moved).
+fn record_preemption(path: &Path, dest: &Path, by: &str) -> io::Result<()> {
+    let preempted = Preempted {
+        by: by.to_string(),
+        time: Utc::now(),
+    };
+    let content = preempted
+        .record(&std::fs::read_to_string(path)?)
+        .map_err(io::Error::other)?;
+    std::fs::write(dest, content)?;
+    if dest != path {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
 fn path_to_unique_id(path: &Path) -> String {
     path.file_name().unwrap().to_str().unwrap().to_string()
 }
@@ -336,6 +610,39 @@ steps:
         handle.abort();
     }
 
+    #[tokio::test]
+    async fn run_events_are_sent() {
+        let base = setup();
+        write_active(base.path(), "ok.yaml", TASK_OK);
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let base_path = base.path().to_path_buf();
+        let handle = tokio::spawn(async move { run_with_events(&base_path, Some(tx)).await });
+
+        let mut events = Vec::new();
+        for _ in 0..4 {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+            events.push(event.unwrap().unwrap());
+        }
+        handle.abort();
+        assert_eq!(
+            events,
+            [
+                RunEvent::Started("ok".into()),
+                RunEvent::Step("ok".into(), StepEvent::Started { step: 0 }),
+                RunEvent::Step(
+                    "ok".into(),
+                    StepEvent::Completed {
+                        step: 0,
+                        result: task::runner::StepResult::Completed,
+                        exit_code: Some(0),
+                    }
+                ),
+                RunEvent::Completed("ok".into())
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn invalid_task_moved_to_failed_on_load() {
         let base = setup();
@@ -350,6 +657,57 @@ steps:
         assert!(!base.path().join("Active/bad.yaml").exists());
     }
 
+    #[tokio::test]
+    async fn execute_writes_artifacts_without_moving_the_task() {
+        let base = setup();
+        let task = Task::from_yaml_str(TASK_OK).unwrap();
+        let event = execute(base.path(), "bench", task, None, Default::default()).await;
+        assert_eq!(event, RunEvent::Completed("bench".into()));
+        assert!(base.path().join("Artifacts/bench/task.yml").exists());
+
+        let task = Task::from_yaml_str(TASK_ABORT).unwrap();
+        let event = execute(base.path(), "broken", task, None, Default::default()).await;
+        assert_eq!(event, RunEvent::Aborted("broken".into()));
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_running_tasks() {
+        let base = setup();
+        write_active(
+            base.path(),
+            "slow.yaml",
+            &TASK_OK.replace("\"true\"", "\"sleep 0.5\""),
+        );
+        write_active(
+            base.path(),
+            "later.yaml",
+            "variables:\n  start: \"2099-01-01T00:00:00Z\"\nsteps:\n  - cmd: \"true\"\n",
+        );
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let base_path = base.path().to_path_buf();
+        let handle = tokio::spawn(async move {
+            run_until(
+                &base_path,
+                None,
+                Runs::default(),
+                Preemption::Pending,
+                async {
+                    let _ = shutdown_rx.await;
+                },
+            )
+            .await
+        });
+
+        assert!(wait_for(&base.path().join("Artifacts/slow")).await);
+        shutdown_tx.send(()).unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), handle).await;
+        assert!(result.unwrap().unwrap().is_ok());
+
+        assert!(base.path().join("Completed/slow.yaml").exists());
+        assert!(base.path().join("Active/later.yaml").exists());
+    }
+
     #[tokio::test]
     async fn task_added_at_runtime_is_executed() {
         let base = setup();
@@ -364,4 +722,111 @@ steps:
         assert!(wait_for(&base.path().join("Completed/task.yaml")).await);
         handle.abort();
     }
+    #[tokio::test]
+    async fn task_approved_at_runtime_is_executed() {
+        let base = setup();
+
+        let base_path = base.path().to_path_buf();
+        let handle = tokio::spawn(async move { run(&base_path).await });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // Tasks are approved by moving them from PendingApproval
+        std::fs::create_dir_all(base.path().join("PendingApproval")).unwrap();
+        let pending = base.path().join("PendingApproval/task.yaml");
+        std::fs::write(&pending, TASK_OK).unwrap();
+        std::fs::rename(&pending, base.path().join("Active/task.yaml")).unwrap();
+
+        assert!(wait_for(&base.path().join("Completed/task.yaml")).await);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn task_edited_at_runtime_is_reloaded() {
+        let base = setup();
+        write_active(
+            base.path(),
+            "task.yaml",
+            &TASK_OK.replace(
+                "variables:",
+                "variables:\n  start: \"2099-01-01T00:00:00Z\"",
+            ),
+        );
+
+        let base_path = base.path().to_path_buf();
+        let handle = tokio::spawn(async move { run(&base_path).await });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        write_active(base.path(), "task.yaml", TASK_OK);
+
+        assert!(wait_for(&base.path().join("Completed/task.yaml")).await);
+        handle.abort();
+    }
+
+    fn preempted_by(path: &Path) -> Option<String> {
+        let task = Task::from_yaml_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        task.preempted.map(|p| p.by)
+    }
+
+    #[tokio::test]
+    async fn task_outranked_by_an_overlapping_task_is_not_run() {
+        let base = setup();
+        write_active(base.path(), "routine.yaml", TASK_OK);
+        let start = (Utc::now() + chrono::TimeDelta::milliseconds(500)).to_rfc3339();
+        write_active(
+            base.path(),
+            "emergency.yaml",
+            &TASK_OK.replace(
+                "variables:",
+                &format!("variables:\n  start: \"{start}\"\n  priority: \"10\""),
+            ),
+        );
+
+        let base_path = base.path().to_path_buf();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            let shutdown = std::future::pending();
+            run_until(
+                &base_path,
+                Some(tx),
+                Runs::default(),
+                Preemption::Reject,
+                shutdown,
+            )
+            .await
+        });
+
+        assert!(wait_for(&base.path().join("Completed/emergency.yaml")).await);
+        handle.abort();
+        let routine = base.path().join("Rejected/routine.yaml");
+        assert_eq!(preempted_by(&routine).as_deref(), Some("emergency"));
+        assert!(!base.path().join("Artifacts/routine").exists());
+        let preempted = RunEvent::Preempted("routine".into(), Preemption::Reject);
+        assert_eq!(rx.recv().await, Some(preempted));
+    }
+
+    #[tokio::test]
+    async fn run_of_lower_priority_is_aborted() {
+        let base = setup();
+        write_active(
+            base.path(),
+            "routine.yaml",
+            &TASK_OK.replace("\"true\"", "\"sleep 30\""),
+        );
+
+        let base_path = base.path().to_path_buf();
+        let handle = tokio::spawn(async move { run(&base_path).await });
+
+        assert!(wait_for(&base.path().join("Artifacts/routine")).await);
+        write_active(
+            base.path(),
+            "emergency.yaml",
+            &TASK_OK.replace("variables:", "variables:\n  priority: \"10\""),
+        );
+
+        assert!(wait_for(&base.path().join("Completed/emergency.yaml")).await);
+        let routine = base.path().join("Failed/routine.yaml");
+        assert!(wait_for(&routine).await);
+        handle.abort();
+        assert_eq!(preempted_by(&routine).as_deref(), Some("emergency"));
+    }
 }
@@ -1,32 +1,193 @@
-use std::net::SocketAddrV4;
+use std::future::IntoFuture;
+use std::net::{SocketAddr, SocketAddrV4};
+use std::pin::Pin;
+use std::time::Duration;
 
 use anyhow::Result;
+use axum::serve::ListenerExt;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::{net::TcpListener, spawn};
 use tracing::{info, warn};
 use utoipa_rapidoc::RapiDoc;
 
-use crate::{api, config::Config, frontend, scheduler};
+use crate::{api, config::Config, frontend, scheduler, tls, upload};
+
+/// How long requests in progress (including event streams) have to finish on shutdown.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub async fn run(config: Config, host: String, port: u32) -> Result<()> {
-    // Start scheduler
-    let tasks_path = config.tasks_path.clone();
-    spawn(async move {
-        if let Err(e) = scheduler::run(&tasks_path).await {
-            warn!(?e, "scheduler exited with error");
-        }
-    });
+    serve(config, host, port, |_, _| Vec::new()).await
+}
+
+/// Serves the stations of `config` until the process receives SIGINT or SIGTERM.
+///
+/// `start` is called with the state of each station once they are set up, to start the tasks
+/// running alongside them. These are told to stop through the receiver once the runs in progress
+/// have finished, and waited for before returning.
+pub async fn serve<F>(config: Config, host: String, port: u32, start: F) -> Result<()>
+where
+    F: FnOnce(Vec<api::AppState>, watch::Receiver<bool>) -> Vec<JoinHandle<()>>,
+{
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (background_tx, background_rx) = watch::channel(false);
+    let state = api::state(&config);
+    let mut states = vec![state.clone()];
+    let mut stations = vec![start_station(&state, shutdown_rx.clone())];
+    let mut notifiers = vec![state.notifier.clone()];
 
     // Set up API server
-    let (router, api) = api::router(&config).split_for_parts();
+    let (mut router, api) = api::routes(state.clone()).split_for_parts();
+    for name in config.hosted_stations.keys() {
+        if !is_valid_station_name(name) {
+            warn!(%name, "hosted station names can only contain letters, digits, '-' and '_', ignoring");
+            continue;
+        }
+        let hosted_config = config.hosted_station(name).expect("station should exist");
+        let hosted = api::hosted_state(&state, &hosted_config);
+        stations.push(start_station(&hosted, shutdown_rx.clone()));
+        states.push(hosted.clone());
+        notifiers.push(hosted.notifier.clone());
+        router = router.merge(api::hosted_routes(name, hosted));
+        info!(%name, "serving hosted station");
+    }
     let router = router
+        .merge(api::metrics::router(states.clone()))
         .merge(RapiDoc::with_openapi("/api-docs/openapi.json", api).path("/rapidoc"))
         .fallback_service(frontend::router());
 
     let address: SocketAddrV4 = format!("{host}:{port}").parse()?;
+    let acceptor = config.web.tls.as_ref().map(tls::acceptor).transpose()?;
+    if acceptor.is_none() && !address.ip().is_loopback() {
+        warn!(%address, "serving plain HTTP, API keys are sent in cleartext unless a TLS proxy is used");
+    }
     let listener = TcpListener::bind(address).await?;
+    let background = start(states, background_rx);
 
     // Start the web server
-    info!(%host, %port, "starting web server");
-    axum::serve(listener, router).await?;
+    info!(%host, %port, tls = acceptor.is_some(), "starting web server");
+    // The address of the clients is needed to count their failed authentications
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+    let shutdown = async move {
+        shutdown_signal().await;
+        info!("shutting down, waiting for requests in progress");
+        let _ = shutdown_tx.send(true);
+    };
+    let server: Pin<Box<dyn Future<Output = std::io::Result<()>> + Send>> = match acceptor {
+        Some(acceptor) => {
+            // Tapping the connections also gives their address to the handlers
+            let listener = tls::TlsListener::new(listener, acceptor)?.tap_io(|tls| {
+                let _ = tls.get_ref().0.set_nodelay(true);
+            });
+            let server = axum::serve(listener, service).with_graceful_shutdown(shutdown);
+            Box::pin(server.into_future())
+        }
+        None => {
+            let server = axum::serve(listener, service).with_graceful_shutdown(shutdown);
+            Box::pin(server.into_future())
+        }
+    };
+    let mut drain_rx = shutdown_rx.clone();
+    let drain_deadline = async move {
+        let _ = drain_rx.wait_for(|&shutdown| shutdown).await;
+        tokio::time::sleep(DRAIN_TIMEOUT).await;
+    };
+    tokio::select! {
+        result = server => result?,
+        _ = drain_deadline => warn!(timeout = ?DRAIN_TIMEOUT, "requests still in progress, closing their connections"),
+    }
+
+    // The schedulers were told to stop with the server, let the tasks running finish
+    for station in stations {
+        let _ = station.await;
+    }
+    for notifier in notifiers {
+        notifier.flush().await;
+    }
+    let _ = background_tx.send(true);
+    for task in background {
+        let _ = task.await;
+    }
+    info!("shut down");
     Ok(())
 }
+
+/// Completes when the process receives SIGINT (Ctrl+C) or SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(?e, "failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!(?e, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Starts the scheduler and auto-scheduler of a station.
+///
+/// The scheduler stops when `shutdown` becomes true. The returned handle completes once the tasks
+/// it was running have finished, their artifacts have been uploaded and their events have been
+/// notified.
+fn start_station(state: &api::AppState, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+    let (run_events, run_events_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (notified_events, notified_events_rx) = tokio::sync::mpsc::unbounded_channel();
+    let forwarding = spawn(
+        state
+            .notifier
+            .clone()
+            .forward_run_events(notified_events_rx),
+    );
+    let uploading = spawn(upload::upload_runs(
+        state.config.uploads.clone(),
+        state.config.station_name.clone(),
+        state.tasks_path.join("Artifacts"),
+        run_events_rx,
+        notified_events,
+    ));
+
+    spawn(api::auto_schedule::run(state.clone()));
+    spawn(api::predict::precompute_passes(state.clone()));
+    spawn(api::predict::watch_satellites(state.clone()));
+
+    let tasks_path = state.tasks_path.clone();
+    let runs = state.runs.clone();
+    let preemption = state.config.scheduling.preemption;
+    spawn(async move {
+        let stop = async move {
+            let _ = shutdown.wait_for(|&shutdown| shutdown).await;
+        };
+        let scheduler = scheduler::run_until(&tasks_path, Some(run_events), runs, preemption, stop);
+        if let Err(e) = scheduler.await {
+            warn!(?e, ?tasks_path, "scheduler exited with error");
+        }
+        // The scheduler dropped its sender, so uploading and forwarding end after the last event
+        let _ = uploading.await;
+        let _ = forwarding.await;
+    })
+}
+
+fn is_valid_station_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
+use std::time::Duration;
 
 use chrono::{DateTime, TimeDelta, Utc};
 use serde::Deserialize;
@@ -20,6 +21,8 @@ pub enum Error {
     InvalidVariableInTimeSpec,
     #[error("Invalid time spec")]
     InvalidTimeSpec(serde_yaml::Error),
+    #[error("invalid priority '{0}', expected an integer")]
+    InvalidPriority(String),
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -30,9 +33,72 @@ pub struct Task {
     pub steps: Vec<Step>,
     #[serde(default)]
     pub cleanup: Vec<Step>,
+    /// Identity of whoever submitted the task through the API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Template the task was generated from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Set when the task was taken off the schedule for a task of higher priority.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preempted: Option<Preempted>,
+    /// Set when the task was rejected instead of approved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<Rejected>,
 }
 
-pub const TASK_STATES: &[&str] = &["Active", "PendingApproval", "Completed", "Failed"];
+/// Why a task was taken off the schedule: an overlapping task of higher priority.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Preempted {
+    /// ID of the task it made room for
+    pub by: String,
+    pub time: DateTime<Utc>,
+}
+
+impl Preempted {
+    /// `yaml`, the definition of a task, with the preemption recorded in it. The rest of the
+    /// definition is kept as it is.
+    pub fn record(&self, yaml: &str) -> Result<String, Error> {
+        record(yaml, "preempted", self)
+    }
+}
+
+/// Who rejected a task pending approval, and why.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Rejected {
+    /// Identity of the approver
+    pub by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub time: DateTime<Utc>,
+}
+
+impl Rejected {
+    /// `yaml`, the definition of a task, with the rejection recorded in it.
+    pub fn record(&self, yaml: &str) -> Result<String, Error> {
+        record(yaml, "rejected", self)
+    }
+}
+
+/// `yaml` with `value` set at `key` of its top level mapping.
+fn record(yaml: &str, key: &str, value: &impl Serialize) -> Result<String, Error> {
+    let mut task: Value = serde_yaml::from_str(yaml)?;
+    if let Value::Mapping(mapping) = &mut task {
+        mapping.insert(key.into(), serde_yaml::to_value(value)?);
+    }
+    Ok(serde_yaml::to_string(&task)?)
+}
+
+/// Variable holding the priority of a task.
+pub const PRIORITY_VARIABLE: &str = "priority";
+
+pub const TASK_STATES: &[&str] = &[
+    "Active",
+    "PendingApproval",
+    "Completed",
+    "Failed",
+    "Rejected",
+];
 pub const TASK_EXTENSION: &str = ".yaml";
 
 impl Task {
@@ -41,6 +107,10 @@ impl Task {
             variables,
             steps,
             cleanup,
+            owner: None,
+            template: None,
+            preempted: None,
+            rejected: None,
         };
 
         task.ensure_start_time();
@@ -50,6 +120,7 @@ impl Task {
     pub fn from_yaml_str(yaml: &str) -> Result<Self, Error> {
         let mut task: Task = serde_yaml::from_str(yaml)?;
         task.ensure_start_time();
+        task.parse_priority()?;
 
         Ok(task)
     }
@@ -71,10 +142,19 @@ impl Task {
         filename.strip_suffix(TASK_EXTENSION).unwrap_or(filename)
     }
 
+    /// Whether `id` can name a task: not a path outside its directory, and without control
+    /// characters, which would end up in notification headers.
+    pub fn is_valid_id(id: &str) -> bool {
+        !(id.contains('/')
+            || id.contains('\\')
+            || id == ".."
+            || id == "."
+            || id.chars().any(char::is_control))
+    }
+
     /// Find a task file across all state directories. Returns (state_name, file_contents).
     pub async fn find(tasks_path: &Path, id: &str) -> Option<(String, String)> {
-        // Reject path traversal
-        if id.contains('/') || id.contains('\\') || id == ".." || id == "." {
+        if !Self::is_valid_id(id) {
             return None;
         }
 
@@ -103,6 +183,54 @@ impl Task {
             self.get_time_variable("end")?,
         ))
     }
+
+    /// The `priority` variable: a task preempts the overlapping tasks of lower priority. 0 if
+    /// unset, or not an integer (which [`Task::from_yaml_str`] rejects).
+    pub fn priority(&self) -> i64 {
+        self.parse_priority().unwrap_or_default()
+    }
+
+    fn parse_priority(&self) -> Result<i64, Error> {
+        match self.variables.get(PRIORITY_VARIABLE) {
+            Some(priority) => priority
+                .trim()
+                .parse()
+                .map_err(|_| Error::InvalidPriority(priority.clone())),
+            None => Ok(0),
+        }
+    }
+
+    /// Whether the time ranges of the tasks overlap. Tasks without a valid time range overlap
+    /// with none.
+    pub fn overlaps(&self, other: &Task) -> bool {
+        match (self.time_range(), other.time_range()) {
+            // Two ranges [s1,e1) and [s2,e2) overlap iff s1 < e2 && s2 < e1
+            (Ok((start, end)), Ok((other_start, other_end))) => {
+                start < other_end && other_start < end
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the task to start at `start`, shifting every variable holding a timestamp (e.g. `end`)
+    /// and every absolute step time by the same amount. Relative step times follow the variables
+    /// they refer to.
+    pub fn rebase(&mut self, start: DateTime<Utc>) -> Result<(), Error> {
+        let shift = start - self.get_time_variable("start")?;
+        for value in self.variables.values_mut() {
+            if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+                *value = (time.with_timezone(&Utc) + shift).to_rfc3339();
+            }
+        }
+        // `start` may not have been a timestamp, e.g. relative to another variable
+        self.variables.insert("start".into(), start.to_rfc3339());
+        for step in self.steps.iter_mut().chain(&mut self.cleanup) {
+            if let Some(TimeSpec::Absolute(time)) = &mut step.time {
+                *time += shift;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -111,6 +239,12 @@ pub struct Step {
     pub time: Option<TimeSpec>,
     pub wait: bool,
     pub on_fail: OnFail,
+    /// How long the step may run before it is stopped and counted as failed, e.g. `10m`.
+    pub timeout: Option<Duration>,
+    /// Variables added to the environment of the command, with the task variables substituted.
+    pub env: BTreeMap<String, String>,
+    /// Directory the command runs in, relative to the artifact directory of the run.
+    pub cwd: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -128,7 +262,7 @@ pub enum OnFail {
 }
 
 // --- deserialization ---
-const STEP_FIELDS: &[&str] = &["cmd", "time", "wait", "on_fail"];
+const STEP_FIELDS: &[&str] = &["cmd", "time", "wait", "on_fail", "timeout", "env", "cwd"];
 
 impl<'de> Deserialize<'de> for Step {
     fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
@@ -138,6 +272,9 @@ impl<'de> Deserialize<'de> for Step {
                 time: None,
                 wait: false,
                 on_fail: OnFail::Abort,
+                timeout: None,
+                env: BTreeMap::new(),
+                cwd: None,
             }),
             Value::Mapping(map) => {
                 // Make sure only expected keys are given
@@ -156,6 +293,11 @@ impl<'de> Deserialize<'de> for Step {
                     time: get_field(&map, "time")?,
                     wait: get_field(&map, "wait")?.unwrap_or_default(),
                     on_fail: get_field(&map, "on_fail")?.unwrap_or_default(),
+                    timeout: get_field::<String, _>(&map, "timeout")?
+                        .map(|t| humantime::parse_duration(t.trim()).map_err(de::Error::custom))
+                        .transpose()?,
+                    env: get_field(&map, "env")?.unwrap_or_default(),
+                    cwd: get_field(&map, "cwd")?,
                 })
             }
             _ => Err(de::Error::custom("step must be a string or mapping")),
@@ -250,7 +392,7 @@ impl Serialize for TimeSpec {
                     ("+", *offset)
                 };
                 let dur = abs_offset.to_std().map_err(ser::Error::custom)?;
-                format!("{variable}{sign}{}", humantime::format_duration(dur))
+                format!("${variable}{sign}{}", humantime::format_duration(dur))
             }
         };
         serializer.serialize_str(&s)
@@ -259,7 +401,12 @@ impl Serialize for TimeSpec {
 
 impl Serialize for Step {
     fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let is_simple = self.time.is_none() && !self.wait && matches!(self.on_fail, OnFail::Abort);
+        let is_simple = self.time.is_none()
+            && !self.wait
+            && matches!(self.on_fail, OnFail::Abort)
+            && self.timeout.is_none()
+            && self.env.is_empty()
+            && self.cwd.is_none();
         if is_simple {
             return serializer.serialize_str(&self.cmd);
         }
@@ -275,6 +422,15 @@ impl Serialize for Step {
         if !matches!(self.on_fail, OnFail::Abort) {
             map.serialize_entry("on_fail", &self.on_fail)?;
         }
+        if let Some(timeout) = self.timeout {
+            map.serialize_entry("timeout", &humantime::format_duration(timeout).to_string())?;
+        }
+        if !self.env.is_empty() {
+            map.serialize_entry("env", &self.env)?;
+        }
+        if let Some(cwd) = &self.cwd {
+            map.serialize_entry("cwd", cwd)?;
+        }
         map.end()
     }
 }
@@ -303,6 +459,7 @@ steps:
     cmd: echo "Pass about to end!"
     wait: true
     on_fail: retry(3)
+    timeout: 5m
 
 cleanup:
   - echo "hello from cleanup"
@@ -326,6 +483,10 @@ cleanup:
             if variable == "end" && *offset == TimeDelta::seconds(-10))
         );
         assert!(matches!(&step.on_fail, OnFail::Retry(3)));
+        assert_eq!(step.timeout, Some(Duration::from_secs(300)));
+
+        let yaml = serde_yaml::to_string(step).unwrap();
+        assert!(yaml.contains("timeout: 5m"));
     }
 
     fn deser_time_spec(s: &str) -> TimeSpec {
@@ -373,6 +534,42 @@ cleanup:
         assert!(err.to_string().contains("out of range"));
     }
 
+    #[test]
+    fn time_spec_relative_round_trip() {
+        let yaml = serde_yaml::to_string(&deser_time_spec("$end - 1 minute")).unwrap();
+        assert!(matches!(
+            serde_yaml::from_str::<TimeSpec>(&yaml).unwrap(),
+            TimeSpec::Relative { variable, offset }
+            if variable == "end" && offset == TimeDelta::minutes(-1)
+        ));
+    }
+
+    #[test]
+    fn rebase_shifts_times() {
+        let yaml = r#"
+variables:
+  start: "2099-01-01T12:00:00Z"
+  end: "2099-01-01T12:10:00Z"
+  frequency: "437.5"
+steps:
+  - cmd: "first"
+    time: "2099-01-01T12:01:00Z"
+  - cmd: "second"
+    time: "T+5m"
+"#;
+        let mut task = Task::from_yaml_str(yaml).unwrap();
+        let start = "2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        task.rebase(start).unwrap();
+
+        assert_eq!(task.time_range().unwrap().0, start);
+        assert_eq!(task.variables["end"], "2026-01-01T00:10:00+00:00");
+        assert_eq!(task.variables["frequency"], "437.5");
+        assert!(matches!(
+            task.steps[0].time,
+            Some(TimeSpec::Absolute(t)) if t == start + TimeDelta::minutes(1)
+        ));
+    }
+
     #[test]
     fn step_rejects_unknown_fields() {
         let yaml = r#"
@@ -389,4 +586,37 @@ steps:
             _ => panic!("expected yaml error"),
         }
     }
+
+    #[test]
+    fn priority_defaults_to_zero() {
+        let task = Task::from_yaml_str(FULL_YAML).unwrap();
+        assert_eq!(task.priority(), 0);
+
+        let yaml = FULL_YAML.replace("variables:", "variables:\n  priority: \"10\"");
+        assert_eq!(Task::from_yaml_str(&yaml).unwrap().priority(), 10);
+        let yaml = FULL_YAML.replace("variables:", "variables:\n  priority: high");
+        assert!(matches!(
+            Task::from_yaml_str(&yaml),
+            Err(Error::InvalidPriority(_))
+        ));
+    }
+
+    #[test]
+    fn preemption_is_recorded_in_the_definition() {
+        let preempted = Preempted {
+            by: "emergency".into(),
+            time: "2026-01-12T09:00:00Z".parse().unwrap(),
+        };
+        let yaml = preempted.record(FULL_YAML).unwrap();
+        let task = Task::from_yaml_str(&yaml).unwrap();
+        assert_eq!(task.preempted, Some(preempted));
+        assert_eq!(task.steps.len(), 2);
+
+        let mut other = task.clone();
+        assert!(task.overlaps(&other));
+        other
+            .rebase(task.get_time_variable("end").unwrap())
+            .unwrap();
+        assert!(!task.overlaps(&other));
+    }
 }
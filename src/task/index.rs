@@ -0,0 +1,136 @@
+//! An index of the task files in the directories of each state, so that overlap checks and
+//! listings don't parse every task again. A file is only parsed again once its modification time
+//! or length changes, whoever wrote it: the API, the scheduler or an operator.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use tokio::sync::Mutex;
+
+use super::Task;
+
+/// A task file of the index.
+#[derive(Debug, Clone)]
+pub struct IndexedTask {
+    pub id: String,
+    pub task: Arc<Task>,
+    /// When the file was last written
+    pub modified: SystemTime,
+}
+
+#[derive(Debug)]
+struct Entry {
+    modified: SystemTime,
+    len: u64,
+    /// None for files that aren't valid tasks, so they aren't parsed again either
+    task: Option<Arc<Task>>,
+}
+
+#[derive(Debug)]
+pub struct TaskIndex {
+    tasks_path: PathBuf,
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+}
+
+impl TaskIndex {
+    /// An index of the tasks under `tasks_path`, empty until they are first listed.
+    pub fn new(tasks_path: &Path) -> Self {
+        Self {
+            tasks_path: tasks_path.to_path_buf(),
+            entries: Mutex::default(),
+        }
+    }
+
+    /// The valid tasks in the directory of `state`, e.g. `Active`, in no particular order.
+    pub async fn tasks(&self, state: &str) -> Vec<IndexedTask> {
+        let dir = self.tasks_path.join(state);
+        let mut entries = self.entries.lock().await;
+        let mut tasks = Vec::new();
+        let mut present = HashSet::new();
+        if let Ok(mut read_dir) = tokio::fs::read_dir(&dir).await {
+            while let Ok(Some(file)) = read_dir.next_entry().await {
+                let Ok(metadata) = file.metadata().await else {
+                    continue;
+                };
+                let Ok(modified) = metadata.modified() else {
+                    continue;
+                };
+                let path = file.path();
+                let fresh = entries
+                    .get(&path)
+                    .is_some_and(|e| e.modified == modified && e.len == metadata.len());
+                if !fresh {
+                    let task = tokio::fs::read_to_string(&path)
+                        .await
+                        .ok()
+                        .and_then(|content| Task::from_yaml_str(&content).ok())
+                        .map(Arc::new);
+                    let len = metadata.len();
+                    entries.insert(
+                        path.clone(),
+                        Entry {
+                            modified,
+                            len,
+                            task,
+                        },
+                    );
+                }
+                if let Some(task) = &entries[&path].task {
+                    let file_name = file.file_name().to_string_lossy().to_string();
+                    tasks.push(IndexedTask {
+                        id: Task::id_from_filename(&file_name).to_string(),
+                        task: task.clone(),
+                        modified,
+                    });
+                }
+                present.insert(path);
+            }
+        }
+        // Forget the files moved or deleted since
+        entries.retain(|path, _| path.parent() != Some(dir.as_path()) || present.contains(path));
+        tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK: &str = r#"
+variables:
+  start: "2026-01-01T10:00:00Z"
+  end: "2026-01-01T10:10:00Z"
+steps:
+  - cmd: "echo hello"
+    time: "T+0s"
+"#;
+
+    #[tokio::test]
+    async fn tasks_are_parsed_again_once_changed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let active = tmp.path().join("Active");
+        std::fs::create_dir(&active).unwrap();
+        std::fs::write(active.join("a.yaml"), TASK).unwrap();
+        std::fs::write(active.join("broken.yaml"), "not: [a task").unwrap();
+        let index = TaskIndex::new(tmp.path());
+
+        let tasks = index.tasks("Active").await;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, "a");
+        // Unchanged files are shared rather than parsed again
+        let again = index.tasks("Active").await;
+        assert!(Arc::ptr_eq(&tasks[0].task, &again[0].task));
+
+        let edited = format!("{}# edited\n", TASK.replace("10:10", "10:20"));
+        std::fs::write(active.join("a.yaml"), edited).unwrap();
+        let tasks = index.tasks("Active").await;
+        assert_eq!(tasks[0].task.variables["end"], "2026-01-01T10:20:00Z");
+
+        std::fs::remove_file(active.join("a.yaml")).unwrap();
+        assert!(index.tasks("Active").await.is_empty());
+        assert_eq!(index.entries.lock().await.len(), 1);
+        assert!(index.tasks("Completed").await.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod format;
+pub mod index;
 pub mod runner;
 pub mod utils;
 
@@ -1,18 +1,22 @@
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::ExitStatus;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
 use std::{fs, io};
 
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use tokio::process::Command;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::Receiver;
 use tokio::sync::mpsc::{self, UnboundedSender};
-use tokio::time::{Duration, sleep};
+use tokio::sync::watch;
+use tokio::time::{Duration, Instant, sleep};
 use tokio::{spawn, task};
-use tracing::{info, warn};
+use tracing::{Instrument, info, warn};
 
-use crate::task::format::{self, OnFail, Step, Task, TimeSpec};
+use crate::task::format::{self, OnFail, Step, Task};
 use crate::task::utils::{resolve_time, resolve_variables, substitute_variables};
 
 #[derive(Debug, thiserror::Error)]
@@ -27,29 +31,229 @@ pub enum Error {
     Io(io::Error),
 }
 
+/// Name of the file in the artifact directory recording the outcome of each step.
+pub const EXECUTION_LOG: &str = "execution_log.yaml";
+
+/// Directories in the artifact directory the output (stdout and stderr) of each step and cleanup
+/// step is written to, as `<index>.log`.
+pub const STEP_LOGS: &str = "steps";
+pub const CLEANUP_LOGS: &str = "cleanup";
+
+/// How long a stopped step has to exit after SIGTERM before it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
 pub struct RunConfig {
     pub artifact_base: PathBuf,
+    /// Plays the task through on a simulated clock instead of waiting for the real step times.
+    pub simulation: Option<Simulation>,
+    /// Pauses, resumes or aborts the run while in progress.
+    pub control: RunControl,
+}
+
+/// Progress of a run, and how it was told to proceed through its [`RunControl`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunStatus {
+    /// Index of the last step started, `None` until the first one is.
+    pub step: Option<usize>,
+    /// Whether steps are held back until the run is resumed.
+    pub paused: bool,
+    /// Whether the run was aborted. Cleanup steps still run.
+    pub aborted: bool,
+}
+
+/// A step of a run starting or finishing, published through [`RunControl::subscribe`].
+///
+/// Only the steps of the task are published, not its cleanup steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepEvent {
+    Started {
+        step: usize,
+    },
+    Completed {
+        step: usize,
+        result: StepResult,
+        exit_code: Option<i32>,
+    },
+}
+
+/// Step events kept for subscribers lagging behind.
+const STEP_EVENTS_CAPACITY: usize = 64;
+
+/// Handle to control a run in progress from outside of it.
+///
+/// Pausing holds the following steps back, even once their time has come, until the run is
+/// resumed. The steps already running are not affected. Aborting stops the running steps like the
+/// end of the task does and runs the cleanup steps.
+#[derive(Debug, Clone)]
+pub struct RunControl {
+    status: Arc<watch::Sender<RunStatus>>,
+    events: broadcast::Sender<StepEvent>,
+}
+
+impl Default for RunControl {
+    fn default() -> Self {
+        Self {
+            status: Arc::new(watch::channel(RunStatus::default()).0),
+            events: broadcast::channel(STEP_EVENTS_CAPACITY).0,
+        }
+    }
+}
+
+impl RunControl {
+    pub fn status(&self) -> RunStatus {
+        *self.status.borrow()
+    }
+
+    pub fn pause(&self) {
+        self.status.send_modify(|s| s.paused = true);
+    }
+
+    pub fn resume(&self) {
+        self.status.send_modify(|s| s.paused = false);
+    }
+
+    pub fn abort(&self) {
+        self.status.send_modify(|s| s.aborted = true);
+    }
+
+    /// Receives the [`StepEvent`]s of the run from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<StepEvent> {
+        self.events.subscribe()
+    }
+
+    fn started(&self, step: usize) {
+        self.status.send_modify(|s| s.step = Some(step));
+        // Nobody may be listening
+        let _ = self.events.send(StepEvent::Started { step });
+    }
+
+    fn completed(&self, step: usize, outcome: &StepOutcome) {
+        let entry = LogEntry::from(outcome);
+        let _ = self.events.send(StepEvent::Completed {
+            step,
+            result: entry.result,
+            exit_code: entry.exit_code,
+        });
+    }
+
+    /// Completes once the run is aborted.
+    async fn aborted(&self) {
+        let mut status = self.status.subscribe();
+        // The sender lives as long as `self`
+        let _ = status.wait_for(|s| s.aborted).await;
+    }
+
+    /// Waits until the run is not paused. Returns false if `exit_rx` receives the exit signal
+    /// meanwhile.
+    async fn wait_while_paused(&self, exit_rx: &mut Receiver<()>) -> bool {
+        let mut status = self.status.subscribe();
+        if !status.borrow().paused {
+            return true;
+        }
+        info!("run paused, waiting to be resumed");
+        tokio::select! {
+            _ = status.wait_for(|s| !s.paused) => {
+                info!("run resumed");
+                true
+            }
+            _ = exit_rx.recv() => {
+                info!("waiting aborted due to exit signal");
+                false
+            }
+        }
+    }
+}
+
+/// Accelerated run of a task, to check the sequencing of its steps.
+///
+/// The simulated clock starts at the start time of the task and runs `speed` times faster than
+/// real time. Step commands are not executed: a mock command printing the simulated time and the
+/// command takes their place and always succeeds.
+#[derive(Debug, Clone, Copy)]
+pub struct Simulation {
+    pub speed: f64,
+}
+
+/// Source of the current time for a run.
+#[derive(Debug, Clone, Copy)]
+enum Clock {
+    Real,
+    Simulated {
+        origin: DateTime<Utc>,
+        started: Instant,
+        speed: f64,
+    },
+}
+
+impl Clock {
+    fn now(&self) -> DateTime<Utc> {
+        match self {
+            Clock::Real => Utc::now(),
+            Clock::Simulated {
+                origin,
+                started,
+                speed,
+            } => {
+                let elapsed = started.elapsed().mul_f64(*speed);
+                chrono::Duration::from_std(elapsed)
+                    .ok()
+                    .and_then(|elapsed| origin.checked_add_signed(elapsed))
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC)
+            }
+        }
+    }
+
+    /// Create a sleep future that resolves at `target` (or immediately if already past).
+    fn sleep_until(&self, target: DateTime<Utc>) -> tokio::time::Sleep {
+        let dur = (target - self.now()).to_std().unwrap_or(Duration::ZERO);
+        match self {
+            Clock::Real => sleep(dur),
+            Clock::Simulated { speed, .. } => sleep(dur.div_f64(*speed)),
+        }
+    }
+
+    fn is_simulated(&self) -> bool {
+        matches!(self, Clock::Simulated { .. })
+    }
 }
 
 #[derive(Debug)]
 pub struct RunOutcome {
     pub artifact_dir: PathBuf,
     pub step_outcomes: Vec<StepOutcome>,
+    /// Whether the run was aborted through its [`RunControl`].
+    pub abort_requested: bool,
 }
 
 impl RunOutcome {
     pub fn aborted(&self) -> bool {
-        self.step_outcomes
-            .iter()
-            .any(|o| matches!(o, StepOutcome::Abort { .. }))
+        self.abort_requested
+            || self
+                .step_outcomes
+                .iter()
+                .any(|o| matches!(o, StepOutcome::Abort { .. }))
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum StepOutcome {
-    Completed { cmd: String, status: ExitStatus },
-    Abort { cmd: String, reason: AbortReason },
-    SpawnError { cmd: String, error: String },
+    Completed {
+        cmd: String,
+        status: ExitStatus,
+    },
+    Abort {
+        cmd: String,
+        reason: AbortReason,
+    },
+    SpawnError {
+        cmd: String,
+        error: String,
+    },
+    /// The step ran longer than its `timeout` and was stopped.
+    TimedOut {
+        cmd: String,
+        timeout: Duration,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -57,6 +261,7 @@ pub enum AbortReason {
     ExitStatus(ExitStatus),
     ExitSignalReceived,
     SpawnError(String),
+    TimedOut(Duration),
 }
 impl From<&StepOutcome> for Option<AbortReason> {
     fn from(value: &StepOutcome) -> Self {
@@ -67,11 +272,121 @@ impl From<&StepOutcome> for Option<AbortReason> {
             StepOutcome::SpawnError { cmd: _, error } => {
                 Some(AbortReason::SpawnError(error.clone()))
             }
+            StepOutcome::TimedOut { cmd: _, timeout } => Some(AbortReason::TimedOut(*timeout)),
             _ => None,
         }
     }
 }
 
+/// Entry of the execution log, appended when a step finishes or the artifacts of the run are
+/// uploaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub time: DateTime<Utc>,
+    pub cmd: String,
+    pub result: StepResult,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// How many times the step was run, if retried.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepResult {
+    Completed,
+    Aborted,
+    SpawnError,
+    /// The step ran longer than its timeout and was stopped.
+    TimedOut,
+    /// The artifacts were uploaded to remote storage.
+    Uploaded,
+    /// The artifacts could not be uploaded to remote storage.
+    UploadFailed,
+}
+
+impl StepResult {
+    /// Whether the entry records an upload rather than a step.
+    pub fn is_upload(self) -> bool {
+        matches!(self, StepResult::Uploaded | StepResult::UploadFailed)
+    }
+}
+
+impl From<&StepOutcome> for LogEntry {
+    fn from(outcome: &StepOutcome) -> Self {
+        let (cmd, result, exit_code, error) = match outcome {
+            StepOutcome::Completed { cmd, status } => {
+                (cmd, StepResult::Completed, status.code(), None)
+            }
+            StepOutcome::Abort { cmd, reason } => {
+                let (exit_code, error) = match reason {
+                    AbortReason::ExitStatus(status) => (status.code(), None),
+                    AbortReason::ExitSignalReceived => (None, Some("exit signal received".into())),
+                    AbortReason::SpawnError(e) => (None, Some(e.clone())),
+                    AbortReason::TimedOut(timeout) => (None, Some(timed_out(*timeout))),
+                };
+                (cmd, StepResult::Aborted, exit_code, error)
+            }
+            StepOutcome::SpawnError { cmd, error } => {
+                (cmd, StepResult::SpawnError, None, Some(error.clone()))
+            }
+            StepOutcome::TimedOut { cmd, timeout } => {
+                (cmd, StepResult::TimedOut, None, Some(timed_out(*timeout)))
+            }
+        };
+        LogEntry {
+            time: Utc::now(),
+            cmd: cmd.clone(),
+            result,
+            exit_code,
+            error,
+            attempts: None,
+        }
+    }
+}
+
+fn timed_out(timeout: Duration) -> String {
+    format!("timed out after {}", humantime::format_duration(timeout))
+}
+
+/// Parses the execution log in `artifact_dir`, returning no entries if it does not exist yet.
+pub fn read_execution_log(artifact_dir: &Path) -> io::Result<Vec<LogEntry>> {
+    let content = match fs::read_to_string(artifact_dir.join(EXECUTION_LOG)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_yaml::from_str(&content).map_err(io::Error::other)
+}
+
+/// Appends `entry` to the execution log in `artifact_dir`.
+pub fn append_log_entry(artifact_dir: &Path, entry: &LogEntry) -> io::Result<()> {
+    let entry = serde_yaml::to_string(&[entry]).map_err(io::Error::other)?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(artifact_dir.join(EXECUTION_LOG))?
+        .write_all(entry.as_bytes())
+}
+
+/// Appends the outcome of a step, run `attempts` times, to the execution log in `artifact_dir`.
+fn append_execution_log(artifact_dir: &Path, outcome: &StepOutcome, attempts: u32, clock: Clock) {
+    let entry = LogEntry {
+        time: clock.now(),
+        attempts: (attempts > 1).then_some(attempts),
+        ..LogEntry::from(outcome)
+    };
+    if let Err(e) = append_log_entry(artifact_dir, &entry) {
+        warn!(?e, ?artifact_dir, "failed to write execution log");
+    }
+}
+
 pub async fn run(task: Task, config: RunConfig) -> Result<RunOutcome, Error> {
     // Create artifact directory
     let artifact_dir = config.artifact_base;
@@ -95,27 +410,134 @@ pub async fn run(task: Task, config: RunConfig) -> Result<RunOutcome, Error> {
     let resolved_task_yaml = serde_yaml::to_string(&task).unwrap();
     fs::write(artifact_dir.join("task.yml"), resolved_task_yaml).map_err(Error::Io)?;
 
+    let clock = match config.simulation {
+        None => Clock::Real,
+        Some(Simulation { speed }) => {
+            info!(speed, %start_time, "simulating run");
+            Clock::Simulated {
+                origin: start_time,
+                started: Instant::now(),
+                speed,
+            }
+        }
+    };
+
     // If start is in the future, wait
-    sleep_until(start_time).await;
+    let control = config.control;
+    tokio::select! {
+        _ = clock.sleep_until(start_time) => {}
+        _ = control.aborted() => info!("aborted before the start"),
+    }
 
     // Run main steps with end-time deadline
-    let step_outcomes = run_steps(task.steps, &task.variables, &artifact_dir, end_time).await;
+    let step_outcomes = if control.status().aborted {
+        Vec::new()
+    } else {
+        run_steps(
+            task.steps,
+            &task.variables,
+            &artifact_dir,
+            STEP_LOGS,
+            end_time,
+            clock,
+            Some(&control),
+        )
+        .await
+    };
 
     // Cleanup steps
-    let _ = run_steps(task.cleanup, &task.variables, &artifact_dir, None).await;
+    let _ = run_steps(
+        task.cleanup,
+        &task.variables,
+        &artifact_dir,
+        CLEANUP_LOGS,
+        None,
+        clock,
+        None,
+    )
+    .await;
 
     Ok(RunOutcome {
         artifact_dir,
         step_outcomes,
+        abort_requested: control.status().aborted,
+    })
+}
+
+/// How the runner would go through a task, as found by [`plan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub steps: Vec<PlannedStep>,
+    pub cleanup: Vec<PlannedStep>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedStep {
+    /// Command with the variables substituted
+    pub cmd: String,
+    /// When the step would start. `None` if the task ends before, so it would not be started.
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Goes through `task` as [`run`] would, without creating artifacts or spawning any process: the
+/// time each step would start at and the command it would run.
+///
+/// Variables evaluated by shell commands (`${...}`) are left as they are. Steps waited for are
+/// assumed to finish at once, and the others to run until the end of the task.
+pub fn plan(task: &Task) -> Result<Plan, Error> {
+    let start = task.get_time_variable("start").map_err(Error::Format)?;
+    let end = task.get_time_variable("end").ok();
+
+    let walk = |steps: &[Step], mut now: DateTime<Utc>, deadline: Option<DateTime<Utc>>| {
+        let mut stopped = false;
+        let planned = steps
+            .iter()
+            .map(|step| {
+                if let Some(time) = step
+                    .time
+                    .as_ref()
+                    .and_then(|t| resolve_time(t, &task.variables))
+                {
+                    now = now.max(time);
+                }
+                stopped |= deadline.is_some_and(|deadline| now >= deadline);
+                PlannedStep {
+                    cmd: substitute_variables(&step.cmd, &task.variables),
+                    time: (!stopped).then_some(now),
+                }
+            })
+            .collect::<Vec<_>>();
+        (planned, now)
+    };
+    let (steps, finished) = walk(&task.steps, start, end);
+    let cleanup_start = match end {
+        Some(end) if task.steps.iter().any(|s| !s.wait) => end,
+        Some(end) => finished.min(end),
+        None => finished,
+    };
+    let (cleanup, _) = walk(&task.cleanup, cleanup_start, None);
+    Ok(Plan {
+        start,
+        end,
+        steps,
+        cleanup,
     })
 }
 
 /// Spawns a step runner and monitors the outcome of each task, returning a Vec of StepOutcomes.
+///
+/// The output of the steps is written to `log_dir` under `cwd`. The steps are paused, resumed and
+/// aborted through `control`, if given.
 async fn run_steps(
     steps: Vec<Step>,
     vars: &HashMap<String, String>,
     cwd: &Path,
+    log_dir: &str,
     end_time: Option<DateTime<Utc>>,
+    clock: Clock,
+    control: Option<&RunControl>,
 ) -> Vec<StepOutcome> {
     let mut outcomes = Vec::new();
     let (outcome_tx, mut outcome_rx) = mpsc::unbounded_channel();
@@ -124,19 +546,33 @@ async fn run_steps(
     info!(?steps, ?end_time);
 
     // Spawner task
-    let spawner = spawn(spawn_steps(
-        steps,
-        vars.clone(),
-        cwd.to_path_buf(),
-        exit_tx.clone(),
-        exit_rx,
-        outcome_tx,
-    ));
+    let spawner = spawn(
+        spawn_steps(
+            steps,
+            vars.clone(),
+            cwd.to_path_buf(),
+            cwd.join(log_dir),
+            exit_tx.clone(),
+            exit_rx,
+            outcome_tx,
+            clock,
+            control.cloned(),
+        )
+        .in_current_span(),
+    );
 
     // Monitor loop
-    let deadline = sleep_until(end_time.unwrap_or(Utc::now()));
+    let deadline = clock.sleep_until(end_time.unwrap_or(clock.now()));
     let mut deadline_fired = false;
     tokio::pin!(deadline);
+    let abort = async {
+        match control {
+            Some(control) => control.aborted().await,
+            None => std::future::pending().await,
+        }
+    };
+    let mut abort_fired = false;
+    tokio::pin!(abort);
     loop {
         tokio::select! {
             _ = &mut deadline, if end_time.is_some() && !deadline_fired => {
@@ -144,12 +580,21 @@ async fn run_steps(
                 info!("deadline reached. Sending exit signal.");
                 let _ = exit_tx.send(());
             }
+            _ = &mut abort, if !abort_fired => {
+                abort_fired = true;
+                info!("abort requested. Sending exit signal.");
+                let _ = exit_tx.send(());
+            }
             outcome = outcome_rx.recv() => {
                 if outcome.is_none() {
                     info!("all senders exited");
                     break;
                 }
-                let outcome = outcome.unwrap();
+                let (index, outcome, attempts) = outcome.unwrap();
+                append_execution_log(cwd, &outcome, attempts, clock);
+                if let Some(control) = control {
+                    control.completed(index, &outcome);
+                }
                 outcomes.push(outcome.clone());
 
                 if let StepOutcome::Abort { cmd, reason } = outcome {
@@ -176,29 +621,41 @@ async fn run_steps(
 /// to `outcome_tx`.
 ///
 /// Returns a Vec of JoinHandles for tasks that are not yet completed (i.e. non-waited tasks.)
+#[allow(clippy::too_many_arguments)]
 async fn spawn_steps(
     steps: Vec<Step>,
     vars: HashMap<String, String>,
     cwd: PathBuf,
+    log_dir: PathBuf,
     exit_tx: broadcast::Sender<()>,
     mut exit_rx: Receiver<()>,
-    outcome_tx: UnboundedSender<StepOutcome>,
+    outcome_tx: UnboundedSender<(usize, StepOutcome, u32)>,
+    clock: Clock,
+    control: Option<RunControl>,
 ) -> Vec<task::JoinHandle<StepOutcome>> {
     let mut handles = Vec::new();
-    for step in steps {
+    for (index, step) in steps.into_iter().enumerate() {
         // If step.time is set, resolve it
-        let step_start = step.time.and_then(|t| resolve_time(&t, &vars));
+        let step_start = step.time.as_ref().and_then(|t| resolve_time(t, &vars));
 
         // Wait until the step start time is reached (if configured),
         // while checking if the exit signal has been sent.
-        let should_execute_step = wait_for_step_start_or_abort(step_start, &mut exit_rx).await;
+        let should_execute_step =
+            wait_for_step_start_or_abort(step_start, &mut exit_rx, clock).await;
         if !should_execute_step {
             break;
         }
+        if let Some(control) = &control {
+            if !control.wait_while_paused(&mut exit_rx).await {
+                break;
+            }
+            control.started(index);
+        }
 
         // Substitute variables in step.cmd
         let cmd = substitute_variables(&step.cmd, &vars);
-        info!(cmd = %cmd, wait = step.wait, "executing step");
+        let env = step_env(&step, index, &vars, &cwd);
+        info!(cmd = %cmd, wait = step.wait, cwd = ?env.cwd, "executing step");
 
         let (abort_on_fail, max_attempts) = match &step.on_fail {
             OnFail::Continue => (false, 1),
@@ -207,14 +664,21 @@ async fn spawn_steps(
         };
 
         // Spawn the command for this step
-        let step_handle = spawn(run_step(
-            cmd.clone(),
-            abort_on_fail,
-            max_attempts,
-            cwd.to_path_buf(),
-            exit_tx.subscribe(),
-            outcome_tx.clone(),
-        ));
+        let step_handle = spawn(
+            run_step(
+                index,
+                cmd.clone(),
+                abort_on_fail,
+                max_attempts,
+                step.timeout,
+                env,
+                log_dir.join(format!("{index}.log")),
+                exit_tx.subscribe(),
+                outcome_tx.clone(),
+                clock,
+            )
+            .in_current_span(),
+        );
 
         if step.wait {
             // Wait for the current step to finish executing before continuing
@@ -232,22 +696,35 @@ async fn spawn_steps(
     handles
 }
 
-/// Executes the command for a specific step, retrying if configured,
-/// and sends the `StepOutcome` to `tx`.
+/// Executes the command for step `index`, retrying if configured, appending its output to `log`,
+/// and sends the `StepOutcome` with the index and the number of attempts to `tx`. Each attempt is
+/// stopped after `timeout`, if given.
 /// Returns the `StepOutcome`.
+#[allow(clippy::too_many_arguments)]
 async fn run_step(
+    index: usize,
     cmd: String,
     abort_on_fail: bool,
     max_attempts: u32,
-    cwd: PathBuf,
+    timeout: Option<Duration>,
+    env: StepEnv,
+    log: PathBuf,
     mut exit_rx: Receiver<()>,
-    tx: UnboundedSender<StepOutcome>,
+    tx: UnboundedSender<(usize, StepOutcome, u32)>,
+    clock: Clock,
 ) -> StepOutcome {
     let mut outcome: Option<StepOutcome> = None;
+    let mut attempts = 0;
 
     for _i in 1..=max_attempts {
+        attempts += 1;
         // Try to spawn a child process for `cmd`
-        let mut child = match spawn_command(&cmd, &cwd) {
+        let spawned = if clock.is_simulated() {
+            spawn_mock_command(&cmd, &env.cwd, &log, clock.now())
+        } else {
+            spawn_command(&cmd, &env, &log)
+        };
+        let mut child = match spawned {
             Ok(child) => {
                 info!(pid = ?child.id(), cmd = cmd, "spawned child");
                 child
@@ -264,6 +741,22 @@ async fn run_step(
             }
         };
 
+        let timed_out = async {
+            match timeout {
+                Some(timeout) => {
+                    // A timeout beyond the representable times never expires
+                    let deadline = chrono::Duration::from_std(timeout)
+                        .ok()
+                        .and_then(|timeout| clock.now().checked_add_signed(timeout));
+                    match deadline {
+                        Some(deadline) => clock.sleep_until(deadline).await,
+                        None => std::future::pending().await,
+                    }
+                }
+                None => std::future::pending().await,
+            }
+        };
+
         tokio::select! {
             exit = child.wait() => {
                 // Child finished running
@@ -292,10 +785,21 @@ async fn run_step(
                 }
             }
 
+            _ = timed_out => {
+                // Running for too long, retry if configured
+                let timeout = timeout.unwrap_or_default();
+                warn!(?cmd, ?timeout, "step timed out, stopping child");
+                stop(&mut child).await;
+                outcome = Some(StepOutcome::TimedOut {
+                    cmd: cmd.clone(),
+                    timeout,
+                });
+            }
+
             _ = exit_rx.recv() => {
                 // Exit signal (abort or deadline)
-                info!(child = ?child, "exit signal received, killing child");
-                child.kill().await.unwrap();
+                info!(child = ?child, "exit signal received, stopping child");
+                stop(&mut child).await;
                 outcome = Some(StepOutcome::Abort {
                     cmd: cmd.clone(),
                     reason: AbortReason::ExitSignalReceived
@@ -317,7 +821,7 @@ async fn run_step(
     };
 
     // Send step outcome to monitor loop
-    let _ = tx.send(outcome.clone());
+    let _ = tx.send((index, outcome.clone(), attempts));
 
     outcome
 }
@@ -325,8 +829,9 @@ async fn run_step(
 async fn wait_for_step_start_or_abort(
     step_start: Option<DateTime<Utc>>,
     abort_rx: &mut Receiver<()>,
+    clock: Clock,
 ) -> bool {
-    let step_start = sleep_until(step_start.unwrap_or(Utc::now()));
+    let step_start = clock.sleep_until(step_start.unwrap_or(clock.now()));
 
     tokio::select! {
         _ = step_start => {
@@ -340,23 +845,143 @@ async fn wait_for_step_start_or_abort(
     }
 }
 
-/// Create a sleep future that resolves at `target` (or immediately if already past).
-fn sleep_until(target: DateTime<Utc>) -> tokio::time::Sleep {
-    let dur = (target - Utc::now()).to_std().unwrap_or(Duration::ZERO);
-    sleep(dur)
+/// Where a step runs: its working directory and the variables added to its environment.
+#[derive(Debug, Clone)]
+struct StepEnv {
+    cwd: PathBuf,
+    vars: Vec<(String, String)>,
+}
+
+/// The environment of step `index` of the run in `artifact_dir`: the context of the run
+/// (`SATOMAT_SCHEDULE_ID`, `SATOMAT_STEP_INDEX`, `SATOMAT_START` and `SATOMAT_ARTIFACTS_DIR`),
+/// each of the task `vars` as `SATOMAT_VAR_<NAME>`, and the `env` of the step.
+///
+/// The `cwd` of the step is relative to the artifact directory, where steps run by default.
+fn step_env(
+    step: &Step,
+    index: usize,
+    vars: &HashMap<String, String>,
+    artifact_dir: &Path,
+) -> StepEnv {
+    let artifact_dir = std::path::absolute(artifact_dir).unwrap_or(artifact_dir.to_path_buf());
+    let id = artifact_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut env = vec![
+        ("SATOMAT_SCHEDULE_ID".to_string(), id),
+        ("SATOMAT_STEP_INDEX".to_string(), index.to_string()),
+        (
+            "SATOMAT_START".to_string(),
+            vars.get("start").cloned().unwrap_or_default(),
+        ),
+        (
+            "SATOMAT_ARTIFACTS_DIR".to_string(),
+            artifact_dir.display().to_string(),
+        ),
+    ];
+    let mut names: Vec<&String> = vars.keys().collect();
+    names.sort();
+    for name in names {
+        let name_upper: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        env.push((format!("SATOMAT_VAR_{name_upper}"), vars[name].clone()));
+    }
+    env.extend(
+        step.env
+            .iter()
+            .map(|(name, value)| (name.clone(), substitute_variables(value, vars))),
+    );
+
+    StepEnv {
+        cwd: match &step.cwd {
+            Some(cwd) => artifact_dir.join(substitute_variables(cwd, vars)),
+            None => artifact_dir,
+        },
+        vars: env,
+    }
+}
+
+/// Run `sh -c "cmd"` in the working directory and with the variables of `env`, appending its
+/// output to `log`. Runs `cmd` in its own process group, so that it can be stopped with the
+/// processes it starts.
+fn spawn_command(cmd: &str, env: &StepEnv, log: &Path) -> std::io::Result<tokio::process::Child> {
+    let (stdout, stderr) = output_to(log)?;
+    Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .current_dir(&env.cwd)
+        .envs(env.vars.iter().map(|(name, value)| (name, value)))
+        .stdout(stdout)
+        .stderr(stderr)
+        .process_group(0)
+        .spawn()
+}
+
+/// Opens `log` for appending the output of a step, as its stdout and stderr.
+fn output_to(log: &Path) -> io::Result<(Stdio, Stdio)> {
+    if let Some(dir) = log.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = fs::OpenOptions::new().create(true).append(true).open(log)?;
+    Ok((file.try_clone()?.into(), file.into()))
+}
+
+/// Stops the process group of `child`: SIGTERM first, letting e.g. SDR streams release their
+/// devices, and SIGKILL if it has not exited within [`STOP_TIMEOUT`].
+async fn stop(child: &mut tokio::process::Child) {
+    let Some(pid) = child.id() else {
+        // Already exited
+        return;
+    };
+    let group = -(pid as libc::pid_t);
+    // SAFETY: kill has no memory safety requirements. The child has not been reaped, so its
+    // process group still exists.
+    unsafe { libc::kill(group, libc::SIGTERM) };
+    if tokio::time::timeout(STOP_TIMEOUT, child.wait())
+        .await
+        .is_err()
+    {
+        warn!(pid, "child did not exit on SIGTERM, killing it");
+        // SAFETY: as above
+        unsafe { libc::kill(group, libc::SIGKILL) };
+        let _ = child.kill().await;
+    }
 }
 
-/// Run `sh -c "cmd"` with CWD set to the given directory.
-fn spawn_command(cmd: &str, cwd: &Path) -> std::io::Result<tokio::process::Child> {
+/// Run a command printing `cmd` and the simulated `time` instead of executing `cmd`.
+fn spawn_mock_command(
+    cmd: &str,
+    cwd: &Path,
+    log: &Path,
+    time: DateTime<Utc>,
+) -> std::io::Result<tokio::process::Child> {
+    let (stdout, stderr) = output_to(log)?;
     Command::new("sh")
         .arg("-c")
+        .arg("printf '[%s] %s\\n' \"$1\" \"$2\"")
+        .arg("sh")
+        .arg(time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
         .arg(cmd)
         .current_dir(cwd)
+        .stdout(stdout)
+        .stderr(stderr)
+        .process_group(0)
         .spawn()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use super::*;
     use crate::task::format::{OnFail, Step, Task, TimeSpec};
     use chrono::TimeDelta;
@@ -381,6 +1006,9 @@ mod tests {
             time: None,
             wait: false,
             on_fail: OnFail::Abort,
+            timeout: None,
+            env: Default::default(),
+            cwd: None,
         }
     }
 
@@ -390,6 +1018,9 @@ mod tests {
             time: None,
             wait: true,
             on_fail: OnFail::Abort,
+            timeout: None,
+            env: Default::default(),
+            cwd: None,
         }
     }
 
@@ -399,6 +1030,9 @@ mod tests {
             time: None,
             wait: true,
             on_fail: OnFail::Continue,
+            timeout: None,
+            env: Default::default(),
+            cwd: None,
         }
     }
 
@@ -408,6 +1042,9 @@ mod tests {
             time: None,
             wait: true,
             on_fail: OnFail::Retry(n),
+            timeout: None,
+            env: Default::default(),
+            cwd: None,
         }
     }
 
@@ -416,6 +1053,8 @@ mod tests {
         let temp = tempfile::tempdir().expect("failed to create temp dir");
         let config = RunConfig {
             artifact_base: temp.path().to_path_buf(),
+            simulation: None,
+            control: Default::default(),
         };
         run(task, config).await.expect("run should succeed")
     }
@@ -492,6 +1131,104 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn steps_running_too_long_are_stopped_and_retried() {
+        init_tracing();
+        let task = make_task(
+            vec![
+                Step {
+                    timeout: Some(Duration::from_millis(200)),
+                    ..waited_continue("sleep 60")
+                },
+                Step {
+                    timeout: Some(Duration::from_millis(200)),
+                    ..waited_retry("sleep 60", 2)
+                },
+            ],
+            vec![],
+        );
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let config = RunConfig {
+            artifact_base: temp.path().to_path_buf(),
+            simulation: None,
+            control: Default::default(),
+        };
+        let started = Instant::now();
+        let outcome = run(task, config).await.expect("run should succeed");
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(matches!(
+            &outcome.step_outcomes[1],
+            StepOutcome::Abort {
+                reason: AbortReason::TimedOut(_),
+                ..
+            }
+        ));
+        let log = read_execution_log(temp.path()).unwrap();
+        // The run goes on after the first step, the second is given up after two attempts
+        assert_eq!(log[0].result, StepResult::TimedOut);
+        assert_eq!(log[0].attempts, None);
+        assert_eq!(log[1].result, StepResult::Aborted);
+        assert_eq!(log[1].attempts, Some(2));
+        assert_eq!(log[1].error.as_deref(), Some("timed out after 200ms"));
+    }
+
+    #[tokio::test]
+    async fn huge_timeouts_never_expire() {
+        init_tracing();
+        let task = make_task(
+            vec![Step {
+                timeout: Some(Duration::MAX),
+                ..waited("true")
+            }],
+            vec![],
+        );
+        let outcome = run_with_tempdir(task).await;
+        assert!(matches!(
+            &outcome.step_outcomes[0],
+            StepOutcome::Completed { status, .. } if status.success()
+        ));
+    }
+
+    #[tokio::test]
+    async fn steps_get_the_context_of_the_run_in_their_environment() {
+        init_tracing();
+        let task = Task::new(
+            HashMap::from([
+                ("start".into(), "2026-01-12T10:00:00Z".into()),
+                ("norad-id".into(), "25544".into()),
+            ]),
+            vec![
+                waited("mkdir products"),
+                Step {
+                    env: BTreeMap::from([("SATELLITE".into(), "ISS $norad-id".into())]),
+                    cwd: Some("products".into()),
+                    ..waited(
+                        "echo \"$SATOMAT_SCHEDULE_ID $SATOMAT_STEP_INDEX $SATOMAT_START \
+                         $SATOMAT_VAR_NORAD_ID $SATELLITE\" > env; \
+                         test \"$SATOMAT_ARTIFACTS_DIR/products\" = \"$(pwd)\"",
+                    )
+                },
+            ],
+            vec![],
+        );
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let artifact_dir = temp.path().join("pass");
+        let config = RunConfig {
+            artifact_base: artifact_dir.clone(),
+            simulation: None,
+            control: Default::default(),
+        };
+        let outcome = run(task, config).await.expect("run should succeed");
+
+        assert!(!outcome.aborted());
+        let env = fs::read_to_string(artifact_dir.join("products/env")).unwrap();
+        assert_eq!(
+            env.split_whitespace().collect::<Vec<_>>(),
+            ["pass", "1", "2026-01-12T10:00:00Z", "25544", "ISS", "25544"]
+        );
+    }
+
     #[tokio::test]
     async fn cleanup_always_runs_after_abort() {
         init_tracing();
@@ -500,6 +1237,8 @@ mod tests {
         let temp = std::env::temp_dir().join(format!("sat-o-mat-cleanup-{}", std::process::id()));
         let config = RunConfig {
             artifact_base: temp.clone(),
+            simulation: None,
+            control: Default::default(),
         };
         let outcome = run(task, config).await.expect("run should succeed");
         assert!(outcome.aborted());
@@ -528,6 +1267,35 @@ mod tests {
         assert!(elapsed.as_secs() < 10);
     }
 
+    #[tokio::test]
+    async fn stopped_steps_can_clean_up() {
+        let end = Utc::now() + TimeDelta::seconds(1);
+        let task = Task::new(
+            HashMap::from([("end".into(), end.to_rfc3339())]),
+            vec![waited("trap 'touch stopped; exit 0' TERM; sleep 60 & wait")],
+            vec![],
+        );
+
+        init_tracing();
+        let temp = tempfile::tempdir().unwrap();
+        let config = RunConfig {
+            artifact_base: temp.path().to_path_buf(),
+            simulation: None,
+            control: Default::default(),
+        };
+        let start = std::time::Instant::now();
+        let outcome = run(task, config).await.expect("run should succeed");
+        assert!(start.elapsed().as_secs() < 3);
+        assert!(matches!(
+            &outcome.step_outcomes[0],
+            StepOutcome::Abort {
+                reason: AbortReason::ExitSignalReceived,
+                ..
+            }
+        ));
+        assert!(outcome.artifact_dir.join("stopped").exists());
+    }
+
     #[tokio::test]
     async fn shell_variable_resolution() {
         let task = Task::new(
@@ -548,6 +1316,8 @@ mod tests {
         let temp = std::env::temp_dir().join(format!("sat-o-mat-artifact-{}", std::process::id()));
         let config = RunConfig {
             artifact_base: temp.clone(),
+            simulation: None,
+            control: Default::default(),
         };
         let outcome = run(task, config).await.expect("run should succeed");
         assert!(outcome.artifact_dir.exists());
@@ -556,6 +1326,229 @@ mod tests {
         let _ = tokio::fs::remove_dir_all(&temp).await;
     }
 
+    #[tokio::test]
+    async fn execution_log_records_each_step() {
+        init_tracing();
+        let task = make_task(
+            vec![waited_continue("exit 3"), waited("true")],
+            vec![waited("false")],
+        );
+
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let config = RunConfig {
+            artifact_base: temp.path().to_path_buf(),
+            simulation: None,
+            control: Default::default(),
+        };
+        run(task, config).await.expect("run should succeed");
+
+        let log = read_execution_log(temp.path()).unwrap();
+        let results: Vec<_> = log
+            .iter()
+            .map(|e| (e.cmd.as_str(), e.result, e.exit_code))
+            .collect();
+        assert_eq!(
+            results,
+            [
+                ("exit 3", StepResult::Completed, Some(3)),
+                ("true", StepResult::Completed, Some(0)),
+                ("false", StepResult::Aborted, Some(1)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn step_output_is_written_to_logs() {
+        init_tracing();
+        let task = make_task(
+            vec![
+                waited("echo out; echo err >&2"),
+                waited_retry("echo try; false", 2),
+            ],
+            vec![waited("echo cleaning up")],
+        );
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let config = RunConfig {
+            artifact_base: temp.path().to_path_buf(),
+            simulation: None,
+            control: Default::default(),
+        };
+        run(task, config).await.expect("run should succeed");
+
+        let log = |path: &str| fs::read_to_string(temp.path().join(path)).unwrap();
+        assert_eq!(log("steps/0.log"), "out\nerr\n");
+        // Retries append to the log of the step
+        assert_eq!(log("steps/1.log"), "try\ntry\n");
+        assert_eq!(log("cleanup/0.log"), "cleaning up\n");
+    }
+
+    #[tokio::test]
+    async fn controlled_runs_are_paused_resumed_and_aborted() {
+        init_tracing();
+        let task = make_task(
+            vec![
+                waited("touch first"),
+                waited("sleep 60"),
+                waited("touch last"),
+            ],
+            vec![waited("touch cleaned_up")],
+        );
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let control = RunControl::default();
+        control.pause();
+        let config = RunConfig {
+            artifact_base: temp.path().to_path_buf(),
+            simulation: None,
+            control: control.clone(),
+        };
+        let running = spawn(run(task, config));
+
+        // Nothing starts while paused
+        sleep(Duration::from_millis(200)).await;
+        assert!(!temp.path().join("first").exists());
+        assert_eq!(control.status().step, None);
+
+        control.resume();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while control.status().step != Some(1) {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("second step should start");
+        control.abort();
+        let outcome = running.await.unwrap().expect("run should succeed");
+
+        assert!(outcome.aborted());
+        assert!(temp.path().join("first").exists());
+        assert!(!temp.path().join("last").exists());
+        assert!(temp.path().join("cleaned_up").exists());
+    }
+
+    #[tokio::test]
+    async fn step_events_are_published() {
+        init_tracing();
+        let task = make_task(
+            vec![waited("true"), waited_continue("exit 3")],
+            vec![waited("true")],
+        );
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let control = RunControl::default();
+        let mut events = control.subscribe();
+        let config = RunConfig {
+            artifact_base: temp.path().to_path_buf(),
+            simulation: None,
+            control,
+        };
+        run(task, config).await.expect("run should succeed");
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        // Cleanup steps are not published
+        assert_eq!(
+            received,
+            [
+                StepEvent::Started { step: 0 },
+                StepEvent::Completed {
+                    step: 0,
+                    result: StepResult::Completed,
+                    exit_code: Some(0),
+                },
+                StepEvent::Started { step: 1 },
+                StepEvent::Completed {
+                    step: 1,
+                    result: StepResult::Completed,
+                    exit_code: Some(3),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn simulation_plays_through_step_times() {
+        init_tracing();
+        let start = Utc::now() + TimeDelta::hours(1);
+        let at = |minutes| Step {
+            time: Some(TimeSpec::Relative {
+                variable: "start".into(),
+                offset: TimeDelta::minutes(minutes),
+            }),
+            ..waited("touch executed")
+        };
+        let task = Task::new(
+            HashMap::from([
+                ("start".into(), start.to_rfc3339()),
+                ("end".into(), (start + TimeDelta::minutes(60)).to_rfc3339()),
+            ]),
+            vec![at(10), at(30)],
+            vec![],
+        );
+
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let config = RunConfig {
+            artifact_base: temp.path().to_path_buf(),
+            simulation: Some(Simulation { speed: 3600.0 }),
+            control: Default::default(),
+        };
+        let real_start = std::time::Instant::now();
+        let outcome = run(task, config).await.expect("run should succeed");
+
+        assert!(real_start.elapsed().as_secs() < 10);
+        assert!(!outcome.aborted());
+        assert!(!temp.path().join("executed").exists());
+        let log = read_execution_log(temp.path()).unwrap();
+        assert_eq!(log.len(), 2);
+        for (entry, minutes) in log.iter().zip([10, 30]) {
+            let offset = entry.time - (start + TimeDelta::minutes(minutes));
+            assert!(offset.num_seconds().abs() < 60, "{offset}");
+        }
+    }
+
+    #[test]
+    fn plan_resolves_step_times_without_running_them() {
+        let start = DateTime::parse_from_rfc3339("2026-06-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |minutes, cmd| Step {
+            time: Some(TimeSpec::Relative {
+                variable: "start".into(),
+                offset: TimeDelta::minutes(minutes),
+            }),
+            ..waited(cmd)
+        };
+        let task = Task::new(
+            HashMap::from([
+                ("start".into(), start.to_rfc3339()),
+                ("end".into(), (start + TimeDelta::minutes(15)).to_rfc3339()),
+                ("satellite".into(), "NOAA 19".into()),
+            ]),
+            vec![
+                step("record $satellite"),
+                at(5, "touch tuned"),
+                waited("touch right_after"),
+                at(20, "touch too_late"),
+            ],
+            vec![waited("touch cleaned_up")],
+        );
+
+        let plan = plan(&task).unwrap();
+        let times: Vec<_> = plan.steps.iter().map(|s| s.time).collect();
+        assert_eq!(
+            times,
+            [
+                Some(start),
+                Some(start + TimeDelta::minutes(5)),
+                Some(start + TimeDelta::minutes(5)),
+                None,
+            ]
+        );
+        assert_eq!(plan.steps[0].cmd, "record NOAA 19");
+        // The recording runs until the end, the cleanup follows
+        assert_eq!(plan.cleanup[0].time, plan.end);
+    }
+
     #[tokio::test]
     async fn background_step_returns_spawned() {
         let task = make_task(vec![step("sleep 0.1")], vec![]);
@@ -573,6 +1566,9 @@ mod tests {
             time: None,
             wait: false,
             on_fail: OnFail::Abort,
+            timeout: None,
+            env: Default::default(),
+            cwd: None,
         }
     }
 
@@ -582,6 +1578,9 @@ mod tests {
             time: None,
             wait: false,
             on_fail: OnFail::Continue,
+            timeout: None,
+            env: Default::default(),
+            cwd: None,
         }
     }
 
@@ -625,6 +1624,9 @@ mod tests {
                     }),
                     wait: true,
                     on_fail: OnFail::Abort,
+                    timeout: None,
+                    env: Default::default(),
+                    cwd: None,
                 },
             ],
             vec![],
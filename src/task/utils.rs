@@ -5,6 +5,7 @@ use tokio::{io, process::Command};
 use tracing::info;
 
 use crate::task::format::TimeSpec;
+use crate::task::index::{IndexedTask, TaskIndex};
 
 /// Evaluate `${shell cmd}` variable values in-place; leave unchanged on error or plain strings.
 pub async fn resolve_variables(vars: &mut HashMap<String, String>, cwd: &Path) -> io::Result<()> {
@@ -45,7 +46,7 @@ pub fn substitute_variables(cmd: &str, vars: &HashMap<String, String>) -> String
     let mut result = cmd.to_string();
     // Replace longest names first to avoid prefix collisions (e.g. $FOO before $FO)
     let mut entries: Vec<_> = vars.iter().collect();
-    entries.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+    entries.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
     for (name, value) in entries {
         result = result.replace(&format!("${name}"), value);
     }
@@ -66,43 +67,34 @@ pub fn resolve_time(spec: &TimeSpec, vars: &HashMap<String, String>) -> Option<D
     }
 }
 
-/// Check if the given task's time range overlaps with any other active task.
+/// Check if the given task's time range overlaps with any other active task of the same or
+/// higher priority. Those of lower priority do not conflict: the task preempts them.
 /// Returns the conflicting task's ID if a conflict is found.
 pub async fn check_time_conflict(
-    tasks_path: &Path,
+    index: &TaskIndex,
     exclude_id: &str,
     task: &super::Task,
 ) -> Option<String> {
-    let (new_start, new_end) = task
-        .time_range()
-        .expect("task does not have a valid time range");
-    let exclude_filename = super::Task::filename(exclude_id);
-
-    let active_path = tasks_path.join("Active");
-    let mut read_dir = tokio::fs::read_dir(&active_path).await.ok()?;
-
-    while let Ok(Some(entry)) = read_dir.next_entry().await {
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        if file_name == exclude_filename {
-            continue;
-        }
-
-        let Ok(content) = tokio::fs::read_to_string(entry.path()).await else {
-            continue;
-        };
-        let Ok(other_task) = super::Task::from_yaml_str(&content) else {
-            continue;
-        };
-
-        if let Ok((other_start, other_end)) = other_task.time_range() {
-            // Two ranges [s1,e1) and [s2,e2) overlap iff s1 < e2 && s2 < e1
-            if new_start < other_end && other_start < new_end {
-                return Some(super::Task::id_from_filename(&file_name).to_string());
-            }
-        }
-    }
+    let priority = task.priority();
+    find_overlapping(index, exclude_id, task)
+        .await
+        .into_iter()
+        .find(|other| other.task.priority() >= priority)
+        .map(|other| other.id)
+}
 
-    None
+/// Returns the active tasks whose time range overlaps with the given task's.
+pub async fn find_overlapping(
+    index: &TaskIndex,
+    exclude_id: &str,
+    task: &super::Task,
+) -> Vec<IndexedTask> {
+    index
+        .tasks("Active")
+        .await
+        .into_iter()
+        .filter(|other| other.id != exclude_id && task.overlaps(&other.task))
+        .collect()
 }
 
 #[cfg(test)]
@@ -0,0 +1,328 @@
+//! Keeps the TLEs of a station fresh without the server, e.g. from cron.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow, bail};
+use chrono::Utc;
+use clap::{Args, Subcommand};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::info;
+
+use crate::config::{Config, PredictConfig, SpaceTrackConfig};
+use crate::http;
+use crate::predict::PredictDb;
+
+/// Time a download may take, large groups included.
+const TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Args)]
+pub struct TleArgs {
+    #[command(subcommand)]
+    pub command: TleCommand,
+}
+
+#[derive(Subcommand)]
+pub enum TleCommand {
+    /// Downloads the TLEs of a group to `<output>/<group>.txt`, so the satellites are also grouped
+    /// by its name. The file is only replaced if the download contains TLEs.
+    Fetch {
+        /// Name of the group, e.g. `amateur` or `weather`
+        #[arg(long)]
+        group: String,
+        /// Directory to write the TLEs to. Defaults to the configured `tle_path`
+        #[arg(long, value_name = "DIR")]
+        output: Option<PathBuf>,
+        /// Download from this URL instead of the configured `predict.tle_source` or Space-Track
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Prints the elements of a satellite loaded from `tle_path`
+    Show {
+        /// NORAD ID or name of the satellite
+        norad: String,
+    },
+}
+
+/// Runs the TLE command, returning what to print.
+pub async fn run(args: TleArgs, config: &Config) -> anyhow::Result<String> {
+    match args.command {
+        TleCommand::Fetch { group, output, url } => {
+            let output = output.unwrap_or_else(|| config.tle_path.clone());
+            let source = match url {
+                Some(url) => Source::Url(url),
+                None => Source::of(&group, &config.predict),
+            };
+            let count = fetch(&group, &source, &output).await?;
+            Ok(format!("{count} satellites in {group}\n"))
+        }
+        TleCommand::Show { norad } => {
+            let predict_db = crate::api::predict_db(config);
+            let (name, sat) = predict_db.find(&norad).ok_or_else(|| {
+                anyhow!(
+                    "satellite {norad} not found in {}",
+                    config.tle_path.display()
+                )
+            })?;
+            let age = sat.element_age(Utc::now()).num_seconds() as f64 / 86400.0;
+            let mut output = format!(
+                "{name} (NORAD {})\nepoch: {} ({age:.1} days old)\ngroups: {}\n",
+                sat.elements.norad_id,
+                sat.elements.datetime.and_utc().to_rfc3339(),
+                sat.groups.join(", "),
+            );
+            if let Some(source) = &sat.source {
+                output.push_str(&format!("source: {}\n", source.display()));
+            }
+            output.push_str(sat.orbit_text().trim_end());
+            output.push('\n');
+            Ok(output)
+        }
+    }
+}
+
+/// Where the TLEs of a group are downloaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Url(String),
+    /// The latest elements of `norad_ids`, logging in to Space-Track with each request.
+    SpaceTrack {
+        account: SpaceTrackConfig,
+        norad_ids: Vec<u64>,
+    },
+}
+
+impl Source {
+    /// The source of `group`: Space-Track if it is one of the groups of `predict.space_track`,
+    /// `predict.tle_source` otherwise.
+    pub fn of(group: &str, predict: &PredictConfig) -> Self {
+        if let Some(account) = &predict.space_track
+            && let Some(norad_ids) = account.groups.get(group)
+        {
+            return Self::SpaceTrack {
+                account: account.clone(),
+                norad_ids: norad_ids.clone(),
+            };
+        }
+        Self::Url(predict.tle_source.replace("{group}", group))
+    }
+
+    /// The URL the TLEs are downloaded from.
+    pub fn url(&self) -> String {
+        match self {
+            Self::Url(url) => url.clone(),
+            Self::SpaceTrack { account, norad_ids } => {
+                let ids: Vec<String> = norad_ids.iter().map(u64::to_string).collect();
+                format!(
+                    "{}/basicspacedata/query/class/gp/NORAD_CAT_ID/{}/orderby/NORAD_CAT_ID/format/3le",
+                    account.url.trim_end_matches('/'),
+                    ids.join(",")
+                )
+            }
+        }
+    }
+
+    /// Downloads the TLEs with curl, over HTTP or HTTPS.
+    async fn download(&self) -> anyhow::Result<String> {
+        let (url, config) = match self {
+            Self::Url(url) => (url.clone(), http::curl_option("url", url)),
+            Self::SpaceTrack { account, norad_ids } => {
+                if norad_ids.is_empty() {
+                    bail!("no satellites to download from Space-Track");
+                }
+                // The query is answered by the login itself, so no session has to be kept
+                let login = format!("{}/ajaxauth/login", account.url.trim_end_matches('/'));
+                let fields = [
+                    format!("identity={}", account.username),
+                    format!("password={}", account.password),
+                    format!("query={}", self.url()),
+                ];
+                let mut config = http::curl_option("url", &login);
+                for field in &fields {
+                    config.push_str(&http::curl_option("data-urlencode", field));
+                }
+                (login, config)
+            }
+        };
+
+        // The configuration goes through stdin, keeping the password out of the process list
+        let mut curl = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location"])
+            .arg("--max-redirs")
+            .arg(MAX_REDIRECTS.to_string())
+            .arg("--max-time")
+            .arg(TIMEOUT.as_secs().to_string())
+            .args(["--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run curl")?;
+        let mut stdin = curl.stdin.take().unwrap();
+        stdin.write_all(config.as_bytes()).await?;
+        drop(stdin);
+        let output = curl.wait_with_output().await?;
+        if !output.status.success() {
+            bail!(
+                "request to {url} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Downloads the TLEs of `group` from `source` to `<output>/<group>.txt`, returning how many
+/// satellites were found.
+pub async fn fetch(group: &str, source: &Source, output: &Path) -> anyhow::Result<usize> {
+    if group.is_empty() || group.contains(['/', '\\']) || group.starts_with('.') {
+        bail!("invalid group name {group}");
+    }
+    let url = source.url();
+    info!(%group, %url, "fetching TLEs");
+    let text = source.download().await?;
+    let count = PredictDb::new().add(&text);
+    if count == 0 {
+        bail!("no TLEs found at {url}, keeping the current ones");
+    }
+
+    // Replace the file at once, so the server never loads a partial download
+    fs::create_dir_all(output)?;
+    let path = output.join(format!("{group}.txt"));
+    let partial = output.join(format!(".{group}.txt.partial"));
+    fs::write(&partial, text)?;
+    fs::rename(&partial, &path)?;
+    info!(%group, count, ?path, "TLEs updated");
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use axum::routing::{get, post};
+    use axum::{Form, Router};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const TLE: &str = "\
+NanoFF A
+1 58810U 23185T   26014.62310634  .00002094  00000-0  12909-3 0  9998
+2 58810  97.5476  84.2186 0011747 113.9165 246.3297 15.10986736109848
+";
+
+    async fn serve(body: &'static str) -> String {
+        let router = Router::new().route("/gp", get(move || async move { body }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/gp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn fetched_tles_can_be_shown() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            tle_path: tmp.path().join("tle"),
+            tasks_path: tmp.path().to_path_buf(),
+            ..Default::default()
+        };
+        let fetch = TleCommand::Fetch {
+            group: "stations".into(),
+            output: None,
+            url: Some(serve(TLE).await),
+        };
+        let output = run(TleArgs { command: fetch }, &config).await.unwrap();
+        assert_eq!(output, "1 satellites in stations\n");
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("tle/stations.txt")).unwrap(),
+            TLE
+        );
+
+        let show = TleCommand::Show {
+            norad: "58810".into(),
+        };
+        let output = run(TleArgs { command: show }, &config).await.unwrap();
+        assert!(output.starts_with("NanoFF A (NORAD 58810)\nepoch: 2026-01-14T14:57:"));
+        assert!(output.contains("groups: stations\n"));
+        assert!(output.ends_with("15.10986736109848\n"));
+    }
+
+    #[tokio::test]
+    async fn failed_downloads_keep_the_current_tles() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("stations.txt"), TLE).unwrap();
+        let url = serve("<html>rate limited</html>").await;
+        let error = fetch("stations", &Source::Url(url), tmp.path())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("no TLEs found"));
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("stations.txt")).unwrap(),
+            TLE
+        );
+
+        let url = serve(TLE).await.replace("/gp", "/missing");
+        let error = fetch("stations", &Source::Url(url.clone()), tmp.path())
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with(&format!("request to {url} failed: curl: (22)")),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn space_track_groups_are_downloaded_with_the_account() {
+        let login = |Form(form): Form<HashMap<String, String>>| async move {
+            let query = "/basicspacedata/query/class/gp/NORAD_CAT_ID/58810,25544/orderby/NORAD_CAT_ID/format/3le";
+            if form["identity"] == "station@example.com"
+                && form["password"] == "p&ss word"
+                && form["query"].ends_with(query)
+            {
+                format!("0 {TLE}")
+            } else {
+                r#"{"Login":"Failed"}"#.to_string()
+            }
+        };
+        let router = Router::new().route("/ajaxauth/login", post(login));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let mut account = SpaceTrackConfig {
+            username: "station@example.com".into(),
+            password: "p&ss word".into(),
+            groups: BTreeMap::from([("mission".into(), vec![58810, 25544])]),
+            url,
+        };
+        let predict = PredictConfig {
+            space_track: Some(account.clone()),
+            ..Default::default()
+        };
+        assert!(matches!(Source::of("amateur", &predict), Source::Url(_)));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let source = Source::of("mission", &predict);
+        assert_eq!(fetch("mission", &source, tmp.path()).await.unwrap(), 1);
+        let mut db = PredictDb::new();
+        db.add_tles(&tmp.path().to_path_buf()).unwrap();
+        assert_eq!(db.get("NanoFF A").unwrap().groups, ["mission"]);
+
+        account.password = "wrong".into();
+        let source = Source::SpaceTrack {
+            account,
+            norad_ids: vec![58810, 25544],
+        };
+        let error = fetch("mission", &source, tmp.path()).await.unwrap_err();
+        assert!(error.to_string().starts_with("no TLEs found"));
+    }
+}
@@ -0,0 +1,175 @@
+//! HTTPS for the web server, terminated with rustls.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::serve::Listener;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::server::TlsStream;
+use tracing::debug;
+
+use crate::config::TlsConfig;
+
+/// Time a client has to complete the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections handshaken but not accepted by the server yet.
+const BACKLOG: usize = 64;
+
+/// Loads the certificate chain and the key of `config`.
+pub fn acceptor(config: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read {}", config.cert_path.display()))?;
+    let key = PrivateKeyDer::from_pem_file(&config.key_path)
+        .with_context(|| format!("failed to read {}", config.key_path.display()))?;
+    let mut server = ServerConfig::builder_with_provider(Arc::new(
+        tokio_rustls::rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .context("invalid certificate or key")?;
+    server.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server)))
+}
+
+/// Accepts TLS connections on a TCP listener. The handshakes run in tasks of their own, so that
+/// slow or failing clients do not hold up the others.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    pub fn new(mut listener: TcpListener, acceptor: TlsAcceptor) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, connections) = mpsc::channel(BACKLOG);
+        tokio::spawn(async move {
+            // Stops with the first connection after the server stopped accepting them
+            while !tx.is_closed() {
+                // Retries on errors, as the server does for plain HTTP
+                let (stream, address) = Listener::accept(&mut listener).await;
+                let (acceptor, tx) = (acceptor.clone(), tx.clone());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, address)).await;
+                        }
+                        Ok(Err(e)) => debug!(%address, %e, "TLS handshake failed"),
+                        Err(_) => debug!(%address, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            local_addr,
+            connections,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accepting task only stops once this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::extract::ConnectInfo;
+    use axum::routing::get;
+    use axum::serve::ListenerExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsConnector;
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_https_with_the_configured_certificate() {
+        let tmp = tempfile::tempdir().unwrap();
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let config = TlsConfig {
+            cert_path: tmp.path().join("cert.pem"),
+            key_path: tmp.path().join("key.pem"),
+        };
+        std::fs::write(&config.cert_path, cert.pem()).unwrap();
+        std::fs::write(&config.key_path, key_pair.serialize_pem()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let listener = TlsListener::new(listener, acceptor(&config).unwrap())
+            .unwrap()
+            .tap_io(|_| {});
+        let router =
+            Router::new().route(
+                "/",
+                get(|ConnectInfo(client): ConnectInfo<SocketAddr>| async move {
+                    client.ip().to_string()
+                }),
+            );
+        tokio::spawn(async move {
+            let service = router.into_make_service_with_connect_info::<SocketAddr>();
+            axum::serve(listener, service).await
+        });
+
+        // A client failing the handshake does not stop the others
+        let mut failing = TcpStream::connect(address).await.unwrap();
+        failing.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.der().clone()).unwrap();
+        let client = ClientConfig::builder_with_provider(Arc::new(
+            tokio_rustls::rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        let stream = TcpStream::connect(address).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(client))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with("\r\n\r\n127.0.0.1"), "{response}");
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        let config = TlsConfig {
+            cert_path: "/nonexistent/cert.pem".into(),
+            key_path: "/nonexistent/key.pem".into(),
+        };
+        let error = acceptor(&config).err().unwrap();
+        assert_eq!(error.to_string(), "failed to read /nonexistent/cert.pem");
+    }
+}
@@ -0,0 +1,505 @@
+//! The `tracker` command, also known as `track`, driving the outputs of [`crate::tracker`] with
+//! the observables of the tracked object.
+
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, anyhow, bail};
+use chrono::{DateTime, Utc};
+use clap::Args;
+use lox_space::prelude::Spacecraft;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{broadcast, watch},
+    time::sleep,
+};
+use tracing::{info, warn};
+
+use crate::{
+    config::Config,
+    predict::PredictDb,
+    server::shutdown_signal,
+    tracker::{
+        Frequency, Output, Update, announce, radio,
+        rotator::{self, Rotator},
+        update_at,
+    },
+};
+
+/// How long outputs have to stop (e.g. park the rotator) on shutdown.
+const OUTPUT_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// File the `tracker` command keeps its latest [`TrackerStatus`] in, in the artifacts of the run
+/// it is a step of, for the metrics of the server.
+pub const STATUS_FILE: &str = "tracker.json";
+
+/// The latest update of a tracker and how well the rotators follow it.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TrackerStatus {
+    pub object: String,
+    pub time: DateTime<Utc>,
+    pub azimuth: f64,
+    pub elevation: f64,
+    /// Range rate (m/s)
+    pub range_rate: f64,
+    /// Doppler shift (Hz) of the uplink frequency
+    pub tx_doppler: Option<i64>,
+    /// Doppler shift (Hz) of the downlink frequency
+    pub rx_doppler: Option<i64>,
+    /// Pointing error (degrees) of each rotator, by the name it was given as
+    pub rotator_errors: BTreeMap<String, f64>,
+}
+
+#[derive(Args)]
+pub struct TrackerArgs {
+    /// File with the orbit information of the object, in any of the supported formats
+    /// ({3,T}LE, CCSDS OMM). Read from stdin if unset
+    #[arg(long, value_name = "FILE")]
+    pub tle: Option<PathBuf>,
+    /// NORAD ID or name of the object to track, if the orbit information describes more than one
+    #[arg(long)]
+    pub norad: Option<String>,
+    #[arg(long, name = "tx")]
+    pub tx_freq: Option<Frequency>,
+    #[arg(long, name = "rx")]
+    pub rx_freq: Option<Frequency>,
+    #[arg(short, default_value = "1.0")]
+    pub update_rate: f32,
+    /// Where to send the updates, e.g. `rotctl=uhf1` to steer the rotator resource `uhf1`,
+    /// `rigctl=host:port` to tune the radio at that `rigctld` server or `udp=host:port` to
+    /// announce the Doppler corrected frequencies there as JSON
+    #[arg(short, long)]
+    pub out: Vec<Output>,
+    /// Point this rotator at the object, as `--out rotctl=ROTATOR`
+    #[arg(long)]
+    pub rotator: Option<String>,
+    /// Tune this radio to the Doppler corrected frequencies, as `--out rigctl=RADIO`
+    #[arg(long)]
+    pub radio: Option<String>,
+    /// Time (seconds) between the frequencies announced to `udp` outputs
+    #[arg(long, default_value = "1.0", value_name = "SECONDS")]
+    pub announce_interval: f32,
+    /// Stop tracking at this time (RFC3339), or after this long (e.g. `15m`), instead of when
+    /// interrupted
+    #[arg(long, value_parser = parse_until)]
+    pub until: Option<DateTime<Utc>>,
+    /// Track from this hosted station instead of the main one
+    #[arg(long)]
+    pub station: Option<String>,
+}
+
+/// What to track and where to send the updates.
+struct Session<'a> {
+    name: &'a str,
+    spacecraft: &'a Spacecraft,
+    tx_freq: Option<Frequency>,
+    rx_freq: Option<Frequency>,
+    update_rate: f32,
+    /// Rotators, by the name they were given as
+    rotators: Vec<(String, Rotator)>,
+    /// Addresses of the `rigctld` servers of the radios
+    radios: Vec<String>,
+    /// Addresses the frequencies are announced to, and how often
+    announcements: Vec<String>,
+    announce_interval: Duration,
+    outputs: Vec<Output>,
+    /// Print each update to the terminal
+    print: bool,
+    /// Keep the [`TrackerStatus`] in this file
+    status: Option<PathBuf>,
+    /// Stop at this time
+    until: Option<DateTime<Utc>>,
+}
+
+/// Tracks the object in `args.tle`, or on stdin, until stopped or `args.until`.
+pub async fn run(args: TrackerArgs, config: &Config) -> anyhow::Result<()> {
+    let (source, orbit_info) = match &args.tle {
+        Some(path) => (
+            path.display().to_string(),
+            std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+        ),
+        None => {
+            info!("waiting for orbit info from stdin");
+            let mut orbit_info = String::new();
+            std::io::stdin()
+                .read_to_string(&mut orbit_info)
+                .context("failed to read stdin")?;
+            ("stdin".to_string(), orbit_info)
+        }
+    };
+    let mut pdb = PredictDb::new();
+    let count = pdb.add(&orbit_info);
+    let (name, sat) = match (&args.norad, count) {
+        (_, 0) => bail!("no orbit information found in {source}"),
+        (Some(norad), _) => pdb
+            .find(norad)
+            .ok_or_else(|| anyhow!("object {norad} not found in {source}"))?,
+        (None, 1) => {
+            let (name, _) = pdb.first().expect("one object is loaded");
+            pdb.find(name).expect("the first object can be found")
+        }
+        (None, _) => bail!("{source} describes {count} objects, select one with --norad"),
+    };
+    info!(object = %name, "loaded orbit");
+
+    let announce_interval = Duration::try_from_secs_f32(args.announce_interval)
+        .ok()
+        .filter(|interval| !interval.is_zero())
+        .ok_or_else(|| anyhow!("the announce interval must be positive"))?;
+    let (mut rotators, mut radios, mut announcements, mut outputs) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let shorthands = args.rotator.map(Output::Rotctl).into_iter();
+    let shorthands = shorthands.chain(args.radio.map(Output::Rigctl));
+    for out in args.out.into_iter().chain(shorthands) {
+        match out {
+            Output::Rotctl(rotator) => {
+                let resolved = resolve_rotator(&rotator, config)?;
+                rotators.push((rotator, resolved));
+            }
+            Output::Rigctl(radio) => radios.push(resource_address(&radio, config)?),
+            Output::Udp(address) => announcements.push(address),
+            out => outputs.push(out),
+        }
+    }
+    // Set when running as a step
+    let status =
+        std::env::var_os("SATOMAT_ARTIFACTS_DIR").map(|dir| PathBuf::from(dir).join(STATUS_FILE));
+    let session = Session {
+        name,
+        spacecraft: &sat.spacecraft,
+        tx_freq: args.tx_freq,
+        rx_freq: args.rx_freq,
+        update_rate: args.update_rate,
+        rotators,
+        radios,
+        announcements,
+        announce_interval,
+        outputs,
+        // The log of a step gets the updates already
+        print: status.is_none(),
+        status,
+        until: args.until,
+    };
+    track_session(session, &pdb, config).await
+}
+
+/// The `rotctld` server controlling `rotator`, a configured resource or an address, and the
+/// travel and park position of the rotator.
+pub(crate) fn resolve_rotator(rotator: &str, config: &Config) -> anyhow::Result<Rotator> {
+    let resource = config.resources.iter().find(|r| r.name == rotator);
+    Ok(Rotator {
+        address: resource_address(rotator, config)?,
+        limits: resource.and_then(|r| r.limits).unwrap_or_default(),
+        park: resource.and_then(|r| r.park),
+    })
+}
+
+/// Address of the server controlling `resource`, a configured resource or an address.
+fn resource_address(resource: &str, config: &Config) -> anyhow::Result<String> {
+    match config.resources.iter().find(|r| r.name == resource) {
+        Some(r) => r
+            .address
+            .clone()
+            .ok_or_else(|| anyhow!("resource {resource} has no address")),
+        None if resource.contains(':') => Ok(resource.to_string()),
+        None => bail!("unknown resource {resource}, expected a resource name or host:port"),
+    }
+}
+
+async fn track_session(
+    session: Session<'_>,
+    pdb: &PredictDb,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let gs = config
+        .ground_station
+        .as_ref()
+        .ok_or_else(|| anyhow!("ground station not configured"))?;
+    let (exit_tx, mut exit_rx) = broadcast::channel(1);
+    let (update_tx, _) = broadcast::channel(1);
+
+    let mut outputs = Vec::new();
+    let mut rotator_errors = Vec::new();
+    for (name, rotator) in session.rotators {
+        let (error_tx, error_rx) = watch::channel(None);
+        outputs.push(tokio::spawn(rotator::run(
+            rotator,
+            update_tx.subscribe(),
+            error_tx,
+        )));
+        rotator_errors.push((name, error_rx));
+    }
+    for address in session.radios {
+        outputs.push(tokio::spawn(radio::run(address, update_tx.subscribe())));
+    }
+    for address in session.announcements {
+        outputs.push(tokio::spawn(announce::run(
+            session.name.to_string(),
+            address,
+            session.announce_interval,
+            update_tx.subscribe(),
+        )));
+    }
+    for out in session.outputs.into_iter() {
+        match out {
+            // Resolved into the rotators, radios and announcements of the session
+            Output::Rotctl(_) | Output::Rigctl(_) | Output::Udp(_) => {}
+            Output::File(dest) | Output::Zenoh(dest) => {
+                warn!(%dest, "tracker output not supported yet, ignoring");
+            }
+        }
+    }
+
+    let (name, sc) = (session.name, session.spacecraft);
+
+    let terminal = session.print && std::io::stdout().is_terminal();
+    let mut status_file = session.status;
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = exit_rx.recv() => {
+                info!("exit received, stopping");
+                break;
+            }
+
+            _ = &mut shutdown => {
+                info!("shutdown requested, stopping");
+                break;
+            }
+
+            _ = sleep(Duration::from_secs_f32(session.update_rate)) => {
+                // Sleep completed
+            }
+        }
+        if session.until.is_some_and(|until| Utc::now() >= until) {
+            info!("end of tracking reached, stopping");
+            break;
+        }
+
+        // Compute observables at the current time for the GS, and send them as tracker update
+        let update = match update_at(pdb, Utc::now(), sc, gs, session.tx_freq, session.rx_freq) {
+            Ok(update) => update,
+            Err(e) => {
+                warn!(?name, %e, "failed to compute the observables, skipping this update");
+                continue;
+            }
+        };
+        info!(
+            ?name,
+            timestamp = %update.timestamp,
+            range = update.range_meters,
+            range_rate = update.range_rate_meters_per_second,
+            tx = ?update.tx_frequency_hertz,
+            rx = ?update.rx_frequency_hertz,
+            "az={:.2} el={:.2}",
+            update.azimuth_degrees,
+            update.elevation_degrees
+        );
+        if terminal {
+            // Redraw the same line
+            print!("\r{}\x1b[K", status_line(name, &update));
+            let _ = std::io::stdout().flush();
+        } else if session.print {
+            println!("{}", status_line(name, &update));
+        }
+        if let Some(path) = &status_file {
+            let status = TrackerStatus {
+                object: name.to_string(),
+                time: update.timestamp,
+                azimuth: update.azimuth_degrees,
+                elevation: update.elevation_degrees,
+                range_rate: update.range_rate_meters_per_second,
+                tx_doppler: doppler(update.tx_frequency_hertz, session.tx_freq),
+                rx_doppler: doppler(update.rx_frequency_hertz, session.rx_freq),
+                rotator_errors: rotator_errors
+                    .iter()
+                    .filter_map(|(name, error)| Some((name.clone(), (*error.borrow())?)))
+                    .collect(),
+            };
+            if let Err(e) = write_status(path, &status) {
+                warn!(path = %path.display(), ?e, "failed to write tracker status, not trying again");
+                status_file = None;
+            }
+        }
+        let _ = update_tx.send(update);
+    }
+    if terminal {
+        println!();
+    }
+
+    let _ = exit_tx.send(());
+
+    // Closing the updates channel stops the outputs, which park the rotators
+    drop(update_tx);
+    for output in outputs {
+        if tokio::time::timeout(OUTPUT_STOP_TIMEOUT, output)
+            .await
+            .is_err()
+        {
+            warn!("tracker output did not stop in time");
+        }
+    }
+    Ok(())
+}
+
+/// Shift (Hz) of the `corrected` frequency from the `base` one.
+fn doppler(corrected: Option<u64>, base: Option<Frequency>) -> Option<i64> {
+    let (corrected, Frequency(base)) = corrected.zip(base)?;
+    Some(corrected as i64 - base as i64)
+}
+
+/// Replaces the status in `path`, so that readers never see part of it.
+fn write_status(path: &Path, status: &TrackerStatus) -> anyhow::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(status)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Formats the observables in `update` for the terminal.
+fn status_line(name: &str, update: &Update) -> String {
+    let mut line = format!(
+        "{} {name}  az {:6.2}  el {:6.2}  range {:8.1} km  range rate {:+6.3} km/s",
+        update.timestamp.format("%H:%M:%S"),
+        update.azimuth_degrees,
+        update.elevation_degrees,
+        update.range_meters / 1000.0,
+        update.range_rate_meters_per_second / 1000.0,
+    );
+    for (label, frequency) in [
+        ("rx", update.rx_frequency_hertz),
+        ("tx", update.tx_frequency_hertz),
+    ] {
+        if let Some(hertz) = frequency {
+            line.push_str(&format!("  {label} {:.6} MHz", hertz as f64 / 1e6));
+        }
+    }
+    line
+}
+
+/// An RFC3339 time, or a duration from now such as `15m`.
+fn parse_until(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    humantime::parse_duration(s)
+        .ok()
+        .and_then(|duration| chrono::Duration::from_std(duration).ok())
+        .map(|duration| Utc::now() + duration)
+        .ok_or_else(|| format!("expected an RFC3339 time or a duration, got {s}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn status_line_shows_observables() {
+        let update = Update {
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            azimuth_degrees: 123.456,
+            elevation_degrees: 7.891,
+            range_meters: 1_234_567.0,
+            range_rate_meters_per_second: -2_345.6,
+            tx_frequency_hertz: None,
+            rx_frequency_hertz: Some(437_009_876),
+        };
+        assert_eq!(
+            status_line("ISS", &update),
+            "03:04:05 ISS  az 123.46  el   7.89  range   1234.6 km  range rate -2.346 km/s  rx 437.009876 MHz"
+        );
+    }
+
+    #[test]
+    fn until_is_a_time_or_a_duration() {
+        assert_eq!(
+            parse_until("2026-01-02T03:04:05+01:00"),
+            Ok(Utc.with_ymd_and_hms(2026, 1, 2, 2, 4, 5).unwrap())
+        );
+        let until = parse_until("15m").unwrap() - Utc::now();
+        assert!(until > chrono::Duration::minutes(14) && until <= chrono::Duration::minutes(15));
+        assert!(parse_until("soon").is_err());
+    }
+
+    #[tokio::test]
+    async fn objects_are_selected_with_norad() {
+        let tle = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/tle/nanoff.txt");
+        let args = |norad: Option<&str>| TrackerArgs {
+            tle: Some(tle.clone()),
+            norad: norad.map(Into::into),
+            tx_freq: None,
+            rx_freq: None,
+            update_rate: 1.0,
+            out: Vec::new(),
+            rotator: None,
+            radio: None,
+            announce_interval: 1.0,
+            until: None,
+            station: None,
+        };
+        let config = Config {
+            ground_station: None,
+            ..Default::default()
+        };
+        let error = run(args(None), &config).await.unwrap_err().to_string();
+        assert!(
+            error.ends_with("describes 5 objects, select one with --norad"),
+            "{error}"
+        );
+        let error = run(args(Some("1")), &config).await.unwrap_err().to_string();
+        assert!(error.starts_with("object 1 not found in"), "{error}");
+        // Selected, but there is nowhere to track it from
+        let error = run(args(Some("58810")), &config).await.unwrap_err();
+        assert_eq!(error.to_string(), "ground station not configured");
+    }
+
+    #[test]
+    fn rotators_are_resolved() {
+        use sat_o_mat::tracker::rotator::{ParkPosition, RotatorLimits};
+
+        let limits = RotatorLimits {
+            min_azimuth: -180.0,
+            max_azimuth: 180.0,
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        config.resources = vec![crate::config::ResourceConfig {
+            name: "uhf1".into(),
+            commands: vec!["rotctl".into()],
+            transmit: false,
+            address: Some("10.0.0.5:4533".into()),
+            limits: Some(limits),
+            park: Some(ParkPosition {
+                azimuth: 0.0,
+                elevation: 90.0,
+            }),
+            sdr: None,
+        }];
+        assert_eq!(
+            resolve_rotator("uhf1", &config).unwrap(),
+            Rotator {
+                address: "10.0.0.5:4533".into(),
+                limits,
+                park: Some(ParkPosition {
+                    azimuth: 0.0,
+                    elevation: 90.0,
+                }),
+            }
+        );
+        assert_eq!(
+            resolve_rotator("localhost:4533", &config).unwrap(),
+            Rotator {
+                address: "localhost:4533".into(),
+                limits: RotatorLimits::default(),
+                park: None,
+            }
+        );
+        assert!(resolve_rotator("vhf", &config).is_err());
+    }
+}
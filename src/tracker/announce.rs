@@ -0,0 +1,143 @@
+//! Announces the Doppler corrected frequencies of the tracked object over UDP, so that SDR
+//! software can follow the Doppler shift without being controlled through `rigctld`.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::net::{UdpSocket, lookup_host};
+use tokio::sync::broadcast;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+use crate::tracker::update::Update;
+
+/// The frequencies of the tracked object, sent as a JSON datagram.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Announcement {
+    pub object: String,
+    pub time: DateTime<Utc>,
+    /// Doppler corrected uplink frequency (Hz)
+    pub tx_frequency: Option<u64>,
+    /// Doppler corrected downlink frequency (Hz)
+    pub rx_frequency: Option<u64>,
+    /// Range rate (m/s)
+    pub range_rate: f64,
+}
+
+/// Sends the frequencies of the latest of the `updates` of `object` to `addr` every `interval`,
+/// until the channel is closed.
+pub async fn run(
+    object: String,
+    addr: String,
+    interval: Duration,
+    mut updates: broadcast::Receiver<Update>,
+) {
+    let socket = match connect(&addr).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            error!(%addr, ?e, "failed to open UDP socket for frequency announcements");
+            return;
+        }
+    };
+    info!(%addr, "announcing frequencies");
+
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut latest = None;
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(update) => latest = Some(update),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!(%addr, "announcement task lagging, skipped {n} updates");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ticks.tick() => {
+                let Some(update) = &latest else {
+                    continue;
+                };
+                let announcement = Announcement {
+                    object: object.clone(),
+                    time: update.timestamp,
+                    tx_frequency: update.tx_frequency_hertz,
+                    rx_frequency: update.rx_frequency_hertz,
+                    range_rate: update.range_rate_meters_per_second,
+                };
+                let datagram = serde_json::to_vec(&announcement).expect("announcement serializes");
+                // Nobody may be listening yet, keep announcing
+                if let Err(e) = socket.send(&datagram).await {
+                    warn!(%addr, ?e, "failed to announce frequencies");
+                }
+            }
+        }
+    }
+}
+
+/// A UDP socket sending to `addr` (`host:port`).
+async fn connect(addr: &str) -> std::io::Result<UdpSocket> {
+    let target = lookup_host(addr)
+        .await?
+        .next()
+        .ok_or_else(|| std::io::Error::other(format!("{addr} did not resolve")))?;
+    let local = if target.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(target).await?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn frequencies_are_announced_as_json() {
+        let listener = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let (update_tx, update_rx) = broadcast::channel(4);
+        let running = tokio::spawn(run(
+            "ISS".into(),
+            address,
+            Duration::from_millis(10),
+            update_rx,
+        ));
+        let timestamp = Utc::now();
+        update_tx
+            .send(Update {
+                timestamp,
+                azimuth_degrees: 0.0,
+                elevation_degrees: 10.0,
+                range_meters: 1_000_000.0,
+                range_rate_meters_per_second: -2000.0,
+                tx_frequency_hertz: Some(145_990_001),
+                rx_frequency_hertz: Some(437_002_915),
+            })
+            .unwrap();
+
+        let mut datagram = [0; 512];
+        let len = tokio::time::timeout(Duration::from_secs(5), listener.recv(&mut datagram))
+            .await
+            .unwrap()
+            .unwrap();
+        let announcement: Announcement = serde_json::from_slice(&datagram[..len]).unwrap();
+        assert_eq!(
+            announcement,
+            Announcement {
+                object: "ISS".into(),
+                time: timestamp,
+                tx_frequency: Some(145_990_001),
+                rx_frequency: Some(437_002_915),
+                range_rate: -2000.0,
+            }
+        );
+
+        drop(update_tx);
+        running.await.unwrap();
+    }
+}
@@ -1,110 +1,50 @@
-use std::time::Duration;
+//! Tracking of an object from a ground station: the observables and Doppler corrected
+//! frequencies at a given time, and the outputs they are sent to: rotators through `rotctld`,
+//! radios through `rigctld` and SDR software through UDP announcements.
 
-use chrono::Utc;
-use clap::Args;
-use lox_space::{frames::providers::DefaultRotationProvider, units::SPEED_OF_LIGHT};
-use tokio::{sync::broadcast, time::sleep};
-use tracing::info;
+use chrono::{DateTime, Utc};
+use lox_space::prelude::{GroundStation, Spacecraft};
+use lox_space::units::SPEED_OF_LIGHT;
 
-use crate::{
-    config::Config,
-    predict::PredictDb,
-    tracker::{
-        update::Update,
-        utils::{Frequency, Output},
-    },
-};
+use crate::predict::{self, PredictDb};
 
-mod rotctl;
+pub mod announce;
+pub mod radio;
+pub mod rigctl;
+pub mod rotator;
+pub mod rotctl;
 mod update;
 mod utils;
 
-#[derive(Args)]
-pub struct TrackerArgs {
-    #[arg(long, name = "tx")]
-    pub tx_freq: Option<Frequency>,
-    #[arg(long, name = "rx")]
-    pub rx_freq: Option<Frequency>,
-    #[arg(short, default_value = "1.0")]
-    pub update_rate: f32,
-    #[arg(short, long)]
-    pub out: Vec<Output>,
-}
-
-/// Runs the tracker loop until stopped.
-pub async fn run(args: TrackerArgs, pdb: &PredictDb, config: &Config) {
-    let (exit_tx, mut exit_rx) = broadcast::channel(1);
-    let (update_tx, _) = broadcast::channel(1);
-
-    for out in args.out.into_iter() {
-        match out {
-            Output::Rotctl(addr) => {
-                tokio::spawn(rotctl::run(addr, update_tx.subscribe()));
-            }
-            _ => todo!(),
-        }
-    }
-
-    // Get the spacecraft we are tracking
-    // and the GS
-    let (name, sc) = pdb
-        .first()
-        .expect("no object loaded for tracking, this should not be possible");
-    let gs = config
-        .ground_station
-        .clone()
-        .expect("ground station not configured");
-
-    loop {
-        tokio::select! {
-            _ = exit_rx.recv() => {
-                info!("exit received, stopping");
-                break;
-            }
-
-            _ = sleep(Duration::from_secs_f32(args.update_rate)) => {
-                // Sleep completed
-            }
-        }
-
-        // Compute observables at the current time for the GS
-        let now = Utc::now();
-        let state = pdb.state_at(now, sc).unwrap();
-        let state_body_frame = state
-            .try_to_frame(gs.body_fixed_frame(), &DefaultRotationProvider)
-            .unwrap();
-        let observables = gs.location().observables_dyn(state_body_frame);
+pub use update::Update;
+pub use utils::{Frequency, Output};
 
-        // Compute Doppler corrected frequencies if present
-        let range_rate = observables.range_rate();
-        let tx_frequency_hertz = doppler_correct(args.tx_freq, range_rate, true);
-        let rx_frequency_hertz = doppler_correct(args.rx_freq, range_rate, false);
-
-        // Create and send tracker update
-        let update = Update {
-            timestamp: now,
-            azimuth_degrees: observables.azimuth().to_degrees(),
-            elevation_degrees: observables.elevation().to_degrees(),
-            range_meters: observables.range(),
-            range_rate_meters_per_second: observables.range_rate(),
-            tx_frequency_hertz,
-            rx_frequency_hertz,
-        };
-        info!(
-            ?name,
-            ?range_rate,
-            range = ?observables.range(),
-            "az={:.2} el={:.2}",
-            update.azimuth_degrees,
-            update.elevation_degrees
-        );
-        let _ = update_tx.send(update);
-    }
-
-    let _ = exit_tx.send(());
+/// The observables of `spacecraft` from `ground_station` at `time`, with the `tx` (uplink) and
+/// `rx` (downlink) frequencies corrected for the Doppler shift.
+pub fn update_at(
+    pdb: &PredictDb,
+    time: DateTime<Utc>,
+    spacecraft: &Spacecraft,
+    ground_station: &GroundStation,
+    tx: Option<Frequency>,
+    rx: Option<Frequency>,
+) -> Result<Update, predict::Error> {
+    let observables = pdb.observables_at(time, spacecraft, ground_station)?;
+    let range_rate = observables.range_rate();
+    Ok(Update {
+        timestamp: time,
+        azimuth_degrees: observables.azimuth().to_degrees(),
+        elevation_degrees: observables.elevation().to_degrees(),
+        range_meters: observables.range(),
+        range_rate_meters_per_second: range_rate,
+        tx_frequency_hertz: doppler_correct(tx, range_rate, true),
+        rx_frequency_hertz: doppler_correct(rx, range_rate, false),
+    })
 }
 
-fn doppler_correct(
+/// The frequency to use for `base_freq` given the range rate of the object: uplinks are shifted
+/// so that they arrive at `base_freq`, downlinks to where `base_freq` is received.
+pub fn doppler_correct(
     base_freq: Option<Frequency>,
     range_rate_meters_per_second: f64,
     is_uplink: bool,
@@ -116,3 +56,19 @@ fn doppler_correct(
         freq.saturating_add_signed(f_shift as i64)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doppler_shift_follows_the_range_rate() {
+        let base = Some(Frequency(437_000_000));
+        // Approaching: received above the carrier, sent below it
+        let rx = doppler_correct(base, -5_000.0, false).unwrap();
+        let tx = doppler_correct(base, -5_000.0, true).unwrap();
+        assert!(rx > 437_000_000 && tx < 437_000_000);
+        assert_eq!(rx - 437_000_000, 437_000_000 - tx);
+        assert_eq!(doppler_correct(None, -5_000.0, false), None);
+    }
+}
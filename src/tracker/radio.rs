@@ -0,0 +1,115 @@
+//! Keeps a radio tuned to the Doppler corrected frequencies of the tracked object through its
+//! `rigctld` server.
+
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::tracker::rigctl::RigctlClient;
+use crate::tracker::update::Update;
+
+/// Tunes the radio at the `rigctld` server `addr` to the frequencies of each of the `updates`
+/// until the channel is closed: the downlink on the current VFO and the uplink on the transmit
+/// VFO of split operation. Frequencies are only sent when they change.
+pub async fn run(addr: String, mut updates: broadcast::Receiver<Update>) {
+    let mut client = match RigctlClient::connect(&addr).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!(%addr, ?e, "failed to connect to rigctld");
+            return;
+        }
+    };
+    match client.get_frequency().await {
+        Ok(hertz) => info!(%addr, hertz, "connected to rigctld"),
+        Err(e) => warn!(%addr, ?e, "connected to rigctld, but failed to read frequency"),
+    }
+
+    let (mut rx, mut tx) = (None, None);
+    loop {
+        match updates.recv().await {
+            Ok(update) => {
+                let result = async {
+                    if update.rx_frequency_hertz != rx
+                        && let Some(hertz) = update.rx_frequency_hertz
+                    {
+                        client.set_frequency(hertz).await?;
+                        rx = Some(hertz);
+                    }
+                    if update.tx_frequency_hertz != tx
+                        && let Some(hertz) = update.tx_frequency_hertz
+                    {
+                        client.set_split_frequency(hertz).await?;
+                        tx = Some(hertz);
+                    }
+                    anyhow::Ok(())
+                }
+                .await;
+                if let Err(e) = result {
+                    error!(%addr, ?e, "rigctld failed to set frequency");
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!(%addr, "rigctld task lagging, skipped {n} updates");
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn radio_is_tuned_when_the_frequencies_change() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let rigctld = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read, mut write) = socket.into_split();
+            let mut lines = BufReader::new(read).lines();
+            let mut commands = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = if line == "f" {
+                    b"145800000\n"
+                } else {
+                    b"RPRT 0\n"
+                };
+                write.write_all(reply).await.unwrap();
+                commands.push(line);
+            }
+            commands
+        });
+
+        let (update_tx, update_rx) = broadcast::channel(4);
+        let running = tokio::spawn(run(address, update_rx));
+        for (rx, tx) in [
+            (437_010_000, 145_790_000),
+            (437_010_000, 145_790_000),
+            (437_009_000, 145_790_000),
+        ] {
+            update_tx
+                .send(Update {
+                    timestamp: Utc::now(),
+                    azimuth_degrees: 0.0,
+                    elevation_degrees: 0.0,
+                    range_meters: 0.0,
+                    range_rate_meters_per_second: 0.0,
+                    tx_frequency_hertz: Some(tx),
+                    rx_frequency_hertz: Some(rx),
+                })
+                .unwrap();
+        }
+        drop(update_tx);
+        running.await.unwrap();
+
+        assert_eq!(
+            rigctld.await.unwrap(),
+            ["f", "F 437010000", "I 145790000", "F 437009000"]
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result, anyhow, bail};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+
+/// Minimal client for the `rigctld` TCP protocol.
+///
+/// See https://manpages.ubuntu.com/manpages/xenial/man8/rigctld.8.html
+pub struct RigctlClient {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+impl RigctlClient {
+    pub async fn connect(addr: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .with_context(|| format!("connecting to rigctld at {addr}"))?;
+        let (r, w) = stream.into_split();
+        Ok(Self {
+            reader: BufReader::new(r),
+            writer: w,
+        })
+    }
+
+    /// `F <Hz>` — tune the current (receive) VFO.
+    pub async fn set_frequency(&mut self, hertz: u64) -> Result<()> {
+        self.writer
+            .write_all(format!("F {hertz}\n").as_bytes())
+            .await?;
+        self.expect_rprt_ok().await
+    }
+
+    /// `I <Hz>` — tune the transmit VFO of split operation.
+    pub async fn set_split_frequency(&mut self, hertz: u64) -> Result<()> {
+        self.writer
+            .write_all(format!("I {hertz}\n").as_bytes())
+            .await?;
+        self.expect_rprt_ok().await
+    }
+
+    /// `f` — request the frequency of the current VFO (Hz).
+    pub async fn get_frequency(&mut self) -> Result<u64> {
+        self.writer.write_all(b"f\n").await?;
+        let line = self.read_line().await?;
+        if let Some(rest) = line.strip_prefix("RPRT ") {
+            bail!("rigctld error on `f`: {}", rest);
+        }
+        line.parse()
+            .with_context(|| format!("parsing frequency '{line}'"))
+    }
+
+    /// Read a single `RPRT <code>` reply and fail on non-zero codes.
+    async fn expect_rprt_ok(&mut self) -> Result<()> {
+        let line = self.read_line().await?;
+        let code = line
+            .strip_prefix("RPRT ")
+            .ok_or_else(|| anyhow!("expected 'RPRT <code>', got '{line}'"))?
+            .parse::<i32>()
+            .with_context(|| format!("parsing RPRT code '{line}'"))?;
+        if code == 0 {
+            Ok(())
+        } else {
+            Err(anyhow!("rigctld returned error code {code}"))
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut buf = String::new();
+        let n = self.reader.read_line(&mut buf).await?;
+        if n == 0 {
+            bail!("rigctld connection closed");
+        }
+        Ok(buf.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
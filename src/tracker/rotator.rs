@@ -0,0 +1,273 @@
+//! Steers a rotator along the trajectory of the tracked object through its `rotctld` server.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use tracing::{error, info, warn};
+
+use crate::tracker::rotctl::RotctlClient;
+use crate::tracker::update::Update;
+
+/// Travel of a rotator (degrees). Positions outside of it are clamped to the nearest one the
+/// rotator can reach, e.g. the horizon while the object is below it.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct RotatorLimits {
+    /// Lowest azimuth, e.g. `-180` for rotators centered on north.
+    #[serde(default)]
+    pub min_azimuth: f64,
+    /// Highest azimuth, over `min_azimuth + 360` for rotators with overlap.
+    #[serde(default = "default_max_azimuth")]
+    pub max_azimuth: f64,
+    #[serde(default)]
+    pub min_elevation: f64,
+    #[serde(default = "default_max_elevation")]
+    pub max_elevation: f64,
+}
+
+fn default_max_azimuth() -> f64 {
+    360.0
+}
+
+fn default_max_elevation() -> f64 {
+    90.0
+}
+
+impl Default for RotatorLimits {
+    fn default() -> Self {
+        Self {
+            min_azimuth: 0.0,
+            max_azimuth: default_max_azimuth(),
+            min_elevation: 0.0,
+            max_elevation: default_max_elevation(),
+        }
+    }
+}
+
+impl RotatorLimits {
+    /// The position the rotator is sent to for `azimuth` and `elevation`: the azimuth in the
+    /// range of the rotator, and both clamped to its travel.
+    pub fn position(&self, azimuth: f64, elevation: f64) -> (f64, f64) {
+        let azimuth = self.min_azimuth + (azimuth - self.min_azimuth).rem_euclid(360.0);
+        (
+            azimuth.min(self.max_azimuth),
+            elevation.clamp(self.min_elevation, self.max_elevation),
+        )
+    }
+}
+
+/// Position (degrees) a rotator is parked at.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ParkPosition {
+    pub azimuth: f64,
+    pub elevation: f64,
+}
+
+/// A rotator controlled by a `rotctld` server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rotator {
+    /// `host:port` of the `rotctld` server
+    pub address: String,
+    pub limits: RotatorLimits,
+    /// Where the rotator is parked. The park position of `rotctld` (`K`) if unset.
+    pub park: Option<ParkPosition>,
+}
+
+/// Parks `rotator` now, e.g. between passes or before a storm.
+pub async fn park(rotator: &Rotator) -> anyhow::Result<()> {
+    let mut client = RotctlClient::connect(&rotator.address).await?;
+    park_with(&mut client, rotator).await
+}
+
+async fn park_with(client: &mut RotctlClient, rotator: &Rotator) -> anyhow::Result<()> {
+    match rotator.park {
+        Some(ParkPosition { azimuth, elevation }) => {
+            let (az, el) = rotator.limits.position(azimuth, elevation);
+            client.set_position(az, el).await
+        }
+        None => client.park().await,
+    }
+}
+
+/// Angle (degrees) between the `target` and `actual` positions of a rotator, as azimuth and
+/// elevation in degrees.
+pub fn pointing_error(target: (f64, f64), actual: (f64, f64)) -> f64 {
+    let hav = |angle: f64| (angle / 2.0).sin().powi(2);
+    let (az1, el1) = (target.0.to_radians(), target.1.to_radians());
+    let (az2, el2) = (actual.0.to_radians(), actual.1.to_radians());
+    let h = hav(el2 - el1) + el1.cos() * el2.cos() * hav(az2 - az1);
+    (2.0 * h.sqrt().min(1.0).asin()).to_degrees()
+}
+
+/// Points `rotator` at the position of each of the `updates` until the channel is closed, then
+/// parks it.
+///
+/// Before each new position, the rotator is asked where it is and the angle from the position it
+/// was sent to last is sent to `error`.
+pub async fn run(
+    rotator: Rotator,
+    mut updates: broadcast::Receiver<Update>,
+    error: watch::Sender<Option<f64>>,
+) {
+    let addr = rotator.address.clone();
+    let mut client = match RotctlClient::connect(&addr).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!(%addr, ?e, "failed to connect to rotctld");
+            return;
+        }
+    };
+    match client.get_position().await {
+        Ok((az, el)) => info!(%addr, az, el, "connected to rotctld"),
+        Err(e) => warn!(%addr, ?e, "connected to rotctld, but failed to read position"),
+    }
+
+    let mut target = None;
+    loop {
+        match updates.recv().await {
+            Ok(update) => {
+                if let Some(target) = target {
+                    let current = match client.get_position().await {
+                        Ok(actual) => Some(pointing_error(target, actual)),
+                        Err(e) => {
+                            warn!(%addr, ?e, "rotctld get_position failed");
+                            None
+                        }
+                    };
+                    error.send_replace(current);
+                }
+                let (az, el) = rotator
+                    .limits
+                    .position(update.azimuth_degrees, update.elevation_degrees);
+                if let Err(e) = client.set_position(az, el).await {
+                    error!(%addr, ?e, "rotctld set_position failed");
+                    break;
+                }
+                target = Some((az, el));
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!(%addr, "rotctld task lagging, skipped {n} updates");
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+
+    if let Err(e) = park_with(&mut client, &rotator).await {
+        warn!(%addr, ?e, "rotctld park failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn positions_are_kept_within_the_travel() {
+        let limits = RotatorLimits::default();
+        assert_eq!(limits.position(123.0, 45.0), (123.0, 45.0));
+        // Below the horizon the rotator waits at the azimuth of the object
+        assert_eq!(limits.position(200.0, -12.0), (200.0, 0.0));
+
+        let centered = RotatorLimits {
+            min_azimuth: -180.0,
+            max_azimuth: 180.0,
+            max_elevation: 80.0,
+            ..Default::default()
+        };
+        assert_eq!(centered.position(270.0, 85.0), (-90.0, 80.0));
+        assert_eq!(centered.position(90.0, 10.0), (90.0, 10.0));
+    }
+
+    #[test]
+    fn pointing_error_is_the_angle_between_the_positions() {
+        assert!((pointing_error((350.0, 0.0), (10.0, 0.0)) - 20.0).abs() < 1e-9);
+        assert!((pointing_error((0.0, 40.0), (0.0, 45.5)) - 5.5).abs() < 1e-9);
+        // Near the zenith the azimuth hardly matters
+        assert!(pointing_error((0.0, 89.0), (180.0, 89.0)) < 2.0 + 1e-9);
+        assert_eq!(pointing_error((123.0, 45.0), (123.0, 45.0)), 0.0);
+    }
+
+    #[tokio::test]
+    async fn rotator_follows_the_updates_and_parks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let rotctld = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read, mut write) = socket.into_split();
+            let mut lines = BufReader::new(read).lines();
+            let mut commands = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = if line == "p" {
+                    b"0.00\n0.00\n"
+                } else {
+                    b"RPRT 0\n"
+                };
+                write.write_all(reply).await.unwrap();
+                commands.push(line);
+            }
+            commands
+        });
+
+        let (update_tx, update_rx) = broadcast::channel(4);
+        let rotator = Rotator {
+            address,
+            limits: RotatorLimits::default(),
+            park: None,
+        };
+        let (error_tx, error_rx) = watch::channel(None);
+        let running = tokio::spawn(run(rotator, update_rx, error_tx));
+        for (azimuth, elevation) in [(350.0, -3.0), (10.5, 20.25)] {
+            update_tx
+                .send(Update {
+                    timestamp: Utc::now(),
+                    azimuth_degrees: azimuth,
+                    elevation_degrees: elevation,
+                    range_meters: 0.0,
+                    range_rate_meters_per_second: 0.0,
+                    tx_frequency_hertz: None,
+                    rx_frequency_hertz: None,
+                })
+                .unwrap();
+        }
+        drop(update_tx);
+        running.await.unwrap();
+
+        assert_eq!(
+            rotctld.await.unwrap(),
+            ["p", "P 350.00 0.00", "p", "P 10.50 20.25", "K"]
+        );
+        // Reported at 0/0 after being sent to 350/0
+        assert!((error_rx.borrow().unwrap() - 10.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn rotator_is_parked_at_its_park_position() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let rotctld = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read, mut write) = socket.into_split();
+            let mut line = String::new();
+            BufReader::new(read).read_line(&mut line).await.unwrap();
+            write.write_all(b"RPRT 0\n").await.unwrap();
+            line
+        });
+
+        let rotator = Rotator {
+            address,
+            limits: RotatorLimits {
+                min_azimuth: -180.0,
+                max_azimuth: 180.0,
+                ..Default::default()
+            },
+            park: Some(ParkPosition {
+                azimuth: 270.0,
+                elevation: 90.0,
+            }),
+        };
+        park(&rotator).await.unwrap();
+        assert_eq!(rotctld.await.unwrap(), "P -90.00 90.00\n");
+    }
+}
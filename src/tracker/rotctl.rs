@@ -2,10 +2,6 @@ use anyhow::{Context, Result, anyhow, bail};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
-use tokio::sync::broadcast;
-use tracing::{error, info, warn};
-
-use crate::tracker::update::Update;
 
 /// Minimal client for the `rotctld` TCP protocol.
 ///
@@ -86,40 +82,6 @@ impl RotctlClient {
     }
 }
 
-/// Task that forwards tracker updates to a `rotctld` server.
-pub async fn run(addr: String, mut updates: broadcast::Receiver<Update>) {
-    let mut client = match RotctlClient::connect(&addr).await {
-        Ok(c) => c,
-        Err(e) => {
-            error!(%addr, ?e, "failed to connect to rotctld");
-            return;
-        }
-    };
-    info!(%addr, "connected to rotctld");
-
-    loop {
-        match updates.recv().await {
-            Ok(update) => {
-                if let Err(e) = client
-                    .set_position(update.azimuth_degrees, update.elevation_degrees)
-                    .await
-                {
-                    error!(?e, "rotctld set_position failed");
-                    break;
-                }
-            }
-            Err(broadcast::error::RecvError::Lagged(n)) => {
-                warn!("rotctld task lagging, skipped {n} updates");
-            }
-            Err(broadcast::error::RecvError::Closed) => break,
-        }
-    }
-
-    if let Err(e) = client.park().await {
-        warn!(?e, "rotctld park failed");
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -15,14 +15,17 @@ pub enum Output {
     File(String),
     /// Publish all tracker events to the specified Zenoh topic
     Zenoh(String),
+    /// Announce the Doppler corrected frequencies as JSON datagrams to the specified address.
+    Udp(String),
 }
 
 /// Parses strings like:
-/// ```
+/// ```text
 /// rotctl=127.0.0.1:4533
 /// rigctl=127.0.0.1:9998
 /// file=tracker.json
 /// zenoh=tracker/foo
+/// udp=127.0.0.1:7355
 /// ```
 impl FromStr for Output {
     type Err = String;
@@ -39,8 +42,9 @@ impl FromStr for Output {
             "rotctl" => Ok(Output::Rotctl(v)),
             "file" => Ok(Output::File(v)),
             "zenoh" => Ok(Output::Zenoh(v)),
+            "udp" => Ok(Output::Udp(v)),
             other => Err(format!(
-                "unknown output type '{other}', expected rigctl/rotctl/file/zenoh"
+                "unknown output type '{other}', expected rigctl/rotctl/file/zenoh/udp"
             )),
         }
     }
@@ -115,6 +119,9 @@ mod tests {
 
         let o: Output = "zenoh=tracker/foo".parse().unwrap();
         assert!(matches!(o, Output::Zenoh(t) if t == "tracker/foo"));
+
+        let o: Output = "udp=127.0.0.1:7355".parse().unwrap();
+        assert!(matches!(o, Output::Udp(a) if a == "127.0.0.1:7355"));
     }
 
     #[test]
@@ -0,0 +1,323 @@
+//! Uploads the artifacts of finished runs to remote storage, so that remote stations sync their
+//! products to a central place without anyone fetching them.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use chrono::Utc;
+use sat_o_mat::scheduler::RunEvent;
+use sat_o_mat::task::runner::{LogEntry, StepResult, append_log_entry};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::api::runs::artifact_files;
+use crate::config::{UploadConfig, UploadTarget};
+use crate::http::{curl_option, encode_path_segment};
+
+/// A program to run, with its arguments and what to write to its stdin.
+type Step = (String, Vec<String>, Option<String>);
+
+/// Uploads the artifacts of each run completed, aborted or failed to `uploads`, forwarding every
+/// event to `forward`. Returns once `events` is closed and the uploads in progress have finished.
+///
+/// The result of each upload is appended to the execution log of the run. Runs that failed before
+/// their artifact directory was created have nothing to upload, and are skipped.
+pub async fn upload_runs(
+    uploads: Vec<UploadConfig>,
+    station: String,
+    artifacts: PathBuf,
+    mut events: UnboundedReceiver<RunEvent>,
+    forward: UnboundedSender<RunEvent>,
+) {
+    let mut running = JoinSet::new();
+    while let Some(event) = events.recv().await {
+        if let RunEvent::Completed(id) | RunEvent::Aborted(id) | RunEvent::Failed(id) = &event
+            && artifacts.join(id).is_dir()
+        {
+            for upload in &uploads {
+                let (upload, station, id) = (upload.clone(), station.clone(), id.clone());
+                let dir = artifacts.join(&id);
+                running.spawn(async move { upload_run(&upload, &station, &id, &dir).await });
+            }
+        }
+        // The receiver going away only means nobody is interested anymore
+        let _ = forward.send(event);
+    }
+    if !running.is_empty() {
+        info!(count = running.len(), "waiting for uploads to finish");
+    }
+    while running.join_next().await.is_some() {}
+}
+
+/// Uploads the artifacts in `dir` of run `id` of `station`, retrying as configured, and records
+/// the result in its execution log.
+async fn upload_run(upload: &UploadConfig, station: &str, id: &str, dir: &Path) {
+    let name = &upload.name;
+    let delay = Duration::from_secs(upload.retry_delay_secs);
+    let mut attempt = 1;
+    let result = loop {
+        match send(&upload.target, station, id, dir).await {
+            Ok(()) => break Ok(()),
+            Err(e) if attempt < upload.attempts => {
+                warn!(%name, %id, attempt, ?delay, "upload failed, retrying: {e}");
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => break Err(e),
+        }
+    };
+
+    let entry = LogEntry {
+        time: Utc::now(),
+        cmd: format!("upload to {name}"),
+        result: match result {
+            Ok(()) => StepResult::Uploaded,
+            Err(_) => StepResult::UploadFailed,
+        },
+        exit_code: None,
+        error: result.as_ref().err().map(|e| e.to_string()),
+        attempts: (attempt > 1).then_some(attempt),
+    };
+    match &result {
+        Ok(()) => info!(%name, %id, "artifacts uploaded"),
+        Err(e) => warn!(%name, %id, attempts = attempt, "failed to upload artifacts: {e}"),
+    }
+    if let Err(e) = append_log_entry(dir, &entry) {
+        warn!(?e, ?dir, "failed to write execution log");
+    }
+}
+
+/// Runs the commands uploading `dir`, stopping at the first failing.
+async fn send(target: &UploadTarget, station: &str, id: &str, dir: &Path) -> io::Result<()> {
+    for (program, args, stdin) in commands(target, station, id, dir)? {
+        let mut child = Command::new(&program)
+            .args(&args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to run {program}: {e}")))?;
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "{program} exited with {}: {}",
+                output.status,
+                stderr.trim()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The commands uploading the artifacts in `dir` of run `id` of `station` to `target`.
+fn commands(target: &UploadTarget, station: &str, id: &str, dir: &Path) -> io::Result<Vec<Step>> {
+    let local = dir.display().to_string();
+    let commands = match target {
+        UploadTarget::Rsync { destination } => {
+            let destination = destination.trim_end_matches('/');
+            vec![(
+                "rsync".to_string(),
+                vec![
+                    "-a".to_string(),
+                    "--mkpath".to_string(),
+                    format!("{local}/"),
+                    format!("{destination}/{station}/{id}/"),
+                ],
+                None,
+            )]
+        }
+        UploadTarget::S3 { url, endpoint_url } => {
+            let mut args = vec!["s3", "cp", "--recursive", "--only-show-errors"]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            if let Some(endpoint_url) = endpoint_url {
+                args.extend(["--endpoint-url".to_string(), endpoint_url.clone()]);
+            }
+            let url = url.trim_end_matches('/');
+            args.extend([local, format!("{url}/{station}/{id}")]);
+            vec![("aws".to_string(), args, None)]
+        }
+        UploadTarget::Webdav {
+            url,
+            username,
+            password,
+        } => {
+            // The credentials go through stdin to stay out of the process list
+            let auth = username.as_ref().map(|username| {
+                let password = password.as_deref().unwrap_or_default();
+                curl_option("user", &format!("{username}:{password}"))
+            });
+            let curl = |args: Vec<String>| {
+                let mut all = vec!["--silent".to_string(), "--show-error".to_string()];
+                if auth.is_some() {
+                    all.extend(["--config".to_string(), "-".to_string()]);
+                }
+                all.extend(args);
+                ("curl".to_string(), all, auth.clone())
+            };
+
+            // Collections have to exist before anything is put in them. Creating an existing one
+            // fails, so the status of MKCOL is not checked.
+            let base = format!(
+                "{}/{}/{}",
+                url.trim_end_matches('/'),
+                encode_path_segment(station),
+                encode_path_segment(id)
+            );
+            let mut collections = vec![base.rsplit_once('/').unwrap().0.to_string()];
+            let mut puts = Vec::new();
+            for (relative, path) in artifact_files(dir)? {
+                let segments: Vec<String> = relative.split('/').map(encode_path_segment).collect();
+                for depth in 0..segments.len() {
+                    let collection = [base.clone()]
+                        .into_iter()
+                        .chain(segments[..depth].iter().cloned())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    if !collections.contains(&collection) {
+                        collections.push(collection);
+                    }
+                }
+                puts.push(curl(vec![
+                    "--fail".to_string(),
+                    "--upload-file".to_string(),
+                    path.display().to_string(),
+                    format!("{base}/{}", segments.join("/")),
+                ]));
+            }
+            collections
+                .into_iter()
+                .map(|collection| {
+                    curl(vec![
+                        "--output".to_string(),
+                        "/dev/null".to_string(),
+                        "--request".to_string(),
+                        "MKCOL".to_string(),
+                        format!("{collection}/"),
+                    ])
+                })
+                .chain(puts)
+                .collect()
+        }
+    };
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use sat_o_mat::task::runner::read_execution_log;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+
+    fn run_dir(tmp: &tempfile::TempDir) -> PathBuf {
+        let dir = tmp.path().join("pass 1");
+        std::fs::create_dir_all(dir.join("steps")).unwrap();
+        std::fs::write(dir.join("execution_log.yaml"), "").unwrap();
+        std::fs::write(dir.join("steps/0.raw"), "iq").unwrap();
+        dir
+    }
+
+    #[test]
+    fn commands_upload_below_station_and_run() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = run_dir(&tmp);
+
+        let rsync = UploadTarget::Rsync {
+            destination: "backup@central:/srv/products/".into(),
+        };
+        let rsync = commands(&rsync, "north", "pass 1", &dir).unwrap();
+        assert_eq!(rsync.len(), 1);
+        assert_eq!(
+            rsync[0].1.last().unwrap(),
+            "backup@central:/srv/products/north/pass 1/"
+        );
+
+        let webdav = UploadTarget::Webdav {
+            url: "https://dav.example.com/products".into(),
+            username: Some("station".into()),
+            password: Some("secret".into()),
+        };
+        let webdav = commands(&webdav, "north", "pass 1", &dir).unwrap();
+        let urls: Vec<&str> = webdav
+            .iter()
+            .map(|(_, args, _)| args.last().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "https://dav.example.com/products/north/",
+                "https://dav.example.com/products/north/pass%201/",
+                "https://dav.example.com/products/north/pass%201/steps/",
+                "https://dav.example.com/products/north/pass%201/execution_log.yaml",
+                "https://dav.example.com/products/north/pass%201/steps/0.raw",
+            ]
+        );
+        assert!(webdav[3].1.iter().all(|arg| !arg.contains("secret")));
+        assert_eq!(webdav[3].2.as_deref(), Some("user = \"station:secret\"\n"));
+    }
+
+    #[tokio::test]
+    async fn failed_uploads_are_retried_and_recorded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = run_dir(&tmp);
+        let upload = UploadConfig {
+            name: "central".into(),
+            // Nothing listens on port 1
+            target: UploadTarget::Webdav {
+                url: "http://127.0.0.1:1/products".into(),
+                username: None,
+                password: None,
+            },
+            attempts: 2,
+            retry_delay_secs: 0,
+        };
+
+        let (events_tx, events_rx) = unbounded_channel();
+        let (forward_tx, mut forward_rx) = unbounded_channel();
+        let uploading = tokio::spawn(upload_runs(
+            vec![upload],
+            "north".into(),
+            tmp.path().to_path_buf(),
+            events_rx,
+            forward_tx,
+        ));
+        // Failed before its artifact directory was created, so there is nothing to upload
+        events_tx.send(RunEvent::Failed("pass 2".into())).unwrap();
+        events_tx.send(RunEvent::Failed("pass 1".into())).unwrap();
+        drop(events_tx);
+        uploading.await.unwrap();
+
+        assert_eq!(
+            forward_rx.recv().await,
+            Some(RunEvent::Failed("pass 2".into()))
+        );
+        assert_eq!(
+            forward_rx.recv().await,
+            Some(RunEvent::Failed("pass 1".into()))
+        );
+        assert!(!tmp.path().join("pass 2").exists());
+        let log = read_execution_log(&dir).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].cmd, "upload to central");
+        assert_eq!(log[0].result, StepResult::UploadFailed);
+        assert!(log[0].error.is_some());
+        assert_eq!(log[0].attempts, Some(2));
+    }
+}
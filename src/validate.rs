@@ -0,0 +1,371 @@
+//! Checks task definitions before they are submitted, e.g. in the CI pipeline of a repository of
+//! schedules.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::api::review::{ReviewStep, check_hardware, review_steps};
+use crate::config::Config;
+use crate::task::format::{self, Task};
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// The task definition files
+    #[arg(value_name = "FILE", required = true)]
+    pub files: Vec<PathBuf>,
+    /// Output format. `json` prints an array with a report for each file
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+    /// Check the resources of this hosted station instead of the main one
+    #[arg(long)]
+    pub station: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Report {
+    #[schema(value_type = String)]
+    pub file: PathBuf,
+    pub valid: bool,
+    pub errors: Vec<Problem>,
+    pub warnings: Vec<Problem>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    /// Steps with the task variables substituted and their times resolved
+    pub steps: Vec<ReviewStep>,
+    pub cleanup: Vec<ReviewStep>,
+    /// Names of the station resources used by any step
+    pub resources: Vec<String>,
+    /// Whether any step uses a resource that can transmit
+    pub transmits: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+pub struct Problem {
+    pub message: String,
+    /// Line of the file the problem was found at, if known
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Problem {
+    pub fn new(message: impl ToString) -> Self {
+        Self {
+            message: message.to_string(),
+            line: None,
+            column: None,
+        }
+    }
+}
+
+/// Validates `args.files`, printing a report for each. Fails if any of them is invalid.
+pub fn run(args: ValidateArgs, config: &Config) -> anyhow::Result<()> {
+    let reports: Vec<Report> = args
+        .files
+        .into_iter()
+        .map(|file| match fs::read_to_string(&file) {
+            Ok(yaml) => validate(file, &yaml, config),
+            Err(e) => Report::invalid(file, Problem::new(format!("failed to read file: {e}"))),
+        })
+        .collect();
+
+    match args.format {
+        Format::Text => print!("{}", text(&reports)),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&reports)?),
+    }
+
+    let invalid = reports.iter().filter(|r| !r.valid).count();
+    if invalid > 0 {
+        bail!("{invalid} of {} files are invalid", reports.len());
+    }
+    Ok(())
+}
+
+/// Checks the task definition `yaml` read from `file`.
+///
+/// Variables evaluated by shell commands (`${...}`) are only resolved when the task runs, so the
+/// times depending on them are not checked.
+pub fn validate(file: PathBuf, yaml: &str, config: &Config) -> Report {
+    let task = match Task::from_yaml_str(yaml) {
+        Ok(task) => task,
+        Err(e) => return Report::invalid(file, yaml_problem(e)),
+    };
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    let time = |name| match task.get_time_variable(name) {
+        Ok(time) => Ok(Some(time)),
+        Err(format::Error::MissingVariable(_)) => Ok(None),
+        Err(_) if is_evaluated(task.variables.get(name)) => Ok(None),
+        Err(e) => Err(Problem::new(format!("variable {name}: {e}"))),
+    };
+    let start = time("start").unwrap_or_else(|e| {
+        errors.push(e);
+        None
+    });
+    let end = time("end").unwrap_or_else(|e| {
+        errors.push(e);
+        None
+    });
+    if end.is_none() && !task.variables.contains_key("end") {
+        warnings.push(Problem::new(
+            "variable end is not set, the steps are not stopped at the end of the task",
+        ));
+    }
+    if let (Some(start), Some(end)) = (start, end)
+        && end <= start
+    {
+        errors.push(Problem::new("the task ends before it starts"));
+    }
+
+    let steps = review_steps(&task.steps, &task.variables, &config.resources);
+    let cleanup = review_steps(&task.cleanup, &task.variables, &config.resources);
+    for (list, definitions, reviewed) in [
+        ("steps", &task.steps, &steps),
+        ("cleanup", &task.cleanup, &cleanup),
+    ] {
+        for (i, (step, review)) in definitions.iter().zip(reviewed).enumerate() {
+            let Some(spec) = &step.time else { continue };
+            let Some(time) = &review.time else {
+                if let format::TimeSpec::Relative { variable, .. } = spec
+                    && !is_evaluated(task.variables.get(variable))
+                {
+                    errors.push(Problem::new(format!(
+                        "{list}[{i}]: time refers to variable {variable}, which is not a time"
+                    )));
+                }
+                continue;
+            };
+            let outside = chrono::DateTime::parse_from_rfc3339(time).is_ok_and(|time| {
+                start.is_some_and(|start| time < start) || end.is_some_and(|end| time > end)
+            });
+            if outside {
+                warnings.push(Problem::new(format!(
+                    "{list}[{i}]: starts at {time}, outside of the task"
+                )));
+            }
+        }
+    }
+
+    errors.extend(
+        check_hardware(&task, &config.resources)
+            .into_iter()
+            .map(Problem::new),
+    );
+
+    let resources: BTreeSet<&String> = steps
+        .iter()
+        .chain(&cleanup)
+        .flat_map(|s| &s.resources)
+        .collect();
+    Report {
+        file,
+        valid: errors.is_empty(),
+        errors,
+        warnings,
+        start: start.map(|t| t.to_rfc3339()),
+        end: end.map(|t| t.to_rfc3339()),
+        transmits: steps.iter().chain(&cleanup).any(|s| s.transmit),
+        resources: resources.into_iter().cloned().collect(),
+        steps,
+        cleanup,
+    }
+}
+
+impl Report {
+    fn invalid(file: PathBuf, error: Problem) -> Self {
+        Self {
+            file,
+            valid: false,
+            errors: vec![error],
+            warnings: Vec::new(),
+            start: None,
+            end: None,
+            steps: Vec::new(),
+            cleanup: Vec::new(),
+            resources: Vec::new(),
+            transmits: false,
+        }
+    }
+}
+
+/// Whether the variable is evaluated by a shell command when the task runs.
+fn is_evaluated(value: Option<&String>) -> bool {
+    value.is_some_and(|v| v.contains("${"))
+}
+
+fn yaml_problem(error: format::Error) -> Problem {
+    let location = match &error {
+        format::Error::Yaml(e) | format::Error::InvalidTimeSpec(e) => e.location(),
+        _ => None,
+    };
+    Problem {
+        message: error.to_string(),
+        line: location.as_ref().map(|l| l.line()),
+        column: location.as_ref().map(|l| l.column()),
+    }
+}
+
+/// Formats the reports like compiler diagnostics, one line per problem.
+fn text(reports: &[Report]) -> String {
+    let mut output = String::new();
+    for report in reports {
+        let file = report.file.display();
+        for (severity, problems) in [("error", &report.errors), ("warning", &report.warnings)] {
+            for problem in problems {
+                let location = match (problem.line, problem.column) {
+                    (Some(line), Some(column)) => format!(":{line}:{column}"),
+                    _ => String::new(),
+                };
+                output.push_str(&format!(
+                    "{file}{location}: {severity}: {}\n",
+                    problem.message
+                ));
+            }
+        }
+        if report.valid {
+            output.push_str(&format!(
+                "{file}: ok, {} steps, {} cleanup steps\n",
+                report.steps.len(),
+                report.cleanup.len()
+            ));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ResourceConfig, SdrConfig};
+    use crate::radio::Backend;
+
+    fn config() -> Config {
+        Config {
+            resources: vec![
+                ResourceConfig {
+                    name: "radio".into(),
+                    commands: vec!["rigctl".into()],
+                    transmit: true,
+                    address: None,
+                    limits: None,
+                    park: None,
+                    sdr: None,
+                },
+                ResourceConfig {
+                    name: "uhf1".into(),
+                    commands: vec!["rotctl".into()],
+                    transmit: false,
+                    address: Some("127.0.0.1:4533".into()),
+                    limits: None,
+                    park: None,
+                    sdr: Some(SdrConfig {
+                        backend: Backend::RtlSdr,
+                        device: Some("0".into()),
+                        gain: None,
+                        sample_rates: vec![1_024_000],
+                    }),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reports_resolved_steps_and_resources() {
+        let yaml = "\
+variables:
+  start: \"2099-06-01T10:00:00Z\"
+  end: \"2099-06-01T10:30:00Z\"
+steps:
+  - cmd: \"rigctl F 437000000\"
+    time: \"T+5m\"
+  - cmd: \"echo late\"
+    time: \"T+1h\"
+";
+        let report = validate("pass.yml".into(), yaml, &config());
+        assert!(report.valid);
+        assert_eq!(
+            report.steps[0].time.as_deref(),
+            Some("2099-06-01T10:05:00+00:00")
+        );
+        assert_eq!(report.resources, ["radio"]);
+        assert!(report.transmits);
+        assert_eq!(
+            report.warnings,
+            [Problem::new(
+                "steps[1]: starts at 2099-06-01T11:00:00+00:00, outside of the task"
+            )]
+        );
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["steps"][0]["resources"][0], "radio");
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let yaml = "\
+variables:
+  start: \"2099-06-01T10:00:00Z\"
+steps:
+  - cmd: \"echo hello\"
+    wait: maybe
+";
+        let report = validate("bad.yml".into(), yaml, &config());
+        assert!(!report.valid);
+        assert_eq!(report.errors[0].line, Some(4));
+        assert_eq!(
+            text(&[report]).split(": error: ").next(),
+            Some("bad.yml:4:3")
+        );
+
+        let yaml = "\
+variables:
+  start: \"2099-06-01T10:00:00Z\"
+  end: \"2099-06-01T09:00:00Z\"
+";
+        let report = validate("backwards.yml".into(), yaml, &config());
+        assert_eq!(
+            report.errors,
+            [Problem::new("the task ends before it starts")]
+        );
+    }
+
+    #[test]
+    fn reports_hardware_that_is_not_configured() {
+        let yaml = "\
+variables:
+  start: \"2099-06-01T10:00:00Z\"
+  end: \"2099-06-01T10:30:00Z\"
+  rotator: uhf2
+steps:
+  - cmd: \"sat-o-mat track --tle pass.tle --rotator uhf1 --radio localhost:4532\"
+  - cmd: \"sat-o-mat tracker --out rotctl=$rotator --out rigctl=radio\"
+  - cmd: \"sat-o-mat radio run --sdr uhf1 --frequency 437.5MHz --bandwidth 1.024MHz\"
+  - cmd: \"other --rotator uhf3\"
+cleanup:
+  - cmd: \"/usr/bin/sat-o-mat rotator park --station north vhf\"
+";
+        let report = validate("pass.yml".into(), yaml, &config());
+        assert!(!report.valid);
+        assert_eq!(
+            report.errors,
+            [
+                Problem::new("steps[1]: unknown rotator uhf2"),
+                Problem::new("steps[1]: resource radio has no address"),
+                Problem::new("cleanup[0]: unknown rotator vhf"),
+            ]
+        );
+    }
+}